@@ -0,0 +1,140 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::error::Error as StdError;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use tracing::debug;
+
+use crate::api::v2::account_config;
+use crate::api::v2::account_config::Configuration;
+use crate::Client;
+use crate::RequestError;
+
+
+/// An error preventing an update of the account configuration.
+#[derive(Debug)]
+pub enum ConfigError {
+  /// The current configuration could not be retrieved.
+  Get(RequestError<account_config::GetError>),
+  /// The updated configuration could not be stored.
+  Patch(RequestError<account_config::PatchError>),
+}
+
+impl Display for ConfigError {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    match self {
+      Self::Get(err) => write!(fmt, "failed to retrieve account configuration: {}", err),
+      Self::Patch(err) => write!(fmt, "failed to update account configuration: {}", err),
+    }
+  }
+}
+
+impl StdError for ConfigError {
+  fn source(&self) -> Option<&(dyn StdError + 'static)> {
+    match self {
+      Self::Get(err) => Some(err),
+      Self::Patch(err) => Some(err),
+    }
+  }
+}
+
+
+/// Apply `update` to the given configuration, returning the updated
+/// configuration if it differs from the original one.
+fn apply<F>(config: &Configuration, update: F) -> Option<Configuration>
+where
+  F: FnOnce(&mut Configuration),
+{
+  let mut updated = *config;
+  let () = update(&mut updated);
+  if updated != *config {
+    Some(updated)
+  } else {
+    None
+  }
+}
+
+
+impl Client {
+  /// Update the account configuration using the given function.
+  ///
+  /// The current configuration is retrieved and, only if `update`
+  /// actually changed it, written back. The configuration in effect
+  /// afterwards is returned. Note that the read-modify-write cycle is
+  /// not atomic: a concurrent change of the configuration made
+  /// elsewhere may get overwritten.
+  pub async fn update_configuration<F>(&self, update: F) -> Result<Configuration, ConfigError>
+  where
+    F: FnOnce(&mut Configuration),
+  {
+    let config = self
+      .issue::<account_config::Get>(())
+      .await
+      .map_err(ConfigError::Get)?;
+
+    match apply(&config, update) {
+      Some(updated) => {
+        debug!(config = debug(&updated), "updating account configuration");
+        self
+          .issue::<account_config::Patch>(updated)
+          .await
+          .map_err(ConfigError::Patch)
+      },
+      None => Ok(config),
+    }
+  }
+
+  /// Suspend trading for the account, blocking the submission of new
+  /// orders.
+  pub async fn suspend_trading(&self) -> Result<Configuration, ConfigError> {
+    self
+      .update_configuration(|config| config.trading_suspended = true)
+      .await
+  }
+
+  /// Resume trading for the account after it was suspended.
+  pub async fn resume_trading(&self) -> Result<Configuration, ConfigError> {
+    self
+      .update_configuration(|config| config.trading_suspended = false)
+      .await
+  }
+
+  /// Enable or disable the restriction of the account to submitting
+  /// orders that do not open short positions.
+  pub async fn set_no_shorting(&self, no_shorting: bool) -> Result<Configuration, ConfigError> {
+    self
+      .update_configuration(|config| config.no_shorting = no_shorting)
+      .await
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use test_env_log::test;
+
+  use crate::api::v2::account_config::TradeConfirmation;
+
+
+  /// Check that we only report configurations that actually changed.
+  #[test]
+  fn apply_update() {
+    let config = Configuration {
+      trade_confirmation: TradeConfirmation::Email,
+      trading_suspended: false,
+      no_shorting: false,
+    };
+
+    assert_eq!(apply(&config, |config| config.no_shorting = false), None);
+
+    let updated = apply(&config, |config| config.trading_suspended = true).unwrap();
+    assert!(updated.trading_suspended);
+    assert!(!updated.no_shorting);
+    assert_eq!(updated.trade_confirmation, TradeConfirmation::Email);
+  }
+}
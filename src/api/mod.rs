@@ -2,10 +2,21 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 /// Definitions for the second version of the Alpaca API.
+#[cfg(feature = "trading")]
 pub mod v2;
 
 /// The API base URL used for paper trading.
 pub(crate) const API_BASE_URL: &str = "https://paper-api.alpaca.markets";
+/// The URL of the stream providing updates for the paper trading
+/// account.
+#[cfg(any(test, feature = "trading"))]
+pub(crate) const API_STREAM_URL: &str = "wss://paper-api.alpaca.markets/stream";
+/// The base URL of the Broker API sandbox.
+pub(crate) const BROKER_BASE_URL: &str = "https://broker-api.sandbox.alpaca.markets";
+/// The API base URL used for retrieving market data.
+pub(crate) const DATA_BASE_URL: &str = "https://data.alpaca.markets";
+/// The URL of the stream providing real time market data.
+pub(crate) const DATA_STREAM_URL: &str = "wss://stream.data.alpaca.markets";
 /// The HTTP header representing the key ID.
 pub(crate) const HDR_KEY_ID: &str = "APCA-API-KEY-ID";
 /// The HTTP header representing the secret key.
@@ -1,30 +1,22 @@
 // Copyright (C) 2019-2020 Daniel Mueller <deso@posteo.net>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::ops::Deref;
 use std::time::SystemTime;
 
 use num_decimal::Num;
 
 use serde::Deserialize;
-
-use uuid::Uuid;
+use serde::Serialize;
 
 use time_util::system_time_from_str;
 
 use crate::Str;
 
 
-/// A type representing an account ID.
-#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
-pub struct Id(pub Uuid);
-
-impl Deref for Id {
-  type Target = Uuid;
-
-  fn deref(&self) -> &Self::Target {
-    &self.0
-  }
+Id! {
+  /// A type representing an account ID.
+  #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+  pub struct Id(pub Uuid);
 }
 
 
@@ -60,6 +52,43 @@ pub enum Status {
 }
 
 
+/// The currency an account is denominated in or the prices of an
+/// order are expressed in.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Currency {
+  /// The US dollar.
+  #[serde(rename = "USD")]
+  Usd,
+  /// The Australian dollar.
+  #[serde(rename = "AUD")]
+  Aud,
+  /// The Canadian dollar.
+  #[serde(rename = "CAD")]
+  Cad,
+  /// The Swiss franc.
+  #[serde(rename = "CHF")]
+  Chf,
+  /// The euro.
+  #[serde(rename = "EUR")]
+  Eur,
+  /// The pound sterling.
+  #[serde(rename = "GBP")]
+  Gbp,
+  /// The Hong Kong dollar.
+  #[serde(rename = "HKD")]
+  Hkd,
+  /// The Japanese yen.
+  #[serde(rename = "JPY")]
+  Jpy,
+  /// The Singapore dollar.
+  #[serde(rename = "SGD")]
+  Sgd,
+  /// Any other currency that we have not accounted for.
+  #[serde(other)]
+  Unknown,
+}
+
+
 /// A response as returned by the /v2/account endpoint.
 // TODO: The `sma` field is not yet hooked up.
 #[derive(Clone, Debug, Deserialize, PartialEq)]
@@ -72,7 +101,7 @@ pub struct Account {
   pub status: Status,
   /// The currency the account uses.
   #[serde(rename = "currency")]
-  pub currency: String,
+  pub currency: Currency,
   /// Cash balance.
   #[serde(rename = "cash")]
   pub cash: Num,
@@ -116,10 +145,10 @@ pub struct Account {
   /// classification. Valid values are:
   /// - 1: the standard limited margin account with 1x buying power
   /// - 2: regular margin account with 2x intra day and overnight buying
-  ///      power (the default for all non-pattern-day-trader accounts
-  ///      with USD 2000 or more equity),
+  ///   power (the default for all non-pattern-day-trader accounts with
+  ///   USD 2000 or more equity),
   /// - 4: pattern day trader account with 4x intra day buying power and
-  ///      2x regular overnight buying power
+  ///   2x regular overnight buying power
   #[serde(rename = "multiplier")]
   pub multiplier: Num,
   /// The currently available buying power. Calculated based on the
@@ -139,6 +168,14 @@ pub struct Account {
   /// five trading days (including today).
   #[serde(rename = "daytrade_count")]
   pub daytrade_count: u64,
+  /// The exchange rate used for converting between the account's local
+  /// currency and USD, for accounts using local currency trading.
+  #[serde(rename = "swap_rate", default)]
+  pub swap_rate: Option<Num>,
+  /// The fee charged for currency conversions, in basis points, for
+  /// accounts using local currency trading.
+  #[serde(rename = "swap_fee_bps", default)]
+  pub swap_fee_bps: Option<Num>,
 }
 
 
@@ -172,7 +209,13 @@ mod tests {
   use uuid::Uuid;
 
   use crate::api::API_BASE_URL;
+  use crate::api::API_STREAM_URL;
+  use crate::api::BROKER_BASE_URL;
+  use crate::api::DATA_BASE_URL;
+  use crate::api::DATA_STREAM_URL;
   use crate::api_info::ApiInfo;
+  #[cfg(feature = "data")]
+  use crate::data::v2::Feed;
   use crate::Client;
   use crate::RequestError;
 
@@ -205,12 +248,12 @@ mod tests {
 }"#;
 
     let id = Id(Uuid::parse_str("904837e3-3b76-47ec-b432-046db621571b").unwrap());
-    let acc = from_json::<Account>(&response).unwrap();
+    let acc = from_json::<Account>(response).unwrap();
     assert_eq!(acc.id, id);
     assert_eq!(acc.status, Status::Active);
-    assert_eq!(acc.currency, "USD");
+    assert_eq!(acc.currency, Currency::Usd);
     assert_eq!(acc.buying_power, Num::from(0));
-    assert_eq!(acc.trading_blocked, false);
+    assert!(!acc.trading_blocked);
     assert_eq!(
       acc.created_at,
       parse_system_time_from_str("2018-10-01T13:35:25Z").unwrap()
@@ -229,12 +272,13 @@ mod tests {
     let client = Client::new(api_info);
     let account = client.issue::<Get>(()).await.unwrap();
 
-    assert_eq!(account.currency, "USD");
+    assert_eq!(account.currency, Currency::Usd);
     assert!(!account.account_blocked);
 
     let multiplier = account.multiplier.to_u64().unwrap();
     assert!(
       multiplier == 1 || multiplier == 2 || multiplier == 4,
+      "{}",
       multiplier,
     );
   }
@@ -244,6 +288,12 @@ mod tests {
     let api_base = Url::parse(API_BASE_URL).unwrap();
     let api_info = ApiInfo {
       base_url: api_base,
+      stream_url: Url::parse(API_STREAM_URL).unwrap(),
+      data_base_url: Url::parse(DATA_BASE_URL).unwrap(),
+      data_stream_url: Url::parse(DATA_STREAM_URL).unwrap(),
+      #[cfg(feature = "data")]
+      data_feed: Feed::Iex,
+      broker_base_url: Url::parse(BROKER_BASE_URL).unwrap(),
       key_id: "invalid".to_string(),
       secret: "invalid-too".to_string(),
     };
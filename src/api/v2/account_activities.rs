@@ -19,6 +19,8 @@ use crate::api::v2::de::ContentDeserializer;
 use crate::api::v2::de::TaggedContentVisitor;
 use crate::api::v2::order;
 use crate::api::v2::util::u64_from_str;
+use crate::paged::Paged;
+use crate::util::optional_system_time_from_date_str;
 use crate::Str;
 
 
@@ -75,6 +77,9 @@ pub enum ActivityType {
   /// Dividend (tax exempt).
   #[serde(rename = "DIVTXEX")]
   DividendTaxExtempt,
+  /// Fee denominated in USD.
+  #[serde(rename = "FEE")]
+  Fee,
   /// Interest (credit/margin).
   #[serde(rename = "INT")]
   Interest,
@@ -128,6 +133,41 @@ pub enum ActivityType {
   StockSplit,
 }
 
+impl ActivityType {
+  /// Retrieve the kind of income an activity of this type represents,
+  /// if any.
+  pub fn income_kind(self) -> Option<IncomeKind> {
+    match self {
+      Self::Dividend
+      | Self::CapitalGainLongTerm
+      | Self::CapitalGainShortTerm
+      | Self::DividendAdjusted
+      | Self::DividendAdjustedNraWithheld
+      | Self::DividendReturnOfCapital
+      | Self::DividendAdjustedTefraWithheld
+      | Self::DividendTaxExtempt => Some(IncomeKind::Dividend),
+      Self::Interest | Self::InterestAdjustedNraWithheld | Self::InterestAdjustedTefraWithheld => {
+        Some(IncomeKind::Interest)
+      },
+      Self::Fee | Self::DividendFee => Some(IncomeKind::Fee),
+      _ => None,
+    }
+  }
+}
+
+
+/// The kind of an income related activity.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum IncomeKind {
+  /// A dividend payment or an adjustment thereof, including
+  /// withholdings.
+  Dividend,
+  /// An interest payment or an adjustment thereof.
+  Interest,
+  /// A fee charged to the account.
+  Fee,
+}
+
 
 /// An enumeration describing the side of a trade activity.
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
@@ -148,6 +188,9 @@ pub enum Side {
 // TODO: Not all fields are hooked up.
 #[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct TradeActivity {
+  /// The activity's ID.
+  #[serde(rename = "id")]
+  pub id: String,
   /// The time at which the execution occurred.
   #[serde(rename = "transaction_time", deserialize_with = "system_time_from_str")]
   pub transaction_time: SystemTime,
@@ -186,6 +229,9 @@ pub struct TradeActivity {
 #[doc(hidden)]
 #[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct NonTradeActivityImpl<T> {
+  /// The activity's ID.
+  #[serde(rename = "id")]
+  pub id: String,
   /// The type of non-trade activity.
   ///
   /// Note that the `Fill` variant will never be used here.
@@ -211,6 +257,23 @@ pub struct NonTradeActivityImpl<T> {
   /// present for other activity types.
   #[serde(rename = "per_share_amount")]
   pub per_share_amount: Option<Num>,
+  /// For dividend activities, the date by which one had to be on
+  /// record to be entitled to the payment. Not present for other
+  /// activity types.
+  #[serde(
+    rename = "record_date",
+    default,
+    deserialize_with = "optional_system_time_from_date_str"
+  )]
+  pub record_date: Option<SystemTime>,
+  /// For dividend activities, the date on which the payment was made.
+  /// Not present for other activity types.
+  #[serde(
+    rename = "payable_date",
+    default,
+    deserialize_with = "optional_system_time_from_date_str"
+  )]
+  pub payable_date: Option<SystemTime>,
   /// A description of the activity.
   #[serde(rename = "description")]
   pub description: Option<String>,
@@ -219,27 +282,72 @@ pub struct NonTradeActivityImpl<T> {
 impl<T> NonTradeActivityImpl<T> {
   fn into_other<U>(self, activity_type: U) -> NonTradeActivityImpl<U> {
     let Self {
+      id,
       date,
       net_amount,
       symbol,
       quantity,
       per_share_amount,
+      record_date,
+      payable_date,
       description,
       ..
     } = self;
 
     NonTradeActivityImpl::<U> {
+      id,
       type_: activity_type,
       date,
       net_amount,
       symbol,
       quantity,
       per_share_amount,
+      record_date,
+      payable_date,
       description,
     }
   }
 }
 
+impl NonTradeActivityImpl<ActivityType> {
+  /// Convert this activity into an income activity, if it is of a
+  /// dividend, interest, or fee type.
+  #[allow(clippy::result_large_err)]
+  pub fn into_income(self) -> Result<IncomeActivity, Self> {
+    let kind = match self.type_.income_kind() {
+      Some(kind) => kind,
+      None => return Err(self),
+    };
+
+    let Self {
+      id,
+      type_,
+      date,
+      net_amount,
+      symbol,
+      quantity,
+      per_share_amount,
+      record_date,
+      payable_date,
+      description,
+    } = self;
+
+    Ok(IncomeActivity {
+      id,
+      kind,
+      type_,
+      date,
+      amount: net_amount,
+      symbol,
+      quantity,
+      per_share_amount,
+      record_date,
+      payable_date,
+      description,
+    })
+  }
+}
+
 
 /// A non-trade related activity.
 ///
@@ -247,6 +355,51 @@ impl<T> NonTradeActivityImpl<T> {
 pub type NonTradeActivity = NonTradeActivityImpl<ActivityType>;
 
 
+/// An income related activity, i.e., a dividend or interest payment or
+/// a fee charged.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IncomeActivity {
+  /// The activity's ID.
+  pub id: String,
+  /// The kind of income.
+  pub kind: IncomeKind,
+  /// The precise type of the activity.
+  pub type_: ActivityType,
+  /// The date on which the activity occurred or on which the
+  /// transaction associated with the activity settled.
+  pub date: SystemTime,
+  /// The net amount of money credited to (positive) or debited from
+  /// (negative) the account.
+  pub amount: Num,
+  /// The symbol of the security the income relates to, if any.
+  pub symbol: Option<String>,
+  /// The number of shares that contributed to the payment, if any.
+  pub quantity: Option<Num>,
+  /// The average amount paid per share, if any.
+  pub per_share_amount: Option<Num>,
+  /// The date by which one had to be on record to be entitled to the
+  /// payment, if known.
+  pub record_date: Option<SystemTime>,
+  /// The date on which the payment was made, if known.
+  pub payable_date: Option<SystemTime>,
+  /// A description of the activity.
+  pub description: Option<String>,
+}
+
+impl IncomeActivity {
+  /// Calculate the gross amount of the payment, i.e., the number of
+  /// shares times the amount paid per share, if both are known.
+  ///
+  /// The difference to `amount` reflects withholdings and fees.
+  pub fn gross_amount(&self) -> Option<Num> {
+    match (&self.quantity, &self.per_share_amount) {
+      (Some(quantity), Some(per_share)) => Some(quantity * per_share),
+      _ => None,
+    }
+  }
+}
+
+
 /// An activity.
 #[derive(Clone, Debug, PartialEq)]
 pub enum Activity {
@@ -257,8 +410,17 @@ pub enum Activity {
 }
 
 impl Activity {
+  /// Retrieve the activity's ID.
+  pub fn id(&self) -> &str {
+    match self {
+      Activity::Trade(trade) => &trade.id,
+      Activity::NonTrade(non_trade) => &non_trade.id,
+    }
+  }
+
   /// Convert this activity into a trade activity, if it is of the
   /// corresponding variant.
+  #[allow(clippy::result_large_err)]
   pub fn into_trade(self) -> Result<TradeActivity, Self> {
     match self {
       Activity::Trade(trade) => Ok(trade),
@@ -268,12 +430,23 @@ impl Activity {
 
   /// Convert this activity into a non-trade activity, if it is of the
   /// corresponding variant.
+  #[allow(clippy::result_large_err)]
   pub fn into_non_trade(self) -> Result<NonTradeActivity, Self> {
     match self {
       Activity::Trade(..) => Err(self),
       Activity::NonTrade(non_trade) => Ok(non_trade),
     }
   }
+
+  /// Convert this activity into an income activity, if it is a
+  /// dividend, interest, or fee activity.
+  #[allow(clippy::result_large_err)]
+  pub fn into_income(self) -> Result<IncomeActivity, Self> {
+    match self {
+      Activity::Trade(..) => Err(self),
+      Activity::NonTrade(non_trade) => non_trade.into_income().map_err(Activity::NonTrade),
+    }
+  }
 }
 
 impl<'de> Deserialize<'de> for Activity {
@@ -281,13 +454,10 @@ impl<'de> Deserialize<'de> for Activity {
   where
     D: Deserializer<'de>,
   {
-    let tagged = match Deserializer::deserialize_any(
+    let tagged = Deserializer::deserialize_any(
       deserializer,
       TaggedContentVisitor::<ActivityType>::new("activity_type"),
-    ) {
-      Ok(val) => val,
-      Err(err) => return Err(err),
-    };
+    )?;
 
     let content = ContentDeserializer::new(tagged.content);
     match tagged.tag {
@@ -334,6 +504,18 @@ pub struct ActivityReq {
   /// If `None` all activities will be retrieved.
   #[serde(rename = "activity_types", serialize_with = "optional_vec_to_str")]
   pub types: Option<Vec<ActivityType>>,
+  /// The maximum number of activities to retrieve in a single page.
+  ///
+  /// If `None` the server side default is used.
+  #[serde(rename = "page_size", skip_serializing_if = "Option::is_none")]
+  pub page_size: Option<usize>,
+  /// The ID of the activity after which to start the page (in the
+  /// order in which activities get reported).
+  ///
+  /// `Paged::next_page` takes care of setting this member to the
+  /// correct value when retrieving subsequent pages.
+  #[serde(rename = "page_token", skip_serializing_if = "Option::is_none")]
+  pub page_token: Option<String>,
 }
 
 
@@ -359,11 +541,31 @@ Endpoint! {
   }
 }
 
+impl Paged for Get {
+  fn next_page(input: &Self::Input, output: &Self::Output) -> Option<Self::Input> {
+    let last = output.last()?;
+    // A page that is not full is the last one and issuing another
+    // request would just yield an empty result.
+    if let Some(page_size) = input.page_size {
+      if output.len() < page_size {
+        return None
+      }
+    }
+
+    let mut input = input.clone();
+    input.page_token = Some(last.id().to_string());
+    Some(input)
+  }
+}
+
 
 #[cfg(test)]
 mod tests {
   use super::*;
 
+  use futures::StreamExt;
+  use futures::TryStreamExt;
+
   use serde_json::from_str as from_json;
 
   use time_util::parse_system_time_from_date_str;
@@ -392,7 +594,7 @@ mod tests {
   "type": "fill"
 }"#;
 
-    let trade = from_json::<Activity>(&response)
+    let trade = from_json::<Activity>(response)
       .unwrap()
       .into_trade()
       .unwrap();
@@ -418,7 +620,7 @@ mod tests {
   "per_share_amount": "0.51"
 }"#;
 
-    let non_trade = from_json::<Activity>(&response)
+    let non_trade = from_json::<Activity>(response)
       .unwrap()
       .into_non_trade()
       .unwrap();
@@ -445,7 +647,7 @@ mod tests {
       "qty":"201.9617035750071243",
       "per_share_amount":"0.108783"
 }"#;
-    let non_trade = from_json::<Activity>(&response)
+    let non_trade = from_json::<Activity>(response)
       .unwrap()
       .into_non_trade()
       .unwrap();
@@ -462,6 +664,119 @@ mod tests {
     assert_eq!(non_trade.per_share_amount, Some(Num::new(108783, 1000000)));
   }
 
+  /// Check that we can convert dividend, interest, and fee activities
+  /// into income activities.
+  #[test]
+  fn convert_income_activities() {
+    let response = r#"{
+      "id":"20210301000000000::8d4a4c56-6b7e-4b3c-a8f1-2f8cfa9d2ae1",
+      "activity_type":"DIV",
+      "date":"2021-03-01",
+      "net_amount":"10.5",
+      "symbol":"AAPL",
+      "qty":"50",
+      "per_share_amount":"0.205",
+      "record_date":"2021-02-08",
+      "payable_date":"2021-02-11"
+}"#;
+    let income = from_json::<Activity>(response)
+      .unwrap()
+      .into_income()
+      .unwrap();
+    assert_eq!(income.kind, IncomeKind::Dividend);
+    assert_eq!(income.amount, Num::new(105, 10));
+    assert_eq!(income.gross_amount(), Some(Num::new(1025, 100)));
+    assert_eq!(
+      income.record_date,
+      Some(parse_system_time_from_date_str("2021-02-08").unwrap())
+    );
+    assert_eq!(
+      income.payable_date,
+      Some(parse_system_time_from_date_str("2021-02-11").unwrap())
+    );
+
+    let response = r#"{
+      "id":"20210301000000000::1a0e8c4b-0cb5-4b9f-9bd1-2c2b6e2f6b1d",
+      "activity_type":"INT",
+      "date":"2021-03-01",
+      "net_amount":"0.42",
+      "description":"Interest"
+}"#;
+    let income = from_json::<Activity>(response)
+      .unwrap()
+      .into_income()
+      .unwrap();
+    assert_eq!(income.kind, IncomeKind::Interest);
+    assert_eq!(income.symbol, None);
+    assert_eq!(income.gross_amount(), None);
+
+    let response = r#"{
+      "id":"20210301000000000::5c1d9f0a-4f8b-4d36-9a0b-6c0f2c2d3e4f",
+      "activity_type":"FEE",
+      "date":"2021-03-01",
+      "net_amount":"-0.01",
+      "description":"REG/TAF"
+}"#;
+    let income = from_json::<Activity>(response)
+      .unwrap()
+      .into_income()
+      .unwrap();
+    assert_eq!(income.kind, IncomeKind::Fee);
+    assert_eq!(income.amount, Num::new(-1, 100));
+
+    let response = r#"{
+      "id":"20210301000000000::7e2b1c3d-5a6f-4b8c-9d0e-1f2a3b4c5d6e",
+      "activity_type":"CSD",
+      "date":"2021-03-01",
+      "net_amount":"1000"
+}"#;
+    let activity = from_json::<Activity>(response).unwrap();
+    assert!(activity.into_income().is_err());
+  }
+
+  #[test]
+  fn serialize_paged_request() {
+    let request = ActivityReq {
+      page_size: Some(2),
+      page_token: Some("20190524113406977::8efc7b9a".to_string()),
+      ..Default::default()
+    };
+    let query = to_query(request).unwrap();
+    assert_eq!(query, "page_size=2&page_token=20190524113406977%3A%3A8efc7b9a");
+  }
+
+  #[test]
+  fn next_activity_page() {
+    let response = r#"[{
+  "activity_type": "DIV",
+  "id": "20190801011955195::5f596936-6f23-4cef-bdf1-3806aae57dbf",
+  "date": "2019-08-01",
+  "net_amount": "1.02"
+}, {
+  "activity_type": "DIV",
+  "id": "20190701011955195::a8bd1cbd-9a27-4ba6-bd6d-a0d0e0e3a21e",
+  "date": "2019-07-01",
+  "net_amount": "1.02"
+}]"#;
+    let activities = from_json::<Vec<Activity>>(response).unwrap();
+
+    let request = ActivityReq::default();
+    let next = Get::next_page(&request, &activities).unwrap();
+    assert_eq!(
+      next.page_token.as_deref(),
+      Some("20190701011955195::a8bd1cbd-9a27-4ba6-bd6d-a0d0e0e3a21e")
+    );
+
+    // A page that contains fewer than the requested number of
+    // activities is the last one.
+    let request = ActivityReq {
+      page_size: Some(3),
+      ..Default::default()
+    };
+    assert_eq!(Get::next_page(&request, &activities), None);
+    assert_eq!(Get::next_page(&request, &Vec::new()), None);
+  }
+
   #[test(tokio::test)]
   async fn retrieve_some_activities() {
     let api_info = ApiInfo::from_env().unwrap();
@@ -472,6 +787,7 @@ mod tests {
         ActivityType::Transaction,
         ActivityType::Dividend,
       ]),
+      ..Default::default()
     };
     let activities = client.issue::<Get>(request).await.unwrap();
 
@@ -498,6 +814,7 @@ mod tests {
     let client = Client::new(api_info);
     let request = ActivityReq {
       types: Some(vec![ActivityType::Fill]),
+      ..Default::default()
     };
     let activities = client.issue::<Get>(request).await.unwrap();
 
@@ -525,4 +842,27 @@ mod tests {
     // created accounts, an order may have to be filled first.
     assert!(!activities.is_empty());
   }
+
+  #[test(tokio::test)]
+  async fn retrieve_paged_activities() {
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+    let request = ActivityReq {
+      page_size: Some(1),
+      ..Default::default()
+    };
+    let pages = client
+      .issue_paged::<Get>(request)
+      .take(2)
+      .try_collect::<Vec<_>>()
+      .await
+      .unwrap();
+
+    assert!(!pages.is_empty());
+    let activities = pages.into_iter().flatten().collect::<Vec<_>>();
+    assert!(activities.len() <= 2);
+    if let [first, second] = activities.as_slice() {
+      assert_ne!(first.id(), second.id());
+    }
+  }
 }
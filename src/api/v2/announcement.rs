@@ -0,0 +1,299 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::SystemTime;
+
+use num_decimal::Num;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::util::optional_system_time_from_date_str;
+use crate::Str;
+
+
+Id! {
+  /// An ID uniquely identifying a corporate action announcement.
+  #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+  pub struct Id(pub Uuid);
+}
+
+
+/// The type of corporate action an announcement is about.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Type {
+  /// A dividend payment.
+  #[serde(rename = "dividend")]
+  Dividend,
+  /// A merger of two companies.
+  #[serde(rename = "merger")]
+  Merger,
+  /// A spin off of a part of a company.
+  #[serde(rename = "spinoff")]
+  Spinoff,
+  /// A stock split.
+  #[serde(rename = "split")]
+  Split,
+}
+
+
+/// The sub type of corporate action an announcement is about.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+pub enum SubType {
+  /// A dividend paid in cash.
+  #[serde(rename = "cash")]
+  Cash,
+  /// A dividend paid in stock.
+  #[serde(rename = "stock")]
+  Stock,
+  /// An update to a previously announced merger.
+  #[serde(rename = "merger_update")]
+  MergerUpdate,
+  /// The completion of a merger.
+  #[serde(rename = "merger_completion")]
+  MergerCompletion,
+  /// A spin off.
+  #[serde(rename = "spinoff")]
+  Spinoff,
+  /// A regular stock split.
+  #[serde(rename = "stock_split")]
+  StockSplit,
+  /// A unit split.
+  #[serde(rename = "unit_split")]
+  UnitSplit,
+  /// A reverse stock split.
+  #[serde(rename = "reverse_split")]
+  ReverseSplit,
+  /// A recapitalization.
+  #[serde(rename = "recapitalization")]
+  Recapitalization,
+  /// Any other sub type that we have not accounted for.
+  ///
+  /// Note that having any such sub type should be considered a bug.
+  #[serde(other)]
+  Unknown,
+}
+
+
+/// The type of date that a date range filter applies to.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+pub enum DateType {
+  /// The date the corporate action was declared.
+  #[serde(rename = "declaration_date")]
+  Declaration,
+  /// The ex-date of the corporate action.
+  #[serde(rename = "ex_date")]
+  Ex,
+  /// The record date of the corporate action.
+  #[serde(rename = "record_date")]
+  Record,
+  /// The date on which the corporate action is paid out.
+  #[serde(rename = "payable_date")]
+  Payable,
+}
+
+
+/// A corporate action announcement as returned by the
+/// /v2/corporate_actions/announcements endpoint.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Announcement {
+  /// The announcement's ID.
+  #[serde(rename = "id")]
+  pub id: Id,
+  /// The ID of the corporate action the announcement relates to.
+  #[serde(rename = "corporate_action_id")]
+  pub corporate_action_id: String,
+  /// The type of corporate action.
+  #[serde(rename = "ca_type")]
+  pub type_: Type,
+  /// The sub type of corporate action.
+  #[serde(rename = "ca_sub_type")]
+  pub sub_type: SubType,
+  /// The symbol of the company initiating the corporate action.
+  #[serde(rename = "initiating_symbol")]
+  pub initiating_symbol: String,
+  /// The CUSIP of the company initiating the corporate action.
+  #[serde(rename = "initiating_original_cusip")]
+  pub initiating_cusip: Option<String>,
+  /// The symbol of the company being targeted by the corporate action,
+  /// if any.
+  #[serde(rename = "target_symbol")]
+  pub target_symbol: Option<String>,
+  /// The CUSIP of the company being targeted by the corporate action,
+  /// if any.
+  #[serde(rename = "target_original_cusip")]
+  pub target_cusip: Option<String>,
+  /// The date the corporate action was declared.
+  #[serde(
+    rename = "declaration_date",
+    deserialize_with = "optional_system_time_from_date_str",
+  )]
+  pub declaration_date: Option<SystemTime>,
+  /// The first date on which a buyer of the security would no longer
+  /// be entitled to the corporate action.
+  #[serde(rename = "ex_date", deserialize_with = "optional_system_time_from_date_str")]
+  pub ex_date: Option<SystemTime>,
+  /// The date by which one has to be on record to be entitled to the
+  /// corporate action.
+  #[serde(rename = "record_date", deserialize_with = "optional_system_time_from_date_str")]
+  pub record_date: Option<SystemTime>,
+  /// The date on which the corporate action is paid out.
+  #[serde(rename = "payable_date", deserialize_with = "optional_system_time_from_date_str")]
+  pub payable_date: Option<SystemTime>,
+  /// The amount of cash paid out per share, if any.
+  #[serde(rename = "cash")]
+  pub cash: Num,
+  /// The denominator used for calculating the split or merger ratio.
+  #[serde(rename = "old_rate")]
+  pub old_rate: Num,
+  /// The numerator used for calculating the split or merger ratio.
+  #[serde(rename = "new_rate")]
+  pub new_rate: Num,
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v2/corporate_actions/announcements/<id> endpoint.
+  pub Get(Id),
+  Ok => Announcement, [
+    /// The announcement with the given ID was retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// No announcement was found with the given ID.
+    /* 404 */ NOT_FOUND => NotFound,
+  ]
+
+  fn path(input: &Self::Input) -> Str {
+    format!("/v2/corporate_actions/announcements/{}", input.to_hyphenated_ref()).into()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+  use time_util::parse_system_time_from_date_str;
+
+  use uuid::Uuid;
+
+  use crate::api_info::ApiInfo;
+  use crate::Client;
+  use crate::RequestError;
+
+
+  #[test]
+  fn parse_reference_announcement() {
+    let response = r#"{
+  "id": "be3c368a-4c7c-4384-808e-f02c9f5a8afe",
+  "corporate_action_id": "F58684224_XY37",
+  "ca_type": "dividend",
+  "ca_sub_type": "cash",
+  "initiating_symbol": "MLLAX",
+  "initiating_original_cusip": "55275E101",
+  "target_symbol": "MLLAX",
+  "target_original_cusip": "55275E101",
+  "declaration_date": "2021-01-05",
+  "ex_date": "2021-01-12",
+  "record_date": "2021-01-13",
+  "payable_date": "2021-01-14",
+  "cash": "0.018",
+  "old_rate": "1",
+  "new_rate": "1"
+}"#;
+
+    let id = Id(Uuid::parse_str("be3c368a-4c7c-4384-808e-f02c9f5a8afe").unwrap());
+    let announcement = from_json::<Announcement>(response).unwrap();
+    assert_eq!(announcement.id, id);
+    assert_eq!(announcement.corporate_action_id, "F58684224_XY37");
+    assert_eq!(announcement.type_, Type::Dividend);
+    assert_eq!(announcement.sub_type, SubType::Cash);
+    assert_eq!(announcement.initiating_symbol, "MLLAX");
+    assert_eq!(announcement.target_symbol, Some("MLLAX".to_string()));
+    assert_eq!(
+      announcement.declaration_date,
+      parse_system_time_from_date_str("2021-01-05")
+    );
+    assert_eq!(
+      announcement.ex_date,
+      parse_system_time_from_date_str("2021-01-12")
+    );
+    assert_eq!(announcement.cash, Num::new(18, 1000));
+    assert_eq!(announcement.old_rate, Num::from(1));
+    assert_eq!(announcement.new_rate, Num::from(1));
+  }
+
+  #[test]
+  fn parse_announcement_without_dates() {
+    let response = r#"{
+  "id": "be3c368a-4c7c-4384-808e-f02c9f5a8afe",
+  "corporate_action_id": "F58684224_XY37",
+  "ca_type": "split",
+  "ca_sub_type": "reverse_split",
+  "initiating_symbol": "XYZ",
+  "initiating_original_cusip": null,
+  "target_symbol": null,
+  "target_original_cusip": null,
+  "declaration_date": null,
+  "ex_date": "2021-01-12",
+  "record_date": null,
+  "payable_date": null,
+  "cash": "0",
+  "old_rate": "10",
+  "new_rate": "1"
+}"#;
+
+    let announcement = from_json::<Announcement>(response).unwrap();
+    assert_eq!(announcement.type_, Type::Split);
+    assert_eq!(announcement.sub_type, SubType::ReverseSplit);
+    assert_eq!(announcement.target_symbol, None);
+    assert_eq!(announcement.declaration_date, None);
+    assert_eq!(announcement.old_rate, Num::from(10));
+  }
+
+  #[test]
+  fn parse_unit_split_announcement() {
+    let response = r#"{
+  "id": "be3c368a-4c7c-4384-808e-f02c9f5a8afe",
+  "corporate_action_id": "F58684224_XY38",
+  "ca_type": "split",
+  "ca_sub_type": "unit_split",
+  "initiating_symbol": "XYZ",
+  "initiating_original_cusip": null,
+  "target_symbol": null,
+  "target_original_cusip": null,
+  "declaration_date": null,
+  "ex_date": "2021-01-12",
+  "record_date": null,
+  "payable_date": null,
+  "cash": "0",
+  "old_rate": "1",
+  "new_rate": "3"
+}"#;
+
+    let announcement = from_json::<Announcement>(response).unwrap();
+    assert_eq!(announcement.type_, Type::Split);
+    assert_eq!(announcement.sub_type, SubType::UnitSplit);
+    assert_eq!(announcement.new_rate, Num::from(3));
+  }
+
+  #[test(tokio::test)]
+  async fn retrieve_non_existent_announcement() {
+    let id = Id(Uuid::parse_str("00000000-0000-0000-0000-000000000000").unwrap());
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+    let result = client.issue::<Get>(id).await;
+    let err = result.unwrap_err();
+
+    match err {
+      RequestError::Endpoint(GetError::NotFound(..)) => (),
+      _ => panic!("Received unexpected error: {:?}", err),
+    };
+  }
+}
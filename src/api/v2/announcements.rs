@@ -0,0 +1,155 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::SystemTime;
+
+use serde::Serialize;
+use serde_urlencoded::to_string as to_query;
+
+use crate::api::v2::announcement::Announcement;
+use crate::api::v2::announcement::DateType;
+use crate::api::v2::announcement::Type;
+use crate::util::slice_to_str;
+use crate::util::system_time_to_date_str;
+use crate::Str;
+
+
+/// A helper for initializing `AnnouncementsReq` objects.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AnnouncementsReqInit {
+  /// See `AnnouncementsReq::symbol`.
+  pub symbol: Option<String>,
+  /// See `AnnouncementsReq::cusip`.
+  pub cusip: Option<String>,
+  /// See `AnnouncementsReq::date_type`.
+  pub date_type: Option<DateType>,
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl AnnouncementsReqInit {
+  /// Create an `AnnouncementsReq` from an `AnnouncementsReqInit`.
+  pub fn init<T>(self, types: T, start: SystemTime, end: SystemTime) -> AnnouncementsReq
+  where
+    T: Into<Vec<Type>>,
+  {
+    AnnouncementsReq {
+      types: types.into(),
+      start,
+      end,
+      symbol: self.symbol,
+      cusip: self.cusip,
+      date_type: self.date_type,
+    }
+  }
+}
+
+
+/// A GET request to be made to the /v2/corporate_actions/announcements
+/// endpoint.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AnnouncementsReq {
+  /// The types of corporate actions to retrieve announcements for.
+  #[serde(rename = "ca_types", serialize_with = "slice_to_str")]
+  pub types: Vec<Type>,
+  /// The start (inclusive) of the date range in which to look for
+  /// announcements.
+  ///
+  /// Note that the date range may not span more than 90 days.
+  #[serde(rename = "since", serialize_with = "system_time_to_date_str")]
+  pub start: SystemTime,
+  /// The end (inclusive) of the date range in which to look for
+  /// announcements.
+  #[serde(rename = "until", serialize_with = "system_time_to_date_str")]
+  pub end: SystemTime,
+  /// Only retrieve announcements for the given symbol.
+  #[serde(rename = "symbol", skip_serializing_if = "Option::is_none")]
+  pub symbol: Option<String>,
+  /// Only retrieve announcements for the given CUSIP.
+  #[serde(rename = "cusip", skip_serializing_if = "Option::is_none")]
+  pub cusip: Option<String>,
+  /// The type of date that the date range applies to.
+  ///
+  /// If not set, the date range is checked against all dates of an
+  /// announcement.
+  #[serde(rename = "date_type", skip_serializing_if = "Option::is_none")]
+  pub date_type: Option<DateType>,
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v2/corporate_actions/announcements endpoint.
+  pub Get(AnnouncementsReq),
+  Ok => Vec<Announcement>, [
+    /// The list of announcements was retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// One or more of the arguments are not well formed.
+    /* 422 */ UNPROCESSABLE_ENTITY => InvalidInput,
+  ]
+
+  fn path(_input: &Self::Input) -> Str {
+    "/v2/corporate_actions/announcements".into()
+  }
+
+  fn query(input: &Self::Input) -> Option<Str> {
+    // TODO: Realistically there should be no way for this unwrap to
+    //       ever panic because our conversion to strings should not be
+    //       fallible. But still, ideally we would not have to unwrap.
+    Some(to_query(input).unwrap().into())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::time::Duration;
+
+  use test_env_log::test;
+
+  use time_util::parse_system_time_from_date_str;
+
+  use crate::api_info::ApiInfo;
+  use crate::Client;
+
+
+  #[test]
+  fn emit_request() {
+    let start = parse_system_time_from_date_str("2021-01-01").unwrap();
+    let end = parse_system_time_from_date_str("2021-03-01").unwrap();
+    let request = AnnouncementsReqInit {
+      symbol: Some("AAPL".to_string()),
+      date_type: Some(DateType::Ex),
+      ..Default::default()
+    }
+    .init(vec![Type::Dividend, Type::Split], start, end);
+
+    let query = to_query(&request).unwrap();
+    let expected = "ca_types=dividend%2Csplit&since=2021-01-01&until=2021-03-01&\
+                    symbol=AAPL&date_type=ex_date";
+    assert_eq!(query, expected);
+  }
+
+  #[test(tokio::test)]
+  async fn list_announcements() {
+    let end = SystemTime::now();
+    let start = end - Duration::from_secs(60 * 60 * 24 * 30);
+    let request = AnnouncementsReqInit::default().init(
+      vec![Type::Dividend, Type::Merger, Type::Spinoff, Type::Split],
+      start,
+      end,
+    );
+
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+    let announcements = client.issue::<Get>(request).await.unwrap();
+
+    // We can't really know what announcements there are. But over a
+    // period of a month there should at least be some dividends.
+    assert!(!announcements.is_empty());
+  }
+}
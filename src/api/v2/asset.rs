@@ -4,9 +4,10 @@
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::fmt::Result as FmtResult;
-use std::ops::Deref;
 use std::str::FromStr;
 
+use num_decimal::Num;
+
 use serde::Deserialize;
 use serde::Serialize;
 use serde::Serializer;
@@ -14,62 +15,31 @@ use serde::Serializer;
 use uuid::Error as UuidError;
 use uuid::Uuid;
 
+use crate::instrument::AssetClass;
+use crate::util::vec_from_optional;
 use crate::Str;
 
+/// The asset class of an asset.
+///
+/// This type is an alias of the crate wide `AssetClass`.
+pub use crate::instrument::AssetClass as Class;
+/// The exchange an asset is listed at.
+pub use crate::instrument::Exchange;
 
-/// An ID uniquely identifying an asset.
-#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
-pub struct Id(pub Uuid);
-
-impl Deref for Id {
-  type Target = Uuid;
 
-  fn deref(&self) -> &Self::Target {
-    &self.0
-  }
-}
-
-
-/// An enumeration of the various asset classes available.
-#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
-pub enum Class {
-  /// US equities.
-  #[serde(rename = "us_equity")]
-  UsEquity,
-}
-
-impl AsRef<str> for Class {
-  fn as_ref(&self) -> &'static str {
-    match *self {
-      Class::UsEquity => "us_equity",
-    }
-  }
-}
-
-impl Default for Class {
-  fn default() -> Self {
-    Self::UsEquity
-  }
-}
-
-impl FromStr for Class {
-  type Err = ();
-
-  fn from_str(s: &str) -> Result<Self, Self::Err> {
-    if s == Class::UsEquity.as_ref() {
-      Ok(Class::UsEquity)
-    } else {
-      Err(())
-    }
-  }
+Id! {
+  /// An ID uniquely identifying an asset.
+  #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+  pub struct Id(pub Uuid);
 }
 
 
 /// The status an asset can have.
-#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub enum Status {
   /// The asset is active.
   #[serde(rename = "active")]
+  #[default]
   Active,
   /// The asset is inactive.
   #[serde(rename = "inactive")]
@@ -85,10 +55,38 @@ impl AsRef<str> for Status {
   }
 }
 
-impl Default for Status {
-  fn default() -> Self {
-    Self::Active
-  }
+
+/// An attribute an asset can have.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Attribute {
+  /// The asset is a publicly traded partnership (PTP) without a
+  /// qualified notice, meaning that sales of it are subject to
+  /// withholding for non-US persons.
+  #[serde(rename = "ptp_no_exception")]
+  PtpNoException,
+  /// The asset is a publicly traded partnership (PTP) with a qualified
+  /// notice, exempting sales of it from withholding.
+  #[serde(rename = "ptp_with_exception")]
+  PtpWithException,
+  /// The asset is part of an initial public offering (IPO) and may not
+  /// be tradable regularly yet.
+  #[serde(rename = "ipo")]
+  Ipo,
+  /// Options trading is enabled for the asset.
+  #[serde(rename = "options_enabled")]
+  OptionsEnabled,
+  /// Options contracts are listed on the asset.
+  #[serde(rename = "has_options")]
+  HasOptions,
+  /// Fractional shares of the asset can be traded during extended
+  /// hours.
+  #[serde(rename = "fractional_eh_enabled")]
+  FractionalEhEnabled,
+  /// Any other attribute that we have not accounted for.
+  ///
+  /// Note that having any such attribute should be considered a bug.
+  #[serde(other)]
+  Unknown,
 }
 
 
@@ -121,7 +119,7 @@ impl Display for ParseSymbolError {
 
 
 /// A symbol, and the various ways to represent it.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Symbol {
   /// The symbol. Note that this is not a unique way to identify an
   /// asset (the same symbol may be used in different exchanges or asset
@@ -193,7 +191,7 @@ impl FromStr for Symbol {
 impl Display for Symbol {
   fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
     match self {
-      Self::Sym(sym) => fmt.write_str(&sym),
+      Self::Sym(sym) => fmt.write_str(sym),
       Self::SymExchg(sym, exchg) => write!(fmt, "{}:{}", sym, exchg.as_ref()),
       Self::SymExchgCls(sym, exchg, cls) => {
         write!(fmt, "{}:{}:{}", sym, exchg.as_ref(), cls.as_ref())
@@ -213,75 +211,6 @@ impl Serialize for Symbol {
 }
 
 
-/// An enumeration of the various supported exchanges.
-#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
-pub enum Exchange {
-  /// American Stock Exchange.
-  #[serde(rename = "AMEX")]
-  Amex,
-  /// XXX
-  // TODO: Not quite clear.
-  #[serde(rename = "ARCA")]
-  Arca,
-  /// BATS Global Markets.
-  #[serde(rename = "BATS")]
-  Bats,
-  /// Nasdaq Stock Market.
-  #[serde(rename = "NASDAQ")]
-  Nasdaq,
-  /// New York Stock Exchange.
-  #[serde(rename = "NYSE")]
-  Nyse,
-  /// NYSE Arca.
-  #[serde(rename = "NYSEARCA")]
-  Nysearca,
-  /// Any other exchange that we have not accounted for.
-  ///
-  /// Note that having any such status should be considered a bug.
-  #[serde(other)]
-  Unknown,
-}
-
-impl AsRef<str> for Exchange {
-  fn as_ref(&self) -> &'static str {
-    match *self {
-      Exchange::Amex => "AMEX",
-      Exchange::Arca => "ARCA",
-      Exchange::Bats => "BATS",
-      Exchange::Nasdaq => "NASDAQ",
-      Exchange::Nyse => "NYSE",
-      Exchange::Nysearca => "NYSEARCA",
-      Exchange::Unknown => "unknown",
-    }
-  }
-}
-
-impl FromStr for Exchange {
-  type Err = ();
-
-  fn from_str(s: &str) -> Result<Self, Self::Err> {
-    if s == Exchange::Amex.as_ref() {
-      Ok(Exchange::Amex)
-    } else if s == Exchange::Arca.as_ref() {
-      Ok(Exchange::Arca)
-    } else if s == Exchange::Bats.as_ref() {
-      Ok(Exchange::Bats)
-    } else if s == Exchange::Nasdaq.as_ref() {
-      Ok(Exchange::Nasdaq)
-    } else if s == Exchange::Nyse.as_ref() {
-      Ok(Exchange::Nyse)
-    } else if s == Exchange::Nysearca.as_ref() {
-      Ok(Exchange::Nysearca)
-    } else {
-      // Note that we do not support creating the `Unknown` variant
-      // here. This variant is really only meant to cover
-      // deserialization.
-      Err(())
-    }
-  }
-}
-
-
 /// The representation of an asset as used by Alpaca.
 #[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct Asset {
@@ -290,7 +219,7 @@ pub struct Asset {
   pub id: Id,
   /// The asset's class.
   #[serde(rename = "class")]
-  pub class: Class,
+  pub class: AssetClass,
   /// The exchange the asset is traded at.
   #[serde(rename = "exchange")]
   pub exchange: Exchange,
@@ -314,6 +243,20 @@ pub struct Asset {
   /// A value of `true` is a prerequisite for being able to short it.
   #[serde(rename = "easy_to_borrow")]
   pub easy_to_borrow: bool,
+  /// The maintenance margin requirement for positions in the asset,
+  /// in percent of their market value, if reported.
+  #[serde(rename = "maintenance_margin_requirement", default)]
+  pub maintenance_margin_requirement: Option<Num>,
+  /// The attributes of the asset.
+  #[serde(rename = "attributes", default, deserialize_with = "vec_from_optional")]
+  pub attributes: Vec<Attribute>,
+}
+
+impl Asset {
+  /// Check whether the asset has the given attribute.
+  pub fn has_attribute(&self, attribute: Attribute) -> bool {
+    self.attributes.contains(&attribute)
+  }
 }
 
 
@@ -419,16 +362,55 @@ mod tests {
 }"#;
 
     let id = Id(Uuid::parse_str("904837e3-3b76-47ec-b432-046db621571b").unwrap());
-    let asset = from_json::<Asset>(&response).unwrap();
+    let asset = from_json::<Asset>(response).unwrap();
     assert_eq!(asset.id, id);
     assert_eq!(asset.class, Class::UsEquity);
     assert_eq!(asset.exchange, Exchange::Nasdaq);
     assert_eq!(asset.symbol, "AAPL");
     assert_eq!(asset.status, Status::Active);
-    assert_eq!(asset.tradable, true);
-    assert_eq!(asset.marginable, true);
-    assert_eq!(asset.shortable, true);
-    assert_eq!(asset.easy_to_borrow, true);
+    assert!(asset.tradable);
+    assert!(asset.marginable);
+    assert!(asset.shortable);
+    assert!(asset.easy_to_borrow);
+    assert_eq!(asset.maintenance_margin_requirement, None);
+    assert_eq!(asset.attributes, Vec::new());
+  }
+
+  #[test]
+  fn parse_attributes() {
+    let response = r#"{
+  "id": "904837e3-3b76-47ec-b432-046db621571b",
+  "class": "us_equity",
+  "exchange": "NYSE",
+  "symbol": "ET",
+  "status": "active",
+  "tradable": true,
+  "marginable": true,
+  "shortable": true,
+  "easy_to_borrow": true,
+  "maintenance_margin_requirement": 30,
+  "attributes": ["ptp_no_exception", "has_options", "something_new"]
+}"#;
+
+    let asset = from_json::<Asset>(response).unwrap();
+    assert_eq!(asset.maintenance_margin_requirement, Some(Num::from(30)));
+    assert_eq!(
+      asset.attributes,
+      vec![
+        Attribute::PtpNoException,
+        Attribute::HasOptions,
+        Attribute::Unknown
+      ]
+    );
+    assert!(asset.has_attribute(Attribute::PtpNoException));
+    assert!(!asset.has_attribute(Attribute::Ipo));
+
+    let response = response.replace(
+      r#"["ptp_no_exception", "has_options", "something_new"]"#,
+      "null",
+    );
+    let asset = from_json::<Asset>(&response).unwrap();
+    assert_eq!(asset.attributes, Vec::new());
   }
 
   #[test]
@@ -445,7 +427,7 @@ mod tests {
   "easy_to_borrow": true
 }"#;
 
-    let asset = from_json::<Asset>(&response).unwrap();
+    let asset = from_json::<Asset>(response).unwrap();
     assert_eq!(asset.exchange, Exchange::Unknown);
   }
 
@@ -463,7 +445,7 @@ mod tests {
       assert_eq!(asset.exchange, Exchange::Nasdaq);
       assert_eq!(asset.symbol, "AAPL");
       assert_eq!(asset.status, Status::Active);
-      assert_eq!(asset.tradable, true);
+      assert!(asset.tradable);
     }
 
     let symbols = [
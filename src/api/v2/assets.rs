@@ -5,18 +5,22 @@ use serde::Serialize;
 use serde_urlencoded::to_string as to_query;
 
 use crate::api::v2::asset::Asset;
-use crate::api::v2::asset::Class;
+use crate::api::v2::asset::Attribute;
 use crate::api::v2::asset::Status;
+use crate::instrument::AssetClass;
+use crate::util::slice_to_str;
 use crate::Str;
 
 
 /// A helper for initializing `AssetsReq` objects.
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct AssetsReqInit {
   /// See `AssetsReq::status`.
   pub status: Status,
   /// See `AssetsReq::class`.
-  pub class: Class,
+  pub class: AssetClass,
+  /// See `AssetsReq::attributes`.
+  pub attributes: Vec<Attribute>,
   #[doc(hidden)]
   pub _non_exhaustive: (),
 }
@@ -27,20 +31,31 @@ impl AssetsReqInit {
     AssetsReq {
       status: self.status,
       class: self.class,
+      attributes: self.attributes,
     }
   }
 }
 
 
 /// A GET request to be made to the /v2/assets endpoint.
-#[derive(Clone, Copy, Debug, Serialize, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
 pub struct AssetsReq {
   /// The status of assets to include in the response.
   #[serde(rename = "status")]
   pub status: Status,
   /// The asset class of which to include assets in the response.
   #[serde(rename = "asset_class")]
-  pub class: Class,
+  pub class: AssetClass,
+  /// The attributes of which assets in the response have to have at
+  /// least one.
+  ///
+  /// If empty, assets are not filtered by attributes.
+  #[serde(
+    rename = "attributes",
+    serialize_with = "slice_to_str",
+    skip_serializing_if = "Vec::is_empty"
+  )]
+  pub attributes: Vec<Attribute>,
 }
 
 
@@ -77,6 +92,26 @@ mod tests {
   use crate::Client;
 
 
+  /// Check that we correctly encode the attributes to filter by.
+  #[test]
+  fn emit_attributes() {
+    let request = AssetsReqInit::default().init();
+    assert_eq!(
+      to_query(&request).unwrap(),
+      "status=active&asset_class=us_equity"
+    );
+
+    let request = AssetsReqInit {
+      attributes: vec![Attribute::PtpNoException, Attribute::Ipo],
+      ..Default::default()
+    }
+    .init();
+    assert_eq!(
+      to_query(&request).unwrap(),
+      "status=active&asset_class=us_equity&attributes=ptp_no_exception%2Cipo"
+    );
+  }
+
   #[test(tokio::test)]
   async fn list_assets() {
     let api_info = ApiInfo::from_env().unwrap();
@@ -85,7 +120,7 @@ mod tests {
     let assets = client.issue::<Get>(request).await.unwrap();
 
     let asset = assets.iter().find(|x| x.symbol == "AAPL").unwrap();
-    assert_eq!(asset.class, Class::UsEquity);
+    assert_eq!(asset.class, AssetClass::UsEquity);
     assert_eq!(asset.exchange, Exchange::Nasdaq);
     assert_eq!(asset.status, Status::Active);
   }
@@ -0,0 +1,162 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::Duration;
+use std::time::SystemTime;
+
+use serde::de::Error as SerdeError;
+use serde::de::Unexpected;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde_urlencoded::to_string as to_query;
+
+use time_util::system_time_from_date_str;
+
+use crate::util::system_time_to_date_str;
+use crate::Str;
+
+
+/// Parse a time of day of the form HH:MM into the `Duration` since
+/// midnight.
+fn parse_time_of_day(time: &str) -> Option<Duration> {
+  let mut parts = time.splitn(2, ':');
+  let hours = parts.next()?.parse::<u64>().ok()?;
+  let minutes = parts.next()?.parse::<u64>().ok()?;
+
+  if hours < 24 && minutes < 60 {
+    Some(Duration::from_secs((hours * 60 + minutes) * 60))
+  } else {
+    None
+  }
+}
+
+/// Deserialize a time of day of the form HH:MM as the `Duration` since
+/// midnight.
+fn time_of_day_from_str<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+  D: Deserializer<'de>,
+{
+  let time = String::deserialize(deserializer)?;
+  parse_time_of_day(&time)
+    .ok_or_else(|| SerdeError::invalid_value(Unexpected::Str(&time), &"a time of day (HH:MM)"))
+}
+
+
+/// The market open and close times of a single trading day.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+pub struct OpenClose {
+  /// The trading day.
+  #[serde(rename = "date", deserialize_with = "system_time_from_date_str")]
+  pub date: SystemTime,
+  /// The time the market opens at, as the duration since midnight
+  /// (US Eastern time).
+  #[serde(rename = "open", deserialize_with = "time_of_day_from_str")]
+  pub open: Duration,
+  /// The time the market closes at, as the duration since midnight
+  /// (US Eastern time).
+  #[serde(rename = "close", deserialize_with = "time_of_day_from_str")]
+  pub close: Duration,
+}
+
+
+/// A GET request to be made to the /v2/calendar endpoint.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+pub struct CalendarReq {
+  /// The first date to retrieve market open and close times for.
+  #[serde(rename = "start", serialize_with = "system_time_to_date_str")]
+  pub start: SystemTime,
+  /// The last date to retrieve market open and close times for.
+  #[serde(rename = "end", serialize_with = "system_time_to_date_str")]
+  pub end: SystemTime,
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the /v2/calendar endpoint.
+  pub Get(CalendarReq),
+  Ok => Vec<OpenClose>, [
+    /// The market calendar was retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, []
+
+  fn path(_input: &Self::Input) -> Str {
+    "/v2/calendar".into()
+  }
+
+  fn query(input: &Self::Input) -> Option<Str> {
+    // TODO: Realistically there should be no way for this unwrap to
+    //       ever panic because our conversion to strings should not be
+    //       fallible. But still, ideally we would not have to unwrap.
+    Some(to_query(input).unwrap().into())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+  use time_util::parse_system_time_from_date_str;
+
+  use crate::api_info::ApiInfo;
+  use crate::Client;
+
+
+  #[test]
+  fn parse_times_of_day() {
+    assert_eq!(parse_time_of_day("09:30"), Some(Duration::from_secs(34200)));
+    assert_eq!(parse_time_of_day("16:00"), Some(Duration::from_secs(57600)));
+    assert_eq!(parse_time_of_day("24:00"), None);
+    assert_eq!(parse_time_of_day("12:60"), None);
+    assert_eq!(parse_time_of_day("1200"), None);
+  }
+
+  #[test]
+  fn parse_reference_calendar() {
+    let response = r#"[
+  {
+    "date": "2020-11-27",
+    "open": "09:30",
+    "close": "13:00",
+    "session_open": "0400",
+    "session_close": "1700"
+  }
+]"#;
+
+    let calendar = from_json::<Vec<OpenClose>>(response).unwrap();
+    assert_eq!(calendar.len(), 1);
+    assert_eq!(
+      calendar[0].date,
+      parse_system_time_from_date_str("2020-11-27").unwrap()
+    );
+    assert_eq!(calendar[0].open, Duration::from_secs(9 * 3600 + 30 * 60));
+    assert_eq!(calendar[0].close, Duration::from_secs(13 * 3600));
+  }
+
+  #[test(tokio::test)]
+  async fn retrieve_calendar() {
+    let request = CalendarReq {
+      start: parse_system_time_from_date_str("2020-12-21").unwrap(),
+      end: parse_system_time_from_date_str("2020-12-27").unwrap(),
+    };
+
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+    let calendar = client.issue::<Get>(request).await.unwrap();
+
+    // Christmas day was a holiday, so we expect four trading days.
+    assert_eq!(calendar.len(), 4);
+    assert_eq!(
+      calendar[0].date,
+      parse_system_time_from_date_str("2020-12-21").unwrap()
+    );
+    // December 24th had early close at 13:00.
+    assert_eq!(calendar[3].close, Duration::from_secs(13 * 3600));
+  }
+}
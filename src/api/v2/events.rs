@@ -42,16 +42,57 @@ pub struct AccountUpdate {
   pub deleted_at: Option<SystemTime>,
   /// The account's status.
   #[serde(rename = "status")]
-  pub status: String,
+  pub status: account::Status,
   /// The currency the account uses.
   #[serde(rename = "currency")]
-  pub currency: String,
+  pub currency: account::Currency,
   /// Cash balance.
   #[serde(rename = "cash")]
   pub cash: Num,
   /// Withdrawable cash amount.
   #[serde(rename = "cash_withdrawable")]
   pub withdrawable_cash: Num,
+  /// The sum of cash and the market value of all positions, if
+  /// reported.
+  #[serde(rename = "equity", default)]
+  pub equity: Option<Num>,
+  /// Equity as of the previous trading day at 16:00:00 ET, if reported.
+  #[serde(rename = "last_equity", default)]
+  pub last_equity: Option<Num>,
+  /// The currently available buying power, if reported.
+  #[serde(rename = "buying_power", default)]
+  pub buying_power: Option<Num>,
+  /// The buying power available for day trades, if reported.
+  #[serde(rename = "daytrading_buying_power", default)]
+  pub day_trading_buying_power: Option<Num>,
+  /// The buying power available for overnight positions, if reported.
+  #[serde(rename = "regt_buying_power", default)]
+  pub reg_t_buying_power: Option<Num>,
+  /// Real-time mark-to-market value of all long positions held in the
+  /// account, if reported.
+  #[serde(rename = "long_market_value", default)]
+  pub market_value_long: Option<Num>,
+  /// Real-time mark-to-market value of all short positions held in the
+  /// account, if reported.
+  #[serde(rename = "short_market_value", default)]
+  pub market_value_short: Option<Num>,
+  /// The initial margin requirement, if reported.
+  #[serde(rename = "initial_margin", default)]
+  pub initial_margin: Option<Num>,
+  /// The maintenance margin requirement, if reported.
+  #[serde(rename = "maintenance_margin", default)]
+  pub maintenance_margin: Option<Num>,
+  /// The buying power multiplier of the account, if reported.
+  #[serde(rename = "multiplier", default)]
+  pub multiplier: Option<Num>,
+  /// Whether or not the account has been flagged as a pattern day
+  /// trader, if reported.
+  #[serde(rename = "pattern_day_trader", default)]
+  pub day_trader: Option<bool>,
+  /// The number of day trades made in the last five trading days, if
+  /// reported.
+  #[serde(rename = "daytrade_count", default)]
+  pub daytrade_count: Option<u64>,
 }
 
 
@@ -70,7 +111,7 @@ impl EventStream for AccountUpdates {
 
 
 /// The status of a trade, as reported as part of a `TradeUpdate`.
-#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
 pub enum TradeStatus {
   /// The order has been received by Alpaca, and routed to exchanges for
   /// execution.
@@ -130,6 +171,14 @@ pub enum TradeStatus {
   /// for day), but remaining settlement calculations are still pending.
   #[serde(rename = "calculated")]
   Calculated,
+  /// The order has been accepted by Alpaca, but not yet routed to
+  /// the exchanges for execution.
+  #[serde(rename = "accepted")]
+  Accepted,
+  /// The order is being held, e.g., because it is a leg of a bracket
+  /// order whose entry has not yet been filled.
+  #[serde(rename = "held")]
+  Held,
   /// Any other status that we have not accounted for.
   ///
   /// Note that having any such status should be considered a bug.
@@ -148,6 +197,29 @@ pub struct TradeUpdate {
   /// The order associated with the trade.
   #[serde(rename = "order")]
   pub order: order::Order,
+  /// The ID of the execution reported, if any.
+  #[serde(rename = "execution_id", default)]
+  pub execution_id: Option<String>,
+  /// The price per share of the execution, for fill and partial fill
+  /// events.
+  #[serde(rename = "price", default)]
+  pub price: Option<Num>,
+  /// The number of shares of the execution, for fill and partial fill
+  /// events.
+  #[serde(rename = "qty", default)]
+  pub quantity: Option<Num>,
+  /// The size of the position in the order's symbol after the
+  /// execution, negative for short positions, for fill and partial fill
+  /// events.
+  #[serde(rename = "position_qty", default)]
+  pub position_quantity: Option<Num>,
+  /// The time at which the event occurred.
+  #[serde(
+    rename = "timestamp",
+    deserialize_with = "optional_system_time_from_str",
+    default
+  )]
+  pub timestamp: Option<SystemTime>,
 }
 
 /// A type used for requesting a subscription to the "trade_updates"
@@ -173,18 +245,164 @@ mod tests {
   use futures::StreamExt;
   use futures::TryStreamExt;
 
+  use serde_json::from_str as from_json;
+
   use test_env_log::test;
 
+  use time_util::parse_system_time_from_str;
+
   use url::Url;
 
   use crate::api::API_BASE_URL;
+  use crate::api::API_STREAM_URL;
+  use crate::api::BROKER_BASE_URL;
+  use crate::api::DATA_BASE_URL;
+  use crate::api::DATA_STREAM_URL;
   use crate::api::v2::order;
   use crate::api::v2::order_util::order_aapl;
   use crate::api_info::ApiInfo;
+  #[cfg(feature = "data")]
+  use crate::data::v2::Feed;
   use crate::Client;
   use crate::Error;
 
 
+  #[test]
+  fn parse_account_update() {
+    let response = r#"{
+  "id": "904837e3-3b76-47ec-b432-046db621571b",
+  "created_at": "2018-10-01T13:35:25Z",
+  "updated_at": "2018-10-02T09:31:01Z",
+  "deleted_at": null,
+  "status": "ACTIVE",
+  "currency": "USD",
+  "cash": "1241.54",
+  "cash_withdrawable": "523.71",
+  "equity": "5000.00",
+  "buying_power": "10000.00",
+  "pattern_day_trader": false
+}"#;
+
+    let update = from_json::<AccountUpdate>(response).unwrap();
+    assert_eq!(update.status, account::Status::Active);
+    assert_eq!(update.currency, account::Currency::Usd);
+    assert_eq!(update.cash, Num::new(124154, 100));
+    assert_eq!(update.withdrawable_cash, Num::new(52371, 100));
+    assert_eq!(update.equity, Some(Num::from(5000)));
+    assert_eq!(update.buying_power, Some(Num::from(10000)));
+    assert_eq!(update.day_trader, Some(false));
+    assert_eq!(update.daytrade_count, None);
+    assert_eq!(
+      update.updated_at,
+      Some(parse_system_time_from_str("2018-10-02T09:31:01Z").unwrap())
+    );
+  }
+
+  #[test]
+  fn parse_trade_status() {
+    let statuses = [
+      ("\"replaced\"", TradeStatus::Replaced),
+      ("\"order_replace_rejected\"", TradeStatus::ReplaceRejected),
+      ("\"order_cancel_rejected\"", TradeStatus::CancelRejected),
+      ("\"accepted\"", TradeStatus::Accepted),
+      ("\"held\"", TradeStatus::Held),
+      ("\"something_new\"", TradeStatus::Unknown),
+    ];
+
+    for (json, expected) in statuses.iter() {
+      assert_eq!(from_json::<TradeStatus>(json).unwrap(), *expected);
+    }
+  }
+
+  #[test]
+  fn parse_reference_fill() {
+    let response = r#"{
+  "event": "fill",
+  "execution_id": "2f63ea93-423d-4169-b3f6-3fdafc10c418",
+  "order": {
+    "id": "904837e3-3b76-47ec-b432-046db621571b",
+    "client_order_id": "904837e3-3b76-47ec-b432-046db621571b",
+    "created_at": "2018-10-05T05:48:59Z",
+    "updated_at": "2018-10-05T05:48:59Z",
+    "submitted_at": "2018-10-05T05:48:59Z",
+    "filled_at": "2018-10-05T05:48:59Z",
+    "expired_at": null,
+    "canceled_at": null,
+    "failed_at": null,
+    "asset_id": "904837e3-3b76-47ec-b432-046db621571b",
+    "symbol": "AAPL",
+    "asset_class": "us_equity",
+    "qty": "15",
+    "filled_qty": "15",
+    "type": "market",
+    "side": "sell",
+    "time_in_force": "day",
+    "limit_price": null,
+    "stop_price": null,
+    "filled_avg_price": "106.25",
+    "status": "filled",
+    "extended_hours": false,
+    "legs": null
+  },
+  "position_qty": "-5",
+  "price": "106.25",
+  "qty": "15",
+  "timestamp": "2018-10-05T05:48:59.123Z"
+}"#;
+
+    let update = from_json::<TradeUpdate>(response).unwrap();
+    assert_eq!(update.event, TradeStatus::Filled);
+    assert_eq!(
+      update.execution_id.as_deref(),
+      Some("2f63ea93-423d-4169-b3f6-3fdafc10c418")
+    );
+    assert_eq!(update.price, Some(Num::new(10625, 100)));
+    assert_eq!(update.quantity, Some(Num::from(15)));
+    assert_eq!(update.position_quantity, Some(Num::from(-5)));
+    assert_eq!(
+      update.timestamp,
+      Some(parse_system_time_from_str("2018-10-05T05:48:59.123Z").unwrap())
+    );
+  }
+
+  #[test]
+  fn parse_trade_update_without_execution() {
+    let response = r#"{
+  "event": "canceled",
+  "order": {
+    "id": "904837e3-3b76-47ec-b432-046db621571b",
+    "client_order_id": "904837e3-3b76-47ec-b432-046db621571b",
+    "created_at": "2018-10-05T05:48:59Z",
+    "updated_at": null,
+    "submitted_at": null,
+    "filled_at": null,
+    "expired_at": null,
+    "canceled_at": "2018-10-05T05:49:59Z",
+    "failed_at": null,
+    "asset_id": "904837e3-3b76-47ec-b432-046db621571b",
+    "symbol": "AAPL",
+    "asset_class": "us_equity",
+    "qty": "15",
+    "filled_qty": "0",
+    "type": "market",
+    "side": "buy",
+    "time_in_force": "day",
+    "limit_price": null,
+    "stop_price": null,
+    "filled_avg_price": null,
+    "status": "canceled",
+    "extended_hours": false,
+    "legs": null
+  }
+}"#;
+
+    let update = from_json::<TradeUpdate>(response).unwrap();
+    assert_eq!(update.event, TradeStatus::Canceled);
+    assert_eq!(update.execution_id, None);
+    assert_eq!(update.price, None);
+    assert_eq!(update.timestamp, None);
+  }
+
   #[test(tokio::test)]
   async fn stream_trade_events() {
     // TODO: There may be something amiss here. If we don't cancel the
@@ -230,6 +448,12 @@ mod tests {
     let api_base = Url::parse(API_BASE_URL).unwrap();
     let api_info = ApiInfo {
       base_url: api_base,
+      stream_url: Url::parse(API_STREAM_URL).unwrap(),
+      data_base_url: Url::parse(DATA_BASE_URL).unwrap(),
+      data_stream_url: Url::parse(DATA_STREAM_URL).unwrap(),
+      #[cfg(feature = "data")]
+      data_feed: Feed::Iex,
+      broker_base_url: Url::parse(BROKER_BASE_URL).unwrap(),
       key_id: "invalid".to_string(),
       secret: "invalid-too".to_string(),
     };
@@ -7,13 +7,20 @@ pub mod account;
 pub mod account_activities;
 /// Definitions pertaining the user's account configuration.
 pub mod account_config;
+/// Definitions surrounding corporate action announcements.
+pub mod announcement;
+/// Functionality for listing corporate action announcements.
+pub mod announcements;
 /// Definitions surrounding assets.
 pub mod asset;
 /// Functionality for listing available assets.
 pub mod assets;
+/// Functionality for retrieving the market calendar.
+pub mod calendar;
 /// Functionality for retrieving market open/close timing information.
 pub mod clock;
 /// Definitions for account and trade related events.
+#[cfg(feature = "streaming")]
 pub mod events;
 /// Definitions surrounding orders.
 pub mod order;
@@ -23,6 +30,8 @@ pub mod orders;
 pub mod position;
 /// Functionality for listing open positions.
 pub mod positions;
+/// Definitions surrounding watchlists.
+pub mod watchlist;
 
 mod de;
 mod util;
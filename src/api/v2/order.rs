@@ -1,7 +1,7 @@
 // Copyright (C) 2019-2020 Daniel Mueller <deso@posteo.net>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::ops::Deref;
+use std::convert::TryFrom;
 use std::ops::Not;
 use std::time::SystemTime;
 
@@ -19,27 +19,22 @@ use serde_json::Error as JsonError;
 use serde_json::to_vec as to_json;
 use serde_urlencoded::to_string as to_query;
 
-use uuid::Uuid;
-
 use time_util::optional_system_time_from_str;
 use time_util::system_time_from_str;
 
+use crate::api::v2::account;
 use crate::api::v2::asset;
+use crate::api::v2::position;
 use crate::api::v2::util::u64_from_str;
 use crate::api::v2::util::u64_to_str;
+use crate::instrument::AssetClass;
 use crate::Str;
 
 
-/// An ID uniquely identifying an order.
-#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
-pub struct Id(pub Uuid);
-
-impl Deref for Id {
-  type Target = Uuid;
-
-  fn deref(&self) -> &Self::Target {
-    &self.0
-  }
+Id! {
+  /// An ID uniquely identifying an order.
+  #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+  pub struct Id(pub Uuid);
 }
 
 
@@ -125,6 +120,18 @@ pub enum Status {
   Unknown,
 }
 
+impl Status {
+  /// Check whether the status is terminal, i.e., no further updates
+  /// will occur for the order.
+  #[inline]
+  pub fn is_terminal(self) -> bool {
+    matches!(
+      self,
+      Self::Replaced | Self::Filled | Self::Canceled | Self::Expired | Self::Rejected
+    )
+  }
+}
+
 
 /// The side an order is on.
 #[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
@@ -137,6 +144,42 @@ pub enum Side {
   Sell,
 }
 
+impl Side {
+  /// Retrieve the side of orders changing a position by the given
+  /// signed quantity, i.e., `Sell` for negative quantities and `Buy`
+  /// otherwise.
+  pub fn from_signed(quantity: i64) -> Self {
+    if quantity < 0 {
+      Self::Sell
+    } else {
+      Self::Buy
+    }
+  }
+
+  /// Retrieve the side of the position that orders of this side open
+  /// or increase.
+  pub fn position_side(self) -> position::Side {
+    match self {
+      Self::Buy => position::Side::Long,
+      Self::Sell => position::Side::Short,
+    }
+  }
+
+  /// Sign the given quantity according to this side, i.e., make it
+  /// negative for `Sell`.
+  ///
+  /// Quantities not representable as `i64` saturate at `i64::MAX` and
+  /// `i64::MIN`, respectively.
+  pub fn signed(self, quantity: u64) -> i64 {
+    match self {
+      Self::Buy => i64::try_from(quantity).unwrap_or(i64::MAX),
+      Self::Sell => i64::try_from(quantity)
+        .map(|quantity| -quantity)
+        .unwrap_or(i64::MIN),
+    }
+  }
+}
+
 impl Not for Side {
   type Output = Self;
 
@@ -149,12 +192,60 @@ impl Not for Side {
 }
 
 
+/// The intent of an order with respect to the position it affects.
+///
+/// Conveying the intent is necessary whenever the side of an order
+/// alone is ambiguous, e.g., when a sell order could either close a
+/// long position or open a short one.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum PositionIntent {
+  /// Buy in order to open or increase a long position.
+  #[serde(rename = "buy_to_open")]
+  BuyToOpen,
+  /// Buy in order to close or reduce a short position.
+  #[serde(rename = "buy_to_close")]
+  BuyToClose,
+  /// Sell in order to open or increase a short position.
+  #[serde(rename = "sell_to_open")]
+  SellToOpen,
+  /// Sell in order to close or reduce a long position.
+  #[serde(rename = "sell_to_close")]
+  SellToClose,
+}
+
+impl PositionIntent {
+  /// Retrieve the side of orders with this intent.
+  pub fn side(self) -> Side {
+    match self {
+      Self::BuyToOpen | Self::BuyToClose => Side::Buy,
+      Self::SellToOpen | Self::SellToClose => Side::Sell,
+    }
+  }
+
+  /// Check whether the intent is to open (or increase) a position, as
+  /// opposed to closing (or reducing) one.
+  pub fn is_opening(self) -> bool {
+    matches!(self, Self::BuyToOpen | Self::SellToOpen)
+  }
+
+  /// Retrieve the side of the position affected by orders with this
+  /// intent.
+  pub fn position_side(self) -> position::Side {
+    match self {
+      Self::BuyToOpen | Self::SellToClose => position::Side::Long,
+      Self::SellToOpen | Self::BuyToClose => position::Side::Short,
+    }
+  }
+}
+
+
 /// The class an order belongs to.
-#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
 pub enum Class {
   /// Any non-bracket order (i.e., regular market, limit, or stop loss
   /// orders).
   #[serde(rename = "simple")]
+  #[default]
   Simple,
   /// A bracket order is a chain of three orders that can be used to manage your
   /// position entry and exit. It is a common use case of an
@@ -174,19 +265,14 @@ pub enum Class {
   OneTriggersOther,
 }
 
-impl Default for Class {
-  fn default() -> Self {
-    Self::Simple
-  }
-}
-
 
 /// The type of an order.
 // Note that we currently do not support `stop_limit` orders.
-#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
 pub enum Type {
   /// A market order.
   #[serde(rename = "market")]
+  #[default]
   Market,
   /// A limit order.
   #[serde(rename = "limit")]
@@ -199,19 +285,14 @@ pub enum Type {
   StopLimit,
 }
 
-impl Default for Type {
-  fn default() -> Self {
-    Self::Market
-  }
-}
-
 
 /// A description of the time for which an order is valid.
-#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
 pub enum TimeInForce {
   /// The order is good for the day, and it will be canceled
   /// automatically at the end of Regular Trading Hours if unfilled.
   #[serde(rename = "day")]
+  #[default]
   Day,
   /// The order is good until canceled.
   #[serde(rename = "gtc")]
@@ -226,12 +307,6 @@ pub enum TimeInForce {
   UntilMarketClose,
 }
 
-impl Default for TimeInForce {
-  fn default() -> Self {
-    Self::Day
-  }
-}
-
 
 /// The take profit part of a bracket, one-cancels-other, or
 /// one-triggers-other order.
@@ -310,6 +385,10 @@ pub struct OrderReqInit {
   pub extended_hours: bool,
   /// See `OrderReq::client_order_id`.
   pub client_order_id: Option<String>,
+  /// See `OrderReq::currency`.
+  pub currency: Option<account::Currency>,
+  /// See `OrderReq::position_intent`.
+  pub position_intent: Option<PositionIntent>,
   #[doc(hidden)]
   pub _non_exhaustive: (),
 }
@@ -333,6 +412,8 @@ impl OrderReqInit {
       stop_loss: self.stop_loss,
       extended_hours: self.extended_hours,
       client_order_id: self.client_order_id,
+      currency: self.currency,
+      position_intent: self.position_intent,
     }
   }
 }
@@ -386,6 +467,18 @@ pub struct OrderReq {
   /// The documented maximum length is 48 characters.
   #[serde(rename = "client_order_id")]
   pub client_order_id: Option<String>,
+  /// The currency (e.g., JPY) the prices of the order are expressed
+  /// in.
+  ///
+  /// This is only supported for accounts using local currency trading.
+  /// If not set, prices are in USD.
+  #[serde(rename = "currency", skip_serializing_if = "Option::is_none")]
+  pub currency: Option<account::Currency>,
+  /// The intent of the order with respect to the position it affects.
+  ///
+  /// The intent has to agree with the order's side.
+  #[serde(rename = "position_intent", skip_serializing_if = "Option::is_none")]
+  pub position_intent: Option<PositionIntent>,
 }
 
 
@@ -494,7 +587,7 @@ pub struct Order {
   pub canceled_at: Option<SystemTime>,
   /// The order's asset class.
   #[serde(rename = "asset_class")]
-  pub asset_class: asset::Class,
+  pub asset_class: AssetClass,
   /// The ID of the asset represented by the order.
   #[serde(rename = "asset_id")]
   pub asset_id: asset::Id,
@@ -537,6 +630,31 @@ pub struct Order {
   /// trading hours.
   #[serde(rename = "extended_hours")]
   pub extended_hours: bool,
+  /// The exchange rate used for converting between the local currency
+  /// and USD, for accounts using local currency trading.
+  #[serde(rename = "swap_rate", default)]
+  pub swap_rate: Option<Num>,
+  /// The fee charged for the currency conversion, in basis points, for
+  /// accounts using local currency trading.
+  #[serde(rename = "swap_fee_bps", default)]
+  pub swap_fee_bps: Option<Num>,
+  /// The notional value of the order in local currency.
+  #[serde(rename = "local_notional", default)]
+  pub local_notional: Option<Num>,
+  /// The limit price in local currency.
+  #[serde(rename = "local_limit_price", default)]
+  pub local_limit_price: Option<Num>,
+  /// The stop price in local currency.
+  #[serde(rename = "local_stop_price", default)]
+  pub local_stop_price: Option<Num>,
+  /// The average price at which the order was filled, in local
+  /// currency.
+  #[serde(rename = "local_filled_avg_price", default)]
+  pub local_average_fill_price: Option<Num>,
+  /// The intent of the order with respect to the position it affects,
+  /// if it was provided on submission.
+  #[serde(rename = "position_intent", default)]
+  pub position_intent: Option<PositionIntent>,
   /// Additional legs of the order.
   ///
   /// Such an additional leg could be, for example, the order for the
@@ -593,7 +711,7 @@ Endpoint! {
     }
 
     let order_id = ClientOrderId {
-      order_id: &input,
+      order_id: input,
     };
     // TODO: Realistically there should be no way for this unwrap to
     //       ever panic because our conversion to strings should not be
@@ -712,7 +830,6 @@ mod tests {
 
   use uuid::Uuid;
 
-  use crate::api::v2::asset;
   use crate::api::v2::asset::Exchange;
   use crate::api::v2::asset::Symbol;
   use crate::api::v2::order_util::order_aapl;
@@ -721,6 +838,29 @@ mod tests {
   use crate::RequestError;
 
 
+  /// Check that order IDs can be converted to and from strings.
+  #[test]
+  fn id_string_round_trip() {
+    let string = "904837e3-3b76-47ec-b432-046db621571b";
+    let id = string.parse::<Id>().unwrap();
+    assert_eq!(id, Id::from(Uuid::parse_str(string).unwrap()));
+    assert_eq!(id.to_string(), string);
+
+    assert!("904837e3".parse::<Id>().is_err());
+  }
+
+  /// Check that we correctly classify terminal order statuses.
+  #[test]
+  fn terminal_status() {
+    assert!(Status::Filled.is_terminal());
+    assert!(Status::Canceled.is_terminal());
+    assert!(Status::Replaced.is_terminal());
+    assert!(!Status::New.is_terminal());
+    assert!(!Status::PendingCancel.is_terminal());
+    assert!(!Status::DoneForDay.is_terminal());
+    assert!(!Status::Unknown.is_terminal());
+  }
+
   #[test]
   fn emit_side() {
     assert_eq!(to_json(&Side::Buy).unwrap(), br#""buy""#);
@@ -733,6 +873,38 @@ mod tests {
     assert_eq!(!Side::Sell, Side::Buy);
   }
 
+  /// Check that signing quantities not representable as `i64`
+  /// saturates.
+  #[test]
+  fn signed_quantity_saturates() {
+    assert_eq!(Side::Buy.signed(i64::MAX as u64), i64::MAX);
+    assert_eq!(Side::Buy.signed(u64::MAX), i64::MAX);
+    assert_eq!(Side::Sell.signed(i64::MAX as u64), -i64::MAX);
+    assert_eq!(Side::Sell.signed(i64::MAX as u64 + 1), i64::MIN);
+    assert_eq!(Side::Sell.signed(u64::MAX), i64::MIN);
+  }
+
+  /// Check that we can convert order sides into position sides and
+  /// signed quantities.
+  #[test]
+  fn convert_side() {
+    assert_eq!(Side::Buy.position_side(), position::Side::Long);
+    assert_eq!(Side::Sell.position_side(), position::Side::Short);
+    assert_eq!(Side::Buy.signed(5), 5);
+    assert_eq!(Side::Sell.signed(5), -5);
+    assert_eq!(Side::from_signed(-3), Side::Sell);
+    assert_eq!(Side::from_signed(3), Side::Buy);
+
+    assert_eq!(
+      PositionIntent::BuyToClose.position_side(),
+      position::Side::Short
+    );
+    assert_eq!(
+      PositionIntent::SellToClose.position_side(),
+      position::Side::Long
+    );
+  }
+
   #[test]
   fn emit_type() {
     assert_eq!(to_json(&Type::Market).unwrap(), br#""market""#);
@@ -784,7 +956,7 @@ mod tests {
 }"#;
 
     let id = Id(Uuid::parse_str("904837e3-3b76-47ec-b432-046db621571b").unwrap());
-    let order = from_json::<Order>(&response).unwrap();
+    let order = from_json::<Order>(response).unwrap();
     assert_eq!(order.id, id);
     assert_eq!(
       order.created_at,
@@ -797,12 +969,129 @@ mod tests {
     assert_eq!(order.limit_price, Some(Num::from(107)));
     assert_eq!(order.stop_price, Some(Num::from(106)));
     assert_eq!(order.average_fill_price, Some(Num::new(10625, 100)));
+    assert_eq!(order.swap_rate, None);
+    assert_eq!(order.local_limit_price, None);
+    assert_eq!(order.position_intent, None);
+  }
+
+  #[test]
+  fn parse_position_intent_order() {
+    let response = r#"{
+    "id": "904837e3-3b76-47ec-b432-046db621571b",
+    "client_order_id": "904837e3-3b76-47ec-b432-046db621571b",
+    "created_at": "2018-10-05T05:48:59Z",
+    "updated_at": null,
+    "submitted_at": null,
+    "filled_at": null,
+    "expired_at": null,
+    "canceled_at": null,
+    "asset_id": "904837e3-3b76-47ec-b432-046db621571b",
+    "symbol": "AAPL",
+    "asset_class": "us_equity",
+    "qty": "1",
+    "filled_qty": "0",
+    "type": "market",
+    "side": "sell",
+    "position_intent": "sell_to_open",
+    "time_in_force": "day",
+    "limit_price": null,
+    "stop_price": null,
+    "filled_avg_price": null,
+    "status": "new",
+    "extended_hours": false,
+    "legs": null
+}"#;
+
+    let order = from_json::<Order>(response).unwrap();
+    assert_eq!(order.position_intent, Some(PositionIntent::SellToOpen));
+    assert_eq!(PositionIntent::SellToOpen.side(), order.side);
+    assert!(PositionIntent::SellToOpen.is_opening());
+    assert!(!PositionIntent::BuyToClose.is_opening());
+  }
+
+  #[test]
+  fn emit_position_intent() {
+    let request = OrderReqInit {
+      position_intent: Some(PositionIntent::BuyToClose),
+      ..Default::default()
+    }
+    .init("AAPL", Side::Buy, 1);
+
+    let json = String::from_utf8(to_json(&request).unwrap()).unwrap();
+    assert!(
+      json.contains(r#""position_intent":"buy_to_close""#),
+      "{}",
+      json
+    );
+
+    let request = OrderReqInit::default().init("AAPL", Side::Buy, 1);
+    let json = String::from_utf8(to_json(&request).unwrap()).unwrap();
+    assert!(!json.contains("position_intent"), "{}", json);
+  }
+
+  #[test]
+  fn parse_local_currency_order() {
+    let response = r#"{
+    "id": "904837e3-3b76-47ec-b432-046db621571b",
+    "client_order_id": "904837e3-3b76-47ec-b432-046db621571b",
+    "created_at": "2018-10-05T05:48:59Z",
+    "updated_at": null,
+    "submitted_at": null,
+    "filled_at": null,
+    "expired_at": null,
+    "canceled_at": null,
+    "asset_id": "904837e3-3b76-47ec-b432-046db621571b",
+    "symbol": "AAPL",
+    "asset_class": "us_equity",
+    "qty": "1",
+    "filled_qty": "0",
+    "type": "limit",
+    "side": "buy",
+    "time_in_force": "day",
+    "limit_price": "100",
+    "stop_price": null,
+    "filled_avg_price": null,
+    "status": "new",
+    "extended_hours": false,
+    "legs": null,
+    "swap_rate": "143.5",
+    "swap_fee_bps": "15",
+    "local_notional": null,
+    "local_limit_price": "14350",
+    "local_stop_price": null,
+    "local_filled_avg_price": null
+}"#;
+
+    let order = from_json::<Order>(response).unwrap();
+    assert_eq!(order.swap_rate, Some(Num::new(1435, 10)));
+    assert_eq!(order.swap_fee_bps, Some(Num::from(15)));
+    assert_eq!(order.local_notional, None);
+    assert_eq!(order.local_limit_price, Some(Num::from(14350)));
+    assert_eq!(order.local_average_fill_price, None);
+  }
+
+  #[test]
+  fn emit_currency() {
+    let request = OrderReqInit {
+      type_: Type::Limit,
+      limit_price: Some(Num::from(14350)),
+      currency: Some(account::Currency::Jpy),
+      ..Default::default()
+    }
+    .init("AAPL", Side::Buy, 1);
+
+    let json = String::from_utf8(to_json(&request).unwrap()).unwrap();
+    assert!(json.contains(r#""currency":"JPY""#), "{}", json);
+
+    let request = OrderReqInit::default().init("AAPL", Side::Buy, 1);
+    let json = String::from_utf8(to_json(&request).unwrap()).unwrap();
+    assert!(!json.contains("currency"), "{}", json);
   }
 
   #[test(tokio::test)]
   async fn submit_limit_order() {
     async fn test(extended_hours: bool) -> Result<(), RequestError<PostError>> {
-      let symbol = Symbol::SymExchgCls("SPY".to_string(), Exchange::Arca, asset::Class::UsEquity);
+      let symbol = Symbol::SymExchgCls("SPY".to_string(), Exchange::Arca, AssetClass::UsEquity);
       let request = OrderReqInit {
         type_: Type::Limit,
         limit_price: Some(Num::from(1)),
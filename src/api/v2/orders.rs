@@ -1,9 +1,13 @@
 // Copyright (C) 2019-2021 Daniel Mueller <deso@posteo.net>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use std::time::SystemTime;
+
 use serde::Serialize;
 use serde_urlencoded::to_string as to_query;
 
+use time_util::optional_system_time_to_rfc3339;
+
 use crate::api::v2::order::Order;
 use crate::Str;
 
@@ -23,6 +27,26 @@ pub enum Status {
 }
 
 
+/// The order in which orders are listed, by submission time.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum Direction {
+  /// List orders in ascending order, i.e., oldest first.
+  #[serde(rename = "asc")]
+  Ascending,
+  /// List orders in descending order, i.e., newest first.
+  #[serde(rename = "desc")]
+  Descending,
+}
+
+
+/// The maximum number of orders the /v2/orders endpoint reports in a
+/// single response, i.e., the largest supported `OrdersReq::limit`.
+///
+/// Use [`Client::all_orders`](crate::Client::all_orders) for retrieving
+/// more orders than that.
+pub const MAX_LIMIT: u64 = 500;
+
+
 /// A GET request to be made to the /v2/orders endpoint.
 // Note that we do not expose or supply all parameters that the Alpaca
 // API supports.
@@ -33,13 +57,31 @@ pub struct OrdersReq {
   #[serde(rename = "status")]
   pub status: Status,
   /// The maximum number of orders in response. Defaults to 50 and max
-  /// is 500.
+  /// is [`MAX_LIMIT`].
   #[serde(rename = "limit")]
   pub limit: u64,
   /// If false the result will not roll up multi-leg orders under the
   /// legs field of the primary order.
   #[serde(rename = "nested")]
   pub nested: bool,
+  /// Only list orders submitted after this time (exclusive).
+  #[serde(
+    rename = "after",
+    serialize_with = "optional_system_time_to_rfc3339",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub after: Option<SystemTime>,
+  /// Only list orders submitted until this time (exclusive).
+  #[serde(
+    rename = "until",
+    serialize_with = "optional_system_time_to_rfc3339",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub until: Option<SystemTime>,
+  /// The order in which to list orders. If `None` the server side
+  /// default, newest first, is used.
+  #[serde(rename = "direction", skip_serializing_if = "Option::is_none")]
+  pub direction: Option<Direction>,
 }
 
 impl Default for OrdersReq {
@@ -51,6 +93,9 @@ impl Default for OrdersReq {
       // returned. As such, having them included is very non-intrusive
       // and should be a reasonable default.
       nested: true,
+      after: None,
+      until: None,
+      direction: None,
     }
   }
 }
@@ -86,12 +131,33 @@ mod tests {
 
   use test_env_log::test;
 
+  use time_util::parse_system_time_from_str;
+
   use crate::api::v2::order;
   use crate::api::v2::order_util::order_aapl;
   use crate::api_info::ApiInfo;
   use crate::Client;
 
 
+  /// Check that we serialize the time window of a request correctly.
+  #[test]
+  fn serialize_time_window() {
+    let request = OrdersReq {
+      status: Status::Closed,
+      after: parse_system_time_from_str("2021-06-01T13:30:00Z"),
+      direction: Some(Direction::Ascending),
+      ..Default::default()
+    };
+    let query = to_query(request).unwrap();
+    assert_eq!(
+      query,
+      "status=closed&limit=50&nested=true&after=2021-06-01T13%3A30%3A00%2B00%3A00&direction=asc"
+    );
+
+    let query = to_query(OrdersReq::default()).unwrap();
+    assert_eq!(query, "status=open&limit=50&nested=true");
+  }
+
   #[test(tokio::test)]
   async fn list_orders() {
     async fn test(status: Status) {
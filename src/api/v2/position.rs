@@ -12,6 +12,8 @@ use serde::Deserialize;
 use crate::api::v2::asset;
 use crate::api::v2::order;
 use crate::api::v2::util::u64_from_i64_from_str;
+use crate::instrument::AssetClass;
+use crate::instrument::Exchange;
 use crate::Str;
 
 
@@ -26,6 +28,23 @@ pub enum Side {
   Short,
 }
 
+impl Side {
+  /// Retrieve the side of orders opening or increasing a position of
+  /// this side.
+  pub fn opening_order_side(self) -> order::Side {
+    match self {
+      Self::Long => order::Side::Buy,
+      Self::Short => order::Side::Sell,
+    }
+  }
+
+  /// Retrieve the side of orders closing or reducing a position of
+  /// this side.
+  pub fn closing_order_side(self) -> order::Side {
+    !self.opening_order_side()
+  }
+}
+
 impl Not for Side {
   type Output = Self;
 
@@ -50,10 +69,10 @@ pub struct Position {
   pub symbol: String,
   /// The exchange the position is held at.
   #[serde(rename = "exchange")]
-  pub exchange: asset::Exchange,
+  pub exchange: Exchange,
   /// The position's asset class.
   #[serde(rename = "asset_class")]
-  pub asset_class: asset::Class,
+  pub asset_class: AssetClass,
   /// The average entry price of the position.
   #[serde(rename = "avg_entry_price")]
   pub average_entry_price: Num,
@@ -92,6 +111,14 @@ pub struct Position {
   pub change_today: Num,
 }
 
+impl Position {
+  /// Retrieve the quantity of the position, signed according to its
+  /// side, i.e., negative for short positions.
+  pub fn signed_quantity(&self) -> i64 {
+    self.side.opening_order_side().signed(self.quantity)
+  }
+}
+
 
 Endpoint! {
   /// The representation of a GET request to the /v2/positions/<symbol>
@@ -154,6 +181,15 @@ mod tests {
     assert_eq!(!Side::Short, Side::Long);
   }
 
+  /// Check that we can convert position sides into order sides.
+  #[test]
+  fn convert_side() {
+    assert_eq!(Side::Long.opening_order_side(), order::Side::Buy);
+    assert_eq!(Side::Long.closing_order_side(), order::Side::Sell);
+    assert_eq!(Side::Short.opening_order_side(), order::Side::Sell);
+    assert_eq!(Side::Short.closing_order_side(), order::Side::Buy);
+  }
+
   #[test]
   fn parse_reference_position() {
     let response = r#"{
@@ -175,10 +211,10 @@ mod tests {
     "change_today": "0.0084"
 }"#;
 
-    let pos = from_json::<Position>(&response).unwrap();
+    let pos = from_json::<Position>(response).unwrap();
     assert_eq!(pos.symbol, "AAPL");
-    assert_eq!(pos.exchange, asset::Exchange::Nasdaq);
-    assert_eq!(pos.asset_class, asset::Class::UsEquity);
+    assert_eq!(pos.exchange, Exchange::Nasdaq);
+    assert_eq!(pos.asset_class, AssetClass::UsEquity);
     assert_eq!(pos.average_entry_price, Num::from(100));
     assert_eq!(pos.quantity, 5);
     assert_eq!(pos.side, Side::Long);
@@ -214,7 +250,7 @@ mod tests {
       "change_today":"-0.0573613766730402"
     }"#;
 
-    let pos = from_json::<Position>(&response).unwrap();
+    let pos = from_json::<Position>(response).unwrap();
     assert_eq!(pos.symbol, "XLK");
     assert_eq!(pos.quantity, 24);
   }
@@ -233,7 +269,7 @@ mod tests {
     match result {
       Ok(pos) => {
         assert_eq!(pos.symbol, "SPY");
-        assert_eq!(pos.asset_class, asset::Class::UsEquity);
+        assert_eq!(pos.asset_class, AssetClass::UsEquity);
       },
       Err(err) => match err {
         RequestError::Endpoint(GetError::NotFound(..)) => (),
@@ -13,8 +13,8 @@ fn parse_i64<'de, D>(s: &str) -> Result<i64, D::Error>
 where
   D: Deserializer<'de>,
 {
-  i64::from_str_radix(&s, 10)
-    .map_err(|_| SerdeError::invalid_value(Unexpected::Str(&s), &"an integer"))
+  s.parse::<i64>()
+    .map_err(|_| SerdeError::invalid_value(Unexpected::Str(s), &"an integer"))
 }
 
 /// Parse a `u64` from a string.
@@ -22,8 +22,8 @@ fn parse_u64<'de, D>(s: &str) -> Result<u64, D::Error>
 where
   D: Deserializer<'de>,
 {
-  u64::from_str_radix(&s, 10)
-    .map_err(|_| SerdeError::invalid_value(Unexpected::Str(&s), &"an unsigned integer"))
+  s.parse::<u64>()
+    .map_err(|_| SerdeError::invalid_value(Unexpected::Str(s), &"an unsigned integer"))
 }
 
 /// Deserialize a string encoded `u64`, parsing the value as signed
@@ -33,7 +33,7 @@ where
   D: Deserializer<'de>,
 {
   let value = parse_i64::<D>(&String::deserialize(deserializer)?)?;
-  Ok(value.abs() as u64)
+  Ok(value.unsigned_abs())
 }
 
 /// Deserialize a string encoded `u64`.
@@ -0,0 +1,165 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::SystemTime;
+
+use serde::Deserialize;
+use serde_urlencoded::to_string as to_query;
+
+use time_util::system_time_from_str;
+
+use crate::api::v2::asset::Asset;
+use crate::util::vec_from_optional;
+use crate::Str;
+
+
+Id! {
+  /// An ID uniquely identifying a watchlist.
+  #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+  pub struct Id(pub Uuid);
+}
+
+
+/// A watchlist, i.e., a named list of assets.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Watchlist {
+  /// The watchlist's ID.
+  #[serde(rename = "id")]
+  pub id: Id,
+  /// The watchlist's user defined name.
+  #[serde(rename = "name")]
+  pub name: String,
+  /// The assets on the watchlist, in the order in which they were
+  /// added.
+  #[serde(rename = "assets", default, deserialize_with = "vec_from_optional")]
+  pub assets: Vec<Asset>,
+  /// The time the watchlist was created at.
+  #[serde(rename = "created_at", deserialize_with = "system_time_from_str")]
+  pub created_at: SystemTime,
+  /// The time the watchlist was last updated at.
+  #[serde(rename = "updated_at", deserialize_with = "system_time_from_str")]
+  pub updated_at: SystemTime,
+}
+
+impl Watchlist {
+  /// Retrieve an iterator over the symbols of the assets on the
+  /// watchlist.
+  pub fn symbols(&self) -> impl Iterator<Item = &str> {
+    self.assets.iter().map(|asset| asset.symbol.as_str())
+  }
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the /v2/watchlists/<id>
+  /// endpoint.
+  pub Get(Id),
+  Ok => Watchlist, [
+    /// The watchlist with the given ID was retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// No watchlist was found with the given ID.
+    /* 404 */ NOT_FOUND => NotFound,
+  ]
+
+  fn path(input: &Self::Input) -> Str {
+    format!("/v2/watchlists/{}", input.to_hyphenated_ref()).into()
+  }
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v2/watchlists:by_name endpoint, retrieving a watchlist by its
+  /// name.
+  pub GetByName(String),
+  Ok => Watchlist, [
+    /// The watchlist with the given name was retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetByNameError, [
+    /// No watchlist was found with the given name.
+    /* 404 */ NOT_FOUND => NotFound,
+  ]
+
+  fn path(_input: &Self::Input) -> Str {
+    "/v2/watchlists:by_name".into()
+  }
+
+  fn query(input: &Self::Input) -> Option<Str> {
+    // TODO: Realistically there should be no way for this unwrap to
+    //       ever panic because our conversion to strings should not be
+    //       fallible. But still, ideally we would not have to unwrap.
+    Some(to_query([("name", input)]).unwrap().into())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::str::FromStr;
+
+  use http_endpoint::Endpoint;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+
+  /// Check that we can parse the reference watchlist from the
+  /// documentation.
+  #[test]
+  fn parse_reference_watchlist() {
+    let response = r#"{
+  "account_id": "1d5493c9-ea39-4377-aa94-340734c368ae",
+  "assets": [
+    {
+      "class": "us_equity",
+      "easy_to_borrow": true,
+      "exchange": "ARCA",
+      "id": "b28f4066-5c6d-479b-a2af-85dc1a8f16fb",
+      "marginable": true,
+      "shortable": true,
+      "status": "active",
+      "symbol": "SPY",
+      "tradable": true
+    },
+    {
+      "class": "us_equity",
+      "easy_to_borrow": false,
+      "exchange": "NASDAQ",
+      "id": "f801f835-bfe6-4a9d-a6b1-ccbb84bfd75f",
+      "marginable": true,
+      "shortable": false,
+      "status": "active",
+      "symbol": "AMZN",
+      "tradable": true
+    }
+  ],
+  "created_at": "2019-10-30T07:54:42.981322Z",
+  "id": "fb306e55-16d3-4118-8c3d-c1615fcd4c03",
+  "name": "Monday List",
+  "updated_at": "2019-10-30T07:54:42.981322Z"
+}"#;
+
+    let watchlist = from_json::<Watchlist>(response).unwrap();
+    assert_eq!(watchlist.name, "Monday List");
+    assert_eq!(
+      watchlist.id,
+      Id::from_str("fb306e55-16d3-4118-8c3d-c1615fcd4c03").unwrap()
+    );
+    assert_eq!(watchlist.symbols().collect::<Vec<_>>(), vec!["SPY", "AMZN"]);
+  }
+
+  /// Check that we emit the expected query for retrieving a watchlist
+  /// by name.
+  #[test]
+  fn emit_by_name_query() {
+    let name = "Monday List".to_string();
+    assert_eq!(GetByName::path(&name), "/v2/watchlists:by_name");
+    assert_eq!(GetByName::query(&name).unwrap(), "name=Monday+List");
+  }
+}
@@ -2,33 +2,156 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use std::env::var_os;
-use std::ffi::OsString;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
 
 use url::Url;
 
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
 use crate::api::API_BASE_URL;
+use crate::api::BROKER_BASE_URL;
+use crate::api::DATA_BASE_URL;
+use crate::api::DATA_STREAM_URL;
+#[cfg(feature = "data")]
+use crate::data::v2::Feed;
 use crate::Error;
 
-/// The base URL to the API to use.
-const ENV_API_URL: &str = "APCA_API_BASE_URL";
-/// The environment variable representing the key ID.
-const ENV_KEY_ID: &str = "APCA_API_KEY_ID";
-/// The environment variable representing the secret key.
-const ENV_SECRET: &str = "APCA_API_SECRET_KEY";
+/// The prefix of the environment variables used by
+/// `ApiInfo::from_env`.
+const ENV_PREFIX: &str = "APCA";
+/// The suffix of the variable representing the base URL to the API to
+/// use.
+const ENV_API_URL: &str = "API_BASE_URL";
+/// The suffix of the variable representing the URL of the stream
+/// providing account and trade updates.
+const ENV_STREAM_URL: &str = "API_STREAM_URL";
+/// The suffix of the variable representing the base URL to the market
+/// data API to use.
+const ENV_DATA_URL: &str = "API_DATA_URL";
+/// The suffix of the variable representing the URL of the market data
+/// stream to use.
+const ENV_DATA_STREAM_URL: &str = "API_DATA_STREAM_URL";
+/// The suffix of the variable representing the market data feed to use
+/// by default.
+#[cfg(feature = "data")]
+const ENV_DATA_FEED: &str = "API_DATA_FEED";
+/// The suffix of the variable representing the base URL to the Broker
+/// API to use.
+const ENV_BROKER_URL: &str = "API_BROKER_URL";
+/// The suffix of the variable representing the key ID.
+const ENV_KEY_ID: &str = "API_KEY_ID";
+/// The suffix of the variable representing the secret key.
+const ENV_SECRET: &str = "API_SECRET_KEY";
 
 
 /// An object encapsulating the information used for working with the
 /// Alpaca API.
-#[derive(Clone, Debug, PartialEq)]
+///
+/// Each family of endpoints -- the trading API and its stream, the
+/// market data API and its stream, as well as the Broker API -- is
+/// reached through its own URL, each of which can be overwritten
+/// individually, e.g., for pointing it to a sandbox or a proxy.
+///
+/// The secret is redacted from the object's `Debug` representation.
+/// With the `zeroize` feature enabled, the key ID and secret are
+/// additionally overwritten in memory when the object is dropped.
+#[derive(Clone, PartialEq)]
 pub struct ApiInfo {
-  /// The base URL for the API.
+  /// The base URL for the trading API.
   pub(crate) base_url: Url,
+  /// The URL of the stream providing account and trade updates.
+  pub(crate) stream_url: Url,
+  /// The base URL for the market data API.
+  pub(crate) data_base_url: Url,
+  /// The URL of the market data stream.
+  pub(crate) data_stream_url: Url,
+  /// The market data feed to use by default.
+  #[cfg(feature = "data")]
+  pub(crate) data_feed: Feed,
+  /// The base URL for the Broker API.
+  pub(crate) broker_base_url: Url,
   /// The key ID to use for authentication.
   pub(crate) key_id: String,
   /// The secret to use for authentication.
   pub(crate) secret: String,
 }
 
+impl Debug for ApiInfo {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    let mut debug = fmt.debug_struct("ApiInfo");
+    let _ = debug
+      .field("base_url", &self.base_url)
+      .field("stream_url", &self.stream_url)
+      .field("data_base_url", &self.data_base_url)
+      .field("data_stream_url", &self.data_stream_url);
+    #[cfg(feature = "data")]
+    let _ = debug.field("data_feed", &self.data_feed);
+    debug
+      .field("broker_base_url", &self.broker_base_url)
+      .field("key_id", &self.key_id)
+      .field("secret", &"<redacted>")
+      .finish()
+  }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for ApiInfo {
+  fn drop(&mut self) {
+    self.key_id.zeroize();
+    self.secret.zeroize();
+  }
+}
+
+/// Retrieve the value of the environment variable `name` as a string,
+/// if it is set.
+#[allow(clippy::result_large_err)]
+fn string_from_env(name: &str) -> Result<Option<String>, Error> {
+  var_os(name)
+    .map(|value| {
+      value.into_string().map_err(|_| {
+        Error::Str(format!("{} environment variable is not a valid string", name).into())
+      })
+    })
+    .transpose()
+}
+
+/// Retrieve a URL from the environment variable `name`, falling back
+/// to `default` if it is not set.
+#[allow(clippy::result_large_err)]
+fn url_from_env(name: &str, default: &str) -> Result<Url, Error> {
+  let url = string_from_env(name)?.unwrap_or_else(|| default.to_string());
+  Ok(Url::parse(&url)?)
+}
+
+/// Derive the URL of the stream providing account and trade updates
+/// from the base URL of the trading API.
+#[allow(clippy::result_large_err)]
+fn stream_url_from_base_url(base_url: &Url) -> Result<Url, Error> {
+  let mut url = base_url.clone();
+  match url.scheme() {
+    "ws" | "wss" => (),
+    _ => {
+      url.set_scheme("wss").map_err(|()| {
+        Error::Str(format!("unable to change URL scheme for {}: invalid URL?", url).into())
+      })?;
+    },
+  }
+  url.set_path("stream");
+  Ok(url)
+}
+
+/// Retrieve the value of the environment variable `name`, which has to
+/// be set.
+#[allow(clippy::result_large_err)]
+fn required_from_env(name: &str) -> Result<String, Error> {
+  string_from_env(name)?
+    .ok_or_else(|| Error::Str(format!("{} environment variable not found", name).into()))
+}
+
+
 impl ApiInfo {
   /// Create an `ApiInfo` object with information from the environment.
   ///
@@ -37,37 +160,256 @@ impl ApiInfo {
   /// following information is used:
   /// - the Alpaca API base URL is retrieved from the APCA_API_BASE_URL
   ///   variable
+  /// - the URL of the stream providing account and trade updates is
+  ///   retrieved from the APCA_API_STREAM_URL variable; if it is not
+  ///   set, it is derived from the API base URL
+  /// - the Alpaca market data API base URL is retrieved from the
+  ///   APCA_API_DATA_URL variable
+  /// - the Alpaca market data stream URL is retrieved from the
+  ///   APCA_API_DATA_STREAM_URL variable
+  /// - the market data feed to use by default, `iex` or `sip`, is
+  ///   retrieved from the APCA_API_DATA_FEED variable
+  /// - the Broker API base URL is retrieved from the
+  ///   APCA_API_BROKER_URL variable
   /// - the Alpaca account key ID is retrieved from the APCA_API_KEY_ID
   ///   variable
   /// - the Alpaca account secret is retrieved from the APCA_API_SECRET_KEY
   ///   variable
+  #[allow(clippy::result_large_err)]
   pub fn from_env() -> Result<Self, Error> {
-    let base_url = var_os(ENV_API_URL)
-      .unwrap_or_else(|| OsString::from(API_BASE_URL))
-      .into_string()
-      .map_err(|_| {
-        Error::Str(format!("{} environment variable is not a valid string", ENV_API_URL).into())
-      })?;
-    let base_url = Url::parse(&base_url)?;
+    Self::from_env_prefixed(ENV_PREFIX)
+  }
 
-    let key_id = var_os(ENV_KEY_ID)
-      .ok_or_else(|| Error::Str(format!("{} environment variable not found", ENV_KEY_ID).into()))?
-      .into_string()
-      .map_err(|_| {
-        Error::Str(format!("{} environment variable is not a valid string", ENV_KEY_ID).into())
-      })?;
+  /// Create an `ApiInfo` object with information from environment
+  /// variables using the given prefix.
+  ///
+  /// This constructor works like `ApiInfo::from_env`, but reads
+  /// variables named `<prefix>_API_BASE_URL`, `<prefix>_API_STREAM_URL`,
+  /// `<prefix>_API_DATA_URL`, `<prefix>_API_DATA_STREAM_URL`,
+  /// `<prefix>_API_DATA_FEED`, `<prefix>_API_BROKER_URL`,
+  /// `<prefix>_API_KEY_ID`, and `<prefix>_API_SECRET_KEY`. That allows
+  /// for keeping multiple sets of credentials in the environment, e.g.,
+  /// `APCA_PAPER_*` and `APCA_LIVE_*` ones. Note that the base URL
+  /// defaults to the one for paper trading, the data feed to IEX, and
+  /// the Broker API base URL to the one of the sandbox if not set.
+  #[allow(clippy::result_large_err)]
+  pub fn from_env_prefixed(prefix: &str) -> Result<Self, Error> {
+    let name = |suffix| format!("{}_{}", prefix, suffix);
 
-    let secret = var_os(ENV_SECRET)
-      .ok_or_else(|| Error::Str(format!("{} environment variable not found", ENV_SECRET).into()))?
-      .into_string()
-      .map_err(|_| {
-        Error::Str(format!("{} environment variable is not a valid string", ENV_SECRET).into())
-      })?;
+    let base_url = url_from_env(&name(ENV_API_URL), API_BASE_URL)?;
+    let stream_url = match string_from_env(&name(ENV_STREAM_URL))? {
+      Some(url) => Url::parse(&url)?,
+      None => stream_url_from_base_url(&base_url)?,
+    };
+    let data_base_url = url_from_env(&name(ENV_DATA_URL), DATA_BASE_URL)?;
+    let data_stream_url = url_from_env(&name(ENV_DATA_STREAM_URL), DATA_STREAM_URL)?;
+    #[cfg(feature = "data")]
+    let data_feed = match string_from_env(&name(ENV_DATA_FEED))? {
+      Some(feed) => feed.parse().map_err(|err| {
+        Error::Str(format!("{} environment variable: {}", name(ENV_DATA_FEED), err).into())
+      })?,
+      None => Feed::default(),
+    };
+    let broker_base_url = url_from_env(&name(ENV_BROKER_URL), BROKER_BASE_URL)?;
+    let key_id = required_from_env(&name(ENV_KEY_ID))?;
+    let secret = required_from_env(&name(ENV_SECRET))?;
 
     Ok(Self {
       base_url,
+      stream_url,
+      data_base_url,
+      data_stream_url,
+      #[cfg(feature = "data")]
+      data_feed,
+      broker_base_url,
       key_id,
       secret,
     })
   }
+
+  /// Use the given base URL for requests to the trading API.
+  ///
+  /// Note that the URL of the stream providing account and trade
+  /// updates is left unchanged. Use `ApiInfo::with_stream_url` for
+  /// changing it as well.
+  pub fn with_base_url(mut self, url: Url) -> Self {
+    self.base_url = url;
+    self
+  }
+
+  /// Use the given URL for connecting to the stream providing account
+  /// and trade updates.
+  pub fn with_stream_url(mut self, url: Url) -> Self {
+    self.stream_url = url;
+    self
+  }
+
+  /// Use the given base URL for requests to the market data API.
+  ///
+  /// This URL is used in place of the default market data API for all
+  /// data endpoints, e.g., for routing them through a gateway.
+  pub fn with_data_base_url(mut self, url: Url) -> Self {
+    self.data_base_url = url;
+    self
+  }
+
+  /// Use the given URL for connecting to the market data stream.
+  pub fn with_data_stream_url(mut self, url: Url) -> Self {
+    self.data_stream_url = url;
+    self
+  }
+
+  /// Use the given market data feed by default.
+  ///
+  /// Access to the SIP feed requires a corresponding subscription.
+  #[cfg(feature = "data")]
+  pub fn with_data_feed(mut self, feed: Feed) -> Self {
+    self.data_feed = feed;
+    self
+  }
+
+  /// Use the given base URL for requests to the Broker API.
+  pub fn with_broker_base_url(mut self, url: Url) -> Self {
+    self.broker_base_url = url;
+    self
+  }
+
+  /// Retrieve the base URL used for requests to the trading API.
+  pub fn base_url(&self) -> &Url {
+    &self.base_url
+  }
+
+  /// Retrieve the URL used for connecting to the stream providing
+  /// account and trade updates.
+  pub fn stream_url(&self) -> &Url {
+    &self.stream_url
+  }
+
+  /// Retrieve the base URL used for requests to the market data API.
+  pub fn data_base_url(&self) -> &Url {
+    &self.data_base_url
+  }
+
+  /// Retrieve the URL used for connecting to the market data stream.
+  pub fn data_stream_url(&self) -> &Url {
+    &self.data_stream_url
+  }
+
+  /// Retrieve the market data feed to use by default.
+  ///
+  /// The feed is used for subscriptions to real time market data not
+  /// specifying a source explicitly as well as for requests for
+  /// historical bars, trades, and quotes not specifying a feed. That
+  /// allows for switching between the free IEX and the paid SIP feed
+  /// without code changes.
+  #[cfg(feature = "data")]
+  pub fn data_feed(&self) -> Feed {
+    self.data_feed
+  }
+
+  /// Retrieve the base URL used for requests to the Broker API.
+  pub fn broker_base_url(&self) -> &Url {
+    &self.broker_base_url
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::env::set_var;
+
+  use test_env_log::test;
+
+  use crate::api::API_STREAM_URL;
+
+
+  /// Check that the secret does not show up in the `Debug`
+  /// representation of an `ApiInfo` object.
+  #[test]
+  fn redact_secret() {
+    let api_info = ApiInfo {
+      base_url: Url::parse(API_BASE_URL).unwrap(),
+      stream_url: Url::parse(API_STREAM_URL).unwrap(),
+      data_base_url: Url::parse(DATA_BASE_URL).unwrap(),
+      data_stream_url: Url::parse(DATA_STREAM_URL).unwrap(),
+      #[cfg(feature = "data")]
+      data_feed: Feed::Iex,
+      broker_base_url: Url::parse(BROKER_BASE_URL).unwrap(),
+      key_id: "USER12345678".to_string(),
+      secret: "justletmein".to_string(),
+    };
+
+    let debug = format!("{:?}", api_info);
+    assert!(debug.contains("USER12345678"), "{}", debug);
+    assert!(debug.contains("<redacted>"), "{}", debug);
+    assert!(!debug.contains("justletmein"), "{}", debug);
+  }
+
+  /// Check that we can read credentials from prefixed environment
+  /// variables.
+  #[test]
+  fn read_prefixed_env() {
+    set_var("APCA_TEST_PROFILE_API_BASE_URL", "https://api.example.com");
+    set_var("APCA_TEST_PROFILE_API_KEY_ID", "key");
+    set_var("APCA_TEST_PROFILE_API_SECRET_KEY", "secret");
+
+    let api_info = ApiInfo::from_env_prefixed("APCA_TEST_PROFILE").unwrap();
+    assert_eq!(api_info.base_url().as_str(), "https://api.example.com/");
+    assert_eq!(
+      api_info.stream_url().as_str(),
+      "wss://api.example.com/stream"
+    );
+    assert_eq!(
+      api_info.data_base_url().as_str(),
+      "https://data.alpaca.markets/"
+    );
+    assert_eq!(
+      api_info.broker_base_url().as_str(),
+      "https://broker-api.sandbox.alpaca.markets/"
+    );
+    assert_eq!(api_info.key_id, "key");
+    assert_eq!(api_info.secret, "secret");
+
+    let err = ApiInfo::from_env_prefixed("APCA_TEST_MISSING").unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      "APCA_TEST_MISSING_API_KEY_ID environment variable not found"
+    );
+  }
+  /// Check that we can configure the market data feed and stream via
+  /// the environment.
+  #[cfg(feature = "data")]
+  #[test]
+  fn read_data_env() {
+    set_var("APCA_TEST_DATA_API_KEY_ID", "key");
+    set_var("APCA_TEST_DATA_API_SECRET_KEY", "secret");
+
+    let api_info = ApiInfo::from_env_prefixed("APCA_TEST_DATA").unwrap();
+    assert_eq!(api_info.data_feed(), Feed::Iex);
+    assert_eq!(
+      api_info.data_stream_url().as_str(),
+      "wss://stream.data.alpaca.markets/"
+    );
+
+    set_var("APCA_TEST_DATA_API_DATA_FEED", "SIP");
+    set_var(
+      "APCA_TEST_DATA_API_DATA_STREAM_URL",
+      "wss://stream.example.com",
+    );
+    let api_info = ApiInfo::from_env_prefixed("APCA_TEST_DATA").unwrap();
+    assert_eq!(api_info.data_feed(), Feed::Sip);
+    assert_eq!(
+      api_info.data_stream_url().as_str(),
+      "wss://stream.example.com/"
+    );
+
+    set_var("APCA_TEST_DATA_API_DATA_FEED", "otc");
+    let err = ApiInfo::from_env_prefixed("APCA_TEST_DATA").unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      "APCA_TEST_DATA_API_DATA_FEED environment variable: invalid data feed: 'otc' (expected 'iex' or 'sip')"
+    );
+  }
 }
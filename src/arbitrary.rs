@@ -0,0 +1,439 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! `proptest::arbitrary::Arbitrary` implementations for the core API
+//! types.
+//!
+//! Generated values are realistic rather than exhaustive: prices are
+//! positive and have at most four decimal places, filled quantities do
+//! not exceed order quantities, and so on.
+
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use num_decimal::Num;
+
+use proptest::arbitrary::any;
+use proptest::arbitrary::Arbitrary;
+#[cfg(feature = "data")]
+use proptest::option;
+#[cfg(feature = "trading")]
+use proptest::sample::select;
+use proptest::strategy::BoxedStrategy;
+use proptest::strategy::Strategy;
+
+#[cfg(feature = "trading")]
+use uuid::Uuid;
+
+#[cfg(feature = "trading")]
+use crate::api::v2::account;
+#[cfg(feature = "trading")]
+use crate::api::v2::asset;
+#[cfg(all(feature = "streaming", feature = "trading"))]
+use crate::api::v2::events;
+#[cfg(feature = "trading")]
+use crate::api::v2::order;
+#[cfg(feature = "trading")]
+use crate::api::v2::position;
+#[cfg(feature = "data")]
+use crate::data::v2::bars::Bar;
+#[cfg(feature = "data")]
+use crate::data::v2::quotes::Quote;
+#[cfg(feature = "data")]
+use crate::data::v2::trades::Trade;
+#[cfg(feature = "trading")]
+use crate::instrument;
+use crate::test_support;
+
+
+/// Implement `Arbitrary` for an enum by selecting one of the given
+/// variants.
+#[cfg(feature = "trading")]
+macro_rules! arbitrary_enum {
+  ($type:ty, [$($variant:expr),* $(,)?]) => {
+    impl Arbitrary for $type {
+      type Parameters = ();
+      type Strategy = BoxedStrategy<Self>;
+
+      fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        select(vec![$($variant),*]).boxed()
+      }
+    }
+  };
+}
+
+
+/// A strategy for positive prices with up to four decimal places.
+fn price() -> impl Strategy<Value = Num> {
+  (1u64..10_000_000, 0u32..=4).prop_map(|(value, scale)| Num::new(value, 10u64.pow(scale)))
+}
+
+/// A strategy for points in time between 2000 and 2040, with
+/// millisecond precision.
+fn time() -> impl Strategy<Value = SystemTime> {
+  (946_684_800_000u64..2_208_988_800_000).prop_map(|ms| UNIX_EPOCH + Duration::from_millis(ms))
+}
+
+/// A strategy for ticker symbols.
+#[cfg(feature = "trading")]
+fn symbol() -> impl Strategy<Value = String> {
+  "[A-Z]{1,5}"
+}
+
+#[cfg(feature = "trading")]
+fn uuid() -> impl Strategy<Value = Uuid> {
+  any::<u128>().prop_map(Uuid::from_u128)
+}
+
+
+#[cfg(feature = "trading")]
+arbitrary_enum!(order::Side, [order::Side::Buy, order::Side::Sell]);
+#[cfg(feature = "trading")]
+arbitrary_enum!(
+  order::Type,
+  [
+    order::Type::Market,
+    order::Type::Limit,
+    order::Type::Stop,
+    order::Type::StopLimit,
+  ]
+);
+#[cfg(feature = "trading")]
+arbitrary_enum!(
+  order::TimeInForce,
+  [
+    order::TimeInForce::Day,
+    order::TimeInForce::UntilCanceled,
+    order::TimeInForce::UntilMarketOpen,
+    order::TimeInForce::UntilMarketClose,
+  ]
+);
+#[cfg(feature = "trading")]
+arbitrary_enum!(
+  order::Status,
+  [
+    order::Status::New,
+    order::Status::Replaced,
+    order::Status::PartiallyFilled,
+    order::Status::Filled,
+    order::Status::DoneForDay,
+    order::Status::Canceled,
+    order::Status::Expired,
+    order::Status::Accepted,
+    order::Status::PendingNew,
+    order::Status::AcceptedForBidding,
+    order::Status::PendingCancel,
+    order::Status::PendingReplace,
+    order::Status::Stopped,
+    order::Status::Rejected,
+    order::Status::Suspended,
+    order::Status::Calculated,
+    order::Status::Held,
+  ]
+);
+#[cfg(feature = "trading")]
+arbitrary_enum!(
+  position::Side,
+  [position::Side::Long, position::Side::Short]
+);
+#[cfg(feature = "trading")]
+arbitrary_enum!(
+  instrument::Exchange,
+  [
+    instrument::Exchange::Amex,
+    instrument::Exchange::Arca,
+    instrument::Exchange::Bats,
+    instrument::Exchange::Nasdaq,
+    instrument::Exchange::Nyse,
+    instrument::Exchange::Nysearca,
+    instrument::Exchange::Otc,
+    instrument::Exchange::Crypto,
+  ]
+);
+#[cfg(all(feature = "streaming", feature = "trading"))]
+arbitrary_enum!(
+  events::TradeStatus,
+  [
+    events::TradeStatus::New,
+    events::TradeStatus::Replaced,
+    events::TradeStatus::ReplaceRejected,
+    events::TradeStatus::PartialFill,
+    events::TradeStatus::Filled,
+    events::TradeStatus::DoneForDay,
+    events::TradeStatus::Canceled,
+    events::TradeStatus::CancelRejected,
+    events::TradeStatus::Expired,
+    events::TradeStatus::PendingCancel,
+    events::TradeStatus::Stopped,
+    events::TradeStatus::Rejected,
+    events::TradeStatus::Suspended,
+    events::TradeStatus::PendingNew,
+    events::TradeStatus::PendingReplace,
+    events::TradeStatus::Calculated,
+    events::TradeStatus::Accepted,
+    events::TradeStatus::Held,
+  ]
+);
+
+
+#[cfg(feature = "trading")]
+impl Arbitrary for order::Id {
+  type Parameters = ();
+  type Strategy = BoxedStrategy<Self>;
+
+  fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+    uuid().prop_map(order::Id).boxed()
+  }
+}
+
+#[cfg(feature = "trading")]
+impl Arbitrary for asset::Id {
+  type Parameters = ();
+  type Strategy = BoxedStrategy<Self>;
+
+  fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+    uuid().prop_map(asset::Id).boxed()
+  }
+}
+
+#[cfg(feature = "trading")]
+impl Arbitrary for account::Id {
+  type Parameters = ();
+  type Strategy = BoxedStrategy<Self>;
+
+  fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+    uuid().prop_map(account::Id).boxed()
+  }
+}
+
+
+#[cfg(feature = "trading")]
+impl Arbitrary for account::Account {
+  type Parameters = ();
+  type Strategy = BoxedStrategy<Self>;
+
+  fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+    (any::<account::Id>(), price(), any::<bool>(), 0u64..10)
+      .prop_map(|(id, cash, day_trader, daytrade_count)| account::Account {
+        id,
+        day_trader,
+        daytrade_count,
+        ..test_support::account(cash)
+      })
+      .boxed()
+  }
+}
+
+
+#[cfg(feature = "trading")]
+impl Arbitrary for order::Order {
+  type Parameters = ();
+  type Strategy = BoxedStrategy<Self>;
+
+  fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+    let basics = (
+      any::<order::Id>(),
+      symbol(),
+      any::<order::Side>(),
+      1u64..100_000,
+      0u64..=100,
+      any::<order::Status>(),
+    );
+    let details = (
+      any::<order::Type>(),
+      any::<order::TimeInForce>(),
+      price(),
+      price(),
+      price(),
+      time(),
+      any::<bool>(),
+    );
+
+    (basics, details)
+      .prop_map(|(basics, details)| {
+        let (id, symbol, side, quantity, percent_filled, status) = basics;
+        let (type_, time_in_force, limit, stop, fill, created_at, extended_hours) = details;
+        let filled_quantity = quantity * percent_filled / 100;
+        let limit_price = match type_ {
+          order::Type::Limit | order::Type::StopLimit => Some(limit),
+          order::Type::Market | order::Type::Stop => None,
+        };
+        let stop_price = match type_ {
+          order::Type::Stop | order::Type::StopLimit => Some(stop),
+          order::Type::Market | order::Type::Limit => None,
+        };
+
+        order::Order {
+          id,
+          client_order_id: id.0.to_string(),
+          status,
+          created_at,
+          type_,
+          time_in_force,
+          limit_price,
+          stop_price,
+          filled_quantity,
+          average_fill_price: if filled_quantity > 0 {
+            Some(fill)
+          } else {
+            None
+          },
+          extended_hours,
+          ..test_support::order(&symbol, side, quantity)
+        }
+      })
+      .boxed()
+  }
+}
+
+
+#[cfg(feature = "trading")]
+impl Arbitrary for position::Position {
+  type Parameters = ();
+  type Strategy = BoxedStrategy<Self>;
+
+  fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+    (
+      symbol(),
+      any::<position::Side>(),
+      1u64..100_000,
+      price(),
+      any::<instrument::Exchange>(),
+    )
+      .prop_map(
+        |(symbol, side, quantity, price, exchange)| position::Position {
+          exchange,
+          ..test_support::position(&symbol, side, quantity, price)
+        },
+      )
+      .boxed()
+  }
+}
+
+
+#[cfg(all(feature = "streaming", feature = "trading"))]
+impl Arbitrary for events::TradeUpdate {
+  type Parameters = ();
+  type Strategy = BoxedStrategy<Self>;
+
+  fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+    (any::<events::TradeStatus>(), any::<order::Order>(), time())
+      .prop_map(|(event, order, timestamp)| {
+        let execution = match event {
+          events::TradeStatus::Filled | events::TradeStatus::PartialFill => order
+            .average_fill_price
+            .clone()
+            .map(|price| (price, Num::from(order.filled_quantity))),
+          _ => None,
+        };
+
+        let mut update = test_support::trade_update(event, order);
+        update.timestamp = Some(timestamp);
+        if let Some((price, quantity)) = execution {
+          update.execution_id = Some(update.order.id.0.to_string());
+          update.price = Some(price);
+          update.quantity = Some(quantity);
+        }
+        update
+      })
+      .boxed()
+  }
+}
+
+
+#[cfg(feature = "data")]
+impl Arbitrary for Bar {
+  type Parameters = ();
+  type Strategy = BoxedStrategy<Self>;
+
+  fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+    (
+      time(),
+      [price(), price(), price(), price()],
+      0u64..10_000_000,
+      option::of(0u64..100_000),
+    )
+      .prop_map(|(time, mut prices, volume, trade_count)| {
+        prices.sort();
+        let [low, open, close, high] = prices;
+        Bar {
+          trade_count,
+          ..test_support::bar(time, open, high, low, close, volume)
+        }
+      })
+      .boxed()
+  }
+}
+
+
+#[cfg(feature = "data")]
+impl Arbitrary for Quote {
+  type Parameters = ();
+  type Strategy = BoxedStrategy<Self>;
+
+  fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+    (time(), price(), 0u64..10_000, 0u64..10_000, 0u64..10_000)
+      .prop_map(|(time, bid_price, spread, bid_size, ask_size)| {
+        let ask_price = &bid_price + Num::new(spread, 10_000);
+        Quote {
+          bid_size,
+          ask_size,
+          ..test_support::quote(time, bid_price, ask_price)
+        }
+      })
+      .boxed()
+  }
+}
+
+
+#[cfg(feature = "data")]
+impl Arbitrary for Trade {
+  type Parameters = ();
+  type Strategy = BoxedStrategy<Self>;
+
+  fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+    (time(), price(), 1u64..100_000, any::<u64>())
+      .prop_map(|(time, price, size, id)| Trade {
+        id,
+        ..test_support::trade(time, price, size)
+      })
+      .boxed()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use proptest::proptest;
+
+
+  proptest! {
+    #[cfg(feature = "trading")]
+    #[test]
+    fn orders_are_consistent(order in any::<order::Order>()) {
+      assert!(order.filled_quantity <= order.quantity);
+      assert_eq!(order.average_fill_price.is_some(), order.filled_quantity > 0);
+      assert_eq!(
+        order.limit_price.is_some(),
+        matches!(order.type_, order::Type::Limit | order::Type::StopLimit)
+      );
+    }
+
+    #[cfg(feature = "data")]
+    #[test]
+    fn bars_are_consistent(bar in any::<Bar>()) {
+      assert!(bar.low <= bar.open && bar.open <= bar.high);
+      assert!(bar.low <= bar.close && bar.close <= bar.high);
+    }
+
+    #[cfg(feature = "data")]
+    #[test]
+    fn quotes_are_not_crossed(quote in any::<Quote>()) {
+      assert!(quote.bid_price.is_positive());
+      assert!(quote.bid_price <= quote.ask_price);
+    }
+  }
+}
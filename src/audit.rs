@@ -0,0 +1,200 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::fs::OpenOptions;
+use std::io::LineWriter;
+use std::io::Result as IoResult;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use http::Method;
+use http::StatusCode;
+
+use serde_json::from_slice;
+use serde_json::json;
+use serde_json::to_writer;
+use serde_json::Value;
+
+use tracing::warn;
+
+use crate::util::system_time_to_rfc3339_string;
+
+
+/// Convert a message body into a JSON value.
+///
+/// Bodies that are valid JSON are embedded as is, anything else is
+/// stored as a (lossily converted) string. Empty bodies map to `null`.
+fn body_to_json(body: &[u8]) -> Value {
+  if body.is_empty() {
+    Value::Null
+  } else {
+    from_slice::<Value>(body)
+      .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(body).into_owned()))
+  }
+}
+
+
+/// A sink persisting requests, responses, and stream events as
+/// newline-delimited JSON.
+///
+/// Each entry is written as a single line containing a JSON object
+/// with the time it was recorded at (`time`), its kind (`request`,
+/// `response`, or `event`), and the data belonging to it. Request
+/// entries contain the method, URL, and body of the request issued;
+/// authentication information is never recorded. Response entries
+/// contain the method and URL of the corresponding request as well as
+/// the status and body of the response. Event entries contain the
+/// data received over a stream.
+///
+/// An `AuditLog` is attached to a `Client` using
+/// `Builder::audit_log`. Failures to write entries are reported as
+/// warnings but do not otherwise affect the operation of the client.
+pub struct AuditLog {
+  /// The writer entries are written to.
+  writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl AuditLog {
+  /// Create a new `AuditLog` writing entries to the given writer.
+  pub fn new<W>(writer: W) -> Self
+  where
+    W: Write + Send + 'static,
+  {
+    Self {
+      writer: Mutex::new(Box::new(writer)),
+    }
+  }
+
+  /// Create a new `AuditLog` appending entries to the file at the
+  /// given path, creating it if it does not exist.
+  ///
+  /// Each entry is flushed to the file as soon as it was written.
+  pub fn open<P>(path: P) -> IoResult<Self>
+  where
+    P: AsRef<Path>,
+  {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    Ok(Self::new(LineWriter::new(file)))
+  }
+
+  /// Write an entry of the given kind.
+  fn record(&self, kind: &str, mut entry: Value) {
+    let time = system_time_to_rfc3339_string(&SystemTime::now());
+    // `entry` is always an object created by one of our callers.
+    let object = entry.as_object_mut().unwrap();
+    let _ = object.insert("time".to_string(), Value::String(time));
+    let _ = object.insert("kind".to_string(), Value::String(kind.to_string()));
+
+    let mut writer = self.writer.lock().unwrap();
+    let result = to_writer(&mut *writer, &entry)
+      .map_err(Into::into)
+      .and_then(|()| writer.write_all(b"\n"));
+    if let Err(err) = result {
+      warn!(err = display(&err), "failed to write audit log entry");
+    }
+  }
+
+  /// Record a request being issued.
+  pub(crate) fn record_request(&self, method: &Method, url: &str, body: &[u8]) {
+    let entry = json!({
+      "method": method.as_str(),
+      "url": url,
+      "body": body_to_json(body),
+    });
+    self.record("request", entry)
+  }
+
+  /// Record a response received.
+  pub(crate) fn record_response(
+    &self,
+    method: &Method,
+    url: &str,
+    status: StatusCode,
+    body: &[u8],
+  ) {
+    let entry = json!({
+      "method": method.as_str(),
+      "url": url,
+      "status": status.as_u16(),
+      "body": body_to_json(body),
+    });
+    self.record("response", entry)
+  }
+
+  /// Record an event received over a stream.
+  #[cfg(feature = "streaming")]
+  pub(crate) fn record_event(&self, data: &[u8]) {
+    let entry = json!({
+      "body": body_to_json(data),
+    });
+    self.record("event", entry)
+  }
+
+  /// Flush all buffered entries to the underlying writer.
+  pub fn flush(&self) -> IoResult<()> {
+    self.writer.lock().unwrap().flush()
+  }
+}
+
+impl Debug for AuditLog {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    fmt.debug_struct("AuditLog").finish()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::sync::Arc;
+
+  use test_env_log::test;
+
+
+  /// A writer that can be inspected while being owned by an
+  /// `AuditLog`.
+  #[derive(Clone, Default)]
+  struct Shared(Arc<Mutex<Vec<u8>>>);
+
+  impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+      self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+      Ok(())
+    }
+  }
+
+  #[test]
+  fn write_entries() {
+    let buffer = Shared::default();
+    let log = AuditLog::new(buffer.clone());
+    let url = "https://paper-api.alpaca.markets/v2/orders";
+
+    log.record_request(&Method::POST, url, br#"{"symbol":"AAPL"}"#);
+    log.record_response(&Method::POST, url, StatusCode::FORBIDDEN, b"not json");
+
+    let data = buffer.0.lock().unwrap().clone();
+    let lines = String::from_utf8(data).unwrap();
+    let entries = lines
+      .lines()
+      .map(|line| serde_json::from_str::<Value>(line).unwrap())
+      .collect::<Vec<_>>();
+
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["kind"], "request");
+    assert_eq!(entries[0]["method"], "POST");
+    assert_eq!(entries[0]["body"]["symbol"], "AAPL");
+    assert!(entries[0]["time"].is_string());
+    assert_eq!(entries[1]["kind"], "response");
+    assert_eq!(entries[1]["status"], 403);
+    assert_eq!(entries[1]["body"], "not json");
+  }
+}
@@ -0,0 +1,238 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use futures::stream::unfold;
+use futures::stream::Stream;
+use futures::StreamExt;
+
+use tokio::sync::broadcast::channel;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::broadcast::Sender;
+
+
+/// An error indicating that a subscriber fell behind and missed some
+/// items.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Lagged(pub u64);
+
+impl Display for Lagged {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    write!(fmt, "subscriber lagged behind by {} items", self.0)
+  }
+}
+
+impl std::error::Error for Lagged {}
+
+
+/// An adapter fanning out the items of a single stream to an arbitrary
+/// number of subscribers.
+///
+/// Alpaca limits the number of concurrent websocket connections per
+/// account. A `Broadcast` allows for using a single connection, e.g.,
+/// one subscribed to trade updates, from multiple tasks. Each item is
+/// wrapped in an `Arc` and handed to all subscribers, which means that
+/// items do not have to be `Clone`.
+///
+/// Items are buffered up to a configurable capacity per subscriber. A
+/// subscriber not keeping up with the rest will miss the oldest items,
+/// which is reported by means of a `Lagged` error.
+///
+/// Once the driver finished (or got dropped), all subscriber streams
+/// end, irrespective of whether `Broadcast` handles are still around.
+/// Subscriptions made after that point end right away.
+///
+/// ```no_run
+/// # use apca::api::v2::events;
+/// # use apca::broadcast::Broadcast;
+/// # use apca::ApiInfo;
+/// # use apca::Client;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = Client::new(ApiInfo::from_env()?);
+/// let stream = client.subscribe::<events::TradeUpdates>().await?;
+/// let (broadcast, driver) = Broadcast::new(stream, 1024);
+/// let updates1 = broadcast.subscribe();
+/// let updates2 = broadcast.subscribe();
+/// // The driver needs to be polled for items to be forwarded.
+/// tokio::spawn(driver);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Broadcast<T> {
+  /// The sender used for subscribing, or `None` once the driver
+  /// finished.
+  sender: Arc<Mutex<Option<Sender<Arc<T>>>>>,
+}
+
+
+/// A guard closing a `Broadcast` when dropped.
+struct Closer<T>(Arc<Mutex<Option<Sender<Arc<T>>>>>);
+
+impl<T> Drop for Closer<T> {
+  fn drop(&mut self) {
+    // Dropping the shared sender leaves the driver's as the last one,
+    // causing all subscriber streams to end once it is gone as well.
+    let _ = self.0.lock().unwrap().take();
+  }
+}
+
+impl<T> Broadcast<T>
+where
+  T: Send + Sync + 'static,
+{
+  /// Create a new `Broadcast` for the given stream, buffering at most
+  /// `capacity` items per subscriber.
+  ///
+  /// The returned future forwards the items of the stream to all
+  /// subscribers and has to be polled (e.g., by spawning it as a task)
+  /// for anything to happen. It resolves once the stream is exhausted,
+  /// at which point all subscriber streams end as well. The same holds
+  /// if the future is dropped early.
+  ///
+  /// # Panics
+  /// The method panics if `capacity` is zero.
+  pub fn new<S>(stream: S, capacity: usize) -> (Self, impl Future<Output = ()>)
+  where
+    S: Stream<Item = T>,
+  {
+    let (sender, _) = channel(capacity);
+    let forwarder = sender.clone();
+    let sender = Arc::new(Mutex::new(Some(sender)));
+    let closer = Closer(Arc::clone(&sender));
+    let driver = async move {
+      let _closer = closer;
+      stream
+        .for_each(|item| {
+          // Sending only fails if there are no subscribers, in which
+          // case the item is simply dropped.
+          let _ = forwarder.send(Arc::new(item));
+          async {}
+        })
+        .await
+    };
+
+    (Self { sender }, driver)
+  }
+
+  /// Subscribe to the items of the underlying stream.
+  ///
+  /// Only items reported after the subscription was made are
+  /// received.
+  pub fn subscribe(&self) -> impl Stream<Item = Result<Arc<T>, Lagged>> {
+    let receiver = self.sender.lock().unwrap().as_ref().map(Sender::subscribe);
+    unfold(receiver, |receiver: Option<Receiver<Arc<T>>>| async move {
+      let mut receiver = receiver?;
+      match receiver.recv().await {
+        Ok(item) => Some((Ok(item), Some(receiver))),
+        Err(RecvError::Lagged(count)) => Some((Err(Lagged(count)), Some(receiver))),
+        Err(RecvError::Closed) => None,
+      }
+    })
+  }
+
+  /// Retrieve the number of active subscribers.
+  pub fn subscriber_count(&self) -> usize {
+    self
+      .sender
+      .lock()
+      .unwrap()
+      .as_ref()
+      .map(Sender::receiver_count)
+      .unwrap_or(0)
+  }
+}
+
+impl<T> Clone for Broadcast<T> {
+  fn clone(&self) -> Self {
+    Self {
+      sender: Arc::clone(&self.sender),
+    }
+  }
+}
+
+impl<T> Debug for Broadcast<T> {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    fmt
+      .debug_struct("Broadcast")
+      .field(
+        "subscribers",
+        &self
+          .sender
+          .lock()
+          .unwrap()
+          .as_ref()
+          .map(Sender::receiver_count)
+          .unwrap_or(0),
+      )
+      .finish()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use futures::stream::iter;
+  use futures::TryStreamExt;
+
+  use test_env_log::test;
+
+
+  /// Check that all subscribers receive all items.
+  #[test(tokio::test)]
+  async fn fan_out() {
+    let (broadcast, driver) = Broadcast::new(iter(vec![1, 2, 3]), 8);
+    let sub1 = broadcast.subscribe();
+    let sub2 = broadcast.clone().subscribe();
+    assert_eq!(broadcast.subscriber_count(), 2);
+
+    // Streams end once the driver did, even though the `Broadcast`
+    // handle is still alive.
+    driver.await;
+
+    let items1 = sub1.map_ok(|item| *item).try_collect::<Vec<_>>().await;
+    let items2 = sub2.map_ok(|item| *item).try_collect::<Vec<_>>().await;
+    assert_eq!(items1, Ok(vec![1, 2, 3]));
+    assert_eq!(items2, Ok(vec![1, 2, 3]));
+    assert_eq!(broadcast.subscriber_count(), 0);
+
+    // Subscriptions made after the fact end right away.
+    let items = broadcast.subscribe().collect::<Vec<_>>().await;
+    assert_eq!(items, Vec::new());
+  }
+
+  /// Check that subscriber streams end when the driver is dropped.
+  #[test(tokio::test)]
+  async fn drop_driver() {
+    let (broadcast, driver) = Broadcast::new(iter(vec![1, 2, 3]), 8);
+    let sub = broadcast.subscribe();
+    drop(driver);
+
+    let items = sub.collect::<Vec<_>>().await;
+    assert_eq!(items, Vec::new());
+  }
+
+  /// Check that a lagging subscriber is informed about missed items.
+  #[test(tokio::test)]
+  async fn lagging_subscriber() {
+    let (broadcast, driver) = Broadcast::new(iter(vec![1, 2, 3, 4]), 2);
+    let sub = broadcast.subscribe();
+    driver.await;
+
+    let items = sub.collect::<Vec<_>>().await;
+    assert_eq!(items.len(), 3);
+    assert_eq!(items[0], Err(Lagged(2)));
+    assert_eq!(items[1].as_deref(), Ok(&3));
+    assert_eq!(items[2].as_deref(), Ok(&4));
+  }
+}
@@ -0,0 +1,5 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+/// Definitions for the first version of the Broker API.
+pub mod v1;
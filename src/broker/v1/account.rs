@@ -0,0 +1,13 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use serde::Deserialize;
+use serde::Serialize;
+
+
+Id! {
+  /// An ID uniquely identifying an account managed through the Broker
+  /// API.
+  #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+  pub struct Id(pub Uuid);
+}
@@ -0,0 +1,156 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use serde_urlencoded::to_string as to_query;
+
+use crate::api::v2::account_activities::Activity;
+use crate::api::v2::account_activities::ActivityReq;
+use crate::broker::v1::account;
+use crate::paged::Paged;
+use crate::Str;
+
+
+Endpoint! {
+  /// The representation of a GET request to the /v1/accounts/activities
+  /// endpoint, retrieving the activities of a single account.
+  pub Get((account::Id, ActivityReq)),
+  Ok => Vec<Activity>, [
+    /// The activities were retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// Some data in the request was invalid.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+  ]
+  Api => Broker,
+
+  fn path(_input: &Self::Input) -> Str {
+    "/v1/accounts/activities".into()
+  }
+
+  fn query(input: &Self::Input) -> Option<Str> {
+    let (id, request) = input;
+    // TODO: Realistically there should be no way for these unwraps to
+    //       ever panic because our conversion to strings should not be
+    //       fallible. But still, ideally we would not have to unwrap.
+    let mut query = to_query([("account_id", id.to_string())]).unwrap();
+    let request = to_query(request).unwrap();
+    if !request.is_empty() {
+      query.push('&');
+      query.push_str(&request);
+    }
+    Some(query.into())
+  }
+}
+
+impl Paged for Get {
+  fn next_page(input: &Self::Input, output: &Self::Output) -> Option<Self::Input> {
+    let (id, request) = input;
+    let last = output.last()?;
+    // A page that is not full is the last one and issuing another
+    // request would just yield an empty result.
+    if let Some(page_size) = request.page_size {
+      if output.len() < page_size {
+        return None
+      }
+    }
+
+    let mut request = request.clone();
+    request.page_token = Some(last.id().to_string());
+    Some((*id, request))
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::str::FromStr as _;
+
+  use http_endpoint::Endpoint;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+  use crate::api::v2::account_activities::ActivityType;
+
+
+  /// Check that we emit the expected query for retrieving the
+  /// activities of an account.
+  #[test]
+  fn emit_query() {
+    let id = account::Id::from_str("bf2b0f93-f296-4276-a9cf-288586cf4fb7").unwrap();
+    let input = (id, ActivityReq::default());
+    assert_eq!(
+      Get::query(&input).unwrap(),
+      "account_id=bf2b0f93-f296-4276-a9cf-288586cf4fb7"
+    );
+
+    let request = ActivityReq {
+      types: Some(vec![ActivityType::Fill, ActivityType::Dividend]),
+      page_size: Some(2),
+      ..Default::default()
+    };
+    let input = (id, request);
+    assert_eq!(
+      Get::query(&input).unwrap(),
+      "account_id=bf2b0f93-f296-4276-a9cf-288586cf4fb7&activity_types=FILL%2CDIV&page_size=2"
+    );
+  }
+
+  /// Check that we can parse account activities and determine the
+  /// next page to retrieve.
+  #[test]
+  fn parse_activities() {
+    let response = r#"[
+  {
+    "id": "20220808000000000::8f3e5a71-b71e-4b03-a5c7-6d2afc5a56a1",
+    "account_id": "bf2b0f93-f296-4276-a9cf-288586cf4fb7",
+    "activity_type": "FILL",
+    "transaction_time": "2022-08-08T14:30:00.000Z",
+    "type": "fill",
+    "price": "167.51",
+    "qty": "2",
+    "side": "buy",
+    "symbol": "AAPL",
+    "leaves_qty": "0",
+    "order_id": "904837e3-3b76-47ec-b432-046db621571b",
+    "cum_qty": "2",
+    "order_status": "filled"
+  },
+  {
+    "id": "20220808000000000::b5d2a8e4-0a8c-4a7e-a8f1-0e9e4ea7b5b0",
+    "account_id": "bf2b0f93-f296-4276-a9cf-288586cf4fb7",
+    "activity_type": "DIV",
+    "date": "2022-08-08",
+    "net_amount": "1.02",
+    "description": "Cash DIV @ 0.23",
+    "symbol": "AAPL",
+    "qty": "4.43",
+    "per_share_amount": "0.23"
+  }
+]"#;
+
+    let activities = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    assert_eq!(activities.len(), 2);
+    assert!(activities[0].clone().into_trade().is_ok());
+    assert!(activities[1].clone().into_non_trade().is_ok());
+
+    let id = account::Id::from_str("bf2b0f93-f296-4276-a9cf-288586cf4fb7").unwrap();
+    let request = ActivityReq {
+      page_size: Some(2),
+      ..Default::default()
+    };
+    let (next_id, next) = Get::next_page(&(id, request.clone()), &activities).unwrap();
+    assert_eq!(next_id, id);
+    assert_eq!(next.page_token.as_deref(), Some(activities[1].id()));
+
+    let request = ActivityReq {
+      page_size: Some(3),
+      ..Default::default()
+    };
+    assert_eq!(Get::next_page(&(id, request), &activities), None);
+  }
+}
@@ -0,0 +1,579 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::SystemTime;
+
+use http::Method;
+use http_endpoint::Bytes;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Error as JsonError;
+use serde_json::to_vec as to_json;
+
+use time_util::optional_system_time_from_str;
+use time_util::optional_system_time_to_rfc3339;
+use time_util::system_time_from_str;
+
+use crate::broker::v1::account;
+use crate::util::optional_system_time_from_date_str;
+use crate::util::optional_system_time_to_date_str;
+use crate::Str;
+
+
+/// The outcome of a check performed as part of the customer
+/// identification program.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum CheckResult {
+  /// The check passed.
+  #[serde(rename = "clear")]
+  Clear,
+  /// The check flagged the applicant for review.
+  #[serde(rename = "consider")]
+  Consider,
+  /// Any other result that we have not accounted for.
+  ///
+  /// Note that having any such result should be considered a bug.
+  #[serde(other)]
+  Unknown,
+}
+
+
+/// The status of a check performed as part of the customer
+/// identification program.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum CheckStatus {
+  /// The check has completed.
+  #[serde(rename = "complete")]
+  Complete,
+  /// The check was withdrawn.
+  #[serde(rename = "withdrawn")]
+  Withdrawn,
+  /// Any other status that we have not accounted for.
+  ///
+  /// Note that having any such status should be considered a bug.
+  #[serde(other)]
+  Unknown,
+}
+
+
+/// The decision reached on a KYC check.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum ApprovalStatus {
+  /// No decision has been reached yet.
+  #[serde(rename = "pending")]
+  Pending,
+  /// The applicant was approved.
+  #[serde(rename = "approved")]
+  Approved,
+  /// The applicant was rejected.
+  #[serde(rename = "rejected")]
+  Rejected,
+  /// Any other status that we have not accounted for.
+  ///
+  /// Note that having any such status should be considered a bug.
+  #[serde(other)]
+  Unknown,
+}
+
+
+/// The results of a KYC (know your customer) check.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Kyc {
+  /// The KYC vendor's ID for the check.
+  #[serde(rename = "id")]
+  pub id: String,
+  /// The overall risk score assigned to the applicant.
+  #[serde(
+    rename = "risk_score",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub risk_score: Option<u64>,
+  /// The overall risk level assigned to the applicant.
+  #[serde(
+    rename = "risk_level",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub risk_level: Option<String>,
+  /// The risk categories the applicant was found to fall into.
+  #[serde(
+    rename = "risk_categories",
+    default,
+    skip_serializing_if = "Vec::is_empty"
+  )]
+  pub risk_categories: Vec<String>,
+  /// The applicant's full name.
+  #[serde(
+    rename = "applicant_name",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub applicant_name: Option<String>,
+  /// The applicant's email address.
+  #[serde(
+    rename = "email_address",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub email_address: Option<String>,
+  /// The applicant's nationality.
+  #[serde(
+    rename = "nationality",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub nationality: Option<String>,
+  /// The applicant's date of birth.
+  #[serde(
+    rename = "date_of_birth",
+    default,
+    deserialize_with = "optional_system_time_from_date_str",
+    serialize_with = "optional_system_time_to_date_str",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub date_of_birth: Option<SystemTime>,
+  /// The applicant's address.
+  #[serde(rename = "address", default, skip_serializing_if = "Option::is_none")]
+  pub address: Option<String>,
+  /// The applicant's postal code.
+  #[serde(
+    rename = "postal_code",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub postal_code: Option<String>,
+  /// The applicant's country of residency.
+  #[serde(
+    rename = "country_of_residency",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub country_of_residency: Option<String>,
+  /// The IP address the applicant used when being checked.
+  #[serde(
+    rename = "ip_address",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub ip_address: Option<String>,
+  /// The time the check was initiated at.
+  #[serde(
+    rename = "check_initiated_at",
+    default,
+    deserialize_with = "optional_system_time_from_str",
+    serialize_with = "optional_system_time_to_rfc3339",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub check_initiated_at: Option<SystemTime>,
+  /// The time the check was completed at.
+  #[serde(
+    rename = "check_completed_at",
+    default,
+    deserialize_with = "optional_system_time_from_str",
+    serialize_with = "optional_system_time_to_rfc3339",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub check_completed_at: Option<SystemTime>,
+  /// The decision reached on the check.
+  #[serde(
+    rename = "approval_status",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub approval_status: Option<ApprovalStatus>,
+  /// The person who reached the decision.
+  #[serde(
+    rename = "approved_by",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub approved_by: Option<String>,
+  /// The reason for the decision.
+  #[serde(
+    rename = "approved_reason",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub approved_reason: Option<String>,
+  /// The time the decision was reached at.
+  #[serde(
+    rename = "approved_at",
+    default,
+    deserialize_with = "optional_system_time_from_str",
+    serialize_with = "optional_system_time_to_rfc3339",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub approved_at: Option<SystemTime>,
+}
+
+
+/// The results of a check of an identity document.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Document {
+  /// The KYC vendor's ID for the check.
+  #[serde(rename = "id")]
+  pub id: String,
+  /// The outcome of the check.
+  #[serde(rename = "result", default, skip_serializing_if = "Option::is_none")]
+  pub result: Option<CheckResult>,
+  /// The status of the check.
+  #[serde(rename = "status", default, skip_serializing_if = "Option::is_none")]
+  pub status: Option<CheckStatus>,
+  /// The type of document checked, e.g., a passport.
+  #[serde(
+    rename = "document_type",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub document_type: Option<String>,
+  /// The number of the document.
+  #[serde(
+    rename = "document_number",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub document_number: Option<String>,
+  /// The first name as stated on the document.
+  #[serde(
+    rename = "first_name",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub first_name: Option<String>,
+  /// The last name as stated on the document.
+  #[serde(rename = "last_name", default, skip_serializing_if = "Option::is_none")]
+  pub last_name: Option<String>,
+  /// The date of birth as stated on the document.
+  #[serde(
+    rename = "date_of_birth",
+    default,
+    deserialize_with = "optional_system_time_from_date_str",
+    serialize_with = "optional_system_time_to_date_str",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub date_of_birth: Option<SystemTime>,
+  /// The date the document expires.
+  #[serde(
+    rename = "date_of_expiry",
+    default,
+    deserialize_with = "optional_system_time_from_date_str",
+    serialize_with = "optional_system_time_to_date_str",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub date_of_expiry: Option<SystemTime>,
+  /// The country that issued the document.
+  #[serde(
+    rename = "issuing_country",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub issuing_country: Option<String>,
+}
+
+
+/// The results of a photo or identity check.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Check {
+  /// The KYC vendor's ID for the check.
+  #[serde(rename = "id")]
+  pub id: String,
+  /// The outcome of the check.
+  #[serde(rename = "result", default, skip_serializing_if = "Option::is_none")]
+  pub result: Option<CheckResult>,
+  /// The status of the check.
+  #[serde(rename = "status", default, skip_serializing_if = "Option::is_none")]
+  pub status: Option<CheckStatus>,
+  /// The time the check was performed at.
+  #[serde(
+    rename = "created_at",
+    default,
+    deserialize_with = "optional_system_time_from_str",
+    serialize_with = "optional_system_time_to_rfc3339",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub created_at: Option<SystemTime>,
+}
+
+
+/// The results of a screening against watchlists, e.g., sanction
+/// lists.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Watchlist {
+  /// The KYC vendor's ID for the check.
+  #[serde(rename = "id")]
+  pub id: String,
+  /// The outcome of the check.
+  #[serde(rename = "result", default, skip_serializing_if = "Option::is_none")]
+  pub result: Option<CheckResult>,
+  /// The status of the check.
+  #[serde(rename = "status", default, skip_serializing_if = "Option::is_none")]
+  pub status: Option<CheckStatus>,
+  /// The outcome of the check for politically exposed persons.
+  #[serde(
+    rename = "politically_exposed_person",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub politically_exposed_person: Option<CheckResult>,
+  /// The outcome of the check against sanction lists.
+  #[serde(rename = "sanction", default, skip_serializing_if = "Option::is_none")]
+  pub sanction: Option<CheckResult>,
+  /// The outcome of the check for adverse media coverage.
+  #[serde(
+    rename = "adverse_media",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub adverse_media: Option<CheckResult>,
+  /// The outcome of the check against monitored lists.
+  #[serde(
+    rename = "monitored_lists",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub monitored_lists: Option<CheckResult>,
+  /// The time the check was performed at.
+  #[serde(
+    rename = "created_at",
+    default,
+    deserialize_with = "optional_system_time_from_str",
+    serialize_with = "optional_system_time_to_rfc3339",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub created_at: Option<SystemTime>,
+}
+
+
+/// A POST request to be made to the /v1/accounts/<account-id>/cip
+/// endpoint, submitting the results of the customer identification
+/// program performed by the broker for an account.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct CipReq {
+  /// The names of the KYC vendors used.
+  #[serde(rename = "provider_name")]
+  pub provider_name: Vec<String>,
+  /// The results of the KYC check.
+  #[serde(rename = "kyc", skip_serializing_if = "Option::is_none")]
+  pub kyc: Option<Kyc>,
+  /// The results of the document check.
+  #[serde(rename = "document", skip_serializing_if = "Option::is_none")]
+  pub document: Option<Document>,
+  /// The results of the photo check.
+  #[serde(rename = "photo", skip_serializing_if = "Option::is_none")]
+  pub photo: Option<Check>,
+  /// The results of the identity check.
+  #[serde(rename = "identity", skip_serializing_if = "Option::is_none")]
+  pub identity: Option<Check>,
+  /// The results of the watchlist screening.
+  #[serde(rename = "watchlist", skip_serializing_if = "Option::is_none")]
+  pub watchlist: Option<Watchlist>,
+}
+
+
+/// The customer identification program results of an account.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Cip {
+  /// The ID of the account the results belong to.
+  #[serde(rename = "account_id")]
+  pub account_id: account::Id,
+  /// The names of the KYC vendors used.
+  #[serde(rename = "provider_name", default)]
+  pub provider_name: Vec<String>,
+  /// The results of the KYC check.
+  #[serde(rename = "kyc", default)]
+  pub kyc: Option<Kyc>,
+  /// The results of the document check.
+  #[serde(rename = "document", default)]
+  pub document: Option<Document>,
+  /// The results of the photo check.
+  #[serde(rename = "photo", default)]
+  pub photo: Option<Check>,
+  /// The results of the identity check.
+  #[serde(rename = "identity", default)]
+  pub identity: Option<Check>,
+  /// The results of the watchlist screening.
+  #[serde(rename = "watchlist", default)]
+  pub watchlist: Option<Watchlist>,
+  /// The time the results were submitted at.
+  #[serde(rename = "created_at", deserialize_with = "system_time_from_str")]
+  pub created_at: SystemTime,
+  /// The time the results were last updated at.
+  #[serde(rename = "updated_at", deserialize_with = "system_time_from_str")]
+  pub updated_at: SystemTime,
+}
+
+
+Endpoint! {
+  /// The representation of a POST request to the
+  /// /v1/accounts/<account-id>/cip endpoint.
+  pub Post((account::Id, CipReq)),
+  Ok => Cip, [
+    /// The results were submitted successfully.
+    /* 200 */ OK,
+  ],
+  Err => PostError, [
+    /// Some data in the request was invalid.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+    /// No account was found with the given ID.
+    /* 404 */ NOT_FOUND => NotFound,
+  ]
+  Api => Broker,
+
+  fn method() -> Method {
+    Method::POST
+  }
+
+  fn path(input: &Self::Input) -> Str {
+    let (id, _) = input;
+    format!("/v1/accounts/{}/cip", id.to_hyphenated_ref()).into()
+  }
+
+  fn body(input: &Self::Input) -> Result<Bytes, JsonError> {
+    let (_, request) = input;
+    let json = to_json(request)?;
+    let bytes = Bytes::from(json);
+    Ok(bytes)
+  }
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v1/accounts/<account-id>/cip endpoint.
+  pub Get(account::Id),
+  Ok => Cip, [
+    /// The results were retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// No account or no results were found for the given account ID.
+    /* 404 */ NOT_FOUND => NotFound,
+  ]
+  Api => Broker,
+
+  fn path(input: &Self::Input) -> Str {
+    format!("/v1/accounts/{}/cip", input.to_hyphenated_ref()).into()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::str::FromStr as _;
+
+  use http_endpoint::Endpoint;
+
+  use serde_json::from_slice as from_json_slice;
+  use serde_json::from_str as from_json;
+  use serde_json::Value;
+
+  use test_env_log::test;
+
+  use time_util::parse_system_time_from_date_str;
+
+
+  /// Check that we emit the expected body when submitting results.
+  #[test]
+  fn emit_request() {
+    let id = account::Id::from_str("b9b19618-22dd-4e80-8432-fc9e1ba0b27d").unwrap();
+    let request = CipReq {
+      provider_name: vec!["onfido".to_string()],
+      kyc: Some(Kyc {
+        id: "kyc-1".to_string(),
+        applicant_name: Some("John Doe".to_string()),
+        date_of_birth: parse_system_time_from_date_str("1990-01-01"),
+        approval_status: Some(ApprovalStatus::Approved),
+        ..Default::default()
+      }),
+      watchlist: Some(Watchlist {
+        id: "watchlist-1".to_string(),
+        result: Some(CheckResult::Clear),
+        ..Default::default()
+      }),
+      ..Default::default()
+    };
+    let input = (id, request);
+
+    assert_eq!(
+      Post::path(&input),
+      "/v1/accounts/b9b19618-22dd-4e80-8432-fc9e1ba0b27d/cip"
+    );
+
+    let body = Post::body(&input).unwrap();
+    let value = from_json_slice::<Value>(&body).unwrap();
+    assert_eq!(value["provider_name"][0], "onfido");
+    assert_eq!(value["kyc"]["date_of_birth"], "1990-01-01");
+    assert_eq!(value["kyc"]["approval_status"], "approved");
+    assert_eq!(value["kyc"].get("risk_score"), None);
+    assert_eq!(value["watchlist"]["result"], "clear");
+    assert_eq!(value.get("document"), None);
+  }
+
+  /// Check that we can emit dates of birth before the Unix epoch.
+  #[test]
+  fn emit_pre_epoch_date_of_birth() {
+    let id = account::Id::from_str("b9b19618-22dd-4e80-8432-fc9e1ba0b27d").unwrap();
+    let request = CipReq {
+      provider_name: vec!["onfido".to_string()],
+      kyc: Some(Kyc {
+        id: "kyc-1".to_string(),
+        date_of_birth: parse_system_time_from_date_str("1962-07-14"),
+        ..Default::default()
+      }),
+      document: Some(Document {
+        id: "document-1".to_string(),
+        date_of_birth: parse_system_time_from_date_str("1962-07-14"),
+        ..Default::default()
+      }),
+      ..Default::default()
+    };
+    let input = (id, request);
+
+    let body = Post::body(&input).unwrap();
+    let value = from_json_slice::<Value>(&body).unwrap();
+    assert_eq!(value["kyc"]["date_of_birth"], "1962-07-14");
+    assert_eq!(value["document"]["date_of_birth"], "1962-07-14");
+  }
+
+  /// Check that we can parse submitted results.
+  #[test]
+  fn parse_cip() {
+    let response = r#"{
+  "id": "1f2e5c7a-2c1d-4e3a-b7f1-3f0f6dd0b0a1",
+  "account_id": "b9b19618-22dd-4e80-8432-fc9e1ba0b27d",
+  "provider_name": ["onfido"],
+  "kyc": {
+    "id": "kyc-1",
+    "risk_score": 1,
+    "risk_level": "LOW",
+    "risk_categories": ["geography"],
+    "applicant_name": "John Doe",
+    "check_completed_at": "2021-06-01T12:00:00Z",
+    "approval_status": "approved"
+  },
+  "identity": {
+    "id": "identity-1",
+    "result": "consider",
+    "status": "complete"
+  },
+  "created_at": "2021-06-01T12:00:00Z",
+  "updated_at": "2021-06-01T12:00:00Z"
+}"#;
+
+    let cip = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    assert_eq!(cip.provider_name, vec!["onfido".to_string()]);
+    let kyc = cip.kyc.unwrap();
+    assert_eq!(kyc.risk_score, Some(1));
+    assert_eq!(kyc.risk_categories, vec!["geography".to_string()]);
+    assert_eq!(kyc.approval_status, Some(ApprovalStatus::Approved));
+    assert!(kyc.check_completed_at.is_some());
+    let identity = cip.identity.unwrap();
+    assert_eq!(identity.result, Some(CheckResult::Consider));
+    assert_eq!(identity.status, Some(CheckStatus::Complete));
+    assert_eq!(cip.document, None);
+  }
+}
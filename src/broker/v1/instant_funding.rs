@@ -0,0 +1,366 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::SystemTime;
+
+use http::Method;
+use http_endpoint::Bytes;
+
+use num_decimal::Num;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Error as JsonError;
+use serde_json::to_vec as to_json;
+use serde_urlencoded::to_string as to_query;
+
+use time_util::system_time_from_str;
+
+use crate::util::optional_system_time_from_date_str;
+use crate::util::strings_to_str;
+use crate::Str;
+
+
+Id! {
+  /// An ID uniquely identifying an instant funding transfer.
+  #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+  pub struct Id(pub Uuid);
+}
+
+
+/// The status of an instant funding transfer.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+pub enum Status {
+  /// The transfer was created but the funds have not yet been made
+  /// available.
+  #[serde(rename = "PENDING")]
+  Pending,
+  /// The funds were made available to the account, but the transfer is
+  /// yet to be settled by the correspondent.
+  #[serde(rename = "EXECUTED")]
+  Executed,
+  /// The transfer was settled.
+  #[serde(rename = "COMPLETED")]
+  Completed,
+  /// The transfer was canceled.
+  #[serde(rename = "CANCELED")]
+  Canceled,
+  /// Any other status that we have not accounted for.
+  ///
+  /// Note that having any such status should be considered a bug.
+  #[serde(other)]
+  Unknown,
+}
+
+
+/// A POST request to be made to the /v1/instant_funding endpoint.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct InstantFundingReq {
+  /// The number of the account to make the funds available to.
+  #[serde(rename = "account_no")]
+  pub account_no: String,
+  /// The number of the correspondent's account the funds are provided
+  /// from.
+  #[serde(rename = "source_account_no")]
+  pub source_account_no: String,
+  /// The amount to make available.
+  #[serde(rename = "amount")]
+  pub amount: Num,
+}
+
+
+/// An instant funding transfer, making funds available to an account
+/// before the underlying deposit settled.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct InstantFunding {
+  /// The transfer's ID.
+  #[serde(rename = "id")]
+  pub id: Id,
+  /// The number of the account the funds were made available to.
+  #[serde(rename = "account_no")]
+  pub account_no: String,
+  /// The number of the account the funds are provided from.
+  #[serde(rename = "source_account_no")]
+  pub source_account_no: String,
+  /// The amount made available.
+  #[serde(rename = "amount")]
+  pub amount: Num,
+  /// The amount that still has to be settled.
+  #[serde(rename = "remaining_payable", default)]
+  pub remaining_payable: Option<Num>,
+  /// The total interest accrued on the transfer.
+  #[serde(rename = "total_interest", default)]
+  pub total_interest: Option<Num>,
+  /// The transfer's status.
+  #[serde(rename = "status")]
+  pub status: Status,
+  /// The trading day the transfer is accounted for.
+  #[serde(
+    rename = "system_date",
+    default,
+    deserialize_with = "optional_system_time_from_date_str"
+  )]
+  pub system_date: Option<SystemTime>,
+  /// The date by which the transfer has to be settled.
+  #[serde(
+    rename = "deadline",
+    default,
+    deserialize_with = "optional_system_time_from_date_str"
+  )]
+  pub deadline: Option<SystemTime>,
+  /// The time the transfer was created at.
+  #[serde(rename = "created_at", deserialize_with = "system_time_from_str")]
+  pub created_at: SystemTime,
+}
+
+
+/// The instant funding limits of a correspondent or an individual
+/// account.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Limits {
+  /// The number of the account the limits apply to, if they are
+  /// account specific.
+  #[serde(rename = "account_no", default)]
+  pub account_no: Option<String>,
+  /// The amount that can still be made available.
+  #[serde(rename = "amount_available")]
+  pub amount_available: Num,
+  /// The amount currently made available but not yet settled.
+  #[serde(rename = "amount_in_use")]
+  pub amount_in_use: Num,
+  /// The total amount that can be made available.
+  #[serde(rename = "amount_limit")]
+  pub amount_limit: Num,
+}
+
+
+Endpoint! {
+  /// The representation of a POST request to the /v1/instant_funding
+  /// endpoint.
+  pub Post(InstantFundingReq),
+  Ok => InstantFunding, [
+    /// The instant funding transfer was created successfully.
+    /* 200 */ OK,
+  ],
+  Err => PostError, [
+    /// Some data in the request was invalid.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+    /// The transfer would exceed the instant funding limits.
+    /* 403 */ FORBIDDEN => LimitExceeded,
+    /// Some data in the request could not be processed.
+    /* 422 */ UNPROCESSABLE_ENTITY => Unprocessable,
+  ]
+  Api => Broker,
+
+  fn method() -> Method {
+    Method::POST
+  }
+
+  fn path(_input: &Self::Input) -> Str {
+    "/v1/instant_funding".into()
+  }
+
+  fn body(input: &Self::Input) -> Result<Bytes, JsonError> {
+    let json = to_json(input)?;
+    let bytes = Bytes::from(json);
+    Ok(bytes)
+  }
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v1/instant_funding/<id> endpoint.
+  pub Get(Id),
+  Ok => InstantFunding, [
+    /// The instant funding transfer was retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// No instant funding transfer was found with the given ID.
+    /* 404 */ NOT_FOUND => NotFound,
+  ]
+  Api => Broker,
+
+  fn path(input: &Self::Input) -> Str {
+    format!("/v1/instant_funding/{}", input.to_hyphenated_ref()).into()
+  }
+}
+
+
+Endpoint! {
+  /// The representation of a DELETE request to the
+  /// /v1/instant_funding/<id> endpoint, canceling a pending transfer.
+  pub Delete(Id),
+  Ok => (), [
+    /// The instant funding transfer was canceled successfully.
+    /* 204 */ NO_CONTENT,
+  ],
+  Err => DeleteError, [
+    /// No instant funding transfer was found with the given ID.
+    /* 404 */ NOT_FOUND => NotFound,
+    /// The transfer can no longer be canceled.
+    /* 422 */ UNPROCESSABLE_ENTITY => NotCancelable,
+  ]
+  Api => Broker,
+
+  fn method() -> Method {
+    Method::DELETE
+  }
+
+  fn path(input: &Self::Input) -> Str {
+    format!("/v1/instant_funding/{}", input.to_hyphenated_ref()).into()
+  }
+
+  fn parse(body: &[u8]) -> Result<Self::Output, Self::Error> {
+    debug_assert_eq!(body, b"");
+    Ok(())
+  }
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v1/instant_funding/limits endpoint, retrieving the limits of the
+  /// correspondent.
+  pub GetLimits(()),
+  Ok => Limits, [
+    /// The limits were retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetLimitsError, []
+  Api => Broker,
+
+  fn path(_input: &Self::Input) -> Str {
+    "/v1/instant_funding/limits".into()
+  }
+}
+
+
+/// A GET request to be made to the /v1/accounts/instant_funding/limits
+/// endpoint.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct AccountLimitsReq {
+  /// The numbers of the accounts to retrieve limits for.
+  #[serde(rename = "account_numbers", serialize_with = "strings_to_str")]
+  pub account_numbers: Vec<String>,
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v1/accounts/instant_funding/limits endpoint, retrieving the
+  /// limits of individual accounts.
+  pub GetAccountLimits(AccountLimitsReq),
+  Ok => Vec<Limits>, [
+    /// The limits were retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetAccountLimitsError, [
+    /// Some data in the request was invalid.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+  ]
+  Api => Broker,
+
+  fn path(_input: &Self::Input) -> Str {
+    "/v1/accounts/instant_funding/limits".into()
+  }
+
+  fn query(input: &Self::Input) -> Option<Str> {
+    // TODO: Realistically there should be no way for this unwrap to
+    //       ever panic because our conversion to strings should not be
+    //       fallible. But still, ideally we would not have to unwrap.
+    Some(to_query(input).unwrap().into())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use http_endpoint::Endpoint;
+
+  use serde_json::from_slice as from_json_slice;
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+
+  /// Check that we emit the expected body for an instant funding
+  /// request.
+  #[test]
+  fn emit_request() {
+    let request = InstantFundingReq {
+      account_no: "920964623".to_string(),
+      source_account_no: "SI".to_string(),
+      amount: Num::from(20),
+    };
+
+    let body = Post::body(&request).unwrap();
+    let value = from_json_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(value["account_no"], "920964623");
+    assert_eq!(value["source_account_no"], "SI");
+    assert_eq!(value["amount"], "20");
+  }
+
+  /// Check that we can parse an instant funding transfer.
+  #[test]
+  fn parse_reference_transfer() {
+    let response = r#"{
+  "id": "6d7b0ba9-cf6b-4e5d-8e5f-8ce4bc0ed577",
+  "account_no": "920964623",
+  "source_account_no": "SI",
+  "amount": "20",
+  "remaining_payable": "20",
+  "total_interest": "0",
+  "interests": [],
+  "fees": [],
+  "status": "PENDING",
+  "system_date": "2024-08-05",
+  "deadline": "2024-08-07",
+  "created_at": "2024-08-05T09:40:12.508153Z"
+}"#;
+    let transfer = from_json::<InstantFunding>(response).unwrap();
+    assert_eq!(transfer.account_no, "920964623");
+    assert_eq!(transfer.amount, Num::from(20));
+    assert_eq!(transfer.remaining_payable, Some(Num::from(20)));
+    assert_eq!(transfer.status, Status::Pending);
+    assert!(transfer.deadline > transfer.system_date);
+  }
+
+  /// Check that we can parse instant funding limits.
+  #[test]
+  fn parse_limits() {
+    let response = r#"[{
+  "account_no": "920964623",
+  "amount_available": "80",
+  "amount_in_use": "20",
+  "amount_limit": "100"
+}]"#;
+
+    let limits = from_json::<<GetAccountLimits as Endpoint>::Output>(response).unwrap();
+    assert_eq!(limits.len(), 1);
+    assert_eq!(limits[0].account_no.as_deref(), Some("920964623"));
+    assert_eq!(limits[0].amount_available, Num::from(80));
+    assert_eq!(limits[0].amount_in_use, Num::from(20));
+    assert_eq!(limits[0].amount_limit, Num::from(100));
+
+    let response = r#"{"amount_available":"1000","amount_in_use":"0","amount_limit":"1000"}"#;
+    let limits = from_json::<<GetLimits as Endpoint>::Output>(response).unwrap();
+    assert_eq!(limits.account_no, None);
+  }
+
+  /// Check that we emit the expected query for retrieving account
+  /// limits.
+  #[test]
+  fn emit_account_limits_query() {
+    let request = AccountLimitsReq {
+      account_numbers: vec!["920964623".to_string(), "920964624".to_string()],
+    };
+    assert_eq!(
+      GetAccountLimits::query(&request).unwrap(),
+      "account_numbers=920964623%2C920964624"
+    );
+  }
+}
@@ -0,0 +1,24 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+/// Definitions surrounding accounts managed through the Broker API.
+pub mod account;
+/// Functionality for retrieving the activities of an account.
+#[cfg(feature = "trading")]
+pub mod account_activities;
+/// Definitions for submitting and retrieving customer identification
+/// program (CIP) results.
+pub mod cip;
+/// Definitions surrounding instant funding of accounts.
+pub mod instant_funding;
+/// Functionality for retrieving the portfolio history of an account.
+pub mod portfolio_history;
+/// Definitions for the rebalancing of accounts towards model
+/// portfolios.
+pub mod rebalancing;
+/// Functionality for retrieving the trading account of an account,
+/// including its buying power.
+#[cfg(feature = "trading")]
+pub mod trading_account;
+/// Functionality for retrieving the transfer limits of an account.
+pub mod transfer_limits;
@@ -0,0 +1,236 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use num_decimal::Num;
+
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde_urlencoded::to_string as to_query;
+
+use crate::broker::v1::account;
+use crate::util::optional_system_time_to_date_str;
+use crate::Str;
+
+
+/// The resolution of the data points in a portfolio history.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum TimeFrame {
+  /// One data point per minute.
+  #[serde(rename = "1Min")]
+  OneMinute,
+  /// One data point per five minutes.
+  #[serde(rename = "5Min")]
+  FiveMinutes,
+  /// One data point per fifteen minutes.
+  #[serde(rename = "15Min")]
+  FifteenMinutes,
+  /// One data point per hour.
+  #[serde(rename = "1H")]
+  OneHour,
+  /// One data point per day.
+  #[serde(rename = "1D")]
+  OneDay,
+  /// Any other time frame that we have not accounted for.
+  ///
+  /// Note that having any such time frame should be considered a bug.
+  #[serde(other)]
+  Unknown,
+}
+
+
+/// Deserialize a list of Unix timestamps (in seconds) into a
+/// `Vec<SystemTime>`.
+fn system_times_from_secs<'de, D>(deserializer: D) -> Result<Vec<SystemTime>, D::Error>
+where
+  D: Deserializer<'de>,
+{
+  let secs = Vec::<u64>::deserialize(deserializer)?;
+  let times = secs
+    .into_iter()
+    .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+    .collect();
+  Ok(times)
+}
+
+
+/// The history of an account's equity and profit/loss.
+///
+/// All series are of the same length, with the values at one index
+/// belonging to the time stamp at the same index.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct PortfolioHistory {
+  /// The time stamps of the data points.
+  #[serde(rename = "timestamp", deserialize_with = "system_times_from_secs")]
+  pub timestamps: Vec<SystemTime>,
+  /// The equity of the account at each time stamp.
+  ///
+  /// Values are absent for time stamps at which the account did not
+  /// exist yet.
+  #[serde(rename = "equity")]
+  pub equity: Vec<Option<Num>>,
+  /// The profit/loss of the account at each time stamp, relative to
+  /// `base_value`.
+  #[serde(rename = "profit_loss")]
+  pub profit_loss: Vec<Option<Num>>,
+  /// The profit/loss at each time stamp, as a fraction of `base_value`.
+  #[serde(rename = "profit_loss_pct")]
+  pub profit_loss_pct: Vec<Option<Num>>,
+  /// The equity the profit/loss is calculated relative to.
+  #[serde(rename = "base_value")]
+  pub base_value: Num,
+  /// The resolution of the data points.
+  #[serde(rename = "timeframe")]
+  pub time_frame: TimeFrame,
+}
+
+impl PortfolioHistory {
+  /// Retrieve the number of data points in the history.
+  #[inline]
+  pub fn len(&self) -> usize {
+    self.timestamps.len()
+  }
+
+  /// Check whether the history is empty.
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.timestamps.is_empty()
+  }
+}
+
+
+/// A GET request to be made to the
+/// /v1/trading/accounts/<account-id>/account/portfolio/history
+/// endpoint.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct PortfolioHistoryReq {
+  /// The duration of the history, as a number followed by a unit of
+  /// `D` (days), `W` (weeks), `M` (months), or `A` (years), e.g.,
+  /// `1M`.
+  ///
+  /// If `None` the server side default of one month is used.
+  #[serde(rename = "period", skip_serializing_if = "Option::is_none")]
+  pub period: Option<String>,
+  /// The resolution of the data points.
+  ///
+  /// If `None` the server picks a resolution based on the period.
+  #[serde(rename = "timeframe", skip_serializing_if = "Option::is_none")]
+  pub time_frame: Option<TimeFrame>,
+  /// The date the history ends at.
+  ///
+  /// If `None` the history ends at the current market date.
+  #[serde(
+    rename = "date_end",
+    skip_serializing_if = "Option::is_none",
+    serialize_with = "optional_system_time_to_date_str"
+  )]
+  pub end: Option<SystemTime>,
+  /// Whether to include data points for extended hours, for intraday
+  /// time frames.
+  #[serde(rename = "extended_hours", skip_serializing_if = "Option::is_none")]
+  pub extended_hours: Option<bool>,
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v1/trading/accounts/<account-id>/account/portfolio/history
+  /// endpoint.
+  pub Get((account::Id, PortfolioHistoryReq)),
+  Ok => PortfolioHistory, [
+    /// The portfolio history was retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// Some data in the request was invalid.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+    /// No account was found with the given ID.
+    /* 404 */ NOT_FOUND => NotFound,
+  ]
+  Api => Broker,
+
+  fn path(input: &Self::Input) -> Str {
+    let (id, _) = input;
+    format!(
+      "/v1/trading/accounts/{}/account/portfolio/history",
+      id.to_hyphenated_ref()
+    )
+    .into()
+  }
+
+  fn query(input: &Self::Input) -> Option<Str> {
+    let (_, request) = input;
+    // TODO: Realistically there should be no way for this unwrap to
+    //       ever panic because our conversion to strings should not be
+    //       fallible. But still, ideally we would not have to unwrap.
+    Some(to_query(request).unwrap().into())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::str::FromStr as _;
+
+  use http_endpoint::Endpoint;
+
+  use serde_json::from_str as from_json;
+
+  use time_util::parse_system_time_from_date_str;
+
+  use test_env_log::test;
+
+
+  /// Check that we emit the expected path and query for retrieving the
+  /// portfolio history of an account.
+  #[test]
+  fn emit_request() {
+    let id = account::Id::from_str("bf2b0f93-f296-4276-a9cf-288586cf4fb7").unwrap();
+    let request = PortfolioHistoryReq {
+      period: Some("1W".to_string()),
+      time_frame: Some(TimeFrame::OneHour),
+      end: Some(parse_system_time_from_date_str("2022-08-05").unwrap()),
+      ..Default::default()
+    };
+    let input = (id, request);
+    assert_eq!(
+      Get::path(&input),
+      "/v1/trading/accounts/bf2b0f93-f296-4276-a9cf-288586cf4fb7/account/portfolio/history"
+    );
+    assert_eq!(
+      Get::query(&input).unwrap(),
+      "period=1W&timeframe=1H&date_end=2022-08-05"
+    );
+  }
+
+  /// Check that we can parse a portfolio history.
+  #[test]
+  fn parse_portfolio_history() {
+    let response = r#"{
+  "timestamp": [1659643200, 1659902400],
+  "equity": [null, 10257.38],
+  "profit_loss": [0, 257.38],
+  "profit_loss_pct": [0, 0.025738],
+  "base_value": 10000,
+  "timeframe": "1D"
+}"#;
+
+    let history = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(
+      history.timestamps[1],
+      UNIX_EPOCH + Duration::from_secs(1659902400)
+    );
+    assert_eq!(history.equity[0], None);
+    assert_eq!(history.equity[1], Some(Num::new(1025738, 100)));
+    assert_eq!(history.profit_loss[0], Some(Num::from(0)));
+    assert_eq!(history.base_value, Num::from(10000));
+    assert_eq!(history.time_frame, TimeFrame::OneDay);
+  }
+}
@@ -0,0 +1,16 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+/// Definitions surrounding model portfolios.
+pub mod portfolio;
+/// Functionality for listing model portfolios.
+pub mod portfolios;
+/// Definitions surrounding rebalancing runs.
+pub mod run;
+/// Functionality for listing rebalancing runs.
+pub mod runs;
+/// Definitions surrounding subscriptions of accounts to model
+/// portfolios.
+pub mod subscription;
+/// Functionality for listing subscriptions.
+pub mod subscriptions;
@@ -0,0 +1,412 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::SystemTime;
+
+use http::Method;
+use http_endpoint::Bytes;
+
+use num_decimal::Num;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Error as JsonError;
+use serde_json::to_vec as to_json;
+
+use time_util::system_time_from_str;
+
+use crate::Str;
+
+
+Id! {
+  /// An ID uniquely identifying a model portfolio.
+  #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+  pub struct Id(pub Uuid);
+}
+
+
+/// The status of a model portfolio.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Status {
+  /// The portfolio is active and can be subscribed to.
+  #[serde(rename = "active")]
+  Active,
+  /// The portfolio is inactive.
+  #[serde(rename = "inactive")]
+  Inactive,
+  /// Any other status that we have not accounted for.
+  ///
+  /// Note that having any such status should be considered a bug.
+  #[serde(other)]
+  Unknown,
+}
+
+
+/// The kind of holding a weight refers to.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum WeightType {
+  /// A weight of an individual asset.
+  #[serde(rename = "asset")]
+  Asset,
+  /// A weight of cash.
+  #[serde(rename = "cash")]
+  Cash,
+}
+
+
+/// The target weight of a holding in a model portfolio.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Weight {
+  /// The kind of holding.
+  #[serde(rename = "type")]
+  pub type_: WeightType,
+  /// The symbol of the asset, for asset weights.
+  #[serde(rename = "symbol", default, skip_serializing_if = "Option::is_none")]
+  pub symbol: Option<String>,
+  /// The target percentage of the portfolio's value.
+  #[serde(rename = "percent")]
+  pub percent: Num,
+}
+
+impl Weight {
+  /// Create a `Weight` for the asset with the given symbol.
+  pub fn asset<S>(symbol: S, percent: Num) -> Self
+  where
+    S: Into<String>,
+  {
+    Self {
+      type_: WeightType::Asset,
+      symbol: Some(symbol.into()),
+      percent,
+    }
+  }
+
+  /// Create a `Weight` for cash.
+  pub fn cash(percent: Num) -> Self {
+    Self {
+      type_: WeightType::Cash,
+      symbol: None,
+      percent,
+    }
+  }
+}
+
+
+/// The kind of condition triggering a rebalance.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum ConditionType {
+  /// Rebalance once holdings drift from their target weights by more
+  /// than a given percentage.
+  #[serde(rename = "drift_band")]
+  DriftBand,
+  /// Rebalance on a calendar schedule.
+  #[serde(rename = "calendar")]
+  Calendar,
+}
+
+
+/// A condition triggering a rebalance of the accounts subscribed to a
+/// model portfolio.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Condition {
+  /// The kind of condition.
+  #[serde(rename = "type")]
+  pub type_: ConditionType,
+  /// The sub type of the condition, e.g., `absolute` or `relative` for
+  /// drift bands and `weekly`, `monthly`, `quarterly`, or `annually`
+  /// for calendar conditions.
+  #[serde(rename = "sub_type")]
+  pub sub_type: String,
+  /// The drift percentage, for drift band conditions.
+  #[serde(rename = "percent", default, skip_serializing_if = "Option::is_none")]
+  pub percent: Option<Num>,
+  /// The day to rebalance on, for calendar conditions.
+  #[serde(rename = "day", default, skip_serializing_if = "Option::is_none")]
+  pub day: Option<String>,
+}
+
+
+/// A model portfolio, i.e., a set of target weights that subscribed
+/// accounts are rebalanced towards.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Portfolio {
+  /// The portfolio's ID.
+  #[serde(rename = "id")]
+  pub id: Id,
+  /// The portfolio's name.
+  #[serde(rename = "name")]
+  pub name: String,
+  /// The portfolio's description.
+  #[serde(rename = "description", default)]
+  pub description: Option<String>,
+  /// The portfolio's status.
+  #[serde(rename = "status")]
+  pub status: Status,
+  /// The minimum number of days between rebalances.
+  #[serde(rename = "cooldown_days", default)]
+  pub cooldown_days: Option<u32>,
+  /// The target weights.
+  #[serde(rename = "weights")]
+  pub weights: Vec<Weight>,
+  /// The conditions triggering a rebalance.
+  #[serde(rename = "rebalance_conditions", default)]
+  pub rebalance_conditions: Vec<Condition>,
+  /// The time the portfolio was created at.
+  #[serde(rename = "created_at", deserialize_with = "system_time_from_str")]
+  pub created_at: SystemTime,
+  /// The time the portfolio was last updated at.
+  #[serde(rename = "updated_at", deserialize_with = "system_time_from_str")]
+  pub updated_at: SystemTime,
+}
+
+
+/// A POST request to be made to the /v1/rebalancing/portfolios
+/// endpoint.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PortfolioReq {
+  /// The portfolio's name.
+  #[serde(rename = "name")]
+  pub name: String,
+  /// The portfolio's description.
+  #[serde(rename = "description")]
+  pub description: String,
+  /// The target weights, which have to add up to 100.
+  #[serde(rename = "weights")]
+  pub weights: Vec<Weight>,
+  /// The minimum number of days between rebalances.
+  #[serde(rename = "cooldown_days", skip_serializing_if = "Option::is_none")]
+  pub cooldown_days: Option<u32>,
+  /// The conditions triggering a rebalance.
+  #[serde(rename = "rebalance_conditions", skip_serializing_if = "Vec::is_empty")]
+  pub rebalance_conditions: Vec<Condition>,
+}
+
+
+/// A PATCH request to be made to the
+/// /v1/rebalancing/portfolios/<portfolio-id> endpoint.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ChangeReq {
+  /// The portfolio's new name.
+  #[serde(rename = "name", skip_serializing_if = "Option::is_none")]
+  pub name: Option<String>,
+  /// The portfolio's new description.
+  #[serde(rename = "description", skip_serializing_if = "Option::is_none")]
+  pub description: Option<String>,
+  /// The new target weights.
+  #[serde(rename = "weights", skip_serializing_if = "Option::is_none")]
+  pub weights: Option<Vec<Weight>>,
+  /// The new minimum number of days between rebalances.
+  #[serde(rename = "cooldown_days", skip_serializing_if = "Option::is_none")]
+  pub cooldown_days: Option<u32>,
+  /// The new conditions triggering a rebalance.
+  #[serde(
+    rename = "rebalance_conditions",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub rebalance_conditions: Option<Vec<Condition>>,
+}
+
+
+Endpoint! {
+  /// The representation of a POST request to the
+  /// /v1/rebalancing/portfolios endpoint.
+  pub Post(PortfolioReq),
+  Ok => Portfolio, [
+    /// The portfolio was created successfully.
+    /* 200 */ OK,
+  ],
+  Err => PostError, [
+    /// Some data in the request was invalid.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+    /// A portfolio with the same name exists already.
+    /* 409 */ CONFLICT => Conflict,
+    /// Some data in the request could not be processed.
+    /* 422 */ UNPROCESSABLE_ENTITY => Unprocessable,
+  ]
+  Api => Broker,
+
+  fn method() -> Method {
+    Method::POST
+  }
+
+  fn path(_input: &Self::Input) -> Str {
+    "/v1/rebalancing/portfolios".into()
+  }
+
+  fn body(input: &Self::Input) -> Result<Bytes, JsonError> {
+    let json = to_json(input)?;
+    let bytes = Bytes::from(json);
+    Ok(bytes)
+  }
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v1/rebalancing/portfolios/<portfolio-id> endpoint.
+  pub Get(Id),
+  Ok => Portfolio, [
+    /// The portfolio was retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// No portfolio was found with the given ID.
+    /* 404 */ NOT_FOUND => NotFound,
+  ]
+  Api => Broker,
+
+  fn path(input: &Self::Input) -> Str {
+    format!("/v1/rebalancing/portfolios/{}", input.to_hyphenated_ref()).into()
+  }
+}
+
+
+Endpoint! {
+  /// The representation of a PATCH request to the
+  /// /v1/rebalancing/portfolios/<portfolio-id> endpoint.
+  pub Patch((Id, ChangeReq)),
+  Ok => Portfolio, [
+    /// The portfolio was changed successfully.
+    /* 200 */ OK,
+  ],
+  Err => PatchError, [
+    /// Some data in the request was invalid.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+    /// No portfolio was found with the given ID.
+    /* 404 */ NOT_FOUND => NotFound,
+    /// Some data in the request could not be processed.
+    /* 422 */ UNPROCESSABLE_ENTITY => Unprocessable,
+  ]
+  Api => Broker,
+
+  fn method() -> Method {
+    Method::PATCH
+  }
+
+  fn path(input: &Self::Input) -> Str {
+    let (id, _) = input;
+    format!("/v1/rebalancing/portfolios/{}", id.to_hyphenated_ref()).into()
+  }
+
+  fn body(input: &Self::Input) -> Result<Bytes, JsonError> {
+    let (_, request) = input;
+    let json = to_json(request)?;
+    let bytes = Bytes::from(json);
+    Ok(bytes)
+  }
+}
+
+
+Endpoint! {
+  /// The representation of a DELETE request to the
+  /// /v1/rebalancing/portfolios/<portfolio-id> endpoint, deactivating
+  /// the portfolio.
+  pub Delete(Id),
+  Ok => (), [
+    /// The portfolio was deactivated successfully.
+    /* 204 */ NO_CONTENT,
+  ],
+  Err => DeleteError, [
+    /// No portfolio was found with the given ID.
+    /* 404 */ NOT_FOUND => NotFound,
+    /// The portfolio still has subscribers.
+    /* 409 */ CONFLICT => Conflict,
+  ]
+  Api => Broker,
+
+  fn method() -> Method {
+    Method::DELETE
+  }
+
+  fn path(input: &Self::Input) -> Str {
+    format!("/v1/rebalancing/portfolios/{}", input.to_hyphenated_ref()).into()
+  }
+
+  fn parse(body: &[u8]) -> Result<Self::Output, Self::Error> {
+    debug_assert_eq!(body, b"");
+    Ok(())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use http_endpoint::Endpoint;
+
+  use serde_json::from_slice as from_json_slice;
+  use serde_json::from_str as from_json;
+  use serde_json::Value;
+
+  use test_env_log::test;
+
+
+  /// Check that we emit the expected body when creating a portfolio.
+  #[test]
+  fn emit_request() {
+    let request = PortfolioReq {
+      name: "Balanced".to_string(),
+      description: "A balanced portfolio".to_string(),
+      weights: vec![
+        Weight::asset("SPY", Num::from(60)),
+        Weight::asset("TLT", Num::from(35)),
+        Weight::cash(Num::from(5)),
+      ],
+      cooldown_days: Some(7),
+      rebalance_conditions: vec![Condition {
+        type_: ConditionType::DriftBand,
+        sub_type: "absolute".to_string(),
+        percent: Some(Num::from(5)),
+        day: None,
+      }],
+    };
+
+    let body = Post::body(&request).unwrap();
+    let value = from_json_slice::<Value>(&body).unwrap();
+    assert_eq!(value["weights"][0]["type"], "asset");
+    assert_eq!(value["weights"][0]["symbol"], "SPY");
+    assert_eq!(value["weights"][0]["percent"], "60");
+    assert_eq!(value["weights"][2]["type"], "cash");
+    assert_eq!(value["weights"][2].get("symbol"), None);
+    assert_eq!(value["cooldown_days"], 7);
+    assert_eq!(value["rebalance_conditions"][0]["type"], "drift_band");
+    assert_eq!(value["rebalance_conditions"][0].get("day"), None);
+  }
+
+  /// Check that we can parse a portfolio.
+  #[test]
+  fn parse_portfolio() {
+    let response = r#"{
+  "id": "2d49d00e-ab1c-4014-89d8-70c5f64df2fc",
+  "name": "Balanced",
+  "description": "A balanced portfolio",
+  "status": "active",
+  "cooldown_days": 7,
+  "created_at": "2022-08-07T18:56:45.116867Z",
+  "updated_at": "2022-08-07T18:56:45.196857Z",
+  "weights": [
+    {"type": "asset", "symbol": "SPY", "percent": "60"},
+    {"type": "cash", "symbol": null, "percent": "40"}
+  ],
+  "rebalance_conditions": [
+    {"type": "calendar", "sub_type": "weekly", "day": "Monday"}
+  ]
+}"#;
+
+    let portfolio = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    assert_eq!(portfolio.name, "Balanced");
+    assert_eq!(portfolio.status, Status::Active);
+    assert_eq!(portfolio.cooldown_days, Some(7));
+    assert_eq!(portfolio.weights[0], Weight::asset("SPY", Num::from(60)));
+    assert_eq!(portfolio.weights[1], Weight::cash(Num::from(40)));
+    assert_eq!(
+      portfolio.rebalance_conditions[0].type_,
+      ConditionType::Calendar
+    );
+    assert_eq!(
+      portfolio.rebalance_conditions[0].day.as_deref(),
+      Some("Monday")
+    );
+  }
+}
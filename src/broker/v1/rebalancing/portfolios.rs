@@ -0,0 +1,77 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use serde::Serialize;
+use serde_urlencoded::to_string as to_query;
+
+use crate::broker::v1::rebalancing::portfolio::Portfolio;
+use crate::broker::v1::rebalancing::portfolio::Status;
+use crate::Str;
+
+
+/// A GET request to be made to the /v1/rebalancing/portfolios
+/// endpoint.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct PortfoliosReq {
+  /// Only include portfolios containing the given name.
+  #[serde(rename = "name", skip_serializing_if = "Option::is_none")]
+  pub name: Option<String>,
+  /// Only include portfolios holding the asset with the given symbol.
+  #[serde(rename = "symbol", skip_serializing_if = "Option::is_none")]
+  pub symbol: Option<String>,
+  /// Only include portfolios with the given status.
+  #[serde(rename = "status", skip_serializing_if = "Option::is_none")]
+  pub status: Option<Status>,
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v1/rebalancing/portfolios endpoint.
+  pub Get(PortfoliosReq),
+  Ok => Vec<Portfolio>, [
+    /// The list of portfolios was retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// Some data in the request was invalid.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+  ]
+  Api => Broker,
+
+  fn path(_input: &Self::Input) -> Str {
+    "/v1/rebalancing/portfolios".into()
+  }
+
+  fn query(input: &Self::Input) -> Option<Str> {
+    // TODO: Realistically there should be no way for this unwrap to
+    //       ever panic because our conversion to strings should not be
+    //       fallible. But still, ideally we would not have to unwrap.
+    Some(to_query(input).unwrap().into())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use http_endpoint::Endpoint;
+
+  use test_env_log::test;
+
+
+  /// Check that we emit the expected query for listing portfolios.
+  #[test]
+  fn emit_query() {
+    let request = PortfoliosReq::default();
+    assert_eq!(Get::query(&request).unwrap(), "");
+
+    let request = PortfoliosReq {
+      symbol: Some("SPY".to_string()),
+      status: Some(Status::Active),
+      ..Default::default()
+    };
+    assert_eq!(Get::query(&request).unwrap(), "symbol=SPY&status=active");
+  }
+}
@@ -0,0 +1,315 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::SystemTime;
+
+use http::Method;
+use http_endpoint::Bytes;
+
+use num_decimal::Num;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Error as JsonError;
+use serde_json::to_vec as to_json;
+
+use time_util::optional_system_time_from_str;
+use time_util::system_time_from_str;
+
+use crate::broker::v1::account;
+use crate::broker::v1::rebalancing::portfolio;
+use crate::broker::v1::rebalancing::portfolio::Weight;
+use crate::Str;
+
+
+Id! {
+  /// An ID uniquely identifying a rebalancing run.
+  #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+  pub struct Id(pub Uuid);
+}
+
+
+/// The type of a rebalancing run.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Type {
+  /// Sell and buy positions so that the account matches its target
+  /// weights.
+  #[serde(rename = "full_rebalance")]
+  FullRebalance,
+  /// Invest available cash according to the target weights, without
+  /// selling any positions.
+  #[serde(rename = "invest_cash")]
+  InvestCash,
+  /// Any other type that we have not accounted for.
+  ///
+  /// Note that having any such type should be considered a bug.
+  #[serde(other)]
+  Unknown,
+}
+
+
+/// The status of a rebalancing run.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+pub enum Status {
+  /// The run is queued for execution.
+  #[serde(rename = "QUEUED")]
+  Queued,
+  /// The run is in progress.
+  #[serde(rename = "IN_PROGRESS")]
+  InProgress,
+  /// The run was canceled before it started.
+  #[serde(rename = "CANCELED")]
+  Canceled,
+  /// The run was canceled while it was in progress.
+  #[serde(rename = "CANCELED_MID_RUN")]
+  CanceledMidRun,
+  /// The run failed.
+  #[serde(rename = "ERROR")]
+  Error,
+  /// The run timed out.
+  #[serde(rename = "TIMEOUT")]
+  Timeout,
+  /// The run completed and the account matches its target weights.
+  #[serde(rename = "COMPLETED_SUCCESS")]
+  CompletedSuccess,
+  /// The run completed, but orders had to be adjusted, e.g., due to
+  /// insufficient buying power.
+  #[serde(rename = "COMPLETED_ADJUSTED")]
+  CompletedAdjusted,
+  /// Any other status that we have not accounted for.
+  ///
+  /// Note that having any such status should be considered a bug.
+  #[serde(other)]
+  Unknown,
+}
+
+impl Status {
+  /// Check whether the status is terminal, i.e., the run will not
+  /// change any further.
+  #[inline]
+  pub fn is_terminal(self) -> bool {
+    !matches!(self, Self::Queued | Self::InProgress | Self::Unknown)
+  }
+}
+
+
+/// A rebalancing run of an account.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Run {
+  /// The run's ID.
+  #[serde(rename = "id")]
+  pub id: Id,
+  /// The run's type.
+  #[serde(rename = "type")]
+  pub type_: Type,
+  /// The amount of cash to invest, for cash investing runs.
+  #[serde(rename = "amount", default)]
+  pub amount: Option<Num>,
+  /// What initiated the run, e.g., `SYSTEM` or `API`.
+  #[serde(rename = "initiated_from", default)]
+  pub initiated_from: Option<String>,
+  /// The run's status.
+  #[serde(rename = "status")]
+  pub status: Status,
+  /// The reason for the run's status, if any.
+  #[serde(rename = "reason", default)]
+  pub reason: Option<String>,
+  /// The ID of the rebalanced account.
+  #[serde(rename = "account_id")]
+  pub account_id: account::Id,
+  /// The ID of the portfolio the account was rebalanced towards, if
+  /// the run was triggered by a subscription.
+  #[serde(rename = "portfolio_id", default)]
+  pub portfolio_id: Option<portfolio::Id>,
+  /// The target weights of the run.
+  #[serde(rename = "weights", default)]
+  pub weights: Vec<Weight>,
+  /// The time the run was created at.
+  #[serde(rename = "created_at", deserialize_with = "system_time_from_str")]
+  pub created_at: SystemTime,
+  /// The time the run was last updated at.
+  #[serde(rename = "updated_at", deserialize_with = "system_time_from_str")]
+  pub updated_at: SystemTime,
+  /// The time the run completed at, if it did.
+  #[serde(
+    rename = "completed_at",
+    default,
+    deserialize_with = "optional_system_time_from_str"
+  )]
+  pub completed_at: Option<SystemTime>,
+  /// The time the run was canceled at, if it was.
+  #[serde(
+    rename = "canceled_at",
+    default,
+    deserialize_with = "optional_system_time_from_str"
+  )]
+  pub canceled_at: Option<SystemTime>,
+}
+
+
+/// A POST request to be made to the /v1/rebalancing/runs endpoint.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RunReq {
+  /// The ID of the account to rebalance.
+  #[serde(rename = "account_id")]
+  pub account_id: account::Id,
+  /// The type of run to perform.
+  #[serde(rename = "type")]
+  pub type_: Type,
+  /// The target weights to rebalance towards.
+  #[serde(rename = "weights")]
+  pub weights: Vec<Weight>,
+  /// The amount of cash to invest, for cash investing runs.
+  #[serde(rename = "amount", skip_serializing_if = "Option::is_none")]
+  pub amount: Option<Num>,
+}
+
+
+Endpoint! {
+  /// The representation of a POST request to the /v1/rebalancing/runs
+  /// endpoint.
+  pub Post(RunReq),
+  Ok => Run, [
+    /// The run was created successfully.
+    /* 200 */ OK,
+  ],
+  Err => PostError, [
+    /// Some data in the request was invalid.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+    /// The account has a run in progress already.
+    /* 409 */ CONFLICT => Conflict,
+    /// Some data in the request could not be processed.
+    /* 422 */ UNPROCESSABLE_ENTITY => Unprocessable,
+  ]
+  Api => Broker,
+
+  fn method() -> Method {
+    Method::POST
+  }
+
+  fn path(_input: &Self::Input) -> Str {
+    "/v1/rebalancing/runs".into()
+  }
+
+  fn body(input: &Self::Input) -> Result<Bytes, JsonError> {
+    let json = to_json(input)?;
+    let bytes = Bytes::from(json);
+    Ok(bytes)
+  }
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v1/rebalancing/runs/<run-id> endpoint.
+  pub Get(Id),
+  Ok => Run, [
+    /// The run was retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// No run was found with the given ID.
+    /* 404 */ NOT_FOUND => NotFound,
+  ]
+  Api => Broker,
+
+  fn path(input: &Self::Input) -> Str {
+    format!("/v1/rebalancing/runs/{}", input.to_hyphenated_ref()).into()
+  }
+}
+
+
+Endpoint! {
+  /// The representation of a DELETE request to the
+  /// /v1/rebalancing/runs/<run-id> endpoint, canceling the run.
+  pub Delete(Id),
+  Ok => (), [
+    /// The run was canceled successfully.
+    /* 204 */ NO_CONTENT,
+  ],
+  Err => DeleteError, [
+    /// No run was found with the given ID.
+    /* 404 */ NOT_FOUND => NotFound,
+    /// The run can no longer be canceled.
+    /* 422 */ UNPROCESSABLE_ENTITY => NotCancelable,
+  ]
+  Api => Broker,
+
+  fn method() -> Method {
+    Method::DELETE
+  }
+
+  fn path(input: &Self::Input) -> Str {
+    format!("/v1/rebalancing/runs/{}", input.to_hyphenated_ref()).into()
+  }
+
+  fn parse(body: &[u8]) -> Result<Self::Output, Self::Error> {
+    debug_assert_eq!(body, b"");
+    Ok(())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::str::FromStr;
+
+  use http_endpoint::Endpoint;
+
+  use serde_json::from_slice as from_json_slice;
+  use serde_json::from_str as from_json;
+  use serde_json::Value;
+
+  use test_env_log::test;
+
+
+  /// Check that we emit the expected body when requesting a run.
+  #[test]
+  fn emit_request() {
+    let request = RunReq {
+      account_id: account::Id::from_str("bf2b0f93-f296-4276-a9cf-288586cf4fb7").unwrap(),
+      type_: Type::InvestCash,
+      weights: vec![Weight::asset("SPY", Num::from(100))],
+      amount: Some(Num::new(2505, 100)),
+    };
+
+    let body = Post::body(&request).unwrap();
+    let value = from_json_slice::<Value>(&body).unwrap();
+    assert_eq!(value["type"], "invest_cash");
+    assert_eq!(value["amount"], "25.05");
+    assert_eq!(value["weights"][0]["symbol"], "SPY");
+  }
+
+  /// Check that we can parse a rebalancing run.
+  #[test]
+  fn parse_run() {
+    let response = r#"{
+  "id": "2ad28f83-796c-4c5e-895e-b63b0ae5bb8c",
+  "type": "full_rebalance",
+  "amount": null,
+  "initiated_from": "SYSTEM",
+  "status": "COMPLETED_ADJUSTED",
+  "reason": "insufficient buying power",
+  "account_id": "bf2b0f93-f296-4276-a9cf-288586cf4fb7",
+  "portfolio_id": "2d49d00e-ab1c-4014-89d8-70c5f64df2fc",
+  "weights": [
+    {"type": "asset", "symbol": "SPY", "percent": "100"}
+  ],
+  "created_at": "2022-08-08T14:00:00.023421Z",
+  "updated_at": "2022-08-08T14:02:04.269389Z",
+  "completed_at": "2022-08-08T14:02:04.269389Z",
+  "canceled_at": null
+}"#;
+
+    let run = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    assert_eq!(run.type_, Type::FullRebalance);
+    assert_eq!(run.amount, None);
+    assert_eq!(run.status, Status::CompletedAdjusted);
+    assert!(run.status.is_terminal());
+    assert!(run.portfolio_id.is_some());
+    assert!(run.completed_at.is_some());
+    assert_eq!(run.canceled_at, None);
+  }
+}
@@ -0,0 +1,128 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_urlencoded::to_string as to_query;
+
+use crate::broker::v1::account;
+use crate::broker::v1::rebalancing::run::Run;
+use crate::broker::v1::rebalancing::run::Type;
+use crate::paged::next_page_by_token;
+use crate::paged::Paged;
+use crate::Str;
+
+
+/// A GET request to be made to the /v1/rebalancing/runs endpoint.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct RunsReq {
+  /// Only include runs of the account with the given ID.
+  #[serde(rename = "account_id", skip_serializing_if = "Option::is_none")]
+  pub account_id: Option<account::Id>,
+  /// Only include runs of the given type.
+  #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+  pub type_: Option<Type>,
+  /// The maximum number of runs to return.
+  #[serde(rename = "limit", skip_serializing_if = "Option::is_none")]
+  pub limit: Option<usize>,
+  /// If provided, this token is used for retrieving the next page of
+  /// runs, following a previous request.
+  #[serde(rename = "page_token", skip_serializing_if = "Option::is_none")]
+  pub page_token: Option<String>,
+}
+
+
+/// A page of rebalancing runs as returned by the /v1/rebalancing/runs
+/// endpoint.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Runs {
+  /// The list of returned runs.
+  #[serde(rename = "runs", default)]
+  pub runs: Vec<Run>,
+  /// The token to provide to a request to get the next page of runs
+  /// for this request.
+  #[serde(rename = "next_page_token", default)]
+  pub next_page_token: Option<String>,
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the /v1/rebalancing/runs
+  /// endpoint.
+  pub Get(RunsReq),
+  Ok => Runs, [
+    /// The runs were retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// Some data in the request was invalid.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+  ]
+  Api => Broker,
+
+  fn path(_input: &Self::Input) -> Str {
+    "/v1/rebalancing/runs".into()
+  }
+
+  fn query(input: &Self::Input) -> Option<Str> {
+    // TODO: Realistically there should be no way for this unwrap to
+    //       ever panic because our conversion to strings should not be
+    //       fallible. But still, ideally we would not have to unwrap.
+    Some(to_query(input).unwrap().into())
+  }
+}
+
+impl Paged for Get {
+  fn next_page(input: &Self::Input, output: &Self::Output) -> Option<Self::Input> {
+    next_page_by_token(input, output.next_page_token.as_deref(), |input| {
+      &mut input.page_token
+    })
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use http_endpoint::Endpoint;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+
+  /// Check that we emit the expected query for listing runs.
+  #[test]
+  fn emit_query() {
+    let request = RunsReq {
+      type_: Some(Type::FullRebalance),
+      limit: Some(10),
+      page_token: Some("abc".to_string()),
+      ..Default::default()
+    };
+    assert_eq!(
+      Get::query(&request).unwrap(),
+      "type=full_rebalance&limit=10&page_token=abc"
+    );
+  }
+
+  /// Check that we request the next page of runs only if the
+  /// response reports a page token.
+  #[test]
+  fn next_runs_page() {
+    let request = RunsReq {
+      limit: Some(10),
+      ..Default::default()
+    };
+    let response = r#"{"runs":[],"next_page_token":"cnVuczox"}"#;
+    let output = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    let next = Get::next_page(&request, &output).unwrap();
+    assert_eq!(next.page_token.as_deref(), Some("cnVuczox"));
+    assert_eq!(next.limit, Some(10));
+
+    let response = r#"{"runs":[],"next_page_token":null}"#;
+    let output = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    assert_eq!(Get::next_page(&next, &output), None);
+  }
+}
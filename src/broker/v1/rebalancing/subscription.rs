@@ -0,0 +1,197 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::SystemTime;
+
+use http::Method;
+use http_endpoint::Bytes;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Error as JsonError;
+use serde_json::to_vec as to_json;
+
+use time_util::optional_system_time_from_str;
+use time_util::system_time_from_str;
+
+use crate::broker::v1::account;
+use crate::broker::v1::rebalancing::portfolio;
+use crate::Str;
+
+
+Id! {
+  /// An ID uniquely identifying a subscription of an account to a model
+  /// portfolio.
+  #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+  pub struct Id(pub Uuid);
+}
+
+
+/// A subscription of an account to a model portfolio.
+///
+/// Subscribed accounts get rebalanced towards the portfolio's weights
+/// whenever one of its rebalance conditions is met.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub struct Subscription {
+  /// The subscription's ID.
+  #[serde(rename = "id")]
+  pub id: Id,
+  /// The ID of the subscribed account.
+  #[serde(rename = "account_id")]
+  pub account_id: account::Id,
+  /// The ID of the portfolio subscribed to.
+  #[serde(rename = "portfolio_id")]
+  pub portfolio_id: portfolio::Id,
+  /// The time the subscription was created at.
+  #[serde(rename = "created_at", deserialize_with = "system_time_from_str")]
+  pub created_at: SystemTime,
+  /// The time the account was last rebalanced at, if any.
+  #[serde(
+    rename = "last_rebalanced_at",
+    default,
+    deserialize_with = "optional_system_time_from_str"
+  )]
+  pub last_rebalanced_at: Option<SystemTime>,
+}
+
+
+/// A POST request to be made to the /v1/rebalancing/subscriptions
+/// endpoint.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+pub struct SubscriptionReq {
+  /// The ID of the account to subscribe.
+  #[serde(rename = "account_id")]
+  pub account_id: account::Id,
+  /// The ID of the portfolio to subscribe to.
+  #[serde(rename = "portfolio_id")]
+  pub portfolio_id: portfolio::Id,
+}
+
+
+Endpoint! {
+  /// The representation of a POST request to the
+  /// /v1/rebalancing/subscriptions endpoint.
+  pub Post(SubscriptionReq),
+  Ok => Subscription, [
+    /// The subscription was created successfully.
+    /* 200 */ OK,
+  ],
+  Err => PostError, [
+    /// Some data in the request was invalid.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+    /// The account is subscribed to a portfolio already.
+    /* 409 */ CONFLICT => Conflict,
+    /// Some data in the request could not be processed.
+    /* 422 */ UNPROCESSABLE_ENTITY => Unprocessable,
+  ]
+  Api => Broker,
+
+  fn method() -> Method {
+    Method::POST
+  }
+
+  fn path(_input: &Self::Input) -> Str {
+    "/v1/rebalancing/subscriptions".into()
+  }
+
+  fn body(input: &Self::Input) -> Result<Bytes, JsonError> {
+    let json = to_json(input)?;
+    let bytes = Bytes::from(json);
+    Ok(bytes)
+  }
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v1/rebalancing/subscriptions/<subscription-id> endpoint.
+  pub Get(Id),
+  Ok => Subscription, [
+    /// The subscription was retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// No subscription was found with the given ID.
+    /* 404 */ NOT_FOUND => NotFound,
+  ]
+  Api => Broker,
+
+  fn path(input: &Self::Input) -> Str {
+    format!("/v1/rebalancing/subscriptions/{}", input.to_hyphenated_ref()).into()
+  }
+}
+
+
+Endpoint! {
+  /// The representation of a DELETE request to the
+  /// /v1/rebalancing/subscriptions/<subscription-id> endpoint.
+  pub Delete(Id),
+  Ok => (), [
+    /// The subscription was deleted successfully.
+    /* 204 */ NO_CONTENT,
+  ],
+  Err => DeleteError, [
+    /// No subscription was found with the given ID.
+    /* 404 */ NOT_FOUND => NotFound,
+  ]
+  Api => Broker,
+
+  fn method() -> Method {
+    Method::DELETE
+  }
+
+  fn path(input: &Self::Input) -> Str {
+    format!("/v1/rebalancing/subscriptions/{}", input.to_hyphenated_ref()).into()
+  }
+
+  fn parse(body: &[u8]) -> Result<Self::Output, Self::Error> {
+    debug_assert_eq!(body, b"");
+    Ok(())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::str::FromStr;
+
+  use http_endpoint::Endpoint;
+
+  use serde_json::from_slice as from_json_slice;
+  use serde_json::from_str as from_json;
+  use serde_json::Value;
+
+  use test_env_log::test;
+
+
+  /// Check that we can serialize a subscription request and parse the
+  /// resulting subscription.
+  #[test]
+  fn subscribe() {
+    let request = SubscriptionReq {
+      account_id: account::Id::from_str("bf2b0f93-f296-4276-a9cf-288586cf4fb7").unwrap(),
+      portfolio_id: portfolio::Id::from_str("2d49d00e-ab1c-4014-89d8-70c5f64df2fc").unwrap(),
+    };
+    let body = Post::body(&request).unwrap();
+    let value = from_json_slice::<Value>(&body).unwrap();
+    assert_eq!(value["account_id"], "bf2b0f93-f296-4276-a9cf-288586cf4fb7");
+    assert_eq!(
+      value["portfolio_id"],
+      "2d49d00e-ab1c-4014-89d8-70c5f64df2fc"
+    );
+
+    let response = r#"{
+  "id": "0d18ab0e-b1d6-4a1b-a1f3-6e8b2ee3a1f4",
+  "account_id": "bf2b0f93-f296-4276-a9cf-288586cf4fb7",
+  "portfolio_id": "2d49d00e-ab1c-4014-89d8-70c5f64df2fc",
+  "created_at": "2022-08-07T19:37:46.575855Z",
+  "last_rebalanced_at": null
+}"#;
+    let subscription = from_json::<<Post as Endpoint>::Output>(response).unwrap();
+    assert_eq!(subscription.account_id, request.account_id);
+    assert_eq!(subscription.portfolio_id, request.portfolio_id);
+    assert_eq!(subscription.last_rebalanced_at, None);
+  }
+}
@@ -0,0 +1,139 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_urlencoded::to_string as to_query;
+
+use crate::broker::v1::account;
+use crate::broker::v1::rebalancing::portfolio;
+use crate::broker::v1::rebalancing::subscription::Subscription;
+use crate::paged::next_page_by_token;
+use crate::paged::Paged;
+use crate::Str;
+
+
+/// A GET request to be made to the /v1/rebalancing/subscriptions
+/// endpoint.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct SubscriptionsReq {
+  /// Only include subscriptions of the account with the given ID.
+  #[serde(rename = "account_id", skip_serializing_if = "Option::is_none")]
+  pub account_id: Option<account::Id>,
+  /// Only include subscriptions to the portfolio with the given ID.
+  #[serde(rename = "portfolio_id", skip_serializing_if = "Option::is_none")]
+  pub portfolio_id: Option<portfolio::Id>,
+  /// The maximum number of subscriptions to return.
+  #[serde(rename = "limit", skip_serializing_if = "Option::is_none")]
+  pub limit: Option<usize>,
+  /// If provided, this token is used for retrieving the next page of
+  /// subscriptions, following a previous request.
+  #[serde(rename = "page_token", skip_serializing_if = "Option::is_none")]
+  pub page_token: Option<String>,
+}
+
+
+/// A page of subscriptions as returned by the
+/// /v1/rebalancing/subscriptions endpoint.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Subscriptions {
+  /// The list of returned subscriptions.
+  #[serde(rename = "subscriptions", default)]
+  pub subscriptions: Vec<Subscription>,
+  /// The token to provide to a request to get the next page of
+  /// subscriptions for this request.
+  #[serde(rename = "next_page_token", default)]
+  pub next_page_token: Option<String>,
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v1/rebalancing/subscriptions endpoint.
+  pub Get(SubscriptionsReq),
+  Ok => Subscriptions, [
+    /// The subscriptions were retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// Some data in the request was invalid.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+  ]
+  Api => Broker,
+
+  fn path(_input: &Self::Input) -> Str {
+    "/v1/rebalancing/subscriptions".into()
+  }
+
+  fn query(input: &Self::Input) -> Option<Str> {
+    // TODO: Realistically there should be no way for this unwrap to
+    //       ever panic because our conversion to strings should not be
+    //       fallible. But still, ideally we would not have to unwrap.
+    Some(to_query(input).unwrap().into())
+  }
+}
+
+impl Paged for Get {
+  fn next_page(input: &Self::Input, output: &Self::Output) -> Option<Self::Input> {
+    next_page_by_token(input, output.next_page_token.as_deref(), |input| {
+      &mut input.page_token
+    })
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::str::FromStr as _;
+
+  use http_endpoint::Endpoint;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+
+  /// Check that we emit the expected query for listing subscriptions.
+  #[test]
+  fn emit_query() {
+    let request = SubscriptionsReq {
+      account_id: Some(account::Id::from_str("bf2b0f93-f296-4276-a9cf-288586cf4fb7").unwrap()),
+      limit: Some(50),
+      ..Default::default()
+    };
+    assert_eq!(
+      Get::query(&request).unwrap(),
+      "account_id=bf2b0f93-f296-4276-a9cf-288586cf4fb7&limit=50"
+    );
+  }
+
+  /// Check that we can parse a page of subscriptions.
+  #[test]
+  fn parse_subscriptions() {
+    let response = r#"{"subscriptions":[],"next_page_token":null}"#;
+    let subscriptions = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    assert!(subscriptions.subscriptions.is_empty());
+    assert_eq!(subscriptions.next_page_token, None);
+  }
+
+  /// Check that we request the next page of subscriptions only if the
+  /// response reports a page token.
+  #[test]
+  fn next_subscriptions_page() {
+    let request = SubscriptionsReq {
+      limit: Some(10),
+      ..Default::default()
+    };
+    let response = r#"{"subscriptions":[],"next_page_token":"c3Viczox"}"#;
+    let output = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    let next = Get::next_page(&request, &output).unwrap();
+    assert_eq!(next.page_token.as_deref(), Some("c3Viczox"));
+    assert_eq!(next.limit, Some(10));
+
+    let response = r#"{"subscriptions":[],"next_page_token":null}"#;
+    let output = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    assert_eq!(Get::next_page(&next, &output), None);
+  }
+}
@@ -0,0 +1,90 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::api::v2::account::Account;
+use crate::broker::v1::account;
+use crate::Str;
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v1/trading/accounts/<account-id>/account endpoint.
+  ///
+  /// The reported trading account contains the account's buying power,
+  /// margin requirements, and day trading state, i.e., the limits
+  /// imposed on trading.
+  pub Get(account::Id),
+  Ok => Account, [
+    /// The trading account was retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// No account was found with the given ID.
+    /* 404 */ NOT_FOUND => NotFound,
+  ]
+  Api => Broker,
+
+  fn path(input: &Self::Input) -> Str {
+    format!("/v1/trading/accounts/{}/account", input.to_hyphenated_ref()).into()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::str::FromStr as _;
+
+  use http_endpoint::Endpoint;
+
+  use num_decimal::Num;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+
+  /// Check that we can parse the trading account of an account.
+  #[test]
+  fn parse_trading_account() {
+    let response = r#"{
+  "id": "bf2b0f93-f296-4276-a9cf-288586cf4fb7",
+  "account_number": "601842165",
+  "status": "ACTIVE",
+  "currency": "USD",
+  "buying_power": "40000",
+  "regt_buying_power": "40000",
+  "daytrading_buying_power": "0",
+  "non_marginable_buying_power": "20000",
+  "cash": "20000",
+  "cash_withdrawable": "20000",
+  "portfolio_value": "20000",
+  "pattern_day_trader": false,
+  "trading_blocked": false,
+  "transfers_blocked": false,
+  "account_blocked": false,
+  "created_at": "2022-08-08T19:27:09.760854Z",
+  "trade_suspended_by_user": false,
+  "multiplier": "2",
+  "shorting_enabled": true,
+  "equity": "20000",
+  "last_equity": "20000",
+  "long_market_value": "0",
+  "short_market_value": "0",
+  "initial_margin": "0",
+  "maintenance_margin": "0",
+  "last_maintenance_margin": "0",
+  "sma": "0",
+  "daytrade_count": 0
+}"#;
+
+    let account = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    let id = account::Id::from_str("bf2b0f93-f296-4276-a9cf-288586cf4fb7").unwrap();
+    assert_eq!(account.id.0, id.0);
+    assert_eq!(account.buying_power, Num::from(40000));
+    assert_eq!(account.multiplier, Num::from(2));
+    assert!(account.shorting_enabled);
+    assert_eq!(account.daytrade_count, 0);
+  }
+}
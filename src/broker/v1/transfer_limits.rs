@@ -0,0 +1,95 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use num_decimal::Num;
+
+use serde::Deserialize;
+
+use crate::broker::v1::account;
+use crate::Str;
+
+
+/// The limits applying to transfers into and out of an account.
+///
+/// Limits are reported only if they apply to the account.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct TransferLimits {
+  /// The maximum amount that can be deposited per day.
+  #[serde(rename = "daily_deposit_limit", default)]
+  pub daily_deposit_limit: Option<Num>,
+  /// The amount that can still be deposited today.
+  #[serde(rename = "daily_deposit_remaining", default)]
+  pub daily_deposit_remaining: Option<Num>,
+  /// The maximum amount that can be withdrawn per day.
+  #[serde(rename = "daily_withdrawal_limit", default)]
+  pub daily_withdrawal_limit: Option<Num>,
+  /// The amount that can still be withdrawn today.
+  #[serde(rename = "daily_withdrawal_remaining", default)]
+  pub daily_withdrawal_remaining: Option<Num>,
+  /// The amount currently available for withdrawal.
+  #[serde(rename = "withdrawable_cash", default)]
+  pub withdrawable_cash: Option<Num>,
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v1/accounts/<account-id>/transfers/limits endpoint.
+  pub Get(account::Id),
+  Ok => TransferLimits, [
+    /// The transfer limits were retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// No account was found with the given ID.
+    /* 404 */ NOT_FOUND => NotFound,
+  ]
+  Api => Broker,
+
+  fn path(input: &Self::Input) -> Str {
+    format!("/v1/accounts/{}/transfers/limits", input.to_hyphenated_ref()).into()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::str::FromStr as _;
+
+  use http_endpoint::Endpoint;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+
+  /// Check that we emit the expected path for retrieving transfer
+  /// limits.
+  #[test]
+  fn emit_path() {
+    let id = account::Id::from_str("b9b19618-22dd-4e80-8432-fc9e1ba0b27d").unwrap();
+    assert_eq!(
+      Get::path(&id),
+      "/v1/accounts/b9b19618-22dd-4e80-8432-fc9e1ba0b27d/transfers/limits"
+    );
+  }
+
+  /// Check that we can parse transfer limits, some of which may be
+  /// missing.
+  #[test]
+  fn parse_limits() {
+    let response = r#"{
+  "daily_deposit_limit": "50000",
+  "daily_deposit_remaining": "49000",
+  "withdrawable_cash": "1000.5"
+}"#;
+
+    let limits = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    assert_eq!(limits.daily_deposit_limit, Some(Num::from(50000)));
+    assert_eq!(limits.daily_deposit_remaining, Some(Num::from(49000)));
+    assert_eq!(limits.daily_withdrawal_limit, None);
+    assert_eq!(limits.withdrawable_cash, Some(Num::new(10005, 10)));
+  }
+}
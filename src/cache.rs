@@ -0,0 +1,188 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use http_endpoint::Endpoint;
+
+use crate::ApiEndpoint;
+use crate::Client;
+use crate::RequestError;
+
+
+/// The map of cached responses, keyed by request input, along with the
+/// time each was retrieved at.
+type Entries<R> = HashMap<<R as Endpoint>::Input, (Instant, <R as Endpoint>::Output)>;
+
+
+/// An opt-in cache for the responses of an endpoint.
+///
+/// A `Cache` is meant to be used for endpoints serving data that
+/// changes only slowly, such as the list of assets or the market
+/// calendar. Responses are kept for a configurable amount of time,
+/// after which they are retrieved anew. Entries can also be invalidated
+/// manually.
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use apca::api::v2::clock;
+/// # use apca::cache::Cache;
+/// # use apca::ApiInfo;
+/// # use apca::Client;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = Client::new(ApiInfo::from_env()?);
+/// let cache = Cache::<clock::Get>::new(Duration::from_secs(60));
+/// // Only the first call results in a request to the API.
+/// let clock = cache.issue(&client, ()).await?;
+/// let clock = cache.issue(&client, ()).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Cache<R>
+where
+  R: Endpoint,
+{
+  /// The time after which an entry is considered stale.
+  ttl: Duration,
+  /// The cached responses along with the time they were retrieved at.
+  entries: Mutex<Entries<R>>,
+}
+
+impl<R> Cache<R>
+where
+  R: ApiEndpoint,
+  R::Input: Clone + Eq + Hash,
+  R::Output: Clone,
+{
+  /// Create a new `Cache` keeping responses for the given amount of
+  /// time.
+  pub fn new(ttl: Duration) -> Self {
+    Self {
+      ttl,
+      entries: Mutex::new(HashMap::new()),
+    }
+  }
+
+  /// Retrieve the response to the request with the given input,
+  /// consulting the cache first.
+  ///
+  /// If no fresh response is cached, the request is issued using the
+  /// provided client and the response is cached on success.
+  pub async fn issue(
+    &self,
+    client: &Client,
+    input: R::Input,
+  ) -> Result<R::Output, RequestError<R::Error>> {
+    if let Some(output) = self.lookup(&input, Instant::now()) {
+      return Ok(output)
+    }
+
+    let output = client.issue::<R>(input.clone()).await?;
+    self.store(input, output.clone(), Instant::now());
+    Ok(output)
+  }
+
+  /// Look up a cached response that is still fresh at time `now`.
+  fn lookup(&self, input: &R::Input, now: Instant) -> Option<R::Output> {
+    let entries = self.entries.lock().unwrap();
+    entries.get(input).and_then(|(time, output)| {
+      if now.saturating_duration_since(*time) < self.ttl {
+        Some(output.clone())
+      } else {
+        None
+      }
+    })
+  }
+
+  /// Cache a response retrieved at time `time`.
+  fn store(&self, input: R::Input, output: R::Output, time: Instant) {
+    let _ = self.entries.lock().unwrap().insert(input, (time, output));
+  }
+
+  /// Invalidate the cached response to the request with the given
+  /// input, if any.
+  pub fn invalidate(&self, input: &R::Input) {
+    let _ = self.entries.lock().unwrap().remove(input);
+  }
+
+  /// Invalidate all cached responses.
+  pub fn clear(&self) {
+    self.entries.lock().unwrap().clear()
+  }
+}
+
+impl<R> Debug for Cache<R>
+where
+  R: Endpoint,
+{
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    let entries = self.entries.lock().map(|entries| entries.len()).ok();
+    fmt
+      .debug_struct("Cache")
+      .field("ttl", &self.ttl)
+      .field("entries", &entries)
+      .finish()
+  }
+}
+
+
+#[cfg(all(test, feature = "trading"))]
+mod tests {
+  use super::*;
+
+  use time_util::parse_system_time_from_date_str;
+
+  use crate::api::v2::calendar;
+  use crate::api::v2::calendar::CalendarReq;
+
+
+  #[test]
+  fn lookup_and_expire() {
+    let ttl = Duration::from_secs(10);
+    let cache = Cache::<calendar::Get>::new(ttl);
+    let input = CalendarReq {
+      start: parse_system_time_from_date_str("2021-01-04").unwrap(),
+      end: parse_system_time_from_date_str("2021-01-08").unwrap(),
+    };
+
+    let now = Instant::now();
+    assert_eq!(cache.lookup(&input, now), None);
+
+    cache.store(input, Vec::new(), now);
+    assert_eq!(cache.lookup(&input, now), Some(Vec::new()));
+    assert_eq!(cache.lookup(&input, now + ttl / 2), Some(Vec::new()));
+    assert_eq!(cache.lookup(&input, now + ttl), None);
+  }
+
+  #[test]
+  fn invalidate_entries() {
+    let cache = Cache::<calendar::Get>::new(Duration::from_secs(60));
+    let input1 = CalendarReq {
+      start: parse_system_time_from_date_str("2021-01-04").unwrap(),
+      end: parse_system_time_from_date_str("2021-01-08").unwrap(),
+    };
+    let input2 = CalendarReq {
+      start: parse_system_time_from_date_str("2021-02-01").unwrap(),
+      end: parse_system_time_from_date_str("2021-02-05").unwrap(),
+    };
+
+    let now = Instant::now();
+    cache.store(input1, Vec::new(), now);
+    cache.store(input2, Vec::new(), now);
+
+    cache.invalidate(&input1);
+    assert_eq!(cache.lookup(&input1, now), None);
+    assert_eq!(cache.lookup(&input2, now), Some(Vec::new()));
+
+    cache.clear();
+    assert_eq!(cache.lookup(&input2, now), None);
+  }
+}
@@ -0,0 +1,225 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::error::Error as StdError;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::time::Duration;
+
+use tokio::time::sleep;
+use tokio::time::Instant;
+
+use tracing::debug;
+
+use crate::api::v2::order;
+use crate::Client;
+use crate::RequestError;
+
+
+/// The interval at which to poll the order while waiting for its
+/// cancellation to take effect.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+
+/// An error preventing the confirmation of an order's cancellation.
+#[derive(Debug)]
+pub enum CancelError {
+  /// The cancellation request failed.
+  Cancel(RequestError<order::DeleteError>),
+  /// The order could not be retrieved.
+  Get(RequestError<order::GetError>),
+  /// The order did not reach a terminal state in time. The order as
+  /// last retrieved is included.
+  Timeout(Box<order::Order>),
+}
+
+impl Display for CancelError {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    match self {
+      Self::Cancel(err) => write!(fmt, "failed to cancel order: {}", err),
+      Self::Get(err) => write!(fmt, "failed to retrieve order: {}", err),
+      Self::Timeout(order) => write!(
+        fmt,
+        "order {} did not reach terminal state in time (status: {:?})",
+        order.id, order.status
+      ),
+    }
+  }
+}
+
+impl StdError for CancelError {
+  fn source(&self) -> Option<&(dyn StdError + 'static)> {
+    match self {
+      Self::Cancel(err) => Some(err),
+      Self::Get(err) => Some(err),
+      Self::Timeout(..) => None,
+    }
+  }
+}
+
+
+impl Client {
+  /// Cancel the order with the given ID and wait for the cancellation
+  /// to take effect, returning the order in its final state.
+  ///
+  /// A successful cancellation request merely means that Alpaca
+  /// accepted it, not that the order is no longer working. This method
+  /// polls the order until its status is terminal (see
+  /// `order::Status::is_terminal`) or until `timeout` elapsed. Note
+  /// that the final order is not necessarily canceled: it may have
+  /// filled, for example, before the cancellation reached the
+  /// execution venue. An order that is no longer cancelable is not
+  /// considered an error, for the same reason.
+  pub async fn cancel_and_confirm(
+    &self,
+    id: order::Id,
+    timeout: Duration,
+  ) -> Result<order::Order, CancelError> {
+    let deadline = Instant::now() + timeout;
+
+    match self.issue::<order::Delete>(id).await {
+      Ok(()) | Err(RequestError::Endpoint(order::DeleteError::NotCancelable(..))) => (),
+      Err(err) => return Err(CancelError::Cancel(err)),
+    }
+
+    loop {
+      let order = self
+        .issue::<order::Get>(id)
+        .await
+        .map_err(CancelError::Get)?;
+      if order.status.is_terminal() {
+        break Ok(order)
+      }
+
+      if Instant::now() + POLL_INTERVAL > deadline {
+        break Err(CancelError::Timeout(Box::new(order)))
+      }
+
+      debug!(
+        id = display(&order.id),
+        status = debug(&order.status),
+        "waiting for order cancellation"
+      );
+      sleep(POLL_INTERVAL).await
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::sync::Arc;
+
+  use num_decimal::Num;
+
+  use test_env_log::test;
+
+  use uuid::Uuid;
+
+  use crate::simulator::Simulator;
+
+
+  /// The timeout to use when canceling orders.
+  const TIMEOUT: Duration = Duration::from_secs(5);
+
+
+  /// Create a simulated client for a market in which SPY trades at
+  /// 400.
+  fn client() -> (Client, Arc<Simulator>) {
+    let prices = |_symbol: &str| Some(Num::from(400));
+    let simulator = Arc::new(Simulator::new(Num::from(100_000), prices));
+    (Client::simulated(simulator.clone()), simulator)
+  }
+
+  /// Submit a limit order for SPY that will not fill immediately.
+  async fn submit_limit_order(client: &Client) -> order::Order {
+    let request = order::OrderReqInit {
+      type_: order::Type::Limit,
+      limit_price: Some(Num::from(300)),
+      ..Default::default()
+    }
+    .init("SPY", order::Side::Buy, 1);
+    let order = client.issue::<order::Post>(request).await.unwrap();
+    assert_eq!(order.status, order::Status::New);
+    order
+  }
+
+  /// Check that canceling an open order reports it as canceled.
+  #[test(tokio::test)]
+  async fn cancel_open_order() {
+    let (client, _) = client();
+    let order = submit_limit_order(&client).await;
+
+    let order = client.cancel_and_confirm(order.id, TIMEOUT).await.unwrap();
+    assert_eq!(order.status, order::Status::Canceled);
+  }
+
+  /// Check that canceling an order that is already terminal reports
+  /// it in its final state.
+  #[test(tokio::test)]
+  async fn cancel_terminal_order() {
+    let (client, _) = client();
+    let order = submit_limit_order(&client).await;
+    let () = client.issue::<order::Delete>(order.id).await.unwrap();
+
+    let order = client.cancel_and_confirm(order.id, TIMEOUT).await.unwrap();
+    assert_eq!(order.status, order::Status::Canceled);
+  }
+
+  /// Check that an order no longer being cancelable, because it got
+  /// filled, is not reported as an error.
+  #[test(tokio::test)]
+  async fn cancel_filled_order() {
+    let (client, _) = client();
+    let request = order::OrderReqInit::default().init("SPY", order::Side::Buy, 1);
+    let order = client.issue::<order::Post>(request).await.unwrap();
+    assert_eq!(order.status, order::Status::Filled);
+
+    let err = client.issue::<order::Delete>(order.id).await.unwrap_err();
+    assert!(matches!(
+      err,
+      RequestError::Endpoint(order::DeleteError::NotCancelable(..))
+    ));
+
+    let order = client.cancel_and_confirm(order.id, TIMEOUT).await.unwrap();
+    assert_eq!(order.status, order::Status::Filled);
+  }
+
+  /// Check that a failure to cancel an unknown order is reported.
+  #[test(tokio::test)]
+  async fn cancel_unknown_order() {
+    let (client, _) = client();
+    let id = order::Id(Uuid::from_u128(1337));
+
+    let err = client.cancel_and_confirm(id, TIMEOUT).await.unwrap_err();
+    assert!(matches!(
+      err,
+      CancelError::Cancel(RequestError::Endpoint(order::DeleteError::NotFound(..)))
+    ));
+  }
+
+  /// Check that an order not reaching a terminal state in time is
+  /// reported in its last known state.
+  #[test(tokio::test)]
+  async fn cancel_order_timeout() {
+    let (client, simulator) = client();
+    let order = submit_limit_order(&client).await;
+    let () = simulator.hold_cancellations(true);
+
+    let err = client
+      .cancel_and_confirm(order.id, Duration::from_millis(100))
+      .await
+      .unwrap_err();
+    match err {
+      CancelError::Timeout(order) => assert_eq!(order.status, order::Status::PendingCancel),
+      _ => panic!("Received unexpected error: {:?}", err),
+    }
+
+    let () = simulator.process();
+    let order = client.cancel_and_confirm(order.id, TIMEOUT).await.unwrap();
+    assert_eq!(order.status, order::Status::Canceled);
+  }
+}
@@ -1,62 +1,404 @@
 // Copyright (C) 2019-2021 Daniel Mueller <deso@posteo.net>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::future::Future;
+use std::io::Result as IoResult;
+use std::net::SocketAddr;
 use std::str::from_utf8;
+use std::sync::Arc;
+use std::sync::RwLock;
+use std::time::Duration;
 
+use futures::future::BoxFuture;
+use futures::stream::iter;
+#[cfg(feature = "streaming")]
 use futures::stream::Stream;
+use futures::FutureExt;
+use futures::StreamExt;
 
+use http::header::AUTHORIZATION;
 use http::request::Builder as HttpRequestBuilder;
+use http::Error as HttpError;
+use http::HeaderValue;
+use http::Method;
 use http::Request;
+use http::StatusCode;
 use http_endpoint::Endpoint;
 
 use hyper::Body;
 use hyper::body::to_bytes;
+use hyper::body::Bytes;
 use hyper::Client as HttpClient;
 use hyper::client::Builder as HttpClientBuilder;
 use hyper::client::HttpConnector;
+use hyper::Error as HyperError;
 use hyper_tls::HttpsConnector;
 
+#[cfg(feature = "http2")]
+use native_tls::TlsConnector;
+
+use serde::de::Error as DeError;
 use serde_json::Error as JsonError;
 
+use tokio::time::sleep;
+
 use tracing::debug;
+#[cfg(feature = "streaming")]
 use tracing::instrument;
 use tracing::span;
 use tracing::trace;
 use tracing::Level;
 use tracing_futures::Instrument;
 
+#[cfg(feature = "streaming")]
 use websocket_util::tungstenite::Error as WebSocketError;
 
 use url::Url;
 
+#[cfg(feature = "trading")]
+use crate::api::API_BASE_URL;
+#[cfg(feature = "trading")]
+use crate::api::API_STREAM_URL;
+#[cfg(any(all(test, feature = "broker"), feature = "trading"))]
+use crate::api::BROKER_BASE_URL;
+#[cfg(any(test, feature = "trading"))]
+use crate::api::DATA_BASE_URL;
+#[cfg(feature = "trading")]
+use crate::api::DATA_STREAM_URL;
 use crate::api::HDR_KEY_ID;
 use crate::api::HDR_SECRET;
 use crate::api_info::ApiInfo;
+use crate::audit::AuditLog;
+#[cfg(all(feature = "data", feature = "trading"))]
+use crate::data::v2::Feed;
+#[cfg(feature = "trading")]
+use crate::dry_run;
+use crate::endpoint::Api;
+use crate::endpoint::ApiEndpoint;
 use crate::error::RequestError;
+#[cfg(feature = "streaming")]
 use crate::Error;
+#[cfg(feature = "streaming")]
 use crate::events::EventStream;
-use crate::events::stream;
+#[cfg(feature = "streaming")]
+use crate::events::stream_confirmed_with;
+#[cfg(feature = "streaming")]
+use crate::events::stream_raw_confirmed_with;
+#[cfg(feature = "streaming")]
+use crate::events::RawEvent;
+#[cfg(feature = "streaming")]
+use crate::events::Subscription;
+use crate::resolver::Resolve;
+use crate::resolver::Resolver;
+#[cfg(feature = "trading")]
+use crate::simulator::Simulator;
+use crate::socket::SocketOptions;
+use crate::strict::unknown_fields;
+
+
+/// The initial duration to back off for when a request ran into the
+/// rate limit as part of `Client::issue_all`.
+const RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(1);
+/// The maximum number of times a request issued as part of
+/// `Client::issue_all` is retried after running into the rate limit.
+const RATE_LIMIT_RETRIES: usize = 5;
+/// The default time to wait for a connection attempt using the
+/// preferred address family to succeed before trying the other one in
+/// parallel, as suggested by RFC 6555.
+const HAPPY_EYEBALLS_TIMEOUT: Duration = Duration::from_millis(300);
+
+
+/// The type of the hook invoked for retrieving a fresh OAuth token.
+type Refresh = Arc<dyn Fn() -> BoxFuture<'static, Option<String>> + Send + Sync>;
+
+
+/// The OAuth state shared by a `Client` and its clones.
+struct OAuth {
+  /// The token currently in use.
+  token: RwLock<Option<String>>,
+  /// The hook to invoke for retrieving a fresh token.
+  refresh: Option<Refresh>,
+}
+
+impl OAuth {
+  /// Retrieve the `Authorization` header value for the current token,
+  /// if any.
+  fn header(&self) -> Option<HeaderValue> {
+    self
+      .token
+      .read()
+      .unwrap()
+      .as_ref()
+      .and_then(|token| bearer(token))
+  }
+}
+
+impl Debug for OAuth {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    fmt
+      .debug_struct("OAuth")
+      .field("token", &"<redacted>")
+      .field("refresh", &self.refresh.is_some())
+      .finish()
+  }
+}
+
+
+/// Append the given path to that of a URL.
+///
+/// Any path prefix of the URL, e.g., as used by a proxy, is retained.
+fn append_path(url: &mut Url, path: &str) {
+  let path = format!(
+    "{}/{}",
+    url.path().trim_end_matches('/'),
+    path.trim_start_matches('/')
+  );
+  url.set_path(&path);
+}
+
+
+/// Create the `Authorization` header value for the given OAuth token.
+fn bearer(token: &str) -> Option<HeaderValue> {
+  HeaderValue::try_from(format!("Bearer {}", token)).ok()
+}
+
+
+/// Create the `Authorization` header value for HTTP Basic
+/// authentication using the given key ID and secret.
+fn basic(key_id: &str, secret: &str) -> Option<HeaderValue> {
+  let credentials = base64::encode(format!("{}:{}", key_id, secret));
+  let mut header = HeaderValue::try_from(format!("Basic {}", credentials)).ok()?;
+  header.set_sensitive(true);
+  Some(header)
+}
 
 
 /// A builder for creating customized `Client` objects.
-#[derive(Debug)]
 pub struct Builder {
   builder: HttpClientBuilder,
+  audit: Option<Arc<AuditLog>>,
+  strict: bool,
+  #[cfg(feature = "trading")]
+  dry_run: bool,
+  basic_auth: bool,
+  token: Option<String>,
+  refresh: Option<Refresh>,
+  resolver: Option<Resolve>,
+  happy_eyeballs_timeout: Option<Duration>,
+  socket: SocketOptions,
+  #[cfg(feature = "http2")]
+  http2: bool,
 }
 
 impl Builder {
+  /// Record all requests issued, responses received, and stream
+  /// events received in the given audit log.
+  pub fn audit_log(&mut self, audit: Arc<AuditLog>) -> &mut Self {
+    self.audit = Some(audit);
+    self
+  }
+
+  /// Enable or disable strict deserialization of responses.
+  ///
+  /// In strict mode, responses containing fields that are not known
+  /// to the corresponding type are reported as a JSON error instead of
+  /// these fields being ignored silently. That is useful for catching
+  /// changes to the API's schema, e.g., in tests, but should not be
+  /// used in production, where new fields being added on the server
+  /// side would break otherwise working requests. Strict mode is off
+  /// by default.
+  pub fn strict(&mut self, strict: bool) -> &mut Self {
+    self.strict = strict;
+    self
+  }
+
+  /// Enable or disable dry-run mode for requests changing orders and
+  /// positions.
+  ///
+  /// In dry-run mode, order submissions are validated locally and,
+  /// instead of being sent, answered with a synthetic order in the
+  /// `Accepted` state. Invalid submissions are rejected with the
+  /// `InvalidInput` error the API would report. Cancellations are
+  /// reported as successful without being sent, while order
+  /// replacements and position liquidations are refused. All other
+  /// requests are sent as usual. Each intercepted request is logged.
+  /// Dry-run mode is off by default.
+  #[cfg(feature = "trading")]
+  pub fn dry_run(&mut self, dry_run: bool) -> &mut Self {
+    self.dry_run = dry_run;
+    self
+  }
+
+  /// Enable or disable HTTP Basic authentication of requests.
+  ///
+  /// By default, requests carry the key ID and secret of the `ApiInfo`
+  /// object in dedicated headers, as expected by the Trading and
+  /// market data APIs. The Broker API instead expects them as user
+  /// name and password, respectively, of HTTP Basic authentication.
+  /// An OAuth token, if set, takes precedence. Streams are not
+  /// affected by this setting.
+  pub fn basic_auth(&mut self, enable: bool) -> &mut Self {
+    self.basic_auth = enable;
+    self
+  }
+
   /// Adjust the maximum number of idle connections per host.
   pub fn max_idle_per_host(&mut self, max_idle: usize) -> &mut Self {
     let _ = self.builder.pool_max_idle_per_host(max_idle);
     self
   }
 
+  /// Resolve host names using the given function instead of the
+  /// system's resolver.
+  ///
+  /// That can be used to work around resolvers that are slow to
+  /// respond or to pin the API to certain addresses. The function
+  /// receives the host name to resolve and reports the addresses to
+  /// try connecting to; their ports are ignored. The resolver is only
+  /// used for requests to the REST API.
+  pub fn resolver<F, R>(&mut self, resolve: F) -> &mut Self
+  where
+    F: Fn(&str) -> R + Send + Sync + 'static,
+    R: Future<Output = IoResult<Vec<SocketAddr>>> + Send + 'static,
+  {
+    self.resolver = Some(Arc::new(move |name| resolve(name).boxed()));
+    self
+  }
+
+  /// Set the time to wait for a connection attempt to an address of
+  /// the preferred family (IPv6 or IPv4) to succeed before trying one
+  /// of the other family in parallel, as per the "happy eyeballs"
+  /// algorithm.
+  ///
+  /// Dual-stack connection attempts are enabled by default, with a
+  /// timeout of 300ms. `None` disables them, trying each address in
+  /// turn.
+  pub fn happy_eyeballs_timeout(&mut self, timeout: Option<Duration>) -> &mut Self {
+    self.happy_eyeballs_timeout = timeout;
+    self
+  }
+
+  /// Set the options to use for the TCP connections made to the API,
+  /// both for requests and streams.
+  pub fn socket_options(&mut self, options: SocketOptions) -> &mut Self {
+    self.socket = options;
+    self
+  }
+
+  /// Enable or disable the use of HTTP/2 for requests.
+  ///
+  /// With HTTP/2, concurrent requests to the same host are multiplexed
+  /// onto a single connection, instead of each of them requiring a
+  /// connection of its own. HTTP/2 is requested from the server during
+  /// the TLS handshake and then used exclusively; requests to servers
+  /// not supporting it fail. HTTP/2 is off by default.
+  #[cfg(feature = "http2")]
+  pub fn http2(&mut self, enable: bool) -> &mut Self {
+    let _ = self.builder.http2_only(enable);
+    self.http2 = enable;
+    self
+  }
+
+  /// Authenticate requests using the given OAuth token instead of the
+  /// key ID and secret of the `ApiInfo` object.
+  ///
+  /// The token is only used for requests to the REST API; streams
+  /// still authenticate using the key ID and secret.
+  pub fn oauth_token<S>(&mut self, token: S) -> &mut Self
+  where
+    S: Into<String>,
+  {
+    self.token = Some(token.into());
+    self
+  }
+
+  /// Set a hook to invoke for retrieving a fresh OAuth token when a
+  /// request got rejected as unauthorized.
+  ///
+  /// If the hook provides a token, it replaces the one in use and the
+  /// request is retried once. If it does not, the original response is
+  /// reported. The hook is shared by all clones of the built `Client`.
+  pub fn oauth_refresh<F, R>(&mut self, refresh: F) -> &mut Self
+  where
+    F: Fn() -> R + Send + Sync + 'static,
+    R: Future<Output = Option<String>> + Send + 'static,
+  {
+    self.refresh = Some(Arc::new(move || refresh().boxed()));
+    self
+  }
+
   /// Build the final `Client` object.
   pub fn build(&self, api_info: ApiInfo) -> Client {
-    let https = HttpsConnector::new();
+    let resolver = match &self.resolver {
+      Some(resolve) => Resolver::Custom(resolve.clone()),
+      None => Resolver::default(),
+    };
+    let mut http = HttpConnector::new_with_resolver(resolver);
+    http.enforce_http(false);
+    http.set_happy_eyeballs_timeout(self.happy_eyeballs_timeout);
+    http.set_keepalive(self.socket.keepalive);
+    http.set_nodelay(self.socket.nodelay);
+    http.set_connect_timeout(self.socket.connect_timeout);
+
+    #[cfg(feature = "http2")]
+    let https = if self.http2 {
+      let tls = TlsConnector::builder()
+        .request_alpns(&["h2"])
+        .build()
+        .unwrap_or_else(|err| panic!("failed to create TLS connector: {}", err));
+      HttpsConnector::from((http, tls.into()))
+    } else {
+      HttpsConnector::new_with_connector(http)
+    };
+    #[cfg(not(feature = "http2"))]
+    let https = HttpsConnector::new_with_connector(http);
     let client = self.builder.build(https);
+    let oauth = if self.token.is_some() || self.refresh.is_some() {
+      Some(Arc::new(OAuth {
+        token: RwLock::new(self.token.clone()),
+        refresh: self.refresh.clone(),
+      }))
+    } else {
+      None
+    };
+
+    Client {
+      api_info,
+      transport: Transport::Http(client),
+      audit: self.audit.clone(),
+      strict: self.strict,
+      #[cfg(feature = "trading")]
+      dry_run: self.dry_run,
+      basic_auth: self.basic_auth,
+      oauth,
+      socket: self.socket,
+    }
+  }
+}
 
-    Client { api_info, client }
+impl Debug for Builder {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    let mut debug = fmt.debug_struct("Builder");
+    let _ = debug
+      .field("builder", &self.builder)
+      .field("audit", &self.audit)
+      .field("strict", &self.strict)
+      .field("basic_auth", &self.basic_auth)
+      .field("token", &self.token.as_ref().map(|_| "<redacted>"))
+      .field("refresh", &self.refresh.is_some())
+      .field("resolver", &self.resolver.is_some())
+      .field("happy_eyeballs_timeout", &self.happy_eyeballs_timeout)
+      .field("socket", &self.socket);
+    #[cfg(feature = "trading")]
+    let _ = debug.field("dry_run", &self.dry_run);
+    #[cfg(feature = "http2")]
+    let _ = debug.field("http2", &self.http2);
+    debug.finish()
   }
 }
 
@@ -76,6 +418,18 @@ impl Default for Builder {
 
     Self {
       builder,
+      audit: None,
+      strict: false,
+      #[cfg(feature = "trading")]
+      dry_run: false,
+      basic_auth: false,
+      token: None,
+      refresh: None,
+      resolver: None,
+      happy_eyeballs_timeout: Some(HAPPY_EYEBALLS_TIMEOUT),
+      socket: SocketOptions::default(),
+      #[cfg(feature = "http2")]
+      http2: false,
     }
   }
 
@@ -83,6 +437,18 @@ impl Default for Builder {
   fn default() -> Self {
     Self {
       builder: HttpClient::builder(),
+      audit: None,
+      strict: false,
+      #[cfg(feature = "trading")]
+      dry_run: false,
+      basic_auth: false,
+      token: None,
+      refresh: None,
+      resolver: None,
+      happy_eyeballs_timeout: Some(HAPPY_EYEBALLS_TIMEOUT),
+      socket: SocketOptions::default(),
+      #[cfg(feature = "http2")]
+      http2: false,
     }
   }
 }
@@ -93,10 +459,32 @@ impl Default for Builder {
 /// primitives and also implements the `Trader` trait, which abstracts
 /// away the trading related functionality common among all supported
 /// services.
-#[derive(Debug)]
+///
+/// Cloning a `Client` is cheap: all clones share the same pool of
+/// connections.
+#[derive(Clone, Debug)]
 pub struct Client {
   api_info: ApiInfo,
-  client: HttpClient<HttpsConnector<HttpConnector>, Body>,
+  transport: Transport,
+  audit: Option<Arc<AuditLog>>,
+  strict: bool,
+  #[cfg(feature = "trading")]
+  dry_run: bool,
+  basic_auth: bool,
+  oauth: Option<Arc<OAuth>>,
+  #[cfg_attr(not(feature = "streaming"), allow(dead_code))]
+  socket: SocketOptions,
+}
+
+
+/// The means by which requests are issued.
+#[derive(Clone, Debug)]
+enum Transport {
+  /// Requests are sent to the API via HTTP.
+  Http(HttpClient<HttpsConnector<HttpConnector<Resolver>>, Body>),
+  /// Requests are handled by an in-process simulator.
+  #[cfg(feature = "trading")]
+  Simulated(Arc<Simulator>),
 }
 
 impl Client {
@@ -111,34 +499,116 @@ impl Client {
     Builder::default().build(api_info)
   }
 
+  /// Create a new `Client` handling all requests using the given
+  /// in-process simulator, without any network access.
+  ///
+  /// Please refer to `Simulator` for the endpoints supported.
+  #[cfg(feature = "trading")]
+  pub fn simulated(simulator: Arc<Simulator>) -> Self {
+    // The URLs are only used for constructing requests; no connections
+    // are ever made.
+    let api_info = ApiInfo {
+      base_url: Url::parse(API_BASE_URL).unwrap(),
+      stream_url: Url::parse(API_STREAM_URL).unwrap(),
+      data_base_url: Url::parse(DATA_BASE_URL).unwrap(),
+      data_stream_url: Url::parse(DATA_STREAM_URL).unwrap(),
+      #[cfg(feature = "data")]
+      data_feed: Feed::Iex,
+      broker_base_url: Url::parse(BROKER_BASE_URL).unwrap(),
+      key_id: String::new(),
+      secret: String::new(),
+    };
+
+    Self {
+      api_info,
+      transport: Transport::Simulated(simulator),
+      audit: None,
+      strict: false,
+      dry_run: false,
+      basic_auth: false,
+      oauth: None,
+      socket: SocketOptions::default(),
+    }
+  }
+
+  /// Create a new `Client` using the given `ApiInfo`, but sharing all
+  /// other state with this one.
+  ///
+  /// The derived client uses the same connection pool, audit log, and
+  /// settings. That makes it cheap to work with multiple sets of
+  /// credentials at once, e.g., for paper and live trading.
+  pub fn with_api_info(&self, api_info: ApiInfo) -> Self {
+    Self {
+      api_info,
+      ..self.clone()
+    }
+  }
+
+  /// Prepare the input to a request to the endpoint, filling in
+  /// defaults from the `ApiInfo`.
+  fn prepare<R>(&self, mut input: R::Input) -> R::Input
+  where
+    R: ApiEndpoint,
+  {
+    let () = R::prepare(&mut input, &self.api_info);
+    input
+  }
+
   /// Create a `Request` to the endpoint.
   fn request<R>(&self, input: &R::Input) -> Result<Request<Body>, R::Error>
   where
-    R: Endpoint,
+    R: ApiEndpoint,
   {
-    let mut url = R::base_url()
-      .map(|url| Url::parse(url.as_ref()).expect("endpoint definition contains invalid URL"))
-      .unwrap_or_else(|| self.api_info.base_url.clone());
-
-    url.set_path(&R::path(&input));
-    url.set_query(R::query(&input).as_ref().map(AsRef::as_ref));
+    let mut url = match R::api() {
+      Api::Trading => match R::base_url() {
+        Some(url) => Url::parse(url.as_ref()).expect("endpoint definition contains invalid URL"),
+        None => self.api_info.base_url.clone(),
+      },
+      Api::Data => self.api_info.data_base_url.clone(),
+      Api::Broker => self.api_info.broker_base_url.clone(),
+    };
 
-    let request = HttpRequestBuilder::new()
-      .method(R::method())
-      .uri(url.as_str())
-      // Add required authentication information.
-      .header(HDR_KEY_ID, self.api_info.key_id.as_str())
-      .header(HDR_SECRET, self.api_info.secret.as_str())
-      .body(Body::from(R::body(input)?))?;
+    let () = append_path(&mut url, &R::path(input));
+    url.set_query(R::query(input).as_ref().map(AsRef::as_ref));
 
+    let body = R::body(input)?;
+    let request = self.build_request(R::method(), &url, body)?;
     Ok(request)
   }
 
+  /// Create a `Request` with the given method, URL, and body, adding
+  /// authentication information.
+  fn build_request(
+    &self,
+    method: Method,
+    url: &Url,
+    body: Cow<'static, [u8]>,
+  ) -> Result<Request<Body>, HttpError> {
+    if let Some(audit) = &self.audit {
+      audit.record_request(&method, url.as_str(), &body);
+    }
+
+    let builder = HttpRequestBuilder::new().method(method).uri(url.as_str());
+    // Add required authentication information.
+    let builder = match self.oauth.as_ref().and_then(|oauth| oauth.header()) {
+      Some(header) => builder.header(AUTHORIZATION, header),
+      None if self.basic_auth => match basic(&self.api_info.key_id, &self.api_info.secret) {
+        Some(header) => builder.header(AUTHORIZATION, header),
+        None => builder,
+      },
+      None => builder
+        .header(HDR_KEY_ID, self.api_info.key_id.as_str())
+        .header(HDR_SECRET, self.api_info.secret.as_str()),
+    };
+    builder.body(Body::from(body))
+  }
+
   /// Create and issue a request and decode the response.
   pub async fn issue<R>(&self, input: R::Input) -> Result<R::Output, RequestError<R::Error>>
   where
-    R: Endpoint,
+    R: ApiEndpoint,
   {
+    let input = self.prepare::<R>(input);
     let request = self.request::<R>(&input).map_err(RequestError::Endpoint)?;
     let span = span!(
       Level::INFO,
@@ -149,16 +619,203 @@ impl Client {
     self.issue_::<R>(request).instrument(span).await
   }
 
+  /// Create and issue a request and return the raw response body.
+  ///
+  /// In contrast to `issue`, this method does not deserialize the
+  /// response on success. That allows for deserializing it into types
+  /// borrowing from the returned data, avoiding allocations for
+  /// contained strings. An unsuccessful response is evaluated in the
+  /// same way as `issue` does it.
+  pub async fn issue_raw<R>(&self, input: R::Input) -> Result<Bytes, RequestError<R::Error>>
+  where
+    R: ApiEndpoint,
+  {
+    let input = self.prepare::<R>(input);
+    let request = self.request::<R>(&input).map_err(RequestError::Endpoint)?;
+    let span = span!(
+      Level::INFO,
+      "issue_raw",
+      method = display(request.method()),
+      uri = display(request.uri())
+    );
+
+    async move {
+      let (status, bytes) = self.retrieve(request).await?;
+      if status.is_success() {
+        Ok(bytes)
+      } else {
+        // For unsuccessful requests we don't expect much data, so it
+        // is fine to evaluate the body in full. We also don't expect
+        // the endpoint to consider an unsuccessful status as success,
+        // but if it does we just hand out the data.
+        let _ = R::evaluate(status, &bytes).map_err(RequestError::Endpoint)?;
+        Ok(bytes)
+      }
+    }
+    .instrument(span)
+    .await
+  }
+
+  /// Issue a request to an arbitrary path relative to the configured
+  /// API base URL and return the status and body of the response.
+  ///
+  /// This method is meant as an escape hatch for using API endpoints
+  /// not (yet) covered by this crate. Authentication information is
+  /// added as for all other requests, but the response is reported as
+  /// is, irrespective of its status. `query` is expected to be URL
+  /// encoded already.
+  pub async fn issue_custom(
+    &self,
+    method: Method,
+    path: &str,
+    query: Option<&str>,
+    body: Vec<u8>,
+  ) -> Result<(StatusCode, Bytes), RequestError<HttpError>> {
+    let mut url = self.api_info.base_url.clone();
+    let () = append_path(&mut url, path);
+    url.set_query(query);
+
+    let request = self
+      .build_request(method, &url, body.into())
+      .map_err(RequestError::Endpoint)?;
+    let span = span!(
+      Level::INFO,
+      "issue_custom",
+      method = display(request.method()),
+      uri = display(request.uri())
+    );
+    let result = self.retrieve(request).instrument(span).await?;
+    Ok(result)
+  }
+
   /// Issue a request.
-  #[allow(clippy::cognitive_complexity)]
   async fn issue_<R>(&self, request: Request<Body>) -> Result<R::Output, RequestError<R::Error>>
   where
     R: Endpoint,
   {
+    let (status, bytes) = self.retrieve(request).await?;
+    self.evaluate::<R>(status, &bytes)
+  }
+
+  /// Evaluate a response, checking for unknown fields when in strict
+  /// mode.
+  fn evaluate<R>(
+    &self,
+    status: StatusCode,
+    body: &[u8],
+  ) -> Result<R::Output, RequestError<R::Error>>
+  where
+    R: Endpoint,
+  {
+    let output = R::evaluate(status, body).map_err(RequestError::Endpoint)?;
+    if self.strict {
+      let unknown = unknown_fields::<R::Output>(body);
+      if !unknown.is_empty() {
+        let message = format!("unknown fields: {}", unknown.join(", "));
+        let err = <JsonError as DeError>::custom(message);
+        return Err(RequestError::Endpoint(R::Error::from(err)))
+      }
+    }
+    Ok(output)
+  }
+
+  /// Issue a request and retrieve the status and body of the response,
+  /// recording the response in the audit log, if any.
+  async fn retrieve(&self, request: Request<Body>) -> Result<(StatusCode, Bytes), HyperError> {
+    let audit = self
+      .audit
+      .as_ref()
+      .map(|audit| (audit, request.method().clone(), request.uri().to_string()));
+
+    let (status, bytes) = self.send_with_refresh(request).await?;
+    if let Some((audit, method, uri)) = audit {
+      audit.record_response(&method, &uri, status, &bytes);
+    }
+    Ok((status, bytes))
+  }
+
+  /// Send a request, retrying it once with a fresh OAuth token if it
+  /// got rejected as unauthorized and a refresh hook is configured.
+  async fn send_with_refresh(
+    &self,
+    request: Request<Body>,
+  ) -> Result<(StatusCode, Bytes), HyperError> {
+    let (oauth, refresh) = match &self.oauth {
+      Some(oauth) => match &oauth.refresh {
+        Some(refresh) => (oauth, refresh),
+        None => return self.send(request).await,
+      },
+      None => return self.send(request).await,
+    };
+
+    // We may have to send the request twice, which means that we need
+    // to hold on to its contents.
+    let (parts, body) = request.into_parts();
+    let body = to_bytes(body).await?;
+    let copy = || {
+      let mut request = Request::new(Body::from(body.clone()));
+      *request.method_mut() = parts.method.clone();
+      *request.uri_mut() = parts.uri.clone();
+      *request.version_mut() = parts.version;
+      *request.headers_mut() = parts.headers.clone();
+      request
+    };
+
+    let (status, bytes) = self.send(copy()).await?;
+    if status != StatusCode::UNAUTHORIZED {
+      return Ok((status, bytes))
+    }
+
+    debug!("request unauthorized; refreshing OAuth token");
+    let header = match refresh().await.and_then(|token| {
+      let header = bearer(&token);
+      *oauth.token.write().unwrap() = Some(token);
+      header
+    }) {
+      Some(header) => header,
+      None => return Ok((status, bytes)),
+    };
+
+    let mut request = copy();
+    let headers = request.headers_mut();
+    let _ = headers.remove(HDR_KEY_ID);
+    let _ = headers.remove(HDR_SECRET);
+    let _ = headers.insert(AUTHORIZATION, header);
+    self.send(request).await
+  }
+
+  /// Send a request and retrieve the status and body of the response.
+  #[allow(clippy::cognitive_complexity)]
+  async fn send(&self, request: Request<Body>) -> Result<(StatusCode, Bytes), HyperError> {
     debug!("requesting");
     trace!(body = debug(request.body()));
 
-    let result = self.client.request(request).await?;
+    #[cfg(feature = "trading")]
+    let request = if self.dry_run && dry_run::intercepts(request.method(), request.uri().path()) {
+      let (parts, body) = request.into_parts();
+      let body = to_bytes(body).await?;
+      let (status, bytes) = dry_run::handle(&parts.method, parts.uri.path(), &body);
+      debug!(status = debug(&status));
+      return Ok((status, Bytes::from(bytes)))
+    } else {
+      request
+    };
+
+    #[cfg_attr(not(feature = "trading"), allow(clippy::infallible_destructuring_match))]
+    let client = match &self.transport {
+      Transport::Http(client) => client,
+      #[cfg(feature = "trading")]
+      Transport::Simulated(simulator) => {
+        let (parts, body) = request.into_parts();
+        let body = to_bytes(body).await?;
+        let (status, bytes) =
+          simulator.handle(&parts.method, parts.uri.path(), parts.uri.query(), &body);
+        debug!(status = debug(&status));
+        return Ok((status, Bytes::from(bytes)))
+      },
+    };
+
+    let result = client.request(request).await?;
     let status = result.status();
     debug!(status = debug(&status));
     trace!(response = debug(&result));
@@ -180,10 +837,73 @@ impl Client {
       Err(b) => trace!(body = display(&b)),
     }
 
-    R::evaluate(status, body).map_err(RequestError::Endpoint)
+    Ok((status, bytes))
+  }
+
+  /// Create and issue a set of requests to the same endpoint,
+  /// decoding the responses.
+  ///
+  /// At most `max_concurrent` requests are in flight at any given
+  /// time. Requests that are denied because of the rate limit are
+  /// retried after an exponentially increasing back off, up to a
+  /// handful of times, before the rate limit error gets reported.
+  ///
+  /// The results are returned in the order of the provided inputs.
+  ///
+  /// # Panics
+  /// The method panics if `max_concurrent` is zero.
+  pub async fn issue_all<R, I>(
+    &self,
+    inputs: I,
+    max_concurrent: usize,
+  ) -> Vec<Result<R::Output, RequestError<R::Error>>>
+  where
+    R: ApiEndpoint,
+    I: IntoIterator<Item = R::Input>,
+  {
+    assert!(max_concurrent > 0, "maximum concurrency must not be zero");
+
+    iter(inputs)
+      .map(|input| self.issue_with_retry::<R>(input))
+      .buffered(max_concurrent)
+      .collect()
+      .await
+  }
+
+  /// Create and issue a request, retrying it when the rate limit was
+  /// exceeded.
+  async fn issue_with_retry<R>(&self, input: R::Input) -> Result<R::Output, RequestError<R::Error>>
+  where
+    R: ApiEndpoint,
+  {
+    let input = self.prepare::<R>(input);
+    let mut backoff = RATE_LIMIT_BACKOFF;
+    let mut retries = 0;
+
+    loop {
+      let request = self.request::<R>(&input).map_err(RequestError::Endpoint)?;
+      let span = span!(
+        Level::INFO,
+        "issue",
+        method = display(request.method()),
+        uri = display(request.uri())
+      );
+
+      let (status, bytes) = self.retrieve(request).instrument(span).await?;
+      if status == StatusCode::TOO_MANY_REQUESTS && retries < RATE_LIMIT_RETRIES {
+        debug!(backoff = debug(&backoff), "rate limit exceeded; backing off");
+        sleep(backoff).await;
+        backoff *= 2;
+        retries += 1;
+        continue
+      }
+
+      break self.evaluate::<R>(status, &bytes)
+    }
   }
 
   /// Subscribe to the given stream in order to receive updates.
+  #[cfg(feature = "streaming")]
   #[instrument(level = "debug", skip(self))]
   pub async fn subscribe<S>(
     &self,
@@ -191,13 +911,72 @@ impl Client {
   where
     S: EventStream,
   {
-    stream::<S>(&self.api_info).await
+    let (_, stream) =
+      stream_confirmed_with::<S>(&self.api_info, self.audit.clone(), &self.socket).await?;
+    Ok(stream)
+  }
+
+  /// Subscribe to the given stream in order to receive updates, also
+  /// reporting the server's confirmation of the subscription.
+  ///
+  /// The returned future only resolves once the subscription is
+  /// active, meaning that it is safe to, say, submit orders and expect
+  /// the corresponding trade updates to be reported.
+  #[cfg(feature = "streaming")]
+  #[instrument(level = "debug", skip(self))]
+  pub async fn subscribe_confirmed<S>(
+    &self,
+  ) -> Result<
+    (
+      Subscription,
+      impl Stream<Item = Result<Result<S::Event, JsonError>, WebSocketError>>,
+    ),
+    Error,
+  >
+  where
+    S: EventStream,
+  {
+    stream_confirmed_with::<S>(&self.api_info, self.audit.clone(), &self.socket).await
+  }
+
+  /// Subscribe to the given stream just like `subscribe_confirmed`, but
+  /// have each event carry the raw data it was decoded from.
+  ///
+  /// Raw events are meant for diagnosing decoding issues, e.g., due to
+  /// message types not known to this crate.
+  #[cfg(feature = "streaming")]
+  #[instrument(level = "debug", skip(self))]
+  pub async fn subscribe_raw<S>(
+    &self,
+  ) -> Result<
+    (
+      Subscription,
+      impl Stream<Item = Result<RawEvent<S::Event>, WebSocketError>>,
+    ),
+    Error,
+  >
+  where
+    S: EventStream,
+  {
+    stream_raw_confirmed_with::<S>(&self.api_info, self.audit.clone(), &self.socket).await
   }
 
   /// Retrieve the `ApiInfo` object used by this `Client` instance.
   pub fn api_info(&self) -> &ApiInfo {
     &self.api_info
   }
+
+  /// Retrieve the socket options used by this `Client` instance.
+  #[cfg(all(feature = "data", feature = "streaming"))]
+  pub(crate) fn socket_options(&self) -> &SocketOptions {
+    &self.socket
+  }
+
+  /// Retrieve the audit log used by this `Client` instance, if any.
+  #[cfg(all(feature = "data", feature = "streaming"))]
+  pub(crate) fn audit(&self) -> Option<Arc<AuditLog>> {
+    self.audit.clone()
+  }
 }
 
 
@@ -205,14 +984,78 @@ impl Client {
 mod tests {
   use super::*;
 
-  use http::StatusCode;
+  use std::io::Read;
+  use std::io::Write;
+  use std::net::TcpListener;
+  use std::sync::atomic::AtomicUsize;
+  use std::sync::atomic::Ordering;
+  use std::thread::spawn;
+
+  use futures::future::ready;
+
+  #[cfg(feature = "trading")]
+  use num_decimal::Num;
 
   use test_env_log::test;
 
+  #[cfg(feature = "broker")]
+  use uuid::Uuid;
+
+  #[cfg(feature = "trading")]
+  use crate::api::v2::asset;
+  #[cfg(feature = "trading")]
+  use crate::api::v2::asset::Symbol;
+  #[cfg(feature = "trading")]
+  use crate::api::v2::order;
+  #[cfg(feature = "broker")]
+  use crate::broker::v1::account;
+  #[cfg(feature = "broker")]
+  use crate::broker::v1::transfer_limits;
+  #[cfg(feature = "data")]
+  use crate::data::v2::bars;
+  #[cfg(feature = "data")]
+  use crate::data::v2::Feed;
   use crate::endpoint::ErrorMessage;
   use crate::Str;
 
 
+  /// Serve HTTP requests on a local port, responding with `200 OK` to
+  /// those carrying the given `Authorization` header and with `401
+  /// Unauthorized` to all others.
+  fn serve_authorized(authorization: &'static str) -> Url {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let _ = spawn(move || {
+      let expected = format!("authorization: {}\r\n", authorization).to_lowercase();
+      for stream in listener.incoming() {
+        let mut stream = stream.unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0; 1024];
+        while !request.ends_with(b"\r\n\r\n") {
+          let count = stream.read(&mut buffer).unwrap();
+          if count == 0 {
+            break
+          }
+          request.extend_from_slice(&buffer[..count]);
+        }
+
+        let request = String::from_utf8_lossy(&request).to_lowercase();
+        let status = if request.contains(&expected) {
+          "200 OK"
+        } else {
+          "401 Unauthorized"
+        };
+        let response = format!(
+          "HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+          status
+        );
+        stream.write_all(response.as_bytes()).unwrap();
+      }
+    });
+    Url::parse(&format!("http://{}", addr)).unwrap()
+  }
+
+
   Endpoint! {
     GetNotFound(()),
     Ok => (), [],
@@ -223,6 +1066,435 @@ mod tests {
     }
   }
 
+  /// Check that requests to data endpoints are directed at the
+  /// configured data API base URL.
+  #[cfg(feature = "data")]
+  #[test]
+  fn data_request_url() {
+    let api_info = ApiInfo {
+      base_url: Url::parse("https://api.example.com").unwrap(),
+      stream_url: Url::parse("wss://api.example.com/stream").unwrap(),
+      data_base_url: Url::parse(DATA_BASE_URL).unwrap(),
+      data_stream_url: Url::parse("wss://stream.example.com").unwrap(),
+      #[cfg(feature = "data")]
+      data_feed: Feed::Iex,
+      broker_base_url: Url::parse("https://broker.example.com").unwrap(),
+      key_id: "key".to_string(),
+      secret: "secret".to_string(),
+    };
+    let request = bars::BarsReqInit::default().init(
+      "SPY",
+      std::time::UNIX_EPOCH,
+      std::time::UNIX_EPOCH,
+      bars::TimeFrame::OneDay,
+    );
+
+    let client = Client::new(api_info.clone());
+    let req = client.request::<bars::Get>(&request).unwrap();
+    assert_eq!(req.uri().host(), Some("data.alpaca.markets"));
+
+    let gateway = Url::parse("https://gateway.example.com:8443").unwrap();
+    let client = Client::new(api_info.with_data_base_url(gateway));
+    let req = client.request::<bars::Get>(&request).unwrap();
+    assert_eq!(req.uri().host(), Some("gateway.example.com"));
+    assert_eq!(req.uri().port_u16(), Some(8443));
+    assert_eq!(req.uri().path(), "/v2/stocks/SPY/bars");
+  }
+
+  /// Check that requests to Broker API endpoints are directed at the
+  /// configured Broker API base URL.
+  #[cfg(feature = "broker")]
+  #[test]
+  fn broker_request_url() {
+    let api_info = ApiInfo {
+      base_url: Url::parse("https://api.example.com").unwrap(),
+      stream_url: Url::parse("wss://api.example.com/stream").unwrap(),
+      data_base_url: Url::parse(DATA_BASE_URL).unwrap(),
+      data_stream_url: Url::parse("wss://stream.example.com").unwrap(),
+      #[cfg(feature = "data")]
+      data_feed: Feed::Iex,
+      broker_base_url: Url::parse(BROKER_BASE_URL).unwrap(),
+      key_id: "key".to_string(),
+      secret: "secret".to_string(),
+    };
+    let id = account::Id(Uuid::nil());
+
+    let client = Client::new(api_info.clone());
+    let req = client.request::<transfer_limits::Get>(&id).unwrap();
+    assert_eq!(req.uri().host(), Some("broker-api.sandbox.alpaca.markets"));
+
+    let proxy = Url::parse("https://proxy.example.com").unwrap();
+    let client = Client::new(api_info.with_broker_base_url(proxy));
+    let req = client.request::<transfer_limits::Get>(&id).unwrap();
+    assert_eq!(req.uri().host(), Some("proxy.example.com"));
+  }
+
+  /// Check that we retain path prefixes of base URLs when appending
+  /// endpoint paths.
+  #[test]
+  fn append_url_path() {
+    let mut url = Url::parse("https://api.example.com").unwrap();
+    let () = append_path(&mut url, "/v2/account");
+    assert_eq!(url.as_str(), "https://api.example.com/v2/account");
+
+    let mut url = Url::parse("https://proxy.example.com/alpaca").unwrap();
+    let () = append_path(&mut url, "/v2/account");
+    assert_eq!(url.as_str(), "https://proxy.example.com/alpaca/v2/account");
+
+    let mut url = Url::parse("https://proxy.example.com/alpaca/").unwrap();
+    let () = append_path(&mut url, "/v2/account");
+    assert_eq!(url.as_str(), "https://proxy.example.com/alpaca/v2/account");
+  }
+
+  /// Check that requests to an endpoint are directed at the configured
+  /// base URL, including its path prefix.
+  #[cfg(feature = "data")]
+  #[test]
+  fn request_url_with_path_prefix() {
+    let api_info = ApiInfo {
+      base_url: Url::parse("https://api.example.com").unwrap(),
+      stream_url: Url::parse("wss://api.example.com/stream").unwrap(),
+      data_base_url: Url::parse("https://proxy.example.com/alpaca/").unwrap(),
+      data_stream_url: Url::parse("wss://stream.example.com").unwrap(),
+      data_feed: Feed::Iex,
+      broker_base_url: Url::parse("https://broker.example.com").unwrap(),
+      key_id: "key".to_string(),
+      secret: "secret".to_string(),
+    };
+    let request = bars::BarsReqInit::default().init(
+      "SPY",
+      std::time::UNIX_EPOCH,
+      std::time::UNIX_EPOCH,
+      bars::TimeFrame::OneDay,
+    );
+
+    let client = Client::new(api_info);
+    let req = client.request::<bars::Get>(&request).unwrap();
+    assert_eq!(req.uri().host(), Some("proxy.example.com"));
+    assert_eq!(req.uri().path(), "/alpaca/v2/stocks/SPY/bars");
+  }
+
+  /// Check that historical data requests without an explicit feed use
+  /// the one configured in the client's `ApiInfo`.
+  #[cfg(feature = "data")]
+  #[test]
+  fn request_with_default_feed() {
+    let api_info = ApiInfo {
+      base_url: Url::parse("https://api.example.com").unwrap(),
+      stream_url: Url::parse("wss://api.example.com/stream").unwrap(),
+      data_base_url: Url::parse("https://data.example.com").unwrap(),
+      data_stream_url: Url::parse("wss://stream.example.com").unwrap(),
+      data_feed: Feed::Sip,
+      broker_base_url: Url::parse("https://broker.example.com").unwrap(),
+      key_id: "key".to_string(),
+      secret: "secret".to_string(),
+    };
+    let client = Client::new(api_info);
+
+    let request = bars::BarsReqInit::default().init(
+      "SPY",
+      std::time::UNIX_EPOCH,
+      std::time::UNIX_EPOCH,
+      bars::TimeFrame::OneDay,
+    );
+    let request = client.prepare::<bars::Get>(request);
+    assert_eq!(request.feed, Some(Feed::Sip));
+    let req = client.request::<bars::Get>(&request).unwrap();
+    assert!(req.uri().query().unwrap().contains("feed=sip"));
+
+    let request = bars::BarsReqInit {
+      feed: Some(Feed::Iex),
+      ..Default::default()
+    }
+    .init(
+      "SPY",
+      std::time::UNIX_EPOCH,
+      std::time::UNIX_EPOCH,
+      bars::TimeFrame::OneDay,
+    );
+    let request = client.prepare::<bars::Get>(request);
+    assert_eq!(request.feed, Some(Feed::Iex));
+  }
+
+  /// Check that a client derived via `with_api_info` uses the new
+  /// credentials while the original one is left untouched.
+  #[test]
+  fn derive_client_with_api_info() {
+    let api_info = ApiInfo {
+      base_url: Url::parse("https://api.example.com").unwrap(),
+      stream_url: Url::parse("wss://api.example.com/stream").unwrap(),
+      data_base_url: Url::parse(DATA_BASE_URL).unwrap(),
+      data_stream_url: Url::parse("wss://stream.example.com").unwrap(),
+      #[cfg(feature = "data")]
+      data_feed: Feed::Iex,
+      broker_base_url: Url::parse("https://broker.example.com").unwrap(),
+      key_id: "paper-key".to_string(),
+      secret: "paper-secret".to_string(),
+    };
+    let live = ApiInfo {
+      base_url: Url::parse("https://live.example.com").unwrap(),
+      stream_url: Url::parse("wss://live.example.com/stream").unwrap(),
+      data_base_url: Url::parse(DATA_BASE_URL).unwrap(),
+      data_stream_url: Url::parse("wss://stream.example.com").unwrap(),
+      #[cfg(feature = "data")]
+      data_feed: Feed::Iex,
+      broker_base_url: Url::parse("https://broker.example.com").unwrap(),
+      key_id: "live-key".to_string(),
+      secret: "live-secret".to_string(),
+    };
+
+    let client = Client::new(api_info.clone());
+    let derived = client.clone().with_api_info(live.clone());
+    assert_eq!(client.api_info(), &api_info);
+    assert_eq!(derived.api_info(), &live);
+
+    let req = derived.request::<GetNotFound>(&()).unwrap();
+    assert_eq!(req.uri().host(), Some("live.example.com"));
+    assert_eq!(req.headers()[HDR_KEY_ID], "live-key");
+
+    let req = client.request::<GetNotFound>(&()).unwrap();
+    assert_eq!(req.uri().host(), Some("api.example.com"));
+    assert_eq!(req.headers()[HDR_KEY_ID], "paper-key");
+  }
+
+  /// Check that we can issue a batch of requests and receive the
+  /// results in the order of the inputs.
+  #[cfg(feature = "trading")]
+  #[test(tokio::test)]
+  async fn issue_all_in_order() {
+    let symbols = vec!["AAPL", "SPY", "MSFT", "XLK", "AMZN"];
+    let inputs = symbols
+      .iter()
+      .map(|symbol| Symbol::Sym(symbol.to_string()))
+      .collect::<Vec<_>>();
+
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+    let results = client.issue_all::<asset::Get, _>(inputs, 2).await;
+
+    assert_eq!(results.len(), symbols.len());
+    for (result, symbol) in results.into_iter().zip(symbols) {
+      assert_eq!(result.unwrap().symbol, symbol);
+    }
+  }
+
+  /// Check that we can issue requests to arbitrary paths.
+  #[cfg(feature = "trading")]
+  #[test(tokio::test)]
+  async fn issue_custom_request() {
+    let simulator = Simulator::new(Num::from(1000), |_: &str| None);
+    let client = Client::simulated(Arc::new(simulator));
+
+    let (status, body) = client
+      .issue_custom(Method::GET, "/v2/account", None, Vec::new())
+      .await
+      .unwrap();
+    assert_eq!(status, StatusCode::OK);
+    let account = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(account["cash"], "1000");
+
+    let (status, _) = client
+      .issue_custom(Method::DELETE, "/v2/foobarbaz", Some("foo=bar"), Vec::new())
+      .await
+      .unwrap();
+    assert_eq!(status, StatusCode::NOT_FOUND);
+  }
+
+  /// Check that order submissions are answered locally in dry-run
+  /// mode.
+  #[cfg(feature = "trading")]
+  #[test(tokio::test)]
+  async fn dry_run_orders() {
+    // Nothing listens on the discard port, so any request actually
+    // sent would fail.
+    let api_info = ApiInfo {
+      base_url: Url::parse("http://127.0.0.1:9").unwrap(),
+      stream_url: Url::parse("wss://api.example.com/stream").unwrap(),
+      data_base_url: Url::parse(DATA_BASE_URL).unwrap(),
+      data_stream_url: Url::parse("wss://stream.example.com").unwrap(),
+      #[cfg(feature = "data")]
+      data_feed: Feed::Iex,
+      broker_base_url: Url::parse("https://broker.example.com").unwrap(),
+      key_id: "key".to_string(),
+      secret: "secret".to_string(),
+    };
+    let client = Client::builder().dry_run(true).build(api_info);
+
+    let request = order::OrderReqInit {
+      type_: order::Type::Limit,
+      limit_price: Some(Num::from(100)),
+      ..Default::default()
+    }
+    .init("AAPL", order::Side::Buy, 1);
+    let order = client.issue::<order::Post>(request).await.unwrap();
+    assert_eq!(order.status, order::Status::Accepted);
+    assert_eq!(order.symbol, "AAPL");
+
+    let result = client.issue::<order::Delete>(order.id).await;
+    assert!(result.is_ok(), "{:?}", result);
+
+    let request = order::OrderReqInit {
+      type_: order::Type::Limit,
+      ..Default::default()
+    }
+    .init("AAPL", order::Side::Buy, 1);
+    let err = client.issue::<order::Post>(request).await.unwrap_err();
+    match err {
+      RequestError::Endpoint(order::PostError::InvalidInput(..)) => (),
+      _ => panic!("Received unexpected error: {:?}", err),
+    }
+
+    // Reads are not intercepted and fail to connect.
+    let result = client.issue::<order::Get>(order.id).await;
+    assert!(result.is_err());
+  }
+
+  /// Check that the OAuth token is refreshed and the request retried
+  /// once it got rejected as unauthorized.
+  #[test(tokio::test)]
+  async fn refresh_oauth_token() {
+    let api_info = ApiInfo {
+      base_url: serve_authorized("Bearer fresh"),
+      stream_url: Url::parse("wss://api.example.com/stream").unwrap(),
+      data_base_url: Url::parse(DATA_BASE_URL).unwrap(),
+      data_stream_url: Url::parse("wss://stream.example.com").unwrap(),
+      #[cfg(feature = "data")]
+      data_feed: Feed::Iex,
+      broker_base_url: Url::parse("https://broker.example.com").unwrap(),
+      key_id: "key".to_string(),
+      secret: "secret".to_string(),
+    };
+
+    let refreshes = Arc::new(AtomicUsize::new(0));
+    let count = refreshes.clone();
+    let client = Client::builder()
+      .oauth_token("stale")
+      .oauth_refresh(move || {
+        let _ = count.fetch_add(1, Ordering::SeqCst);
+        ready(Some("fresh".to_string()))
+      })
+      .build(api_info.clone());
+
+    for _ in 0..2 {
+      let (status, _) = client
+        .issue_custom(Method::GET, "/v2/account", None, Vec::new())
+        .await
+        .unwrap();
+      assert_eq!(status, StatusCode::OK);
+    }
+    // The fresh token is used for all requests after the first one.
+    assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+
+    let client = Client::builder()
+      .oauth_token("stale")
+      .oauth_refresh(|| ready(None))
+      .build(api_info);
+    let (status, _) = client
+      .issue_custom(Method::GET, "/v2/account", None, Vec::new())
+      .await
+      .unwrap();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+  }
+
+  /// Check that requests carry the key ID and secret as HTTP Basic
+  /// credentials if requested.
+  #[test(tokio::test)]
+  async fn authenticate_using_basic_auth() {
+    let api_info = ApiInfo {
+      base_url: serve_authorized("Basic a2V5OnNlY3JldA=="),
+      stream_url: Url::parse("wss://api.example.com/stream").unwrap(),
+      data_base_url: Url::parse(DATA_BASE_URL).unwrap(),
+      data_stream_url: Url::parse("wss://stream.example.com").unwrap(),
+      #[cfg(feature = "data")]
+      data_feed: Feed::Iex,
+      broker_base_url: Url::parse("https://broker.example.com").unwrap(),
+      key_id: "key".to_string(),
+      secret: "secret".to_string(),
+    };
+
+    let client = Client::builder().basic_auth(true).build(api_info.clone());
+    let (status, _) = client
+      .issue_custom(Method::GET, "/v1/accounts", None, Vec::new())
+      .await
+      .unwrap();
+    assert_eq!(status, StatusCode::OK);
+
+    let client = Client::new(api_info);
+    let (status, _) = client
+      .issue_custom(Method::GET, "/v1/accounts", None, Vec::new())
+      .await
+      .unwrap();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+  }
+
+  /// Check that host names are resolved using the configured resolver.
+  #[test(tokio::test)]
+  async fn resolve_using_custom_resolver() {
+    let url = serve_authorized("Bearer token");
+    let port = url.port().unwrap();
+    let api_info = ApiInfo {
+      base_url: Url::parse(&format!("http://api.apca.invalid:{}", port)).unwrap(),
+      stream_url: Url::parse("wss://api.example.com/stream").unwrap(),
+      data_base_url: Url::parse(DATA_BASE_URL).unwrap(),
+      data_stream_url: Url::parse("wss://stream.example.com").unwrap(),
+      #[cfg(feature = "data")]
+      data_feed: Feed::Iex,
+      broker_base_url: Url::parse("https://broker.example.com").unwrap(),
+      key_id: "key".to_string(),
+      secret: "secret".to_string(),
+    };
+
+    let client = Client::builder()
+      .oauth_token("token")
+      .happy_eyeballs_timeout(None)
+      .resolver(|name| {
+        assert_eq!(name, "api.apca.invalid");
+        ready(Ok(vec![SocketAddr::from(([127, 0, 0, 1], 0))]))
+      })
+      .build(api_info);
+
+    let (status, _) = client
+      .issue_custom(Method::GET, "/v2/account", None, Vec::new())
+      .await
+      .unwrap();
+    assert_eq!(status, StatusCode::OK);
+  }
+
+  /// Check that requests are issued over HTTP/2 when it is enabled.
+  #[cfg(feature = "http2")]
+  #[test(tokio::test)]
+  async fn issue_over_http2() {
+    /// The connection preface sent by HTTP/2 clients.
+    const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let api_info = ApiInfo {
+      base_url: Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap(),
+      stream_url: Url::parse("wss://api.example.com/stream").unwrap(),
+      data_base_url: Url::parse(DATA_BASE_URL).unwrap(),
+      data_stream_url: Url::parse("wss://stream.example.com").unwrap(),
+      #[cfg(feature = "data")]
+      data_feed: Feed::Iex,
+      broker_base_url: Url::parse("https://broker.example.com").unwrap(),
+      key_id: "key".to_string(),
+      secret: "secret".to_string(),
+    };
+    let server = spawn(move || {
+      let (mut stream, _) = listener.accept().unwrap();
+      let mut preface = [0; PREFACE.len()];
+      stream.read_exact(&mut preface).unwrap();
+      preface
+    });
+
+    let client = Client::builder().http2(true).build(api_info);
+    // The server never responds using HTTP/2, so the request will
+    // eventually fail.
+    let _ = client
+      .issue_custom(Method::GET, "/v2/account", None, Vec::new())
+      .await;
+    assert_eq!(&server.join().unwrap()[..], PREFACE);
+  }
+
   #[test(tokio::test)]
   async fn unexpected_status_code_return() {
     let api_info = ApiInfo::from_env().unwrap();
@@ -3,6 +3,10 @@
 
 /// Definitions for the first version of the Alpaca Data API.
 pub mod v1;
-
-/// The API base URL used for retrieving market data.
-pub(crate) const DATA_BASE_URL: &str = "https://data.alpaca.markets";
+/// Definitions for the second version of the Alpaca Data API.
+pub mod v2;
+/// Definitions for the beta version of the Alpaca Data API.
+pub mod v1beta1;
+/// Definitions for the v1beta3 version of the Alpaca Data API, which
+/// covers crypto currencies.
+pub mod v1beta3;
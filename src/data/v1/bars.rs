@@ -13,7 +13,6 @@ use serde_urlencoded::to_string as to_query;
 use time_util::optional_system_time_to_rfc3339;
 use time_util::system_time_from_secs;
 
-use crate::data::DATA_BASE_URL;
 use crate::Str;
 
 
@@ -129,10 +128,7 @@ Endpoint! {
     /// One or more of the arguments are not well formed.
     /* 422 */ UNPROCESSABLE_ENTITY => InvalidArgument,
   ]
-
-  fn base_url() -> Option<Str> {
-    Some(DATA_BASE_URL.into())
-  }
+  Api => Data,
 
   fn path(input: &Self::Input) -> Str {
     let (timeframe, _) = input;
@@ -181,7 +177,7 @@ mod tests {
   ]
 }"#;
 
-    let bars = from_json::<<Get as Endpoint>::Output>(&response).unwrap();
+    let bars = from_json::<<Get as Endpoint>::Output>(response).unwrap();
     let aapl = bars.get("AAPL").unwrap();
     assert_eq!(aapl.len(), 1);
     assert_eq!(aapl[0].time, UNIX_EPOCH + Duration::from_secs(1544129220));
@@ -246,7 +242,7 @@ mod tests {
 
     let aapl = bars.get("AAPL").unwrap();
     assert_eq!(aapl.len(), 1);
-    assert!(aapl[0].time <= now, aapl[0].time);
-    assert!(aapl[0].time >= earlier, aapl[0].time);
+    assert!(aapl[0].time <= now, "{:?}", aapl[0].time);
+    assert!(aapl[0].time >= earlier, "{:?}", aapl[0].time);
   }
 }
@@ -0,0 +1,467 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::SystemTime;
+
+use num_decimal::Num;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_urlencoded::to_string as to_query;
+
+use time_util::system_time_from_date_str;
+
+use crate::paged::next_page_by_token;
+use crate::paged::Paged;
+use crate::util::optional_system_time_from_date_str;
+use crate::util::optional_system_time_to_date_str;
+use crate::util::slice_to_str;
+use crate::util::strings_to_str;
+use crate::Str;
+
+
+/// The types of corporate actions that can be retrieved.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+pub enum Type {
+  /// A forward stock split.
+  #[serde(rename = "forward_split")]
+  ForwardSplit,
+  /// A reverse stock split.
+  #[serde(rename = "reverse_split")]
+  ReverseSplit,
+  /// A dividend paid in cash.
+  #[serde(rename = "cash_dividend")]
+  CashDividend,
+  /// A merger paid for in stock.
+  #[serde(rename = "stock_merger")]
+  StockMerger,
+}
+
+
+/// A helper for initializing `CorporateActionsReq` objects.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CorporateActionsReqInit {
+  /// See `CorporateActionsReq::types`.
+  pub types: Vec<Type>,
+  /// See `CorporateActionsReq::start`.
+  pub start: Option<SystemTime>,
+  /// See `CorporateActionsReq::end`.
+  pub end: Option<SystemTime>,
+  /// See `CorporateActionsReq::limit`.
+  pub limit: Option<usize>,
+  /// See `CorporateActionsReq::page_token`.
+  pub page_token: Option<String>,
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl CorporateActionsReqInit {
+  /// Create a `CorporateActionsReq` from a `CorporateActionsReqInit`.
+  pub fn init<I, S>(self, symbols: I) -> CorporateActionsReq
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+  {
+    CorporateActionsReq {
+      symbols: symbols.into_iter().map(Into::into).collect(),
+      types: self.types,
+      start: self.start,
+      end: self.end,
+      limit: self.limit,
+      page_token: self.page_token,
+    }
+  }
+}
+
+
+/// A GET request to be issued to the /v1/corporate-actions endpoint.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CorporateActionsReq {
+  /// The symbols for which to retrieve corporate actions.
+  #[serde(rename = "symbols", serialize_with = "strings_to_str")]
+  pub symbols: Vec<String>,
+  /// The types of corporate actions to retrieve.
+  ///
+  /// If empty, corporate actions of all types are retrieved.
+  #[serde(
+    rename = "types",
+    serialize_with = "slice_to_str",
+    skip_serializing_if = "Vec::is_empty"
+  )]
+  pub types: Vec<Type>,
+  /// The first date (inclusive) for which to retrieve corporate
+  /// actions.
+  #[serde(
+    rename = "start",
+    serialize_with = "optional_system_time_to_date_str",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub start: Option<SystemTime>,
+  /// The last date (inclusive) for which to retrieve corporate
+  /// actions.
+  #[serde(
+    rename = "end",
+    serialize_with = "optional_system_time_to_date_str",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub end: Option<SystemTime>,
+  /// The maximum number of corporate actions to retrieve with a single
+  /// request.
+  ///
+  /// It can be between 1 and 1000. Defaults to 100 if not set.
+  #[serde(rename = "limit", skip_serializing_if = "Option::is_none")]
+  pub limit: Option<usize>,
+  /// If provided, this token is used for retrieving the next page of
+  /// data, following a previous request.
+  #[serde(rename = "page_token", skip_serializing_if = "Option::is_none")]
+  pub page_token: Option<String>,
+}
+
+
+/// A forward stock split.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ForwardSplit {
+  /// The symbol of the company performing the split.
+  #[serde(rename = "symbol")]
+  pub symbol: String,
+  /// The CUSIP of the company performing the split.
+  #[serde(rename = "cusip")]
+  pub cusip: Option<String>,
+  /// The number of shares after the split.
+  #[serde(rename = "new_rate")]
+  pub new_rate: Num,
+  /// The number of shares before the split.
+  #[serde(rename = "old_rate")]
+  pub old_rate: Num,
+  /// The date the split is processed on.
+  #[serde(rename = "process_date", deserialize_with = "system_time_from_date_str")]
+  pub process_date: SystemTime,
+  /// The ex-date of the split.
+  #[serde(rename = "ex_date", deserialize_with = "system_time_from_date_str")]
+  pub ex_date: SystemTime,
+  /// The record date of the split.
+  #[serde(
+    rename = "record_date",
+    default,
+    deserialize_with = "optional_system_time_from_date_str"
+  )]
+  pub record_date: Option<SystemTime>,
+  /// The payable date of the split.
+  #[serde(
+    rename = "payable_date",
+    default,
+    deserialize_with = "optional_system_time_from_date_str"
+  )]
+  pub payable_date: Option<SystemTime>,
+}
+
+
+/// A reverse stock split.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ReverseSplit {
+  /// The symbol of the company performing the split.
+  #[serde(rename = "symbol")]
+  pub symbol: String,
+  /// The CUSIP of the company after the split.
+  #[serde(rename = "new_cusip")]
+  pub new_cusip: Option<String>,
+  /// The CUSIP of the company before the split.
+  #[serde(rename = "old_cusip")]
+  pub old_cusip: Option<String>,
+  /// The number of shares after the split.
+  #[serde(rename = "new_rate")]
+  pub new_rate: Num,
+  /// The number of shares before the split.
+  #[serde(rename = "old_rate")]
+  pub old_rate: Num,
+  /// The date the split is processed on.
+  #[serde(rename = "process_date", deserialize_with = "system_time_from_date_str")]
+  pub process_date: SystemTime,
+  /// The ex-date of the split.
+  #[serde(rename = "ex_date", deserialize_with = "system_time_from_date_str")]
+  pub ex_date: SystemTime,
+  /// The record date of the split.
+  #[serde(
+    rename = "record_date",
+    default,
+    deserialize_with = "optional_system_time_from_date_str"
+  )]
+  pub record_date: Option<SystemTime>,
+  /// The payable date of the split.
+  #[serde(
+    rename = "payable_date",
+    default,
+    deserialize_with = "optional_system_time_from_date_str"
+  )]
+  pub payable_date: Option<SystemTime>,
+}
+
+
+/// A dividend paid in cash.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct CashDividend {
+  /// The symbol of the company paying the dividend.
+  #[serde(rename = "symbol")]
+  pub symbol: String,
+  /// The CUSIP of the company paying the dividend.
+  #[serde(rename = "cusip")]
+  pub cusip: Option<String>,
+  /// The amount of cash paid per share.
+  #[serde(rename = "rate")]
+  pub rate: Num,
+  /// Whether the dividend is a special one (i.e., is not paid as
+  /// part of the regular schedule).
+  #[serde(rename = "special")]
+  pub special: bool,
+  /// Whether the dividend is paid by a foreign company.
+  #[serde(rename = "foreign")]
+  pub foreign: bool,
+  /// The date the dividend is processed on.
+  #[serde(rename = "process_date", deserialize_with = "system_time_from_date_str")]
+  pub process_date: SystemTime,
+  /// The ex-date of the dividend.
+  #[serde(rename = "ex_date", deserialize_with = "system_time_from_date_str")]
+  pub ex_date: SystemTime,
+  /// The record date of the dividend.
+  #[serde(
+    rename = "record_date",
+    default,
+    deserialize_with = "optional_system_time_from_date_str"
+  )]
+  pub record_date: Option<SystemTime>,
+  /// The date on which the dividend is paid out.
+  #[serde(
+    rename = "payable_date",
+    default,
+    deserialize_with = "optional_system_time_from_date_str"
+  )]
+  pub payable_date: Option<SystemTime>,
+}
+
+
+/// A merger paid for in stock.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct StockMerger {
+  /// The symbol of the acquiring company.
+  #[serde(rename = "acquirer_symbol")]
+  pub acquirer_symbol: String,
+  /// The CUSIP of the acquiring company.
+  #[serde(rename = "acquirer_cusip")]
+  pub acquirer_cusip: Option<String>,
+  /// The number of shares of the acquiring company handed out.
+  #[serde(rename = "acquirer_rate")]
+  pub acquirer_rate: Num,
+  /// The symbol of the company being acquired.
+  #[serde(rename = "acquiree_symbol")]
+  pub acquiree_symbol: String,
+  /// The CUSIP of the company being acquired.
+  #[serde(rename = "acquiree_cusip")]
+  pub acquiree_cusip: Option<String>,
+  /// The number of shares of the company being acquired that are
+  /// exchanged for `acquirer_rate` shares of the acquirer.
+  #[serde(rename = "acquiree_rate")]
+  pub acquiree_rate: Num,
+  /// The date the merger is processed on.
+  #[serde(rename = "process_date", deserialize_with = "system_time_from_date_str")]
+  pub process_date: SystemTime,
+  /// The date the merger becomes effective.
+  #[serde(rename = "effective_date", deserialize_with = "system_time_from_date_str")]
+  pub effective_date: SystemTime,
+  /// The date on which the new shares are handed out.
+  #[serde(
+    rename = "payable_date",
+    default,
+    deserialize_with = "optional_system_time_from_date_str"
+  )]
+  pub payable_date: Option<SystemTime>,
+}
+
+
+/// The corporate actions as reported by the /v1/corporate-actions
+/// endpoint, grouped by type.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Actions {
+  /// The reported forward stock splits.
+  #[serde(rename = "forward_splits", default)]
+  pub forward_splits: Vec<ForwardSplit>,
+  /// The reported reverse stock splits.
+  #[serde(rename = "reverse_splits", default)]
+  pub reverse_splits: Vec<ReverseSplit>,
+  /// The reported cash dividends.
+  #[serde(rename = "cash_dividends", default)]
+  pub cash_dividends: Vec<CashDividend>,
+  /// The reported stock mergers.
+  #[serde(rename = "stock_mergers", default)]
+  pub stock_mergers: Vec<StockMerger>,
+}
+
+
+/// A collection of corporate actions as returned by the
+/// /v1/corporate-actions endpoint.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct CorporateActions {
+  /// The corporate actions.
+  #[serde(rename = "corporate_actions")]
+  pub actions: Actions,
+  /// The token to provide to a request to get the next page of
+  /// corporate actions for this request.
+  #[serde(rename = "next_page_token")]
+  pub next_page_token: Option<String>,
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the /v1/corporate-actions
+  /// endpoint.
+  pub Get(CorporateActionsReq),
+  Ok => CorporateActions, [
+    /// The corporate actions were retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// One or more of the arguments are not well formed.
+    /* 422 */ UNPROCESSABLE_ENTITY => InvalidArgument,
+  ]
+  Api => Data,
+
+  fn path(_input: &Self::Input) -> Str {
+    "/v1/corporate-actions".into()
+  }
+
+  fn query(input: &Self::Input) -> Option<Str> {
+    // TODO: Realistically there should be no way for this unwrap to
+    //       ever panic because our conversion to strings should not be
+    //       fallible. But still, ideally we would not have to unwrap.
+    Some(to_query(input).unwrap().into())
+  }
+}
+
+impl Paged for Get {
+  fn next_page(input: &Self::Input, output: &Self::Output) -> Option<Self::Input> {
+    next_page_by_token(input, output.next_page_token.as_deref(), |input| {
+      &mut input.page_token
+    })
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use http_endpoint::Endpoint;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+  use time_util::parse_system_time_from_date_str;
+
+  use crate::api_info::ApiInfo;
+  use crate::Client;
+
+
+  #[test]
+  fn emit_request() {
+    let request = CorporateActionsReqInit {
+      types: vec![Type::ForwardSplit, Type::CashDividend],
+      start: parse_system_time_from_date_str("2020-08-01"),
+      page_token: Some("abc".to_string()),
+      ..Default::default()
+    }
+    .init(vec!["AAPL", "TSLA"]);
+
+    let query = to_query(&request).unwrap();
+    let expected =
+      "symbols=AAPL%2CTSLA&types=forward_split%2Ccash_dividend&start=2020-08-01&page_token=abc";
+    assert_eq!(query, expected);
+  }
+
+  #[test]
+  fn parse_reference_corporate_actions() {
+    let response = r#"{
+  "corporate_actions": {
+    "forward_splits": [
+      {
+        "symbol": "AAPL",
+        "cusip": "037833100",
+        "new_rate": 4,
+        "old_rate": 1,
+        "process_date": "2020-08-31",
+        "ex_date": "2020-08-31",
+        "record_date": "2020-08-24",
+        "payable_date": "2020-08-28"
+      }
+    ],
+    "cash_dividends": [
+      {
+        "symbol": "AAPL",
+        "cusip": "037833100",
+        "rate": 0.205,
+        "special": false,
+        "foreign": false,
+        "process_date": "2020-08-14",
+        "ex_date": "2020-08-07",
+        "record_date": "2020-08-10",
+        "payable_date": "2020-08-13"
+      }
+    ],
+    "stock_mergers": [
+      {
+        "acquirer_symbol": "XYZ",
+        "acquirer_cusip": "98412A107",
+        "acquirer_rate": 0.3,
+        "acquiree_symbol": "ABC",
+        "acquiree_cusip": "000360206",
+        "acquiree_rate": 1,
+        "process_date": "2021-03-10",
+        "effective_date": "2021-03-10",
+        "payable_date": null
+      }
+    ]
+  },
+  "next_page_token": "QUFQTHwyMDIwLTA4LTMx"
+}"#;
+
+    let result = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    let actions = result.actions;
+    assert_eq!(actions.forward_splits.len(), 1);
+    assert_eq!(actions.forward_splits[0].symbol, "AAPL");
+    assert_eq!(actions.forward_splits[0].new_rate, Num::from(4));
+    assert_eq!(actions.forward_splits[0].old_rate, Num::from(1));
+    assert_eq!(
+      actions.forward_splits[0].ex_date,
+      parse_system_time_from_date_str("2020-08-31").unwrap()
+    );
+    assert_eq!(actions.reverse_splits, Vec::new());
+    assert_eq!(actions.cash_dividends.len(), 1);
+    assert_eq!(actions.cash_dividends[0].rate, Num::new(205, 1000));
+    assert!(!actions.cash_dividends[0].special);
+    assert_eq!(actions.stock_mergers.len(), 1);
+    assert_eq!(actions.stock_mergers[0].acquiree_symbol, "ABC");
+    assert_eq!(actions.stock_mergers[0].acquirer_rate, Num::new(3, 10));
+    assert_eq!(actions.stock_mergers[0].payable_date, None);
+    assert_eq!(result.next_page_token, Some("QUFQTHwyMDIwLTA4LTMx".to_string()));
+  }
+
+  #[test(tokio::test)]
+  async fn request_corporate_actions() {
+    let request = CorporateActionsReqInit {
+      types: vec![Type::ForwardSplit],
+      start: parse_system_time_from_date_str("2020-08-01"),
+      end: parse_system_time_from_date_str("2020-09-30"),
+      ..Default::default()
+    }
+    .init(vec!["AAPL"]);
+
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+    let result = client.issue::<Get>(request).await.unwrap();
+
+    let splits = result.actions.forward_splits;
+    assert_eq!(splits.len(), 1);
+    assert_eq!(splits[0].symbol, "AAPL");
+    assert_eq!(splits[0].new_rate, Num::from(4));
+    assert_eq!(splits[0].old_rate, Num::from(1));
+  }
+}
@@ -3,3 +3,5 @@
 
 /// Definitions for retrieval of market data bars.
 pub mod bars;
+/// Definitions for retrieval of corporate actions.
+pub mod corporate_actions;
@@ -0,0 +1,7 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+/// Definitions for retrieval of the top market movers.
+pub mod movers;
+/// Definitions for retrieval of the most active stocks.
+pub mod most_actives;
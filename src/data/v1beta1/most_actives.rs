@@ -0,0 +1,167 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::SystemTime;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_urlencoded::to_string as to_query;
+
+use time_util::system_time_from_str;
+
+use crate::Str;
+
+
+/// The metric by which to rank stocks.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Serialize)]
+pub enum RankBy {
+  /// Rank stocks by their traded volume.
+  #[serde(rename = "volume")]
+  #[default]
+  Volume,
+  /// Rank stocks by their number of trades.
+  #[serde(rename = "trades")]
+  Trades,
+}
+
+
+/// A GET request to be issued to the
+/// /v1beta1/screener/stocks/most-actives endpoint.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Serialize)]
+pub struct MostActivesReq {
+  /// The metric by which to rank stocks.
+  #[serde(rename = "by")]
+  pub by: RankBy,
+  /// The number of stocks to retrieve.
+  ///
+  /// Defaults to 10 if not set.
+  #[serde(rename = "top", skip_serializing_if = "Option::is_none")]
+  pub top: Option<usize>,
+}
+
+
+/// A stock that is among the most actively traded ones.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct MostActive {
+  /// The stock's symbol.
+  #[serde(rename = "symbol")]
+  pub symbol: String,
+  /// The volume traded.
+  #[serde(rename = "volume")]
+  pub volume: u64,
+  /// The number of trades.
+  #[serde(rename = "trade_count")]
+  pub trade_count: u64,
+}
+
+
+/// The most active stocks as returned by the
+/// /v1beta1/screener/stocks/most-actives endpoint.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct MostActives {
+  /// The most active stocks, in descending order of activity.
+  #[serde(rename = "most_actives")]
+  pub most_actives: Vec<MostActive>,
+  /// The time the data were last updated.
+  #[serde(rename = "last_updated", deserialize_with = "system_time_from_str")]
+  pub last_updated: SystemTime,
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v1beta1/screener/stocks/most-actives endpoint.
+  pub Get(MostActivesReq),
+  Ok => MostActives, [
+    /// The most active stocks were retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// One or more of the arguments are not well formed.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+  ]
+  Api => Data,
+
+  fn path(_input: &Self::Input) -> Str {
+    "/v1beta1/screener/stocks/most-actives".into()
+  }
+
+  fn query(input: &Self::Input) -> Option<Str> {
+    // TODO: Realistically there should be no way for this unwrap to
+    //       ever panic because our conversion to strings should not be
+    //       fallible. But still, ideally we would not have to unwrap.
+    Some(to_query(input).unwrap().into())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use http_endpoint::Endpoint;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+  use time_util::parse_system_time_from_str;
+
+  use crate::api_info::ApiInfo;
+  use crate::Client;
+
+
+  #[test]
+  fn emit_request() {
+    let request = MostActivesReq {
+      by: RankBy::Trades,
+      top: Some(5),
+    };
+
+    assert_eq!(Get::query(&request).unwrap(), "by=trades&top=5");
+  }
+
+  #[test]
+  fn parse_reference_most_actives() {
+    let response = r#"{
+  "most_actives": [
+    {
+      "symbol": "TSLA",
+      "volume": 56477631,
+      "trade_count": 518362
+    },
+    {
+      "symbol": "AAPL",
+      "volume": 43955515,
+      "trade_count": 336840
+    }
+  ],
+  "last_updated": "2021-08-10T20:00:01.123456Z"
+}"#;
+
+    let result = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    assert_eq!(result.most_actives.len(), 2);
+    assert_eq!(result.most_actives[0].symbol, "TSLA");
+    assert_eq!(result.most_actives[0].volume, 56477631);
+    assert_eq!(result.most_actives[1].trade_count, 336840);
+    assert_eq!(
+      result.last_updated,
+      parse_system_time_from_str("2021-08-10T20:00:01.123456Z").unwrap()
+    );
+  }
+
+  #[test(tokio::test)]
+  async fn request_most_actives() {
+    let request = MostActivesReq {
+      top: Some(3),
+      ..Default::default()
+    };
+
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+    let result = client.issue::<Get>(request).await.unwrap();
+
+    assert_eq!(result.most_actives.len(), 3);
+    assert!(result.most_actives[0].volume >= result.most_actives[1].volume);
+  }
+}
@@ -0,0 +1,191 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::SystemTime;
+
+use num_decimal::Num;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_urlencoded::to_string as to_query;
+
+use time_util::system_time_from_str;
+
+use crate::Str;
+
+
+/// The market to retrieve the top movers for.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum MarketType {
+  /// The stock market.
+  #[serde(rename = "stocks")]
+  #[default]
+  Stocks,
+  /// The crypto currency market.
+  #[serde(rename = "crypto")]
+  Crypto,
+}
+
+impl AsRef<str> for MarketType {
+  fn as_ref(&self) -> &'static str {
+    match *self {
+      MarketType::Stocks => "stocks",
+      MarketType::Crypto => "crypto",
+    }
+  }
+}
+
+
+/// A GET request to be issued to the
+/// /v1beta1/screener/<market-type>/movers endpoint.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Serialize)]
+pub struct MoversReq {
+  /// The market to retrieve the top movers for.
+  #[serde(skip)]
+  pub market_type: MarketType,
+  /// The number of gainers and losers to retrieve, each.
+  ///
+  /// Defaults to 10 if not set.
+  #[serde(rename = "top", skip_serializing_if = "Option::is_none")]
+  pub top: Option<usize>,
+}
+
+
+/// A symbol whose price moved significantly.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Mover {
+  /// The mover's symbol.
+  #[serde(rename = "symbol")]
+  pub symbol: String,
+  /// The relative price change, in percent.
+  #[serde(rename = "percent_change")]
+  pub percent_change: Num,
+  /// The absolute price change.
+  #[serde(rename = "change")]
+  pub change: Num,
+  /// The current price.
+  #[serde(rename = "price")]
+  pub price: Num,
+}
+
+
+/// The top market movers as returned by the
+/// /v1beta1/screener/<market-type>/movers endpoint.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Movers {
+  /// The symbols that gained the most, in descending order.
+  #[serde(rename = "gainers")]
+  pub gainers: Vec<Mover>,
+  /// The symbols that lost the most, in descending order.
+  #[serde(rename = "losers")]
+  pub losers: Vec<Mover>,
+  /// The market the movers belong to.
+  #[serde(rename = "market_type")]
+  pub market_type: MarketType,
+  /// The time the data were last updated.
+  #[serde(rename = "last_updated", deserialize_with = "system_time_from_str")]
+  pub last_updated: SystemTime,
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v1beta1/screener/<market-type>/movers endpoint.
+  pub Get(MoversReq),
+  Ok => Movers, [
+    /// The top movers were retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// One or more of the arguments are not well formed.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+  ]
+  Api => Data,
+
+  fn path(input: &Self::Input) -> Str {
+    format!("/v1beta1/screener/{}/movers", input.market_type.as_ref()).into()
+  }
+
+  fn query(input: &Self::Input) -> Option<Str> {
+    // TODO: Realistically there should be no way for this unwrap to
+    //       ever panic because our conversion to strings should not be
+    //       fallible. But still, ideally we would not have to unwrap.
+    Some(to_query(input).unwrap().into())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use http_endpoint::Endpoint;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+  use crate::api_info::ApiInfo;
+  use crate::Client;
+
+
+  #[test]
+  fn emit_request() {
+    let request = MoversReq {
+      market_type: MarketType::Crypto,
+      top: Some(3),
+    };
+
+    assert_eq!(Get::path(&request), "/v1beta1/screener/crypto/movers");
+    assert_eq!(Get::query(&request).unwrap(), "top=3");
+  }
+
+  #[test]
+  fn parse_reference_movers() {
+    let response = r#"{
+  "gainers": [
+    {
+      "symbol": "XYZ",
+      "percent_change": 41.29,
+      "change": 1.79,
+      "price": 6.12
+    }
+  ],
+  "losers": [
+    {
+      "symbol": "ABC",
+      "percent_change": -22.5,
+      "change": -2.25,
+      "price": 7.75
+    }
+  ],
+  "market_type": "stocks",
+  "last_updated": "2021-08-10T20:00:01Z"
+}"#;
+
+    let result = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    assert_eq!(result.market_type, MarketType::Stocks);
+    assert_eq!(result.gainers.len(), 1);
+    assert_eq!(result.gainers[0].symbol, "XYZ");
+    assert_eq!(result.gainers[0].percent_change, Num::new(4129, 100));
+    assert_eq!(result.losers.len(), 1);
+    assert_eq!(result.losers[0].change, Num::new(-225, 100));
+    assert_eq!(result.losers[0].price, Num::new(775, 100));
+  }
+
+  #[test(tokio::test)]
+  async fn request_movers() {
+    let request = MoversReq {
+      top: Some(2),
+      ..Default::default()
+    };
+
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+    let result = client.issue::<Get>(request).await.unwrap();
+
+    assert_eq!(result.market_type, MarketType::Stocks);
+    assert!(result.gainers.len() <= 2);
+    assert!(result.losers.len() <= 2);
+  }
+}
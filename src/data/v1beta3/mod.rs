@@ -0,0 +1,9 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+/// Functionality for maintaining local crypto currency order books.
+#[cfg(feature = "streaming")]
+pub mod orderbook;
+/// Definitions for real time crypto currency market data streaming.
+#[cfg(feature = "streaming")]
+pub mod stream;
@@ -0,0 +1,261 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use futures::Stream;
+use futures::StreamExt as _;
+
+use num_decimal::Num;
+
+use crate::data::v1beta3::stream::Data;
+use crate::data::v1beta3::stream::Level;
+use crate::data::v1beta3::stream::Orderbook;
+use crate::data::v2::stream::MessageError;
+
+
+/// A local copy of the order book of a single currency pair, as
+/// assembled from the snapshots and deltas reported by the stream.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Book {
+  /// The bid levels, mapping price to size.
+  bids: BTreeMap<Num, Num>,
+  /// The ask levels, mapping price to size.
+  asks: BTreeMap<Num, Num>,
+  /// The time of the last update applied.
+  time: Option<SystemTime>,
+}
+
+impl Book {
+  /// Apply an order book update as received through the stream.
+  ///
+  /// A snapshot replaces all existing levels. Levels of zero size are
+  /// removed.
+  pub fn apply(&mut self, update: &Orderbook) {
+    fn merge(levels: &mut BTreeMap<Num, Num>, update: &[Level]) {
+      for level in update {
+        if level.size.is_zero() {
+          let _ = levels.remove(&level.price);
+        } else {
+          let _ = levels.insert(level.price.clone(), level.size.clone());
+        }
+      }
+    }
+
+    if update.reset {
+      self.bids.clear();
+      self.asks.clear();
+    }
+
+    merge(&mut self.bids, &update.bids);
+    merge(&mut self.asks, &update.asks);
+    self.time = Some(update.time);
+  }
+
+  /// Retrieve the bid levels as (price, size) pairs, best (i.e.,
+  /// highest) price first.
+  pub fn bids(&self) -> impl DoubleEndedIterator<Item = (&Num, &Num)> {
+    self.bids.iter().rev()
+  }
+
+  /// Retrieve the ask levels as (price, size) pairs, best (i.e.,
+  /// lowest) price first.
+  pub fn asks(&self) -> impl DoubleEndedIterator<Item = (&Num, &Num)> {
+    self.asks.iter()
+  }
+
+  /// Retrieve the best bid level, if any.
+  pub fn best_bid(&self) -> Option<(&Num, &Num)> {
+    self.bids().next()
+  }
+
+  /// Retrieve the best ask level, if any.
+  pub fn best_ask(&self) -> Option<(&Num, &Num)> {
+    self.asks().next()
+  }
+
+  /// Calculate the difference between the best ask and the best bid
+  /// price, if both sides have levels.
+  pub fn spread(&self) -> Option<Num> {
+    let (bid, _) = self.best_bid()?;
+    let (ask, _) = self.best_ask()?;
+    Some(ask - bid)
+  }
+
+  /// Retrieve the time of the last update applied, if any.
+  #[inline]
+  pub fn time(&self) -> Option<SystemTime> {
+    self.time
+  }
+
+  /// Check whether the book has neither bid nor ask levels.
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.bids.is_empty() && self.asks.is_empty()
+  }
+}
+
+
+/// A handle to the local order books maintained by [`track`].
+///
+/// The handle can be cloned freely. All clones refer to the same set
+/// of books.
+#[derive(Clone, Debug, Default)]
+pub struct Books {
+  /// The books, keyed by currency pair.
+  books: Arc<RwLock<HashMap<String, Book>>>,
+}
+
+impl Books {
+  /// Retrieve a copy of the current book of the given currency pair,
+  /// if any update for it has been received.
+  pub fn get(&self, symbol: &str) -> Option<Book> {
+    self.books.read().unwrap().get(symbol).cloned()
+  }
+
+  /// Inspect the current book of the given currency pair without
+  /// copying it.
+  pub fn with<F, R>(&self, symbol: &str, f: F) -> Option<R>
+  where
+    F: FnOnce(&Book) -> R,
+  {
+    self.books.read().unwrap().get(symbol).map(f)
+  }
+
+  /// Retrieve the currency pairs for which books are maintained.
+  pub fn symbols(&self) -> Vec<String> {
+    self.books.read().unwrap().keys().cloned().collect()
+  }
+
+  /// Apply the given order book update for the given pair.
+  fn apply(&self, symbol: &str, update: &Orderbook) {
+    let mut books = self.books.write().unwrap();
+    match books.get_mut(symbol) {
+      Some(book) => book.apply(update),
+      None => {
+        let mut book = Book::default();
+        book.apply(update);
+        let _ = books.insert(symbol.to_string(), book);
+      },
+    }
+  }
+}
+
+
+/// Maintain local order books based on the updates reported by a
+/// crypto currency market data stream.
+///
+/// All items are passed through unchanged; order book updates are
+/// applied to the books accessible through the returned `Books`
+/// handle before being yielded.
+pub fn track<S, E>(
+  stream: S,
+) -> (
+  Books,
+  impl Stream<Item = Result<Result<Data, MessageError>, E>>,
+)
+where
+  S: Stream<Item = Result<Result<Data, MessageError>, E>>,
+{
+  let books = Books::default();
+  let handle = books.clone();
+  let stream = stream.inspect(move |item| {
+    if let Ok(Ok(Data::Orderbook(orderbook))) = item {
+      books.apply(&orderbook.symbol, &orderbook.data);
+    }
+  });
+  (handle, stream)
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use futures::stream::iter;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+  use crate::data::v2::stream::SymbolData;
+
+
+  fn update(json: &str) -> Orderbook {
+    from_json::<Orderbook>(json).unwrap()
+  }
+
+
+  /// Check that we can assemble a book from a snapshot and deltas.
+  #[test]
+  fn apply_updates() {
+    let mut book = Book::default();
+    assert!(book.is_empty());
+    assert_eq!(book.spread(), None);
+
+    book.apply(&update(
+      r#"{"t":"2022-08-08T14:30:00Z","b":[{"p":99,"s":1},{"p":100,"s":2}],"a":[{"p":101,"s":3},{"p":102,"s":4}],"r":true}"#,
+    ));
+    assert_eq!(book.best_bid(), Some((&Num::from(100), &Num::from(2))));
+    assert_eq!(book.best_ask(), Some((&Num::from(101), &Num::from(3))));
+    assert_eq!(book.spread(), Some(Num::from(1)));
+
+    // Remove the best bid, change an ask, and add a new ask level.
+    book.apply(&update(
+      r#"{"t":"2022-08-08T14:30:01Z","b":[{"p":100,"s":0}],"a":[{"p":102,"s":1},{"p":100.5,"s":1}]}"#,
+    ));
+    let bids = book.bids().map(|(p, _)| p.clone()).collect::<Vec<_>>();
+    assert_eq!(bids, vec![Num::from(99)]);
+    let asks = book
+      .asks()
+      .map(|(p, s)| (p.clone(), s.clone()))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      asks,
+      vec![
+        (Num::new(201, 2), Num::from(1)),
+        (Num::from(101), Num::from(3)),
+        (Num::from(102), Num::from(1)),
+      ]
+    );
+
+    // A snapshot replaces everything.
+    book.apply(&update(
+      r#"{"t":"2022-08-08T14:30:02Z","b":[{"p":98,"s":1}],"a":[],"r":true}"#,
+    ));
+    assert_eq!(book.bids().count(), 1);
+    assert_eq!(book.asks().count(), 0);
+    assert_eq!(book.spread(), None);
+  }
+
+  /// Check that order book updates passing through a stream are
+  /// tracked.
+  #[test(tokio::test)]
+  async fn track_books() {
+    let items = vec![
+      Ok::<_, ()>(Ok(Data::Orderbook(SymbolData {
+        symbol: "BTC/USD".to_string(),
+        data: update(r#"{"t":"2022-08-08T14:30:00Z","b":[{"p":100,"s":2}],"a":[],"r":true}"#),
+      }))),
+      Ok(Ok(Data::Orderbook(SymbolData {
+        symbol: "BTC/USD".to_string(),
+        data: update(r#"{"t":"2022-08-08T14:30:01Z","b":[],"a":[{"p":101,"s":1}]}"#),
+      }))),
+    ];
+
+    let (books, stream) = track(iter(items));
+    assert_eq!(books.get("BTC/USD"), None);
+
+    let count = stream.count().await;
+    assert_eq!(count, 2);
+    assert_eq!(books.symbols(), vec!["BTC/USD".to_string()]);
+    assert_eq!(
+      books.with("BTC/USD", Book::spread),
+      Some(Some(Num::from(1)))
+    );
+  }
+}
@@ -0,0 +1,672 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use futures::stream::iter;
+use futures::Stream;
+use futures::StreamExt;
+
+use num_decimal::Num;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::to_string as to_json;
+
+use time_util::system_time_from_str;
+
+use tokio::sync::mpsc::unbounded_channel;
+use tokio::sync::mpsc::UnboundedSender;
+
+use tracing::debug;
+use tracing::trace;
+
+use websocket_util::stream as do_stream;
+use websocket_util::tungstenite::Error as WebSocketError;
+use websocket_util::tungstenite::Message;
+
+use crate::api_info::ApiInfo;
+use crate::audit::AuditLog;
+use crate::data::v2::stream::decode_control;
+use crate::data::v2::stream::handshake;
+use crate::data::v2::stream::BatchDecoder;
+use crate::data::v2::stream::Connection;
+use crate::data::v2::stream::Header;
+use crate::data::v2::stream::MessageError;
+use crate::data::v2::stream::RawMessage;
+use crate::data::v2::stream::SymbolData;
+use crate::socket::connect as connect_socket;
+use crate::socket::SocketOptions;
+use crate::Client;
+use crate::Error;
+
+
+/// The path of the crypto currency market data stream, relative to
+/// the data stream URL.
+const STREAM_PATH: &str = "v1beta3/crypto/us";
+
+
+/// A set of currency pairs (e.g., `BTC/USD`) to subscribe to or
+/// unsubscribe from, per type of market data.
+///
+/// The pair `*` can be used for referring to all pairs.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct MarketData {
+  /// The pairs for which to receive minute bars.
+  #[serde(rename = "bars", default)]
+  pub bars: Vec<String>,
+  /// The pairs for which to receive daily bars.
+  #[serde(rename = "dailyBars", default)]
+  pub daily_bars: Vec<String>,
+  /// The pairs for which to receive corrections of minute bars, sent
+  /// when late trades arrive.
+  #[serde(rename = "updatedBars", default)]
+  pub updated_bars: Vec<String>,
+  /// The pairs for which to receive quotes.
+  #[serde(rename = "quotes", default)]
+  pub quotes: Vec<String>,
+  /// The pairs for which to receive trades.
+  #[serde(rename = "trades", default)]
+  pub trades: Vec<String>,
+  /// The pairs for which to receive order book updates.
+  #[serde(rename = "orderbooks", default)]
+  pub orderbooks: Vec<String>,
+}
+
+impl MarketData {
+  /// Check whether the object does not reference any pairs.
+  pub fn is_empty(&self) -> bool {
+    self.bars.is_empty()
+      && self.daily_bars.is_empty()
+      && self.updated_bars.is_empty()
+      && self.quotes.is_empty()
+      && self.trades.is_empty()
+      && self.orderbooks.is_empty()
+  }
+}
+
+
+/// A crypto currency bar.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Bar {
+  /// The beginning time of this bar.
+  #[serde(rename = "t", deserialize_with = "system_time_from_str")]
+  pub time: SystemTime,
+  /// The open price.
+  #[serde(rename = "o")]
+  pub open: Num,
+  /// The close price.
+  #[serde(rename = "c")]
+  pub close: Num,
+  /// The highest price.
+  #[serde(rename = "h")]
+  pub high: Num,
+  /// The lowest price.
+  #[serde(rename = "l")]
+  pub low: Num,
+  /// The trading volume, which may be fractional.
+  #[serde(rename = "v")]
+  pub volume: Num,
+  /// The volume weighted average price.
+  #[serde(rename = "vw", default)]
+  pub weighted_average: Option<Num>,
+  /// The number of trades that took place during the bar.
+  #[serde(rename = "n", default)]
+  pub trade_count: Option<u64>,
+}
+
+
+/// A crypto currency quote.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Quote {
+  /// The time of the quote.
+  #[serde(rename = "t", deserialize_with = "system_time_from_str")]
+  pub time: SystemTime,
+  /// The bid price.
+  #[serde(rename = "bp")]
+  pub bid_price: Num,
+  /// The bid size.
+  #[serde(rename = "bs")]
+  pub bid_size: Num,
+  /// The ask price.
+  #[serde(rename = "ap")]
+  pub ask_price: Num,
+  /// The ask size.
+  #[serde(rename = "as")]
+  pub ask_size: Num,
+}
+
+
+/// The side of the party that initiated a trade.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+pub enum TakerSide {
+  /// The taker bought.
+  #[serde(rename = "B")]
+  Buy,
+  /// The taker sold.
+  #[serde(rename = "S")]
+  Sell,
+  /// Any other side that we have not accounted for.
+  ///
+  /// Note that having any such side should be considered a bug.
+  #[serde(other)]
+  Unknown,
+}
+
+
+/// A crypto currency trade.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Trade {
+  /// The time of the trade.
+  #[serde(rename = "t", deserialize_with = "system_time_from_str")]
+  pub time: SystemTime,
+  /// The trade's ID.
+  #[serde(rename = "i")]
+  pub id: u64,
+  /// The price of the trade.
+  #[serde(rename = "p")]
+  pub price: Num,
+  /// The size of the trade, which may be fractional.
+  #[serde(rename = "s")]
+  pub size: Num,
+  /// The side of the party initiating the trade.
+  #[serde(rename = "tks")]
+  pub taker_side: TakerSide,
+}
+
+
+/// A price level of an order book.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Level {
+  /// The price of the level.
+  #[serde(rename = "p")]
+  pub price: Num,
+  /// The size available at the price.
+  ///
+  /// A size of zero signals that the level got removed.
+  #[serde(rename = "s")]
+  pub size: Num,
+}
+
+
+/// An update to the order book of a currency pair.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Orderbook {
+  /// The time of the update.
+  #[serde(rename = "t", deserialize_with = "system_time_from_str")]
+  pub time: SystemTime,
+  /// The bid levels that changed.
+  #[serde(rename = "b", default)]
+  pub bids: Vec<Level>,
+  /// The ask levels that changed.
+  #[serde(rename = "a", default)]
+  pub asks: Vec<Level>,
+  /// Whether the update is a full snapshot of the book, replacing all
+  /// previously reported levels.
+  #[serde(rename = "r", default)]
+  pub reset: bool,
+}
+
+
+/// A crypto currency market data message as received through the
+/// stream.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Data {
+  /// A minute bar.
+  Bar(SymbolData<Bar>),
+  /// A daily bar.
+  DailyBar(SymbolData<Bar>),
+  /// A correction of a previously reported minute bar.
+  UpdatedBar(SymbolData<Bar>),
+  /// A quote.
+  Quote(SymbolData<Quote>),
+  /// A trade.
+  Trade(SymbolData<Trade>),
+  /// An order book update.
+  Orderbook(SymbolData<Orderbook>),
+}
+
+impl Data {
+  /// Retrieve the currency pair the data belongs to.
+  pub fn symbol(&self) -> &str {
+    match self {
+      Self::Bar(bar) | Self::DailyBar(bar) | Self::UpdatedBar(bar) => &bar.symbol,
+      Self::Quote(quote) => &quote.symbol,
+      Self::Trade(trade) => &trade.symbol,
+      Self::Orderbook(orderbook) => &orderbook.symbol,
+    }
+  }
+}
+
+
+/// A request as sent to the server.
+#[derive(Debug, Serialize)]
+#[serde(tag = "action")]
+enum Request<'d> {
+  /// A request to subscribe to market data.
+  #[serde(rename = "subscribe")]
+  Subscribe(&'d MarketData),
+  /// A request to unsubscribe from market data.
+  #[serde(rename = "unsubscribe")]
+  Unsubscribe(&'d MarketData),
+}
+
+impl Request<'_> {
+  fn to_message(&self) -> Message {
+    let json = to_json(self).unwrap();
+    trace!(request = display(&json));
+    Message::text(json)
+  }
+}
+
+
+/// The filter deciding which messages to decode and report.
+#[derive(Debug, Default)]
+struct Filter {
+  /// The pairs subscribed to for receiving minute bars.
+  bars: HashSet<String>,
+  /// The pairs subscribed to for receiving daily bars.
+  daily_bars: HashSet<String>,
+  /// The pairs subscribed to for receiving updated bars.
+  updated_bars: HashSet<String>,
+  /// The pairs subscribed to for receiving quotes.
+  quotes: HashSet<String>,
+  /// The pairs subscribed to for receiving trades.
+  trades: HashSet<String>,
+  /// The pairs subscribed to for receiving order book updates.
+  orderbooks: HashSet<String>,
+}
+
+impl Filter {
+  /// Retrieve the set of pairs and the corresponding list from `data`
+  /// for each type of market data.
+  fn sets<'s, 'd>(
+    &'s mut self,
+    data: &'d MarketData,
+  ) -> [(&'s mut HashSet<String>, &'d Vec<String>); 6] {
+    [
+      (&mut self.bars, &data.bars),
+      (&mut self.daily_bars, &data.daily_bars),
+      (&mut self.updated_bars, &data.updated_bars),
+      (&mut self.quotes, &data.quotes),
+      (&mut self.trades, &data.trades),
+      (&mut self.orderbooks, &data.orderbooks),
+    ]
+  }
+
+  fn add(&mut self, data: &MarketData) {
+    for (set, symbols) in self.sets(data).iter_mut() {
+      set.extend(symbols.iter().cloned());
+    }
+  }
+
+  fn remove(&mut self, data: &MarketData) {
+    for (set, symbols) in self.sets(data).iter_mut() {
+      symbols.iter().for_each(|symbol| {
+        let _ = set.remove(symbol);
+      });
+    }
+  }
+
+  /// Check whether a message of the given type for the given pair
+  /// should be reported.
+  fn accepts(&self, type_: &str, symbol: &str) -> bool {
+    let subscribed = match type_ {
+      "b" => &self.bars,
+      "d" => &self.daily_bars,
+      "u" => &self.updated_bars,
+      "q" => &self.quotes,
+      "t" => &self.trades,
+      "o" => &self.orderbooks,
+      _ => return true,
+    };
+
+    subscribed.contains(symbol) || subscribed.contains("*")
+  }
+}
+
+
+/// A handle for changing the market data subscriptions of a crypto
+/// currency stream.
+///
+/// The handle can be cloned freely. All clones refer to the same
+/// stream.
+#[derive(Clone, Debug)]
+pub struct Subscriber {
+  /// The channel used for sending requests to the server.
+  control: UnboundedSender<Message>,
+  /// The filter applied to received messages.
+  filter: Arc<RwLock<Filter>>,
+}
+
+impl Subscriber {
+  #[allow(clippy::result_large_err)]
+  fn send(&self, request: Request<'_>) -> Result<(), Error> {
+    self
+      .control
+      .send(request.to_message())
+      .map_err(|_| Error::Str("crypto market data stream is no longer active".into()))
+  }
+
+  /// Subscribe to additional market data.
+  ///
+  /// Data is reported as soon as the server acted on the request.
+  #[allow(clippy::result_large_err)]
+  pub fn subscribe(&self, data: &MarketData) -> Result<(), Error> {
+    self.filter.write().unwrap().add(data);
+    self.send(Request::Subscribe(data))
+  }
+
+  /// Unsubscribe from market data.
+  ///
+  /// Messages for the pairs in question that the server sent before
+  /// it acted on the request are dropped without decoding them.
+  #[allow(clippy::result_large_err)]
+  pub fn unsubscribe(&self, data: &MarketData) -> Result<(), Error> {
+    self.filter.write().unwrap().remove(data);
+    self.send(Request::Unsubscribe(data))
+  }
+}
+
+
+/// Decode a single message, unless it is filtered out.
+fn decode(raw: RawMessage<'_>, filter: &Filter) -> Option<Result<Data, MessageError>> {
+  let header = match raw.decode::<Header<'_>>() {
+    Ok(header) => header,
+    Err(err) => return Some(Err(err.into())),
+  };
+
+  let type_ = header.type_.as_ref();
+  match type_ {
+    "b" | "d" | "u" | "q" | "t" | "o" => {
+      let symbol = header.symbol.as_deref().unwrap_or_default();
+      if !filter.accepts(type_, symbol) {
+        trace!(
+          message = "dropping filtered message",
+          symbol = display(symbol)
+        );
+        return None
+      }
+
+      let data = match type_ {
+        "b" => raw.decode().map(Data::Bar),
+        "d" => raw.decode().map(Data::DailyBar),
+        "u" => raw.decode().map(Data::UpdatedBar),
+        "q" => raw.decode().map(Data::Quote),
+        "t" => raw.decode().map(Data::Trade),
+        _ => raw.decode().map(Data::Orderbook),
+      };
+      Some(data.map_err(MessageError::from))
+    },
+    _ => decode_control(raw, type_),
+  }
+}
+
+
+/// Connect to the real time crypto currency market data stream and
+/// subscribe to the provided market data.
+///
+/// The stream mirrors the one for equities (see
+/// `data::v2::stream::stream`): along with it a `Subscriber` is
+/// returned, which can be used for changing the subscriptions later
+/// on, and messages for pairs not subscribed to are dropped without
+/// being decoded in full. Errors are reported in the same way.
+pub async fn stream(
+  api_info: &ApiInfo,
+  data: &MarketData,
+  audit: Option<Arc<AuditLog>>,
+) -> Result<
+  (
+    Subscriber,
+    impl Stream<Item = Result<Result<Data, MessageError>, WebSocketError>>,
+  ),
+  Error,
+> {
+  stream_with(api_info, data, audit, &SocketOptions::default()).await
+}
+
+/// Connect to the real time crypto currency market data stream just
+/// like [`stream`], using the given socket options.
+async fn stream_with(
+  api_info: &ApiInfo,
+  data: &MarketData,
+  audit: Option<Arc<AuditLog>>,
+  options: &SocketOptions,
+) -> Result<
+  (
+    Subscriber,
+    impl Stream<Item = Result<Result<Data, MessageError>, WebSocketError>>,
+  ),
+  Error,
+> {
+  let mut url = api_info.data_stream_url.clone();
+  url.set_path(STREAM_PATH);
+
+  debug!(message = "connecting", url = display(&url));
+  let (mut stream, response) = connect_socket(url, options).await?;
+  debug!("connection successful");
+  trace!(response = debug(&response));
+
+  let subscribe = if data.is_empty() {
+    None
+  } else {
+    Some(Request::Subscribe(data).to_message())
+  };
+  handshake(&mut stream, &api_info.key_id, &api_info.secret, subscribe).await?;
+  debug!("subscription successful");
+
+  let (sender, receiver) = unbounded_channel();
+  let mut filter = Filter::default();
+  filter.add(data);
+
+  let subscriber = Subscriber {
+    control: sender,
+    filter: Arc::new(RwLock::new(filter)),
+  };
+  let filter = subscriber.filter.clone();
+
+  let connection = Connection::new(stream, receiver);
+  let mut decoder = BatchDecoder::default();
+  let stream = do_stream(connection).await.flat_map(move |result| {
+    let (decoded, err) = match result {
+      Ok(data) => {
+        if let Some(audit) = &audit {
+          audit.record_event(&data);
+        }
+
+        let filter = filter.read().unwrap();
+        (decoder.decode(&data, |raw| decode(raw, &filter)), None)
+      },
+      Err(err) => (Vec::new(), Some(err)),
+    };
+    // Report the decoded messages as they are, without collecting them
+    // yet again.
+    iter(decoded.into_iter().map(Ok).chain(err.map(Err)))
+  });
+
+  Ok((subscriber, stream))
+}
+
+
+impl Client {
+  /// Subscribe to real time crypto currency market data.
+  ///
+  /// See [`stream`] for details.
+  pub async fn subscribe_crypto_data(
+    &self,
+    data: &MarketData,
+  ) -> Result<
+    (
+      Subscriber,
+      impl Stream<Item = Result<Result<Data, MessageError>, WebSocketError>>,
+    ),
+    Error,
+  > {
+    stream_with(self.api_info(), data, self.audit(), self.socket_options()).await
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use futures::SinkExt;
+  use futures::TryStreamExt;
+
+  use test_env_log::test;
+
+  use url::Url;
+
+  use websocket_util::test::mock_server;
+  use websocket_util::test::WebSocketStream;
+
+  use crate::api::API_BASE_URL;
+  use crate::api::API_STREAM_URL;
+  use crate::api::BROKER_BASE_URL;
+  use crate::api::DATA_BASE_URL;
+  use crate::data::v2::Feed;
+
+
+  const CONNECTED: &str = r#"[{"T":"success","msg":"connected"}]"#;
+  const AUTH_REQ: &str = r#"{"action":"auth","key":"USER12345678","secret":"justletmein"}"#;
+  const AUTH_RESP: &str = r#"[{"T":"success","msg":"authenticated"}]"#;
+  const SUB_REQ: &str = r#"{"action":"subscribe","bars":[],"dailyBars":[],"updatedBars":[],"quotes":[],"trades":["BTC/USD"],"orderbooks":["ETH/USD"]}"#;
+  const SUB_RESP: &str = r#"[{"T":"subscription","trades":["BTC/USD"],"quotes":[],"orderbooks":["ETH/USD"],"bars":[],"updatedBars":[],"dailyBars":[]}]"#;
+  const DATA: &str = r#"[
+  {"T":"t","S":"BTC/USD","p":23512.5,"s":0.0421,"t":"2022-08-08T14:30:00.123Z","i":31455282,"tks":"B"},
+  {"T":"t","S":"ETH/USD","p":1771.2,"s":1.5,"t":"2022-08-08T14:30:00.124Z","i":31455283,"tks":"S"},
+  {"T":"o","S":"ETH/USD","t":"2022-08-08T14:30:00.125Z","b":[{"p":1771.1,"s":2.5}],"a":[{"p":1771.3,"s":0}]}
+]"#;
+
+
+  /// Perform the server side of the handshake.
+  async fn accept(stream: &mut WebSocketStream) -> Result<(), WebSocketError> {
+    stream.send(Message::Text(CONNECTED.to_string())).await?;
+    assert_eq!(
+      stream.next().await.unwrap()?,
+      Message::Text(AUTH_REQ.to_string()),
+    );
+    stream.send(Message::Text(AUTH_RESP.to_string())).await?;
+    assert_eq!(
+      stream.next().await.unwrap()?,
+      Message::Text(SUB_REQ.to_string()),
+    );
+    stream.send(Message::Text(SUB_RESP.to_string())).await?;
+    Ok(())
+  }
+
+  fn market_data() -> MarketData {
+    MarketData {
+      trades: vec!["BTC/USD".to_string()],
+      orderbooks: vec!["ETH/USD".to_string()],
+      ..Default::default()
+    }
+  }
+
+
+  /// Check that we can decode the various crypto market data messages.
+  #[test]
+  fn decode_messages() {
+    let json = r#"[
+  {"T":"b","S":"BTC/USD","o":23510,"h":23530.5,"l":23500,"c":23512.5,"v":3.2051,"n":88,"vw":23515.2,"t":"2022-08-08T14:30:00Z"},
+  {"T":"d","S":"BTC/USD","o":23000,"h":23600,"l":22950,"c":23512.5,"v":1255.9,"n":40211,"vw":23301.7,"t":"2022-08-08T00:00:00Z"},
+  {"T":"q","S":"BTC/USD","bp":23512,"bs":0.5,"ap":23513,"as":1.25,"t":"2022-08-08T14:30:00.5Z"},
+  {"T":"o","S":"BTC/USD","t":"2022-08-08T14:30:00.6Z","b":[{"p":23512,"s":0.5}],"a":[],"r":true}
+]"#;
+    let mut filter = Filter::default();
+    filter.add(&MarketData {
+      bars: vec!["*".to_string()],
+      quotes: vec!["BTC/USD".to_string()],
+      orderbooks: vec!["BTC/USD".to_string()],
+      ..Default::default()
+    });
+
+    let data = BatchDecoder::default()
+      .decode(json.as_bytes(), |raw| decode(raw, &filter))
+      .into_iter()
+      .collect::<Result<Vec<_>, _>>()
+      .unwrap();
+
+    // The daily bar is not subscribed to and hence dropped.
+    assert_eq!(data.len(), 3);
+    match &data[0] {
+      Data::Bar(bar) => {
+        assert_eq!(bar.symbol, "BTC/USD");
+        assert_eq!(bar.data.volume, Num::new(32051, 10000));
+        assert_eq!(bar.data.trade_count, Some(88));
+      },
+      data => panic!("received unexpected data: {:?}", data),
+    }
+    match &data[1] {
+      Data::Quote(quote) => {
+        assert_eq!(quote.data.bid_size, Num::new(1, 2));
+        assert_eq!(quote.data.ask_price, Num::from(23513));
+      },
+      data => panic!("received unexpected data: {:?}", data),
+    }
+    match &data[2] {
+      Data::Orderbook(orderbook) => {
+        assert!(orderbook.data.reset);
+        assert_eq!(orderbook.data.bids.len(), 1);
+        assert!(orderbook.data.asks.is_empty());
+      },
+      data => panic!("received unexpected data: {:?}", data),
+    }
+  }
+
+  /// Check that we can subscribe to the stream and receive data for
+  /// the pairs and channels subscribed to only.
+  #[test(tokio::test)]
+  async fn stream_crypto_data() {
+    async fn test(mut stream: WebSocketStream) -> Result<(), WebSocketError> {
+      accept(&mut stream).await?;
+      assert_eq!(
+        stream.next().await.unwrap()?,
+        Message::Text(r#"{"action":"unsubscribe","bars":[],"dailyBars":[],"updatedBars":[],"quotes":[],"trades":[],"orderbooks":["ETH/USD"]}"#.into()),
+      );
+      stream.send(Message::Text(DATA.to_string())).await?;
+      stream.send(Message::Close(None)).await?;
+      Ok(())
+    }
+
+    let addr = mock_server(test).await;
+    let api_info = ApiInfo {
+      base_url: Url::parse(API_BASE_URL).unwrap(),
+      stream_url: Url::parse(API_STREAM_URL).unwrap(),
+      data_base_url: Url::parse(DATA_BASE_URL).unwrap(),
+      data_stream_url: Url::parse(&format!("ws://{}", addr)).unwrap(),
+      data_feed: Feed::Iex,
+      broker_base_url: Url::parse(BROKER_BASE_URL).unwrap(),
+      key_id: "USER12345678".to_string(),
+      secret: "justletmein".to_string(),
+    };
+
+    let (subscriber, stream) = stream(&api_info, &market_data(), None).await.unwrap();
+    let unsubscribe = MarketData {
+      orderbooks: vec!["ETH/USD".to_string()],
+      ..Default::default()
+    };
+    subscriber.unsubscribe(&unsubscribe).unwrap();
+
+    let data = stream
+      .map_err(Error::from)
+      .map_ok(|data| data.unwrap())
+      .try_collect::<Vec<_>>()
+      .await
+      .unwrap();
+
+    assert_eq!(data.len(), 1);
+    match &data[0] {
+      Data::Trade(trade) => {
+        assert_eq!(trade.symbol, "BTC/USD");
+        assert_eq!(trade.data.size, Num::new(421, 10000));
+        assert_eq!(trade.data.taker_side, TakerSide::Buy);
+      },
+      data => panic!("received unexpected data: {:?}", data),
+    }
+  }
+}
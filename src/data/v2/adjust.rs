@@ -0,0 +1,285 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use futures::TryStreamExt as _;
+
+use num_decimal::Num;
+
+use crate::data::v1::corporate_actions;
+use crate::data::v1::corporate_actions::Actions;
+use crate::data::v2::bars::Bar;
+use crate::util::us_eastern_offset;
+use crate::Client;
+use crate::RequestError;
+
+
+/// A factor to apply to all bars before a given time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Factor {
+  /// The time before which the factor applies, i.e., the start of
+  /// the ex-date (midnight US Eastern time).
+  pub before: SystemTime,
+  /// The factor to multiply prices with.
+  pub price: Num,
+  /// The factor to multiply volumes with.
+  pub volume: Num,
+}
+
+
+/// Convert an ex-date, as reported by the corporate actions endpoint,
+/// into the start of that day in US Eastern time.
+fn start_of_day(ex_date: SystemTime) -> SystemTime {
+  // It is fine to unwrap here because no time relevant to us can ever
+  // be before `UNIX_EPOCH`.
+  let secs = ex_date.duration_since(UNIX_EPOCH).unwrap().as_secs();
+  let secs = (secs as i64 - us_eastern_offset(secs)) as u64;
+  UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+
+/// Calculate the adjustment factors for the given symbol's splits and
+/// cash dividends.
+///
+/// A split of `old_rate` into `new_rate` shares divides prices by the
+/// split ratio and multiplies volumes by it. A cash dividend multiplies
+/// prices by one minus the ratio of the dividend to the close of the
+/// last bar before the ex-date, leaving volumes untouched. Dividends
+/// for which no such bar exists in `bars`, which have to be in
+/// ascending order, are ignored, as there is nothing to adjust for
+/// them.
+pub fn factors(actions: &Actions, symbol: &str, bars: &[Bar]) -> Vec<Factor> {
+  let forward = actions
+    .forward_splits
+    .iter()
+    .filter(|split| split.symbol == symbol)
+    .map(|split| (split.ex_date, &split.new_rate / &split.old_rate));
+  let reverse = actions
+    .reverse_splits
+    .iter()
+    .filter(|split| split.symbol == symbol)
+    .map(|split| (split.ex_date, &split.new_rate / &split.old_rate));
+  let splits = forward.chain(reverse).map(|(ex_date, ratio)| Factor {
+    before: start_of_day(ex_date),
+    price: Num::from(1) / &ratio,
+    volume: ratio,
+  });
+
+  let dividends = actions
+    .cash_dividends
+    .iter()
+    .filter(|dividend| dividend.symbol == symbol)
+    .filter_map(|dividend| {
+      let before = start_of_day(dividend.ex_date);
+      let index = bars.partition_point(|bar| bar.time < before);
+      let close = &bars[..index].last()?.close;
+      if !close.is_positive() {
+        return None
+      }
+
+      Some(Factor {
+        before,
+        price: Num::from(1) - &dividend.rate / close,
+        volume: Num::from(1),
+      })
+    });
+
+  let mut factors = splits.chain(dividends).collect::<Vec<_>>();
+  factors.sort_by_key(|factor| factor.before);
+  factors
+}
+
+
+/// Back-adjust bars for the given factors.
+///
+/// Each bar is adjusted by all factors applying to times after it,
+/// resulting in prices and volumes that are comparable to those of the
+/// most recent bars. `bars` have to be in ascending order.
+pub fn apply(bars: &mut [Bar], factors: &[Factor]) {
+  let mut factors = factors.iter().rev().peekable();
+  let mut price = Num::from(1);
+  let mut volume = Num::from(1);
+
+  for bar in bars.iter_mut().rev() {
+    while let Some(factor) = factors.next_if(|factor| bar.time < factor.before) {
+      price *= &factor.price;
+      volume *= &factor.volume;
+    }
+
+    bar.open *= &price;
+    bar.high *= &price;
+    bar.low *= &price;
+    bar.close *= &price;
+    if let Some(weighted_average) = &mut bar.weighted_average {
+      *weighted_average *= &price;
+    }
+    // Volumes are integral, but splits may result in fractional ones.
+    // We round those to the nearest integer.
+    bar.volume = (Num::from(bar.volume) * &volume)
+      .round()
+      .to_u64()
+      .unwrap_or(bar.volume);
+  }
+}
+
+
+/// Back-adjust the given symbol's bars for its splits and cash
+/// dividends, as reported by `actions`.
+///
+/// This function produces data matching that retrieved from the API
+/// with all adjustments applied, for bars that were retrieved in raw
+/// form before the corporate actions occurred. `bars` have to be in
+/// ascending order.
+pub fn back_adjust(bars: &mut [Bar], symbol: &str, actions: &Actions) {
+  let factors = factors(actions, symbol, bars);
+  apply(bars, &factors)
+}
+
+
+impl Client {
+  /// Retrieve the splits and cash dividends of the given symbol that
+  /// occurred since the first of the provided bars and back-adjust
+  /// them accordingly.
+  ///
+  /// Please refer to [`back_adjust`] for details.
+  pub async fn back_adjust(
+    &self,
+    symbol: &str,
+    bars: &mut [Bar],
+  ) -> Result<(), RequestError<corporate_actions::GetError>> {
+    let start = match bars.first() {
+      Some(bar) => bar.time,
+      None => return Ok(()),
+    };
+
+    let request = corporate_actions::CorporateActionsReqInit {
+      types: vec![
+        corporate_actions::Type::ForwardSplit,
+        corporate_actions::Type::ReverseSplit,
+        corporate_actions::Type::CashDividend,
+      ],
+      start: Some(start),
+      ..Default::default()
+    }
+    .init([symbol]);
+
+    let actions = self
+      .issue_paged::<corporate_actions::Get>(request)
+      .try_fold(Actions::default(), |mut actions, page| async move {
+        let page = page.actions;
+        actions.forward_splits.extend(page.forward_splits);
+        actions.reverse_splits.extend(page.reverse_splits);
+        actions.cash_dividends.extend(page.cash_dividends);
+        actions.stock_mergers.extend(page.stock_mergers);
+        Ok(actions)
+      })
+      .await?;
+
+    back_adjust(bars, symbol, &actions);
+    Ok(())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+  use time_util::parse_system_time_from_str;
+
+  use crate::test_support;
+
+
+  /// Create a daily bar closing at the given price.
+  fn bar(time: &str, close: Num, volume: u64) -> Bar {
+    let time = parse_system_time_from_str(time).unwrap();
+    test_support::bar(
+      time,
+      close.clone(),
+      close.clone(),
+      close.clone(),
+      close,
+      volume,
+    )
+  }
+
+  /// Check that we back-adjust bars for a split.
+  #[test]
+  fn adjust_for_split() {
+    let actions = from_json::<Actions>(
+      r#"{
+  "forward_splits": [{
+    "symbol": "AAPL",
+    "new_rate": 4,
+    "old_rate": 1,
+    "process_date": "2020-08-31",
+    "ex_date": "2020-08-31"
+  }]
+}"#,
+    )
+    .unwrap();
+
+    let mut bars = vec![
+      bar("2020-08-28T04:00:00Z", Num::from(500), 100),
+      bar("2020-08-31T04:00:00Z", Num::from(125), 400),
+    ];
+    back_adjust(&mut bars, "AAPL", &actions);
+    assert_eq!(bars[0].close, Num::from(125));
+    assert_eq!(bars[0].volume, 400);
+    assert_eq!(bars[1].close, Num::from(125));
+    assert_eq!(bars[1].volume, 400);
+
+    // Actions for other symbols are ignored.
+    let mut bars = vec![bar("2020-08-28T04:00:00Z", Num::from(500), 100)];
+    back_adjust(&mut bars, "MSFT", &actions);
+    assert_eq!(bars[0].close, Num::from(500));
+  }
+
+  /// Check that we back-adjust bars for a cash dividend and a split in
+  /// combination.
+  #[test]
+  fn adjust_for_dividend_and_split() {
+    let actions = from_json::<Actions>(
+      r#"{
+  "reverse_splits": [{
+    "symbol": "XYZ",
+    "new_rate": 1,
+    "old_rate": 2,
+    "process_date": "2021-03-03",
+    "ex_date": "2021-03-03"
+  }],
+  "cash_dividends": [{
+    "symbol": "XYZ",
+    "rate": 1,
+    "special": false,
+    "foreign": false,
+    "process_date": "2021-03-02",
+    "ex_date": "2021-03-02"
+  }]
+}"#,
+    )
+    .unwrap();
+
+    let mut bars = vec![
+      bar("2021-03-01T05:00:00Z", Num::from(50), 100),
+      bar("2021-03-02T05:00:00Z", Num::from(49), 100),
+      bar("2021-03-03T05:00:00Z", Num::from(98), 50),
+    ];
+    back_adjust(&mut bars, "XYZ", &actions);
+    // The first bar is adjusted for the dividend (a factor of 0.98)
+    // and the split (a factor of 2).
+    assert_eq!(bars[0].close, Num::from(98));
+    assert_eq!(bars[0].volume, 50);
+    assert_eq!(bars[1].close, Num::from(98));
+    assert_eq!(bars[1].volume, 50);
+    assert_eq!(bars[2].close, Num::from(98));
+    assert_eq!(bars[2].volume, 50);
+  }
+}
@@ -0,0 +1,330 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Conversion of historical market data into Apache Arrow record
+//! batches and Parquet files.
+//!
+//! Each of the supported types maps to a fixed schema with one column
+//! per member. Times are represented as nanosecond timestamps in UTC
+//! and prices as 64 bit floating point values, which is what most
+//! columnar tooling expects.
+//!
+//! ```no_run
+//! # use apca::data::v2::arrow::write_parquet;
+//! # use apca::data::v2::bars::Bar;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let bars = Vec::<Bar>::new();
+//! let file = std::fs::File::create("bars.parquet")?;
+//! let _file = write_parquet(file, &bars)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io::Write;
+use std::sync::Arc;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use arrow_array::builder::ListBuilder;
+use arrow_array::builder::StringBuilder;
+use arrow_array::ArrayRef;
+use arrow_array::Float64Array;
+use arrow_array::RecordBatch;
+use arrow_array::StringArray;
+use arrow_array::TimestampNanosecondArray;
+use arrow_array::UInt64Array;
+use arrow_schema::ArrowError;
+use arrow_schema::DataType;
+use arrow_schema::Field;
+use arrow_schema::Schema;
+use arrow_schema::SchemaRef;
+use arrow_schema::TimeUnit;
+
+use num_decimal::Num;
+
+use parquet::arrow::ArrowWriter;
+use parquet::errors::ParquetError;
+
+use serde::Serialize;
+use serde_variant::to_variant_name;
+
+use crate::data::v2::bars::Bar;
+use crate::data::v2::quotes::Quote;
+use crate::data::v2::trades::Trade;
+
+
+/// The time zone all timestamps are reported in.
+const UTC: &str = "UTC";
+
+
+/// The data type used for timestamp columns.
+fn timestamp_type() -> DataType {
+  DataType::Timestamp(TimeUnit::Nanosecond, Some(UTC.into()))
+}
+
+/// The data type used for condition columns.
+fn conditions_type() -> DataType {
+  DataType::List(Arc::new(Field::new_list_field(DataType::Utf8, true)))
+}
+
+/// Create a timestamp column from the given times.
+fn timestamps<I>(times: I) -> ArrayRef
+where
+  I: Iterator<Item = SystemTime>,
+{
+  let nanos = times
+    .map(|time| {
+      // It is fine to unwrap here because no time relevant to us can
+      // ever be before `UNIX_EPOCH`.
+      time.duration_since(UNIX_EPOCH).unwrap().as_nanos() as i64
+    })
+    .collect::<Vec<_>>();
+  Arc::new(TimestampNanosecondArray::from(nanos).with_timezone(UTC))
+}
+
+/// Create a floating point column from the given numbers.
+fn floats<'n, I>(nums: I) -> ArrayRef
+where
+  I: Iterator<Item = Option<&'n Num>>,
+{
+  let floats = nums
+    .map(|num| num.and_then(Num::to_f64))
+    .collect::<Vec<_>>();
+  Arc::new(Float64Array::from(floats))
+}
+
+/// Create an unsigned integer column from the given values.
+fn integers<I>(values: I) -> ArrayRef
+where
+  I: Iterator<Item = Option<u64>>,
+{
+  Arc::new(UInt64Array::from(values.collect::<Vec<_>>()))
+}
+
+/// Create a string column from the textual representations of the
+/// given enum variants.
+fn variants<'v, I, T>(variants: I) -> ArrayRef
+where
+  I: Iterator<Item = &'v T>,
+  T: Serialize + 'v,
+{
+  // We know that we are dealing with enum variants and the function
+  // will never return an error for those, so it's fine to unwrap.
+  let names = variants
+    .map(|variant| to_variant_name(variant).unwrap())
+    .collect::<Vec<_>>();
+  Arc::new(StringArray::from(names))
+}
+
+/// Create a list column from the given conditions.
+fn conditions<'c, I>(conditions: I) -> ArrayRef
+where
+  I: Iterator<Item = &'c Vec<String>>,
+{
+  let mut builder = ListBuilder::new(StringBuilder::new());
+  for conditions in conditions {
+    conditions
+      .iter()
+      .for_each(|condition| builder.values().append_value(condition));
+    builder.append(true);
+  }
+  Arc::new(builder.finish())
+}
+
+
+/// A trait for types that can be represented as rows in an Arrow
+/// record batch.
+pub trait Columnar: Sized {
+  /// Retrieve the schema of record batches of this type.
+  fn schema() -> SchemaRef;
+
+  /// Convert a slice of objects into a record batch.
+  fn to_record_batch(objects: &[Self]) -> Result<RecordBatch, ArrowError>;
+}
+
+impl Columnar for Bar {
+  fn schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+      Field::new("time", timestamp_type(), false),
+      Field::new("open", DataType::Float64, true),
+      Field::new("high", DataType::Float64, true),
+      Field::new("low", DataType::Float64, true),
+      Field::new("close", DataType::Float64, true),
+      Field::new("volume", DataType::UInt64, true),
+      Field::new("vwap", DataType::Float64, true),
+      Field::new("trade_count", DataType::UInt64, true),
+    ]))
+  }
+
+  fn to_record_batch(bars: &[Self]) -> Result<RecordBatch, ArrowError> {
+    let columns = vec![
+      timestamps(bars.iter().map(|bar| bar.time)),
+      floats(bars.iter().map(|bar| Some(&bar.open))),
+      floats(bars.iter().map(|bar| Some(&bar.high))),
+      floats(bars.iter().map(|bar| Some(&bar.low))),
+      floats(bars.iter().map(|bar| Some(&bar.close))),
+      integers(bars.iter().map(|bar| Some(bar.volume))),
+      floats(bars.iter().map(|bar| bar.weighted_average.as_ref())),
+      integers(bars.iter().map(|bar| bar.trade_count)),
+    ];
+    RecordBatch::try_new(Self::schema(), columns)
+  }
+}
+
+impl Columnar for Trade {
+  fn schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+      Field::new("time", timestamp_type(), false),
+      Field::new("exchange", DataType::Utf8, false),
+      Field::new("price", DataType::Float64, true),
+      Field::new("size", DataType::UInt64, true),
+      Field::new("conditions", conditions_type(), true),
+      Field::new("id", DataType::UInt64, true),
+      Field::new("tape", DataType::Utf8, false),
+    ]))
+  }
+
+  fn to_record_batch(trades: &[Self]) -> Result<RecordBatch, ArrowError> {
+    let columns = vec![
+      timestamps(trades.iter().map(|trade| trade.time)),
+      variants(trades.iter().map(|trade| &trade.exchange)),
+      floats(trades.iter().map(|trade| Some(&trade.price))),
+      integers(trades.iter().map(|trade| Some(trade.size))),
+      conditions(trades.iter().map(|trade| &trade.conditions)),
+      integers(trades.iter().map(|trade| Some(trade.id))),
+      variants(trades.iter().map(|trade| &trade.tape)),
+    ];
+    RecordBatch::try_new(Self::schema(), columns)
+  }
+}
+
+impl Columnar for Quote {
+  fn schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+      Field::new("time", timestamp_type(), false),
+      Field::new("ask_exchange", DataType::Utf8, false),
+      Field::new("ask_price", DataType::Float64, true),
+      Field::new("ask_size", DataType::UInt64, true),
+      Field::new("bid_exchange", DataType::Utf8, false),
+      Field::new("bid_price", DataType::Float64, true),
+      Field::new("bid_size", DataType::UInt64, true),
+      Field::new("conditions", conditions_type(), true),
+      Field::new("tape", DataType::Utf8, false),
+    ]))
+  }
+
+  fn to_record_batch(quotes: &[Self]) -> Result<RecordBatch, ArrowError> {
+    let columns = vec![
+      timestamps(quotes.iter().map(|quote| quote.time)),
+      variants(quotes.iter().map(|quote| &quote.ask_exchange)),
+      floats(quotes.iter().map(|quote| Some(&quote.ask_price))),
+      integers(quotes.iter().map(|quote| Some(quote.ask_size))),
+      variants(quotes.iter().map(|quote| &quote.bid_exchange)),
+      floats(quotes.iter().map(|quote| Some(&quote.bid_price))),
+      integers(quotes.iter().map(|quote| Some(quote.bid_size))),
+      conditions(quotes.iter().map(|quote| &quote.conditions)),
+      variants(quotes.iter().map(|quote| &quote.tape)),
+    ];
+    RecordBatch::try_new(Self::schema(), columns)
+  }
+}
+
+
+/// Write the given objects to `writer` in the Parquet format.
+///
+/// On success the underlying writer is returned.
+pub fn write_parquet<W, T>(writer: W, objects: &[T]) -> Result<W, ParquetError>
+where
+  W: Write + Send,
+  T: Columnar,
+{
+  let batch = T::to_record_batch(objects)?;
+  let mut writer = ArrowWriter::try_new(writer, T::schema(), None)?;
+  writer.write(&batch)?;
+  writer.into_inner()
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use arrow_array::Array;
+  use arrow_array::ListArray;
+
+  use serde_json::from_str as from_json;
+
+
+  #[test]
+  fn convert_bars() {
+    let bars = [
+      r#"{"t":"2021-02-01T16:01:00Z","o":133.32,"h":133.74,"l":133.31,"c":133.5,"v":9876,"vw":133.5,"n":12}"#,
+      r#"{"t":"2021-02-01T16:02:00Z","o":133.5,"h":133.6,"l":133.4,"c":133.45,"v":1234}"#,
+    ]
+    .iter()
+    .map(|bar| from_json::<Bar>(bar).unwrap())
+    .collect::<Vec<_>>();
+
+    let batch = Bar::to_record_batch(&bars).unwrap();
+    assert_eq!(batch.num_rows(), 2);
+    assert_eq!(batch.num_columns(), 8);
+
+    let time = batch
+      .column(0)
+      .as_any()
+      .downcast_ref::<TimestampNanosecondArray>()
+      .unwrap();
+    assert_eq!(time.value(0), 1_612_195_260_000_000_000);
+
+    let close = batch
+      .column(4)
+      .as_any()
+      .downcast_ref::<Float64Array>()
+      .unwrap();
+    assert_eq!(close.value(1), 133.45);
+
+    let count = batch
+      .column(7)
+      .as_any()
+      .downcast_ref::<UInt64Array>()
+      .unwrap();
+    assert_eq!(count.value(0), 12);
+    assert!(count.is_null(1));
+  }
+
+  #[test]
+  fn convert_trades() {
+    let trade = from_json::<Trade>(
+      r#"{"t":"2021-02-06T13:04:56.334320128Z","x":"C","p":387.62,"s":100,"c":[" ","T"],"i":52983525029461,"z":"B"}"#,
+    )
+    .unwrap();
+
+    let batch = Trade::to_record_batch(&[trade]).unwrap();
+    let exchange = batch
+      .column(1)
+      .as_any()
+      .downcast_ref::<StringArray>()
+      .unwrap();
+    assert_eq!(exchange.value(0), "C");
+
+    let conditions = batch
+      .column(4)
+      .as_any()
+      .downcast_ref::<ListArray>()
+      .unwrap();
+    assert_eq!(conditions.value(0).len(), 2);
+  }
+
+  #[test]
+  fn write_quotes() {
+    let quote = from_json::<Quote>(
+      r#"{"t":"2021-02-06T13:04:56.334320128Z","ax":"C","ap":387.62,"as":1,"bx":"V","bp":387.5,"bs":2,"c":["R"],"z":"C"}"#,
+    )
+    .unwrap();
+
+    let data = write_parquet(Vec::new(), &[quote]).unwrap();
+    // Parquet files start and end with a magic number.
+    assert_eq!(&data[..4], b"PAR1");
+    assert_eq!(&data[data.len() - 4..], b"PAR1");
+  }
+}
@@ -0,0 +1,268 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use num_decimal::Num;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_urlencoded::to_string as to_query;
+
+use time_util::system_time_from_date_str;
+use time_util::system_time_from_str;
+use time_util::system_time_to_rfc3339;
+
+use crate::data::v2::exchanges::Exchange;
+use crate::paged::next_page_by_token;
+use crate::paged::Paged;
+use crate::util::strings_to_str;
+use crate::Str;
+
+
+/// A helper for initializing `AuctionsReq` objects.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AuctionsReqInit {
+  /// See `AuctionsReq::limit`.
+  pub limit: Option<usize>,
+  /// See `AuctionsReq::page_token`.
+  pub page_token: Option<String>,
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl AuctionsReqInit {
+  /// Create an `AuctionsReq` from an `AuctionsReqInit`.
+  pub fn init<I, S>(self, symbols: I, start: SystemTime, end: SystemTime) -> AuctionsReq
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+  {
+    AuctionsReq {
+      symbols: symbols.into_iter().map(Into::into).collect(),
+      start,
+      end,
+      limit: self.limit,
+      page_token: self.page_token,
+    }
+  }
+}
+
+
+/// A GET request to be issued to the /v2/stocks/auctions endpoint.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AuctionsReq {
+  /// The symbols for which to retrieve auction data.
+  #[serde(rename = "symbols", serialize_with = "strings_to_str")]
+  pub symbols: Vec<String>,
+  /// Filter auctions equal to or after this time.
+  #[serde(rename = "start", serialize_with = "system_time_to_rfc3339")]
+  pub start: SystemTime,
+  /// Filter auctions equal to or before this time.
+  #[serde(rename = "end", serialize_with = "system_time_to_rfc3339")]
+  pub end: SystemTime,
+  /// The maximum number of daily auctions to be returned.
+  ///
+  /// It can be between 1 and 10000. Defaults to 1000 if not set.
+  #[serde(rename = "limit", skip_serializing_if = "Option::is_none")]
+  pub limit: Option<usize>,
+  /// If provided, this token is used for retrieving the next page of
+  /// data, following a previous request.
+  #[serde(rename = "page_token", skip_serializing_if = "Option::is_none")]
+  pub page_token: Option<String>,
+}
+
+
+/// A single print of an opening or closing auction.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct AuctionPrint {
+  /// The time of the print.
+  #[serde(rename = "t", deserialize_with = "system_time_from_str")]
+  pub time: SystemTime,
+  /// The exchange the auction took place on.
+  #[serde(rename = "x")]
+  pub exchange: Exchange,
+  /// The auction price.
+  #[serde(rename = "p")]
+  pub price: Num,
+  /// The auction size.
+  #[serde(rename = "s", default)]
+  pub size: Option<u64>,
+  /// The condition flag of the print.
+  #[serde(rename = "c")]
+  pub condition: String,
+}
+
+
+/// The opening and closing auctions of a single trading day.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct DailyAuctions {
+  /// The trading day.
+  #[serde(rename = "d", deserialize_with = "system_time_from_date_str")]
+  pub date: SystemTime,
+  /// The prints of the opening auction.
+  #[serde(rename = "o", default)]
+  pub opening: Vec<AuctionPrint>,
+  /// The prints of the closing auction.
+  #[serde(rename = "c", default)]
+  pub closing: Vec<AuctionPrint>,
+}
+
+
+/// A collection of auctions as returned by the /v2/stocks/auctions
+/// endpoint.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Auctions {
+  /// The daily auctions, keyed by symbol.
+  #[serde(rename = "auctions", default)]
+  pub auctions: HashMap<String, Vec<DailyAuctions>>,
+  /// The token to provide to a request to get the next page of
+  /// auctions for this request.
+  #[serde(rename = "next_page_token")]
+  pub next_page_token: Option<String>,
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the /v2/stocks/auctions
+  /// endpoint.
+  pub Get(AuctionsReq),
+  Ok => Auctions, [
+    /// The auction data was retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// One or more of the arguments are not well formed.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+  ]
+  Api => Data,
+
+  fn path(_input: &Self::Input) -> Str {
+    "/v2/stocks/auctions".into()
+  }
+
+  fn query(input: &Self::Input) -> Option<Str> {
+    // TODO: Realistically there should be no way for this unwrap to
+    //       ever panic because our conversion to strings should not be
+    //       fallible. But still, ideally we would not have to unwrap.
+    Some(to_query(input).unwrap().into())
+  }
+}
+
+impl Paged for Get {
+  fn next_page(input: &Self::Input, output: &Self::Output) -> Option<Self::Input> {
+    next_page_by_token(input, output.next_page_token.as_deref(), |input| {
+      &mut input.page_token
+    })
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use http_endpoint::Endpoint;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+  use time_util::parse_system_time_from_date_str;
+  use time_util::parse_system_time_from_str;
+
+  use crate::api_info::ApiInfo;
+  use crate::Client;
+
+
+  #[test]
+  fn emit_request() {
+    let start = parse_system_time_from_str("2021-04-01T00:00:00Z").unwrap();
+    let end = parse_system_time_from_str("2021-04-02T00:00:00Z").unwrap();
+    let request = AuctionsReqInit {
+      limit: Some(10),
+      ..Default::default()
+    }
+    .init(vec!["AAPL", "MSFT"], start, end);
+
+    let query = to_query(&request).unwrap();
+    let expected = "symbols=AAPL%2CMSFT&start=2021-04-01T00%3A00%3A00%2B00%3A00&\
+                    end=2021-04-02T00%3A00%3A00%2B00%3A00&limit=10";
+    assert_eq!(query, expected);
+  }
+
+  #[test]
+  fn parse_reference_auctions() {
+    let response = r#"{
+  "auctions": {
+    "AAPL": [
+      {
+        "d": "2021-04-01",
+        "o": [
+          {
+            "c": "@",
+            "p": 123.66,
+            "t": "2021-04-01T13:30:00.01351Z",
+            "x": "P"
+          },
+          {
+            "c": "Q",
+            "p": 123.64,
+            "s": 337915,
+            "t": "2021-04-01T13:30:00.616551Z",
+            "x": "Q"
+          }
+        ],
+        "c": [
+          {
+            "c": "6",
+            "p": 123,
+            "s": 5733551,
+            "t": "2021-04-01T20:00:00.228899424Z",
+            "x": "Q"
+          }
+        ]
+      }
+    ]
+  },
+  "next_page_token": null
+}"#;
+
+    let result = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    let aapl = result.auctions.get("AAPL").unwrap();
+    assert_eq!(aapl.len(), 1);
+    assert_eq!(aapl[0].date, parse_system_time_from_date_str("2021-04-01").unwrap());
+    assert_eq!(aapl[0].opening.len(), 2);
+    assert_eq!(aapl[0].opening[0].size, None);
+    assert_eq!(aapl[0].opening[1].price, Num::new(12364, 100));
+    assert_eq!(aapl[0].opening[1].size, Some(337915));
+    assert_eq!(aapl[0].opening[1].exchange, Exchange::NasdaqOmx);
+    assert_eq!(aapl[0].closing.len(), 1);
+    assert_eq!(aapl[0].closing[0].price, Num::from(123));
+    assert_eq!(aapl[0].closing[0].condition, "6");
+    assert_eq!(
+      aapl[0].closing[0].time,
+      parse_system_time_from_str("2021-04-01T20:00:00.228899424Z").unwrap()
+    );
+    assert_eq!(result.next_page_token, None);
+  }
+
+  #[test(tokio::test)]
+  async fn request_auctions() {
+    let start = parse_system_time_from_str("2021-04-01T00:00:00Z").unwrap();
+    let end = parse_system_time_from_str("2021-04-03T00:00:00Z").unwrap();
+    let request = AuctionsReqInit::default().init(vec!["AAPL"], start, end);
+
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+    let result = client.issue::<Get>(request).await.unwrap();
+
+    // April 2nd 2021 was Good Friday, so we expect a single day of
+    // auction data.
+    let aapl = result.auctions.get("AAPL").unwrap();
+    assert_eq!(aapl.len(), 1);
+    assert!(!aapl[0].opening.is_empty());
+    assert!(!aapl[0].closing.is_empty());
+  }
+}
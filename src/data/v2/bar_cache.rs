@@ -0,0 +1,454 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! An on-disk cache for historical bars.
+//!
+//! Bars downloaded for a given symbol, time frame, and time range are
+//! persisted by a `Store` and served from there when the same range is
+//! requested again, saving on requests counting against the quota,
+//! e.g., when iterating on research code. The `Directory` store keeps
+//! one CSV file per range, at
+//! `<directory>/<symbol>/<timeframe>/<start>_<end>.csv` (with an
+//! `_asof<date>` suffix if the request maps symbols as of a date).
+//! With the `sled` feature enabled, the `Sled` store keeps the same
+//! data in a sled database instead. Other backends can be plugged in
+//! by implementing `Store`.
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use std::time::SystemTime;
+//! # use apca::data::v2::bar_cache::BarCache;
+//! # use apca::data::v2::bar_cache::Directory;
+//! # use apca::data::v2::bars;
+//! # use apca::ApiInfo;
+//! # use apca::Client;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let start = SystemTime::now() - Duration::from_secs(86400 * 30);
+//! # let end = SystemTime::now() - Duration::from_secs(86400);
+//! let client = Client::new(ApiInfo::from_env()?);
+//! let cache = BarCache::new(Directory::new("bars"));
+//! let request =
+//!   bars::BarsReqInit::default().init("AAPL", start, end, bars::TimeFrame::OneDay);
+//! // Only the first call results in requests to the API.
+//! let bars = cache.bars(&client, request.clone()).await?;
+//! let bars = cache.bars(&client, request).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::error::Error as StdError;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::fs::create_dir_all;
+use std::fs::rename;
+use std::fs::File;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+#[cfg(feature = "sled")]
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use futures::TryStreamExt as _;
+
+use serde_variant::to_variant_name;
+
+use crate::data::v2::bars;
+use crate::data::v2::bars::Bar;
+use crate::data::v2::bars::TimeFrame;
+use crate::data::v2::csv::Reader;
+use crate::data::v2::csv::Writer;
+use crate::data::v2::Sort;
+use crate::util::system_time_to_date_string;
+use crate::util::system_time_to_rfc3339_string;
+use crate::Client;
+use crate::RequestError;
+
+
+/// The key identifying a range of bars in a `Store`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Key {
+  /// The symbol the bars belong to.
+  pub symbol: String,
+  /// The time frame of the bars.
+  pub timeframe: TimeFrame,
+  /// The start of the time range covered.
+  pub start: SystemTime,
+  /// The end of the time range covered.
+  pub end: SystemTime,
+  /// The date as of which the symbol got mapped, if any.
+  pub asof: Option<SystemTime>,
+}
+
+impl From<&bars::BarsReq> for Key {
+  fn from(request: &bars::BarsReq) -> Self {
+    Self {
+      symbol: request.symbol.clone(),
+      timeframe: request.timeframe,
+      start: request.start,
+      end: request.end,
+      asof: request.asof,
+    }
+  }
+}
+
+impl Key {
+  /// Retrieve the components naming the range of bars described by
+  /// the key: the symbol, the time frame, and the time range along
+  /// with the symbol mapping date.
+  fn components(&self) -> [String; 3] {
+    // Colons are not valid in file names on all systems, so we use the
+    // basic ISO 8601 format for times.
+    let time = |time| system_time_to_rfc3339_string(time).replace(&['-', ':'][..], "");
+    // We know that we are dealing with an enum variant and the
+    // function will never return an error for those, so it's fine to
+    // unwrap.
+    let timeframe = to_variant_name(&self.timeframe).unwrap();
+
+    let asof = self
+      .asof
+      .map(|asof| {
+        format!(
+          "_asof{}",
+          system_time_to_date_string(&asof).replace('-', "")
+        )
+      })
+      .unwrap_or_default();
+
+    [
+      self.symbol.replace('/', "-"),
+      timeframe.to_string(),
+      format!("{}_{}{}", time(&self.start), time(&self.end), asof),
+    ]
+  }
+}
+
+
+/// A trait for persistence backends of a `BarCache`.
+pub trait Store {
+  /// Load the bars stored for the given key, if any.
+  fn load(&self, key: &Key) -> Result<Option<Vec<Bar>>, IoError>;
+
+  /// Store bars under the given key, replacing any stored previously.
+  fn store(&self, key: &Key, bars: &[Bar]) -> Result<(), IoError>;
+}
+
+
+/// A `Store` keeping bars in CSV files within a directory.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Directory {
+  /// The directory containing the files.
+  directory: PathBuf,
+}
+
+impl Directory {
+  /// Create a new `Directory` store using the given directory, which
+  /// is created as necessary.
+  pub fn new<P>(directory: P) -> Self
+  where
+    P: Into<PathBuf>,
+  {
+    Self {
+      directory: directory.into(),
+    }
+  }
+
+  /// Retrieve the path of the file storing bars for the given key.
+  fn path(&self, key: &Key) -> PathBuf {
+    let [symbol, timeframe, range] = key.components();
+    self
+      .directory
+      .join(symbol)
+      .join(timeframe)
+      .join(format!("{}.csv", range))
+  }
+}
+
+impl Store for Directory {
+  fn load(&self, key: &Key) -> Result<Option<Vec<Bar>>, IoError> {
+    let file = match File::open(self.path(key)) {
+      Ok(file) => file,
+      Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+      Err(err) => return Err(err),
+    };
+
+    let bars = Reader::<_, Bar>::new(file)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(Some(bars))
+  }
+
+  fn store(&self, key: &Key, bars: &[Bar]) -> Result<(), IoError> {
+    let path = self.path(key);
+    // It is fine to unwrap here because our paths always have a
+    // parent.
+    let () = create_dir_all(path.parent().unwrap())?;
+
+    // Write to a temporary file first and move it into place only once
+    // complete, so that an interrupted write never leaves behind a
+    // truncated file that would later be served as complete.
+    let partial = path.with_extension("csv.partial");
+    let mut writer = Writer::<_, Bar>::new(File::create(&partial)?)?;
+    let () = writer.write_all(bars)?;
+    let file = writer.into_inner()?;
+    let () = file.sync_all()?;
+    rename(partial, path)
+  }
+}
+
+
+
+/// A `Store` keeping bars in a sled database.
+///
+/// Bars are stored in CSV format, keyed by
+/// `<symbol>/<timeframe>/<start>_<end>` (with the same `_asof<date>`
+/// suffix as used by `Directory`).
+#[cfg(feature = "sled")]
+#[derive(Clone, Debug)]
+pub struct Sled {
+  /// The database containing the bars.
+  db: sled::Db,
+}
+
+#[cfg(feature = "sled")]
+impl Sled {
+  /// Open the sled database at the given path, creating it as
+  /// necessary.
+  pub fn open<P>(path: P) -> Result<Self, IoError>
+  where
+    P: AsRef<Path>,
+  {
+    let db = sled::open(path).map_err(IoError::from)?;
+    Ok(Self::from_db(db))
+  }
+
+  /// Create a new `Sled` store using an already opened database.
+  pub fn from_db(db: sled::Db) -> Self {
+    Self { db }
+  }
+}
+
+#[cfg(feature = "sled")]
+impl Store for Sled {
+  fn load(&self, key: &Key) -> Result<Option<Vec<Bar>>, IoError> {
+    let data = match self.db.get(key.components().join("/"))? {
+      Some(data) => data,
+      None => return Ok(None),
+    };
+
+    let bars = Reader::<_, Bar>::new(data.as_ref())?.collect::<Result<Vec<_>, _>>()?;
+    Ok(Some(bars))
+  }
+
+  fn store(&self, key: &Key, bars: &[Bar]) -> Result<(), IoError> {
+    let mut writer = Writer::<_, Bar>::new(Vec::new())?;
+    let () = writer.write_all(bars)?;
+    let data = writer.into_inner()?;
+
+    let _previous = self.db.insert(key.components().join("/"), data)?;
+    let _size = self.db.flush()?;
+    Ok(())
+  }
+}
+
+
+/// An error reported by a `BarCache`.
+#[derive(Debug)]
+pub enum CacheError {
+  /// The store could not be accessed.
+  Io(IoError),
+  /// The bars could not be retrieved.
+  Request(RequestError<bars::GetError>),
+}
+
+impl Display for CacheError {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    match self {
+      Self::Io(err) => write!(fmt, "failed to access bar store: {}", err),
+      Self::Request(err) => write!(fmt, "failed to retrieve bars: {}", err),
+    }
+  }
+}
+
+impl StdError for CacheError {
+  fn source(&self) -> Option<&(dyn StdError + 'static)> {
+    match self {
+      Self::Io(err) => Some(err),
+      Self::Request(err) => Some(err),
+    }
+  }
+}
+
+
+/// A cache for historical bars, persisting them using a `Store`.
+///
+/// Bars are cached per symbol, time frame, time range, and symbol
+/// mapping date; requests for ranges overlapping but not equal to a
+/// cached one are issued to the API. Ranges ending in the future are
+/// never stored, as more bars may become available later on.
+#[derive(Debug)]
+pub struct BarCache<S> {
+  /// The store used for persisting bars.
+  store: S,
+}
+
+impl<S> BarCache<S>
+where
+  S: Store,
+{
+  /// Create a new `BarCache` persisting bars using the given store.
+  pub fn new(store: S) -> Self {
+    Self { store }
+  }
+
+  /// Retrieve all bars for the given request, consulting the store
+  /// first.
+  ///
+  /// If no bars are stored for the request, all pages of bars are
+  /// retrieved using the provided client and the result is stored,
+  /// unless the request's end lies in the future. The request's `limit`
+  /// merely controls the page size used in that case.
+  pub async fn bars(
+    &self,
+    client: &Client,
+    request: bars::BarsReq,
+  ) -> Result<Vec<Bar>, CacheError> {
+    let key = Key::from(&request);
+    let mut bars = match self.store.load(&key).map_err(CacheError::Io)? {
+      Some(bars) => bars,
+      None => {
+        // Bars are always stored in ascending order.
+        let mut request = request.clone();
+        request.sort = None;
+        request.page_token = None;
+
+        let bars = client
+          .issue_paged::<bars::Get>(request)
+          .map_ok(|page| page.bars)
+          .try_concat()
+          .await
+          .map_err(CacheError::Request)?;
+        if key.end <= SystemTime::now() {
+          let () = self.store.store(&key, &bars).map_err(CacheError::Io)?;
+        }
+        bars
+      },
+    };
+
+    if request.sort == Some(Sort::Descending) {
+      bars.reverse();
+    }
+    Ok(bars)
+  }
+
+  /// Retrieve the store used for persisting bars.
+  pub fn store(&self) -> &S {
+    &self.store
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::env::temp_dir;
+  use std::fs::remove_dir_all;
+
+  use num_decimal::Num;
+
+  use test_env_log::test;
+
+  use time_util::parse_system_time_from_str;
+
+  use uuid::Uuid;
+
+  use crate::test_support;
+
+
+  /// Create a key and a couple of bars for it.
+  fn key_and_bars() -> (Key, Vec<Bar>) {
+    let key = Key {
+      symbol: "AAPL".to_string(),
+      timeframe: TimeFrame::OneDay,
+      start: parse_system_time_from_str("2021-02-01T00:00:00Z").unwrap(),
+      end: parse_system_time_from_str("2021-02-03T00:00:00Z").unwrap(),
+      asof: None,
+    };
+    let bars = vec![
+      test_support::bar(
+        parse_system_time_from_str("2021-02-01T05:00:00Z").unwrap(),
+        Num::from(133),
+        Num::from(136),
+        Num::from(132),
+        Num::from(134),
+        1000,
+      ),
+      test_support::bar(
+        parse_system_time_from_str("2021-02-02T05:00:00Z").unwrap(),
+        Num::from(134),
+        Num::from(137),
+        Num::from(133),
+        Num::from(135),
+        2000,
+      ),
+    ];
+    (key, bars)
+  }
+
+  /// Check that we can store bars in and load them from a `Directory`.
+  #[test]
+  fn store_and_load_bars() {
+    let directory = temp_dir().join(Uuid::new_v4().to_string());
+    let store = Directory::new(&directory);
+    let (key, bars) = key_and_bars();
+    assert_eq!(
+      store.path(&key),
+      directory.join("AAPL/1Day/20210201T000000Z_20210203T000000Z.csv")
+    );
+
+    let asof = Key {
+      asof: Some(parse_system_time_from_str("2021-06-01T00:00:00Z").unwrap()),
+      ..key.clone()
+    };
+    assert_eq!(
+      store.path(&asof),
+      directory.join("AAPL/1Day/20210201T000000Z_20210203T000000Z_asof20210601.csv")
+    );
+    assert_eq!(store.load(&key).unwrap(), None);
+
+    let () = store.store(&key, &bars).unwrap();
+    assert_eq!(store.load(&key).unwrap(), Some(bars.clone()));
+
+    // Storing bars again replaces the previous ones.
+    let () = store.store(&key, &bars[..1]).unwrap();
+    assert_eq!(store.load(&key).unwrap(), Some(bars[..1].to_vec()));
+
+    remove_dir_all(directory).unwrap();
+  }
+
+  /// Check that we can store bars in and load them from a `Sled`
+  /// database.
+  #[cfg(feature = "sled")]
+  #[test]
+  fn store_and_load_bars_in_sled() {
+    let db = sled::Config::new().temporary(true).open().unwrap();
+    let store = Sled::from_db(db);
+    let (key, bars) = key_and_bars();
+    assert_eq!(store.load(&key).unwrap(), None);
+
+    let () = store.store(&key, &bars).unwrap();
+    assert_eq!(store.load(&key).unwrap(), Some(bars.clone()));
+
+    // Bars for a different symbol mapping date are stored separately.
+    let asof = Key {
+      asof: Some(parse_system_time_from_str("2021-06-01T00:00:00Z").unwrap()),
+      ..key.clone()
+    };
+    assert_eq!(store.load(&asof).unwrap(), None);
+
+    // Storing bars again replaces the previous ones.
+    let () = store.store(&key, &bars[..1]).unwrap();
+    assert_eq!(store.load(&key).unwrap(), Some(bars[..1].to_vec()));
+  }
+}
@@ -0,0 +1,429 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::borrow::Cow;
+use std::time::SystemTime;
+
+use num_decimal::Num;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_urlencoded::to_string as to_query;
+
+use time_util::system_time_from_str;
+use time_util::system_time_to_rfc3339;
+
+use crate::data::v2::Feed;
+use crate::data::v2::Sort;
+use crate::paged::next_page_by_token;
+use crate::paged::Paged;
+use crate::util::optional_cow_str;
+use crate::util::optional_system_time_to_date_str;
+use crate::util::vec_from_optional;
+use crate::Str;
+
+
+/// An enumeration of the various supported time frames.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+pub enum TimeFrame {
+  /// A time frame of one minute.
+  #[serde(rename = "1Min")]
+  OneMinute,
+  /// A time frame of five minutes.
+  #[serde(rename = "5Min")]
+  FiveMinutes,
+  /// A time frame of fifteen minutes.
+  #[serde(rename = "15Min")]
+  FifteenMinutes,
+  /// A time frame of one hour.
+  #[serde(rename = "1Hour")]
+  OneHour,
+  /// A time frame of one day.
+  #[serde(rename = "1Day")]
+  OneDay,
+}
+
+
+/// A helper for initializing `BarsReq` objects.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BarsReqInit {
+  /// See `BarsReq::limit`.
+  pub limit: Option<usize>,
+  /// See `BarsReq::page_token`.
+  pub page_token: Option<String>,
+  /// See `BarsReq::sort`.
+  pub sort: Option<Sort>,
+  /// See `BarsReq::asof`.
+  pub asof: Option<SystemTime>,
+  /// See `BarsReq::feed`.
+  pub feed: Option<Feed>,
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl BarsReqInit {
+  /// Create a `BarsReq` from a `BarsReqInit`.
+  pub fn init<S>(
+    self,
+    symbol: S,
+    start: SystemTime,
+    end: SystemTime,
+    timeframe: TimeFrame,
+  ) -> BarsReq
+  where
+    S: Into<String>,
+  {
+    BarsReq {
+      symbol: symbol.into(),
+      start,
+      end,
+      timeframe,
+      limit: self.limit,
+      page_token: self.page_token,
+      sort: self.sort,
+      asof: self.asof,
+      feed: self.feed,
+    }
+  }
+}
+
+
+/// A GET request to be issued to the /v2/stocks/<symbol>/bars endpoint.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BarsReq {
+  /// The symbol for which to retrieve market data.
+  #[serde(skip)]
+  pub symbol: String,
+  /// Filter bars equal to or after this time.
+  #[serde(rename = "start", serialize_with = "system_time_to_rfc3339")]
+  pub start: SystemTime,
+  /// Filter bars equal to or before this time.
+  #[serde(rename = "end", serialize_with = "system_time_to_rfc3339")]
+  pub end: SystemTime,
+  /// The time frame for the bars.
+  #[serde(rename = "timeframe")]
+  pub timeframe: TimeFrame,
+  /// The maximum number of bars to be returned.
+  ///
+  /// It can be between 1 and 10000. Defaults to 1000 if not set.
+  #[serde(rename = "limit", skip_serializing_if = "Option::is_none")]
+  pub limit: Option<usize>,
+  /// If provided, this token is used for retrieving the next page of
+  /// data, following a previous request.
+  #[serde(rename = "page_token", skip_serializing_if = "Option::is_none")]
+  pub page_token: Option<String>,
+  /// The order in which to return bars.
+  ///
+  /// Defaults to ascending order if not set.
+  #[serde(rename = "sort", skip_serializing_if = "Option::is_none")]
+  pub sort: Option<Sort>,
+  /// The date as of which to map symbols.
+  ///
+  /// Providing a date allows for retrieving data for symbols that got
+  /// renamed or delisted since, using the symbol as it was valid on
+  /// this date. If not set, the current date is used.
+  #[serde(
+    rename = "asof",
+    serialize_with = "optional_system_time_to_date_str",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub asof: Option<SystemTime>,
+  /// The data feed to retrieve bars from.
+  ///
+  /// If not set, the feed configured in the client's `ApiInfo` is
+  /// used.
+  #[serde(rename = "feed", skip_serializing_if = "Option::is_none")]
+  pub feed: Option<Feed>,
+}
+
+
+/// A market data bar as returned by the /v2/stocks/<symbol>/bars
+/// endpoint.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Bar {
+  /// The beginning time of this bar.
+  #[serde(rename = "t", deserialize_with = "system_time_from_str")]
+  pub time: SystemTime,
+  /// The open price.
+  #[serde(rename = "o")]
+  pub open: Num,
+  /// The close price.
+  #[serde(rename = "c")]
+  pub close: Num,
+  /// The highest price.
+  #[serde(rename = "h")]
+  pub high: Num,
+  /// The lowest price.
+  #[serde(rename = "l")]
+  pub low: Num,
+  /// The trading volume.
+  #[serde(rename = "v")]
+  pub volume: u64,
+  /// The volume weighted average price.
+  #[serde(rename = "vw", default)]
+  pub weighted_average: Option<Num>,
+  /// The number of trades that took place during the bar.
+  #[serde(rename = "n", default)]
+  pub trade_count: Option<u64>,
+}
+
+
+/// A collection of bars as returned by the /v2/stocks/<symbol>/bars
+/// endpoint.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Bars {
+  /// The list of returned bars.
+  #[serde(rename = "bars", deserialize_with = "vec_from_optional")]
+  pub bars: Vec<Bar>,
+  /// The symbol the bars correspond to.
+  #[serde(rename = "symbol")]
+  pub symbol: String,
+  /// The token to provide to a request to get the next page of bars
+  /// for this request.
+  #[serde(rename = "next_page_token")]
+  pub next_page_token: Option<String>,
+}
+
+
+/// The borrowed counterpart to `Bars`.
+///
+/// The type is meant to be used in conjunction with
+/// `Client::issue_raw`, for cases where allocations are to be kept to
+/// a minimum.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct BorrowedBars<'a> {
+  /// The list of returned bars.
+  #[serde(rename = "bars", deserialize_with = "vec_from_optional")]
+  pub bars: Vec<Bar>,
+  /// The symbol the bars correspond to.
+  #[serde(rename = "symbol", borrow)]
+  pub symbol: Cow<'a, str>,
+  /// The token to provide to a request to get the next page of bars
+  /// for this request.
+  #[serde(rename = "next_page_token", borrow, deserialize_with = "optional_cow_str")]
+  pub next_page_token: Option<Cow<'a, str>>,
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v2/stocks/<symbol>/bars endpoint.
+  pub Get(BarsReq),
+  Ok => Bars, [
+    /// The market data was retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// One or more of the arguments are not well formed.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+    /// The provided symbol was not found.
+    /* 404 */ NOT_FOUND => NotFound,
+    /// One or more of the arguments are invalid.
+    /* 422 */ UNPROCESSABLE_ENTITY => InvalidArgument,
+  ]
+  Api => Data,
+  Prepare => |input, api_info| {
+    let _ = input.feed.get_or_insert(api_info.data_feed());
+  },
+
+  fn path(input: &Self::Input) -> Str {
+    format!("/v2/stocks/{}/bars", input.symbol).into()
+  }
+
+  fn query(input: &Self::Input) -> Option<Str> {
+    // TODO: Realistically there should be no way for this unwrap to
+    //       ever panic because our conversion to strings should not be
+    //       fallible. But still, ideally we would not have to unwrap.
+    Some(to_query(input).unwrap().into())
+  }
+}
+
+impl Paged for Get {
+  fn next_page(input: &Self::Input, output: &Self::Output) -> Option<Self::Input> {
+    next_page_by_token(input, output.next_page_token.as_deref(), |input| {
+      &mut input.page_token
+    })
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use http_endpoint::Endpoint;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+  use time_util::parse_system_time_from_str;
+
+  use crate::api_info::ApiInfo;
+  use crate::Client;
+
+
+  #[test]
+  fn emit_request() {
+    let start = parse_system_time_from_str("2021-02-01T00:00:00Z").unwrap();
+    let end = parse_system_time_from_str("2021-02-02T00:00:00Z").unwrap();
+    let request = BarsReqInit {
+      limit: Some(2),
+      ..Default::default()
+    }
+    .init("SPY", start, end, TimeFrame::OneHour);
+
+    assert_eq!(Get::path(&request), "/v2/stocks/SPY/bars");
+    let expected = "start=2021-02-01T00%3A00%3A00%2B00%3A00&\
+                    end=2021-02-02T00%3A00%3A00%2B00%3A00&timeframe=1Hour&limit=2";
+    assert_eq!(Get::query(&request).unwrap(), expected);
+  }
+
+  #[test]
+  fn emit_sorted_request() {
+    let start = parse_system_time_from_str("2021-02-01T00:00:00Z").unwrap();
+    let end = parse_system_time_from_str("2021-02-02T00:00:00Z").unwrap();
+    let request = BarsReqInit {
+      sort: Some(Sort::Descending),
+      asof: Some(parse_system_time_from_str("2020-06-01T00:00:00Z").unwrap()),
+      ..Default::default()
+    }
+    .init("FB", start, end, TimeFrame::OneDay);
+
+    let expected = "start=2021-02-01T00%3A00%3A00%2B00%3A00&\
+                    end=2021-02-02T00%3A00%3A00%2B00%3A00&timeframe=1Day&\
+                    sort=desc&asof=2020-06-01";
+    assert_eq!(Get::query(&request).unwrap(), expected);
+  }
+
+  #[test]
+  fn parse_reference_bars() {
+    let response = r#"{
+  "bars": [
+    {
+      "t": "2021-02-01T16:01:00Z",
+      "o": 133.32,
+      "h": 133.74,
+      "l": 133.31,
+      "c": 133.5,
+      "v": 9876,
+      "n": 63,
+      "vw": 133.513
+    }
+  ],
+  "symbol": "AAPL",
+  "next_page_token": "MjAyMS0wMi0wMVQxNDowMjowMFo7MQ=="
+}"#;
+
+    let result = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    assert_eq!(result.symbol, "AAPL");
+    assert_eq!(result.bars.len(), 1);
+    assert_eq!(
+      result.bars[0].time,
+      parse_system_time_from_str("2021-02-01T16:01:00Z").unwrap()
+    );
+    assert_eq!(result.bars[0].open, Num::new(13332, 100));
+    assert_eq!(result.bars[0].high, Num::new(13374, 100));
+    assert_eq!(result.bars[0].low, Num::new(13331, 100));
+    assert_eq!(result.bars[0].close, Num::new(1335, 10));
+    assert_eq!(result.bars[0].volume, 9876);
+    assert_eq!(result.bars[0].weighted_average, Some(Num::new(133513, 1000)));
+    assert_eq!(result.bars[0].trade_count, Some(63));
+    assert_eq!(
+      result.next_page_token.as_deref(),
+      Some("MjAyMS0wMi0wMVQxNDowMjowMFo7MQ==")
+    );
+  }
+
+  #[test]
+  fn next_bars_page() {
+    let start = parse_system_time_from_str("2021-02-01T00:00:00Z").unwrap();
+    let end = parse_system_time_from_str("2021-02-02T00:00:00Z").unwrap();
+    let request = BarsReqInit::default().init("AAPL", start, end, TimeFrame::OneMinute);
+    let response = r#"{
+  "bars": [],
+  "symbol": "AAPL",
+  "next_page_token": "MjAyMS0wMi0wMVQxNDowMjowMFo7MQ=="
+}"#;
+
+    let output = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    let next = Get::next_page(&request, &output).unwrap();
+    assert_eq!(
+      next.page_token.as_deref(),
+      Some("MjAyMS0wMi0wMVQxNDowMjowMFo7MQ==")
+    );
+    assert_eq!(next.start, request.start);
+
+    let response = r#"{"bars": [], "symbol": "AAPL", "next_page_token": null}"#;
+    let output = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    assert_eq!(Get::next_page(&next, &output), None);
+  }
+
+  #[test]
+  fn parse_borrowed_bars() {
+    let response = r#"{
+  "bars": [
+    {
+      "t": "2021-02-01T16:01:00Z",
+      "o": 133.32,
+      "h": 133.74,
+      "l": 133.31,
+      "c": 133.5,
+      "v": 9876
+    }
+  ],
+  "symbol": "AAPL",
+  "next_page_token": null
+}"#;
+
+    let result = from_json::<BorrowedBars<'_>>(response).unwrap();
+    assert!(matches!(result.symbol, Cow::Borrowed("AAPL")));
+    assert_eq!(result.bars.len(), 1);
+    assert_eq!(result.bars[0].volume, 9876);
+    assert_eq!(result.bars[0].weighted_average, None);
+    assert_eq!(result.bars[0].trade_count, None);
+    assert_eq!(result.next_page_token, None);
+  }
+
+  #[test]
+  fn parse_borrowed_bars_with_escaped_token() {
+    let response = r#"{"bars":[],"symbol":"AAPL","next_page_token":"QUFQTHwy\/MDIx"}"#;
+    let result = from_json::<BorrowedBars<'_>>(response).unwrap();
+    assert!(matches!(result.next_page_token, Some(Cow::Owned(_))));
+    assert_eq!(result.next_page_token.as_deref(), Some("QUFQTHwy/MDIx"));
+
+    let response = r#"{"bars":[],"symbol":"AAPL","next_page_token":"QUFQTHwyMDIx"}"#;
+    let result = from_json::<BorrowedBars<'_>>(response).unwrap();
+    assert!(matches!(result.next_page_token, Some(Cow::Borrowed("QUFQTHwyMDIx"))));
+  }
+
+  #[test]
+  fn parse_empty_bars() {
+    let response = r#"{"bars":null,"symbol":"AAPL","next_page_token":null}"#;
+    let result = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    assert_eq!(result.bars, Vec::new());
+    assert_eq!(result.next_page_token, None);
+  }
+
+  #[test(tokio::test)]
+  async fn request_bars() {
+    let start = parse_system_time_from_str("2021-02-08T00:00:00Z").unwrap();
+    let end = parse_system_time_from_str("2021-02-13T00:00:00Z").unwrap();
+    let request = BarsReqInit {
+      limit: Some(2),
+      ..Default::default()
+    }
+    .init("AAPL", start, end, TimeFrame::OneDay);
+
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+    let result = client.issue::<Get>(request).await.unwrap();
+
+    assert_eq!(result.symbol, "AAPL");
+    assert_eq!(result.bars.len(), 2);
+    assert!(result.bars[0].time < result.bars[1].time);
+    assert!(result.bars[0].weighted_average.is_some());
+    assert!(result.bars[0].trade_count.is_some());
+    assert!(result.next_page_token.is_some());
+  }
+}
@@ -0,0 +1,237 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_urlencoded::to_string as to_query;
+
+use crate::Str;
+
+
+/// The kind of ticks that condition codes apply to.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TickType {
+  /// Condition codes of trades.
+  Trade,
+  /// Condition codes of quotes.
+  Quote,
+}
+
+impl AsRef<str> for TickType {
+  fn as_ref(&self) -> &'static str {
+    match *self {
+      TickType::Trade => "trade",
+      TickType::Quote => "quote",
+    }
+  }
+}
+
+
+/// An enumeration of the consolidated tapes securities are reported
+/// on.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Tape {
+  /// Tape A, covering securities listed on the NYSE.
+  #[serde(rename = "A")]
+  A,
+  /// Tape B, covering securities listed on NYSE Arca, NYSE American,
+  /// and regional exchanges.
+  #[serde(rename = "B")]
+  B,
+  /// Tape C, covering securities listed on NASDAQ.
+  #[serde(rename = "C")]
+  C,
+}
+
+
+/// A classification of trades based on their condition codes.
+///
+/// The classification is meant for analytics such as VWAP or volume
+/// calculations, which commonly only consider regular trades.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TradeClass {
+  /// A regular trade, eligible to update last sale and volume
+  /// statistics.
+  Regular,
+  /// A trade of less than a round lot, i.e., less than 100 shares.
+  ///
+  /// Odd lot trades count towards volume but have been excluded from
+  /// last sale price updates historically.
+  OddLot,
+  /// A trade priced based on a reference price or benchmark rather
+  /// than the prevailing market price.
+  Derivative,
+  /// A trade that is reported out of the regular session or sequence,
+  /// or otherwise carries special settlement or pricing terms, e.g.,
+  /// an average price trade or an official open or close print.
+  Irregular,
+}
+
+impl TradeClass {
+  /// Classify a trade based on the tape it was reported on and its
+  /// condition codes.
+  ///
+  /// If multiple condition codes are present, derivatively priced
+  /// trades take precedence over irregular ones, which in turn take
+  /// precedence over odd lots.
+  pub fn classify<S>(tape: Tape, conditions: &[S]) -> Self
+  where
+    S: AsRef<str>,
+  {
+    let mut class = TradeClass::Regular;
+    for condition in conditions {
+      let condition = condition.as_ref().trim();
+      if condition == "4" {
+        return TradeClass::Derivative
+      } else if is_irregular(tape, condition) {
+        class = TradeClass::Irregular
+      } else if condition == "I" && class == TradeClass::Regular {
+        class = TradeClass::OddLot
+      }
+    }
+    class
+  }
+
+  /// Check whether the trade is a regular one.
+  #[inline]
+  pub fn is_regular(self) -> bool {
+    self == TradeClass::Regular
+  }
+}
+
+
+/// Check whether a trade condition code marks a trade as irregular.
+fn is_irregular(tape: Tape, condition: &str) -> bool {
+  match condition {
+    // Cash sale, bunched sold trade, price variation trade, market
+    // center official close, next day, prior reference price, market
+    // center official open, seller, extended hours trade, extended
+    // hours sold, contingent trade, average price trade (UTP), sold
+    // out of sequence, qualified contingent trade, and corrected
+    // consolidated close.
+    "C" | "G" | "H" | "M" | "N" | "P" | "Q" | "R" | "T" | "U" | "V" | "W" | "Z" | "7" | "9" => true,
+    // Average price trade on the CTA tapes; on tape C the code denotes
+    // a regular bunched trade.
+    "B" => tape != Tape::C,
+    _ => false,
+  }
+}
+
+
+/// A GET request to be issued to the
+/// /v2/stocks/meta/conditions/<tick-type> endpoint.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+pub struct ConditionsReq {
+  /// The kind of ticks to retrieve condition codes for.
+  #[serde(skip)]
+  pub tick_type: TickType,
+  /// The tape to retrieve condition codes for.
+  #[serde(rename = "tape")]
+  pub tape: Tape,
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v2/stocks/meta/conditions/<tick-type> endpoint.
+  ///
+  /// The result maps condition codes to their human readable
+  /// descriptions.
+  pub Get(ConditionsReq),
+  Ok => HashMap<String, String>, [
+    /// The condition codes were retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// One or more of the arguments are not well formed.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+  ]
+  Api => Data,
+
+  fn path(input: &Self::Input) -> Str {
+    format!("/v2/stocks/meta/conditions/{}", input.tick_type.as_ref()).into()
+  }
+
+  fn query(input: &Self::Input) -> Option<Str> {
+    // TODO: Realistically there should be no way for this unwrap to
+    //       ever panic because our conversion to strings should not be
+    //       fallible. But still, ideally we would not have to unwrap.
+    Some(to_query(input).unwrap().into())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use http_endpoint::Endpoint;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+  use crate::api_info::ApiInfo;
+  use crate::Client;
+
+
+  /// Check that we classify trades based on their condition codes
+  /// correctly.
+  #[test]
+  fn classify_trades() {
+    let classify = |tape, conditions: &[&str]| TradeClass::classify(tape, conditions);
+
+    assert_eq!(classify(Tape::A, &[]), TradeClass::Regular);
+    assert_eq!(classify(Tape::A, &[" "]), TradeClass::Regular);
+    assert_eq!(classify(Tape::C, &["@", "F"]), TradeClass::Regular);
+    assert_eq!(classify(Tape::C, &["@", "I"]), TradeClass::OddLot);
+    assert_eq!(classify(Tape::B, &[" ", "T"]), TradeClass::Irregular);
+    assert_eq!(classify(Tape::B, &["T", "I"]), TradeClass::Irregular);
+    assert_eq!(classify(Tape::B, &["I", "T"]), TradeClass::Irregular);
+    assert_eq!(classify(Tape::A, &["B"]), TradeClass::Irregular);
+    assert_eq!(classify(Tape::C, &["B"]), TradeClass::Regular);
+    assert_eq!(classify(Tape::C, &["@", "4", "I"]), TradeClass::Derivative);
+    assert!(classify(Tape::C, &["@"]).is_regular());
+  }
+
+  #[test]
+  fn emit_request() {
+    let request = ConditionsReq {
+      tick_type: TickType::Quote,
+      tape: Tape::C,
+    };
+
+    assert_eq!(Get::path(&request), "/v2/stocks/meta/conditions/quote");
+    assert_eq!(Get::query(&request).unwrap(), "tape=C");
+  }
+
+  #[test]
+  fn parse_reference_conditions() {
+    let response = r#"{
+  " ": "Regular Sale",
+  "4": "Derivatively Priced",
+  "I": "Odd Lot Trade"
+}"#;
+
+    let conditions = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    assert_eq!(conditions.len(), 3);
+    assert_eq!(conditions.get(" ").unwrap(), "Regular Sale");
+    assert_eq!(conditions.get("I").unwrap(), "Odd Lot Trade");
+  }
+
+  #[test(tokio::test)]
+  async fn request_trade_conditions() {
+    let request = ConditionsReq {
+      tick_type: TickType::Trade,
+      tape: Tape::A,
+    };
+
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+    let conditions = client.issue::<Get>(request).await.unwrap();
+
+    assert!(!conditions.is_empty());
+  }
+}
@@ -0,0 +1,304 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Conflation of real time market data, i.e., dropping stale quotes and
+//! bars for which a more recent one was received already.
+//!
+//! A consumer not keeping up with the market data stream usually does
+//! not benefit from working through a backlog of outdated quotes. A
+//! `Conflated` stream reads ahead as far as data is available and only
+//! keeps the latest quote and bar per symbol, while still reporting
+//! every trade and error, in the order received.
+//!
+//! To bound memory use and the time spent in a single poll, read-ahead
+//! stops once a certain number of items is queued. That limit is
+//! [`DEFAULT_READ_AHEAD`][crate::data::v2::conflate::DEFAULT_READ_AHEAD]
+//! for streams created by [`conflate`][crate::data::v2::conflate::conflate]
+//! and can be set explicitly using
+//! [`conflate_with_limit`][crate::data::v2::conflate::conflate_with_limit].
+//!
+//! ```no_run
+//! # use apca::data::v2::conflate::conflate;
+//! # use apca::data::v2::stream::MarketData;
+//! # use apca::data::v2::stream::Source;
+//! # use apca::ApiInfo;
+//! # use apca::Client;
+//! # use futures::StreamExt as _;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = Client::new(ApiInfo::from_env()?);
+//! let data = MarketData {
+//!   quotes: vec!["SPY".to_string()],
+//!   ..Default::default()
+//! };
+//! let (_, stream) = client.subscribe_market_data(Some(Source::Iex), &data).await?;
+//! let mut stream = conflate(Box::pin(stream));
+//! while let Some(quote) = stream.next().await {
+//!   // Only the most recent quote is processed after a slow iteration.
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+use futures::Stream;
+
+use websocket_util::tungstenite::Error as WebSocketError;
+
+use crate::data::v2::stream::Data;
+use crate::data::v2::stream::MessageError;
+
+
+/// The maximum number of items queued by default.
+pub const DEFAULT_READ_AHEAD: usize = 1024;
+
+
+/// The type of items reported by the market data stream.
+type Item = Result<Result<Data, MessageError>, WebSocketError>;
+
+
+/// The kind of data an item is conflated with.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Kind {
+  /// A bar.
+  Bar,
+  /// A quote.
+  Quote,
+}
+
+/// Retrieve the key to conflate the given item under, if it is to be
+/// conflated at all.
+fn key(item: &Item) -> Option<(Kind, String)> {
+  match item {
+    Ok(Ok(Data::Bar(bar))) => Some((Kind::Bar, bar.symbol.clone())),
+    Ok(Ok(Data::Quote(quote))) => Some((Kind::Quote, quote.symbol.clone())),
+    _ => None,
+  }
+}
+
+
+/// A stream adapter conflating quotes and bars per symbol.
+///
+/// Please refer to the [module level documentation][self] for details.
+#[derive(Debug)]
+pub struct Conflated<S> {
+  /// The underlying stream.
+  inner: S,
+  /// The items read ahead but not reported yet. Items superseded by a
+  /// more recent one are replaced with `None`.
+  queue: VecDeque<Option<Item>>,
+  /// The sequence number of the latest queued item, per key.
+  latest: HashMap<(Kind, String), u64>,
+  /// The sequence number of the item at the front of the queue.
+  front: u64,
+  /// The maximum number of items to queue.
+  limit: usize,
+  /// Whether the underlying stream is exhausted.
+  done: bool,
+}
+
+impl<S> Conflated<S>
+where
+  S: Stream<Item = Item> + Unpin,
+{
+  /// Add an item to the back of the queue, superseding any queued item
+  /// with the same key.
+  fn push(&mut self, item: Item) {
+    let seq = self.front + self.queue.len() as u64;
+
+    if let Some(key) = key(&item) {
+      if let Some(prev) = self.latest.insert(key, seq) {
+        // The sequence number is always that of an item still in the
+        // queue, as we remove it once the item is reported.
+        self.queue[(prev - self.front) as usize] = None;
+      }
+    }
+    self.queue.push_back(Some(item))
+  }
+
+  /// Remove the first item that has not been superseded from the front
+  /// of the queue.
+  fn pop(&mut self) -> Option<Item> {
+    while let Some(item) = self.queue.pop_front() {
+      let seq = self.front;
+      self.front += 1;
+
+      if let Some(item) = item {
+        if let Some(key) = key(&item) {
+          if self.latest.get(&key) == Some(&seq) {
+            let _ = self.latest.remove(&key);
+          }
+        }
+        return Some(item)
+      }
+    }
+    None
+  }
+}
+
+impl<S> Stream for Conflated<S>
+where
+  S: Stream<Item = Item> + Unpin,
+{
+  type Item = Item;
+
+  fn poll_next(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    // Read ahead as far as possible, so that stale data gets replaced
+    // before it is reported, but without letting the queue grow
+    // unbounded in the face of a stream that is always ready.
+    while !self.done && self.queue.len() < self.limit {
+      match Pin::new(&mut self.inner).poll_next(ctx) {
+        Poll::Ready(Some(item)) => self.push(item),
+        Poll::Ready(None) => self.done = true,
+        Poll::Pending => break,
+      }
+    }
+
+    match self.pop() {
+      Some(item) => Poll::Ready(Some(item)),
+      None if self.done => Poll::Ready(None),
+      None => Poll::Pending,
+    }
+  }
+}
+
+
+/// Conflate the quotes and bars of the given market data stream per
+/// symbol.
+///
+/// The stream has to be `Unpin`, which can be achieved by pinning it
+/// on the heap using `Box::pin`. At most [`DEFAULT_READ_AHEAD`] items
+/// are read ahead.
+pub fn conflate<S>(stream: S) -> Conflated<S>
+where
+  S: Stream<Item = Item> + Unpin,
+{
+  conflate_with_limit(stream, DEFAULT_READ_AHEAD)
+}
+
+
+/// Conflate the quotes and bars of the given market data stream per
+/// symbol, reading ahead at most `limit` items.
+///
+/// Only items queued at the same time get conflated, i.e., a lower
+/// limit means less memory use but potentially more stale data
+/// reported.
+///
+/// # Panics
+/// The function panics if `limit` is zero.
+pub fn conflate_with_limit<S>(stream: S, limit: usize) -> Conflated<S>
+where
+  S: Stream<Item = Item> + Unpin,
+{
+  assert!(limit > 0, "read-ahead limit must not be zero");
+
+  Conflated {
+    inner: stream,
+    queue: VecDeque::new(),
+    latest: HashMap::new(),
+    front: 0,
+    limit,
+    done: false,
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use futures::stream::iter;
+  use futures::StreamExt as _;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+
+  /// Create a quote for the given symbol with the given bid size.
+  #[allow(clippy::result_large_err)]
+  fn quote(symbol: &str, size: u64) -> Item {
+    let json = format!(
+      r#"{{"S":"{}","bx":"U","bp":87.66,"bs":{},"ax":"Q","ap":87.68,"as":4,"t":"2021-02-22T15:51:45.335689322Z","c":["R"],"z":"C"}}"#,
+      symbol, size
+    );
+    Ok(Ok(Data::Quote(from_json(&json).unwrap())))
+  }
+
+  /// Create a trade for the given symbol with the given size.
+  #[allow(clippy::result_large_err)]
+  fn trade(symbol: &str, size: u64) -> Item {
+    let json = format!(
+      r#"{{"S":"{}","i":1,"x":"V","p":400.5,"s":{},"c":["@"],"z":"B","t":"2021-06-01T14:30:00.1Z"}}"#,
+      symbol, size
+    );
+    Ok(Ok(Data::Trade(from_json(&json).unwrap())))
+  }
+
+  /// Convert an item into a string for easier comparison.
+  fn describe(item: Item) -> String {
+    match item.unwrap().unwrap() {
+      Data::Quote(quote) => format!("q:{}:{}", quote.symbol, quote.data.bid_size),
+      Data::Trade(trade) => format!("t:{}:{}", trade.symbol, trade.data.size),
+      Data::Bar(bar) => format!("b:{}", bar.symbol),
+    }
+  }
+
+
+  /// Check that we only report the latest quote per symbol, while
+  /// reporting all trades, in order.
+  #[test(tokio::test)]
+  async fn conflate_quotes() {
+    let items = vec![
+      quote("SPY", 1),
+      quote("AAPL", 1),
+      quote("SPY", 2),
+      trade("SPY", 10),
+      trade("SPY", 20),
+      quote("SPY", 3),
+    ];
+    let items = conflate(iter(items))
+      .map(describe)
+      .collect::<Vec<_>>()
+      .await;
+    assert_eq!(items, vec!["q:AAPL:1", "t:SPY:10", "t:SPY:20", "q:SPY:3"]);
+  }
+
+  /// Check that we read ahead no more than the configured number of
+  /// items.
+  #[test(tokio::test)]
+  async fn limit_read_ahead() {
+    let items = vec![
+      quote("SPY", 1),
+      quote("SPY", 2),
+      quote("SPY", 3),
+      quote("SPY", 4),
+      quote("SPY", 5),
+    ];
+    let mut stream = conflate_with_limit(iter(items), 2);
+    assert_eq!(describe(stream.next().await.unwrap()), "q:SPY:2");
+    assert_eq!(stream.queue.len(), 0);
+
+    let items = stream.map(describe).collect::<Vec<_>>().await;
+    assert_eq!(items, vec!["q:SPY:4", "q:SPY:5"]);
+  }
+
+  /// Check that items already reported are not affected by later ones.
+  #[test(tokio::test)]
+  async fn report_after_conflation() {
+    let mut stream = conflate(iter(vec![quote("SPY", 1)]));
+    assert_eq!(describe(stream.next().await.unwrap()), "q:SPY:1");
+    assert!(stream.next().await.is_none());
+
+    let () = stream.push(quote("SPY", 2));
+    let () = stream.push(quote("SPY", 3));
+    assert_eq!(stream.queue.len(), 2);
+    assert_eq!(describe(stream.pop().unwrap()), "q:SPY:3");
+    assert!(stream.latest.is_empty());
+  }
+}
@@ -0,0 +1,473 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Adapters for writing historical market data to and reading it from
+//! CSV.
+//!
+//! Each of the supported types is represented by a fixed set of
+//! columns, in a fixed order, with a header row describing them. Times
+//! are represented as RFC 3339 strings in UTC.
+//!
+//! ```no_run
+//! # use apca::data::v2::bars::Bar;
+//! # use apca::data::v2::csv::Writer;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let bars = Vec::<Bar>::new();
+//! let file = std::fs::File::create("bars.csv")?;
+//! let mut writer = Writer::<_, Bar>::new(file)?;
+//! writer.write_all(&bars)?;
+//! writer.flush()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Write;
+use std::marker::PhantomData;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use csv::Error;
+use csv::Reader as CsvReader;
+use csv::StringRecord;
+use csv::StringRecordsIntoIter;
+use csv::Writer as CsvWriter;
+
+use num_decimal::Num;
+
+use serde::de::value::Error as ValueError;
+use serde::de::IntoDeserializer;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_variant::to_variant_name;
+
+use time_util::parse_system_time_from_str;
+
+use crate::data::v2::bars::Bar;
+use crate::data::v2::conditions::Tape;
+use crate::data::v2::exchanges::Exchange;
+use crate::data::v2::quotes::Quote;
+use crate::data::v2::trades::Trade;
+use crate::util::system_time_to_rfc3339_string;
+
+
+/// Create an error indicating that a record contained invalid data.
+fn invalid_data(column: &str, value: &str) -> Error {
+  let message = format!("invalid value for column {}: {}", column, value);
+  Error::from(IoError::new(ErrorKind::InvalidData, message))
+}
+
+/// Format an enum variant using its textual representation.
+fn format_variant<T>(variant: &T) -> String
+where
+  T: Serialize,
+{
+  // We know that we are dealing with an enum variant and the function
+  // will never return an error for those, so it's fine to unwrap.
+  to_variant_name(variant).unwrap().to_string()
+}
+
+/// Format a list of conditions as a single comma separated string.
+fn format_conditions(conditions: &[String]) -> String {
+  conditions.join(",")
+}
+
+/// Format an optional value, using an empty string for `None`.
+fn format_optional<T>(value: &Option<T>) -> String
+where
+  T: ToString,
+{
+  value.as_ref().map(ToString::to_string).unwrap_or_default()
+}
+
+
+/// Retrieve the field at `index` of a record.
+fn field<'r>(record: &'r StringRecord, header: &[&str], index: usize) -> Result<&'r str, Error> {
+  record
+    .get(index)
+    .ok_or_else(|| invalid_data(header[index], "<missing>"))
+}
+
+/// Parse the field at `index` of a record.
+fn parse<T>(record: &StringRecord, header: &[&str], index: usize) -> Result<T, Error>
+where
+  T: FromStr,
+{
+  let value = field(record, header, index)?;
+  value
+    .parse()
+    .map_err(|_| invalid_data(header[index], value))
+}
+
+/// Parse the optional field at `index` of a record.
+fn parse_optional<T>(
+  record: &StringRecord,
+  header: &[&str],
+  index: usize,
+) -> Result<Option<T>, Error>
+where
+  T: FromStr,
+{
+  let value = field(record, header, index)?;
+  if value.is_empty() {
+    Ok(None)
+  } else {
+    parse(record, header, index).map(Some)
+  }
+}
+
+/// Parse the time stored in the field at `index` of a record.
+fn parse_time(record: &StringRecord, header: &[&str], index: usize) -> Result<SystemTime, Error> {
+  let value = field(record, header, index)?;
+  parse_system_time_from_str(value).ok_or_else(|| invalid_data(header[index], value))
+}
+
+/// Parse the enum variant stored in the field at `index` of a record.
+fn parse_variant<'de, T>(record: &StringRecord, header: &[&str], index: usize) -> Result<T, Error>
+where
+  T: Deserialize<'de>,
+{
+  let value = field(record, header, index)?;
+  T::deserialize(value.to_string().into_deserializer())
+    .map_err(|_: ValueError| invalid_data(header[index], value))
+}
+
+/// Parse the list of conditions stored in the field at `index` of a
+/// record.
+fn parse_conditions(
+  record: &StringRecord,
+  header: &[&str],
+  index: usize,
+) -> Result<Vec<String>, Error> {
+  let value = field(record, header, index)?;
+  if value.is_empty() {
+    Ok(Vec::new())
+  } else {
+    Ok(value.split(',').map(ToString::to_string).collect())
+  }
+}
+
+
+/// A trait for types that can be represented as a row in a CSV file.
+pub trait Record: Sized {
+  /// The names of the columns, in the order in which they are written.
+  const HEADER: &'static [&'static str];
+
+  /// Convert the object into the fields of a row.
+  fn to_record(&self) -> Vec<String>;
+
+  /// Create an object from the fields of a row.
+  fn from_record(record: &StringRecord) -> Result<Self, Error>;
+}
+
+impl Record for Bar {
+  const HEADER: &'static [&'static str] = &[
+    "time",
+    "open",
+    "high",
+    "low",
+    "close",
+    "volume",
+    "vwap",
+    "trade_count",
+  ];
+
+  fn to_record(&self) -> Vec<String> {
+    vec![
+      system_time_to_rfc3339_string(&self.time),
+      self.open.to_string(),
+      self.high.to_string(),
+      self.low.to_string(),
+      self.close.to_string(),
+      self.volume.to_string(),
+      format_optional(&self.weighted_average),
+      format_optional(&self.trade_count),
+    ]
+  }
+
+  fn from_record(record: &StringRecord) -> Result<Self, Error> {
+    let header = Self::HEADER;
+    Ok(Self {
+      time: parse_time(record, header, 0)?,
+      open: parse::<Num>(record, header, 1)?,
+      high: parse::<Num>(record, header, 2)?,
+      low: parse::<Num>(record, header, 3)?,
+      close: parse::<Num>(record, header, 4)?,
+      volume: parse(record, header, 5)?,
+      weighted_average: parse_optional::<Num>(record, header, 6)?,
+      trade_count: parse_optional(record, header, 7)?,
+    })
+  }
+}
+
+impl Record for Trade {
+  const HEADER: &'static [&'static str] = &[
+    "time",
+    "exchange",
+    "price",
+    "size",
+    "conditions",
+    "id",
+    "tape",
+  ];
+
+  fn to_record(&self) -> Vec<String> {
+    vec![
+      system_time_to_rfc3339_string(&self.time),
+      format_variant(&self.exchange),
+      self.price.to_string(),
+      self.size.to_string(),
+      format_conditions(&self.conditions),
+      self.id.to_string(),
+      format_variant(&self.tape),
+    ]
+  }
+
+  fn from_record(record: &StringRecord) -> Result<Self, Error> {
+    let header = Self::HEADER;
+    Ok(Self {
+      time: parse_time(record, header, 0)?,
+      exchange: parse_variant::<Exchange>(record, header, 1)?,
+      price: parse::<Num>(record, header, 2)?,
+      size: parse(record, header, 3)?,
+      conditions: parse_conditions(record, header, 4)?,
+      id: parse(record, header, 5)?,
+      tape: parse_variant::<Tape>(record, header, 6)?,
+    })
+  }
+}
+
+impl Record for Quote {
+  const HEADER: &'static [&'static str] = &[
+    "time",
+    "ask_exchange",
+    "ask_price",
+    "ask_size",
+    "bid_exchange",
+    "bid_price",
+    "bid_size",
+    "conditions",
+    "tape",
+  ];
+
+  fn to_record(&self) -> Vec<String> {
+    vec![
+      system_time_to_rfc3339_string(&self.time),
+      format_variant(&self.ask_exchange),
+      self.ask_price.to_string(),
+      self.ask_size.to_string(),
+      format_variant(&self.bid_exchange),
+      self.bid_price.to_string(),
+      self.bid_size.to_string(),
+      format_conditions(&self.conditions),
+      format_variant(&self.tape),
+    ]
+  }
+
+  fn from_record(record: &StringRecord) -> Result<Self, Error> {
+    let header = Self::HEADER;
+    Ok(Self {
+      time: parse_time(record, header, 0)?,
+      ask_exchange: parse_variant::<Exchange>(record, header, 1)?,
+      ask_price: parse::<Num>(record, header, 2)?,
+      ask_size: parse(record, header, 3)?,
+      bid_exchange: parse_variant::<Exchange>(record, header, 4)?,
+      bid_price: parse::<Num>(record, header, 5)?,
+      bid_size: parse(record, header, 6)?,
+      conditions: parse_conditions(record, header, 7)?,
+      tape: parse_variant::<Tape>(record, header, 8)?,
+    })
+  }
+}
+
+
+/// A writer for emitting objects of type `T` as CSV.
+///
+/// The header row is written as part of the construction.
+#[derive(Debug)]
+pub struct Writer<W, T>
+where
+  W: Write,
+{
+  writer: CsvWriter<W>,
+  _phantom: PhantomData<T>,
+}
+
+impl<W, T> Writer<W, T>
+where
+  W: Write,
+  T: Record,
+{
+  /// Create a new `Writer` emitting data to `writer`.
+  pub fn new(writer: W) -> Result<Self, Error> {
+    let mut writer = CsvWriter::from_writer(writer);
+    writer.write_record(T::HEADER)?;
+
+    Ok(Self {
+      writer,
+      _phantom: PhantomData,
+    })
+  }
+
+  /// Write a single object.
+  pub fn write(&mut self, object: &T) -> Result<(), Error> {
+    self.writer.write_record(object.to_record())
+  }
+
+  /// Write all objects produced by the given iterator.
+  pub fn write_all<'t, I>(&mut self, objects: I) -> Result<(), Error>
+  where
+    I: IntoIterator<Item = &'t T>,
+    T: 't,
+  {
+    objects
+      .into_iter()
+      .try_for_each(|object| self.write(object))
+  }
+
+  /// Flush all buffered data to the underlying writer.
+  pub fn flush(&mut self) -> Result<(), Error> {
+    self.writer.flush().map_err(Error::from)
+  }
+
+  /// Flush all buffered data and retrieve the underlying writer.
+  pub fn into_inner(self) -> Result<W, Error> {
+    self
+      .writer
+      .into_inner()
+      .map_err(|err| Error::from(IoError::new(err.error().kind(), err.to_string())))
+  }
+}
+
+
+/// A reader for objects of type `T` stored as CSV, as emitted by a
+/// `Writer`.
+///
+/// The reader is an iterator over the contained objects.
+pub struct Reader<R, T>
+where
+  R: Read,
+{
+  records: StringRecordsIntoIter<R>,
+  _phantom: PhantomData<T>,
+}
+
+impl<R, T> Reader<R, T>
+where
+  R: Read,
+  T: Record,
+{
+  /// Create a new `Reader` consuming data from `reader`.
+  ///
+  /// The header row of the data is checked to match the one expected
+  /// for `T`.
+  pub fn new(reader: R) -> Result<Self, Error> {
+    let mut reader = CsvReader::from_reader(reader);
+    let header = reader.headers()?;
+    if header.iter().ne(T::HEADER.iter().copied()) {
+      let header = header.iter().collect::<Vec<_>>().join(",");
+      return Err(invalid_data("header", &header))
+    }
+
+    Ok(Self {
+      records: reader.into_records(),
+      _phantom: PhantomData,
+    })
+  }
+}
+
+impl<R, T> Debug for Reader<R, T>
+where
+  R: Read,
+{
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    fmt.debug_struct("Reader").finish()
+  }
+}
+
+impl<R, T> Iterator for Reader<R, T>
+where
+  R: Read,
+  T: Record,
+{
+  type Item = Result<T, Error>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self
+      .records
+      .next()
+      .map(|result| result.and_then(|record| T::from_record(&record)))
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde_json::from_str as from_json;
+
+
+  #[test]
+  fn write_bars() {
+    let bar = from_json::<Bar>(
+      r#"{"t":"2021-02-01T16:01:00Z","o":133.32,"h":133.74,"l":133.31,"c":133.5,"v":9876}"#,
+    )
+    .unwrap();
+
+    let mut writer = Writer::<_, Bar>::new(Vec::new()).unwrap();
+    writer.write(&bar).unwrap();
+    let data = writer.into_inner().unwrap();
+
+    let expected = "time,open,high,low,close,volume,vwap,trade_count\n\
+                    2021-02-01T16:01:00Z,133.32,133.74,133.31,133.5,9876,,\n";
+    assert_eq!(String::from_utf8(data).unwrap(), expected);
+  }
+
+  #[test]
+  fn round_trip_trades() {
+    let trades = [
+      r#"{"t":"2021-02-06T13:04:56.334320128Z","x":"C","p":387.62,"s":100,"c":[" ","T"],"i":52983525029461,"z":"B"}"#,
+      r#"{"t":"2021-02-06T13:09:42.325484032Z","x":"V","p":387.7,"s":1,"c":[],"i":1,"z":"B"}"#,
+    ]
+    .iter()
+    .map(|trade| from_json::<Trade>(trade).unwrap())
+    .collect::<Vec<_>>();
+
+    let mut writer = Writer::<_, Trade>::new(Vec::new()).unwrap();
+    writer.write_all(&trades).unwrap();
+    let data = writer.into_inner().unwrap();
+
+    let reader = Reader::<_, Trade>::new(data.as_slice()).unwrap();
+    let read = reader.collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(read, trades);
+  }
+
+  #[test]
+  fn round_trip_quotes() {
+    let quote = from_json::<Quote>(
+      r#"{"t":"2021-02-06T13:04:56.334320128Z","ax":"C","ap":387.62,"as":1,"bx":"V","bp":387.5,"bs":2,"c":["R"],"z":"C"}"#,
+    )
+    .unwrap();
+
+    let mut writer = Writer::<_, Quote>::new(Vec::new()).unwrap();
+    writer.write(&quote).unwrap();
+    let data = writer.into_inner().unwrap();
+
+    let mut reader = Reader::<_, Quote>::new(data.as_slice()).unwrap();
+    assert_eq!(reader.next().unwrap().unwrap(), quote);
+    assert!(reader.next().is_none());
+  }
+
+  #[test]
+  fn reject_mismatched_header() {
+    let data = b"time,open,high,low,close,volume\n";
+    let result = Reader::<_, Trade>::new(&data[..]);
+    assert!(result.is_err());
+  }
+}
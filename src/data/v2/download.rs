@@ -0,0 +1,434 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! A downloader for long ranges of historical market data.
+//!
+//! The historical data endpoints cap the number of items returned by a
+//! single request and hand out a page token for retrieving the rest.
+//! For ranges spanning months or years that results in a lot of
+//! bookkeeping, which the `Downloader` takes care of: it splits the
+//! range into chunks, follows the pagination within each chunk, backs
+//! off when running into the rate limit, and reports the progress made
+//! along with each page so that an interrupted download can be resumed
+//! later on.
+
+use std::cmp::min;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use futures::stream::unfold;
+use futures::stream::Stream;
+
+use tokio::time::sleep;
+
+use tracing::debug;
+
+use crate::data::v2::bars;
+use crate::data::v2::quotes;
+use crate::data::v2::trades;
+use crate::ApiEndpoint;
+use crate::Client;
+use crate::RequestError;
+
+
+/// A trait for endpoints whose requests can be split into chunks of
+/// time and paged through.
+pub trait Chunkable: ApiEndpoint
+where
+  Self::Input: Clone,
+{
+  /// The type of item reported by the endpoint.
+  type Item;
+
+  /// Retrieve the time range covered by a request.
+  fn range(input: &Self::Input) -> (SystemTime, SystemTime);
+
+  /// Adjust the time range covered by a request.
+  fn set_range(input: &mut Self::Input, start: SystemTime, end: SystemTime);
+
+  /// Set the page token to use for a request.
+  fn set_page_token(input: &mut Self::Input, page_token: Option<String>);
+
+  /// Split the endpoint's output into the reported items and the token
+  /// for retrieving the next page, if any.
+  fn into_page(output: Self::Output) -> (Vec<Self::Item>, Option<String>);
+
+  /// Check whether an error indicates that the rate limit was hit.
+  fn is_rate_limited(error: &Self::Error) -> bool;
+}
+
+impl Chunkable for bars::Get {
+  type Item = bars::Bar;
+
+  fn range(input: &Self::Input) -> (SystemTime, SystemTime) {
+    (input.start, input.end)
+  }
+
+  fn set_range(input: &mut Self::Input, start: SystemTime, end: SystemTime) {
+    input.start = start;
+    input.end = end;
+  }
+
+  fn set_page_token(input: &mut Self::Input, page_token: Option<String>) {
+    input.page_token = page_token;
+  }
+
+  fn into_page(output: Self::Output) -> (Vec<Self::Item>, Option<String>) {
+    (output.bars, output.next_page_token)
+  }
+
+  fn is_rate_limited(error: &Self::Error) -> bool {
+    matches!(error, bars::GetError::RateLimitExceeded(..))
+  }
+}
+
+impl Chunkable for quotes::Get {
+  type Item = quotes::Quote;
+
+  fn range(input: &Self::Input) -> (SystemTime, SystemTime) {
+    (input.start, input.end)
+  }
+
+  fn set_range(input: &mut Self::Input, start: SystemTime, end: SystemTime) {
+    input.start = start;
+    input.end = end;
+  }
+
+  fn set_page_token(input: &mut Self::Input, page_token: Option<String>) {
+    input.page_token = page_token;
+  }
+
+  fn into_page(output: Self::Output) -> (Vec<Self::Item>, Option<String>) {
+    (output.quotes, output.next_page_token)
+  }
+
+  fn is_rate_limited(error: &Self::Error) -> bool {
+    matches!(error, quotes::GetError::RateLimitExceeded(..))
+  }
+}
+
+impl Chunkable for trades::Get {
+  type Item = trades::Trade;
+
+  fn range(input: &Self::Input) -> (SystemTime, SystemTime) {
+    (input.start, input.end)
+  }
+
+  fn set_range(input: &mut Self::Input, start: SystemTime, end: SystemTime) {
+    input.start = start;
+    input.end = end;
+  }
+
+  fn set_page_token(input: &mut Self::Input, page_token: Option<String>) {
+    input.page_token = page_token;
+  }
+
+  fn into_page(output: Self::Output) -> (Vec<Self::Item>, Option<String>) {
+    (output.trades, output.next_page_token)
+  }
+
+  fn is_rate_limited(error: &Self::Error) -> bool {
+    matches!(error, trades::GetError::RateLimitExceeded(..))
+  }
+}
+
+
+/// The progress of a download.
+///
+/// A `Progress` object can be used to resume a download at the point
+/// where it left off.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Progress {
+  /// The start of the chunk that is to be downloaded next.
+  pub start: SystemTime,
+  /// The page token to use for retrieving the next page within the
+  /// chunk, if any.
+  pub page_token: Option<String>,
+  /// Whether the download has completed.
+  pub done: bool,
+}
+
+
+/// A page of downloaded items.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Page<T> {
+  /// The items, in the order reported by the endpoint.
+  pub items: Vec<T>,
+  /// The progress of the download after this page.
+  pub progress: Progress,
+}
+
+
+/// Calculate the time range of the chunk of the given duration
+/// starting at `start`.
+///
+/// Because the data endpoints treat the end of a range as inclusive we
+/// stop just short of the next chunk, so that an item is never reported
+/// twice. The last chunk ends at the end of the overall range, which is
+/// also indicated by the returned flag.
+fn chunk_range<C>(
+  request: &C::Input,
+  start: SystemTime,
+  chunk: Duration,
+) -> (SystemTime, SystemTime, bool)
+where
+  C: Chunkable,
+  C::Input: Clone,
+{
+  let (_, end) = C::range(request);
+  let chunk_end = min(start + chunk, end);
+  if chunk_end >= end {
+    (start, end, true)
+  } else {
+    (start, chunk_end - Duration::from_nanos(1), false)
+  }
+}
+
+
+/// Determine the progress after the chunk ending at `chunk_end` (as
+/// reported by `chunk_range`) has been downloaded completely.
+///
+/// Chunk ends are inclusive, so the next chunk starts right after.
+fn finish_chunk(chunk_end: SystemTime, last: bool) -> Progress {
+  Progress {
+    start: chunk_end + Duration::from_nanos(1),
+    page_token: None,
+    done: last,
+  }
+}
+
+
+/// A helper for downloading long ranges of historical data.
+#[derive(Clone, Copy, Debug)]
+pub struct Downloader<'c> {
+  client: &'c Client,
+  chunk: Duration,
+  max_retries: usize,
+  backoff: Duration,
+}
+
+impl<'c> Downloader<'c> {
+  /// Create a new `Downloader` using the given client.
+  ///
+  /// By default the covered range is split into chunks of 30 days and
+  /// a request running into the rate limit is retried up to five
+  /// times, with an exponentially increasing back off starting at one
+  /// second.
+  pub fn new(client: &'c Client) -> Self {
+    Self {
+      client,
+      chunk: Duration::from_secs(30 * 24 * 60 * 60),
+      max_retries: 5,
+      backoff: Duration::from_secs(1),
+    }
+  }
+
+  /// Set the duration of a single chunk.
+  ///
+  /// # Panics
+  /// The function panics if the provided duration is zero.
+  pub fn chunk(mut self, chunk: Duration) -> Self {
+    assert!(chunk > Duration::from_secs(0), "chunk duration must not be zero");
+    self.chunk = chunk;
+    self
+  }
+
+  /// Set the maximum number of times a request is retried after
+  /// running into the rate limit.
+  pub fn max_retries(mut self, max_retries: usize) -> Self {
+    self.max_retries = max_retries;
+    self
+  }
+
+  /// Set the initial duration to back off for after running into the
+  /// rate limit.
+  pub fn backoff(mut self, backoff: Duration) -> Self {
+    self.backoff = backoff;
+    self
+  }
+
+  /// Download all data covered by the given request.
+  ///
+  /// The resulting stream reports pages of items in chronological
+  /// order. It ends after the last page or after the first error.
+  pub fn download<C>(
+    &self,
+    request: C::Input,
+  ) -> impl Stream<Item = Result<Page<C::Item>, RequestError<C::Error>>> + 'c
+  where
+    C: Chunkable,
+    C::Input: Clone + 'c,
+    C::Item: 'c,
+    C::Error: 'c,
+  {
+    let (start, _) = C::range(&request);
+    let progress = Progress {
+      start,
+      page_token: None,
+      done: false,
+    };
+    self.resume::<C>(request, progress)
+  }
+
+  /// Resume a download of the data covered by the given request.
+  ///
+  /// The provided `progress` is typically the one reported along with
+  /// the last page received before the download was interrupted.
+  pub fn resume<C>(
+    &self,
+    request: C::Input,
+    progress: Progress,
+  ) -> impl Stream<Item = Result<Page<C::Item>, RequestError<C::Error>>> + 'c
+  where
+    C: Chunkable,
+    C::Input: Clone + 'c,
+    C::Item: 'c,
+    C::Error: 'c,
+  {
+    let this = *self;
+    let state = (request, progress);
+
+    unfold(state, move |(request, progress)| async move {
+      if progress.done {
+        return None
+      }
+
+      let (chunk_start, chunk_end, last) = chunk_range::<C>(&request, progress.start, this.chunk);
+      let mut input = request.clone();
+      C::set_range(&mut input, chunk_start, chunk_end);
+      C::set_page_token(&mut input, progress.page_token.clone());
+
+      let result = this.issue::<C>(input).await;
+      match result {
+        Ok(output) => {
+          let (items, page_token) = C::into_page(output);
+          let progress = match page_token {
+            Some(page_token) => Progress {
+              start: chunk_start,
+              page_token: Some(page_token),
+              done: false,
+            },
+            None => finish_chunk(chunk_end, last),
+          };
+          let page = Page {
+            items,
+            progress: progress.clone(),
+          };
+          Some((Ok(page), (request, progress)))
+        },
+        Err(err) => {
+          let progress = Progress {
+            done: true,
+            ..progress
+          };
+          Some((Err(err), (request, progress)))
+        },
+      }
+    })
+  }
+
+  /// Issue a request, retrying it if the rate limit was hit.
+  async fn issue<C>(&self, input: C::Input) -> Result<C::Output, RequestError<C::Error>>
+  where
+    C: Chunkable,
+    C::Input: Clone,
+  {
+    let mut backoff = self.backoff;
+    let mut retries = 0;
+
+    loop {
+      match self.client.issue::<C>(input.clone()).await {
+        Err(RequestError::Endpoint(ref err))
+          if C::is_rate_limited(err) && retries < self.max_retries =>
+        {
+          debug!(backoff = debug(&backoff), "rate limit exceeded; backing off");
+          sleep(backoff).await;
+          backoff *= 2;
+          retries += 1;
+        },
+        result => break result,
+      }
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use futures::TryStreamExt;
+
+  use test_env_log::test;
+
+  use time_util::parse_system_time_from_str;
+
+  use crate::api_info::ApiInfo;
+  use crate::data::v2::bars::BarsReqInit;
+  use crate::data::v2::bars::TimeFrame;
+
+
+  #[test]
+  fn calculate_chunk_ranges() {
+    let start = parse_system_time_from_str("2021-01-01T00:00:00Z").unwrap();
+    let end = parse_system_time_from_str("2021-01-03T12:00:00Z").unwrap();
+    let request = BarsReqInit::default().init("SPY", start, end, TimeFrame::OneDay);
+
+    let day = Duration::from_secs(24 * 60 * 60);
+
+    let (first_start, first_end, last) = chunk_range::<bars::Get>(&request, start, day);
+    assert_eq!(first_start, start);
+    assert_eq!(first_end, start + day - Duration::from_nanos(1));
+    assert!(!last);
+
+    let progress = finish_chunk(first_end, last);
+    assert!(!progress.done);
+
+    let (second_start, second_end, last) = chunk_range::<bars::Get>(&request, progress.start, day);
+    assert_eq!(second_start, start + day);
+    assert_eq!(second_end, start + 2 * day - Duration::from_nanos(1));
+    assert!(!last);
+
+    let progress = finish_chunk(second_end, last);
+    assert!(!progress.done);
+
+    let (third_start, third_end, last) = chunk_range::<bars::Get>(&request, progress.start, day);
+    assert_eq!(third_start, start + 2 * day);
+    assert_eq!(third_end, end);
+    assert!(last);
+
+    let progress = finish_chunk(third_end, last);
+    assert!(progress.done);
+  }
+
+  /// Check that we can download a longer range of daily bars in
+  /// chunks, with pagination happening within the chunks.
+  #[test(tokio::test)]
+  async fn download_bars() {
+    let start = parse_system_time_from_str("2021-01-01T00:00:00Z").unwrap();
+    let end = parse_system_time_from_str("2021-03-01T00:00:00Z").unwrap();
+    let request = BarsReqInit {
+      limit: Some(5),
+      ..Default::default()
+    }
+    .init("AAPL", start, end, TimeFrame::OneDay);
+
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+    let pages = Downloader::new(&client)
+      .chunk(Duration::from_secs(14 * 24 * 60 * 60))
+      .download::<bars::Get>(request)
+      .try_collect::<Vec<_>>()
+      .await
+      .unwrap();
+
+    let bars = pages
+      .iter()
+      .flat_map(|page| page.items.iter())
+      .collect::<Vec<_>>();
+    // January and February 2021 had 19 + 19 trading days.
+    assert_eq!(bars.len(), 38);
+    assert!(bars.windows(2).all(|bars| bars[0].time < bars[1].time));
+    assert!(pages.last().unwrap().progress.done);
+  }
+}
@@ -0,0 +1,145 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::Str;
+
+
+/// An enumeration of the exchanges that market data may originate
+/// from.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Exchange {
+  /// NYSE American (AMEX).
+  #[serde(rename = "A")]
+  NyseAmerican,
+  /// NASDAQ OMX BX.
+  #[serde(rename = "B")]
+  NasdaqOmxBx,
+  /// National Stock Exchange.
+  #[serde(rename = "C")]
+  National,
+  /// FINRA ADF.
+  #[serde(rename = "D")]
+  FinraAdf,
+  /// Market Independent.
+  #[serde(rename = "E")]
+  MarketIndependent,
+  /// MIAX.
+  #[serde(rename = "H")]
+  Miax,
+  /// International Securities Exchange.
+  #[serde(rename = "I")]
+  InternationalSecurities,
+  /// Cboe EDGA.
+  #[serde(rename = "J")]
+  CboeEdga,
+  /// Cboe EDGX.
+  #[serde(rename = "K")]
+  CboeEdgx,
+  /// Long Term Stock Exchange.
+  #[serde(rename = "L")]
+  LongTerm,
+  /// Chicago Stock Exchange.
+  #[serde(rename = "M")]
+  Chicago,
+  /// New York Stock Exchange.
+  #[serde(rename = "N")]
+  Nyse,
+  /// NYSE Arca.
+  #[serde(rename = "P")]
+  NyseArca,
+  /// NASDAQ OMX.
+  #[serde(rename = "Q")]
+  NasdaqOmx,
+  /// NASDAQ Small Cap.
+  #[serde(rename = "S")]
+  NasdaqSmallCap,
+  /// NASDAQ Int.
+  #[serde(rename = "T")]
+  NasdaqInt,
+  /// Members Exchange.
+  #[serde(rename = "U")]
+  Members,
+  /// IEX.
+  #[serde(rename = "V")]
+  Iex,
+  /// CBOE.
+  #[serde(rename = "W")]
+  Cboe,
+  /// NASDAQ OMX PSX.
+  #[serde(rename = "X")]
+  NasdaqOmxPsx,
+  /// Cboe BYX.
+  #[serde(rename = "Y")]
+  CboeByx,
+  /// Cboe BZX.
+  #[serde(rename = "Z")]
+  CboeBzx,
+  /// Any other exchange that we have not accounted for.
+  ///
+  /// Note that having any such exchange should be considered a bug.
+  #[serde(other)]
+  Unknown,
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v2/stocks/meta/exchanges endpoint.
+  pub Get(()),
+  Ok => HashMap<Exchange, String>, [
+    /// The exchange codes were retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, []
+  Api => Data,
+
+  fn path(_input: &Self::Input) -> Str {
+    "/v2/stocks/meta/exchanges".into()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use http_endpoint::Endpoint;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+  use crate::api_info::ApiInfo;
+  use crate::Client;
+
+
+  #[test]
+  fn parse_reference_exchanges() {
+    let response = r#"{
+  "A": "NYSE American (AMEX)",
+  "N": "New York Stock Exchange",
+  "V": "IEX"
+}"#;
+
+    let exchanges = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    assert_eq!(exchanges.len(), 3);
+    assert_eq!(exchanges.get(&Exchange::NyseAmerican).unwrap(), "NYSE American (AMEX)");
+    assert_eq!(exchanges.get(&Exchange::Nyse).unwrap(), "New York Stock Exchange");
+    assert_eq!(exchanges.get(&Exchange::Iex).unwrap(), "IEX");
+  }
+
+  #[test(tokio::test)]
+  async fn request_exchanges() {
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+    let exchanges = client.issue::<Get>(()).await.unwrap();
+
+    assert!(exchanges.contains_key(&Exchange::Nyse));
+    assert!(!exchanges.contains_key(&Exchange::Unknown));
+  }
+}
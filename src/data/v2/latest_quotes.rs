@@ -0,0 +1,132 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::from_slice as from_json;
+use serde_urlencoded::to_string as to_query;
+
+use crate::data::v2::quotes::Quote;
+use crate::util::strings_to_str;
+use crate::Str;
+
+
+/// A GET request to be issued to the /v2/stocks/quotes/latest
+/// endpoint.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+pub struct LatestQuotesReq {
+  /// The symbols for which to retrieve the latest quote.
+  #[serde(rename = "symbols", serialize_with = "strings_to_str")]
+  pub symbols: Vec<String>,
+}
+
+
+/// The response as returned by the /v2/stocks/quotes/latest endpoint.
+#[derive(Deserialize)]
+struct LatestQuotes {
+  /// The latest quotes, keyed by symbol.
+  #[serde(rename = "quotes", default)]
+  quotes: HashMap<String, Quote>,
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v2/stocks/quotes/latest endpoint.
+  ///
+  /// The latest quote of each of the requested symbols is reported,
+  /// keyed by symbol.
+  pub Get(LatestQuotesReq),
+  Ok => HashMap<String, Quote>, [
+    /// The latest quotes were retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// One or more of the arguments are not well formed.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+  ]
+  Api => Data,
+
+  fn path(_input: &Self::Input) -> Str {
+    "/v2/stocks/quotes/latest".into()
+  }
+
+  fn query(input: &Self::Input) -> Option<Str> {
+    // TODO: Realistically there should be no way for this unwrap to
+    //       ever panic because our conversion to strings should not be
+    //       fallible. But still, ideally we would not have to unwrap.
+    Some(to_query(input).unwrap().into())
+  }
+
+  fn parse(body: &[u8]) -> Result<Self::Output, Self::Error> {
+    from_json::<LatestQuotes>(body)
+      .map(|latest| latest.quotes)
+      .map_err(Self::Error::from)
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use http_endpoint::Endpoint;
+
+  use num_decimal::Num;
+
+  use test_env_log::test;
+
+  use crate::api_info::ApiInfo;
+  use crate::Client;
+
+
+  #[test]
+  fn emit_request() {
+    let request = LatestQuotesReq {
+      symbols: vec!["AAPL".to_string(), "MSFT".to_string()],
+    };
+
+    assert_eq!(Get::query(&request).unwrap(), "symbols=AAPL%2CMSFT");
+  }
+
+  #[test]
+  fn parse_reference_latest_quotes() {
+    let response = br#"{
+  "quotes": {
+    "AAPL": {
+      "t": "2021-04-30T19:59:59.981764Z",
+      "ax": "Q",
+      "ap": 131.47,
+      "as": 3,
+      "bx": "V",
+      "bp": 131.44,
+      "bs": 1,
+      "c": ["R"],
+      "z": "C"
+    }
+  }
+}"#;
+
+    let quotes = Get::parse(response).unwrap();
+    assert_eq!(quotes.len(), 1);
+    assert_eq!(quotes["AAPL"].ask_price, Num::new(13147, 100));
+    assert_eq!(quotes["AAPL"].bid_size, 1);
+  }
+
+  #[test(tokio::test)]
+  async fn request_latest_quotes() {
+    let request = LatestQuotesReq {
+      symbols: vec!["AAPL".to_string(), "SPY".to_string()],
+    };
+
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+    let quotes = client.issue::<Get>(request).await.unwrap();
+
+    assert_eq!(quotes.len(), 2);
+    assert!(quotes.contains_key("AAPL"));
+    assert!(quotes.contains_key("SPY"));
+  }
+}
@@ -0,0 +1,139 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::from_slice as from_json;
+use serde_urlencoded::to_string as to_query;
+
+use crate::data::v2::trades::Trade;
+use crate::util::strings_to_str;
+use crate::Str;
+
+
+/// A GET request to be issued to the /v2/stocks/trades/latest
+/// endpoint.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+pub struct LatestTradesReq {
+  /// The symbols for which to retrieve the latest trade.
+  #[serde(rename = "symbols", serialize_with = "strings_to_str")]
+  pub symbols: Vec<String>,
+}
+
+
+/// The response as returned by the /v2/stocks/trades/latest endpoint.
+#[derive(Deserialize)]
+struct LatestTrades {
+  /// The latest trades, keyed by symbol.
+  #[serde(rename = "trades", default)]
+  trades: HashMap<String, Trade>,
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v2/stocks/trades/latest endpoint.
+  ///
+  /// The latest trade of each of the requested symbols is reported,
+  /// keyed by symbol.
+  pub Get(LatestTradesReq),
+  Ok => HashMap<String, Trade>, [
+    /// The latest trades were retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// One or more of the arguments are not well formed.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+  ]
+  Api => Data,
+
+  fn path(_input: &Self::Input) -> Str {
+    "/v2/stocks/trades/latest".into()
+  }
+
+  fn query(input: &Self::Input) -> Option<Str> {
+    // TODO: Realistically there should be no way for this unwrap to
+    //       ever panic because our conversion to strings should not be
+    //       fallible. But still, ideally we would not have to unwrap.
+    Some(to_query(input).unwrap().into())
+  }
+
+  fn parse(body: &[u8]) -> Result<Self::Output, Self::Error> {
+    from_json::<LatestTrades>(body)
+      .map(|latest| latest.trades)
+      .map_err(Self::Error::from)
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use http_endpoint::Endpoint;
+
+  use num_decimal::Num;
+
+  use test_env_log::test;
+
+  use crate::api_info::ApiInfo;
+  use crate::Client;
+
+
+  #[test]
+  fn emit_request() {
+    let request = LatestTradesReq {
+      symbols: vec!["AAPL".to_string(), "MSFT".to_string()],
+    };
+
+    assert_eq!(Get::query(&request).unwrap(), "symbols=AAPL%2CMSFT");
+  }
+
+  #[test]
+  fn parse_reference_latest_trades() {
+    let response = br#"{
+  "trades": {
+    "AAPL": {
+      "t": "2021-04-30T19:59:59.977312Z",
+      "x": "V",
+      "p": 131.46,
+      "s": 100,
+      "c": ["@"],
+      "i": 9825,
+      "z": "C"
+    },
+    "MSFT": {
+      "t": "2021-04-30T19:59:59.987869Z",
+      "x": "V",
+      "p": 252.18,
+      "s": 200,
+      "c": ["@"],
+      "i": 7067,
+      "z": "C"
+    }
+  }
+}"#;
+
+    let trades = Get::parse(response).unwrap();
+    assert_eq!(trades.len(), 2);
+    assert_eq!(trades["AAPL"].price, Num::new(13146, 100));
+    assert_eq!(trades["MSFT"].size, 200);
+  }
+
+  #[test(tokio::test)]
+  async fn request_latest_trades() {
+    let request = LatestTradesReq {
+      symbols: vec!["AAPL".to_string(), "SPY".to_string()],
+    };
+
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+    let trades = client.issue::<Get>(request).await.unwrap();
+
+    assert_eq!(trades.len(), 2);
+    assert!(trades.contains_key("AAPL"));
+    assert!(trades.contains_key("SPY"));
+  }
+}
@@ -0,0 +1,518 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Sharing of a single market data connection among any number of
+//! subscriptions.
+//!
+//! Alpaca limits the number of concurrent connections to the market
+//! data stream per account, with most plans allowing for only one per
+//! source. A `ConnectionManager` owns that connection and hands out
+//! `Handle` objects, each of which is a stream of only the data it was
+//! created for. Subscriptions of all handles are coalesced: a symbol is
+//! subscribed to with the server once the first handle needs it and
+//! unsubscribed from once the last handle needing it is dropped.
+//!
+//! The event stream of the trading API can be shared in a similar
+//! fashion by means of a [`Broadcast`][crate::broadcast::Broadcast].
+//!
+//! ```no_run
+//! # use apca::data::v2::manager::ConnectionManager;
+//! # use apca::data::v2::stream::Source;
+//! # use apca::ApiInfo;
+//! # use apca::Client;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = Client::new(ApiInfo::from_env()?);
+//! let (manager, driver) = ConnectionManager::connect(&client, Some(Source::Iex)).await?;
+//! // The driver needs to be polled for data to be received.
+//! tokio::spawn(driver);
+//!
+//! // Both handles are served by the same connection. SPY trades are
+//! // only subscribed to once.
+//! let spy = manager.trades(["SPY"])?;
+//! let tech = manager.trades(["SPY", "AAPL", "MSFT"])?;
+//! let quotes = manager.quotes(["AAPL"])?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::Context;
+use std::task::Poll;
+
+use futures::Stream;
+use futures::StreamExt as _;
+
+use tokio::sync::mpsc::unbounded_channel;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::mpsc::UnboundedSender;
+
+use tracing::warn;
+
+use websocket_util::tungstenite::Error as WebSocketError;
+
+use crate::data::v2::bars::Bar;
+use crate::data::v2::quotes::Quote;
+use crate::data::v2::stream::Data;
+use crate::data::v2::stream::MarketData;
+use crate::data::v2::stream::MessageError;
+use crate::data::v2::stream::Source;
+use crate::data::v2::stream::StreamError;
+use crate::data::v2::stream::Subscriber;
+use crate::data::v2::stream::SymbolData;
+use crate::data::v2::trades::Trade;
+use crate::Client;
+use crate::Error;
+
+
+/// The index of bars in per-type arrays.
+const BARS: usize = 0;
+/// The index of quotes in per-type arrays.
+const QUOTES: usize = 1;
+/// The index of trades in per-type arrays.
+const TRADES: usize = 2;
+
+
+/// Retrieve the symbols of a `MarketData` object, per type of data.
+fn symbols(data: &MarketData) -> [&Vec<String>; 3] {
+  [&data.bars, &data.quotes, &data.trades]
+}
+
+/// Create a `MarketData` object from symbols per type of data.
+fn market_data(symbols: [Vec<String>; 3]) -> MarketData {
+  let [bars, quotes, trades] = symbols;
+  MarketData {
+    bars,
+    quotes,
+    trades,
+  }
+}
+
+/// Retrieve the index of the type of the given piece of data.
+fn index(data: &Data) -> usize {
+  match data {
+    Data::Bar(..) => BARS,
+    Data::Quote(..) => QUOTES,
+    Data::Trade(..) => TRADES,
+  }
+}
+
+
+/// The subscriptions of a single handle.
+#[derive(Debug)]
+struct Entry {
+  /// The symbols subscribed to, per type of data.
+  symbols: [HashSet<String>; 3],
+  /// The channel used for sending data to the handle.
+  sender: UnboundedSender<Result<Data, StreamError>>,
+}
+
+impl Entry {
+  /// Check whether the handle subscribed to the given piece of data.
+  fn accepts(&self, data: &Data) -> bool {
+    let symbols = &self.symbols[index(data)];
+    symbols.contains(data.symbol()) || symbols.contains("*")
+  }
+}
+
+
+/// The state shared among a `ConnectionManager`, its handles, and the
+/// connection driver.
+#[derive(Debug, Default)]
+struct State {
+  /// The ID to assign to the next handle.
+  next_id: usize,
+  /// The subscriptions of all live handles.
+  entries: HashMap<usize, Entry>,
+  /// The number of handles subscribed to each symbol, per type of
+  /// data.
+  counts: [HashMap<String, usize>; 3],
+}
+
+impl State {
+  /// Register a handle subscribing to the given market data.
+  ///
+  /// Returned are the ID of the handle and the market data not
+  /// subscribed to by any other handle, i.e., the data to subscribe to
+  /// with the server.
+  fn add(
+    &mut self,
+    data: &MarketData,
+    sender: UnboundedSender<Result<Data, StreamError>>,
+  ) -> (usize, MarketData) {
+    let mut entry = Entry {
+      symbols: Default::default(),
+      sender,
+    };
+    let mut added = <[Vec<String>; 3]>::default();
+
+    for (i, symbols) in symbols(data).iter().enumerate() {
+      for symbol in symbols.iter() {
+        if entry.symbols[i].insert(symbol.clone()) {
+          let count = self.counts[i].entry(symbol.clone()).or_insert(0);
+          if *count == 0 {
+            added[i].push(symbol.clone());
+          }
+          *count += 1;
+        }
+      }
+    }
+
+    let id = self.next_id;
+    self.next_id += 1;
+    let _ = self.entries.insert(id, entry);
+    (id, market_data(added))
+  }
+
+  /// Unregister the handle with the given ID.
+  ///
+  /// Returned is the market data no longer subscribed to by any
+  /// handle, i.e., the data to unsubscribe from with the server.
+  fn remove(&mut self, id: usize) -> MarketData {
+    let mut removed = <[Vec<String>; 3]>::default();
+
+    if let Some(entry) = self.entries.remove(&id) {
+      for (i, symbols) in entry.symbols.iter().enumerate() {
+        for symbol in symbols {
+          // The count has to be present, as we have accounted for all
+          // of the entry's symbols when adding it.
+          let count = self.counts[i].get_mut(symbol).unwrap();
+          *count -= 1;
+          if *count == 0 {
+            let _ = self.counts[i].remove(symbol);
+            removed[i].push(symbol.clone());
+          }
+        }
+      }
+    }
+
+    removed.iter_mut().for_each(|symbols| symbols.sort());
+    market_data(removed)
+  }
+
+  /// Forward a piece of data to all handles subscribed to it.
+  fn dispatch(&self, data: &Data) {
+    self
+      .entries
+      .values()
+      .filter(|entry| entry.accepts(data))
+      .for_each(|entry| {
+        // The handle may be in the process of being dropped, in which
+        // case the data is simply discarded.
+        let _ = entry.sender.send(Ok(data.clone()));
+      })
+  }
+
+  /// Forward an error reported by the server to all handles.
+  fn dispatch_error(&self, error: &StreamError) {
+    self.entries.values().for_each(|entry| {
+      let _ = entry.sender.send(Err(error.clone()));
+    })
+  }
+
+  /// Retrieve the market data subscribed to by all handles combined.
+  fn subscriptions(&self) -> MarketData {
+    let mut symbols = <[Vec<String>; 3]>::default();
+    for (i, counts) in self.counts.iter().enumerate() {
+      symbols[i] = counts.keys().cloned().collect();
+      symbols[i].sort();
+    }
+    market_data(symbols)
+  }
+}
+
+
+/// The parts of a `ConnectionManager` shared with its handles.
+#[derive(Debug)]
+struct Shared {
+  /// The subscriber used for changing the connection's subscriptions.
+  subscriber: Subscriber,
+  /// The subscriptions of all handles.
+  state: Mutex<State>,
+}
+
+
+/// A stream of the market data of one or more types that a handle was
+/// created for.
+///
+/// Errors reported by the server, for instance because the symbol
+/// limit was exceeded, are reported to all handles. The stream ends
+/// once the connection was closed. Dropping the handle unsubscribes
+/// from all data no other handle needs.
+pub struct Handle<T> {
+  /// The ID of the handle.
+  id: usize,
+  /// The channel data is received on.
+  receiver: UnboundedReceiver<Result<Data, StreamError>>,
+  /// The function extracting the reported items from received data.
+  extract: fn(Data) -> Option<T>,
+  /// The state shared with the manager.
+  shared: Arc<Shared>,
+}
+
+impl<T> Stream for Handle<T> {
+  type Item = Result<T, StreamError>;
+
+  fn poll_next(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    loop {
+      match self.receiver.poll_recv(ctx) {
+        Poll::Ready(Some(Ok(data))) => {
+          if let Some(item) = (self.extract)(data) {
+            break Poll::Ready(Some(Ok(item)))
+          }
+        },
+        Poll::Ready(Some(Err(err))) => break Poll::Ready(Some(Err(err))),
+        Poll::Ready(None) => break Poll::Ready(None),
+        Poll::Pending => break Poll::Pending,
+      }
+    }
+  }
+}
+
+impl<T> Drop for Handle<T> {
+  fn drop(&mut self) {
+    let removed = self.shared.state.lock().unwrap().remove(self.id);
+    if !removed.is_empty() {
+      // If the connection is gone there is nothing to unsubscribe
+      // from anymore.
+      let _ = self.shared.subscriber.unsubscribe(&removed);
+    }
+  }
+}
+
+impl<T> Debug for Handle<T> {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    fmt.debug_struct("Handle").field("id", &self.id).finish()
+  }
+}
+
+
+/// A manager of a market data connection, coalescing the subscriptions
+/// of any number of handles onto it.
+#[derive(Debug)]
+pub struct ConnectionManager {
+  /// The state shared with all handles.
+  shared: Arc<Shared>,
+}
+
+impl ConnectionManager {
+  /// Connect to the real time market data stream of the given source,
+  /// or the configured feed if `None`.
+  ///
+  /// The returned future drives the connection and has to be polled
+  /// (e.g., by spawning it as a task) for any data to be received. It
+  /// resolves once the connection was closed or failed, at which point
+  /// all handles' streams end as well. Messages that could not be
+  /// decoded are logged and dropped, as they cannot be attributed to a
+  /// handle.
+  pub async fn connect(
+    client: &Client,
+    source: Option<Source>,
+  ) -> Result<(Self, impl Future<Output = Result<(), WebSocketError>>), Error> {
+    let (subscriber, stream) = client
+      .subscribe_market_data(source, &MarketData::default())
+      .await?;
+    let shared = Arc::new(Shared {
+      subscriber,
+      state: Mutex::new(State::default()),
+    });
+
+    let state = shared.clone();
+    let driver = async move {
+      let mut stream = Box::pin(stream);
+      let result = loop {
+        match stream.next().await {
+          Some(Ok(Ok(data))) => state.state.lock().unwrap().dispatch(&data),
+          Some(Ok(Err(MessageError::Stream(err)))) => {
+            state.state.lock().unwrap().dispatch_error(&err)
+          },
+          Some(Ok(Err(MessageError::Json(err)))) => {
+            warn!(
+              error = display(&err),
+              "failed to decode market data message"
+            )
+          },
+          Some(Err(err)) => break Err(err),
+          None => break Ok(()),
+        }
+      };
+
+      // Close all handles' streams. Nothing is subscribed to anymore
+      // at this point.
+      let mut state = state.state.lock().unwrap();
+      state.entries.clear();
+      state.counts = Default::default();
+      result
+    };
+
+    Ok((Self { shared }, driver))
+  }
+
+  /// Create a handle for the given market data, of any type.
+  #[allow(clippy::result_large_err)]
+  pub fn subscribe(&self, data: &MarketData) -> Result<Handle<Data>, Error> {
+    self.handle(data, Some)
+  }
+
+  /// Create a handle for the bars of the given symbols.
+  #[allow(clippy::result_large_err)]
+  pub fn bars<I, S>(&self, symbols: I) -> Result<Handle<SymbolData<Bar>>, Error>
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+  {
+    let data = MarketData {
+      bars: symbols.into_iter().map(Into::into).collect(),
+      ..Default::default()
+    };
+    self.handle(&data, |data| match data {
+      Data::Bar(bar) => Some(bar),
+      _ => None,
+    })
+  }
+
+  /// Create a handle for the quotes of the given symbols.
+  #[allow(clippy::result_large_err)]
+  pub fn quotes<I, S>(&self, symbols: I) -> Result<Handle<SymbolData<Quote>>, Error>
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+  {
+    let data = MarketData {
+      quotes: symbols.into_iter().map(Into::into).collect(),
+      ..Default::default()
+    };
+    self.handle(&data, |data| match data {
+      Data::Quote(quote) => Some(quote),
+      _ => None,
+    })
+  }
+
+  /// Create a handle for the trades of the given symbols.
+  #[allow(clippy::result_large_err)]
+  pub fn trades<I, S>(&self, symbols: I) -> Result<Handle<SymbolData<Trade>>, Error>
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+  {
+    let data = MarketData {
+      trades: symbols.into_iter().map(Into::into).collect(),
+      ..Default::default()
+    };
+    self.handle(&data, |data| match data {
+      Data::Trade(trade) => Some(trade),
+      _ => None,
+    })
+  }
+
+  /// Create a handle for the given market data, reporting the items
+  /// produced by `extract`.
+  #[allow(clippy::result_large_err)]
+  fn handle<T>(
+    &self,
+    data: &MarketData,
+    extract: fn(Data) -> Option<T>,
+  ) -> Result<Handle<T>, Error> {
+    let (sender, receiver) = unbounded_channel();
+    let (id, added) = self.shared.state.lock().unwrap().add(data, sender);
+    let handle = Handle {
+      id,
+      receiver,
+      extract,
+      shared: self.shared.clone(),
+    };
+
+    if !added.is_empty() {
+      // Should the request fail, dropping the handle unregisters it
+      // again.
+      let () = self.shared.subscriber.subscribe(&added)?;
+    }
+    Ok(handle)
+  }
+
+  /// Retrieve the market data subscribed to on behalf of all handles
+  /// combined.
+  pub fn subscriptions(&self) -> MarketData {
+    self.shared.state.lock().unwrap().subscriptions()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+
+  /// Create a `MarketData` object subscribing to the given trades and
+  /// quotes.
+  fn data(trades: &[&str], quotes: &[&str]) -> MarketData {
+    MarketData {
+      trades: trades.iter().map(ToString::to_string).collect(),
+      quotes: quotes.iter().map(ToString::to_string).collect(),
+      ..Default::default()
+    }
+  }
+
+  /// Create a trade for the given symbol.
+  fn trade(symbol: &str) -> Data {
+    let json = format!(
+      r#"{{"S":"{}","i":1,"x":"V","p":400.5,"s":100,"c":["@"],"z":"B","t":"2021-06-01T14:30:00.1Z"}}"#,
+      symbol
+    );
+    Data::Trade(from_json(&json).unwrap())
+  }
+
+
+  /// Check that we only subscribe to and unsubscribe from data not
+  /// needed by any other handle.
+  #[test]
+  fn coalesce_subscriptions() {
+    let mut state = State::default();
+    let (sender, _receiver) = unbounded_channel();
+
+    let (id1, added) = state.add(&data(&["SPY", "AAPL", "SPY"], &[]), sender.clone());
+    assert_eq!(added, data(&["SPY", "AAPL"], &[]));
+
+    let (id2, added) = state.add(&data(&["SPY", "MSFT"], &["SPY"]), sender);
+    assert_eq!(added, data(&["MSFT"], &["SPY"]));
+    assert_eq!(
+      state.subscriptions(),
+      data(&["AAPL", "MSFT", "SPY"], &["SPY"])
+    );
+
+    assert_eq!(state.remove(id1), data(&["AAPL"], &[]));
+    assert_eq!(state.remove(id2), data(&["MSFT", "SPY"], &["SPY"]));
+    assert!(state.subscriptions().is_empty());
+    assert!(state.remove(id2).is_empty());
+  }
+
+  /// Check that data is only forwarded to the handles subscribed to it.
+  #[test]
+  fn route_data() {
+    let mut state = State::default();
+    let (sender1, mut receiver1) = unbounded_channel();
+    let (sender2, mut receiver2) = unbounded_channel();
+    let _ = state.add(&data(&["SPY"], &[]), sender1);
+    let _ = state.add(&data(&["*"], &[]), sender2);
+
+    state.dispatch(&trade("SPY"));
+    state.dispatch(&trade("MSFT"));
+
+    assert_eq!(receiver1.try_recv().unwrap().unwrap(), trade("SPY"));
+    assert!(receiver1.try_recv().is_err());
+    assert_eq!(receiver2.try_recv().unwrap().unwrap(), trade("SPY"));
+    assert_eq!(receiver2.try_recv().unwrap().unwrap(), trade("MSFT"));
+  }
+}
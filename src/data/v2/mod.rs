@@ -0,0 +1,116 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::error::Error as StdError;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+/// Conversion of historical data into Arrow record batches and
+/// Parquet files.
+#[cfg(feature = "arrow")]
+pub mod arrow;
+/// Utilities for back-adjusting bars for splits and dividends.
+pub mod adjust;
+/// Definitions for retrieval of historical stock auctions.
+pub mod auctions;
+/// An on-disk cache for historical bars.
+#[cfg(feature = "csv")]
+pub mod bar_cache;
+/// Definitions for retrieval of historical market data bars.
+pub mod bars;
+/// Definitions for retrieval of trade and quote condition codes.
+pub mod conditions;
+/// Conflation of real time market data, keeping only the latest quote
+/// and bar per symbol.
+#[cfg(feature = "streaming")]
+pub mod conflate;
+/// Adapters for writing historical data to and reading it from CSV.
+#[cfg(feature = "csv")]
+pub mod csv;
+/// Functionality for downloading long ranges of historical data.
+pub mod download;
+/// Definitions surrounding exchanges and their codes.
+pub mod exchanges;
+/// Definitions for retrieval of the latest quotes of multiple symbols.
+pub mod latest_quotes;
+/// Definitions for retrieval of the latest trades of multiple symbols.
+pub mod latest_trades;
+/// Sharing of a single real time market data connection among
+/// multiple subscriptions.
+#[cfg(feature = "streaming")]
+pub mod manager;
+/// Definitions for retrieval of historical quotes.
+pub mod quotes;
+/// Recording of live market data to rotating CSV or JSONL files.
+#[cfg(all(feature = "csv", feature = "streaming"))]
+pub mod recorder;
+/// Utilities for resampling bars into larger intervals.
+pub mod resample;
+/// Adapters exposing bars as column slices and rolling windows for
+/// technical indicators.
+pub mod series;
+/// Definitions for real time market data streaming.
+#[cfg(feature = "streaming")]
+pub mod stream;
+/// Definitions for retrieval of historical trades.
+pub mod trades;
+
+
+/// A feed of market data.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Serialize)]
+pub enum Feed {
+  /// Data from the Investors Exchange (IEX) only.
+  #[serde(rename = "iex")]
+  #[default]
+  Iex,
+  /// Data from all US exchanges, as consolidated by the Securities
+  /// Information Processors (SIP).
+  ///
+  /// Access to this feed requires a corresponding subscription.
+  #[serde(rename = "sip")]
+  Sip,
+}
+
+impl FromStr for Feed {
+  type Err = InvalidFeed;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    if s.eq_ignore_ascii_case("iex") {
+      Ok(Self::Iex)
+    } else if s.eq_ignore_ascii_case("sip") {
+      Ok(Self::Sip)
+    } else {
+      Err(InvalidFeed(s.to_string()))
+    }
+  }
+}
+
+
+/// An error indicating that a string does not name a valid `Feed`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InvalidFeed(pub String);
+
+impl Display for InvalidFeed {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    write!(fmt, "invalid data feed: '{}' (expected 'iex' or 'sip')", self.0)
+  }
+}
+
+impl StdError for InvalidFeed {}
+
+
+/// The order in which historical data is returned.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Serialize)]
+pub enum Sort {
+  /// Return data in ascending order, i.e., oldest first.
+  #[serde(rename = "asc")]
+  #[default]
+  Ascending,
+  /// Return data in descending order, i.e., newest first.
+  #[serde(rename = "desc")]
+  Descending,
+}
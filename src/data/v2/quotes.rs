@@ -0,0 +1,435 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::borrow::Cow;
+use std::time::SystemTime;
+
+use num_decimal::Num;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_urlencoded::to_string as to_query;
+
+use time_util::system_time_from_str;
+use time_util::system_time_to_rfc3339;
+
+use crate::data::v2::bars::Bar;
+use crate::data::v2::conditions::Tape;
+use crate::data::v2::exchanges::Exchange;
+use crate::data::v2::Feed;
+use crate::data::v2::Sort;
+use crate::paged::next_page_by_token;
+use crate::paged::Paged;
+use crate::util::optional_cow_str;
+use crate::util::optional_system_time_to_date_str;
+use crate::util::vec_from_optional;
+use crate::Str;
+
+
+/// A helper for initializing `QuotesReq` objects.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QuotesReqInit {
+  /// See `QuotesReq::limit`.
+  pub limit: Option<usize>,
+  /// See `QuotesReq::page_token`.
+  pub page_token: Option<String>,
+  /// See `QuotesReq::sort`.
+  pub sort: Option<Sort>,
+  /// See `QuotesReq::asof`.
+  pub asof: Option<SystemTime>,
+  /// See `QuotesReq::feed`.
+  pub feed: Option<Feed>,
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl QuotesReqInit {
+  /// Create a `QuotesReq` from a `QuotesReqInit`.
+  pub fn init<S>(self, symbol: S, start: SystemTime, end: SystemTime) -> QuotesReq
+  where
+    S: Into<String>,
+  {
+    QuotesReq {
+      symbol: symbol.into(),
+      start,
+      end,
+      limit: self.limit,
+      page_token: self.page_token,
+      sort: self.sort,
+      asof: self.asof,
+      feed: self.feed,
+    }
+  }
+}
+
+
+/// A GET request to be issued to the /v2/stocks/<symbol>/quotes
+/// endpoint.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct QuotesReq {
+  /// The symbol for which to retrieve quotes.
+  #[serde(skip)]
+  pub symbol: String,
+  /// Filter quotes equal to or after this time.
+  #[serde(rename = "start", serialize_with = "system_time_to_rfc3339")]
+  pub start: SystemTime,
+  /// Filter quotes equal to or before this time.
+  #[serde(rename = "end", serialize_with = "system_time_to_rfc3339")]
+  pub end: SystemTime,
+  /// The maximum number of quotes to be returned.
+  ///
+  /// It can be between 1 and 10000. Defaults to 1000 if not set.
+  #[serde(rename = "limit", skip_serializing_if = "Option::is_none")]
+  pub limit: Option<usize>,
+  /// If provided, this token is used for retrieving the next page of
+  /// data, following a previous request.
+  #[serde(rename = "page_token", skip_serializing_if = "Option::is_none")]
+  pub page_token: Option<String>,
+  /// The order in which to return quotes.
+  ///
+  /// Defaults to ascending order if not set.
+  #[serde(rename = "sort", skip_serializing_if = "Option::is_none")]
+  pub sort: Option<Sort>,
+  /// The date as of which to map symbols.
+  ///
+  /// Providing a date allows for retrieving data for symbols that got
+  /// renamed or delisted since, using the symbol as it was valid on
+  /// this date. If not set, the current date is used.
+  #[serde(
+    rename = "asof",
+    serialize_with = "optional_system_time_to_date_str",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub asof: Option<SystemTime>,
+  /// The data feed to retrieve quotes from.
+  ///
+  /// If not set, the feed configured in the client's `ApiInfo` is
+  /// used.
+  #[serde(rename = "feed", skip_serializing_if = "Option::is_none")]
+  pub feed: Option<Feed>,
+}
+
+
+/// A quote as returned by the /v2/stocks/<symbol>/quotes endpoint.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Quote {
+  /// The time of the quote.
+  #[serde(rename = "t", deserialize_with = "system_time_from_str")]
+  pub time: SystemTime,
+  /// The exchange the ask originates from.
+  #[serde(rename = "ax")]
+  pub ask_exchange: Exchange,
+  /// The ask price.
+  #[serde(rename = "ap")]
+  pub ask_price: Num,
+  /// The ask size.
+  #[serde(rename = "as")]
+  pub ask_size: u64,
+  /// The exchange the bid originates from.
+  #[serde(rename = "bx")]
+  pub bid_exchange: Exchange,
+  /// The bid price.
+  #[serde(rename = "bp")]
+  pub bid_price: Num,
+  /// The bid size.
+  #[serde(rename = "bs")]
+  pub bid_size: u64,
+  /// The quote conditions.
+  #[serde(rename = "c", default, deserialize_with = "vec_from_optional")]
+  pub conditions: Vec<String>,
+  /// The tape the quote was reported on.
+  #[serde(rename = "z")]
+  pub tape: Tape,
+}
+
+impl Quote {
+  /// Check whether the quote has both a bid and an ask, i.e., whether
+  /// both prices are positive.
+  #[inline]
+  fn is_two_sided(&self) -> bool {
+    self.bid_price.is_positive() && self.ask_price.is_positive()
+  }
+
+  /// Calculate the midpoint between bid and ask price.
+  ///
+  /// `None` is returned if either side is missing, i.e., its price is
+  /// not positive.
+  pub fn midpoint(&self) -> Option<Num> {
+    if self.is_two_sided() {
+      Some((&self.bid_price + &self.ask_price) / 2)
+    } else {
+      None
+    }
+  }
+
+  /// Calculate the difference between ask and bid price.
+  ///
+  /// `None` is returned if either side is missing.
+  pub fn spread(&self) -> Option<Num> {
+    if self.is_two_sided() {
+      Some(&self.ask_price - &self.bid_price)
+    } else {
+      None
+    }
+  }
+
+  /// Calculate the spread in basis points of the midpoint.
+  ///
+  /// `None` is returned if either side is missing.
+  pub fn spread_bps(&self) -> Option<Num> {
+    let midpoint = self.midpoint()?;
+    let spread = &self.ask_price - &self.bid_price;
+    Some(spread * 10_000 / midpoint)
+  }
+
+  /// Check whether the quote is stale compared to the given bar, e.g.,
+  /// the most recent minute bar of the symbol.
+  ///
+  /// A quote is considered stale if it predates the beginning of the
+  /// bar, i.e., if trading activity was recorded after the quote was
+  /// reported.
+  pub fn is_stale(&self, bar: &Bar) -> bool {
+    self.time < bar.time
+  }
+}
+
+
+/// A quote that borrows its conditions from the data it got
+/// deserialized from.
+///
+/// The type is meant to be used in conjunction with
+/// `Client::issue_raw`, for cases where allocations are to be kept to
+/// a minimum.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct BorrowedQuote<'a> {
+  /// The time of the quote.
+  #[serde(rename = "t", deserialize_with = "system_time_from_str")]
+  pub time: SystemTime,
+  /// The exchange the ask originates from.
+  #[serde(rename = "ax")]
+  pub ask_exchange: Exchange,
+  /// The ask price.
+  #[serde(rename = "ap")]
+  pub ask_price: Num,
+  /// The ask size.
+  #[serde(rename = "as")]
+  pub ask_size: u64,
+  /// The exchange the bid originates from.
+  #[serde(rename = "bx")]
+  pub bid_exchange: Exchange,
+  /// The bid price.
+  #[serde(rename = "bp")]
+  pub bid_price: Num,
+  /// The bid size.
+  #[serde(rename = "bs")]
+  pub bid_size: u64,
+  /// The quote conditions.
+  // Condition codes are short and never contain characters needing
+  // escaping, so we can borrow them directly.
+  #[serde(rename = "c", borrow, default, deserialize_with = "vec_from_optional")]
+  pub conditions: Vec<&'a str>,
+  /// The tape the quote was reported on.
+  #[serde(rename = "z")]
+  pub tape: Tape,
+}
+
+
+/// A collection of quotes as returned by the
+/// /v2/stocks/<symbol>/quotes endpoint.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Quotes {
+  /// The list of returned quotes.
+  #[serde(rename = "quotes", deserialize_with = "vec_from_optional")]
+  pub quotes: Vec<Quote>,
+  /// The symbol the quotes correspond to.
+  #[serde(rename = "symbol")]
+  pub symbol: String,
+  /// The token to provide to a request to get the next page of quotes
+  /// for this request.
+  #[serde(rename = "next_page_token")]
+  pub next_page_token: Option<String>,
+}
+
+
+/// The borrowed counterpart to `Quotes`.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct BorrowedQuotes<'a> {
+  /// The list of returned quotes.
+  #[serde(rename = "quotes", borrow, deserialize_with = "vec_from_optional")]
+  pub quotes: Vec<BorrowedQuote<'a>>,
+  /// The symbol the quotes correspond to.
+  #[serde(rename = "symbol", borrow)]
+  pub symbol: Cow<'a, str>,
+  /// The token to provide to a request to get the next page of quotes
+  /// for this request.
+  #[serde(rename = "next_page_token", borrow, deserialize_with = "optional_cow_str")]
+  pub next_page_token: Option<Cow<'a, str>>,
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v2/stocks/<symbol>/quotes endpoint.
+  pub Get(QuotesReq),
+  Ok => Quotes, [
+    /// The quotes were retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// One or more of the arguments are not well formed.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+    /// The provided symbol was not found.
+    /* 404 */ NOT_FOUND => NotFound,
+    /// One or more of the arguments are invalid.
+    /* 422 */ UNPROCESSABLE_ENTITY => InvalidArgument,
+  ]
+  Api => Data,
+  Prepare => |input, api_info| {
+    let _ = input.feed.get_or_insert(api_info.data_feed());
+  },
+
+  fn path(input: &Self::Input) -> Str {
+    format!("/v2/stocks/{}/quotes", input.symbol).into()
+  }
+
+  fn query(input: &Self::Input) -> Option<Str> {
+    // TODO: Realistically there should be no way for this unwrap to
+    //       ever panic because our conversion to strings should not be
+    //       fallible. But still, ideally we would not have to unwrap.
+    Some(to_query(input).unwrap().into())
+  }
+}
+
+impl Paged for Get {
+  fn next_page(input: &Self::Input, output: &Self::Output) -> Option<Self::Input> {
+    next_page_by_token(input, output.next_page_token.as_deref(), |input| {
+      &mut input.page_token
+    })
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::time::Duration;
+
+  use http_endpoint::Endpoint;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+  use time_util::parse_system_time_from_str;
+
+  use crate::api_info::ApiInfo;
+  use crate::test_support;
+  use crate::Client;
+
+
+  const RESPONSE: &str = r#"{
+  "quotes": [
+    {
+      "t": "2021-02-06T13:04:56.334320128Z",
+      "ax": "C",
+      "ap": 387.7,
+      "as": 1,
+      "bx": "N",
+      "bp": 387.67,
+      "bs": 1,
+      "c": [
+        "R"
+      ],
+      "z": "A"
+    }
+  ],
+  "symbol": "SPY",
+  "next_page_token": "U1BZfDIwMjEtMDItMDZUMTM6MDQ6NTYuMzM0MzIwMTI4Wg=="
+}"#;
+
+
+  #[test]
+  fn parse_reference_quotes() {
+    let result = from_json::<<Get as Endpoint>::Output>(RESPONSE).unwrap();
+    assert_eq!(result.symbol, "SPY");
+    assert_eq!(result.quotes.len(), 1);
+
+    let quote = &result.quotes[0];
+    assert_eq!(
+      quote.time,
+      parse_system_time_from_str("2021-02-06T13:04:56.334320128Z").unwrap()
+    );
+    assert_eq!(quote.ask_exchange, Exchange::National);
+    assert_eq!(quote.ask_price, Num::new(3877, 10));
+    assert_eq!(quote.ask_size, 1);
+    assert_eq!(quote.bid_exchange, Exchange::Nyse);
+    assert_eq!(quote.bid_price, Num::new(38767, 100));
+    assert_eq!(quote.bid_size, 1);
+    assert_eq!(quote.conditions, vec!["R".to_string()]);
+    assert_eq!(quote.tape, Tape::A);
+    assert!(result.next_page_token.is_some());
+  }
+
+  /// Check that we can calculate quote analytics.
+  #[test]
+  fn quote_analytics() {
+    let time = parse_system_time_from_str("2021-02-06T13:04:00Z").unwrap();
+    let quote = test_support::quote(time, Num::new(9995, 100), Num::new(10005, 100));
+    assert_eq!(quote.midpoint(), Some(Num::from(100)));
+    assert_eq!(quote.spread(), Some(Num::new(1, 10)));
+    assert_eq!(quote.spread_bps(), Some(Num::from(10)));
+
+    let p = || Num::from(100);
+    let bar = test_support::bar(time, p(), p(), p(), p(), 1);
+    assert!(!quote.is_stale(&bar));
+    let later = time + Duration::from_secs(60);
+    let bar = test_support::bar(later, p(), p(), p(), p(), 1);
+    assert!(quote.is_stale(&bar));
+
+    let one_sided = test_support::quote(time, Num::from(0), Num::from(100));
+    assert_eq!(one_sided.midpoint(), None);
+    assert_eq!(one_sided.spread(), None);
+    assert_eq!(one_sided.spread_bps(), None);
+  }
+
+  #[test]
+  fn parse_borrowed_quotes() {
+    let borrowed = from_json::<BorrowedQuotes<'_>>(RESPONSE).unwrap();
+    let owned = from_json::<Quotes>(RESPONSE).unwrap();
+
+    assert!(matches!(borrowed.symbol, Cow::Borrowed("SPY")));
+    assert_eq!(borrowed.quotes.len(), 1);
+    assert_eq!(borrowed.quotes[0].time, owned.quotes[0].time);
+    assert_eq!(borrowed.quotes[0].ask_price, owned.quotes[0].ask_price);
+    assert_eq!(borrowed.quotes[0].bid_price, owned.quotes[0].bid_price);
+    assert_eq!(borrowed.quotes[0].conditions, vec!["R"]);
+    assert_eq!(borrowed.next_page_token.as_deref(), owned.next_page_token.as_deref());
+  }
+
+  #[test]
+  fn parse_borrowed_quotes_with_escaped_token() {
+    let response = r#"{"quotes":[],"symbol":"SPY","next_page_token":"U1BZfDIw\/MjE="}"#;
+    let result = from_json::<BorrowedQuotes<'_>>(response).unwrap();
+    assert_eq!(result.next_page_token.as_deref(), Some("U1BZfDIw/MjE="));
+  }
+
+  #[test(tokio::test)]
+  async fn request_quotes() {
+    let start = parse_system_time_from_str("2021-02-08T14:30:00Z").unwrap();
+    let end = parse_system_time_from_str("2021-02-08T14:35:00Z").unwrap();
+    let request = QuotesReqInit {
+      limit: Some(5),
+      ..Default::default()
+    }
+    .init("SPY", start, end);
+
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+    let result = client.issue::<Get>(request).await.unwrap();
+
+    assert_eq!(result.symbol, "SPY");
+    assert_eq!(result.quotes.len(), 5);
+    assert!(result.next_page_token.is_some());
+  }
+}
@@ -0,0 +1,478 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Recording of live market data to rotating files.
+//!
+//! Data is written to one file per channel (`bars`, `quotes`, or
+//! `trades`), symbol, and rotation period, at
+//! `<directory>/<channel>/<symbol>/<period>.<extension>`. The period is
+//! derived from the time stamp of the data (in UTC), e.g., `2021-02-01`
+//! for daily and `2021-02-01T16` for hourly rotation. Files existing
+//! already are appended to.
+//!
+//! CSV files use the columns of the corresponding `csv::Record`
+//! implementation and can be read back using a `csv::Reader`. JSONL
+//! files contain one JSON object per line, with the same keys and
+//! values.
+//!
+//! ```no_run
+//! # use apca::data::v2::recorder::Config;
+//! # use apca::data::v2::recorder::Recorder;
+//! # use apca::data::v2::stream::MarketData;
+//! # use apca::data::v2::stream::Source;
+//! # use apca::ApiInfo;
+//! # use apca::Client;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = Client::new(ApiInfo::from_env()?);
+//! let data = MarketData {
+//!   trades: vec!["AAPL".to_string(), "SPY".to_string()],
+//!   ..Default::default()
+//! };
+//! let (_subscriber, stream) = client
+//!   .subscribe_market_data(Some(Source::Iex), &data)
+//!   .await?;
+//!
+//! let config = Config {
+//!   directory: "market-data".into(),
+//!   ..Default::default()
+//! };
+//! let mut recorder = Recorder::new(config);
+//! recorder.record_stream(stream).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::fs::create_dir_all;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::BufWriter;
+use std::io::Error as IoError;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use csv::Writer as CsvWriter;
+
+use futures::Stream;
+use futures::StreamExt as _;
+
+use serde_json::to_writer;
+use serde_json::Map;
+use serde_json::Value;
+
+use tracing::warn;
+
+use crate::data::v2::csv::Record;
+use crate::data::v2::stream::Data;
+use crate::data::v2::stream::MessageError;
+use crate::util::system_time_to_date_string;
+
+
+/// The format data is recorded in.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Format {
+  /// Comma separated values, with a header row.
+  #[default]
+  Csv,
+  /// Newline delimited JSON objects.
+  JsonLines,
+}
+
+impl Format {
+  /// Retrieve the file extension used for the format.
+  fn extension(self) -> &'static str {
+    match self {
+      Self::Csv => "csv",
+      Self::JsonLines => "jsonl",
+    }
+  }
+}
+
+
+/// The period after which to start a new file.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Rotation {
+  /// Start a new file every hour.
+  Hourly,
+  /// Start a new file every day.
+  #[default]
+  Daily,
+}
+
+impl Rotation {
+  /// Retrieve the name of the period the given time belongs to.
+  fn period(self, time: &SystemTime) -> String {
+    let date = system_time_to_date_string(time);
+    match self {
+      Self::Hourly => {
+        // It is fine to unwrap here because no time relevant to us can
+        // ever be before `UNIX_EPOCH`.
+        let secs = time.duration_since(UNIX_EPOCH).unwrap().as_secs();
+        format!("{}T{:02}", date, secs / 3600 % 24)
+      },
+      Self::Daily => date,
+    }
+  }
+}
+
+
+/// The configuration of a `Recorder`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+  /// The directory to record data in.
+  pub directory: PathBuf,
+  /// The format to record data in.
+  pub format: Format,
+  /// The period after which to start a new file.
+  pub rotation: Rotation,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+
+/// An error reported while recording a stream.
+#[derive(Debug)]
+pub enum RecordError<E> {
+  /// The data could not be written.
+  Io(IoError),
+  /// The stream reported an error.
+  Stream(E),
+}
+
+impl<E> Display for RecordError<E>
+where
+  E: Display,
+{
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    match self {
+      Self::Io(err) => write!(fmt, "failed to write market data: {}", err),
+      Self::Stream(err) => write!(fmt, "failed to receive market data: {}", err),
+    }
+  }
+}
+
+impl<E> StdError for RecordError<E>
+where
+  E: StdError + 'static,
+{
+  fn source(&self) -> Option<&(dyn StdError + 'static)> {
+    match self {
+      Self::Io(err) => Some(err),
+      Self::Stream(err) => Some(err),
+    }
+  }
+}
+
+
+/// The writer of an open file.
+enum Output {
+  /// A CSV writer.
+  Csv(Box<CsvWriter<File>>),
+  /// A JSONL writer.
+  JsonLines(BufWriter<File>),
+}
+
+impl Output {
+  /// Write a single row.
+  fn write(&mut self, header: &[&str], fields: Vec<String>) -> Result<(), IoError> {
+    match self {
+      Self::Csv(writer) => writer.write_record(fields).map_err(IoError::from),
+      Self::JsonLines(writer) => {
+        let object = header
+          .iter()
+          .map(|column| column.to_string())
+          .zip(fields.into_iter().map(Value::String))
+          .collect::<Map<_, _>>();
+        let () = to_writer(&mut *writer, &object)?;
+        writer.write_all(b"\n")
+      },
+    }
+  }
+
+  /// Flush all buffered data to the file.
+  fn flush(&mut self) -> Result<(), IoError> {
+    match self {
+      Self::Csv(writer) => writer.flush(),
+      Self::JsonLines(writer) => writer.flush(),
+    }
+  }
+}
+
+
+/// A currently open file.
+struct Sink {
+  /// The rotation period the file belongs to.
+  period: String,
+  /// The writer of the file.
+  output: Output,
+}
+
+
+/// A recorder of live market data, writing it to rotating files.
+pub struct Recorder {
+  /// The configuration in use.
+  config: Config,
+  /// The open files, per channel and symbol.
+  sinks: HashMap<(&'static str, String), Sink>,
+}
+
+impl Recorder {
+  /// Create a new `Recorder` using the given configuration.
+  ///
+  /// Files are only created once data is recorded to them.
+  pub fn new(config: Config) -> Self {
+    Self {
+      config,
+      sinks: HashMap::new(),
+    }
+  }
+
+  /// Open the file for the given channel, symbol, and period.
+  fn open(
+    &self,
+    channel: &str,
+    symbol: &str,
+    period: &str,
+    header: &[&str],
+  ) -> Result<Output, IoError> {
+    // Crypto symbols contain a slash, which we do not want to be
+    // interpreted as a path separator.
+    let directory = self
+      .config
+      .directory
+      .join(channel)
+      .join(symbol.replace('/', "-"));
+    let () = create_dir_all(&directory)?;
+
+    let path = directory.join(format!("{}.{}", period, self.config.format.extension()));
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let empty = file.metadata()?.len() == 0;
+
+    let output = match self.config.format {
+      Format::Csv => {
+        let mut writer = CsvWriter::from_writer(file);
+        if empty {
+          let () = writer.write_record(header)?;
+        }
+        Output::Csv(Box::new(writer))
+      },
+      Format::JsonLines => Output::JsonLines(BufWriter::new(file)),
+    };
+    Ok(output)
+  }
+
+  /// Write an object to the file for the given channel and symbol,
+  /// rotating the file if necessary.
+  fn write<T>(
+    &mut self,
+    channel: &'static str,
+    symbol: &str,
+    time: &SystemTime,
+    object: &T,
+  ) -> Result<(), IoError>
+  where
+    T: Record,
+  {
+    let period = self.config.rotation.period(time);
+    let key = (channel, symbol.to_string());
+
+    let current = self.sinks.get(&key).map(|sink| sink.period == period);
+    let sink = match current {
+      Some(true) => self.sinks.get_mut(&key).unwrap(),
+      _ => {
+        if let Some(mut sink) = self.sinks.remove(&key) {
+          let () = sink.output.flush()?;
+        }
+        let output = self.open(channel, symbol, &period, T::HEADER)?;
+        self.sinks.entry(key).or_insert(Sink { period, output })
+      },
+    };
+    sink.output.write(T::HEADER, object.to_record())
+  }
+
+  /// Record a single piece of market data.
+  pub fn record(&mut self, data: &Data) -> Result<(), IoError> {
+    match data {
+      Data::Bar(bar) => self.write("bars", &bar.symbol, &bar.data.time, &bar.data),
+      Data::Quote(quote) => self.write("quotes", &quote.symbol, &quote.data.time, &quote.data),
+      Data::Trade(trade) => self.write("trades", &trade.symbol, &trade.data.time, &trade.data),
+    }
+  }
+
+  /// Record all market data received through the given stream, until
+  /// it ends or reports an error.
+  ///
+  /// Messages that could not be decoded are skipped with a warning.
+  pub async fn record_stream<S, E>(&mut self, stream: S) -> Result<(), RecordError<E>>
+  where
+    S: Stream<Item = Result<Result<Data, MessageError>, E>>,
+  {
+    let mut stream = Box::pin(stream);
+    let result = loop {
+      match stream.next().await {
+        Some(Ok(Ok(data))) => {
+          if let Err(err) = self.record(&data) {
+            break Err(RecordError::Io(err))
+          }
+        },
+        Some(Ok(Err(err))) => {
+          warn!(err = display(&err), "skipping market data message");
+        },
+        Some(Err(err)) => break Err(RecordError::Stream(err)),
+        None => break Ok(()),
+      }
+    };
+
+    let () = self.flush().map_err(RecordError::Io)?;
+    result
+  }
+
+  /// Flush all buffered data to the files.
+  pub fn flush(&mut self) -> Result<(), IoError> {
+    self
+      .sinks
+      .values_mut()
+      .try_for_each(|sink| sink.output.flush())
+  }
+}
+
+impl Debug for Recorder {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    fmt
+      .debug_struct("Recorder")
+      .field("config", &self.config)
+      .finish()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::env::temp_dir;
+  use std::fs::read_to_string;
+  use std::fs::remove_dir_all;
+
+  use futures::stream::iter;
+
+  use num_decimal::Num;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+  use time_util::parse_system_time_from_str;
+
+  use uuid::Uuid;
+
+  use crate::data::v2::bars::Bar;
+  use crate::data::v2::csv::Reader;
+  use crate::data::v2::stream::SymbolData;
+  use crate::test_support;
+
+
+  /// Create a bar for the given symbol at the given time.
+  fn bar(symbol: &str, time: &str) -> Data {
+    let time = parse_system_time_from_str(time).unwrap();
+    let price = Num::from(100);
+    Data::Bar(SymbolData {
+      symbol: symbol.to_string(),
+      data: test_support::bar(time, price.clone(), price.clone(), price.clone(), price, 10),
+    })
+  }
+
+  /// Check that we write data to rotating CSV files.
+  #[test(tokio::test)]
+  async fn record_csv() {
+    let directory = temp_dir().join(Uuid::new_v4().to_string());
+    let config = Config {
+      directory: directory.clone(),
+      rotation: Rotation::Daily,
+      ..Default::default()
+    };
+    let data: Vec<Result<_, IoError>> = vec![
+      Ok(Ok(bar("AAPL", "2021-02-01T16:00:00Z"))),
+      Ok(Ok(bar("SPY", "2021-02-01T16:00:00Z"))),
+      Ok(Ok(bar("AAPL", "2021-02-01T16:01:00Z"))),
+      Ok(Ok(bar("AAPL", "2021-02-02T14:30:00Z"))),
+    ];
+    let mut recorder = Recorder::new(config);
+    let () = recorder.record_stream(iter(data)).await.unwrap();
+
+    let file = File::open(directory.join("bars/AAPL/2021-02-01.csv")).unwrap();
+    let bars = Reader::<_, Bar>::new(file)
+      .unwrap()
+      .collect::<Result<Vec<_>, _>>()
+      .unwrap();
+    assert_eq!(bars.len(), 2);
+
+    let file = File::open(directory.join("bars/AAPL/2021-02-02.csv")).unwrap();
+    let bars = Reader::<_, Bar>::new(file)
+      .unwrap()
+      .collect::<Result<Vec<_>, _>>()
+      .unwrap();
+    assert_eq!(bars.len(), 1);
+    assert!(directory.join("bars/SPY/2021-02-01.csv").exists());
+
+    // Recording more data for the same period appends to the existing
+    // file, without repeating the header.
+    let config = Config {
+      directory: directory.clone(),
+      ..Default::default()
+    };
+    let mut recorder = Recorder::new(config);
+    let () = recorder
+      .record(&bar("SPY", "2021-02-01T16:05:00Z"))
+      .unwrap();
+    let () = recorder.flush().unwrap();
+
+    let file = File::open(directory.join("bars/SPY/2021-02-01.csv")).unwrap();
+    let bars = Reader::<_, Bar>::new(file)
+      .unwrap()
+      .collect::<Result<Vec<_>, _>>()
+      .unwrap();
+    assert_eq!(bars.len(), 2);
+
+    remove_dir_all(directory).unwrap();
+  }
+
+  /// Check that we write data to hourly rotated JSONL files.
+  #[test]
+  fn record_json_lines() {
+    let directory = temp_dir().join(Uuid::new_v4().to_string());
+    let config = Config {
+      directory: directory.clone(),
+      format: Format::JsonLines,
+      rotation: Rotation::Hourly,
+      ..Default::default()
+    };
+    let mut recorder = Recorder::new(config);
+    let () = recorder
+      .record(&bar("AAPL", "2021-02-01T16:00:00Z"))
+      .unwrap();
+    let () = recorder
+      .record(&bar("AAPL", "2021-02-01T17:00:00Z"))
+      .unwrap();
+    let () = recorder.flush().unwrap();
+
+    let content = read_to_string(directory.join("bars/AAPL/2021-02-01T16.jsonl")).unwrap();
+    let lines = content.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 1);
+    let object = from_json::<Value>(lines[0]).unwrap();
+    assert_eq!(object["time"], "2021-02-01T16:00:00Z");
+    assert_eq!(object["close"], "100");
+    assert!(directory.join("bars/AAPL/2021-02-01T17.jsonl").exists());
+
+    remove_dir_all(directory).unwrap();
+  }
+}
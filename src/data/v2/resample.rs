@@ -0,0 +1,589 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::cmp::max;
+#[cfg(feature = "trading")]
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use futures::stream::iter;
+use futures::stream::Stream;
+use futures::StreamExt;
+
+use num_decimal::Num;
+
+use crate::data::v2::bars::Bar;
+#[cfg(feature = "trading")]
+use crate::sessions::Session;
+use crate::util::us_eastern_offset;
+
+
+/// The number of seconds in a day.
+const DAY: i64 = 24 * 60 * 60;
+/// The regular trading hours of US stock exchanges.
+const REGULAR_HOURS: Hours = Hours {
+  open: 9 * 3600 + 30 * 60,
+  close: 16 * 3600,
+};
+
+
+/// An error indicating that an interval has a length of zero.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct EmptyInterval;
+
+impl Display for EmptyInterval {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    write!(fmt, "interval must not be empty")
+  }
+}
+
+impl std::error::Error for EmptyInterval {}
+
+
+/// Split the given time into the day, in US Eastern time, and the
+/// number of seconds since midnight on that day.
+fn eastern_time(time: SystemTime) -> (i64, i64) {
+  // It is fine to unwrap here because no time relevant to us can ever
+  // be before `UNIX_EPOCH`.
+  let secs = time.duration_since(UNIX_EPOCH).unwrap().as_secs();
+  let local = secs as i64 + us_eastern_offset(secs);
+  let day = local.div_euclid(DAY);
+  (day, local - day * DAY)
+}
+
+
+/// The trading hours of a day, as seconds since midnight US Eastern
+/// time.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Hours {
+  /// The time the market opens at.
+  open: i64,
+  /// The time the market closes at.
+  close: i64,
+}
+
+impl Hours {
+  /// Check whether the given time, in seconds since midnight, is
+  /// within the trading hours.
+  fn contains(&self, since_midnight: i64) -> bool {
+    (self.open..self.close).contains(&since_midnight)
+  }
+
+  /// Calculate the start of the intraday interval of the given length
+  /// the given time, in seconds since midnight, falls into.
+  ///
+  /// Intervals are aligned to the open and never span the open or the
+  /// close, so that regular and extended hours trading are not mixed.
+  fn align(&self, since_midnight: i64, length: i64) -> i64 {
+    let start = self.open + (since_midnight - self.open).div_euclid(length) * length;
+    let boundary = if since_midnight >= self.close {
+      self.close
+    } else if since_midnight >= self.open {
+      self.open
+    } else {
+      0
+    };
+    max(start, boundary)
+  }
+}
+
+#[cfg(feature = "trading")]
+impl From<&Session> for Hours {
+  fn from(session: &Session) -> Self {
+    Self {
+      open: eastern_time(session.open).1,
+      close: eastern_time(session.close).1,
+    }
+  }
+}
+
+
+/// The interval to resample bars into.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Interval {
+  /// An interval of the given number of minutes.
+  Minutes(u32),
+  /// An interval of the given number of hours.
+  Hours(u32),
+  /// An interval of one trading day.
+  Day,
+  /// An interval of one week, starting on Monday.
+  Week,
+}
+
+impl Interval {
+  /// Check whether the interval is shorter than a trading day.
+  fn is_intraday(&self) -> bool {
+    matches!(self, Self::Minutes(..) | Self::Hours(..))
+  }
+
+  /// Calculate the start of the interval the given time falls into.
+  ///
+  /// Intraday intervals are aligned to the open of the provided trading
+  /// hours and never span multiple days. Day and week intervals start
+  /// at midnight US Eastern time, the time zone of the US stock
+  /// exchanges.
+  fn start(&self, time: SystemTime, hours: &Hours) -> SystemTime {
+    // It is fine to unwrap here because no time relevant to us can ever
+    // be before `UNIX_EPOCH`.
+    let secs = time.duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let (day, since_midnight) = eastern_time(time);
+
+    let start = match *self {
+      Self::Minutes(minutes) => {
+        let length = i64::from(minutes) * 60;
+        day * DAY + hours.align(since_midnight, length)
+      },
+      Self::Hours(count) => {
+        let length = i64::from(count) * 3600;
+        day * DAY + hours.align(since_midnight, length)
+      },
+      Self::Day => day * DAY,
+      // 1970-01-01 was a Thursday, i.e., three days after a Monday.
+      Self::Week => (day - (day + 3).rem_euclid(7)) * DAY,
+    };
+
+    // Convert back to UTC. The offset is the one in effect at the start
+    // of the interval, which may differ from the one at `time` if a
+    // daylight saving time transition happened in between.
+    let guess = (start - us_eastern_offset(secs)) as u64;
+    let start = (start - us_eastern_offset(guess)) as u64;
+    UNIX_EPOCH + Duration::from_secs(start)
+  }
+}
+
+
+/// Merge a bar into the bar aggregating the current interval.
+fn merge(aggregate: &mut Bar, bar: Bar) {
+  if bar.high > aggregate.high {
+    aggregate.high = bar.high;
+  }
+  if bar.low < aggregate.low {
+    aggregate.low = bar.low;
+  }
+
+  aggregate.weighted_average = match (aggregate.weighted_average.take(), bar.weighted_average) {
+    (Some(lhs), Some(rhs)) => {
+      let volume = aggregate.volume + bar.volume;
+      if volume == 0 {
+        Some(lhs)
+      } else {
+        let total = lhs * Num::from(aggregate.volume) + rhs * Num::from(bar.volume);
+        Some(total / Num::from(volume))
+      }
+    },
+    _ => None,
+  };
+  aggregate.trade_count = match (aggregate.trade_count, bar.trade_count) {
+    (Some(lhs), Some(rhs)) => Some(lhs + rhs),
+    _ => None,
+  };
+  aggregate.close = bar.close;
+  aggregate.volume += bar.volume;
+}
+
+
+/// A type for resampling bars of a single symbol into bars covering a
+/// larger interval.
+///
+/// Bars are expected to be provided in ascending order of time. Each
+/// resulting bar starts at the beginning of its interval, as opposed
+/// to the time of the first bar it aggregates. The volume weighted
+/// average price and the trade count are only reported if all
+/// aggregated bars contain them.
+///
+/// Unless sessions are provided, the regular trading hours are assumed
+/// to be 09:30 to 16:00 US Eastern time on every day. Intraday
+/// intervals are aligned to the open and pre- and post-market bars are
+/// aggregated separately from those of the regular session. Day and
+/// week intervals only cover the regular session, i.e., extended hours
+/// bars are ignored.
+///
+/// ```
+/// # use apca::data::v2::resample::Interval;
+/// # use apca::data::v2::resample::Resampler;
+/// # fn resample(bars: Vec<apca::data::v2::bars::Bar>) {
+/// let mut resampler = Resampler::new(Interval::Minutes(5)).unwrap();
+/// for bar in bars {
+///   if let Some(bar) = resampler.push(bar) {
+///     println!("{:?}", bar);
+///   }
+/// }
+/// if let Some(bar) = resampler.finish() {
+///   println!("{:?}", bar);
+/// }
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Resampler {
+  /// The interval to resample to.
+  interval: Interval,
+  /// The bar aggregating the current interval, if any.
+  current: Option<Bar>,
+  /// The trading sessions to restrict resampling to, if any, oldest
+  /// first.
+  #[cfg(feature = "trading")]
+  sessions: Option<VecDeque<Session>>,
+}
+
+impl Resampler {
+  /// Create a new `Resampler` resampling to the given interval.
+  ///
+  /// An error is reported if the interval has a length of zero.
+  pub fn new(interval: Interval) -> Result<Self, EmptyInterval> {
+    match interval {
+      Interval::Minutes(0) | Interval::Hours(0) => return Err(EmptyInterval),
+      _ => (),
+    }
+
+    Ok(Self {
+      interval,
+      current: None,
+      #[cfg(feature = "trading")]
+      sessions: None,
+    })
+  }
+
+  /// Restrict resampling to the given trading sessions, in ascending
+  /// order of time.
+  ///
+  /// Intraday intervals are aligned to the open of the respective
+  /// session, accounting for early closes, and bars outside of all
+  /// sessions, including extended hours bars, are ignored.
+  #[cfg(feature = "trading")]
+  pub fn sessions<I>(mut self, sessions: I) -> Self
+  where
+    I: IntoIterator<Item = Session>,
+  {
+    self.sessions = Some(sessions.into_iter().collect());
+    self
+  }
+
+  /// Retrieve the trading hours relevant to a bar at the given time,
+  /// if the bar is to be considered at all.
+  fn hours(&mut self, time: SystemTime) -> Option<Hours> {
+    #[cfg(feature = "trading")]
+    if let Some(sessions) = &mut self.sessions {
+      // Bars arrive in ascending order of time, so sessions that closed
+      // already will not be needed again.
+      while sessions.front().map(|session| session.close <= time) == Some(true) {
+        let _ = sessions.pop_front();
+      }
+      return sessions
+        .front()
+        .filter(|session| session.contains(time))
+        .map(Hours::from)
+    }
+
+    let hours = REGULAR_HOURS;
+    if self.interval.is_intraday() || hours.contains(eastern_time(time).1) {
+      Some(hours)
+    } else {
+      None
+    }
+  }
+
+  /// Add a bar, returning the bar for the previous interval if the new
+  /// one starts a new interval.
+  ///
+  /// Bars not covered by the interval, e.g., extended hours bars when
+  /// resampling to days, are ignored.
+  pub fn push(&mut self, bar: Bar) -> Option<Bar> {
+    let hours = self.hours(bar.time)?;
+    let start = self.interval.start(bar.time, &hours);
+    match &mut self.current {
+      Some(current) if current.time == start => {
+        merge(current, bar);
+        None
+      },
+      _ => self.current.replace(Bar { time: start, ..bar }),
+    }
+  }
+
+  /// Finish resampling, returning the bar for the last interval, if
+  /// any.
+  pub fn finish(&mut self) -> Option<Bar> {
+    self.current.take()
+  }
+}
+
+
+/// Resample the given bars of a single symbol, in ascending order of
+/// time, to the provided interval.
+///
+/// An error is reported if the interval has a length of zero.
+pub fn resample<I>(bars: I, interval: Interval) -> Result<Vec<Bar>, EmptyInterval>
+where
+  I: IntoIterator<Item = Bar>,
+{
+  let mut resampler = Resampler::new(interval)?;
+  let mut resampled = bars
+    .into_iter()
+    .filter_map(|bar| resampler.push(bar))
+    .collect::<Vec<_>>();
+  resampled.extend(resampler.finish());
+  Ok(resampled)
+}
+
+
+/// Resample a stream of bars of a single symbol, in ascending order of
+/// time, to the provided interval.
+///
+/// A resampled bar is reported once the first bar of the next interval
+/// was received or the stream ended. An error is reported if the
+/// interval has a length of zero.
+pub fn resample_stream<S>(
+  bars: S,
+  interval: Interval,
+) -> Result<impl Stream<Item = Bar>, EmptyInterval>
+where
+  S: Stream<Item = Bar>,
+{
+  let mut resampler = Resampler::new(interval)?;
+  let stream = bars.map(Some).chain(iter(vec![None])).flat_map(move |bar| {
+    let bar = match bar {
+      Some(bar) => resampler.push(bar),
+      None => resampler.finish(),
+    };
+    iter(bar)
+  });
+  Ok(stream)
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use time_util::parse_system_time_from_str;
+
+  use test_env_log::test;
+
+  use crate::test_support::bar;
+
+
+  /// Create a bar at the given time with the given prices.
+  fn bar_at(time: &str, open: u64, high: u64, low: u64, close: u64, volume: u64) -> Bar {
+    let time = parse_system_time_from_str(time).unwrap();
+    bar(
+      time,
+      Num::from(open),
+      Num::from(high),
+      Num::from(low),
+      Num::from(close),
+      volume,
+    )
+  }
+
+  /// Parse a time string.
+  fn time(time: &str) -> SystemTime {
+    parse_system_time_from_str(time).unwrap()
+  }
+
+
+  #[test]
+  fn resample_minutes() {
+    let bars = vec![
+      bar_at("2021-01-05T14:30:00Z", 10, 12, 9, 11, 100),
+      bar_at("2021-01-05T14:31:00Z", 11, 14, 11, 13, 200),
+      bar_at("2021-01-05T14:34:00Z", 13, 13, 8, 9, 50),
+      bar_at("2021-01-05T14:35:00Z", 9, 10, 9, 10, 10),
+    ];
+
+    let bars = resample(bars, Interval::Minutes(5)).unwrap();
+    assert_eq!(bars.len(), 2);
+    assert_eq!(bars[0].time, time("2021-01-05T14:30:00Z"));
+    assert_eq!(bars[0].open, Num::from(10));
+    assert_eq!(bars[0].high, Num::from(14));
+    assert_eq!(bars[0].low, Num::from(8));
+    assert_eq!(bars[0].close, Num::from(9));
+    assert_eq!(bars[0].volume, 350);
+    assert_eq!(bars[1].time, time("2021-01-05T14:35:00Z"));
+    assert_eq!(bars[1].volume, 10);
+  }
+
+  /// Check that hour intervals are aligned to the open.
+  #[test]
+  fn resample_hours() {
+    let bars = vec![
+      bar_at("2021-07-06T13:30:00Z", 10, 10, 10, 10, 1),
+      bar_at("2021-07-06T14:29:00Z", 10, 10, 10, 10, 1),
+      bar_at("2021-07-06T14:30:00Z", 10, 10, 10, 10, 1),
+    ];
+
+    let bars = resample(bars, Interval::Hours(1)).unwrap();
+    assert_eq!(bars.len(), 2);
+    assert_eq!(bars[0].time, time("2021-07-06T13:30:00Z"));
+    assert_eq!(bars[0].volume, 2);
+    assert_eq!(bars[1].time, time("2021-07-06T14:30:00Z"));
+  }
+
+  /// Check that intraday intervals do not mix extended hours bars with
+  /// those of the regular session.
+  #[test]
+  fn resample_extended_hours_intraday() {
+    let bars = vec![
+      // 08:10 EDT, pre-market.
+      bar_at("2021-07-06T12:10:00Z", 10, 10, 10, 10, 1),
+      // 09:10 EDT, pre-market.
+      bar_at("2021-07-06T13:10:00Z", 11, 11, 11, 11, 1),
+      bar_at("2021-07-06T13:30:00Z", 12, 12, 12, 12, 1),
+      // 15:50 EDT, in the last regular interval.
+      bar_at("2021-07-06T19:50:00Z", 13, 13, 13, 13, 1),
+      // 16:10 EDT, post-market.
+      bar_at("2021-07-06T20:10:00Z", 14, 14, 14, 14, 1),
+    ];
+
+    let bars = resample(bars, Interval::Hours(1)).unwrap();
+    let times = bars.iter().map(|bar| bar.time).collect::<Vec<_>>();
+    let expected = vec![
+      time("2021-07-06T11:30:00Z"),
+      time("2021-07-06T12:30:00Z"),
+      time("2021-07-06T13:30:00Z"),
+      time("2021-07-06T19:30:00Z"),
+      time("2021-07-06T20:00:00Z"),
+    ];
+    assert_eq!(times, expected);
+    assert!(bars.iter().all(|bar| bar.volume == 1));
+  }
+
+  /// Check that days are split at midnight US Eastern time and only
+  /// cover the regular session.
+  #[test]
+  fn resample_days() {
+    let bars = vec![
+      // 07:00 EST, pre-market.
+      bar_at("2021-01-04T12:00:00Z", 9, 9, 9, 9, 1),
+      bar_at("2021-01-04T14:30:00Z", 10, 10, 10, 10, 1),
+      bar_at("2021-01-04T20:59:00Z", 11, 11, 11, 11, 1),
+      // 19:59 EST, post-market on January 4th.
+      bar_at("2021-01-05T00:59:00Z", 20, 20, 20, 20, 1),
+      bar_at("2021-01-05T14:30:00Z", 12, 12, 12, 12, 1),
+    ];
+
+    let bars = resample(bars, Interval::Day).unwrap();
+    assert_eq!(bars.len(), 2);
+    assert_eq!(bars[0].time, time("2021-01-04T05:00:00Z"));
+    assert_eq!(bars[0].open, Num::from(10));
+    assert_eq!(bars[0].high, Num::from(11));
+    assert_eq!(bars[0].close, Num::from(11));
+    assert_eq!(bars[0].volume, 2);
+    assert_eq!(bars[1].time, time("2021-01-05T05:00:00Z"));
+  }
+
+  /// Check that we restrict resampling to the provided sessions.
+  #[cfg(feature = "trading")]
+  #[test]
+  fn resample_sessions() {
+    // The day after Thanksgiving, closing early at 13:00 EST.
+    let session = Session {
+      date: time("2020-11-27T00:00:00Z"),
+      open: time("2020-11-27T14:30:00Z"),
+      close: time("2020-11-27T18:00:00Z"),
+      early_close: true,
+    };
+    let bars = vec![
+      bar_at("2020-11-26T15:00:00Z", 9, 9, 9, 9, 1),
+      bar_at("2020-11-27T14:30:00Z", 10, 10, 10, 10, 1),
+      bar_at("2020-11-27T17:59:00Z", 11, 11, 11, 11, 1),
+      // 13:30 EST, after the early close.
+      bar_at("2020-11-27T18:30:00Z", 12, 12, 12, 12, 1),
+    ];
+
+    let mut resampler = Resampler::new(Interval::Day)
+      .unwrap()
+      .sessions(vec![session]);
+    let mut bars = bars
+      .into_iter()
+      .filter_map(|bar| resampler.push(bar))
+      .collect::<Vec<_>>();
+    bars.extend(resampler.finish());
+    assert_eq!(bars.len(), 1);
+    assert_eq!(bars[0].time, time("2020-11-27T05:00:00Z"));
+    assert_eq!(bars[0].close, Num::from(11));
+    assert_eq!(bars[0].volume, 2);
+  }
+
+  #[test]
+  fn resample_weeks() {
+    let bars = vec![
+      // Friday.
+      bar_at("2021-07-09T19:59:00Z", 10, 10, 10, 10, 1),
+      // Monday.
+      bar_at("2021-07-12T13:30:00Z", 11, 11, 11, 11, 1),
+      // Friday.
+      bar_at("2021-07-16T19:59:00Z", 12, 12, 12, 12, 1),
+    ];
+
+    let bars = resample(bars, Interval::Week).unwrap();
+    assert_eq!(bars.len(), 2);
+    assert_eq!(bars[0].time, time("2021-07-05T04:00:00Z"));
+    assert_eq!(bars[1].time, time("2021-07-12T04:00:00Z"));
+    assert_eq!(bars[1].volume, 2);
+  }
+
+  /// Check that daylight saving time transitions within an interval
+  /// are accounted for.
+  #[test]
+  fn resample_across_dst_transition() {
+    let start = Interval::Week.start(time("2021-03-15T13:30:00Z"), &REGULAR_HOURS);
+    assert_eq!(start, time("2021-03-15T04:00:00Z"));
+
+    let start = Interval::Week.start(time("2021-03-19T13:30:00Z"), &REGULAR_HOURS);
+    assert_eq!(start, time("2021-03-15T04:00:00Z"));
+
+    // The week of the transition started in EST.
+    let start = Interval::Week.start(time("2021-03-14T13:30:00Z"), &REGULAR_HOURS);
+    assert_eq!(start, time("2021-03-08T05:00:00Z"));
+  }
+
+  #[test]
+  fn aggregate_weighted_average() {
+    let mut first = bar_at("2021-01-05T14:30:00Z", 10, 10, 10, 10, 100);
+    first.weighted_average = Some(Num::from(10));
+    first.trade_count = Some(3);
+    let mut second = bar_at("2021-01-05T14:31:00Z", 20, 20, 20, 20, 300);
+    second.weighted_average = Some(Num::from(20));
+    second.trade_count = Some(5);
+
+    let bars = resample(vec![first.clone(), second], Interval::Minutes(5)).unwrap();
+    assert_eq!(bars[0].weighted_average, Some(Num::new(35, 2)));
+    assert_eq!(bars[0].trade_count, Some(8));
+
+    let third = bar_at("2021-01-05T14:32:00Z", 20, 20, 20, 20, 300);
+    let bars = resample(vec![first, third], Interval::Minutes(5)).unwrap();
+    assert_eq!(bars[0].weighted_average, None);
+    assert_eq!(bars[0].trade_count, None);
+  }
+
+  #[test(tokio::test)]
+  async fn resample_bar_stream() {
+    let bars = vec![
+      bar_at("2021-01-05T14:30:00Z", 10, 10, 10, 10, 1),
+      bar_at("2021-01-05T14:31:00Z", 10, 10, 10, 10, 1),
+      bar_at("2021-01-05T14:45:00Z", 10, 10, 10, 10, 1),
+    ];
+
+    let bars = resample_stream(iter(bars), Interval::Minutes(15))
+      .unwrap()
+      .collect::<Vec<_>>()
+      .await;
+    assert_eq!(bars.len(), 2);
+    assert_eq!(bars[0].volume, 2);
+    assert_eq!(bars[1].time, time("2021-01-05T14:45:00Z"));
+  }
+
+  /// Check that we reject intervals with a length of zero.
+  #[test]
+  fn reject_empty_interval() {
+    let err = Resampler::new(Interval::Minutes(0)).unwrap_err();
+    assert_eq!(err, EmptyInterval);
+    assert_eq!(err.to_string(), "interval must not be empty");
+    assert_eq!(resample(Vec::new(), Interval::Hours(0)), Err(EmptyInterval));
+  }
+}
@@ -0,0 +1,332 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::iter::FromIterator;
+use std::iter::FusedIterator;
+use std::time::SystemTime;
+
+use num_decimal::Num;
+
+use crate::data::v2::bars::Bar;
+
+
+/// Convert a price into a floating point value, using NaN for values
+/// that cannot be represented.
+#[inline]
+fn float(num: &Num) -> f64 {
+  num.to_f64().unwrap_or(f64::NAN)
+}
+
+
+/// A column oriented view of a sequence of bars.
+///
+/// Technical indicator implementations typically operate on slices
+/// of floating point values, e.g., closing prices. A `Series`
+/// converts bars into such aligned columns once, after which all
+/// accessors and windows merely borrow from it: the value at index
+/// `i` of every column belongs to the same bar.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Series {
+  time: Vec<SystemTime>,
+  open: Vec<f64>,
+  high: Vec<f64>,
+  low: Vec<f64>,
+  close: Vec<f64>,
+  volume: Vec<f64>,
+}
+
+impl Series {
+  /// Create a `Series` from the given bars.
+  pub fn new(bars: &[Bar]) -> Self {
+    let mut series = Self {
+      time: Vec::with_capacity(bars.len()),
+      open: Vec::with_capacity(bars.len()),
+      high: Vec::with_capacity(bars.len()),
+      low: Vec::with_capacity(bars.len()),
+      close: Vec::with_capacity(bars.len()),
+      volume: Vec::with_capacity(bars.len()),
+    };
+    series.extend(bars);
+    series
+  }
+
+  /// Append a bar to the series.
+  pub fn push(&mut self, bar: &Bar) {
+    self.time.push(bar.time);
+    self.open.push(float(&bar.open));
+    self.high.push(float(&bar.high));
+    self.low.push(float(&bar.low));
+    self.close.push(float(&bar.close));
+    self.volume.push(bar.volume as f64);
+  }
+
+  /// Retrieve the number of bars in the series.
+  #[inline]
+  pub fn len(&self) -> usize {
+    self.time.len()
+  }
+
+  /// Check whether the series is empty.
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.time.is_empty()
+  }
+
+  /// Retrieve the beginning times of the bars.
+  #[inline]
+  pub fn time(&self) -> &[SystemTime] {
+    &self.time
+  }
+
+  /// Retrieve the open prices.
+  #[inline]
+  pub fn open(&self) -> &[f64] {
+    &self.open
+  }
+
+  /// Retrieve the highest prices.
+  #[inline]
+  pub fn high(&self) -> &[f64] {
+    &self.high
+  }
+
+  /// Retrieve the lowest prices.
+  #[inline]
+  pub fn low(&self) -> &[f64] {
+    &self.low
+  }
+
+  /// Retrieve the close prices.
+  #[inline]
+  pub fn close(&self) -> &[f64] {
+    &self.close
+  }
+
+  /// Retrieve the trading volumes.
+  #[inline]
+  pub fn volume(&self) -> &[f64] {
+    &self.volume
+  }
+
+  /// Retrieve a view of the bars in the given index range.
+  ///
+  /// # Panics
+  /// This method panics if the range is out of bounds.
+  pub fn window(&self, start: usize, end: usize) -> Window<'_> {
+    Window {
+      time: &self.time[start..end],
+      open: &self.open[start..end],
+      high: &self.high[start..end],
+      low: &self.low[start..end],
+      close: &self.close[start..end],
+      volume: &self.volume[start..end],
+    }
+  }
+
+  /// Create an iterator over all overlapping windows of `size` bars,
+  /// advancing by one bar at a time.
+  ///
+  /// If the series contains fewer than `size` bars, no windows are
+  /// produced.
+  ///
+  /// # Panics
+  /// This method panics if `size` is zero.
+  pub fn windows(&self, size: usize) -> Windows<'_> {
+    assert!(size > 0, "window size must not be zero");
+
+    Windows {
+      series: self,
+      size,
+      start: 0,
+    }
+  }
+}
+
+impl<'b> Extend<&'b Bar> for Series {
+  fn extend<I>(&mut self, bars: I)
+  where
+    I: IntoIterator<Item = &'b Bar>,
+  {
+    bars.into_iter().for_each(|bar| self.push(bar))
+  }
+}
+
+impl<'b> FromIterator<&'b Bar> for Series {
+  fn from_iter<I>(bars: I) -> Self
+  where
+    I: IntoIterator<Item = &'b Bar>,
+  {
+    let mut series = Self::default();
+    series.extend(bars);
+    series
+  }
+}
+
+impl From<&[Bar]> for Series {
+  #[inline]
+  fn from(bars: &[Bar]) -> Self {
+    Self::new(bars)
+  }
+}
+
+
+/// A borrowed view of a contiguous range of a [`Series`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Window<'s> {
+  /// The beginning times of the bars.
+  pub time: &'s [SystemTime],
+  /// The open prices.
+  pub open: &'s [f64],
+  /// The highest prices.
+  pub high: &'s [f64],
+  /// The lowest prices.
+  pub low: &'s [f64],
+  /// The close prices.
+  pub close: &'s [f64],
+  /// The trading volumes.
+  pub volume: &'s [f64],
+}
+
+impl Window<'_> {
+  /// Retrieve the number of bars in the window.
+  #[inline]
+  pub fn len(&self) -> usize {
+    self.time.len()
+  }
+
+  /// Check whether the window is empty.
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.time.is_empty()
+  }
+}
+
+
+/// An iterator over rolling windows of a [`Series`].
+///
+/// Created by [`Series::windows`].
+#[derive(Clone, Debug)]
+pub struct Windows<'s> {
+  series: &'s Series,
+  size: usize,
+  start: usize,
+}
+
+impl<'s> Iterator for Windows<'s> {
+  type Item = Window<'s>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let end = self.start + self.size;
+    if end > self.series.len() {
+      return None
+    }
+
+    let window = self.series.window(self.start, end);
+    self.start += 1;
+    Some(window)
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let remaining = (self.series.len() + 1).saturating_sub(self.start + self.size);
+    (remaining, Some(remaining))
+  }
+}
+
+impl ExactSizeIterator for Windows<'_> {}
+
+impl FusedIterator for Windows<'_> {}
+
+
+/// Create an iterator over the close prices of the given bars, in
+/// their original precision and without copying them.
+#[inline]
+pub fn closes(bars: &[Bar]) -> impl ExactSizeIterator<Item = &Num> + DoubleEndedIterator {
+  bars.iter().map(|bar| &bar.close)
+}
+
+/// Create an iterator over the trading volumes of the given bars.
+#[inline]
+pub fn volumes(bars: &[Bar]) -> impl ExactSizeIterator<Item = u64> + DoubleEndedIterator + '_ {
+  bars.iter().map(|bar| bar.volume)
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::time::Duration;
+  use std::time::UNIX_EPOCH;
+
+  use test_env_log::test;
+
+  use crate::test_support::bar;
+
+
+  /// Create a number of bars with increasing prices.
+  fn bars(count: u64) -> Vec<Bar> {
+    (0..count)
+      .map(|i| {
+        bar(
+          UNIX_EPOCH + Duration::from_secs(60 * i),
+          Num::from(10 + i),
+          Num::from(12 + i),
+          Num::from(9 + i),
+          Num::new(21 + 2 * i as i64, 2),
+          100 * (i + 1),
+        )
+      })
+      .collect()
+  }
+
+
+  /// Check that a `Series` holds aligned columns of bar data.
+  #[test]
+  fn series_columns() {
+    let bars = bars(3);
+    let series = Series::new(&bars);
+
+    assert_eq!(series.len(), 3);
+    assert_eq!(series.time()[2], UNIX_EPOCH + Duration::from_secs(120));
+    assert_eq!(series.open(), &[10.0, 11.0, 12.0]);
+    assert_eq!(series.high(), &[12.0, 13.0, 14.0]);
+    assert_eq!(series.low(), &[9.0, 10.0, 11.0]);
+    assert_eq!(series.close(), &[10.5, 11.5, 12.5]);
+    assert_eq!(series.volume(), &[100.0, 200.0, 300.0]);
+
+    assert_eq!(bars.iter().collect::<Series>(), series);
+    assert_eq!(Series::from(bars.as_slice()), series);
+  }
+
+  /// Check that we can iterate over rolling windows of a `Series`.
+  #[test]
+  fn rolling_windows() {
+    let series = Series::new(&bars(4));
+    let windows = series.windows(3);
+    assert_eq!(windows.len(), 2);
+
+    let sums = windows
+      .map(|window| window.close.iter().sum::<f64>())
+      .collect::<Vec<_>>();
+    assert_eq!(sums, vec![34.5, 37.5]);
+
+    let window = series.windows(4).next().unwrap();
+    assert_eq!(window.len(), 4);
+    assert_eq!(window.volume[3], 400.0);
+
+    assert_eq!(series.windows(5).len(), 0);
+    assert_eq!(series.windows(5).next(), None);
+    assert_eq!(Series::default().windows(1).next(), None);
+  }
+
+  /// Check that the borrowing iterators yield the original values.
+  #[test]
+  fn borrowed_columns() {
+    let bars = bars(2);
+    let closes = closes(&bars).collect::<Vec<_>>();
+    assert_eq!(closes, vec![&Num::new(21, 2), &Num::new(23, 2)]);
+
+    let volumes = volumes(&bars).rev().collect::<Vec<_>>();
+    assert_eq!(volumes, vec![200, 100]);
+  }
+}
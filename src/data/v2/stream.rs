@@ -0,0 +1,1391 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::error::Error as StdError;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::RwLock;
+use std::task::Context;
+use std::task::Poll;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use futures::stream::iter;
+use futures::Sink;
+use futures::SinkExt;
+use futures::Stream;
+use futures::StreamExt;
+
+#[cfg(not(feature = "simd-json"))]
+use serde::de::DeserializeSeed;
+#[cfg(feature = "simd-json")]
+use serde::de::Error as _;
+#[cfg(not(feature = "simd-json"))]
+use serde::de::SeqAccess;
+#[cfg(not(feature = "simd-json"))]
+use serde::de::Visitor;
+use serde::Deserialize;
+#[cfg(not(feature = "simd-json"))]
+use serde::Deserializer;
+use serde::Serialize;
+use serde_json::from_slice;
+#[cfg(not(feature = "simd-json"))]
+use serde_json::from_str;
+use serde_json::to_string as to_json;
+#[cfg(not(feature = "simd-json"))]
+use serde_json::value::RawValue;
+#[cfg(not(feature = "simd-json"))]
+use serde_json::Deserializer as JsonDeserializer;
+use serde_json::Error as JsonError;
+use serde_variant::to_variant_name;
+
+#[cfg(feature = "simd-json")]
+use simd_json::to_borrowed_value_with_buffers;
+#[cfg(feature = "simd-json")]
+use simd_json::BorrowedValue;
+#[cfg(feature = "simd-json")]
+use simd_json::Buffers;
+
+use tokio::sync::mpsc::unbounded_channel;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::mpsc::UnboundedSender;
+
+use tracing::debug;
+use tracing::trace;
+
+use url::Url;
+
+use websocket_util::stream as do_stream;
+use websocket_util::tungstenite::Error as WebSocketError;
+use websocket_util::tungstenite::Message;
+
+use crate::api_info::ApiInfo;
+use crate::audit::AuditLog;
+use crate::data::v2::bars::Bar;
+use crate::data::v2::quotes::Quote;
+use crate::data::v2::trades::Trade;
+use crate::socket::connect as connect_socket;
+use crate::socket::SocketOptions;
+use crate::Client;
+use crate::Error;
+
+
+/// The source of real time market data.
+///
+/// The feed configured as part of the `ApiInfo` object in use is
+/// available through `ApiInfo::data_feed`.
+pub use crate::data::v2::Feed as Source;
+
+
+/// A set of symbols to subscribe to or unsubscribe from, per type of
+/// market data.
+///
+/// The symbol `*` can be used for referring to all symbols.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct MarketData {
+  /// The symbols for which to receive minute bars.
+  #[serde(rename = "bars", default)]
+  pub bars: Vec<String>,
+  /// The symbols for which to receive quotes.
+  #[serde(rename = "quotes", default)]
+  pub quotes: Vec<String>,
+  /// The symbols for which to receive trades.
+  #[serde(rename = "trades", default)]
+  pub trades: Vec<String>,
+}
+
+impl MarketData {
+  /// Check whether the object does not reference any symbols.
+  pub fn is_empty(&self) -> bool {
+    self.bars.is_empty() && self.quotes.is_empty() && self.trades.is_empty()
+  }
+}
+
+
+/// A piece of market data along with the symbol it belongs to.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct SymbolData<T> {
+  /// The symbol the data belongs to.
+  #[serde(rename = "S")]
+  pub symbol: String,
+  /// The actual data.
+  #[serde(flatten)]
+  pub data: T,
+}
+
+
+/// A market data message as received through the stream.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Data {
+  /// A minute bar.
+  Bar(SymbolData<Bar>),
+  /// A quote.
+  Quote(SymbolData<Quote>),
+  /// A trade.
+  Trade(SymbolData<Trade>),
+}
+
+impl Data {
+  /// Retrieve the symbol the data belongs to.
+  pub fn symbol(&self) -> &str {
+    match self {
+      Self::Bar(bar) => &bar.symbol,
+      Self::Quote(quote) => &quote.symbol,
+      Self::Trade(trade) => &trade.symbol,
+    }
+  }
+
+  /// Retrieve the time of the data, i.e., the beginning of the bar or
+  /// the time of the quote or trade.
+  ///
+  /// Times are reported by the server with nanosecond precision, which
+  /// is retained on platforms where `SystemTime` has such a resolution
+  /// (e.g., Linux and macOS).
+  pub fn time(&self) -> SystemTime {
+    match self {
+      Self::Bar(bar) => bar.data.time,
+      Self::Quote(quote) => quote.data.time,
+      Self::Trade(trade) => trade.data.time,
+    }
+  }
+
+  /// Retrieve the time of the data as the number of nanoseconds since
+  /// the Unix epoch, e.g., for sequencing messages or storing them
+  /// losslessly.
+  pub fn timestamp_nanos(&self) -> u128 {
+    // It is fine to unwrap here because no time relevant to us can ever
+    // be before `UNIX_EPOCH`.
+    self.time().duration_since(UNIX_EPOCH).unwrap().as_nanos()
+  }
+}
+
+
+/// The code of an error reported by the market data stream server.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ErrorCode {
+  /// The request was not well formed (400).
+  InvalidSyntax,
+  /// A request was sent before authenticating (401).
+  NotAuthenticated,
+  /// Authentication failed, e.g., due to invalid credentials (402).
+  AuthFailed,
+  /// The connection is already authenticated (403).
+  AlreadyAuthenticated,
+  /// Authentication did not happen in time (404).
+  AuthTimeout,
+  /// The maximum number of symbols that can be subscribed to was
+  /// exceeded (405).
+  SymbolLimitExceeded,
+  /// The maximum number of concurrent connections was exceeded
+  /// (406).
+  ConnectionLimitExceeded,
+  /// The client did not keep up with the data sent (407).
+  SlowClient,
+  /// The account's subscription does not cover the data requested
+  /// (409).
+  InsufficientSubscription,
+  /// An error internal to the server (500).
+  InternalError,
+  /// Any other error code.
+  Other(u64),
+}
+
+impl ErrorCode {
+  /// Retrieve the numeric code as reported by the server.
+  pub fn code(&self) -> u64 {
+    match self {
+      Self::InvalidSyntax => 400,
+      Self::NotAuthenticated => 401,
+      Self::AuthFailed => 402,
+      Self::AlreadyAuthenticated => 403,
+      Self::AuthTimeout => 404,
+      Self::SymbolLimitExceeded => 405,
+      Self::ConnectionLimitExceeded => 406,
+      Self::SlowClient => 407,
+      Self::InsufficientSubscription => 409,
+      Self::InternalError => 500,
+      Self::Other(code) => *code,
+    }
+  }
+}
+
+impl From<u64> for ErrorCode {
+  fn from(code: u64) -> Self {
+    match code {
+      400 => Self::InvalidSyntax,
+      401 => Self::NotAuthenticated,
+      402 => Self::AuthFailed,
+      403 => Self::AlreadyAuthenticated,
+      404 => Self::AuthTimeout,
+      405 => Self::SymbolLimitExceeded,
+      406 => Self::ConnectionLimitExceeded,
+      407 => Self::SlowClient,
+      409 => Self::InsufficientSubscription,
+      500 => Self::InternalError,
+      code => Self::Other(code),
+    }
+  }
+}
+
+
+/// An error reported by the market data stream server.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StreamError {
+  /// The error code.
+  pub code: ErrorCode,
+  /// The message accompanying the error.
+  pub message: String,
+}
+
+impl Display for StreamError {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    write!(
+      fmt,
+      "server reported error {}: {}",
+      self.code.code(),
+      self.message
+    )
+  }
+}
+
+impl StdError for StreamError {}
+
+
+/// An error affecting a single message received through the market
+/// data stream.
+#[derive(Debug)]
+pub enum MessageError {
+  /// The message could not be decoded.
+  Json(JsonError),
+  /// The server reported an error, e.g., because a subscription
+  /// exceeded the symbol limit.
+  Stream(StreamError),
+}
+
+impl Display for MessageError {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    match self {
+      Self::Json(err) => write!(fmt, "{}", err),
+      Self::Stream(err) => write!(fmt, "{}", err),
+    }
+  }
+}
+
+impl StdError for MessageError {
+  fn source(&self) -> Option<&(dyn StdError + 'static)> {
+    match self {
+      Self::Json(err) => err.source(),
+      Self::Stream(..) => None,
+    }
+  }
+}
+
+impl From<JsonError> for MessageError {
+  fn from(e: JsonError) -> Self {
+    Self::Json(e)
+  }
+}
+
+
+/// A control message sent by the server.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "T")]
+pub(crate) enum Control {
+  /// An operation succeeded.
+  #[serde(rename = "success")]
+  Success {
+    #[serde(rename = "msg")]
+    message: String,
+  },
+  /// An operation failed.
+  #[serde(rename = "error")]
+  Error {
+    #[serde(rename = "code")]
+    code: u64,
+    #[serde(rename = "msg")]
+    message: String,
+  },
+  /// The set of symbols currently subscribed to.
+  #[serde(rename = "subscription")]
+  Subscription(MarketData),
+}
+
+
+/// The part of a message we inspect before decoding it in full.
+#[derive(Debug, Deserialize)]
+pub(crate) struct Header<'a> {
+  /// The type of the message.
+  #[serde(rename = "T", borrow)]
+  pub(crate) type_: Cow<'a, str>,
+  /// The symbol the message belongs to, if any.
+  #[serde(rename = "S", borrow, default)]
+  pub(crate) symbol: Option<Cow<'a, str>>,
+}
+
+
+/// A request as sent to the server.
+#[derive(Debug, Serialize)]
+#[serde(tag = "action")]
+enum Request<'d> {
+  /// An authentication request.
+  #[serde(rename = "auth")]
+  Auth {
+    #[serde(rename = "key")]
+    key_id: &'d str,
+    #[serde(rename = "secret")]
+    secret: &'d str,
+  },
+  /// A request to subscribe to market data.
+  #[serde(rename = "subscribe")]
+  Subscribe(&'d MarketData),
+  /// A request to unsubscribe from market data.
+  #[serde(rename = "unsubscribe")]
+  Unsubscribe(&'d MarketData),
+}
+
+impl Request<'_> {
+  fn to_message(&self) -> Message {
+    let json = to_json(self).unwrap();
+    trace!(request = display(&json));
+    Message::text(json)
+  }
+}
+
+
+/// The filter deciding which messages to decode and report.
+#[derive(Debug, Default)]
+struct Filter {
+  /// The symbols subscribed to for receiving bars.
+  bars: HashSet<String>,
+  /// The symbols subscribed to for receiving quotes.
+  quotes: HashSet<String>,
+  /// The symbols subscribed to for receiving trades.
+  trades: HashSet<String>,
+  /// An additional set of symbols to restrict messages to.
+  symbols: Option<HashSet<String>>,
+}
+
+impl Filter {
+  fn add(&mut self, data: &MarketData) {
+    self.bars.extend(data.bars.iter().cloned());
+    self.quotes.extend(data.quotes.iter().cloned());
+    self.trades.extend(data.trades.iter().cloned());
+  }
+
+  fn remove(&mut self, data: &MarketData) {
+    data.bars.iter().for_each(|symbol| {
+      let _ = self.bars.remove(symbol);
+    });
+    data.quotes.iter().for_each(|symbol| {
+      let _ = self.quotes.remove(symbol);
+    });
+    data.trades.iter().for_each(|symbol| {
+      let _ = self.trades.remove(symbol);
+    });
+  }
+
+  /// Check whether a message of the given type for the given symbol
+  /// should be reported.
+  fn accepts(&self, type_: &str, symbol: &str) -> bool {
+    let subscribed = match type_ {
+      "b" => &self.bars,
+      "q" => &self.quotes,
+      "t" => &self.trades,
+      _ => return true,
+    };
+
+    let subscribed = subscribed.contains(symbol) || subscribed.contains("*");
+    let selected = self
+      .symbols
+      .as_ref()
+      .map(|symbols| symbols.contains(symbol))
+      .unwrap_or(true);
+
+    subscribed && selected
+  }
+}
+
+
+/// A handle for changing the market data subscriptions of a stream.
+///
+/// The handle can be cloned freely. All clones refer to the same
+/// stream.
+#[derive(Clone, Debug)]
+pub struct Subscriber {
+  /// The channel used for sending requests to the server.
+  control: UnboundedSender<Message>,
+  /// The filter applied to received messages.
+  filter: Arc<RwLock<Filter>>,
+}
+
+impl Subscriber {
+  #[allow(clippy::result_large_err)]
+  fn send(&self, request: Request<'_>) -> Result<(), Error> {
+    self
+      .control
+      .send(request.to_message())
+      .map_err(|_| Error::Str("market data stream is no longer active".into()))
+  }
+
+  /// Subscribe to additional market data.
+  ///
+  /// Data is reported as soon as the server acted on the request.
+  #[allow(clippy::result_large_err)]
+  pub fn subscribe(&self, data: &MarketData) -> Result<(), Error> {
+    self.filter.write().unwrap().add(data);
+    self.send(Request::Subscribe(data))
+  }
+
+  /// Unsubscribe from market data.
+  ///
+  /// Messages for the symbols in question that the server sent before
+  /// it acted on the request are dropped without decoding them.
+  #[allow(clippy::result_large_err)]
+  pub fn unsubscribe(&self, data: &MarketData) -> Result<(), Error> {
+    self.filter.write().unwrap().remove(data);
+    self.send(Request::Unsubscribe(data))
+  }
+
+  /// Restrict the reported market data to the given set of symbols, on
+  /// top of the ones subscribed to.
+  ///
+  /// Messages for other symbols are dropped before decoding them in
+  /// full. `None` removes any previously set restriction.
+  pub fn set_symbol_filter(&self, symbols: Option<HashSet<String>>) {
+    self.filter.write().unwrap().symbols = symbols;
+  }
+}
+
+
+/// A websocket connection that additionally sends the requests issued
+/// through a `Subscriber`.
+pub(crate) struct Connection<S> {
+  /// The actual websocket connection.
+  inner: S,
+  /// The channel requests are received on.
+  control: UnboundedReceiver<Message>,
+  /// A request that could not be sent yet.
+  pending: Option<Message>,
+}
+
+impl<S> Connection<S> {
+  /// Create a `Connection` sending the requests received on `control`
+  /// over `inner`.
+  pub(crate) fn new(inner: S, control: UnboundedReceiver<Message>) -> Self {
+    Self {
+      inner,
+      control,
+      pending: None,
+    }
+  }
+}
+
+impl<S> Connection<S>
+where
+  S: Sink<Message, Error = WebSocketError> + Unpin,
+{
+  /// Send all outstanding requests over the connection.
+  #[allow(clippy::result_large_err)]
+  fn poll_send(&mut self, ctx: &mut Context<'_>) -> Result<(), WebSocketError> {
+    loop {
+      if self.pending.is_none() {
+        match self.control.poll_recv(ctx) {
+          Poll::Ready(Some(message)) => self.pending = Some(message),
+          Poll::Ready(None) | Poll::Pending => break,
+        }
+      }
+
+      match Pin::new(&mut self.inner).poll_ready(ctx) {
+        Poll::Ready(Ok(())) => {
+          // We only ever get here with a pending request.
+          let message = self.pending.take().unwrap();
+          Pin::new(&mut self.inner).start_send(message)?
+        },
+        Poll::Ready(Err(err)) => return Err(err),
+        Poll::Pending => break,
+      }
+    }
+
+    match Pin::new(&mut self.inner).poll_flush(ctx) {
+      Poll::Ready(Err(err)) => Err(err),
+      Poll::Ready(Ok(())) | Poll::Pending => Ok(()),
+    }
+  }
+}
+
+impl<S> Stream for Connection<S>
+where
+  S: Sink<Message, Error = WebSocketError>,
+  S: Stream<Item = Result<Message, WebSocketError>> + Unpin,
+{
+  type Item = Result<Message, WebSocketError>;
+
+  fn poll_next(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    if let Err(err) = self.poll_send(ctx) {
+      return Poll::Ready(Some(Err(err)))
+    }
+    Pin::new(&mut self.inner).poll_next(ctx)
+  }
+}
+
+impl<S> Sink<Message> for Connection<S>
+where
+  S: Sink<Message, Error = WebSocketError> + Unpin,
+{
+  type Error = WebSocketError;
+
+  fn poll_ready(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    Pin::new(&mut self.inner).poll_ready(ctx)
+  }
+
+  fn start_send(mut self: Pin<&mut Self>, message: Message) -> Result<(), Self::Error> {
+    Pin::new(&mut self.inner).start_send(message)
+  }
+
+  fn poll_flush(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    Pin::new(&mut self.inner).poll_flush(ctx)
+  }
+
+  fn poll_close(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    Pin::new(&mut self.inner).poll_close(ctx)
+  }
+}
+
+
+/// Receive the next control message from the server.
+async fn next_control<S>(stream: &mut S) -> Result<Control, Error>
+where
+  S: Stream<Item = Result<Message, WebSocketError>> + Unpin,
+{
+  let message = stream
+    .next()
+    .await
+    .ok_or_else(|| Error::Str("connection closed unexpectedly".into()))??;
+
+  let data = match message {
+    Message::Text(text) => text.into_bytes(),
+    Message::Binary(data) => data,
+    m => {
+      let e = format!("received unexpected message: {:?}", m);
+      return Err(Error::Str(e.into()))
+    },
+  };
+  trace!(response = display(&String::from_utf8_lossy(&data)));
+
+  from_slice::<Vec<Control>>(&data)?
+    .into_iter()
+    .next()
+    .ok_or_else(|| Error::Str("received empty control message".into()))
+}
+
+
+/// Check that a control message reports success, with the given
+/// message.
+#[allow(clippy::result_large_err)]
+fn check_success(control: Control, expected: &str) -> Result<(), Error> {
+  match control {
+    Control::Success { message } if message == expected => Ok(()),
+    Control::Error { code, message } => Err(Error::DataStream(StreamError {
+      code: code.into(),
+      message,
+    })),
+    control => {
+      let e = format!("received unexpected control message: {:?}", control);
+      Err(Error::Str(e.into()))
+    },
+  }
+}
+
+
+/// Connect to, authenticate with, and subscribe to the market data
+/// stream.
+///
+/// `subscribe` is the subscription request to send, if any.
+pub(crate) async fn handshake<S>(
+  stream: &mut S,
+  key_id: &str,
+  secret: &str,
+  subscribe: Option<Message>,
+) -> Result<(), Error>
+where
+  S: Sink<Message, Error = WebSocketError>,
+  S: Stream<Item = Result<Message, WebSocketError>> + Unpin,
+{
+  check_success(next_control(stream).await?, "connected")?;
+
+  stream
+    .send(Request::Auth { key_id, secret }.to_message())
+    .await?;
+  check_success(next_control(stream).await?, "authenticated")?;
+
+  if let Some(subscribe) = subscribe {
+    stream.send(subscribe).await?;
+    match next_control(stream).await? {
+      Control::Subscription(subscription) => {
+        debug!(subscription = debug(&subscription));
+      },
+      control => check_success(control, "")?,
+    }
+  }
+  Ok(())
+}
+
+
+/// A single message of a batch, not yet decoded in full.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RawMessage<'m> {
+  /// The undecoded message.
+  #[cfg(not(feature = "simd-json"))]
+  raw: &'m RawValue,
+  /// The parsed but not yet deserialized message.
+  #[cfg(feature = "simd-json")]
+  raw: &'m BorrowedValue<'m>,
+}
+
+impl<'m> RawMessage<'m> {
+  /// Deserialize the message, or the parts of it represented by `T`.
+  pub(crate) fn decode<T>(&self) -> Result<T, JsonError>
+  where
+    T: Deserialize<'m>,
+  {
+    #[cfg(not(feature = "simd-json"))]
+    {
+      from_str(self.raw.get())
+    }
+    #[cfg(feature = "simd-json")]
+    {
+      T::deserialize(self.raw).map_err(JsonError::custom)
+    }
+  }
+}
+
+impl Display for RawMessage<'_> {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    #[cfg(not(feature = "simd-json"))]
+    {
+      fmt.write_str(self.raw.get())
+    }
+    #[cfg(feature = "simd-json")]
+    {
+      write!(fmt, "{}", self.raw)
+    }
+  }
+}
+
+
+/// A visitor decoding the messages of a batch one by one, as they are
+/// encountered.
+#[cfg(not(feature = "simd-json"))]
+struct Batch<'v, D, F> {
+  /// The function used for decoding individual messages.
+  decode: F,
+  /// The decoded messages.
+  decoded: &'v mut Vec<Result<D, MessageError>>,
+}
+
+#[cfg(not(feature = "simd-json"))]
+impl<'de, D, F> DeserializeSeed<'de> for Batch<'_, D, F>
+where
+  F: FnMut(RawMessage<'de>) -> Option<Result<D, MessageError>>,
+{
+  type Value = ();
+
+  fn deserialize<De>(self, deserializer: De) -> Result<Self::Value, De::Error>
+  where
+    De: Deserializer<'de>,
+  {
+    deserializer.deserialize_seq(self)
+  }
+}
+
+#[cfg(not(feature = "simd-json"))]
+impl<'de, D, F> Visitor<'de> for Batch<'_, D, F>
+where
+  F: FnMut(RawMessage<'de>) -> Option<Result<D, MessageError>>,
+{
+  type Value = ();
+
+  fn expecting(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    fmt.write_str("a batch of messages")
+  }
+
+  fn visit_seq<A>(mut self, mut seq: A) -> Result<Self::Value, A::Error>
+  where
+    A: SeqAccess<'de>,
+  {
+    while let Some(raw) = seq.next_element::<&'de RawValue>()? {
+      if let Some(result) = (self.decode)(RawMessage { raw }) {
+        self.decoded.push(result)
+      }
+    }
+    Ok(())
+  }
+}
+
+
+/// A decoder for batches of messages as received from the server.
+///
+/// By default, messages are decoded as they are encountered, without
+/// first collecting references to all of them. Should a batch turn out
+/// to be malformed, the messages decoded up to that point are reported
+/// followed by the error.
+///
+/// With the `simd-json` feature enabled, batches are instead parsed in
+/// full using SIMD instructions before the individual messages are
+/// decoded. Because the parser works in place, each batch is copied
+/// into a buffer that, just as the parser's internal buffers, is reused
+/// for subsequent batches. A malformed batch is reported as a single
+/// error.
+#[derive(Default)]
+pub(crate) struct BatchDecoder {
+  /// The buffer batches are copied into for parsing.
+  #[cfg(feature = "simd-json")]
+  buffer: Vec<u8>,
+  /// The parser's internal buffers.
+  #[cfg(feature = "simd-json")]
+  buffers: Buffers,
+}
+
+impl BatchDecoder {
+  /// Decode all messages contained in a batch, using the given function
+  /// for decoding each individual one.
+  #[cfg(not(feature = "simd-json"))]
+  pub(crate) fn decode<D, F>(&mut self, data: &[u8], decode: F) -> Vec<Result<D, MessageError>>
+  where
+    F: FnMut(RawMessage<'_>) -> Option<Result<D, MessageError>>,
+  {
+    let mut decoded = Vec::new();
+    let mut deserializer = JsonDeserializer::from_slice(data);
+    let batch = Batch {
+      decode,
+      decoded: &mut decoded,
+    };
+    let result = batch
+      .deserialize(&mut deserializer)
+      .and_then(|()| deserializer.end());
+    if let Err(err) = result {
+      decoded.push(Err(err.into()))
+    }
+    decoded
+  }
+
+  /// Decode all messages contained in a batch, using the given function
+  /// for decoding each individual one.
+  #[cfg(feature = "simd-json")]
+  pub(crate) fn decode<D, F>(&mut self, data: &[u8], mut decode: F) -> Vec<Result<D, MessageError>>
+  where
+    F: FnMut(RawMessage<'_>) -> Option<Result<D, MessageError>>,
+  {
+    self.buffer.clear();
+    self.buffer.extend_from_slice(data);
+
+    match to_borrowed_value_with_buffers(&mut self.buffer, &mut self.buffers) {
+      Ok(BorrowedValue::Array(messages)) => messages
+        .iter()
+        .filter_map(|raw| decode(RawMessage { raw }))
+        .collect(),
+      Ok(..) => vec![Err(MessageError::Json(JsonError::custom(
+        "expected a batch of messages",
+      )))],
+      Err(err) => vec![Err(MessageError::Json(JsonError::custom(err)))],
+    }
+  }
+}
+
+
+/// Decode a control message that is not otherwise handled, reporting
+/// errors sent by the server.
+pub(crate) fn decode_control<D>(
+  raw: RawMessage<'_>,
+  type_: &str,
+) -> Option<Result<D, MessageError>> {
+  match raw.decode::<Control>() {
+    Ok(Control::Error { code, message }) => Some(Err(MessageError::Stream(StreamError {
+      code: code.into(),
+      message,
+    }))),
+    Ok(control) => {
+      debug!(control = debug(&control));
+      None
+    },
+    Err(..) => {
+      debug!(
+        message = "ignoring unsupported message",
+        type_ = display(type_),
+        raw = display(raw)
+      );
+      None
+    },
+  }
+}
+
+
+/// Decode a single message, unless it is filtered out.
+fn decode(raw: RawMessage<'_>, filter: &Filter) -> Option<Result<Data, MessageError>> {
+  let header = match raw.decode::<Header<'_>>() {
+    Ok(header) => header,
+    Err(err) => return Some(Err(err.into())),
+  };
+
+  let type_ = header.type_.as_ref();
+  match type_ {
+    "b" | "q" | "t" => {
+      let symbol = header.symbol.as_deref().unwrap_or_default();
+      if !filter.accepts(type_, symbol) {
+        trace!(
+          message = "dropping filtered message",
+          symbol = display(symbol)
+        );
+        return None
+      }
+
+      let data = match type_ {
+        "b" => raw.decode().map(Data::Bar),
+        "q" => raw.decode().map(Data::Quote),
+        _ => raw.decode().map(Data::Trade),
+      };
+      Some(data.map_err(MessageError::from))
+    },
+    _ => decode_control(raw, type_),
+  }
+}
+
+
+/// Connect to the real time market data stream of the given source and
+/// subscribe to the provided market data.
+///
+/// If no source is provided, the feed configured as part of the
+/// `ApiInfo` (see `ApiInfo::data_feed`) is used.
+///
+/// Along with the stream a `Subscriber` is returned, which can be used
+/// for changing the subscriptions later on. Messages for symbols not
+/// subscribed to are dropped without being decoded in full.
+///
+/// Errors reported by the server while establishing the connection,
+/// e.g., because of failed authentication or because the connection
+/// limit was exceeded, are reported as `Error::DataStream`. Errors
+/// reported later on, for instance in response to a subscription
+/// exceeding the symbol limit, are yielded by the stream as
+/// `MessageError::Stream`.
+pub async fn stream(
+  api_info: &ApiInfo,
+  source: Option<Source>,
+  data: &MarketData,
+  audit: Option<Arc<AuditLog>>,
+) -> Result<
+  (
+    Subscriber,
+    impl Stream<Item = Result<Result<Data, MessageError>, WebSocketError>>,
+  ),
+  Error,
+> {
+  stream_with(api_info, source, data, audit, &SocketOptions::default()).await
+}
+
+/// Build the URL of the real time market data stream for the given
+/// source, falling back to the feed configured in `api_info`.
+#[allow(clippy::result_large_err)]
+fn stream_url(api_info: &ApiInfo, source: Option<Source>) -> Result<Url, Error> {
+  let source = source.unwrap_or(api_info.data_feed);
+  let mut url = api_info.data_stream_url.clone();
+  let _ = url
+    .path_segments_mut()
+    .map_err(|()| Error::Str("data stream URL cannot be a base".into()))?
+    .pop_if_empty()
+    // We know that we are dealing with an enum variant and the function
+    // will never return an error for those, so it's fine to unwrap.
+    .extend(&["v2", to_variant_name(&source).unwrap()]);
+  Ok(url)
+}
+
+/// Connect to the real time market data stream just like [`stream`],
+/// using the given socket options.
+async fn stream_with(
+  api_info: &ApiInfo,
+  source: Option<Source>,
+  data: &MarketData,
+  audit: Option<Arc<AuditLog>>,
+  options: &SocketOptions,
+) -> Result<
+  (
+    Subscriber,
+    impl Stream<Item = Result<Result<Data, MessageError>, WebSocketError>>,
+  ),
+  Error,
+> {
+  let url = stream_url(api_info, source)?;
+  debug!(message = "connecting", url = display(&url));
+  let (mut stream, response) = connect_socket(url, options).await?;
+  debug!("connection successful");
+  trace!(response = debug(&response));
+
+  let subscribe = if data.is_empty() {
+    None
+  } else {
+    Some(Request::Subscribe(data).to_message())
+  };
+  handshake(&mut stream, &api_info.key_id, &api_info.secret, subscribe).await?;
+  debug!("subscription successful");
+
+  let (sender, receiver) = unbounded_channel();
+  let mut filter = Filter::default();
+  filter.add(data);
+
+  let subscriber = Subscriber {
+    control: sender,
+    filter: Arc::new(RwLock::new(filter)),
+  };
+  let filter = subscriber.filter.clone();
+
+  let connection = Connection::new(stream, receiver);
+  let mut decoder = BatchDecoder::default();
+  let stream = do_stream(connection).await.flat_map(move |result| {
+    let (decoded, err) = match result {
+      Ok(data) => {
+        if let Some(audit) = &audit {
+          audit.record_event(&data);
+        }
+
+        let filter = filter.read().unwrap();
+        (decoder.decode(&data, |raw| decode(raw, &filter)), None)
+      },
+      Err(err) => (Vec::new(), Some(err)),
+    };
+    // Report the decoded messages as they are, without collecting them
+    // yet again.
+    iter(decoded.into_iter().map(Ok).chain(err.map(Err)))
+  });
+
+  Ok((subscriber, stream))
+}
+
+
+impl Client {
+  /// Subscribe to real time market data from the given source, or
+  /// the configured feed if `None`.
+  ///
+  /// See [`stream`] for details.
+  pub async fn subscribe_market_data(
+    &self,
+    source: Option<Source>,
+    data: &MarketData,
+  ) -> Result<
+    (
+      Subscriber,
+      impl Stream<Item = Result<Result<Data, MessageError>, WebSocketError>>,
+    ),
+    Error,
+  > {
+    stream_with(
+      self.api_info(),
+      source,
+      data,
+      self.audit(),
+      self.socket_options(),
+    )
+    .await
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::future::Future;
+
+  use futures::TryStreamExt;
+
+  use num_decimal::Num;
+
+  use test_env_log::test;
+
+
+  use websocket_util::test::mock_server;
+  use websocket_util::test::WebSocketStream;
+
+  use crate::api::API_BASE_URL;
+  use crate::api::API_STREAM_URL;
+  use crate::api::BROKER_BASE_URL;
+  use crate::api::DATA_BASE_URL;
+  use crate::api::DATA_STREAM_URL;
+  use crate::data::v2::Feed;
+
+
+  const CONNECTED: &str = r#"[{"T":"success","msg":"connected"}]"#;
+  const AUTH_REQ: &str = r#"{"action":"auth","key":"USER12345678","secret":"justletmein"}"#;
+  const AUTH_RESP: &str = r#"[{"T":"success","msg":"authenticated"}]"#;
+  const SUB_REQ: &str = r#"{"action":"subscribe","bars":[],"quotes":[],"trades":["SPY","AAPL"]}"#;
+  const SUB_RESP: &str = r#"[{"T":"subscription","trades":["SPY","AAPL"],"quotes":[],"bars":[]}]"#;
+  const TRADES: &str = r#"[
+  {"T":"t","S":"SPY","i":1,"x":"V","p":400.5,"s":100,"c":["@"],"z":"B","t":"2021-06-01T14:30:00.1Z"},
+  {"T":"t","S":"MSFT","i":2,"x":"V","p":250,"s":10,"c":["@"],"z":"C","t":"2021-06-01T14:30:00.2Z"},
+  {"T":"t","S":"AAPL","i":3,"x":"V","p":125.25,"s":5,"c":["@"],"z":"C","t":"2021-06-01T14:30:00.3Z"}
+]"#;
+
+
+  /// Start a mock server and connect to it.
+  async fn mock_stream<F, R>(
+    f: F,
+    data: &MarketData,
+  ) -> Result<
+    (
+      Subscriber,
+      impl Stream<Item = Result<Result<Data, MessageError>, WebSocketError>>,
+    ),
+    Error,
+  >
+  where
+    F: FnOnce(WebSocketStream) -> R + Send + Sync + 'static,
+    R: Future<Output = Result<(), WebSocketError>> + Send + Sync + 'static,
+  {
+    let addr = mock_server(f).await;
+    let api_info = ApiInfo {
+      base_url: Url::parse(API_BASE_URL).unwrap(),
+      stream_url: Url::parse(API_STREAM_URL).unwrap(),
+      data_base_url: Url::parse(DATA_BASE_URL).unwrap(),
+      data_stream_url: Url::parse(&format!("ws://{}", addr)).unwrap(),
+      data_feed: Feed::Iex,
+      broker_base_url: Url::parse(BROKER_BASE_URL).unwrap(),
+      key_id: "USER12345678".to_string(),
+      secret: "justletmein".to_string(),
+    };
+
+    stream(&api_info, Some(Source::Iex), data, None).await
+  }
+
+  /// Check that the stream URL retains any path prefix of the
+  /// configured data stream URL and falls back to the configured feed.
+  #[test]
+  fn stream_url_with_path_prefix() {
+    let api_info = ApiInfo {
+      base_url: Url::parse(API_BASE_URL).unwrap(),
+      stream_url: Url::parse(API_STREAM_URL).unwrap(),
+      data_base_url: Url::parse(DATA_BASE_URL).unwrap(),
+      data_stream_url: Url::parse("wss://proxy.example.com/alpaca/").unwrap(),
+      data_feed: Feed::Sip,
+      broker_base_url: Url::parse(BROKER_BASE_URL).unwrap(),
+      key_id: "USER12345678".to_string(),
+      secret: "justletmein".to_string(),
+    };
+
+    let url = stream_url(&api_info, None).unwrap();
+    assert_eq!(url.as_str(), "wss://proxy.example.com/alpaca/v2/sip");
+
+    let url = stream_url(&api_info, Some(Source::Iex)).unwrap();
+    assert_eq!(url.as_str(), "wss://proxy.example.com/alpaca/v2/iex");
+  }
+
+  /// Perform the server side of the handshake.
+  async fn accept(stream: &mut WebSocketStream) -> Result<(), WebSocketError> {
+    stream.send(Message::Text(CONNECTED.to_string())).await?;
+    assert_eq!(
+      stream.next().await.unwrap()?,
+      Message::Text(AUTH_REQ.to_string()),
+    );
+    stream.send(Message::Text(AUTH_RESP.to_string())).await?;
+    assert_eq!(
+      stream.next().await.unwrap()?,
+      Message::Text(SUB_REQ.to_string()),
+    );
+    stream.send(Message::Text(SUB_RESP.to_string())).await?;
+    Ok(())
+  }
+
+  fn trades() -> MarketData {
+    MarketData {
+      trades: vec!["SPY".to_string(), "AAPL".to_string()],
+      ..Default::default()
+    }
+  }
+
+
+  /// Check that we can decode the various market data messages.
+  #[test]
+  fn decode_messages() {
+    let json = r#"[
+  {"T":"b","S":"SPY","o":388.985,"h":389.13,"l":388.975,"c":389.12,"v":49378,"t":"2021-02-22T19:15:00Z"},
+  {"T":"q","S":"AMD","bx":"U","bp":87.66,"bs":1,"ax":"Q","ap":87.68,"as":4,"t":"2021-02-22T15:51:45.335689322Z","c":["R"],"z":"C"},
+  {"T":"subscription","trades":[],"quotes":["AMD"],"bars":["*"]},
+  {"T":"n","S":"SPY"}
+]"#;
+    let mut filter = Filter::default();
+    filter.add(&MarketData {
+      bars: vec!["*".to_string()],
+      quotes: vec!["AMD".to_string()],
+      ..Default::default()
+    });
+
+    let data = BatchDecoder::default()
+      .decode(json.as_bytes(), |raw| decode(raw, &filter))
+      .into_iter()
+      .collect::<Result<Vec<_>, _>>()
+      .unwrap();
+
+    assert_eq!(data.len(), 2);
+    match &data[0] {
+      Data::Bar(bar) => {
+        assert_eq!(bar.symbol, "SPY");
+        assert_eq!(bar.data.open, Num::new(388985, 1000));
+        assert_eq!(bar.data.volume, 49378);
+      },
+      data => panic!("received unexpected data: {:?}", data),
+    }
+    match &data[1] {
+      Data::Quote(quote) => {
+        assert_eq!(quote.symbol, "AMD");
+        assert_eq!(quote.data.bid_price, Num::new(8766, 100));
+        assert_eq!(quote.data.ask_size, 4);
+      },
+      data => panic!("received unexpected data: {:?}", data),
+    }
+  }
+
+  /// Check that messages preceding a malformed part of a batch are
+  /// still reported.
+  #[cfg(not(feature = "simd-json"))]
+  #[test]
+  fn decode_malformed_batch() {
+    let mut filter = Filter::default();
+    filter.add(&trades());
+
+    let json = br#"[
+  {"T":"t","S":"SPY","i":1,"x":"V","p":400.5,"s":100,"c":["@"],"z":"B","t":"2021-06-01T14:30:00.1Z"},
+  {"T":"t","S":"AAPL""#;
+    let data = BatchDecoder::default().decode(json, |raw| decode(raw, &filter));
+    assert_eq!(data.len(), 2);
+    assert_eq!(data[0].as_ref().unwrap().symbol(), "SPY");
+    assert!(matches!(data[1], Err(MessageError::Json(..))));
+
+    let data = BatchDecoder::default().decode(b"{}", |raw| decode(raw, &filter));
+    assert_eq!(data.len(), 1);
+    assert!(data[0].is_err());
+  }
+
+  /// Check that a malformed batch is reported as a single error when
+  /// parsing with simd-json, and that the decoder can be reused
+  /// afterwards.
+  #[cfg(feature = "simd-json")]
+  #[test]
+  fn decode_malformed_batch() {
+    let mut filter = Filter::default();
+    filter.add(&trades());
+
+    let mut decoder = BatchDecoder::default();
+    let json = br#"[
+  {"T":"t","S":"SPY","i":1,"x":"V","p":400.5,"s":100,"c":["@"],"z":"B","t":"2021-06-01T14:30:00.1Z"},
+  {"T":"t","S":"AAPL""#;
+    let data = decoder.decode(json, |raw| decode(raw, &filter));
+    assert_eq!(data.len(), 1);
+    assert!(matches!(data[0], Err(MessageError::Json(..))));
+
+    let data = decoder.decode(b"{}", |raw| decode(raw, &filter));
+    assert_eq!(data.len(), 1);
+    assert!(data[0].is_err());
+
+    let json = br#"[
+  {"T":"t","S":"SPY","i":1,"x":"V","p":400.5,"s":100,"c":["@"],"z":"B","t":"2021-06-01T14:30:00.1Z"}
+]"#;
+    let data = decoder.decode(json, |raw| decode(raw, &filter));
+    assert_eq!(data.len(), 1);
+    assert_eq!(data[0].as_ref().unwrap().symbol(), "SPY");
+  }
+
+  /// Check that message times are decoded with nanosecond precision.
+  #[test]
+  fn decode_nanosecond_times() {
+    let json = r#"[
+  {"T":"q","S":"AMD","bx":"U","bp":87.66,"bs":1,"ax":"Q","ap":87.68,"as":4,"t":"2021-02-22T15:51:45.335689322Z","c":["R"],"z":"C"},
+  {"T":"q","S":"AMD","bx":"U","bp":87.66,"bs":1,"ax":"Q","ap":87.68,"as":4,"t":"2021-02-22T15:51:45.335689323Z","c":["R"],"z":"C"}
+]"#;
+    let mut filter = Filter::default();
+    filter.add(&MarketData {
+      quotes: vec!["AMD".to_string()],
+      ..Default::default()
+    });
+
+    let data = BatchDecoder::default()
+      .decode(json.as_bytes(), |raw| decode(raw, &filter))
+      .into_iter()
+      .collect::<Result<Vec<_>, _>>()
+      .unwrap();
+
+    assert_eq!(data[0].timestamp_nanos(), 1614009105335689322);
+    assert_eq!(data[1].timestamp_nanos(), 1614009105335689323);
+    assert!(data[0].time() < data[1].time());
+  }
+
+  /// Check that messages are filtered based on the subscribed symbols
+  /// and the symbol filter.
+  #[test]
+  fn filter_messages() {
+    let mut filter = Filter::default();
+    filter.add(&trades());
+    assert!(filter.accepts("t", "SPY"));
+    assert!(!filter.accepts("t", "MSFT"));
+    assert!(!filter.accepts("q", "SPY"));
+
+    filter.symbols = Some(vec!["AAPL".to_string()].into_iter().collect());
+    assert!(!filter.accepts("t", "SPY"));
+    assert!(filter.accepts("t", "AAPL"));
+
+    filter.remove(&trades());
+    assert!(!filter.accepts("t", "AAPL"));
+  }
+
+  /// Check that we can subscribe to the stream and receive data for
+  /// the symbols subscribed to only.
+  #[test(tokio::test)]
+  async fn stream_trades() {
+    async fn test(mut stream: WebSocketStream) -> Result<(), WebSocketError> {
+      accept(&mut stream).await?;
+      stream.send(Message::Text(TRADES.to_string())).await?;
+      stream.send(Message::Close(None)).await?;
+      Ok(())
+    }
+
+    let (_subscriber, stream) = mock_stream(test, &trades()).await.unwrap();
+    let symbols = stream
+      .map_err(Error::from)
+      .map_ok(|data| data.unwrap().symbol().to_string())
+      .try_collect::<Vec<_>>()
+      .await
+      .unwrap();
+
+    assert_eq!(symbols, vec!["SPY".to_string(), "AAPL".to_string()]);
+  }
+
+  /// Check that unsubscribing sends the corresponding request and drops
+  /// messages for the symbols in question right away.
+  #[test(tokio::test)]
+  async fn unsubscribe_symbols() {
+    async fn test(mut stream: WebSocketStream) -> Result<(), WebSocketError> {
+      accept(&mut stream).await?;
+      assert_eq!(
+        stream.next().await.unwrap()?,
+        Message::Text(r#"{"action":"unsubscribe","bars":[],"quotes":[],"trades":["SPY"]}"#.into()),
+      );
+      // Data for the unsubscribed symbol may still be in flight.
+      stream.send(Message::Text(TRADES.to_string())).await?;
+      stream.send(Message::Close(None)).await?;
+      Ok(())
+    }
+
+    let (subscriber, stream) = mock_stream(test, &trades()).await.unwrap();
+    let unsubscribe = MarketData {
+      trades: vec!["SPY".to_string()],
+      ..Default::default()
+    };
+    subscriber.unsubscribe(&unsubscribe).unwrap();
+
+    let symbols = stream
+      .map_err(Error::from)
+      .map_ok(|data| data.unwrap().symbol().to_string())
+      .try_collect::<Vec<_>>()
+      .await
+      .unwrap();
+
+    assert_eq!(symbols, vec!["AAPL".to_string()]);
+  }
+
+  /// Check that errors reported after the connection got established
+  /// are yielded by the stream.
+  #[test(tokio::test)]
+  async fn symbol_limit_exceeded() {
+    async fn test(mut stream: WebSocketStream) -> Result<(), WebSocketError> {
+      accept(&mut stream).await?;
+      let _ = stream.next().await.unwrap()?;
+      let resp = r#"[{"T":"error","code":405,"msg":"symbol limit exceeded"}]"#;
+      stream.send(Message::Text(resp.to_string())).await?;
+      stream.send(Message::Close(None)).await?;
+      Ok(())
+    }
+
+    let (subscriber, stream) = mock_stream(test, &trades()).await.unwrap();
+    let subscribe = MarketData {
+      quotes: vec!["*".to_string()],
+      ..Default::default()
+    };
+    subscriber.subscribe(&subscribe).unwrap();
+
+    let items = stream
+      .map_err(Error::from)
+      .try_collect::<Vec<_>>()
+      .await
+      .unwrap();
+
+    assert_eq!(items.len(), 1);
+    match &items[0] {
+      Err(MessageError::Stream(err)) => {
+        assert_eq!(err.code, ErrorCode::SymbolLimitExceeded);
+        assert_eq!(err.message, "symbol limit exceeded");
+      },
+      item => panic!("received unexpected item: {:?}", item),
+    }
+  }
+
+  /// Check that we map error codes as expected.
+  #[test]
+  fn map_error_codes() {
+    assert_eq!(ErrorCode::from(402), ErrorCode::AuthFailed);
+    assert_eq!(ErrorCode::from(405), ErrorCode::SymbolLimitExceeded);
+    assert_eq!(ErrorCode::from(406), ErrorCode::ConnectionLimitExceeded);
+    assert_eq!(ErrorCode::from(999), ErrorCode::Other(999));
+
+    for code in [400, 401, 402, 403, 404, 405, 406, 407, 409, 500, 999].iter() {
+      assert_eq!(ErrorCode::from(*code).code(), *code);
+    }
+  }
+
+  /// Check that an authentication failure is reported as an error.
+  #[test(tokio::test)]
+  async fn authentication_failure() {
+    async fn test(mut stream: WebSocketStream) -> Result<(), WebSocketError> {
+      stream.send(Message::Text(CONNECTED.to_string())).await?;
+      let _ = stream.next().await.unwrap()?;
+      let resp = r#"[{"T":"error","code":402,"msg":"auth failed"}]"#;
+      stream.send(Message::Text(resp.to_string())).await?;
+      Ok(())
+    }
+
+    let result = mock_stream(test, &trades()).await;
+    match result {
+      Err(Error::DataStream(ref e)) => {
+        assert_eq!(e.code, ErrorCode::AuthFailed);
+        assert_eq!(e.message, "auth failed");
+        assert_eq!(e.to_string(), "server reported error 402: auth failed");
+      },
+      Err(e) => panic!("received unexpected error: {}", e),
+      Ok(_) => panic!("authentication succeeded unexpectedly"),
+    }
+  }
+
+  /// Check that invalid credentials are rejected by the server.
+  #[test(tokio::test)]
+  async fn stream_with_invalid_credentials() {
+    let api_info = ApiInfo {
+      base_url: Url::parse(API_BASE_URL).unwrap(),
+      stream_url: Url::parse(API_STREAM_URL).unwrap(),
+      data_base_url: Url::parse(DATA_BASE_URL).unwrap(),
+      data_stream_url: Url::parse(DATA_STREAM_URL).unwrap(),
+      data_feed: Feed::Iex,
+      broker_base_url: Url::parse(BROKER_BASE_URL).unwrap(),
+      key_id: "invalid".to_string(),
+      secret: "invalid-too".to_string(),
+    };
+
+    let client = Client::new(api_info);
+    let result = client.subscribe_market_data(Some(Source::Iex), &trades()).await;
+
+    match result {
+      Ok(_) => panic!("operation succeeded unexpectedly"),
+      Err(Error::DataStream(ref e)) if e.code == ErrorCode::AuthFailed => (),
+      Err(e) => panic!("received unexpected error: {}", e),
+    }
+  }
+}
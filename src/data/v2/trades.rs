@@ -0,0 +1,371 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::borrow::Cow;
+use std::time::SystemTime;
+
+use num_decimal::Num;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_urlencoded::to_string as to_query;
+
+use time_util::system_time_from_str;
+use time_util::system_time_to_rfc3339;
+
+use crate::data::v2::conditions::Tape;
+use crate::data::v2::conditions::TradeClass;
+use crate::data::v2::exchanges::Exchange;
+use crate::data::v2::Feed;
+use crate::data::v2::Sort;
+use crate::paged::next_page_by_token;
+use crate::paged::Paged;
+use crate::util::optional_cow_str;
+use crate::util::optional_system_time_to_date_str;
+use crate::util::vec_from_optional;
+use crate::Str;
+
+
+/// A helper for initializing `TradesReq` objects.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TradesReqInit {
+  /// See `TradesReq::limit`.
+  pub limit: Option<usize>,
+  /// See `TradesReq::page_token`.
+  pub page_token: Option<String>,
+  /// See `TradesReq::sort`.
+  pub sort: Option<Sort>,
+  /// See `TradesReq::asof`.
+  pub asof: Option<SystemTime>,
+  /// See `TradesReq::feed`.
+  pub feed: Option<Feed>,
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl TradesReqInit {
+  /// Create a `TradesReq` from a `TradesReqInit`.
+  pub fn init<S>(self, symbol: S, start: SystemTime, end: SystemTime) -> TradesReq
+  where
+    S: Into<String>,
+  {
+    TradesReq {
+      symbol: symbol.into(),
+      start,
+      end,
+      limit: self.limit,
+      page_token: self.page_token,
+      sort: self.sort,
+      asof: self.asof,
+      feed: self.feed,
+    }
+  }
+}
+
+
+/// A GET request to be issued to the /v2/stocks/<symbol>/trades
+/// endpoint.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TradesReq {
+  /// The symbol for which to retrieve trades.
+  #[serde(skip)]
+  pub symbol: String,
+  /// Filter trades equal to or after this time.
+  #[serde(rename = "start", serialize_with = "system_time_to_rfc3339")]
+  pub start: SystemTime,
+  /// Filter trades equal to or before this time.
+  #[serde(rename = "end", serialize_with = "system_time_to_rfc3339")]
+  pub end: SystemTime,
+  /// The maximum number of trades to be returned.
+  ///
+  /// It can be between 1 and 10000. Defaults to 1000 if not set.
+  #[serde(rename = "limit", skip_serializing_if = "Option::is_none")]
+  pub limit: Option<usize>,
+  /// If provided, this token is used for retrieving the next page of
+  /// data, following a previous request.
+  #[serde(rename = "page_token", skip_serializing_if = "Option::is_none")]
+  pub page_token: Option<String>,
+  /// The order in which to return trades.
+  ///
+  /// Defaults to ascending order if not set.
+  #[serde(rename = "sort", skip_serializing_if = "Option::is_none")]
+  pub sort: Option<Sort>,
+  /// The date as of which to map symbols.
+  ///
+  /// Providing a date allows for retrieving data for symbols that got
+  /// renamed or delisted since, using the symbol as it was valid on
+  /// this date. If not set, the current date is used.
+  #[serde(
+    rename = "asof",
+    serialize_with = "optional_system_time_to_date_str",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub asof: Option<SystemTime>,
+  /// The data feed to retrieve trades from.
+  ///
+  /// If not set, the feed configured in the client's `ApiInfo` is
+  /// used.
+  #[serde(rename = "feed", skip_serializing_if = "Option::is_none")]
+  pub feed: Option<Feed>,
+}
+
+
+/// A trade as returned by the /v2/stocks/<symbol>/trades endpoint.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Trade {
+  /// The time of the trade.
+  #[serde(rename = "t", deserialize_with = "system_time_from_str")]
+  pub time: SystemTime,
+  /// The exchange the trade happened on.
+  #[serde(rename = "x")]
+  pub exchange: Exchange,
+  /// The trade price.
+  #[serde(rename = "p")]
+  pub price: Num,
+  /// The trade size.
+  #[serde(rename = "s")]
+  pub size: u64,
+  /// The trade conditions.
+  #[serde(rename = "c", default, deserialize_with = "vec_from_optional")]
+  pub conditions: Vec<String>,
+  /// The trade ID, unique per exchange.
+  #[serde(rename = "i")]
+  pub id: u64,
+  /// The tape the trade was reported on.
+  #[serde(rename = "z")]
+  pub tape: Tape,
+}
+
+
+impl Trade {
+  /// Classify the trade based on its condition codes.
+  #[inline]
+  pub fn class(&self) -> TradeClass {
+    TradeClass::classify(self.tape, &self.conditions)
+  }
+}
+
+
+/// A trade that borrows its conditions from the data it got
+/// deserialized from.
+///
+/// The type is meant to be used in conjunction with
+/// `Client::issue_raw`, for cases where allocations are to be kept to
+/// a minimum.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct BorrowedTrade<'a> {
+  /// The time of the trade.
+  #[serde(rename = "t", deserialize_with = "system_time_from_str")]
+  pub time: SystemTime,
+  /// The exchange the trade happened on.
+  #[serde(rename = "x")]
+  pub exchange: Exchange,
+  /// The trade price.
+  #[serde(rename = "p")]
+  pub price: Num,
+  /// The trade size.
+  #[serde(rename = "s")]
+  pub size: u64,
+  /// The trade conditions.
+  // Condition codes are short and never contain characters needing
+  // escaping, so we can borrow them directly.
+  #[serde(rename = "c", borrow, default, deserialize_with = "vec_from_optional")]
+  pub conditions: Vec<&'a str>,
+  /// The trade ID, unique per exchange.
+  #[serde(rename = "i")]
+  pub id: u64,
+  /// The tape the trade was reported on.
+  #[serde(rename = "z")]
+  pub tape: Tape,
+}
+
+
+impl BorrowedTrade<'_> {
+  /// Classify the trade based on its condition codes.
+  #[inline]
+  pub fn class(&self) -> TradeClass {
+    TradeClass::classify(self.tape, &self.conditions)
+  }
+}
+
+
+/// A collection of trades as returned by the
+/// /v2/stocks/<symbol>/trades endpoint.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Trades {
+  /// The list of returned trades.
+  #[serde(rename = "trades", deserialize_with = "vec_from_optional")]
+  pub trades: Vec<Trade>,
+  /// The symbol the trades correspond to.
+  #[serde(rename = "symbol")]
+  pub symbol: String,
+  /// The token to provide to a request to get the next page of trades
+  /// for this request.
+  #[serde(rename = "next_page_token")]
+  pub next_page_token: Option<String>,
+}
+
+
+/// The borrowed counterpart to `Trades`.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct BorrowedTrades<'a> {
+  /// The list of returned trades.
+  #[serde(rename = "trades", borrow, deserialize_with = "vec_from_optional")]
+  pub trades: Vec<BorrowedTrade<'a>>,
+  /// The symbol the trades correspond to.
+  #[serde(rename = "symbol", borrow)]
+  pub symbol: Cow<'a, str>,
+  /// The token to provide to a request to get the next page of trades
+  /// for this request.
+  #[serde(rename = "next_page_token", borrow, deserialize_with = "optional_cow_str")]
+  pub next_page_token: Option<Cow<'a, str>>,
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v2/stocks/<symbol>/trades endpoint.
+  pub Get(TradesReq),
+  Ok => Trades, [
+    /// The trades were retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// One or more of the arguments are not well formed.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+    /// The provided symbol was not found.
+    /* 404 */ NOT_FOUND => NotFound,
+    /// One or more of the arguments are invalid.
+    /* 422 */ UNPROCESSABLE_ENTITY => InvalidArgument,
+  ]
+  Api => Data,
+  Prepare => |input, api_info| {
+    let _ = input.feed.get_or_insert(api_info.data_feed());
+  },
+
+  fn path(input: &Self::Input) -> Str {
+    format!("/v2/stocks/{}/trades", input.symbol).into()
+  }
+
+  fn query(input: &Self::Input) -> Option<Str> {
+    // TODO: Realistically there should be no way for this unwrap to
+    //       ever panic because our conversion to strings should not be
+    //       fallible. But still, ideally we would not have to unwrap.
+    Some(to_query(input).unwrap().into())
+  }
+}
+
+impl Paged for Get {
+  fn next_page(input: &Self::Input, output: &Self::Output) -> Option<Self::Input> {
+    next_page_by_token(input, output.next_page_token.as_deref(), |input| {
+      &mut input.page_token
+    })
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use http_endpoint::Endpoint;
+
+  use serde_json::from_slice;
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+  use time_util::parse_system_time_from_str;
+
+  use crate::api_info::ApiInfo;
+  use crate::Client;
+
+
+  #[test]
+  fn parse_reference_trades() {
+    let response = r#"{
+  "trades": [
+    {
+      "t": "2021-02-06T13:04:56.334320128Z",
+      "x": "C",
+      "p": 387.62,
+      "s": 100,
+      "c": [
+        " ",
+        "T"
+      ],
+      "i": 52983525029461,
+      "z": "B"
+    }
+  ],
+  "symbol": "SPY",
+  "next_page_token": null
+}"#;
+
+    let result = from_json::<<Get as Endpoint>::Output>(response).unwrap();
+    assert_eq!(result.symbol, "SPY");
+    assert_eq!(result.trades.len(), 1);
+
+    let trade = &result.trades[0];
+    assert_eq!(
+      trade.time,
+      parse_system_time_from_str("2021-02-06T13:04:56.334320128Z").unwrap()
+    );
+    assert_eq!(trade.exchange, Exchange::National);
+    assert_eq!(trade.price, Num::new(38762, 100));
+    assert_eq!(trade.size, 100);
+    assert_eq!(trade.conditions, vec![" ".to_string(), "T".to_string()]);
+    assert_eq!(trade.class(), TradeClass::Irregular);
+    assert_eq!(trade.id, 52983525029461);
+    assert_eq!(trade.tape, Tape::B);
+    assert_eq!(result.next_page_token, None);
+  }
+
+  #[test]
+  fn parse_borrowed_trades_with_escaped_token() {
+    let response = r#"{"trades":[],"symbol":"SPY","next_page_token":"U1BZfDIw\/MjE="}"#;
+    let result = from_json::<BorrowedTrades<'_>>(response).unwrap();
+    assert_eq!(result.next_page_token.as_deref(), Some("U1BZfDIw/MjE="));
+  }
+
+  #[test(tokio::test)]
+  async fn request_trades() {
+    let start = parse_system_time_from_str("2021-02-08T14:30:00Z").unwrap();
+    let end = parse_system_time_from_str("2021-02-08T14:35:00Z").unwrap();
+    let request = TradesReqInit {
+      limit: Some(5),
+      ..Default::default()
+    }
+    .init("SPY", start, end);
+
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+    let result = client.issue::<Get>(request).await.unwrap();
+
+    assert_eq!(result.symbol, "SPY");
+    assert_eq!(result.trades.len(), 5);
+    assert!(result.next_page_token.is_some());
+  }
+
+  /// Check that we can retrieve the raw response to a trades request
+  /// and deserialize it into the borrowed representation.
+  #[test(tokio::test)]
+  async fn request_borrowed_trades() {
+    let start = parse_system_time_from_str("2021-02-08T14:30:00Z").unwrap();
+    let end = parse_system_time_from_str("2021-02-08T14:35:00Z").unwrap();
+    let request = TradesReqInit {
+      limit: Some(5),
+      ..Default::default()
+    }
+    .init("SPY", start, end);
+
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+    let bytes = client.issue_raw::<Get>(request).await.unwrap();
+    let result = from_slice::<BorrowedTrades<'_>>(&bytes).unwrap();
+
+    assert!(matches!(result.symbol, Cow::Borrowed("SPY")));
+    assert_eq!(result.trades.len(), 5);
+    assert!(result.next_page_token.is_some());
+  }
+}
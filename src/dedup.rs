@@ -0,0 +1,189 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use futures::future::ready;
+use futures::stream::Stream;
+use futures::StreamExt;
+
+use serde_json::Error as JsonError;
+
+use websocket_util::tungstenite::Error as WebSocketError;
+
+use crate::api::v2::events::TradeStatus;
+use crate::api::v2::events::TradeUpdate;
+use crate::api::v2::order;
+
+
+/// The key identifying a trade update.
+type Key = (order::Id, TradeStatus, Option<String>);
+
+
+/// The trade updates seen so far.
+#[derive(Debug)]
+struct Seen {
+  /// The keys of the updates seen, for quick lookup.
+  keys: HashSet<Key>,
+  /// The keys of the updates seen, in the order they were seen in.
+  order: VecDeque<Key>,
+}
+
+
+/// A filter for trade updates that were reported before.
+///
+/// The recommended way of dealing with a broken trade updates stream
+/// is to reconnect and to backfill missed events by querying orders.
+/// Doing so can cause the same event to be delivered more than once.
+/// A `Deduplicator` identifies trade updates by their order ID, event,
+/// and execution ID and drops those it has seen already. A single
+/// instance can be used across reconnects.
+///
+/// Only the most recent `capacity` updates are remembered, so that
+/// memory use stays bounded.
+#[derive(Debug)]
+pub struct Deduplicator {
+  /// The maximum number of updates to remember.
+  capacity: usize,
+  /// The updates seen so far.
+  seen: Mutex<Seen>,
+}
+
+impl Deduplicator {
+  /// Create a new `Deduplicator` remembering at most `capacity` trade
+  /// updates.
+  pub fn new(capacity: usize) -> Self {
+    Self {
+      capacity,
+      seen: Mutex::new(Seen {
+        keys: HashSet::with_capacity(capacity),
+        order: VecDeque::with_capacity(capacity),
+      }),
+    }
+  }
+
+  /// Check whether the given trade update was seen before, remembering
+  /// it if it was not.
+  pub fn is_duplicate(&self, update: &TradeUpdate) -> bool {
+    let key = (update.order.id, update.event, update.execution_id.clone());
+    let mut seen = self.seen.lock().unwrap();
+
+    if seen.keys.contains(&key) {
+      return true
+    }
+
+    if self.capacity == 0 {
+      return false
+    }
+
+    if seen.order.len() >= self.capacity {
+      if let Some(oldest) = seen.order.pop_front() {
+        let _ = seen.keys.remove(&oldest);
+      }
+    }
+
+    let _ = seen.keys.insert(key.clone());
+    seen.order.push_back(key);
+    false
+  }
+
+  /// Filter a stream of trade updates, as retrieved via
+  /// `Client::subscribe`, dropping all updates seen before.
+  ///
+  /// Errors reported by the stream are passed through.
+  pub fn dedup<'d, S>(
+    &'d self,
+    updates: S,
+  ) -> impl Stream<Item = Result<Result<TradeUpdate, JsonError>, WebSocketError>> + 'd
+  where
+    S: Stream<Item = Result<Result<TradeUpdate, JsonError>, WebSocketError>> + 'd,
+  {
+    updates.filter(move |result| {
+      let keep = match result {
+        Ok(Ok(update)) => !self.is_duplicate(update),
+        Ok(Err(..)) | Err(..) => true,
+      };
+      ready(keep)
+    })
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use futures::stream::iter;
+
+  use num_decimal::Num;
+
+  use test_env_log::test;
+
+  use crate::test_support::filled_order;
+  use crate::test_support::order;
+  use crate::test_support::trade_update;
+
+
+  /// Create a fill event with the given execution ID.
+  fn fill(order: &order::Order, execution_id: &str) -> TradeUpdate {
+    TradeUpdate {
+      execution_id: Some(execution_id.to_string()),
+      ..trade_update(TradeStatus::PartialFill, order.clone())
+    }
+  }
+
+
+  #[test]
+  fn detect_duplicates() {
+    let dedup = Deduplicator::new(16);
+    let order = order("AAPL", order::Side::Buy, 10);
+    let new = trade_update(TradeStatus::New, order.clone());
+
+    assert!(!dedup.is_duplicate(&new));
+    assert!(dedup.is_duplicate(&new));
+    assert!(!dedup.is_duplicate(&fill(&order, "1")));
+    assert!(!dedup.is_duplicate(&fill(&order, "2")));
+    assert!(dedup.is_duplicate(&fill(&order, "1")));
+
+    let other = filled_order("AAPL", order::Side::Buy, 10, Num::from(100));
+    assert!(!dedup.is_duplicate(&trade_update(TradeStatus::New, other)));
+  }
+
+  #[test]
+  fn forget_oldest_updates() {
+    let dedup = Deduplicator::new(2);
+    let order = order("SPY", order::Side::Sell, 3);
+
+    assert!(!dedup.is_duplicate(&fill(&order, "1")));
+    assert!(!dedup.is_duplicate(&fill(&order, "2")));
+    assert!(!dedup.is_duplicate(&fill(&order, "3")));
+    // The first update got evicted.
+    assert!(!dedup.is_duplicate(&fill(&order, "1")));
+    assert!(dedup.is_duplicate(&fill(&order, "3")));
+  }
+
+  #[test(tokio::test)]
+  async fn dedup_across_streams() {
+    let dedup = Deduplicator::new(16);
+    let order = order("AAPL", order::Side::Buy, 10);
+    let first = vec![
+      Ok(Ok(trade_update(TradeStatus::New, order.clone()))),
+      Ok(Ok(fill(&order, "1"))),
+    ];
+    // After a reconnect we may see some of the same updates again.
+    let second = vec![Ok(Ok(fill(&order, "1"))), Ok(Ok(fill(&order, "2")))];
+
+    let count = dedup.dedup(iter(first)).count().await;
+    assert_eq!(count, 2);
+
+    let updates = dedup
+      .dedup(iter(second))
+      .map(|result| result.unwrap().unwrap())
+      .collect::<Vec<_>>()
+      .await;
+    assert_eq!(updates.len(), 1);
+    assert_eq!(updates[0].execution_id, Some("2".to_string()));
+  }
+}
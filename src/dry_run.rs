@@ -0,0 +1,282 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use http::Method;
+use http::StatusCode;
+
+use num_decimal::Num;
+
+use serde::Deserialize;
+use serde_json::json;
+use serde_json::to_vec as to_json;
+use serde_json::Value;
+
+use tracing::info;
+
+use uuid::Uuid;
+
+use crate::api::v2::order;
+use crate::util::system_time_to_rfc3339_string;
+
+
+/// A counter making the IDs of synthetic orders unique within the
+/// process.
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+
+/// The subset of an order submission that we validate.
+#[derive(Debug, Deserialize)]
+struct Submission {
+  #[serde(rename = "symbol")]
+  symbol: String,
+  #[serde(rename = "qty")]
+  quantity: String,
+  #[serde(rename = "side")]
+  side: order::Side,
+  #[serde(rename = "order_class")]
+  class: order::Class,
+  #[serde(rename = "type")]
+  type_: order::Type,
+  #[serde(rename = "time_in_force")]
+  time_in_force: order::TimeInForce,
+  #[serde(rename = "limit_price")]
+  limit_price: Option<Num>,
+  #[serde(rename = "stop_price")]
+  stop_price: Option<Num>,
+  #[serde(rename = "take_profit")]
+  take_profit: Option<Value>,
+  #[serde(rename = "stop_loss")]
+  stop_loss: Option<Value>,
+  #[serde(rename = "extended_hours")]
+  extended_hours: bool,
+  #[serde(rename = "client_order_id")]
+  client_order_id: Option<String>,
+}
+
+impl Submission {
+  /// Check the submission for the mistakes the API would reject it
+  /// for, returning a description of the first one found.
+  fn validate(&self) -> Result<u64, &'static str> {
+    if self.symbol.is_empty() {
+      return Err("symbol is required")
+    }
+    let quantity = match self.quantity.parse::<u64>() {
+      Ok(quantity) if quantity > 0 => quantity,
+      _ => return Err("qty must be a positive integer"),
+    };
+
+    let limit = matches!(self.type_, order::Type::Limit | order::Type::StopLimit);
+    let stop = matches!(self.type_, order::Type::Stop | order::Type::StopLimit);
+    match &self.limit_price {
+      Some(_) if !limit => return Err("limit_price is only allowed for limit orders"),
+      Some(price) if !price.is_positive() => return Err("limit_price must be positive"),
+      None if limit => return Err("limit_price is required for limit orders"),
+      _ => (),
+    }
+    match &self.stop_price {
+      Some(_) if !stop => return Err("stop_price is only allowed for stop orders"),
+      Some(price) if !price.is_positive() => return Err("stop_price must be positive"),
+      None if stop => return Err("stop_price is required for stop orders"),
+      _ => (),
+    }
+
+    let legs = (self.take_profit.is_some(), self.stop_loss.is_some());
+    let valid = match self.class {
+      order::Class::Simple => legs == (false, false),
+      order::Class::Bracket | order::Class::OneCancelsOther => legs == (true, true),
+      order::Class::OneTriggersOther => legs.0 != legs.1,
+    };
+    if !valid {
+      return Err("take_profit and stop_loss do not match the order_class")
+    }
+
+    if self.extended_hours
+      && (self.type_ != order::Type::Limit || self.time_in_force != order::TimeInForce::Day)
+    {
+      return Err("extended hours orders must be DAY limit orders")
+    }
+    Ok(quantity)
+  }
+}
+
+
+/// Create an error response in the format used by Alpaca.
+fn error(status: StatusCode, message: &str) -> (StatusCode, Vec<u8>) {
+  let code = u64::from(status.as_u16()) * 100_000;
+  let body = json!({"code": code, "message": message});
+  // Serialization of a `Value` cannot fail.
+  (status, to_json(&body).unwrap())
+}
+
+
+/// Create a new unique ID for a synthetic order.
+fn next_id() -> Uuid {
+  // It is fine to unwrap here because no time relevant to us can ever
+  // be before `UNIX_EPOCH`.
+  let secs = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap()
+    .as_secs();
+  let count = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+  Uuid::from_u128(u128::from(secs) << 64 | u128::from(count))
+}
+
+
+/// Check whether a request to the given path using the given method
+/// changes orders or positions and, hence, must not be sent in dry-run
+/// mode.
+pub(crate) fn intercepts(method: &Method, path: &str) -> bool {
+  let path = path.trim_matches('/');
+  let mutating = *method == Method::POST || *method == Method::PATCH || *method == Method::DELETE;
+  mutating
+    && (path == "v2/orders"
+      || path.starts_with("v2/orders/")
+      || path == "v2/positions"
+      || path.starts_with("v2/positions/"))
+}
+
+
+/// Validate an intercepted request and create the response the API
+/// would be expected to send.
+pub(crate) fn handle(method: &Method, path: &str, body: &[u8]) -> (StatusCode, Vec<u8>) {
+  let segments = path
+    .trim_matches('/')
+    .split('/')
+    .filter(|segment| !segment.is_empty())
+    .collect::<Vec<_>>();
+
+  match (method, segments.as_slice()) {
+    (&Method::POST, ["v2", "orders"]) => submit(body),
+    (&Method::DELETE, ["v2", "orders"]) => {
+      info!("dry-run: not canceling all orders");
+      (StatusCode::MULTI_STATUS, b"[]".to_vec())
+    },
+    (&Method::DELETE, ["v2", "orders", id]) => {
+      info!(id = display(id), "dry-run: not canceling order");
+      (StatusCode::NO_CONTENT, Vec::new())
+    },
+    (&Method::PATCH, ["v2", "orders", id]) => {
+      info!(id = display(id), "dry-run: refusing to replace order");
+      error(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "order replacement is not supported in dry-run mode",
+      )
+    },
+    _ => {
+      info!(method = display(method), path, "dry-run: refusing request");
+      error(
+        StatusCode::FORBIDDEN,
+        "request is not supported in dry-run mode",
+      )
+    },
+  }
+}
+
+
+/// Validate an order submission and create a synthetic accepted order
+/// for it.
+fn submit(body: &[u8]) -> (StatusCode, Vec<u8>) {
+  let submission = match serde_json::from_slice::<Submission>(body) {
+    Ok(submission) => submission,
+    Err(err) => return error(StatusCode::UNPROCESSABLE_ENTITY, &err.to_string()),
+  };
+  let quantity = match submission.validate() {
+    Ok(quantity) => quantity,
+    Err(message) => {
+      info!(
+        symbol = display(&submission.symbol),
+        message, "dry-run: rejecting order"
+      );
+      return error(StatusCode::UNPROCESSABLE_ENTITY, message)
+    },
+  };
+
+  let id = next_id().to_hyphenated().to_string();
+  info!(
+    id = display(&id),
+    symbol = display(&submission.symbol),
+    side = debug(submission.side),
+    qty = quantity,
+    type_ = debug(submission.type_),
+    "dry-run: accepting order"
+  );
+
+  let num = |num: &Option<Num>| num.as_ref().map(ToString::to_string);
+  let now = system_time_to_rfc3339_string(&SystemTime::now());
+  let json = json!({
+    "id": id,
+    "client_order_id": submission.client_order_id.unwrap_or_else(|| id.clone()),
+    "status": "accepted",
+    "created_at": now,
+    "updated_at": now,
+    "submitted_at": now,
+    "filled_at": null,
+    "expired_at": null,
+    "canceled_at": null,
+    "asset_class": "us_equity",
+    "asset_id": Uuid::nil().to_hyphenated().to_string(),
+    "symbol": submission.symbol,
+    "qty": quantity.to_string(),
+    "filled_qty": "0",
+    "type": submission.type_,
+    "side": submission.side,
+    "time_in_force": submission.time_in_force,
+    "limit_price": num(&submission.limit_price),
+    "stop_price": num(&submission.stop_price),
+    "filled_avg_price": null,
+    "extended_hours": submission.extended_hours,
+    "legs": null,
+  });
+  // Serialization of a `Value` cannot fail.
+  (StatusCode::OK, to_json(&json).unwrap())
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::api::v2::order::Order;
+  use crate::api::v2::order::Status;
+
+  use test_env_log::test;
+
+
+  /// Check that we only intercept requests changing orders or
+  /// positions.
+  #[test]
+  fn intercept_mutating_requests() {
+    assert!(intercepts(&Method::POST, "/v2/orders"));
+    assert!(intercepts(&Method::DELETE, "/v2/orders"));
+    assert!(intercepts(&Method::PATCH, "/v2/orders/1234"));
+    assert!(intercepts(&Method::DELETE, "/v2/positions/AAPL"));
+    assert!(!intercepts(&Method::GET, "/v2/orders"));
+    assert!(!intercepts(&Method::GET, "/v2/positions"));
+    assert!(!intercepts(&Method::POST, "/v2/watchlists"));
+    assert!(!intercepts(&Method::DELETE, "/v2/ordersfoo"));
+  }
+
+  /// Check that we accept a valid submission and reject invalid ones.
+  #[test]
+  fn validate_submission() {
+    let body = br#"{"symbol":"SPY","qty":"5","side":"buy","order_class":"simple","type":"limit","time_in_force":"day","limit_price":"300","stop_price":null,"take_profit":null,"stop_loss":null,"extended_hours":false,"client_order_id":"my-order"}"#;
+    let (status, body) = submit(body);
+    assert_eq!(status, StatusCode::OK);
+    let order = serde_json::from_slice::<Order>(&body).unwrap();
+    assert_eq!(order.status, Status::Accepted);
+    assert_eq!(order.symbol, "SPY");
+    assert_eq!(order.client_order_id, "my-order");
+    assert_eq!(order.limit_price, Some(Num::from(300)));
+
+    let body = br#"{"symbol":"SPY","qty":"5","side":"buy","order_class":"simple","type":"stop_limit","time_in_force":"day","limit_price":"300","stop_price":null,"take_profit":null,"stop_loss":null,"extended_hours":false,"client_order_id":null}"#;
+    let (status, body) = submit(body);
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let error = serde_json::from_slice::<Value>(&body).unwrap();
+    assert_eq!(error["message"], "stop_price is required for stop orders");
+  }
+}
@@ -5,9 +5,15 @@ use std::fmt::Display;
 use std::fmt::Formatter;
 use std::fmt::Result as FmtResult;
 
+use http_endpoint::Endpoint;
+
 use serde::Deserialize;
 
+use crate::ApiInfo;
+
 
+/// An error message as reported by the API alongside an unsuccessful
+/// HTTP status.
 #[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct ErrorMessage {
   /// An error code as provided by Alpaca.
@@ -25,15 +31,96 @@ impl Display for ErrorMessage {
 }
 
 
+/// The family of APIs an endpoint belongs to.
+///
+/// The family determines which of the base URLs configured as part of
+/// the `ApiInfo` in use requests to the endpoint are sent to.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Api {
+  /// The trading API, using `ApiInfo::base_url`.
+  ///
+  /// An endpoint of this family may provide a different base URL by
+  /// means of `Endpoint::base_url`, which then takes precedence.
+  Trading,
+  /// The market data API, using `ApiInfo::data_base_url`.
+  Data,
+  /// The Broker API, using `ApiInfo::broker_base_url`.
+  Broker,
+}
+
+
+/// An `Endpoint` belonging to one of the APIs provided by Alpaca.
+///
+/// The trait is typically not implemented manually, but by means of
+/// the `Endpoint!` macro.
+pub trait ApiEndpoint: Endpoint {
+  /// Retrieve the family of APIs the endpoint belongs to.
+  fn api() -> Api;
+
+  /// Prepare the input to a request before it is issued, e.g., by
+  /// filling in defaults configured as part of the `ApiInfo` in use.
+  fn prepare(input: &mut Self::Input, api_info: &ApiInfo) {
+    let _ = (input, api_info);
+  }
+}
+
+
 /// A macro used for defining the properties for a request to a
 /// particular HTTP endpoint.
+///
+/// The macro defines a type implementing the `Endpoint` and
+/// `ApiEndpoint` traits along with an error type for it, which can
+/// then be used with `Client::issue` and its relatives. Errors for HTTP
+/// statuses 401 and 429 are added to every endpoint and API reported
+/// errors are parsed into an `ErrorMessage`. That makes it possible to
+/// define endpoints not (yet) covered by this crate outside of it. By
+/// default, endpoints belong to the trading API. An `Api => <family>,`
+/// clause following the error definitions selects a different `Api`.
+/// It may be followed by a `Prepare => <function>,` clause providing
+/// an implementation of `ApiEndpoint::prepare`.
+/// Note that the expansion refers to the `http`, `http_endpoint`, and
+/// `serde_json` crates, which hence have to be dependencies of the
+/// crate using the macro.
+///
+/// ```
+/// use std::borrow::Cow;
+///
+/// use apca::Endpoint;
+///
+/// #[derive(Debug, serde::Deserialize)]
+/// pub struct Clock {
+///   pub is_open: bool,
+/// }
+///
+/// Endpoint! {
+///   /// A GET request to the /v2/clock endpoint.
+///   pub GetClock(()),
+///   Ok => Clock, [
+///     /// The clock was retrieved successfully.
+///     /* 200 */ OK,
+///   ],
+///   Err => GetClockError, []
+///
+///   fn path(_input: &Self::Input) -> Cow<'static, str> {
+///     "/v2/clock".into()
+///   }
+/// }
+///
+/// # #[allow(unused)]
+/// async fn is_open(client: &apca::Client) -> bool {
+///   client.issue::<GetClock>(()).await.unwrap().is_open
+/// }
+/// ```
+#[macro_export]
 macro_rules! Endpoint {
   ( $(#[$docs:meta])* $pub:vis $name:ident($in:ty),
     Ok => $out:ty, [$($(#[$ok_docs:meta])* $ok_status:ident,)*],
     Err => $err:ident, [$($(#[$err_docs:meta])* $err_status:ident => $variant:ident,)*]
+    Api => $api:ident,
+    Prepare => $prepare:expr,
     $($defs:tt)* ) => {
 
-    EndpointDef! {
+    $crate::EndpointDef! {
       $(#[$docs])* $pub $name($in),
       Ok => $out, [$($ok_status,)*],
       Err => $err, [
@@ -47,7 +134,46 @@ macro_rules! Endpoint {
         /* 429 */ TOO_MANY_REQUESTS => RateLimitExceeded,
         $($(#[$err_docs])* $err_status => $variant,)*
       ],
-      ApiErr => crate::endpoint::ErrorMessage,
+      ApiErr => $crate::ErrorMessage,
+      $($defs)*
+    }
+
+    impl $crate::ApiEndpoint for $name {
+      fn api() -> $crate::Api {
+        $crate::Api::$api
+      }
+
+      fn prepare(input: &mut Self::Input, api_info: &$crate::ApiInfo) {
+        let prepare: fn(&mut Self::Input, &$crate::ApiInfo) = $prepare;
+        prepare(input, api_info)
+      }
+    }
+  };
+  ( $(#[$docs:meta])* $pub:vis $name:ident($in:ty),
+    Ok => $out:ty, [$($(#[$ok_docs:meta])* $ok_status:ident,)*],
+    Err => $err:ident, [$($(#[$err_docs:meta])* $err_status:ident => $variant:ident,)*]
+    Api => $api:ident,
+    $($defs:tt)* ) => {
+
+    $crate::Endpoint! {
+      $(#[$docs])* $pub $name($in),
+      Ok => $out, [$($(#[$ok_docs])* $ok_status,)*],
+      Err => $err, [$($(#[$err_docs])* $err_status => $variant,)*]
+      Api => $api,
+      Prepare => |_, _| (),
+      $($defs)*
+    }
+  };
+  ( $(#[$docs:meta])* $pub:vis $name:ident($in:ty),
+    Ok => $out:ty, [$($(#[$ok_docs:meta])* $ok_status:ident,)*],
+    Err => $err:ident, [$($(#[$err_docs:meta])* $err_status:ident => $variant:ident,)*]
+    $($defs:tt)* ) => {
+
+    $crate::Endpoint! {
+      $(#[$docs])* $pub $name($in),
+      Ok => $out, [$($(#[$ok_docs])* $ok_status,)*],
+      Err => $err, [$($(#[$err_docs])* $err_status => $variant,)*]
+      Api => Trading,
       $($defs)*
     }
   };
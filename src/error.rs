@@ -14,8 +14,11 @@ use http_endpoint::Error as EndpointError;
 use hyper::Error as HyperError;
 use serde_json::Error as JsonError;
 use url::ParseError;
+#[cfg(feature = "streaming")]
 use websocket_util::tungstenite::Error as WebSocketError;
 
+#[cfg(all(feature = "data", feature = "streaming"))]
+use crate::data::v2::stream::StreamError;
 use crate::Str;
 
 
@@ -67,6 +70,9 @@ pub enum Error {
   /// We encountered an HTTP that either represents a failure or is not
   /// supported.
   HttpStatus(HttpStatusCode, Vec<u8>),
+  /// An error reported by the real time market data stream.
+  #[cfg(all(feature = "data", feature = "streaming"))]
+  DataStream(StreamError),
   /// A JSON conversion error.
   Json(JsonError),
   /// An error directly originating in this module.
@@ -74,6 +80,7 @@ pub enum Error {
   /// An URL parsing error.
   Url(ParseError),
   /// A websocket error.
+  #[cfg(feature = "streaming")]
   WebSocket(WebSocketError),
 }
 
@@ -83,15 +90,18 @@ impl Display for Error {
       Error::Http(err) => write!(fmt, "{}", err),
       Error::HttpStatus(status, data) => {
         write!(fmt, "Received HTTP status: {}: ", status)?;
-        match from_utf8(&data) {
+        match from_utf8(data) {
           Ok(s) => fmt.write_str(s)?,
           Err(b) => write!(fmt, "{:?}", b)?,
         }
         Ok(())
       },
+      #[cfg(all(feature = "data", feature = "streaming"))]
+      Error::DataStream(err) => write!(fmt, "{}", err),
       Error::Json(err) => write!(fmt, "{}", err),
       Error::Str(err) => fmt.write_str(err),
       Error::Url(err) => write!(fmt, "{}", err),
+      #[cfg(feature = "streaming")]
       Error::WebSocket(err) => write!(fmt, "{}", err),
     }
   }
@@ -102,9 +112,12 @@ impl StdError for Error {
     match self {
       Error::Http(err) => err.source(),
       Error::HttpStatus(..) => None,
+      #[cfg(all(feature = "data", feature = "streaming"))]
+      Error::DataStream(..) => None,
       Error::Json(err) => err.source(),
       Error::Str(..) => None,
       Error::Url(err) => err.source(),
+      #[cfg(feature = "streaming")]
       Error::WebSocket(err) => err.source(),
     }
   }
@@ -138,6 +151,7 @@ impl From<ParseError> for Error {
   }
 }
 
+#[cfg(feature = "streaming")]
 impl From<WebSocketError> for Error {
   fn from(e: WebSocketError) -> Self {
     Error::WebSocket(e)
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use std::str::from_utf8;
+use std::time::SystemTime;
 
 use futures::Sink;
 use futures::SinkExt;
@@ -50,6 +51,21 @@ impl From<&[StreamType]> for Streams {
 }
 
 
+/// The confirmation of an event stream subscription, as reported by
+/// the server during the initial handshake.
+///
+/// Once it has been received the server is guaranteed to report all
+/// subsequent events on the subscribed streams, e.g., updates for
+/// orders submitted from this point on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Subscription {
+  /// The streams the server confirmed to be listening on.
+  pub streams: Vec<StreamType>,
+  /// The local time at which the confirmation was received.
+  pub confirmed_at: SystemTime,
+}
+
+
 /// Definitions for requests in the initial handshake.
 mod req {
   use super::*;
@@ -187,6 +203,7 @@ where
 
 
 /// Check the response to an authentication request.
+#[allow(clippy::result_large_err)]
 fn check_auth(msg: &[u8]) -> Result<(), Error> {
   match from_utf8(msg) {
     Ok(s) => trace!(response = display(&s)),
@@ -227,16 +244,18 @@ where
 }
 
 
-/// Check the response to a stream subscription request.
-fn check_subscribe(msg: &[u8], stream: StreamType) -> Result<(), Error> {
+/// Check the response to a stream subscription request, returning the
+/// streams the server confirmed.
+#[allow(clippy::result_large_err)]
+fn check_subscribe(msg: &[u8], stream: StreamType) -> Result<Vec<StreamType>, Error> {
   match from_utf8(msg) {
     Ok(s) => trace!(response = display(&s)),
     Err(b) => trace!(response = display(&b)),
   }
 
-  match from_json::<StreamResponse>(&msg) {
+  match from_json::<StreamResponse>(msg) {
     Ok(resp) => match &resp.data.0.streams[..] {
-      &[s] if s == stream => Ok(()),
+      &[s] if s == stream => Ok(resp.data.0.streams),
       &[] => {
         let e = format!("failed to subscribe to stream {:?}", stream);
         Err(Error::Str(e.into()))
@@ -260,9 +279,10 @@ fn check_subscribe(msg: &[u8], stream: StreamType) -> Result<(), Error> {
 }
 
 
-fn handle_only_data_msg<F>(msg: Message, f: F) -> Result<(), Error>
+#[allow(clippy::result_large_err)]
+fn handle_only_data_msg<F, T>(msg: Message, f: F) -> Result<T, Error>
 where
-  F: FnOnce(&[u8]) -> Result<(), Error>,
+  F: FnOnce(&[u8]) -> Result<T, Error>,
 {
   match msg {
     Message::Text(text) => f(text.as_bytes()),
@@ -294,7 +314,8 @@ where
 
 
 #[instrument(level = "trace", skip(stream, stream_type))]
-async fn subscribe<S>(stream: &mut S, stream_type: StreamType) -> Result<(), Error>
+#[allow(clippy::result_large_err)]
+async fn subscribe<S>(stream: &mut S, stream_type: StreamType) -> Result<Vec<StreamType>, Error>
 where
   S: Sink<Message, Error = WebSocketError>,
   S: Stream<Item = Result<Message, WebSocketError>> + Unpin,
@@ -306,8 +327,7 @@ where
     .ok_or_else(|| Error::Str("no response to subscription request".into()))?;
   let msg = result?;
 
-  handle_only_data_msg(msg, |dat| check_subscribe(dat, stream_type))?;
-  Ok(())
+  handle_only_data_msg(msg, |dat| check_subscribe(dat, stream_type))
 }
 
 
@@ -317,7 +337,7 @@ pub async fn handshake<S>(
   key_id: &str,
   secret: &str,
   stream_type: StreamType,
-) -> Result<(), Error>
+) -> Result<Subscription, Error>
 where
   S: Sink<Message, Error = WebSocketError>,
   S: Stream<Item = Result<Message, WebSocketError>> + Unpin,
@@ -328,8 +348,11 @@ where
   // - if we use `in_scope` the output somehow lacks the span's name
   // Because of that we use dedicated, `instrument`ed, functions.
   authenticate(stream, key_id, secret).await?;
-  subscribe(stream, stream_type).await?;
-  Ok(())
+  let streams = subscribe(stream, stream_type).await?;
+  Ok(Subscription {
+    streams,
+    confirmed_at: SystemTime::now(),
+  })
 }
 
 
@@ -394,4 +417,14 @@ mod tests {
     assert_eq!(resp.op, resp::Operation::Listening);
     assert_eq!(resp.data.0.streams, vec![StreamType::TradeUpdates]);
   }
+
+  #[test]
+  fn check_stream_response() {
+    let json = br#"{"stream":"listening","data":{"streams":["trade_updates"]}}"#;
+    let streams = check_subscribe(json, StreamType::TradeUpdates).unwrap();
+    assert_eq!(streams, vec![StreamType::TradeUpdates]);
+
+    let err = check_subscribe(json, StreamType::AccountUpdates).unwrap_err();
+    assert!(err.to_string().contains("unexpected stream"), "{}", err);
+  }
 }
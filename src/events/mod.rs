@@ -5,7 +5,13 @@ mod handshake;
 mod stream;
 
 pub use handshake::StreamType;
+pub use handshake::Subscription;
 pub use stream::Event;
 pub use stream::EventStream;
+pub use stream::RawEvent;
 pub use stream::stream;
+pub use stream::stream_confirmed;
 pub use stream::stream_raw;
+pub use stream::stream_raw_confirmed;
+pub(crate) use stream::stream_confirmed_with;
+pub(crate) use stream::stream_raw_confirmed_with;
@@ -1,6 +1,9 @@
 // Copyright (C) 2019-2021 Daniel Mueller <deso@posteo.net>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use std::borrow::Cow;
+use std::sync::Arc;
+
 use futures::stream::Stream;
 use futures::StreamExt;
 
@@ -15,15 +18,17 @@ use tracing::trace;
 use tracing::Level;
 use tracing_futures::Instrument;
 
-use tungstenite::connect_async;
-
 use websocket_util::stream as do_stream;
 use websocket_util::tungstenite::Error as WebSocketError;
 
 use crate::api_info::ApiInfo;
+use crate::audit::AuditLog;
 use crate::Error;
 use crate::events::handshake::handshake;
 use crate::events::handshake::StreamType;
+use crate::events::handshake::Subscription;
+use crate::socket::connect as connect_socket;
+use crate::socket::SocketOptions;
 
 
 /// A trait representing a particular event stream.
@@ -48,30 +53,44 @@ pub struct Event<T> {
 }
 
 
-/// Create a stream for the raw event data.
+/// An event along with the raw data it was decoded from.
+///
+/// Raw events allow for diagnosing decoding issues and for logging
+/// messages, e.g., of types not known to this crate, that would
+/// otherwise only surface as a decoding error.
+#[derive(Debug)]
+pub struct RawEvent<T> {
+  /// The raw data as received from the server.
+  pub raw: Vec<u8>,
+  /// The decoded event or the error decoding it resulted in.
+  pub event: Result<T, JsonError>,
+}
+
+impl<T> RawEvent<T> {
+  /// Retrieve the raw data as a string, with invalid UTF-8 sequences
+  /// replaced.
+  pub fn raw_str(&self) -> Cow<'_, str> {
+    String::from_utf8_lossy(&self.raw)
+  }
+}
+
+
+/// Connect to and subscribe to the given event stream, reporting the
+/// server's confirmation along with a stream for the raw event data.
 #[allow(clippy::cognitive_complexity)]
-pub async fn stream_raw(
+async fn connect(
   api_info: &ApiInfo,
   stream_type: StreamType,
-) -> Result<impl Stream<Item = Result<Vec<u8>, WebSocketError>>, Error> {
+  options: &SocketOptions,
+) -> Result<(Subscription, impl Stream<Item = Result<Vec<u8>, WebSocketError>>), Error> {
   let ApiInfo {
-    base_url: url,
+    stream_url: url,
     key_id,
     secret,
+    ..
   } = api_info;
 
-  let mut url = url.clone();
-  // TODO: We really shouldn't need this conditional logic. Find a
-  //       better way.
-  match url.scheme() {
-    "ws" | "wss" => (),
-    _ => {
-      url.set_scheme("wss").map_err(|()| {
-        Error::Str(format!("unable to change URL scheme for {}: invalid URL?", url).into())
-      })?;
-    },
-  }
-  url.set_path("stream");
+  let url = url.clone();
 
   let span = span!(Level::DEBUG, "stream", events = debug(&stream_type));
 
@@ -81,39 +100,142 @@ pub async fn stream_raw(
     // We just ignore the response & headers that are sent along after
     // the connection is made. Alpaca does not seem to be using them,
     // really.
-    let (mut stream, response) = connect_async(url).await?;
+    let (mut stream, response) = connect_socket(url, options).await?;
     debug!("connection successful");
     trace!(response = debug(&response));
 
-    handshake(&mut stream, key_id, secret, stream_type).await?;
+    let subscription = handshake(&mut stream, key_id, secret, stream_type).await?;
     debug!("subscription successful");
 
-    Ok(do_stream(stream).await)
+    Ok((subscription, do_stream(stream).await))
   }
   .instrument(span)
   .await
 }
 
-/// Create a stream for decoded event data.
+/// Create a stream for the raw event data.
+pub async fn stream_raw(
+  api_info: &ApiInfo,
+  stream_type: StreamType,
+) -> Result<impl Stream<Item = Result<Vec<u8>, WebSocketError>>, Error> {
+  let (_, stream) = connect(api_info, stream_type, &SocketOptions::default()).await?;
+  Ok(stream)
+}
+
+/// Create a stream for decoded event data, optionally recording all
+/// events received in an audit log.
 pub async fn stream<S>(
   api_info: &ApiInfo,
+  audit: Option<Arc<AuditLog>>,
 ) -> Result<impl Stream<Item = Result<Result<S::Event, JsonError>, WebSocketError>>, Error>
 where
   S: EventStream,
 {
-  let stream = stream_raw(api_info, S::stream())
-    .await?
-    .map(|stream| stream.map(|data| from_json::<Event<S::Event>>(&data).map(|event| event.data)));
-
+  let (_, stream) = stream_confirmed::<S>(api_info, audit).await?;
   Ok(stream)
 }
 
+/// Create a stream for decoded event data just like [`stream`], but
+/// also report the server's confirmation of the subscription.
+///
+/// The stream is only created once the server confirmed the
+/// subscription, so any event happening afterwards is guaranteed to be
+/// reported through it.
+pub async fn stream_confirmed<S>(
+  api_info: &ApiInfo,
+  audit: Option<Arc<AuditLog>>,
+) -> Result<
+  (
+    Subscription,
+    impl Stream<Item = Result<Result<S::Event, JsonError>, WebSocketError>>,
+  ),
+  Error,
+>
+where
+  S: EventStream,
+{
+  stream_confirmed_with::<S>(api_info, audit, &SocketOptions::default()).await
+}
+
+/// Create a stream for decoded event data just like
+/// [`stream_confirmed`], connecting using the given socket options.
+#[allow(clippy::result_large_err)]
+pub(crate) async fn stream_confirmed_with<S>(
+  api_info: &ApiInfo,
+  audit: Option<Arc<AuditLog>>,
+  options: &SocketOptions,
+) -> Result<
+  (
+    Subscription,
+    impl Stream<Item = Result<Result<S::Event, JsonError>, WebSocketError>>,
+  ),
+  Error,
+>
+where
+  S: EventStream,
+{
+  let (subscription, stream) = stream_raw_confirmed_with::<S>(api_info, audit, options).await?;
+  let stream = stream.map(|result| result.map(|raw| raw.event));
+  Ok((subscription, stream))
+}
+
+/// Create a stream for decoded event data just like
+/// [`stream_confirmed`], but have each event carry the raw data it was
+/// decoded from.
+pub async fn stream_raw_confirmed<S>(
+  api_info: &ApiInfo,
+  audit: Option<Arc<AuditLog>>,
+) -> Result<
+  (
+    Subscription,
+    impl Stream<Item = Result<RawEvent<S::Event>, WebSocketError>>,
+  ),
+  Error,
+>
+where
+  S: EventStream,
+{
+  stream_raw_confirmed_with::<S>(api_info, audit, &SocketOptions::default()).await
+}
+
+/// Create a stream for raw events just like [`stream_raw_confirmed`],
+/// connecting using the given socket options.
+#[allow(clippy::result_large_err)]
+pub(crate) async fn stream_raw_confirmed_with<S>(
+  api_info: &ApiInfo,
+  audit: Option<Arc<AuditLog>>,
+  options: &SocketOptions,
+) -> Result<
+  (
+    Subscription,
+    impl Stream<Item = Result<RawEvent<S::Event>, WebSocketError>>,
+  ),
+  Error,
+>
+where
+  S: EventStream,
+{
+  let (subscription, stream) = connect(api_info, S::stream(), options).await?;
+  let stream = stream.map(move |stream| {
+    stream.map(|raw| {
+      if let Some(audit) = &audit {
+        audit.record_event(&raw);
+      }
+      let event = from_json::<Event<S::Event>>(&raw).map(|event| event.data);
+      RawEvent { raw, event }
+    })
+  });
+
+  Ok((subscription, stream))
+}
+
 
 #[cfg(test)]
 mod tests {
   use super::*;
 
   use std::future::Future;
+  use std::time::SystemTime;
 
   use futures::future::ready;
   use futures::SinkExt;
@@ -127,6 +249,12 @@ mod tests {
   use websocket_util::test::WebSocketStream;
   use websocket_util::tungstenite::Message;
 
+  use crate::api::BROKER_BASE_URL;
+  use crate::api::DATA_BASE_URL;
+  use crate::api::DATA_STREAM_URL;
+  #[cfg(feature = "data")]
+  use crate::data::v2::Feed;
+
   const KEY_ID: &str = "USER12345678";
   const SECRET: &str = "justletmein";
   const AUTH_REQ: &str = {
@@ -151,22 +279,36 @@ mod tests {
     }
   }
 
-  async fn mock_stream<S, F, R>(
-    f: F,
-  ) -> Result<impl Stream<Item = Result<Result<S::Event, JsonError>, WebSocketError>>, Error>
+  /// Start a mock server and create an `ApiInfo` object referencing it.
+  async fn mock_api_info<F, R>(f: F) -> ApiInfo
   where
-    S: EventStream,
     F: FnOnce(WebSocketStream) -> R + Send + Sync + 'static,
     R: Future<Output = Result<(), WebSocketError>> + Send + Sync + 'static,
   {
     let addr = mock_server(f).await;
-    let api_info = ApiInfo {
-      base_url: Url::parse(&format!("ws://{}", addr.to_string())).unwrap(),
+    ApiInfo {
+      base_url: Url::parse(&format!("ws://{}", addr)).unwrap(),
+      stream_url: Url::parse(&format!("ws://{}/stream", addr)).unwrap(),
+      data_base_url: Url::parse(DATA_BASE_URL).unwrap(),
+      data_stream_url: Url::parse(DATA_STREAM_URL).unwrap(),
+      #[cfg(feature = "data")]
+      data_feed: Feed::Iex,
+      broker_base_url: Url::parse(BROKER_BASE_URL).unwrap(),
       key_id: KEY_ID.to_string(),
       secret: SECRET.to_string(),
-    };
+    }
+  }
 
-    stream::<S>(&api_info).await
+  async fn mock_stream<S, F, R>(
+    f: F,
+  ) -> Result<impl Stream<Item = Result<Result<S::Event, JsonError>, WebSocketError>>, Error>
+  where
+    S: EventStream,
+    F: FnOnce(WebSocketStream) -> R + Send + Sync + 'static,
+    R: Future<Output = Result<(), WebSocketError>> + Send + Sync + 'static,
+  {
+    let api_info = mock_api_info(f).await;
+    stream::<S>(&api_info, None).await
   }
 
   #[test(tokio::test)]
@@ -247,6 +389,45 @@ mod tests {
     }
   }
 
+  #[test(tokio::test)]
+  async fn confirmed_subscription() {
+    async fn test(mut stream: WebSocketStream) -> Result<(), WebSocketError> {
+      // Authentication.
+      assert_eq!(
+        stream.next().await.unwrap()?,
+        Message::Text(AUTH_REQ.to_string()),
+      );
+      stream.send(Message::Text(AUTH_RESP.to_string())).await?;
+
+      // Subscription.
+      assert_eq!(
+        stream.next().await.unwrap()?,
+        Message::Text(STREAM_REQ.to_string()),
+      );
+      stream.send(Message::Text(STREAM_RESP.to_string())).await?;
+      stream.send(Message::Text(UNIT_EVENT.to_string())).await?;
+      stream.send(Message::Close(None)).await?;
+      Ok(())
+    }
+
+    let before = SystemTime::now();
+    let api_info = mock_api_info(test).await;
+    let (subscription, stream) = stream_confirmed::<DummyStream>(&api_info, None)
+      .await
+      .unwrap();
+
+    assert_eq!(subscription.streams, vec![StreamType::AccountUpdates]);
+    assert!(subscription.confirmed_at >= before);
+
+    let events = stream
+      .map_err(Error::from)
+      .try_collect::<Vec<_>>()
+      .await
+      .unwrap();
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_ok());
+  }
+
   #[test(tokio::test)]
   async fn decode_error_during_handshake() {
     async fn test(mut stream: WebSocketStream) -> Result<(), WebSocketError> {
@@ -304,6 +485,49 @@ mod tests {
       .unwrap();
   }
 
+  /// Check that raw events carry the data they were decoded from.
+  #[test(tokio::test)]
+  async fn raw_events() {
+    async fn test(mut stream: WebSocketStream) -> Result<(), WebSocketError> {
+      // Authentication.
+      assert_eq!(
+        stream.next().await.unwrap()?,
+        Message::Text(AUTH_REQ.to_string()),
+      );
+      stream.send(Message::Text(AUTH_RESP.to_string())).await?;
+
+      // Subscription.
+      assert_eq!(
+        stream.next().await.unwrap()?,
+        Message::Text(STREAM_REQ.to_string()),
+      );
+      stream.send(Message::Text(STREAM_RESP.to_string())).await?;
+
+      stream
+        .send(Message::Text("{ foobarbaz }".to_string()))
+        .await?;
+      stream.send(Message::Text(UNIT_EVENT.to_string())).await?;
+      stream.send(Message::Close(None)).await?;
+      Ok(())
+    }
+
+    let api_info = mock_api_info(test).await;
+    let (_, stream) = stream_raw_confirmed::<DummyStream>(&api_info, None)
+      .await
+      .unwrap();
+
+    let events = stream
+      .map_err(Error::from)
+      .try_collect::<Vec<_>>()
+      .await
+      .unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].raw_str(), "{ foobarbaz }");
+    assert!(events[0].event.is_err());
+    assert_eq!(events[1].raw_str(), UNIT_EVENT);
+    assert!(events[1].event.is_ok());
+  }
+
   #[test(tokio::test)]
   async fn ping_pong() {
     async fn test(mut stream: WebSocketStream) -> Result<(), WebSocketError> {
@@ -0,0 +1,334 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::error::Error as StdError;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use futures::StreamExt as _;
+
+use num_decimal::Num;
+
+use crate::api::v2::account_activities;
+use crate::api::v2::order;
+use crate::data::v2::quotes;
+use crate::data::v2::quotes::Quote;
+use crate::data::v2::Sort;
+use crate::Client;
+use crate::RequestError;
+
+
+/// The maximum number of quote requests in flight at any given time.
+const MAX_CONCURRENT: usize = 8;
+/// The number of fill activities to retrieve per page.
+const PAGE_SIZE: usize = 100;
+/// The time span before an execution in which to look for the quote
+/// prevailing at the time of the execution.
+const QUOTE_WINDOW: Duration = Duration::from_secs(60);
+
+
+/// An error preventing the creation of a fill quality report.
+#[derive(Debug)]
+pub enum ReportError {
+  /// The order could not be retrieved.
+  Order(RequestError<order::GetError>),
+  /// The fill activities could not be retrieved.
+  Activities(RequestError<account_activities::GetError>),
+  /// The quotes around an execution could not be retrieved.
+  Quotes(RequestError<quotes::GetError>),
+}
+
+impl Display for ReportError {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    match self {
+      Self::Order(err) => write!(fmt, "failed to retrieve order: {}", err),
+      Self::Activities(err) => write!(fmt, "failed to retrieve fill activities: {}", err),
+      Self::Quotes(err) => write!(fmt, "failed to retrieve quotes: {}", err),
+    }
+  }
+}
+
+impl StdError for ReportError {
+  fn source(&self) -> Option<&(dyn StdError + 'static)> {
+    match self {
+      Self::Order(err) => Some(err),
+      Self::Activities(err) => Some(err),
+      Self::Quotes(err) => Some(err),
+    }
+  }
+}
+
+
+/// A single execution of an order, along with the quote prevailing at
+/// the time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fill {
+  /// The ID of the fill activity.
+  pub id: String,
+  /// The time at which the execution occurred.
+  pub time: SystemTime,
+  /// The side of the order.
+  pub side: order::Side,
+  /// The number of shares executed.
+  pub quantity: u64,
+  /// The per-share price the execution happened at.
+  pub price: Num,
+  /// The last quote at or before the time of the execution, if one
+  /// was found.
+  pub quote: Option<Quote>,
+}
+
+impl Fill {
+  /// Retrieve the midpoint between bid and ask of the prevailing
+  /// quote.
+  pub fn midpoint(&self) -> Option<Num> {
+    self.quote.as_ref()?.midpoint()
+  }
+
+  /// Calculate the slippage per share relative to the midpoint of the
+  /// prevailing quote.
+  ///
+  /// The value is positive if the execution happened at a worse price
+  /// than the midpoint.
+  pub fn slippage(&self) -> Option<Num> {
+    let midpoint = self.midpoint()?;
+    let slippage = match self.side {
+      order::Side::Buy => &self.price - midpoint,
+      order::Side::Sell => midpoint - &self.price,
+    };
+    Some(slippage)
+  }
+
+  /// Calculate the price improvement per share relative to the price
+  /// quoted on the opposite side of the prevailing quote, i.e., the
+  /// ask for buys and the bid for sells.
+  ///
+  /// The value is positive if the execution happened at a better price
+  /// than quoted.
+  pub fn price_improvement(&self) -> Option<Num> {
+    let quote = self.quote.as_ref()?;
+    let improvement = match self.side {
+      order::Side::Buy if quote.ask_price.is_positive() => &quote.ask_price - &self.price,
+      order::Side::Sell if quote.bid_price.is_positive() => &self.price - &quote.bid_price,
+      _ => return None,
+    };
+    Some(improvement)
+  }
+}
+
+
+/// A report on the execution quality of an order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+  /// The order the report is about.
+  pub order: order::Order,
+  /// The executions of the order, oldest first.
+  pub fills: Vec<Fill>,
+}
+
+impl Report {
+  /// Calculate the volume weighted average of a per-share metric over
+  /// all fills for which it is known.
+  fn weighted<F>(&self, metric: F) -> Option<Num>
+  where
+    F: Fn(&Fill) -> Option<Num>,
+  {
+    let (total, quantity) = self
+      .fills
+      .iter()
+      .filter_map(|fill| metric(fill).map(|value| (value, fill.quantity)))
+      .fold((Num::from(0), 0), |(total, quantity), (value, size)| {
+        (total + value * Num::from(size), quantity + size)
+      });
+
+    if quantity == 0 {
+      None
+    } else {
+      Some(total / Num::from(quantity))
+    }
+  }
+
+  /// Retrieve the total number of shares executed.
+  pub fn quantity(&self) -> u64 {
+    self.fills.iter().map(|fill| fill.quantity).sum()
+  }
+
+  /// Calculate the average price per share across all executions.
+  pub fn average_price(&self) -> Option<Num> {
+    self.weighted(|fill| Some(fill.price.clone()))
+  }
+
+  /// Calculate the volume weighted average slippage per share, as per
+  /// `Fill::slippage`.
+  ///
+  /// Executions for which no quote is known are not taken into
+  /// account.
+  pub fn slippage(&self) -> Option<Num> {
+    self.weighted(Fill::slippage)
+  }
+
+  /// Calculate the volume weighted average price improvement per
+  /// share, as per `Fill::price_improvement`.
+  ///
+  /// Executions for which no quote is known are not taken into
+  /// account.
+  pub fn price_improvement(&self) -> Option<Num> {
+    self.weighted(Fill::price_improvement)
+  }
+}
+
+
+impl Client {
+  /// Retrieve the executions of the order with the given ID along with
+  /// the quotes prevailing at the time of each, to assess the quality
+  /// of the order's fills.
+  ///
+  /// Executions are determined based on the account's fill activities,
+  /// which are searched back to the time the order was created. Quotes
+  /// are retrieved concurrently from the /v2/stocks/<symbol>/quotes
+  /// data endpoint, meaning that only orders of stocks are supported.
+  pub async fn fill_quality(&self, id: order::Id) -> Result<Report, ReportError> {
+    let order = self
+      .issue::<order::Get>(id)
+      .await
+      .map_err(ReportError::Order)?;
+
+    let request = account_activities::ActivityReq {
+      types: Some(vec![account_activities::ActivityType::Fill]),
+      page_size: Some(PAGE_SIZE),
+      ..Default::default()
+    };
+    let mut pages = Box::pin(self.issue_paged::<account_activities::Get>(request));
+    let mut trades = Vec::new();
+
+    // Activities are reported newest first, so we can stop once we
+    // reach one that predates the order.
+    'pages: while let Some(page) = pages.next().await {
+      let page = page.map_err(ReportError::Activities)?;
+      for activity in page {
+        if let Ok(trade) = activity.into_trade() {
+          if trade.transaction_time < order.created_at {
+            break 'pages
+          }
+          if trade.order_id == id {
+            trades.push(trade)
+          }
+        }
+      }
+    }
+    trades.reverse();
+
+    let requests = trades
+      .iter()
+      .map(|trade| {
+        quotes::QuotesReqInit {
+          limit: Some(1),
+          sort: Some(Sort::Descending),
+          ..Default::default()
+        }
+        .init(
+          order.symbol.clone(),
+          trade.transaction_time - QUOTE_WINDOW,
+          trade.transaction_time,
+        )
+      })
+      .collect::<Vec<_>>();
+    let quotes = self
+      .issue_all::<quotes::Get, _>(requests, MAX_CONCURRENT)
+      .await;
+
+    let fills = trades
+      .into_iter()
+      .zip(quotes)
+      .map(|(trade, quotes)| {
+        let quote = quotes
+          .map_err(ReportError::Quotes)?
+          .quotes
+          .into_iter()
+          .next();
+        Ok(Fill {
+          id: trade.id,
+          time: trade.transaction_time,
+          side: order.side,
+          quantity: trade.quantity,
+          price: trade.price,
+          quote,
+        })
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Report { order, fills })
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::time::UNIX_EPOCH;
+
+  use test_env_log::test;
+
+  use crate::test_support;
+
+
+  /// Create a fill of the given quantity at the given price, executed
+  /// while the given bid and ask were quoted.
+  fn fill(side: order::Side, quantity: u64, price: Num, bid_ask: Option<(Num, Num)>) -> Fill {
+    Fill {
+      id: "20210301000000000::8d4a4c56".to_string(),
+      time: UNIX_EPOCH,
+      side,
+      quantity,
+      price,
+      quote: bid_ask.map(|(bid, ask)| test_support::quote(UNIX_EPOCH, bid, ask)),
+    }
+  }
+
+  /// Check that we correctly calculate the slippage and price
+  /// improvement of individual fills.
+  #[test]
+  fn calculate_fill_quality() {
+    let quote = Some((Num::from(100), Num::new(1002, 10)));
+    let buy = fill(order::Side::Buy, 10, Num::new(1001, 10), quote.clone());
+    assert_eq!(buy.midpoint(), Some(Num::new(1001, 10)));
+    assert_eq!(buy.slippage(), Some(Num::from(0)));
+    assert_eq!(buy.price_improvement(), Some(Num::new(1, 10)));
+
+    let sell = fill(order::Side::Sell, 10, Num::new(999, 10), quote);
+    assert_eq!(sell.slippage(), Some(Num::new(2, 10)));
+    assert_eq!(sell.price_improvement(), Some(Num::new(-1, 10)));
+
+    let unquoted = fill(order::Side::Sell, 10, Num::from(100), None);
+    assert_eq!(unquoted.slippage(), None);
+    assert_eq!(unquoted.price_improvement(), None);
+  }
+
+  /// Check that we correctly aggregate the fills of an order.
+  #[test]
+  fn aggregate_report() {
+    let quote = Some((Num::from(100), Num::from(101)));
+    let report = Report {
+      order: test_support::order("SPY", order::Side::Buy, 40),
+      fills: vec![
+        fill(order::Side::Buy, 10, Num::from(101), quote.clone()),
+        fill(order::Side::Buy, 30, Num::new(1005, 10), quote),
+      ],
+    };
+    assert_eq!(report.quantity(), 40);
+    assert_eq!(report.average_price(), Some(Num::new(100625, 1000)));
+    assert_eq!(report.slippage(), Some(Num::new(125, 1000)));
+    assert_eq!(report.price_improvement(), Some(Num::new(375, 1000)));
+
+    let report = Report {
+      order: test_support::order("SPY", order::Side::Buy, 40),
+      fills: vec![fill(order::Side::Buy, 10, Num::from(101), None)],
+    };
+    assert_eq!(report.average_price(), Some(Num::from(101)));
+    assert_eq!(report.slippage(), None);
+  }
+}
@@ -0,0 +1,190 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashSet;
+use std::error::Error as StdError;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use tracing::warn;
+
+use crate::api::v2::asset;
+use crate::api::v2::order;
+use crate::api::v2::orders;
+use crate::api::v2::position;
+use crate::api::v2::positions;
+use crate::Client;
+use crate::RequestError;
+
+
+/// The maximum number of cancellation or liquidation requests in
+/// flight at any given time.
+const MAX_CONCURRENT: usize = 8;
+
+
+/// An error preventing the flattening of the account.
+#[derive(Debug)]
+pub enum FlattenError {
+  /// The open orders could not be listed.
+  Orders(RequestError<orders::GetError>),
+  /// The open positions could not be listed.
+  Positions(RequestError<positions::GetError>),
+}
+
+impl Display for FlattenError {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    match self {
+      Self::Orders(err) => write!(fmt, "failed to list open orders: {}", err),
+      Self::Positions(err) => write!(fmt, "failed to list open positions: {}", err),
+    }
+  }
+}
+
+impl StdError for FlattenError {
+  fn source(&self) -> Option<&(dyn StdError + 'static)> {
+    match self {
+      Self::Orders(err) => Some(err),
+      Self::Positions(err) => Some(err),
+    }
+  }
+}
+
+
+/// The orders that were attempted to be canceled, along with the result
+/// of the cancellation.
+pub type Canceled = Vec<(order::Order, Result<(), RequestError<order::DeleteError>>)>;
+
+
+/// The outcome of an attempt to flatten the account.
+#[derive(Debug)]
+pub struct Flattened {
+  /// The orders that were attempted to be canceled, along with the
+  /// result of the cancellation.
+  pub canceled: Canceled,
+  /// The symbols of the positions that were attempted to be closed,
+  /// along with the result, which is the liquidating order on success.
+  pub closed: Vec<(String, Result<order::Order, RequestError<position::DeleteError>>)>,
+}
+
+impl Flattened {
+  /// Check whether all orders were canceled and all positions closed
+  /// successfully.
+  pub fn is_success(&self) -> bool {
+    self.canceled.iter().all(|(_, result)| result.is_ok())
+      && self.closed.iter().all(|(_, result)| result.is_ok())
+  }
+}
+
+
+impl Client {
+  /// Cancel all open orders matching the given filter, concurrently.
+  async fn cancel_open_orders<F>(
+    &self,
+    filter: F,
+  ) -> Result<Canceled, RequestError<orders::GetError>>
+  where
+    F: Fn(&order::Order) -> bool,
+  {
+    let orders = self
+      .all_orders(orders::Status::Open, false)
+      .await?
+      .into_iter()
+      .filter(filter)
+      .collect::<Vec<_>>();
+    let ids = orders.iter().map(|order| order.id).collect::<Vec<_>>();
+    let results = self.issue_all::<order::Delete, _>(ids, MAX_CONCURRENT).await;
+    Ok(orders.into_iter().zip(results).collect())
+  }
+
+  /// Cancel all open orders for the given symbols, e.g., when exiting
+  /// these names.
+  ///
+  /// Cancellations are issued concurrently. Individual failures do not
+  /// stop the operation and are reported as part of the result, per
+  /// order.
+  pub async fn cancel_orders_for<I, S>(
+    &self,
+    symbols: I,
+  ) -> Result<Canceled, RequestError<orders::GetError>>
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+  {
+    let symbols = symbols.into_iter().map(Into::into).collect::<HashSet<_>>();
+    let canceled = self
+      .cancel_open_orders(|order| symbols.contains(&order.symbol))
+      .await?;
+
+    if canceled.iter().any(|(_, result)| result.is_err()) {
+      warn!("failed to cancel all orders for symbols");
+    }
+    Ok(canceled)
+  }
+
+  /// Close all open positions and, optionally, cancel all open orders
+  /// first, for use as an emergency stop.
+  ///
+  /// Orders are canceled before positions are closed, because open
+  /// orders may otherwise prevent the liquidation of the position they
+  /// refer to. Cancellations as well as liquidations are issued
+  /// concurrently. Individual failures do not stop the operation and
+  /// are reported as part of the result, per order and symbol.
+  pub async fn flatten_all(&self, cancel_orders: bool) -> Result<Flattened, FlattenError> {
+    let canceled = if cancel_orders {
+      self
+        .cancel_open_orders(|_| true)
+        .await
+        .map_err(FlattenError::Orders)?
+    } else {
+      Vec::new()
+    };
+
+    let positions = self
+      .issue::<positions::Get>(())
+      .await
+      .map_err(FlattenError::Positions)?;
+    let symbols = positions
+      .into_iter()
+      .map(|position| position.symbol)
+      .collect::<Vec<_>>();
+    let inputs = symbols.iter().cloned().map(asset::Symbol::Sym);
+    let results = self
+      .issue_all::<position::Delete, _>(inputs, MAX_CONCURRENT)
+      .await;
+    let closed = symbols.into_iter().zip(results).collect::<Vec<_>>();
+
+    let flattened = Flattened { canceled, closed };
+    if !flattened.is_success() {
+      warn!("failed to flatten account completely");
+    }
+    Ok(flattened)
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use test_env_log::test;
+
+
+  #[test]
+  fn report_success() {
+    let flattened = Flattened {
+      canceled: Vec::new(),
+      closed: vec![(
+        "AAPL".to_string(),
+        Err(RequestError::Endpoint(position::DeleteError::NotFound(Err(Vec::new())))),
+      )],
+    };
+    assert!(!flattened.is_success());
+
+    let flattened = Flattened {
+      canceled: Vec::new(),
+      closed: Vec::new(),
+    };
+    assert!(flattened.is_success());
+  }
+}
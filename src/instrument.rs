@@ -0,0 +1,179 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::str::FromStr;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+
+/// An enumeration of the various asset classes available.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum AssetClass {
+  /// US equities.
+  #[serde(rename = "us_equity")]
+  #[default]
+  UsEquity,
+  /// Crypto currencies.
+  #[serde(rename = "crypto")]
+  Crypto,
+  /// US options.
+  #[serde(rename = "us_option")]
+  UsOption,
+  /// Any other asset class that we have not accounted for.
+  ///
+  /// Note that having any such asset class should be considered a bug.
+  #[serde(other)]
+  Unknown,
+}
+
+impl AsRef<str> for AssetClass {
+  fn as_ref(&self) -> &'static str {
+    match *self {
+      AssetClass::UsEquity => "us_equity",
+      AssetClass::Crypto => "crypto",
+      AssetClass::UsOption => "us_option",
+      AssetClass::Unknown => "unknown",
+    }
+  }
+}
+
+impl FromStr for AssetClass {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    if s == AssetClass::UsEquity.as_ref() {
+      Ok(AssetClass::UsEquity)
+    } else if s == AssetClass::Crypto.as_ref() {
+      Ok(AssetClass::Crypto)
+    } else if s == AssetClass::UsOption.as_ref() {
+      Ok(AssetClass::UsOption)
+    } else {
+      // Note that we do not support creating the `Unknown` variant
+      // here. This variant is really only meant to cover
+      // deserialization.
+      Err(())
+    }
+  }
+}
+
+
+/// An enumeration of the various supported exchanges an asset can be
+/// listed at.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+pub enum Exchange {
+  /// American Stock Exchange.
+  #[serde(rename = "AMEX")]
+  Amex,
+  /// XXX
+  // TODO: Not quite clear.
+  #[serde(rename = "ARCA")]
+  Arca,
+  /// BATS Global Markets.
+  #[serde(rename = "BATS")]
+  Bats,
+  /// Nasdaq Stock Market.
+  #[serde(rename = "NASDAQ")]
+  Nasdaq,
+  /// New York Stock Exchange.
+  #[serde(rename = "NYSE")]
+  Nyse,
+  /// NYSE Arca.
+  #[serde(rename = "NYSEARCA")]
+  Nysearca,
+  /// Over the counter markets.
+  #[serde(rename = "OTC")]
+  Otc,
+  /// The venue crypto currencies are traded at.
+  #[serde(rename = "CRYPTO")]
+  Crypto,
+  /// Any other exchange that we have not accounted for.
+  ///
+  /// Note that having any such status should be considered a bug.
+  #[serde(other)]
+  Unknown,
+}
+
+impl AsRef<str> for Exchange {
+  fn as_ref(&self) -> &'static str {
+    match *self {
+      Exchange::Amex => "AMEX",
+      Exchange::Arca => "ARCA",
+      Exchange::Bats => "BATS",
+      Exchange::Nasdaq => "NASDAQ",
+      Exchange::Nyse => "NYSE",
+      Exchange::Nysearca => "NYSEARCA",
+      Exchange::Otc => "OTC",
+      Exchange::Crypto => "CRYPTO",
+      Exchange::Unknown => "unknown",
+    }
+  }
+}
+
+impl FromStr for Exchange {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    [
+      Exchange::Amex,
+      Exchange::Arca,
+      Exchange::Bats,
+      Exchange::Nasdaq,
+      Exchange::Nyse,
+      Exchange::Nysearca,
+      Exchange::Otc,
+      Exchange::Crypto,
+    ]
+    .iter()
+    .copied()
+    // Note that we do not support creating the `Unknown` variant
+    // here. This variant is really only meant to cover
+    // deserialization.
+    .find(|exchange| s == exchange.as_ref())
+    .ok_or(())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde_json::from_str as from_json;
+  use serde_json::to_string as to_json;
+
+  use test_env_log::test;
+
+
+  /// Check that we can convert asset classes from and to strings.
+  #[test]
+  fn asset_class_strings() {
+    for class in [
+      AssetClass::UsEquity,
+      AssetClass::Crypto,
+      AssetClass::UsOption,
+    ]
+    .iter()
+    {
+      assert_eq!(AssetClass::from_str(class.as_ref()), Ok(*class));
+      let json = to_json(class).unwrap();
+      assert_eq!(json, format!("\"{}\"", class.as_ref()));
+      assert_eq!(from_json::<AssetClass>(&json).unwrap(), *class);
+    }
+
+    assert_eq!(AssetClass::from_str("unknown"), Err(()));
+    assert_eq!(
+      from_json::<AssetClass>(r#""us_future""#).unwrap(),
+      AssetClass::Unknown
+    );
+  }
+
+  /// Check that we can convert exchanges from and to strings.
+  #[test]
+  fn exchange_strings() {
+    assert_eq!(Exchange::from_str("CRYPTO"), Ok(Exchange::Crypto));
+    assert_eq!(Exchange::from_str("OTC"), Ok(Exchange::Otc));
+    assert_eq!(Exchange::from_str("unknown"), Err(()));
+    assert_eq!(from_json::<Exchange>(r#""NYSE""#).unwrap(), Exchange::Nyse);
+  }
+}
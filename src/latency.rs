@@ -0,0 +1,285 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::Duration;
+use std::time::SystemTime;
+
+use futures::stream::Stream;
+use futures::StreamExt;
+
+use websocket_util::tungstenite::Error as WebSocketError;
+
+#[cfg(feature = "trading")]
+use crate::api::v2::events::AccountUpdate;
+#[cfg(feature = "trading")]
+use crate::api::v2::events::TradeUpdate;
+#[cfg(feature = "data")]
+use crate::data::v2::bars::Bar;
+#[cfg(feature = "data")]
+use crate::data::v2::quotes::Quote;
+#[cfg(feature = "data")]
+use crate::data::v2::stream::Data;
+#[cfg(feature = "data")]
+use crate::data::v2::trades::Trade;
+
+
+/// A trait for records and stream messages associated with the point
+/// in time at which the reported event happened, as stamped by the
+/// server or exchange.
+pub trait Timestamped {
+  /// Retrieve the time the event happened at, if known.
+  fn timestamp(&self) -> Option<SystemTime>;
+}
+
+#[cfg(feature = "trading")]
+impl Timestamped for AccountUpdate {
+  fn timestamp(&self) -> Option<SystemTime> {
+    self.updated_at
+  }
+}
+
+#[cfg(feature = "trading")]
+impl Timestamped for TradeUpdate {
+  fn timestamp(&self) -> Option<SystemTime> {
+    self.timestamp
+  }
+}
+
+#[cfg(feature = "data")]
+impl Timestamped for Bar {
+  /// Retrieve the beginning time of the bar.
+  fn timestamp(&self) -> Option<SystemTime> {
+    Some(self.time)
+  }
+}
+
+#[cfg(feature = "data")]
+impl Timestamped for Quote {
+  fn timestamp(&self) -> Option<SystemTime> {
+    Some(self.time)
+  }
+}
+
+#[cfg(feature = "data")]
+impl Timestamped for Trade {
+  fn timestamp(&self) -> Option<SystemTime> {
+    Some(self.time)
+  }
+}
+
+#[cfg(feature = "data")]
+impl Timestamped for Data {
+  fn timestamp(&self) -> Option<SystemTime> {
+    Some(self.time())
+  }
+}
+
+
+/// Calculate the latency of a message reporting an event that happened
+/// at `event_time` and that got received at `received`.
+///
+/// Events seemingly happening after they got received, which can be
+/// the result of the local clock being behind the one of the server,
+/// are reported as having zero latency. Use
+/// [`Skew`](crate::skew::Skew) to correct for such clock differences
+/// where necessary.
+pub fn latency(event_time: SystemTime, received: SystemTime) -> Duration {
+  received
+    .duration_since(event_time)
+    .unwrap_or_else(|_| Duration::from_secs(0))
+}
+
+
+/// Summary statistics over a series of latency measurements.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LatencyStats {
+  /// The number of measurements recorded.
+  count: u64,
+  /// The sum of all measurements.
+  total: Duration,
+  /// The smallest measurement.
+  min: Option<Duration>,
+  /// The largest measurement.
+  max: Option<Duration>,
+  /// The most recent measurement.
+  last: Option<Duration>,
+}
+
+impl LatencyStats {
+  /// Record a latency measurement.
+  pub fn record(&mut self, latency: Duration) {
+    self.count += 1;
+    self.total += latency;
+    self.min = Some(self.min.map_or(latency, |min| min.min(latency)));
+    self.max = Some(self.max.map_or(latency, |max| max.max(latency)));
+    self.last = Some(latency);
+  }
+
+  /// Retrieve the number of measurements recorded.
+  pub fn count(&self) -> u64 {
+    self.count
+  }
+
+  /// Retrieve the smallest latency recorded.
+  pub fn min(&self) -> Option<Duration> {
+    self.min
+  }
+
+  /// Retrieve the largest latency recorded.
+  pub fn max(&self) -> Option<Duration> {
+    self.max
+  }
+
+  /// Retrieve the most recently recorded latency.
+  pub fn last(&self) -> Option<Duration> {
+    self.last
+  }
+
+  /// Retrieve the average latency over all measurements.
+  pub fn mean(&self) -> Option<Duration> {
+    if self.count == 0 {
+      None
+    } else {
+      let nanos = self.total.as_nanos() / u128::from(self.count);
+      // The average can never exceed the largest measurement, which
+      // fit into a `Duration`.
+      Some(Duration::new(
+        (nanos / 1_000_000_000) as u64,
+        (nanos % 1_000_000_000) as u32,
+      ))
+    }
+  }
+}
+
+
+/// Measure the latency of each message received through a stream as
+/// produced by [`Client::subscribe`](crate::Client::subscribe) or
+/// `Client::subscribe_market_data`.
+///
+/// For every successfully decoded message carrying an event time,
+/// `hook` is invoked with the message and the difference between the
+/// event time and the time the message got received. The items of the
+/// stream themselves are passed through unchanged.
+pub fn measure_latency<S, T, E, F>(stream: S, mut hook: F) -> impl Stream<Item = S::Item>
+where
+  S: Stream<Item = Result<Result<T, E>, WebSocketError>>,
+  T: Timestamped,
+  F: FnMut(&T, Duration),
+{
+  stream.inspect(move |item| {
+    if let Ok(Ok(message)) = item {
+      if let Some(event_time) = message.timestamp() {
+        hook(message, latency(event_time, SystemTime::now()))
+      }
+    }
+  })
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::time::UNIX_EPOCH;
+
+  use futures::stream::iter;
+
+  #[cfg(feature = "data")]
+  use num_decimal::Num;
+
+  use serde::de::Error as _;
+  use serde_json::Error as JsonError;
+
+  use test_env_log::test;
+
+  #[cfg(feature = "data")]
+  use crate::data::v2::stream::MessageError;
+  #[cfg(feature = "data")]
+  use crate::data::v2::stream::SymbolData;
+  #[cfg(feature = "data")]
+  use crate::test_support;
+
+
+  /// A message used for testing purposes.
+  struct Message(Option<SystemTime>);
+
+  impl Timestamped for Message {
+    fn timestamp(&self) -> Option<SystemTime> {
+      self.0
+    }
+  }
+
+
+  #[test]
+  fn calculate_latency() {
+    let event_time = UNIX_EPOCH + Duration::from_secs(10);
+    let received = UNIX_EPOCH + Duration::from_millis(10_250);
+    assert_eq!(latency(event_time, received), Duration::from_millis(250));
+    assert_eq!(latency(received, event_time), Duration::from_secs(0));
+  }
+
+  #[test]
+  fn accumulate_stats() {
+    let mut stats = LatencyStats::default();
+    assert_eq!(stats.mean(), None);
+
+    stats.record(Duration::from_millis(30));
+    stats.record(Duration::from_millis(10));
+    stats.record(Duration::from_millis(20));
+
+    assert_eq!(stats.count(), 3);
+    assert_eq!(stats.min(), Some(Duration::from_millis(10)));
+    assert_eq!(stats.max(), Some(Duration::from_millis(30)));
+    assert_eq!(stats.last(), Some(Duration::from_millis(20)));
+    assert_eq!(stats.mean(), Some(Duration::from_millis(20)));
+  }
+
+  #[test(tokio::test)]
+  async fn measure_stream_latency() {
+    let now = SystemTime::now();
+    let items = vec![
+      Ok(Ok(Message(Some(now - Duration::from_secs(5))))),
+      Ok(Ok(Message(None))),
+      Ok(Err(JsonError::custom("invalid"))),
+      Ok(Ok(Message(Some(now - Duration::from_secs(1))))),
+    ];
+
+    let mut stats = LatencyStats::default();
+    let count = measure_latency(iter(items), |_, latency| stats.record(latency))
+      .count()
+      .await;
+
+    assert_eq!(count, 4);
+    assert_eq!(stats.count(), 2);
+    assert!(stats.max().unwrap() >= Duration::from_secs(5));
+    assert!(stats.min().unwrap() >= Duration::from_secs(1));
+    assert!(stats.min().unwrap() < Duration::from_secs(5));
+  }
+  /// Check that we can measure the latency of market data, as reported
+  /// by the real time market data stream.
+  #[cfg(feature = "data")]
+  #[test(tokio::test)]
+  async fn measure_market_data_latency() {
+    let time = SystemTime::now() - Duration::from_secs(2);
+    let trade = test_support::trade(time, Num::from(100), 10);
+    let items = vec![
+      Ok(Ok(Data::Trade(SymbolData {
+        symbol: "SPY".to_string(),
+        data: trade,
+      }))),
+      Ok(Err(MessageError::Json(JsonError::custom("invalid")))),
+    ];
+
+    let mut stats = LatencyStats::default();
+    let count = measure_latency(iter(items), |data, latency| {
+      assert_eq!(data.symbol(), "SPY");
+      stats.record(latency)
+    })
+    .count()
+    .await;
+
+    assert_eq!(count, 2);
+    assert_eq!(stats.count(), 1);
+    assert!(stats.last().unwrap() >= Duration::from_secs(2));
+  }
+}
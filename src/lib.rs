@@ -8,7 +8,6 @@
   broken_intra_doc_links,
   dead_code,
   future_incompatible,
-  illegal_floating_point_literal_pattern,
   improper_ctypes,
   late_bound_lifetime_arguments,
   missing_copy_implementations,
@@ -20,12 +19,10 @@
   overflowing_literals,
   path_statements,
   patterns_in_fns_without_body,
-  private_in_public,
   proc_macro_derive_resolution_fallback,
   renamed_and_removed_lints,
   rust_2018_compatibility,
   rust_2018_idioms,
-  safe_packed_borrows,
   stable_features,
   trivial_bounds,
   trivial_numeric_casts,
@@ -42,47 +39,213 @@
   unused_lifetimes,
   unused_qualifications,
   unused_results,
-  where_clauses_object_safety,
   while_true,
 )]
 
 //! A create for interacting with the Alpaca API.
 
-#[macro_use]
-extern crate http_endpoint;
-
+// Without any of the endpoint families enabled there is nothing
+// making use of our endpoint definition infrastructure.
+#[cfg_attr(
+  not(any(feature = "broker", feature = "data", feature = "trading")),
+  allow(unused)
+)]
 #[macro_use]
 mod endpoint;
+#[cfg_attr(not(any(feature = "data", feature = "trading")), allow(unused))]
+#[macro_use]
+mod util;
 
 /// A module comprising the functionality backing interactions with the
 /// API.
 pub mod api;
 
 /// A module for retrieving market data.
+#[cfg(feature = "data")]
 pub mod data;
 
+/// A module for quickly toggling account configuration flags, e.g.,
+/// for suspending trading.
+#[cfg(feature = "trading")]
+pub mod account_toggles;
+/// A module for persisting requests, responses, and events to an
+/// audit log.
+pub mod audit;
+/// A module for fanning out a single stream to multiple subscribers.
+pub mod broadcast;
+/// A module comprising the functionality backing interactions with the
+/// Broker API.
+///
+/// The Broker API is served from a different host than the trading
+/// API. Requests to it are issued against the Broker API base URL of
+/// the `ApiInfo` object in use, which defaults to the sandbox at
+/// `https://broker-api.sandbox.alpaca.markets`.
+/// The Broker API furthermore expects HTTP Basic authentication, which
+/// can be enabled when building the `Client` (see `Client::builder`).
+#[cfg(feature = "broker")]
+pub mod broker;
+/// A module providing caching of responses to slowly changing
+/// endpoints.
+pub mod cache;
+/// A module for canceling orders and confirming that the cancellation
+/// took effect.
+#[cfg(feature = "trading")]
+pub mod cancel;
+/// A module for dropping duplicate trade updates.
+#[cfg(all(feature = "streaming", feature = "trading"))]
+pub mod dedup;
+/// A module for assessing the execution quality of orders.
+#[cfg(all(feature = "data", feature = "trading"))]
+pub mod execution;
+/// A module for closing all positions and canceling all orders at
+/// once, or only the orders for a set of symbols.
+#[cfg(feature = "trading")]
+pub mod flatten;
+/// A module providing the asset class and exchange definitions shared
+/// among the various API families.
+pub mod instrument;
+/// A module for measuring the latency of messages received through
+/// streams.
+#[cfg(feature = "streaming")]
+pub mod latency;
+/// A module for projecting the effect of an order on buying power and
+/// margin requirements.
+#[cfg(feature = "trading")]
+pub mod margin;
+/// A module for working with multiple accounts at once.
+#[cfg(feature = "trading")]
+pub mod multi;
+/// A module for logging the lifecycle of orders.
+#[cfg(all(feature = "streaming", feature = "trading"))]
+pub mod order_log;
+/// A module for tracking the state of submitted orders.
+#[cfg(all(feature = "streaming", feature = "trading"))]
+pub mod order_tracker;
+/// A module for retrieving results of paginated endpoints.
+pub mod paged;
+/// A module for guarding against violations of the pattern day trader
+/// rule.
+#[cfg(feature = "trading")]
+pub mod pdt;
+/// A module for tracking the value of a portfolio based on real time
+/// prices.
+#[cfg(feature = "trading")]
+pub mod portfolio;
+/// A module for rebalancing a portfolio towards target weights.
+#[cfg(feature = "trading")]
+pub mod rebalance;
+/// A module for replaying historical market data as a stream.
+#[cfg(all(feature = "data", feature = "streaming"))]
+pub mod replay;
+/// A module for reconciling locally tracked positions with the ones
+/// held in the account.
+#[cfg(feature = "trading")]
+pub mod reconcile;
+/// A module for checking order submissions against risk limits.
+#[cfg(feature = "trading")]
+pub mod risk;
+/// A module for submitting orders on a schedule of trading days, e.g.,
+/// for dollar cost averaging.
+#[cfg(feature = "trading")]
+pub mod schedule;
+/// A module for iterating over trading sessions, based on the market
+/// calendar.
+#[cfg(feature = "trading")]
+pub mod sessions;
+/// A module for checking whether assets can be sold short.
+#[cfg(feature = "trading")]
+pub mod short;
+/// A module for estimating the skew between the local clock and the
+/// one of the server.
+#[cfg(feature = "trading")]
+pub mod skew;
+/// A module for estimating the fill price of market orders.
+#[cfg(all(feature = "data", feature = "trading"))]
+pub mod slippage;
+/// A module providing an in-process simulation of the trading API.
+#[cfg(feature = "trading")]
+pub mod simulator;
+/// A module for retrieving a snapshot of the current state of the
+/// account.
+#[cfg(feature = "trading")]
+pub mod snapshot;
+/// A module for caching the status of open orders.
+#[cfg(all(feature = "streaming", feature = "trading"))]
+pub mod status_cache;
+/// A module providing a validated symbol type.
+pub mod symbol;
+/// A module for resolving renamed and delisted symbols in historical
+/// data requests.
+#[cfg(all(feature = "data", feature = "trading"))]
+pub mod symbol_map;
+/// A module for rounding order prices to valid increments.
+#[cfg(feature = "trading")]
+pub mod tick;
+/// A module for guarding order submissions against potential wash
+/// trades.
+#[cfg(feature = "trading")]
+pub mod wash_trade;
+/// A module for keeping market data subscriptions in sync with a
+/// watchlist.
+#[cfg(all(feature = "data", feature = "streaming", feature = "trading"))]
+pub mod watchlist_sync;
+/// A module providing functions for creating API objects in tests.
+#[cfg(any(test, feature = "proptest", feature = "test-support"))]
+#[cfg_attr(not(any(feature = "data", feature = "trading")), allow(unused))]
+pub mod test_support;
+
 mod api_info;
+#[cfg(feature = "proptest")]
+#[cfg_attr(not(any(feature = "data", feature = "trading")), allow(unused))]
+mod arbitrary;
 mod client;
+#[cfg(feature = "trading")]
+mod dry_run;
 mod error;
+#[cfg(feature = "trading")]
+mod market_hours;
+#[cfg(feature = "streaming")]
 mod events;
+#[cfg(feature = "trading")]
+mod order_history;
+mod resolver;
+mod socket;
+mod strict;
 
 use std::borrow::Cow;
 
 pub use crate::api_info::ApiInfo;
 pub use crate::client::Client;
+pub use crate::endpoint::Api;
+pub use crate::endpoint::ApiEndpoint;
+pub use crate::endpoint::ErrorMessage;
 pub use crate::error::Error;
 pub use crate::error::RequestError;
+pub use crate::socket::SocketOptions;
+
+/// The trait describing an HTTP endpoint, as used by `Client::issue`.
+///
+/// The trait is typically not implemented manually, but by means of
+/// the `Endpoint!` macro.
+pub use http_endpoint::Endpoint;
+#[doc(hidden)]
+pub use http_endpoint::EndpointDef;
 
 /// A module providing access to lower level event streaming.
 ///
 /// It is typically only in rare situations that this lower level
 /// functionality needs to be used directly.
+#[cfg(feature = "streaming")]
 pub mod event {
   pub use crate::events::stream;
+  pub use crate::events::stream_confirmed;
   pub use crate::events::stream_raw;
+  pub use crate::events::stream_raw_confirmed;
   pub use crate::events::Event;
   pub use crate::events::EventStream;
+  pub use crate::events::RawEvent;
   pub use crate::events::StreamType;
+  pub use crate::events::Subscription;
 }
 
 type Str = Cow<'static, str>;
@@ -0,0 +1,254 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::cmp::min;
+
+use num_decimal::Num;
+
+use crate::api::v2::account::Account;
+use crate::api::v2::asset::Asset;
+use crate::api::v2::order;
+use crate::api::v2::position::Position;
+
+
+/// The maintenance margin requirement for marginable assets, in
+/// percent, if none is reported for the asset.
+const DEFAULT_MAINTENANCE_MARGIN: u64 = 30;
+
+
+/// The verdict on whether an order is expected to be accepted, based
+/// on its projected effect on the account.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Verdict {
+  /// The account can afford the order.
+  Affordable,
+  /// The order requires more buying power than is available.
+  InsufficientBuyingPower {
+    /// The buying power required by the order.
+    required: Num,
+    /// The buying power available.
+    available: Num,
+  },
+  /// The maintenance margin requirement after the order was filled
+  /// would exceed the account's equity.
+  MaintenanceDeficit {
+    /// The maintenance margin requirement after the order was filled.
+    requirement: Num,
+    /// The equity of the account.
+    equity: Num,
+  },
+}
+
+
+/// The projected effect of an order on an account.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Projection {
+  /// The value of the part of the order opening or increasing a
+  /// position.
+  pub opening_value: Num,
+  /// The value of the part of the order reducing or closing a position.
+  pub closing_value: Num,
+  /// The buying power consumed by the order, net of the buying power
+  /// released by reducing a position.
+  pub buying_power_used: Num,
+  /// The buying power after the order was filled.
+  pub buying_power: Num,
+  /// The initial margin requirement after the order was filled.
+  pub initial_margin: Num,
+  /// The maintenance margin requirement after the order was filled.
+  pub maintenance_margin: Num,
+  /// The verdict on whether the order is expected to be accepted.
+  pub verdict: Verdict,
+}
+
+
+/// Project the effect of an order, filled at `price`, on the buying
+/// power and margin requirements of an account.
+///
+/// `position` is the position currently held in the order's asset, if
+/// any. The projection is an estimate following Regulation T: the
+/// initial margin requirement of marginable assets in margin accounts
+/// is the inverse of the account's multiplier, while other assets have
+/// to be paid for in full. The maintenance margin requirement is the
+/// one reported for the asset or, if none is, 30% for marginable and
+/// 100% for other assets. Equity is assumed not to change as a result
+/// of the order. Alpaca's risk checks are more involved and an order
+/// deemed affordable may still get rejected.
+pub fn project(
+  account: &Account,
+  asset: &Asset,
+  position: Option<&Position>,
+  request: &order::OrderReq,
+  price: &Num,
+) -> Projection {
+  let margin = account.multiplier > Num::from(1) && asset.marginable;
+  let initial_rate = if margin {
+    Num::from(1) / &account.multiplier
+  } else {
+    Num::from(1)
+  };
+  let maintenance_rate = match (&asset.maintenance_margin_requirement, margin) {
+    (Some(requirement), _) => requirement / Num::from(100),
+    (None, true) => Num::new(DEFAULT_MAINTENANCE_MARGIN, 100),
+    (None, false) => Num::from(1),
+  };
+
+  let current = position.map(Position::signed_quantity).unwrap_or(0);
+  let reducing = current != 0 && order::Side::from_signed(current) != request.side;
+  let closing = if reducing {
+    min(request.quantity, current.unsigned_abs())
+  } else {
+    0
+  };
+  let opening = request.quantity - closing;
+
+  let opening_value = price * Num::from(opening);
+  let closing_value = price * Num::from(closing);
+
+  // Buying power is the multiple of the equity not tied up in initial
+  // margin, so a change in initial margin affects it accordingly.
+  let used = &opening_value * &initial_rate * &account.multiplier;
+  let released = &closing_value * &initial_rate * &account.multiplier;
+  let buying_power_used = &used - &released;
+  let buying_power = &account.buying_power - &buying_power_used;
+  let initial_margin = &account.initial_margin + (&opening_value - &closing_value) * &initial_rate;
+  let maintenance_margin =
+    &account.maintenance_margin + (&opening_value - &closing_value) * &maintenance_rate;
+
+  let verdict = if opening > 0 && used > account.buying_power {
+    Verdict::InsufficientBuyingPower {
+      required: used,
+      available: account.buying_power.clone(),
+    }
+  } else if opening > 0 && maintenance_margin > account.equity {
+    Verdict::MaintenanceDeficit {
+      requirement: maintenance_margin.clone(),
+      equity: account.equity.clone(),
+    }
+  } else {
+    Verdict::Affordable
+  };
+
+  Projection {
+    opening_value,
+    closing_value,
+    buying_power_used,
+    buying_power,
+    initial_margin,
+    maintenance_margin,
+    verdict,
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use test_env_log::test;
+
+  use crate::api::v2::position;
+  use crate::test_support;
+
+
+  /// Create an asset with the given marginability and maintenance
+  /// margin requirement.
+  fn asset(marginable: bool, requirement: Option<u64>) -> Asset {
+    let mut asset = test_support::asset("AAPL");
+    asset.marginable = marginable;
+    asset.maintenance_margin_requirement = requirement.map(Num::from);
+    asset
+  }
+
+  /// Create a margin account with the given equity and no positions.
+  fn margin_account(equity: u64) -> Account {
+    let mut account = test_support::account(Num::from(equity));
+    account.multiplier = Num::from(2);
+    account.buying_power = Num::from(2 * equity);
+    account
+  }
+
+  /// Check that we project buying power and margin for opening a
+  /// position in a margin account.
+  #[test]
+  fn project_margin_purchase() {
+    let account = margin_account(10_000);
+    let request = order::OrderReqInit::default().init("AAPL", order::Side::Buy, 100);
+
+    let projection = project(
+      &account,
+      &asset(true, None),
+      None,
+      &request,
+      &Num::from(150),
+    );
+    assert_eq!(projection.opening_value, Num::from(15_000));
+    assert_eq!(projection.buying_power_used, Num::from(15_000));
+    assert_eq!(projection.buying_power, Num::from(5_000));
+    assert_eq!(projection.initial_margin, Num::from(7_500));
+    assert_eq!(projection.maintenance_margin, Num::from(4_500));
+    assert_eq!(projection.verdict, Verdict::Affordable);
+
+    // Non-marginable assets have to be paid for in full.
+    let projection = project(
+      &account,
+      &asset(false, None),
+      None,
+      &request,
+      &Num::from(150),
+    );
+    assert_eq!(projection.buying_power_used, Num::from(30_000));
+    assert_eq!(
+      projection.verdict,
+      Verdict::InsufficientBuyingPower {
+        required: Num::from(30_000),
+        available: Num::from(20_000),
+      }
+    );
+  }
+
+  /// Check that we flag orders resulting in a maintenance deficit.
+  #[test]
+  fn project_maintenance_deficit() {
+    let account = margin_account(10_000);
+    let request = order::OrderReqInit::default().init("AAPL", order::Side::Buy, 100);
+    let projection = project(
+      &account,
+      &asset(true, Some(100)),
+      None,
+      &request,
+      &Num::from(150),
+    );
+    assert_eq!(
+      projection.verdict,
+      Verdict::MaintenanceDeficit {
+        requirement: Num::from(15_000),
+        equity: Num::from(10_000),
+      }
+    );
+  }
+
+  /// Check that reducing a position releases buying power.
+  #[test]
+  fn project_position_reduction() {
+    let mut account = margin_account(10_000);
+    account.initial_margin = Num::from(5_000);
+    account.maintenance_margin = Num::from(3_000);
+    let position = test_support::position("AAPL", position::Side::Long, 100, Num::from(100));
+
+    let request = order::OrderReqInit::default().init("AAPL", order::Side::Sell, 150);
+    let projection = project(
+      &account,
+      &asset(true, None),
+      Some(&position),
+      &request,
+      &Num::from(100),
+    );
+    assert_eq!(projection.closing_value, Num::from(10_000));
+    assert_eq!(projection.opening_value, Num::from(5_000));
+    assert_eq!(projection.buying_power_used, Num::from(-5_000));
+    assert_eq!(projection.initial_margin, Num::from(2_500));
+    assert_eq!(projection.maintenance_margin, Num::from(1_500));
+    assert_eq!(projection.verdict, Verdict::Affordable);
+  }
+}
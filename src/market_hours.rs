@@ -0,0 +1,121 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::cmp::max;
+use std::time::Duration;
+
+use tokio::time::sleep;
+
+use tracing::debug;
+
+use crate::api::v2::clock;
+use crate::api::v2::clock::Clock;
+use crate::Client;
+use crate::RequestError;
+
+
+/// The minimum amount of time to wait before checking the market clock
+/// again.
+///
+/// We wake up right at the reported time of the transition, but the
+/// transition may not be reflected by the clock just yet. This value
+/// prevents us from hammering the API in that case.
+const MIN_WAIT: Duration = Duration::from_secs(1);
+
+
+/// Calculate the time to wait until the market reaches the desired
+/// state, based on the given clock.
+///
+/// The calculation is based solely on times reported by the server,
+/// meaning that it is not affected by skew of the local clock.
+fn wait_time(clock: &Clock, open: bool) -> Option<Duration> {
+  if clock.open == open {
+    None
+  } else {
+    let target = if open {
+      clock.next_open
+    } else {
+      clock.next_close
+    };
+    let wait = target
+      .duration_since(clock.current)
+      .unwrap_or_else(|_| Duration::from_secs(0));
+    Some(max(wait, MIN_WAIT))
+  }
+}
+
+
+impl Client {
+  /// Wait for the market to be in the given state.
+  async fn await_market(&self, open: bool) -> Result<Clock, RequestError<clock::GetError>> {
+    loop {
+      let clock = self.issue::<clock::Get>(()).await?;
+      match wait_time(&clock, open) {
+        None => break Ok(clock),
+        Some(wait) => {
+          debug!(wait = debug(&wait), open, "waiting for market");
+          sleep(wait).await
+        },
+      }
+    }
+  }
+
+  /// Wait for the market to open.
+  ///
+  /// The returned future resolves immediately if the market is open
+  /// already. The time to wait is derived from the market clock as
+  /// reported by Alpaca, meaning that skew of the local clock does not
+  /// matter. The clock as retrieved once the market opened is
+  /// returned.
+  pub async fn await_market_open(&self) -> Result<Clock, RequestError<clock::GetError>> {
+    self.await_market(true).await
+  }
+
+  /// Wait for the market to close.
+  ///
+  /// The returned future resolves immediately if the market is closed
+  /// already. Please refer to `await_market_open` for more details.
+  pub async fn await_market_close(&self) -> Result<Clock, RequestError<clock::GetError>> {
+    self.await_market(false).await
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde_json::from_str as from_json;
+
+
+  #[test]
+  fn calculate_wait_time() {
+    // Note that the clock reports a time that is way off from the
+    // local clock. That should not matter.
+    let response = r#"{
+  "timestamp": "2021-03-05T09:00:00.000-05:00",
+  "is_open": false,
+  "next_open": "2021-03-05T09:30:00-05:00",
+  "next_close": "2021-03-05T16:00:00-05:00"
+}"#;
+    let clock = from_json::<Clock>(response).unwrap();
+
+    assert_eq!(wait_time(&clock, true), Some(Duration::from_secs(30 * 60)));
+    assert_eq!(wait_time(&clock, false), None);
+  }
+
+  #[test]
+  fn calculate_minimum_wait_time() {
+    // The market should have opened according to the reported times,
+    // but the clock does not reflect that yet.
+    let response = r#"{
+  "timestamp": "2021-03-05T09:30:00.100-05:00",
+  "is_open": false,
+  "next_open": "2021-03-05T09:30:00-05:00",
+  "next_close": "2021-03-05T16:00:00-05:00"
+}"#;
+    let clock = from_json::<Clock>(response).unwrap();
+
+    assert_eq!(wait_time(&clock, true), Some(MIN_WAIT));
+  }
+}
@@ -0,0 +1,416 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::error::Error as StdError;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use futures::future::join_all;
+use futures::future::try_join_all;
+
+use num_decimal::Num;
+
+use crate::api::v2::account;
+use crate::api::v2::order;
+use crate::api::v2::orders;
+use crate::api::v2::position;
+use crate::api::v2::positions;
+use crate::ApiInfo;
+use crate::Client;
+use crate::RequestError;
+
+
+/// An error reported for a request issued on behalf of one of the
+/// accounts of a `MultiClient`.
+#[derive(Debug)]
+pub struct AccountError<E> {
+  /// The label of the account the request failed for.
+  pub label: String,
+  /// The error reported by the request.
+  pub error: RequestError<E>,
+}
+
+impl<E> Display for AccountError<E>
+where
+  E: Display,
+{
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    write!(
+      fmt,
+      "request for account {} failed: {}",
+      self.label, self.error
+    )
+  }
+}
+
+impl<E> StdError for AccountError<E>
+where
+  E: StdError + 'static,
+{
+  fn source(&self) -> Option<&(dyn StdError + 'static)> {
+    Some(&self.error)
+  }
+}
+
+
+/// The combined position in an asset across all accounts.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Holding {
+  /// The symbol of the asset.
+  pub symbol: String,
+  /// The net quantity held, negative if short overall.
+  pub quantity: i64,
+  /// The combined market value of the positions.
+  pub market_value: Num,
+  /// The combined cost basis of the positions.
+  pub cost_basis: Num,
+  /// The combined unrealized profit or loss of the positions.
+  pub unrealized_gain_total: Num,
+  /// The individual positions, along with the label of the account
+  /// holding them.
+  pub positions: Vec<(String, position::Position)>,
+}
+
+
+/// The profit or loss of an account since the previous trading day.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountPnl {
+  /// The label of the account.
+  pub label: String,
+  /// The current equity of the account.
+  pub equity: Num,
+  /// The equity as of the end of the previous trading day.
+  pub last_equity: Num,
+}
+
+impl AccountPnl {
+  /// Retrieve the change in equity since the previous trading day.
+  pub fn change(&self) -> Num {
+    &self.equity - &self.last_equity
+  }
+}
+
+
+/// The profit or loss across all accounts since the previous trading
+/// day.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pnl {
+  /// The profit or loss of the individual accounts.
+  pub accounts: Vec<AccountPnl>,
+  /// The combined current equity.
+  pub equity: Num,
+  /// The combined equity as of the end of the previous trading day.
+  pub last_equity: Num,
+}
+
+impl Pnl {
+  /// Retrieve the combined change in equity since the previous trading
+  /// day.
+  pub fn change(&self) -> Num {
+    &self.equity - &self.last_equity
+  }
+}
+
+
+/// Combine the positions of several accounts into holdings, ordered by
+/// symbol.
+fn aggregate(positions: Vec<(String, Vec<position::Position>)>) -> Vec<Holding> {
+  let mut holdings = Vec::<Holding>::new();
+
+  for (label, positions) in positions {
+    for position in positions {
+      let quantity = position.signed_quantity();
+
+      match holdings
+        .iter_mut()
+        .find(|holding| holding.symbol == position.symbol)
+      {
+        Some(holding) => {
+          holding.quantity += quantity;
+          holding.market_value += &position.market_value;
+          holding.cost_basis += &position.cost_basis;
+          holding.unrealized_gain_total += &position.unrealized_gain_total;
+          holding.positions.push((label.clone(), position));
+        },
+        None => holdings.push(Holding {
+          symbol: position.symbol.clone(),
+          quantity,
+          market_value: position.market_value.clone(),
+          cost_basis: position.cost_basis.clone(),
+          unrealized_gain_total: position.unrealized_gain_total.clone(),
+          positions: vec![(label.clone(), position)],
+        }),
+      }
+    }
+  }
+
+  holdings.sort_by(|lhs, rhs| lhs.symbol.cmp(&rhs.symbol));
+  holdings
+}
+
+
+/// A set of clients for working with several accounts at once, e.g.,
+/// paper and live accounts or multiple sub-accounts.
+///
+/// Each account is identified by a label. Requests are issued to all
+/// accounts concurrently and the results combined into aggregated
+/// views, with the individual results remaining available alongside.
+///
+/// ```no_run
+/// # use apca::api::v2::order;
+/// # use apca::multi::MultiClient;
+/// # use apca::ApiInfo;
+/// # use apca::Client;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = Client::new(ApiInfo::from_env()?);
+/// let multi = MultiClient::from_api_infos(
+///   &client,
+///   vec![
+///     ("first", ApiInfo::from_env_prefixed("FIRST")?),
+///     ("second", ApiInfo::from_env_prefixed("SECOND")?),
+///   ],
+/// );
+/// let pnl = multi.pnl().await?;
+/// println!("combined change: {}", pnl.change());
+///
+/// let request = order::OrderReqInit::default().init("AAPL", order::Side::Buy, 1);
+/// for (label, result) in multi.submit_all(&request).await {
+///   println!("{}: {:?}", label, result.map(|order| order.id));
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MultiClient {
+  /// The clients, along with the labels of their accounts.
+  clients: Vec<(String, Client)>,
+}
+
+impl MultiClient {
+  /// Create a new `MultiClient` without any accounts.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Create a `MultiClient` for the given credential sets, each paired
+  /// with a label.
+  ///
+  /// All clients are derived from `client` via `Client::with_api_info`
+  /// and share its connection pool and settings.
+  pub fn from_api_infos<I, S>(client: &Client, api_infos: I) -> Self
+  where
+    I: IntoIterator<Item = (S, ApiInfo)>,
+    S: Into<String>,
+  {
+    let mut multi = Self::new();
+    for (label, api_info) in api_infos {
+      let _ = multi.insert(label, client.with_api_info(api_info));
+    }
+    multi
+  }
+
+  /// Add a client for the account with the given label.
+  ///
+  /// If a client for the label existed already, it is replaced and
+  /// returned.
+  pub fn insert<S>(&mut self, label: S, client: Client) -> Option<Client>
+  where
+    S: Into<String>,
+  {
+    let label = label.into();
+    match self.clients.iter_mut().find(|(other, _)| *other == label) {
+      Some((_, existing)) => Some(std::mem::replace(existing, client)),
+      None => {
+        self.clients.push((label, client));
+        None
+      },
+    }
+  }
+
+  /// Retrieve the client for the account with the given label.
+  pub fn client(&self, label: &str) -> Option<&Client> {
+    self
+      .clients
+      .iter()
+      .find(|(other, _)| other == label)
+      .map(|(_, client)| client)
+  }
+
+  /// Retrieve an iterator over all account labels, in the order in
+  /// which the accounts were added.
+  pub fn labels(&self) -> impl Iterator<Item = &str> {
+    self.clients.iter().map(|(label, _)| label.as_str())
+  }
+
+  /// Retrieve all accounts.
+  pub async fn accounts(
+    &self,
+  ) -> Result<Vec<(String, account::Account)>, AccountError<account::GetError>> {
+    try_join_all(self.clients.iter().map(|(label, client)| async move {
+      client
+        .issue::<account::Get>(())
+        .await
+        .map(|account| (label.clone(), account))
+        .map_err(|error| AccountError {
+          label: label.clone(),
+          error,
+        })
+    }))
+    .await
+  }
+
+  /// Retrieve the profit or loss since the previous trading day, per
+  /// account and combined.
+  pub async fn pnl(&self) -> Result<Pnl, AccountError<account::GetError>> {
+    let mut pnl = Pnl {
+      accounts: Vec::new(),
+      equity: Num::from(0),
+      last_equity: Num::from(0),
+    };
+
+    for (label, account) in self.accounts().await? {
+      pnl.equity += &account.equity;
+      pnl.last_equity += &account.last_equity;
+      pnl.accounts.push(AccountPnl {
+        label,
+        equity: account.equity,
+        last_equity: account.last_equity,
+      });
+    }
+    Ok(pnl)
+  }
+
+  /// Retrieve the open positions of all accounts, combined per asset
+  /// and ordered by symbol.
+  pub async fn positions(&self) -> Result<Vec<Holding>, AccountError<positions::GetError>> {
+    let positions = try_join_all(self.clients.iter().map(|(label, client)| async move {
+      client
+        .issue::<positions::Get>(())
+        .await
+        .map(|positions| (label.clone(), positions))
+        .map_err(|error| AccountError {
+          label: label.clone(),
+          error,
+        })
+    }))
+    .await?;
+
+    Ok(aggregate(positions))
+  }
+
+  /// Retrieve the open orders of all accounts, along with the label of
+  /// the account each was submitted for.
+  pub async fn open_orders(
+    &self,
+  ) -> Result<Vec<(String, order::Order)>, AccountError<orders::GetError>> {
+    let orders = try_join_all(self.clients.iter().map(|(label, client)| async move {
+      client
+        .all_orders(orders::Status::Open, false)
+        .await
+        .map(|orders| {
+          orders
+            .into_iter()
+            .map(|order| (label.clone(), order))
+            .collect::<Vec<_>>()
+        })
+        .map_err(|error| AccountError {
+          label: label.clone(),
+          error,
+        })
+    }))
+    .await?;
+
+    Ok(orders.into_iter().flatten().collect())
+  }
+
+  /// Submit the same order for all accounts.
+  ///
+  /// Submissions happen concurrently and independently of each other,
+  /// i.e., a failure for one account does not prevent the order from
+  /// being submitted for the others. The result for each account is
+  /// reported along with its label.
+  pub async fn submit_all(
+    &self,
+    request: &order::OrderReq,
+  ) -> Vec<(String, Result<order::Order, RequestError<order::PostError>>)> {
+    join_all(self.clients.iter().map(|(label, client)| {
+      let request = request.clone();
+      async move { (label.clone(), client.issue::<order::Post>(request).await) }
+    }))
+    .await
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::sync::Arc;
+
+  use test_env_log::test;
+
+  use crate::simulator::Simulator;
+  use crate::test_support;
+
+
+  /// Check that we combine positions per asset.
+  #[test]
+  fn aggregate_positions() {
+    let first = vec![
+      test_support::position("SPY", position::Side::Long, 10, Num::from(100)),
+      test_support::position("AAPL", position::Side::Long, 5, Num::from(150)),
+    ];
+    let second = vec![test_support::position(
+      "SPY",
+      position::Side::Short,
+      4,
+      Num::from(100),
+    )];
+
+    let holdings = aggregate(vec![
+      ("first".to_string(), first),
+      ("second".to_string(), second),
+    ]);
+    assert_eq!(holdings.len(), 2);
+    assert_eq!(holdings[0].symbol, "AAPL");
+    assert_eq!(holdings[0].quantity, 5);
+    assert_eq!(holdings[1].symbol, "SPY");
+    assert_eq!(holdings[1].quantity, 6);
+    assert_eq!(holdings[1].positions.len(), 2);
+    assert_eq!(holdings[1].positions[1].0, "second");
+  }
+
+  /// Check that we can submit orders to and aggregate the state of
+  /// multiple accounts.
+  #[test(tokio::test)]
+  async fn multiple_accounts() {
+    let prices = |_: &str| Some(Num::from(100));
+    let first = Client::simulated(Arc::new(Simulator::new(Num::from(10_000), prices)));
+    let second = Client::simulated(Arc::new(Simulator::new(Num::from(5_000), prices)));
+
+    let mut multi = MultiClient::new();
+    assert!(multi.insert("first", first).is_none());
+    assert!(multi.insert("second", second).is_none());
+    assert_eq!(multi.labels().collect::<Vec<_>>(), vec!["first", "second"]);
+
+    let request = order::OrderReqInit::default().init("SPY", order::Side::Buy, 10);
+    let results = multi.submit_all(&request).await;
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|(_, result)| result.is_ok()));
+
+    let holdings = multi.positions().await.unwrap();
+    assert_eq!(holdings.len(), 1);
+    assert_eq!(holdings[0].quantity, 20);
+    assert_eq!(holdings[0].market_value, Num::from(2_000));
+
+    let pnl = multi.pnl().await.unwrap();
+    assert_eq!(pnl.accounts.len(), 2);
+    assert_eq!(pnl.equity, Num::from(15_000));
+    assert_eq!(pnl.change(), Num::from(0));
+
+    assert!(multi.open_orders().await.unwrap().is_empty());
+  }
+}
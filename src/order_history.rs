@@ -0,0 +1,237 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use futures::stream::unfold;
+use futures::stream::Stream;
+use futures::TryStreamExt as _;
+
+use tracing::debug;
+
+use crate::api::v2::order;
+use crate::api::v2::orders;
+use crate::Client;
+use crate::RequestError;
+
+
+/// Retrieve the time the given order was submitted at, as used for
+/// filtering by the /v2/orders endpoint.
+fn submitted_at(order: &order::Order) -> SystemTime {
+  order.submitted_at.unwrap_or(order.created_at)
+}
+
+
+/// The state of an order history traversal.
+#[derive(Debug)]
+struct History {
+  /// The time after which to request the next page.
+  after: SystemTime,
+  /// The IDs of the orders reported already that were submitted at
+  /// `boundary`.
+  seen: HashSet<order::Id>,
+  /// The submission time of the most recent order reported.
+  boundary: Option<SystemTime>,
+  /// The orders retrieved but not yet reported.
+  buffer: VecDeque<order::Order>,
+  /// Whether the last page has been retrieved.
+  done: bool,
+}
+
+impl History {
+  /// Create a traversal starting after the given time.
+  fn new(after: SystemTime) -> Self {
+    Self {
+      after,
+      seen: HashSet::new(),
+      boundary: None,
+      buffer: VecDeque::new(),
+      done: false,
+    }
+  }
+
+  /// Process a page of orders, in ascending order of submission.
+  fn advance(&mut self, orders: Vec<order::Order>) {
+    self.done = (orders.len() as u64) < orders::MAX_LIMIT;
+    let first = orders.first().map(submitted_at);
+
+    for order in orders {
+      // Pages overlap at their boundary, so we have to skip orders we
+      // reported as part of the previous one.
+      let time = submitted_at(&order);
+      if Some(time) == self.boundary && self.seen.contains(&order.id) {
+        continue
+      }
+      if Some(time) != self.boundary {
+        self.boundary = Some(time);
+        self.seen.clear();
+      }
+      let _ = self.seen.insert(order.id);
+      self.buffer.push_back(order);
+    }
+
+    if let Some(boundary) = self.boundary {
+      // The `after` parameter is exclusive. To not miss orders that
+      // were submitted at the same time as the last one reported but
+      // did not fit on the page, we start the next page just before
+      // it. If the page consisted solely of orders submitted at the
+      // same time, that would result in retrieving the same page over
+      // and over again, though, and we have no choice but to move on.
+      self.after = if first == Some(boundary) {
+        boundary
+      } else {
+        boundary - Duration::from_nanos(1)
+      };
+    }
+  }
+}
+
+
+impl Client {
+  /// Retrieve all orders with the given status submitted in the time
+  /// window between `start` and `end` (both exclusive), oldest first.
+  ///
+  /// The /v2/orders endpoint reports at most [`orders::MAX_LIMIT`]
+  /// orders per request.
+  /// The returned stream transparently issues as many requests as
+  /// necessary for covering the entire window, each one only once the
+  /// orders retrieved by the previous one have been consumed. The
+  /// stream ends after the last order or after the first error, which
+  /// is reported as well. Note that more than `MAX_LIMIT` orders
+  /// sharing the very same submission time cannot be told apart and
+  /// only the first `MAX_LIMIT` of them are reported.
+  pub fn order_history(
+    &self,
+    status: orders::Status,
+    start: SystemTime,
+    end: SystemTime,
+  ) -> impl Stream<Item = Result<order::Order, RequestError<orders::GetError>>> + '_ {
+    self.orders_between(status, true, start, Some(end))
+  }
+
+  /// Retrieve all orders with the given status, oldest first.
+  ///
+  /// A single request to the /v2/orders endpoint reports at most
+  /// [`orders::MAX_LIMIT`] orders. This method issues as many requests
+  /// as necessary for retrieving all of them, with the same caveat as
+  /// [`Client::order_history`]. `nested` controls whether the legs of
+  /// multi-leg orders are rolled up under the primary order instead
+  /// of being reported as orders of their own.
+  pub async fn all_orders(
+    &self,
+    status: orders::Status,
+    nested: bool,
+  ) -> Result<Vec<order::Order>, RequestError<orders::GetError>> {
+    self
+      .orders_between(status, nested, UNIX_EPOCH, None)
+      .try_collect()
+      .await
+  }
+
+  /// Retrieve all orders with the given status submitted after `start`
+  /// and, if provided, before `end`, oldest first.
+  fn orders_between(
+    &self,
+    status: orders::Status,
+    nested: bool,
+    start: SystemTime,
+    end: Option<SystemTime>,
+  ) -> impl Stream<Item = Result<order::Order, RequestError<orders::GetError>>> + '_ {
+    unfold(Some(History::new(start)), move |history| async move {
+      let mut history = history?;
+      loop {
+        if let Some(order) = history.buffer.pop_front() {
+          break Some((Ok(order), Some(history)))
+        }
+        if history.done {
+          break None
+        }
+
+        let request = orders::OrdersReq {
+          status,
+          limit: orders::MAX_LIMIT,
+          nested,
+          after: Some(history.after),
+          until: end,
+          direction: Some(orders::Direction::Ascending),
+        };
+        debug!(
+          after = debug(&history.after),
+          "retrieving order history page"
+        );
+        match self.issue::<orders::Get>(request).await {
+          Ok(orders) => history.advance(orders),
+          Err(err) => break Some((Err(err), None)),
+        }
+      }
+    })
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use test_env_log::test;
+
+  use crate::test_support;
+
+
+  /// Create an order submitted at the given number of seconds since
+  /// the epoch.
+  fn order(secs: u64) -> order::Order {
+    let mut order = test_support::order("SPY", order::Side::Buy, 1);
+    order.submitted_at = Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+    order
+  }
+
+  /// Check that we skip orders reported already when pages overlap.
+  #[test]
+  fn advance_over_overlapping_pages() {
+    let mut history = History::new(SystemTime::UNIX_EPOCH);
+    let mut page = (0..orders::MAX_LIMIT - 2)
+      .map(|_| order(1))
+      .collect::<Vec<_>>();
+    let last = vec![order(2), order(2)];
+    page.extend(last.clone());
+
+    let () = history.advance(page);
+    assert!(!history.done);
+    assert_eq!(history.buffer.len(), orders::MAX_LIMIT as usize);
+    assert_eq!(
+      history.after,
+      SystemTime::UNIX_EPOCH + Duration::from_secs(2) - Duration::from_nanos(1)
+    );
+    history.buffer.clear();
+
+    let next = vec![last[0].clone(), last[1].clone(), order(2), order(3)];
+    let ids = next[2..].iter().map(|order| order.id).collect::<Vec<_>>();
+    let () = history.advance(next);
+    assert!(history.done);
+    let reported = history
+      .buffer
+      .iter()
+      .map(|order| order.id)
+      .collect::<Vec<_>>();
+    assert_eq!(reported, ids);
+  }
+
+  /// Check that we make progress if a page comprises only orders
+  /// submitted at the same time.
+  #[test]
+  fn advance_over_uniform_page() {
+    let mut history = History::new(SystemTime::UNIX_EPOCH);
+    let page = (0..orders::MAX_LIMIT).map(|_| order(5)).collect::<Vec<_>>();
+    let () = history.advance(page);
+    assert!(!history.done);
+    assert_eq!(
+      history.after,
+      SystemTime::UNIX_EPOCH + Duration::from_secs(5)
+    );
+  }
+}
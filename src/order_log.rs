@@ -0,0 +1,317 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use futures::stream::Stream;
+use futures::StreamExt;
+
+use serde_json::Error as JsonError;
+
+use tracing::info;
+use tracing::warn;
+
+use websocket_util::tungstenite::Error as WebSocketError;
+
+use crate::api::v2::events::TradeUpdate;
+use crate::api::v2::order;
+use crate::Client;
+use crate::RequestError;
+
+
+/// The maximum number of orders in a terminal state we remember, in
+/// order to ignore late reports about them.
+const MAX_TERMINATED: usize = 1024;
+
+
+/// The state of an order as last logged.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct State {
+  /// The status of the order.
+  status: order::Status,
+  /// The quantity filled.
+  filled_quantity: u64,
+}
+
+impl From<&order::Order> for State {
+  fn from(order: &order::Order) -> Self {
+    Self {
+      status: order.status,
+      filled_quantity: order.filled_quantity,
+    }
+  }
+}
+
+
+/// A logger of the lifecycle of orders.
+///
+/// A `LifecycleLog` emits a single `tracing` event at `INFO` level for
+/// every transition of an order, i.e., whenever its status or filled
+/// quantity changed, from its submission up to it reaching a terminal
+/// state. Transitions may be observed on the trade updates stream or
+/// reported manually, e.g., for orders retrieved by polling. All
+/// events carry the same set of fields, making it possible to follow
+/// an order by filtering on its `order_id` or `client_order_id`:
+///
+/// - `order_id`, `client_order_id`, `symbol`, `side`, and `qty`
+///   describing the order
+/// - `from` and `to`, the previous (if known) and new status
+/// - `filled_qty` and `filled_avg_price`
+/// - `source`, the means by which the transition got observed
+///   (`submit`, `trade_update`, or `manual`)
+///
+/// Orders are forgotten once they reached a terminal state, except for
+/// their IDs: reports about the most recently terminated orders, e.g.,
+/// the response to the submission of an order that got filled before
+/// the response arrived, are ignored instead of being logged as a
+/// transition backwards. For the same reason, reports of a smaller
+/// filled quantity than logged already are ignored.
+///
+/// ```no_run
+/// # use futures::StreamExt;
+/// # use apca::api::v2::events;
+/// # use apca::api::v2::order;
+/// # use apca::order_log::LifecycleLog;
+/// # use apca::ApiInfo;
+/// # use apca::Client;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = Client::new(ApiInfo::from_env()?);
+/// let log = LifecycleLog::new();
+/// let updates = client.subscribe::<events::TradeUpdates>().await?;
+/// let mut updates = Box::pin(log.observe(updates));
+///
+/// let request = order::OrderReqInit::default().init("AAPL", order::Side::Buy, 1);
+/// let order = log.submit(&client, request).await?;
+///
+/// while let Some(update) = updates.next().await {
+///   // All transitions of the order are logged by now.
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct LifecycleLog {
+  /// The state of the orders logged.
+  orders: Mutex<Orders>,
+}
+
+
+/// The state of the orders logged by a `LifecycleLog`.
+#[derive(Debug, Default)]
+struct Orders {
+  /// The state of all orders not yet in a terminal state, as last
+  /// logged.
+  states: HashMap<order::Id, State>,
+  /// The IDs of the most recently terminated orders, oldest first.
+  terminated: VecDeque<order::Id>,
+  /// The IDs contained in `terminated`, for quick lookup.
+  terminated_ids: HashSet<order::Id>,
+}
+
+impl Orders {
+  /// Remember that the order with the given ID reached a terminal
+  /// state.
+  fn terminate(&mut self, id: order::Id) {
+    if self.terminated_ids.insert(id) {
+      self.terminated.push_back(id);
+      if self.terminated.len() > MAX_TERMINATED {
+        if let Some(id) = self.terminated.pop_front() {
+          let _ = self.terminated_ids.remove(&id);
+        }
+      }
+    }
+  }
+}
+
+impl LifecycleLog {
+  /// Create a new `LifecycleLog`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Log the given order's transition, if it changed since it was last
+  /// logged.
+  fn log(&self, source: &str, order: &order::Order) -> bool {
+    let state = State::from(order);
+    let from = {
+      let mut orders = self.orders.lock().unwrap();
+      if orders.terminated_ids.contains(&order.id) {
+        // The order reached a terminal state already and we are looking
+        // at a stale report.
+        return false
+      }
+
+      let from = orders.states.get(&order.id).copied();
+      if let Some(from) = from {
+        if from == state || state.filled_quantity < from.filled_quantity {
+          return false
+        }
+      }
+
+      if order.status.is_terminal() {
+        let _ = orders.states.remove(&order.id);
+        let () = orders.terminate(order.id);
+      } else {
+        let _ = orders.states.insert(order.id, state);
+      }
+      from
+    };
+
+    info!(
+      order_id = display(&order.id),
+      client_order_id = display(&order.client_order_id),
+      symbol = display(&order.symbol),
+      side = debug(order.side),
+      qty = order.quantity,
+      from = debug(from.map(|from| from.status)),
+      to = debug(order.status),
+      filled_qty = order.filled_quantity,
+      filled_avg_price = debug(&order.average_fill_price),
+      source,
+      "order transition"
+    );
+    true
+  }
+
+  /// Submit an order, logging the submission and its outcome.
+  pub async fn submit(
+    &self,
+    client: &Client,
+    request: order::OrderReq,
+  ) -> Result<order::Order, RequestError<order::PostError>> {
+    let result = client.issue::<order::Post>(request.clone()).await;
+    match &result {
+      Ok(order) => {
+        let _ = self.log("submit", order);
+      },
+      Err(err) => warn!(
+        client_order_id = debug(&request.client_order_id),
+        symbol = display(&request.symbol),
+        side = debug(request.side),
+        qty = request.quantity,
+        error = display(err),
+        source = "submit",
+        "order submission failed"
+      ),
+    }
+    result
+  }
+
+  /// Log the transition of an order retrieved by other means, e.g.,
+  /// by polling the order.
+  ///
+  /// The return value indicates whether the order changed since it
+  /// was last logged and a transition was logged.
+  pub fn record(&self, order: &order::Order) -> bool {
+    self.log("manual", order)
+  }
+
+  /// Log the transition reported by a trade update.
+  ///
+  /// The return value indicates whether the order changed since it
+  /// was last logged and a transition was logged.
+  pub fn update(&self, update: &TradeUpdate) -> bool {
+    self.log("trade_update", &update.order)
+  }
+
+  /// Log the transitions reported by a stream of trade updates, as
+  /// retrieved via `Client::subscribe`, passing all items through
+  /// unchanged.
+  pub fn observe<'l, S>(
+    &'l self,
+    updates: S,
+  ) -> impl Stream<Item = Result<Result<TradeUpdate, JsonError>, WebSocketError>> + 'l
+  where
+    S: Stream<Item = Result<Result<TradeUpdate, JsonError>, WebSocketError>> + 'l,
+  {
+    updates.inspect(move |result| {
+      if let Ok(Ok(update)) = result {
+        let _ = self.update(update);
+      }
+    })
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use futures::stream::iter;
+
+  use test_env_log::test;
+
+  use crate::api::v2::events::TradeStatus;
+  use crate::test_support;
+
+
+  /// Check that we only log actual transitions and forget orders in a
+  /// terminal state.
+  #[test]
+  fn log_transitions() {
+    let log = LifecycleLog::new();
+    let mut order = test_support::order("SPY", order::Side::Buy, 10);
+
+    assert!(log.record(&order));
+    assert!(!log.record(&order));
+
+    order.status = order::Status::PartiallyFilled;
+    order.filled_quantity = 4;
+    assert!(log.record(&order));
+    order.filled_quantity = 6;
+    assert!(log.record(&order));
+
+    order.status = order::Status::Filled;
+    order.filled_quantity = 10;
+    assert!(log.record(&order));
+    assert!(log.orders.lock().unwrap().states.is_empty());
+  }
+
+  /// Check that we ignore reports about orders that reached a terminal
+  /// state already.
+  #[test]
+  fn ignore_stale_reports() {
+    let log = LifecycleLog::new();
+    let accepted = test_support::order("SPY", order::Side::Buy, 10);
+    let mut filled = accepted.clone();
+    filled.status = order::Status::Filled;
+    filled.filled_quantity = 10;
+
+    // The fill gets reported before the response to the submission.
+    assert!(log.record(&filled));
+    assert!(!log.record(&accepted));
+    assert!(log.orders.lock().unwrap().states.is_empty());
+
+    let mut partial = test_support::order("AAPL", order::Side::Buy, 10);
+    partial.status = order::Status::PartiallyFilled;
+    partial.filled_quantity = 5;
+    let mut new = partial.clone();
+    new.status = order::Status::New;
+    new.filled_quantity = 0;
+    assert!(log.record(&partial));
+    assert!(!log.record(&new));
+  }
+
+  /// Check that we log trade updates while passing them through.
+  #[test(tokio::test)]
+  async fn observe_updates() {
+    let log = LifecycleLog::new();
+    let order = test_support::order("SPY", order::Side::Buy, 10);
+    let update = test_support::trade_update(TradeStatus::New, order);
+    let id = update.order.id;
+
+    let updates = vec![Ok(Ok(update.clone())), Ok(Ok(update))];
+    let updates = log
+      .observe(iter(updates))
+      .map(|result| result.unwrap().unwrap())
+      .collect::<Vec<_>>()
+      .await;
+
+    assert_eq!(updates.len(), 2);
+    assert!(log.orders.lock().unwrap().states.contains_key(&id));
+  }
+}
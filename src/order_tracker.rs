@@ -0,0 +1,554 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::task::Poll;
+
+use futures::stream::poll_fn;
+use futures::stream::Stream;
+use futures::task::AtomicWaker;
+use futures::StreamExt;
+
+use serde_json::Error as JsonError;
+
+use websocket_util::tungstenite::Error as WebSocketError;
+
+use crate::api::v2::events::TradeStatus;
+use crate::api::v2::events::TradeUpdate;
+use crate::api::v2::order;
+use crate::Client;
+use crate::RequestError;
+
+
+/// The maximum number of orders not (yet) tracked for which we retain
+/// the most recent trade update.
+const MAX_PENDING: usize = 256;
+
+
+/// Check whether `order` reflects a more recent state than `other`,
+/// both being states of the same order.
+///
+/// A terminal state is always considered more recent than a
+/// non-terminal one. Otherwise the time of the last update decides,
+/// if reported for both, and the filled quantity if not.
+fn is_newer(order: &order::Order, other: &order::Order) -> bool {
+  match (order.status.is_terminal(), other.status.is_terminal()) {
+    (true, false) => return true,
+    (false, true) => return false,
+    _ => (),
+  }
+
+  match (order.updated_at, other.updated_at) {
+    (Some(time), Some(other_time)) if time != other_time => time > other_time,
+    _ => order.filled_quantity > other.filled_quantity,
+  }
+}
+
+
+/// A notification about a change in the lifecycle of a tracked order.
+///
+/// Each variant contains the order as it was reported as part of the
+/// corresponding trade update.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Notification {
+  /// The order was accepted and routed for execution.
+  Accepted(order::Order),
+  /// The order was partially filled.
+  PartiallyFilled(order::Order),
+  /// The order was filled completely.
+  Filled(order::Order),
+  /// The order was rejected.
+  Rejected(order::Order),
+  /// The order was canceled.
+  Canceled(order::Order),
+  /// The order expired.
+  Expired(order::Order),
+}
+
+impl Notification {
+  /// Create a `Notification` for the given trade update, if the update
+  /// represents a lifecycle change we report.
+  fn from_update(update: &TradeUpdate) -> Option<Self> {
+    let order = update.order.clone();
+    match update.event {
+      TradeStatus::New => Some(Self::Accepted(order)),
+      TradeStatus::PartialFill => Some(Self::PartiallyFilled(order)),
+      TradeStatus::Filled => Some(Self::Filled(order)),
+      TradeStatus::Rejected => Some(Self::Rejected(order)),
+      TradeStatus::Canceled => Some(Self::Canceled(order)),
+      TradeStatus::Expired => Some(Self::Expired(order)),
+      _ => None,
+    }
+  }
+
+  /// Retrieve the order the notification is about.
+  pub fn order(&self) -> &order::Order {
+    match self {
+      Self::Accepted(order)
+      | Self::PartiallyFilled(order)
+      | Self::Filled(order)
+      | Self::Rejected(order)
+      | Self::Canceled(order)
+      | Self::Expired(order) => order,
+    }
+  }
+}
+
+
+/// A tracker of the state of orders.
+///
+/// An `OrderTracker` keeps an in-memory map from order ID to the most
+/// recent state of the order, including its status, filled quantity,
+/// and average fill price. Orders are added to the tracker when they
+/// are submitted through it and updated based on the events reported
+/// through the trade updates stream.
+///
+/// ```no_run
+/// # use futures::StreamExt;
+/// # use apca::api::v2::events;
+/// # use apca::api::v2::order;
+/// # use apca::order_tracker::OrderTracker;
+/// # use apca::ApiInfo;
+/// # use apca::Client;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = Client::new(ApiInfo::from_env()?);
+/// let tracker = OrderTracker::new();
+/// let updates = client.subscribe::<events::TradeUpdates>().await?;
+/// let notifications = tracker.track(updates);
+///
+/// let request = order::OrderReqInit::default().init("AAPL", order::Side::Buy, 1);
+/// let order = tracker.submit(&client, request).await?;
+///
+/// let mut notifications = Box::pin(notifications);
+/// while let Some(notification) = notifications.next().await {
+///   println!("{:?}", notification??);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct OrderTracker {
+  /// The tracked orders and the pending updates.
+  orders: Mutex<Orders>,
+  /// The waker of the task consuming the stream returned by `track`,
+  /// to be woken once deferred notifications became ready.
+  waker: AtomicWaker,
+}
+
+
+/// The state of an order not (yet) tracked, along with the
+/// notifications for the trade updates received for it.
+#[derive(Debug)]
+struct Deferred {
+  /// The most recent state of the order.
+  order: order::Order,
+  /// The notifications for the lifecycle changes reported so far,
+  /// oldest first.
+  notifications: Vec<Notification>,
+}
+
+
+/// The state of the orders known to an `OrderTracker`.
+#[derive(Debug, Default)]
+struct Orders {
+  /// The tracked orders.
+  tracked: HashMap<order::Id, order::Order>,
+  /// The state of orders not (yet) tracked, as reported by trade
+  /// updates, least recently updated first.
+  pending: VecDeque<Deferred>,
+  /// Notifications of deferred trade updates for orders that got
+  /// inserted in the meantime, ready to be reported.
+  ready: VecDeque<Notification>,
+}
+
+impl Orders {
+  /// Remember a trade update for an order not (yet) tracked.
+  fn defer(&mut self, update: &TradeUpdate) {
+    let mut deferred = match self.take_pending(update.order.id) {
+      Some(mut deferred) => {
+        if !is_newer(&deferred.order, &update.order) {
+          deferred.order = update.order.clone();
+        }
+        deferred
+      },
+      None => Deferred {
+        order: update.order.clone(),
+        notifications: Vec::new(),
+      },
+    };
+
+    deferred
+      .notifications
+      .extend(Notification::from_update(update));
+
+    self.pending.push_back(deferred);
+    if self.pending.len() > MAX_PENDING {
+      let _ = self.pending.pop_front();
+    }
+  }
+
+  /// Remove and return the deferred state of the order with the given
+  /// ID, if any.
+  fn take_pending(&mut self, id: order::Id) -> Option<Deferred> {
+    let index = self
+      .pending
+      .iter()
+      .position(|deferred| deferred.order.id == id)?;
+    self.pending.remove(index)
+  }
+}
+
+impl OrderTracker {
+  /// Create a new `OrderTracker` not yet tracking any orders.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Submit an order and start tracking it.
+  ///
+  /// The most recent state of the order is returned, which may already
+  /// reflect trade updates received while the submission was in
+  /// flight.
+  pub async fn submit(
+    &self,
+    client: &Client,
+    request: order::OrderReq,
+  ) -> Result<order::Order, RequestError<order::PostError>> {
+    let order = client.issue::<order::Post>(request).await?;
+    let id = order.id;
+    self.insert(order.clone());
+    Ok(self.get(id).unwrap_or(order))
+  }
+
+  /// Start tracking an order that was submitted by other means.
+  ///
+  /// If a more recent state of the order is known already, e.g.,
+  /// because a trade update for it arrived before the response to its
+  /// submission, that state is kept. Notifications for such deferred
+  /// trade updates that are not older than the inserted state are
+  /// reported through the stream returned by `track`.
+  pub fn insert(&self, order: order::Order) {
+    let mut orders = self.orders.lock().unwrap();
+    let (order, notifications) = match orders.take_pending(order.id) {
+      Some(deferred) => {
+        let notifications = deferred
+          .notifications
+          .into_iter()
+          .filter(|notification| !is_newer(&order, notification.order()))
+          .collect();
+        if is_newer(&deferred.order, &order) {
+          (deferred.order, notifications)
+        } else {
+          (order, notifications)
+        }
+      },
+      None => (order, Vec::new()),
+    };
+
+    match orders.tracked.entry(order.id) {
+      Entry::Occupied(mut entry) => {
+        if is_newer(&order, entry.get()) {
+          let _ = entry.insert(order);
+        }
+      },
+      Entry::Vacant(entry) => {
+        let _ = entry.insert(order);
+      },
+    }
+
+    if !notifications.is_empty() {
+      orders.ready.extend(notifications);
+      let () = self.waker.wake();
+    }
+  }
+
+  /// Stop tracking the order with the given ID, returning its most
+  /// recent state.
+  pub fn remove(&self, id: order::Id) -> Option<order::Order> {
+    self.orders.lock().unwrap().tracked.remove(&id)
+  }
+
+  /// Retrieve the most recent state of the order with the given ID.
+  pub fn get(&self, id: order::Id) -> Option<order::Order> {
+    self.orders.lock().unwrap().tracked.get(&id).cloned()
+  }
+
+  /// Retrieve the most recent state of all tracked orders.
+  pub fn orders(&self) -> Vec<order::Order> {
+    self
+      .orders
+      .lock()
+      .unwrap()
+      .tracked
+      .values()
+      .cloned()
+      .collect()
+  }
+
+  /// Update the state of a tracked order based on a trade update.
+  ///
+  /// A notification is returned if the update represents a change in
+  /// the lifecycle of the order that we report. Updates older than the
+  /// state already known are ignored. Updates for an order not being
+  /// tracked are retained for a while, in case they belong to an order
+  /// still in the process of being submitted, and applied once the
+  /// order gets inserted. No notification is returned for them here;
+  /// it is reported through the stream returned by `track` instead.
+  pub fn update(&self, update: &TradeUpdate) -> Option<Notification> {
+    let mut orders = self.orders.lock().unwrap();
+    match orders.tracked.get_mut(&update.order.id) {
+      Some(order) => {
+        if is_newer(order, &update.order) {
+          return None
+        }
+        *order = update.order.clone();
+        Notification::from_update(update)
+      },
+      None => {
+        let () = orders.defer(update);
+        None
+      },
+    }
+  }
+
+  /// Consume a stream of trade updates, as retrieved via
+  /// `Client::subscribe`, updating tracked orders and reporting
+  /// lifecycle notifications.
+  ///
+  /// Notifications for trade updates that arrived before the order
+  /// they belong to got inserted are reported once it is. Errors
+  /// reported by the stream are passed through. Only a single stream
+  /// returned by this method should be consumed at any time.
+  pub fn track<'t, S>(
+    &'t self,
+    updates: S,
+  ) -> impl Stream<Item = Result<Result<Notification, JsonError>, WebSocketError>> + 't
+  where
+    S: Stream<Item = Result<Result<TradeUpdate, JsonError>, WebSocketError>> + 't,
+  {
+    let mut updates = Box::pin(updates);
+    poll_fn(move |ctx| loop {
+      let () = self.waker.register(ctx.waker());
+      if let Some(notification) = self.orders.lock().unwrap().ready.pop_front() {
+        return Poll::Ready(Some(Ok(Ok(notification))))
+      }
+
+      match updates.poll_next_unpin(ctx) {
+        Poll::Ready(Some(Ok(Ok(update)))) => {
+          if let Some(notification) = self.update(&update) {
+            return Poll::Ready(Some(Ok(Ok(notification))))
+          }
+        },
+        Poll::Ready(Some(Ok(Err(err)))) => return Poll::Ready(Some(Ok(Err(err)))),
+        Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+        Poll::Ready(None) => return Poll::Ready(None),
+        Poll::Pending => return Poll::Pending,
+      }
+    })
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use futures::channel::mpsc::unbounded;
+  use futures::future::join;
+  use futures::stream::iter;
+
+  use num_decimal::Num;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+
+  /// Create a trade update for an order of 10 shares with the given
+  /// event and filled quantity.
+  fn update(event: &str, filled: u64) -> TradeUpdate {
+    let json = format!(
+      r#"{{
+  "event": "{}",
+  "order": {{
+    "id": "904837e3-3b76-47ec-b432-046db621571b",
+    "client_order_id": "904837e3-3b76-47ec-b432-046db621571b",
+    "created_at": "2021-03-01T15:00:00Z",
+    "updated_at": null,
+    "submitted_at": null,
+    "filled_at": null,
+    "expired_at": null,
+    "canceled_at": null,
+    "asset_id": "904837e3-3b76-47ec-b432-046db621571b",
+    "symbol": "AAPL",
+    "asset_class": "us_equity",
+    "qty": "10",
+    "filled_qty": "{}",
+    "type": "market",
+    "side": "buy",
+    "time_in_force": "day",
+    "limit_price": null,
+    "stop_price": null,
+    "filled_avg_price": "120.5",
+    "status": "new",
+    "extended_hours": false,
+    "legs": null
+  }}
+}}"#,
+      event, filled
+    );
+    from_json::<TradeUpdate>(&json).unwrap()
+  }
+
+  #[test]
+  fn update_tracked_order() {
+    let tracker = OrderTracker::new();
+    let accepted = update("new", 0);
+    let id = accepted.order.id;
+
+    // Updates for untracked orders are deferred.
+    assert_eq!(tracker.update(&accepted), None);
+    assert_eq!(tracker.get(id), None);
+
+    tracker.insert(accepted.order.clone());
+    let notification = tracker.update(&accepted).unwrap();
+    assert!(matches!(notification, Notification::Accepted(..)));
+
+    let partial = update("partial_fill", 4);
+    let notification = tracker.update(&partial).unwrap();
+    assert!(matches!(notification, Notification::PartiallyFilled(..)));
+    assert_eq!(tracker.get(id).unwrap().filled_quantity, 4);
+
+    let pending = update("pending_cancel", 4);
+    assert_eq!(tracker.update(&pending), None);
+
+    let filled = update("fill", 10);
+    let notification = tracker.update(&filled).unwrap();
+    assert_eq!(notification.order().filled_quantity, 10);
+    let order = tracker.get(id).unwrap();
+    assert_eq!(order.average_fill_price, Some(Num::new(1205, 10)));
+    assert_eq!(tracker.orders().len(), 1);
+
+    assert!(tracker.remove(id).is_some());
+    assert_eq!(tracker.get(id), None);
+  }
+
+  /// Check that trade updates arriving before an order is inserted are
+  /// not lost.
+  #[test]
+  fn update_before_insert() {
+    let tracker = OrderTracker::new();
+    let accepted = update("new", 0);
+    let id = accepted.order.id;
+
+    let mut filled = update("fill", 10);
+    filled.order.status = order::Status::Filled;
+    assert_eq!(tracker.update(&update("new", 0)), None);
+    assert_eq!(tracker.update(&filled), None);
+    assert_eq!(tracker.get(id), None);
+
+    // The response to the submission reports an older state, which
+    // must not replace the one reported by the trade update.
+    tracker.insert(accepted.order.clone());
+    let order = tracker.get(id).unwrap();
+    assert_eq!(order.status, order::Status::Filled);
+    assert_eq!(order.filled_quantity, 10);
+
+    tracker.insert(accepted.order.clone());
+    assert_eq!(tracker.get(id).unwrap().status, order::Status::Filled);
+
+    // Stale updates for tracked orders are ignored as well.
+    assert_eq!(tracker.update(&accepted), None);
+    assert_eq!(tracker.get(id).unwrap().status, order::Status::Filled);
+  }
+
+  /// Check that notifications for trade updates arriving before the
+  /// order is inserted are reported once it is.
+  #[test(tokio::test)]
+  async fn track_update_before_insert() {
+    let tracker = OrderTracker::new();
+    let accepted = update("new", 0);
+    let mut filled = update("fill", 10);
+    filled.order.status = order::Status::Filled;
+
+    let updates = vec![Ok(Ok(accepted.clone())), Ok(Ok(filled))];
+    let notifications = tracker
+      .track(iter(updates))
+      .map(|result| result.unwrap().unwrap())
+      .collect::<Vec<_>>()
+      .await;
+    assert_eq!(notifications, Vec::new());
+
+    tracker.insert(accepted.order);
+    let notifications = tracker
+      .track(iter(Vec::new()))
+      .map(|result| result.unwrap().unwrap())
+      .collect::<Vec<_>>()
+      .await;
+
+    assert_eq!(notifications.len(), 2);
+    assert!(matches!(notifications[0], Notification::Accepted(..)));
+    assert!(matches!(notifications[1], Notification::Filled(..)));
+    assert_eq!(notifications[1].order().filled_quantity, 10);
+
+    // Notifications are reported only once.
+    tracker.insert(notifications[1].order().clone());
+    let notifications = tracker
+      .track(iter(Vec::new()))
+      .map(|result| result.unwrap().unwrap())
+      .collect::<Vec<_>>()
+      .await;
+    assert_eq!(notifications, Vec::new());
+  }
+
+  /// Check that deferred notifications are reported by a stream already
+  /// being waited on when the order is inserted.
+  #[test(tokio::test)]
+  async fn track_insert_wakes_stream() {
+    let tracker = OrderTracker::new();
+    let accepted = update("new", 0);
+    assert_eq!(tracker.update(&accepted), None);
+
+    let (sender, receiver) = unbounded();
+    let notifications = tracker.track(receiver);
+    let insert = async {
+      let () = tokio::task::yield_now().await;
+      tracker.insert(accepted.order.clone());
+      drop(sender);
+    };
+
+    let collect = notifications
+      .map(|result| result.unwrap().unwrap())
+      .collect::<Vec<_>>();
+    let (notifications, ()) = join(collect, insert).await;
+    assert_eq!(notifications.len(), 1);
+    assert!(matches!(notifications[0], Notification::Accepted(..)));
+  }
+
+  /// Check that lifecycle notifications are reported for tracked orders.
+  #[test(tokio::test)]
+  async fn track_stream() {
+    let tracker = OrderTracker::new();
+    let accepted = update("new", 0);
+    tracker.insert(accepted.order.clone());
+
+    let updates = vec![
+      Ok(Ok(accepted)),
+      Ok(Ok(update("pending_cancel", 0))),
+      Ok(Ok(update("canceled", 0))),
+    ];
+    let notifications = tracker
+      .track(iter(updates))
+      .map(|result| result.unwrap().unwrap())
+      .collect::<Vec<_>>()
+      .await;
+
+    assert_eq!(notifications.len(), 2);
+    assert!(matches!(notifications[0], Notification::Accepted(..)));
+    assert!(matches!(notifications[1], Notification::Canceled(..)));
+  }
+}
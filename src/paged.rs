@@ -0,0 +1,74 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use futures::stream::unfold;
+use futures::stream::Stream;
+
+use crate::ApiEndpoint;
+
+use crate::Client;
+use crate::RequestError;
+
+
+/// A trait for endpoints reporting their results in pages.
+pub trait Paged: ApiEndpoint {
+  /// Create the input for retrieving the page following the one
+  /// reported in `output`, which was retrieved using `input`.
+  ///
+  /// `None` is returned if `output` is the last page.
+  fn next_page(input: &Self::Input, output: &Self::Output) -> Option<Self::Input>;
+}
+
+
+/// Create the input for retrieving the page following the one
+/// reported by an endpoint paginating by means of a page token.
+///
+/// `page_token` provides access to the page token member of `input`.
+/// `None` is returned if `next_page_token` is unset, i.e., if the
+/// current page is the last one.
+#[cfg(any(feature = "broker", feature = "data"))]
+pub(crate) fn next_page_by_token<I, F>(
+  input: &I,
+  next_page_token: Option<&str>,
+  page_token: F,
+) -> Option<I>
+where
+  I: Clone,
+  F: FnOnce(&mut I) -> &mut Option<String>,
+{
+  let next_page_token = next_page_token?;
+  let mut input = input.clone();
+  *page_token(&mut input) = Some(next_page_token.to_string());
+  Some(input)
+}
+
+
+impl Client {
+  /// Create and issue a request to a paginated endpoint, retrieving
+  /// all pages one after the other.
+  ///
+  /// The returned stream reports each page as it got retrieved,
+  /// starting with the one described by `input`. Subsequent pages are
+  /// only requested once the previous one has been consumed. The
+  /// stream ends after the last page or after the first error, which
+  /// is reported as well.
+  pub fn issue_paged<'c, R>(
+    &'c self,
+    input: R::Input,
+  ) -> impl Stream<Item = Result<R::Output, RequestError<R::Error>>> + 'c
+  where
+    R: Paged + 'c,
+    R::Input: Clone + 'c,
+  {
+    unfold(Some(input), move |input| async move {
+      let input = input?;
+      match self.issue::<R>(input.clone()).await {
+        Ok(output) => {
+          let next = R::next_page(&input, &output);
+          Some((Ok(output), next))
+        },
+        Err(err) => Some((Err(err), None)),
+      }
+    })
+  }
+}
@@ -0,0 +1,518 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use futures::TryStreamExt as _;
+
+use num_decimal::Num;
+
+use tracing::warn;
+
+use crate::api::v2::account;
+use crate::api::v2::account_activities;
+use crate::api::v2::asset;
+use crate::api::v2::order;
+use crate::util::us_eastern_offset;
+use crate::Client;
+use crate::RequestError;
+
+
+/// The equity below which accounts are subject to the pattern day
+/// trader rule.
+const PDT_EQUITY: u64 = 25_000;
+/// The maximum number of day trades an account subject to the rule
+/// may make within five business days without being flagged as a
+/// pattern day trader.
+const MAX_DAY_TRADES: u64 = 3;
+
+
+/// Retrieve the trading day, as days since the epoch in US Eastern
+/// time, that the given time falls on.
+fn trading_day(time: SystemTime) -> u64 {
+  // It is fine to unwrap here because no time relevant to us can ever
+  // be before `UNIX_EPOCH`.
+  let secs = time.duration_since(UNIX_EPOCH).unwrap().as_secs();
+  (secs as i64 + us_eastern_offset(secs)) as u64 / 86400
+}
+
+
+/// Retrieve the plain symbol an order request refers to.
+fn symbol(request: &order::OrderReq) -> String {
+  match &request.symbol {
+    asset::Symbol::Sym(symbol)
+    | asset::Symbol::SymExchg(symbol, ..)
+    | asset::Symbol::SymExchgCls(symbol, ..) => symbol.clone(),
+    asset::Symbol::Id(..) => request.symbol.to_string(),
+  }
+}
+
+
+/// What to do about orders that would result in a violation.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Mode {
+  /// Do not submit the order and report the violation instead.
+  #[default]
+  Block,
+  /// Log a warning and submit the order regardless.
+  Warn,
+}
+
+
+/// A day trade that would violate the pattern day trader rule.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Violation {
+  /// The symbol of the order.
+  pub symbol: String,
+  /// The number of day trades made within the last five business
+  /// days, not including the one the order would result in.
+  pub day_trades: u64,
+  /// Whether the account is flagged as a pattern day trader already.
+  pub flagged: bool,
+}
+
+impl Display for Violation {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    if self.flagged {
+      write!(
+        fmt,
+        "day trade in {} not permitted for pattern day trader below minimum equity",
+        self.symbol
+      )
+    } else {
+      write!(
+        fmt,
+        "day trade in {} would exceed {} day trades ({} made already)",
+        self.symbol, MAX_DAY_TRADES, self.day_trades
+      )
+    }
+  }
+}
+
+impl StdError for Violation {}
+
+
+/// An error reported when submitting an order through `PdtGuarded`.
+#[derive(Debug)]
+pub enum PdtError {
+  /// The order would violate the pattern day trader rule and was not
+  /// submitted.
+  Violation(Violation),
+  /// The account could not be retrieved.
+  Account(RequestError<account::GetError>),
+  /// The account activities could not be retrieved.
+  Activities(RequestError<account_activities::GetError>),
+  /// The order submission failed.
+  Order(RequestError<order::PostError>),
+}
+
+impl Display for PdtError {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    match self {
+      Self::Violation(err) => write!(fmt, "order rejected locally: {}", err),
+      Self::Account(err) => write!(fmt, "failed to retrieve account: {}", err),
+      Self::Activities(err) => write!(fmt, "failed to retrieve account activities: {}", err),
+      Self::Order(err) => write!(fmt, "failed to submit order: {}", err),
+    }
+  }
+}
+
+impl StdError for PdtError {
+  fn source(&self) -> Option<&(dyn StdError + 'static)> {
+    match self {
+      Self::Violation(err) => Some(err),
+      Self::Account(err) => Some(err),
+      Self::Activities(err) => Some(err),
+      Self::Order(err) => Some(err),
+    }
+  }
+}
+
+
+/// The sides traded in a symbol on the current trading day.
+#[derive(Clone, Copy, Debug, Default)]
+struct Sides {
+  buy: bool,
+  sell: bool,
+}
+
+
+/// The trades made on a single trading day.
+#[derive(Debug, Default)]
+struct Today {
+  /// The trading day.
+  day: u64,
+  /// The sides traded, per symbol.
+  sides: HashMap<String, Sides>,
+  /// The number of day trades made through us that are not yet
+  /// reflected in the day trade count reported for the account.
+  local: u64,
+  /// The day trade count last reported for the account.
+  reported: u64,
+}
+
+
+/// A tracker of the trades made on the current trading day.
+///
+/// A trade is considered to complete a day trade if the opposite side
+/// of the same symbol was traded earlier on the same trading day.
+#[derive(Debug, Default)]
+pub struct DayTrades {
+  /// The trades of the current trading day.
+  today: Mutex<Today>,
+}
+
+impl DayTrades {
+  /// Create a new `DayTrades` object not knowing about any trades.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Invoke a function on the trades of the trading day the given time
+  /// falls on.
+  fn with_today<F, T>(&self, time: SystemTime, f: F) -> T
+  where
+    F: FnOnce(&mut Today) -> T,
+  {
+    let day = trading_day(time);
+    let mut today = self.today.lock().unwrap();
+    if today.day != day {
+      *today = Today {
+        day,
+        reported: today.reported,
+        ..Default::default()
+      };
+    }
+    f(&mut today)
+  }
+
+  /// Record a trade of the given symbol at the given time.
+  ///
+  /// The return value indicates whether the trade completed a day
+  /// trade.
+  pub fn record(&self, symbol: &str, side: order::Side, time: SystemTime) -> bool {
+    self.with_today(time, |today| {
+      let sides = today.sides.entry(symbol.to_string()).or_default();
+      let day_trade = match side {
+        order::Side::Buy => sides.sell,
+        order::Side::Sell => sides.buy,
+      };
+      match side {
+        order::Side::Buy => sides.buy = true,
+        order::Side::Sell => sides.sell = true,
+      }
+      day_trade
+    })
+  }
+
+  /// Check whether trading the given symbol at the given time would
+  /// complete a day trade.
+  pub fn would_day_trade(&self, symbol: &str, side: order::Side, time: SystemTime) -> bool {
+    self.with_today(time, |today| {
+      let sides = today.sides.get(symbol).copied().unwrap_or_default();
+      match side {
+        order::Side::Buy => sides.sell,
+        order::Side::Sell => sides.buy,
+      }
+    })
+  }
+
+  /// Record a day trade made through us on the trading day the given
+  /// time falls on.
+  fn record_day_trade(&self, time: SystemTime) {
+    self.with_today(time, |today| today.local += 1)
+  }
+
+  /// Determine the number of day trades made, based on the count
+  /// reported for the account and the day trades we made that may not
+  /// be reflected in it yet.
+  fn count(&self, reported: u64, time: SystemTime) -> u64 {
+    self.with_today(time, |today| {
+      if reported > today.reported {
+        today.local = today.local.saturating_sub(reported - today.reported);
+      }
+      today.reported = reported;
+      reported + today.local
+    })
+  }
+
+  /// Check whether an order for the given symbol and side would
+  /// violate the pattern day trader rule for the given account.
+  ///
+  /// Only margin accounts with less than $25,000 of equity as of the
+  /// previous trading day are subject to the rule.
+  pub fn check(
+    &self,
+    account: &account::Account,
+    symbol: &str,
+    side: order::Side,
+    time: SystemTime,
+  ) -> Result<(), Violation> {
+    let margin = account.multiplier > Num::from(1);
+    if !margin || account.last_equity >= Num::from(PDT_EQUITY) {
+      return Ok(())
+    }
+    if !self.would_day_trade(symbol, side, time) {
+      return Ok(())
+    }
+
+    let day_trades = self.count(account.daytrade_count, time);
+    if account.day_trader || day_trades >= MAX_DAY_TRADES {
+      return Err(Violation {
+        symbol: symbol.to_string(),
+        day_trades,
+        flagged: account.day_trader,
+      })
+    }
+    Ok(())
+  }
+}
+
+
+/// A wrapper around a `Client` checking order submissions against the
+/// pattern day trader (PDT) rule.
+///
+/// Margin accounts with less than $25,000 of equity must not make more
+/// than three day trades within five business days. Alpaca rejects
+/// orders that would result in a violation. This guard detects such
+/// orders before they are submitted, based on the day trade count
+/// reported for the account along with the trades of the current
+/// trading day, as loaded from the account activities (see
+/// `PdtGuarded::load`) and as submitted through the guard. Note that
+/// orders submitted through the guard are considered traded right
+/// away, even if they do not fill, erring on the side of caution.
+///
+/// ```no_run
+/// # use apca::api::v2::order;
+/// # use apca::pdt::Mode;
+/// # use apca::pdt::PdtGuarded;
+/// # use apca::ApiInfo;
+/// # use apca::Client;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = PdtGuarded::new(Client::new(ApiInfo::from_env()?), Mode::Block);
+/// let () = client.load().await?;
+/// let request = order::OrderReqInit::default().init("AAPL", order::Side::Sell, 1);
+/// let order = client.submit(request).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct PdtGuarded<C> {
+  /// The wrapped client.
+  client: C,
+  /// What to do about violations.
+  mode: Mode,
+  /// The trades of the current trading day.
+  trades: DayTrades,
+}
+
+impl<C> PdtGuarded<C>
+where
+  C: Borrow<Client>,
+{
+  /// Create a new `PdtGuarded` object wrapping the given client.
+  pub fn new(client: C, mode: Mode) -> Self {
+    Self {
+      client,
+      mode,
+      trades: DayTrades::new(),
+    }
+  }
+
+  /// Retrieve the wrapped client.
+  pub fn client(&self) -> &Client {
+    self.client.borrow()
+  }
+
+  /// Retrieve the trades of the current trading day known to the
+  /// guard.
+  pub fn trades(&self) -> &DayTrades {
+    &self.trades
+  }
+
+  /// Load the fills of the current trading day from the account
+  /// activities, making the guard aware of trades made by other means.
+  pub async fn load(&self) -> Result<(), PdtError> {
+    let now = SystemTime::now();
+    let day = trading_day(now);
+    let request = account_activities::ActivityReq {
+      types: Some(vec![account_activities::ActivityType::Fill]),
+      ..Default::default()
+    };
+
+    // Activities are reported newest first, so we can stop after the
+    // first page reaching back beyond the current trading day.
+    let mut pages = Box::pin(
+      self
+        .client()
+        .issue_paged::<account_activities::Get>(request),
+    );
+    while let Some(page) = pages.try_next().await.map_err(PdtError::Activities)? {
+      let mut done = false;
+      for activity in page {
+        if let account_activities::Activity::Trade(trade) = activity {
+          if trading_day(trade.transaction_time) != day {
+            done = true;
+            continue
+          }
+
+          let side = match trade.side {
+            account_activities::Side::Buy => order::Side::Buy,
+            account_activities::Side::Sell | account_activities::Side::ShortSell => {
+              order::Side::Sell
+            },
+          };
+          let _ = self
+            .trades
+            .record(&trade.symbol, side, trade.transaction_time);
+        }
+      }
+
+      if done {
+        break
+      }
+    }
+    Ok(())
+  }
+
+  /// Check an order against the pattern day trader rule and submit it,
+  /// unless it would result in a violation and the guard is in
+  /// blocking mode.
+  pub async fn submit(&self, request: order::OrderReq) -> Result<order::Order, PdtError> {
+    let account = self
+      .client()
+      .issue::<account::Get>(())
+      .await
+      .map_err(PdtError::Account)?;
+
+    let now = SystemTime::now();
+    let symbol = symbol(&request);
+    let side = request.side;
+    if let Err(violation) = self.trades.check(&account, &symbol, side, now) {
+      match self.mode {
+        Mode::Block => {
+          warn!(violation = display(&violation), "rejecting order");
+          return Err(PdtError::Violation(violation))
+        },
+        Mode::Warn => warn!(
+          violation = display(&violation),
+          "submitting order regardless"
+        ),
+      }
+    }
+
+    let order = self
+      .client()
+      .issue::<order::Post>(request)
+      .await
+      .map_err(PdtError::Order)?;
+
+    if self.trades.record(&symbol, side, now) {
+      let () = self.trades.record_day_trade(now);
+    }
+    Ok(order)
+  }
+
+  /// Unwrap the `PdtGuarded` object, returning the wrapped client.
+  pub fn into_inner(self) -> C {
+    self.client
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use test_env_log::test;
+
+  use time_util::parse_system_time_from_str;
+
+  use crate::test_support;
+
+
+  /// Create a margin account with the given equity and day trade
+  /// count.
+  fn account(equity: u64, daytrade_count: u64) -> account::Account {
+    let mut account = test_support::account(Num::from(equity));
+    account.multiplier = Num::from(2);
+    account.daytrade_count = daytrade_count;
+    account
+  }
+
+  /// Check that we detect day trades on the same trading day only.
+  #[test]
+  fn detect_day_trades() {
+    let time = |time| parse_system_time_from_str(time).unwrap();
+    let trades = DayTrades::new();
+
+    assert!(!trades.record("SPY", order::Side::Buy, time("2021-06-01T14:00:00Z")));
+    assert!(!trades.would_day_trade("SPY", order::Side::Buy, time("2021-06-01T15:00:00Z")));
+    assert!(trades.would_day_trade("SPY", order::Side::Sell, time("2021-06-01T15:00:00Z")));
+    assert!(!trades.would_day_trade("AAPL", order::Side::Sell, time("2021-06-01T15:00:00Z")));
+    // 23:00 UTC is still on the same trading day in US Eastern time.
+    assert!(trades.would_day_trade("SPY", order::Side::Sell, time("2021-06-01T23:00:00Z")));
+    assert!(!trades.would_day_trade("SPY", order::Side::Sell, time("2021-06-02T14:00:00Z")));
+  }
+
+  /// Check that we flag day trades exceeding the limit on small margin
+  /// accounts only.
+  #[test]
+  fn check_violation() {
+    let time = parse_system_time_from_str("2021-06-01T14:00:00Z").unwrap();
+    let trades = DayTrades::new();
+    let _ = trades.record("SPY", order::Side::Buy, time);
+
+    assert!(trades
+      .check(&account(10_000, 2), "SPY", order::Side::Sell, time)
+      .is_ok());
+    assert!(trades
+      .check(&account(10_000, 3), "SPY", order::Side::Buy, time)
+      .is_ok());
+    assert!(trades
+      .check(&account(30_000, 3), "SPY", order::Side::Sell, time)
+      .is_ok());
+    let mut cash = account(10_000, 3);
+    cash.multiplier = Num::from(1);
+    assert!(trades.check(&cash, "SPY", order::Side::Sell, time).is_ok());
+
+    let violation = trades
+      .check(&account(10_000, 3), "SPY", order::Side::Sell, time)
+      .unwrap_err();
+    assert_eq!(violation.day_trades, 3);
+    assert!(!violation.flagged);
+
+    let mut flagged = account(10_000, 0);
+    flagged.day_trader = true;
+    let violation = trades
+      .check(&flagged, "SPY", order::Side::Sell, time)
+      .unwrap_err();
+    assert!(violation.flagged);
+  }
+
+  /// Check that we account for day trades not yet reflected in the
+  /// count reported for the account.
+  #[test]
+  fn count_local_day_trades() {
+    let time = parse_system_time_from_str("2021-06-01T14:00:00Z").unwrap();
+    let trades = DayTrades::new();
+    assert_eq!(trades.count(1, time), 1);
+
+    let () = trades.record_day_trade(time);
+    let () = trades.record_day_trade(time);
+    assert_eq!(trades.count(1, time), 3);
+    // Once the reported count caught up with one of our day trades we
+    // only account for the other one.
+    assert_eq!(trades.count(2, time), 3);
+    assert_eq!(trades.count(3, time), 3);
+  }
+}
@@ -0,0 +1,195 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashMap;
+
+use futures::future::ready;
+use futures::stream::Stream;
+use futures::StreamExt;
+
+use num_decimal::Num;
+
+use crate::api::v2::position::Position;
+
+
+/// A snapshot of the value of a portfolio.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+  /// The cash balance.
+  pub cash: Num,
+  /// The market value of all long positions.
+  pub long_market_value: Num,
+  /// The market value of all short positions, as a negative value.
+  pub short_market_value: Num,
+  /// The total value of the portfolio, i.e., the sum of `cash`,
+  /// `long_market_value`, and `short_market_value`.
+  pub equity: Num,
+  /// The gross exposure, i.e., the sum of the absolute market values
+  /// of all positions.
+  pub gross_exposure: Num,
+  /// The net exposure, i.e., the sum of the market values of all
+  /// positions.
+  pub net_exposure: Num,
+}
+
+
+/// A holding in the portfolio.
+#[derive(Clone, Debug, PartialEq)]
+struct Holding {
+  /// The signed quantity held, negative for short positions.
+  quantity: i64,
+  /// The most recent price per share.
+  price: Num,
+}
+
+
+/// A portfolio whose value is updated based on real time prices.
+///
+/// A `Portfolio` is created from the account's cash balance and its
+/// positions, as retrieved via the /v2/account and /v2/positions
+/// endpoints. Prices are then updated from an arbitrary source, for
+/// example, a stream of quotes or trades, and the value of the
+/// portfolio recalculated.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Portfolio {
+  /// The cash balance.
+  cash: Num,
+  /// The holdings, keyed by symbol.
+  holdings: HashMap<String, Holding>,
+}
+
+impl Portfolio {
+  /// Create a new `Portfolio` from the cash balance and the positions
+  /// held.
+  ///
+  /// Positions are initially valued at their current price, as
+  /// reported by Alpaca.
+  pub fn new(cash: Num, positions: &[Position]) -> Self {
+    let holdings = positions
+      .iter()
+      .map(|position| {
+        let holding = Holding {
+          quantity: position.signed_quantity(),
+          price: position.current_price.clone(),
+        };
+        (position.symbol.clone(), holding)
+      })
+      .collect();
+
+    Self { cash, holdings }
+  }
+
+  /// Update the price of the asset with the given symbol.
+  ///
+  /// The method returns `false` if no position in the asset is held,
+  /// in which case the update is ignored.
+  pub fn update_price(&mut self, symbol: &str, price: Num) -> bool {
+    match self.holdings.get_mut(symbol) {
+      Some(holding) => {
+        holding.price = price;
+        true
+      },
+      None => false,
+    }
+  }
+
+  /// Calculate a snapshot of the current value of the portfolio.
+  pub fn snapshot(&self) -> Snapshot {
+    let zero = Num::from(0);
+    let mut long = zero.clone();
+    let mut short = zero.clone();
+
+    for holding in self.holdings.values() {
+      let value = &holding.price * Num::from(holding.quantity);
+      if value.is_negative() {
+        short += value;
+      } else {
+        long += value;
+      }
+    }
+
+    Snapshot {
+      equity: &self.cash + &long + &short,
+      gross_exposure: &long - &short,
+      net_exposure: &long + &short,
+      cash: self.cash.clone(),
+      long_market_value: long,
+      short_market_value: short,
+    }
+  }
+
+  /// Track the value of the portfolio based on a stream of prices.
+  ///
+  /// The stream is expected to provide pairs of symbol and price. A
+  /// snapshot is emitted for each price update pertaining to an asset
+  /// held in the portfolio.
+  pub fn track<S, Y>(mut self, prices: S) -> impl Stream<Item = Snapshot>
+  where
+    S: Stream<Item = (Y, Num)>,
+    Y: AsRef<str>,
+  {
+    prices.filter_map(move |(symbol, price)| {
+      let snapshot = if self.update_price(symbol.as_ref(), price) {
+        Some(self.snapshot())
+      } else {
+        None
+      };
+      ready(snapshot)
+    })
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use futures::stream::iter;
+
+  use test_env_log::test;
+
+  use crate::api::v2::position;
+  use crate::test_support;
+
+
+  #[test]
+  fn calculate_snapshot() {
+    let positions = vec![
+      test_support::position("AAPL", position::Side::Long, 10, Num::from(120)),
+      test_support::position("SPY", position::Side::Short, 2, Num::from(400)),
+    ];
+    let mut portfolio = Portfolio::new(Num::from(5000), &positions);
+
+    let snapshot = portfolio.snapshot();
+    assert_eq!(snapshot.long_market_value, Num::from(1200));
+    assert_eq!(snapshot.short_market_value, Num::from(-800));
+    assert_eq!(snapshot.equity, Num::from(5400));
+    assert_eq!(snapshot.gross_exposure, Num::from(2000));
+    assert_eq!(snapshot.net_exposure, Num::from(400));
+
+    assert!(portfolio.update_price("AAPL", Num::from(125)));
+    assert!(!portfolio.update_price("MSFT", Num::from(250)));
+    assert_eq!(portfolio.snapshot().equity, Num::from(5450));
+  }
+
+  #[test(tokio::test)]
+  async fn track_prices() {
+    let positions = vec![test_support::position(
+      "AAPL",
+      position::Side::Long,
+      10,
+      Num::from(120),
+    )];
+    let portfolio = Portfolio::new(Num::from(0), &positions);
+    let prices = vec![
+      ("AAPL", Num::from(121)),
+      ("MSFT", Num::from(250)),
+      ("AAPL", Num::new(2425, 20)),
+    ];
+
+    let snapshots = portfolio.track(iter(prices)).collect::<Vec<_>>().await;
+    assert_eq!(snapshots.len(), 2);
+    assert_eq!(snapshots[0].equity, Num::from(1210));
+    assert_eq!(snapshots[1].equity, Num::new(24250, 20));
+  }
+}
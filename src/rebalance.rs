@@ -0,0 +1,319 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use num_decimal::Num;
+
+use crate::api::v2::order;
+use crate::api::v2::position::Position;
+use crate::Client;
+use crate::RequestError;
+
+
+/// An error preventing the calculation of rebalancing orders.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RebalanceError {
+  /// No price is known for the symbol.
+  MissingPrice(String),
+  /// The target quantity for the symbol is out of range.
+  Overflow(String),
+}
+
+impl Display for RebalanceError {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    match self {
+      Self::MissingPrice(symbol) => write!(fmt, "no price known for symbol {}", symbol),
+      Self::Overflow(symbol) => {
+        write!(fmt, "target quantity for symbol {} is out of range", symbol)
+      },
+    }
+  }
+}
+
+impl StdError for RebalanceError {}
+
+
+/// Thresholds below which trades are not worth making.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Thresholds {
+  /// The minimum notional value of a trade.
+  pub min_notional: Num,
+  /// The minimum number of shares to trade.
+  pub min_quantity: u64,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+
+/// Create a market order request for the given signed quantity.
+fn market_order(symbol: &str, quantity: i64) -> order::OrderReq {
+  let side = order::Side::from_signed(quantity);
+  order::OrderReqInit::default().init(symbol, side, quantity.unsigned_abs())
+}
+
+
+/// Calculate the orders necessary to rebalance a portfolio towards
+/// the given target weights.
+///
+/// Targets are provided as pairs of symbol and weight, as a fraction
+/// of `equity`. Negative weights represent short positions. Positions
+/// held in symbols without a target are closed. Target quantities are
+/// rounded towards zero to whole shares. Prices are looked up in
+/// `prices` first and fall back to the current price of the position
+/// held, if any.
+///
+/// Trades below any of the provided thresholds are omitted. Trades
+/// that would flip a position from long to short or vice versa are
+/// split into one order closing the position and another one opening
+/// the new one. Orders reducing exposure are reported first, so that
+/// they can be submitted before those requiring buying power.
+pub fn rebalance<I, S>(
+  targets: I,
+  positions: &[Position],
+  equity: &Num,
+  prices: &HashMap<String, Num>,
+  thresholds: &Thresholds,
+) -> Result<Vec<order::OrderReq>, RebalanceError>
+where
+  I: IntoIterator<Item = (S, Num)>,
+  S: Into<String>,
+{
+  let mut targets = targets
+    .into_iter()
+    .map(|(symbol, weight)| (symbol.into(), Some(weight)))
+    .collect::<BTreeMap<String, Option<Num>>>();
+  for position in positions {
+    let _ = targets.entry(position.symbol.clone()).or_insert(None);
+  }
+
+  let mut reducing = Vec::new();
+  let mut increasing = Vec::new();
+
+  for (symbol, weight) in targets {
+    let position = positions.iter().find(|position| position.symbol == symbol);
+    let current = position.map(Position::signed_quantity).unwrap_or(0);
+    let price = prices
+      .get(&symbol)
+      .or_else(|| position.map(|position| &position.current_price))
+      .filter(|price| price.is_positive());
+    let target = match (weight, price) {
+      // Truncation rounds towards zero for short targets as well.
+      (Some(weight), Some(price)) => match (equity * weight / price).trunc().to_i64() {
+        Some(target) => target,
+        None => return Err(RebalanceError::Overflow(symbol)),
+      },
+      (Some(_), None) => return Err(RebalanceError::MissingPrice(symbol)),
+      (None, _) => 0,
+    };
+
+    let delta = match target.checked_sub(current) {
+      Some(0) => continue,
+      Some(delta) => delta,
+      None => return Err(RebalanceError::Overflow(symbol)),
+    };
+
+    // Positions to be closed completely are always traded, so as to not
+    // leave behind a remainder below the thresholds.
+    if target != 0 {
+      if delta.unsigned_abs() < thresholds.min_quantity {
+        continue
+      }
+      if let Some(price) = price {
+        if price * Num::from(delta.unsigned_abs()) < thresholds.min_notional {
+          continue
+        }
+      }
+    }
+
+    if current != 0 && target != 0 && (current < 0) != (target < 0) {
+      reducing.push(market_order(&symbol, -current));
+      increasing.push(market_order(&symbol, target));
+    } else if target.abs() < current.abs() {
+      reducing.push(market_order(&symbol, delta));
+    } else {
+      increasing.push(market_order(&symbol, delta));
+    }
+  }
+
+  reducing.extend(increasing);
+  Ok(reducing)
+}
+
+
+/// Submit the orders calculated by `rebalance`.
+///
+/// Orders are submitted one after the other, in the order provided,
+/// which allows for orders reducing exposure to free up buying power
+/// for the remaining ones. The results are reported in the same order.
+pub async fn submit(
+  client: &Client,
+  orders: Vec<order::OrderReq>,
+) -> Vec<Result<order::Order, RequestError<order::PostError>>> {
+  let mut results = Vec::with_capacity(orders.len());
+  for request in orders {
+    results.push(client.issue::<order::Post>(request).await);
+  }
+  results
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use test_env_log::test;
+
+  use crate::api::v2::position;
+  use crate::test_support;
+
+
+  /// Extract symbol, side, and quantity from a list of orders.
+  fn summarize(orders: &[order::OrderReq]) -> Vec<(String, order::Side, u64)> {
+    orders
+      .iter()
+      .map(|order| (order.symbol.to_string(), order.side, order.quantity))
+      .collect()
+  }
+
+  #[test]
+  fn rebalance_portfolio() {
+    let positions = vec![
+      test_support::position("AAPL", position::Side::Long, 50, Num::from(100)),
+      test_support::position("SPY", position::Side::Long, 10, Num::from(400)),
+      test_support::position("XLK", position::Side::Long, 5, Num::from(140)),
+    ];
+    let prices = vec![("MSFT".to_string(), Num::from(250))]
+      .into_iter()
+      .collect();
+    let targets = vec![
+      ("AAPL", Num::new(2, 10)),
+      ("SPY", Num::new(5, 10)),
+      ("MSFT", Num::new(3, 10)),
+    ];
+    let equity = Num::from(10000);
+
+    let orders = rebalance(
+      targets,
+      &positions,
+      &equity,
+      &prices,
+      &Thresholds::default(),
+    )
+    .unwrap();
+    let expected = vec![
+      ("AAPL".to_string(), order::Side::Sell, 30),
+      ("XLK".to_string(), order::Side::Sell, 5),
+      ("MSFT".to_string(), order::Side::Buy, 12),
+      ("SPY".to_string(), order::Side::Buy, 2),
+    ];
+    assert_eq!(summarize(&orders), expected);
+  }
+
+  #[test]
+  fn rebalance_with_thresholds() {
+    let positions = vec![test_support::position(
+      "AAPL",
+      position::Side::Long,
+      50,
+      Num::from(100),
+    )];
+    let thresholds = Thresholds {
+      min_notional: Num::from(500),
+      ..Default::default()
+    };
+    let targets = vec![("AAPL", Num::new(54, 100))];
+    let equity = Num::from(10000);
+
+    // Buying four more shares is below the notional threshold.
+    let orders = rebalance(targets, &positions, &equity, &HashMap::new(), &thresholds).unwrap();
+    assert_eq!(orders, Vec::new());
+  }
+
+  /// Check that positions to be closed completely are traded even if
+  /// below the thresholds.
+  #[test]
+  fn rebalance_close_below_thresholds() {
+    let positions = vec![test_support::position(
+      "AAPL",
+      position::Side::Long,
+      3,
+      Num::from(100),
+    )];
+    let thresholds = Thresholds {
+      min_notional: Num::from(500),
+      min_quantity: 5,
+      ..Default::default()
+    };
+    let targets = vec![("AAPL", Num::from(0))];
+    let equity = Num::from(10000);
+
+    let orders = rebalance(targets, &positions, &equity, &HashMap::new(), &thresholds).unwrap();
+    let expected = vec![("AAPL".to_string(), order::Side::Sell, 3)];
+    assert_eq!(summarize(&orders), expected);
+  }
+
+  #[test]
+  fn rebalance_flip_side() {
+    let positions = vec![test_support::position(
+      "AAPL",
+      position::Side::Long,
+      10,
+      Num::from(100),
+    )];
+    let targets = vec![("AAPL", Num::new(-1, 10))];
+    let equity = Num::from(10000);
+
+    let orders = rebalance(
+      targets,
+      &positions,
+      &equity,
+      &HashMap::new(),
+      &Thresholds::default(),
+    )
+    .unwrap();
+    let expected = vec![
+      ("AAPL".to_string(), order::Side::Sell, 10),
+      ("AAPL".to_string(), order::Side::Sell, 10),
+    ];
+    assert_eq!(summarize(&orders), expected);
+  }
+
+  #[test]
+  fn rebalance_missing_price() {
+    let targets = vec![("MSFT", Num::new(1, 2))];
+    let err = rebalance(
+      targets,
+      &[],
+      &Num::from(1000),
+      &HashMap::new(),
+      &Thresholds::default(),
+    )
+    .unwrap_err();
+    assert_eq!(err, RebalanceError::MissingPrice("MSFT".to_string()));
+  }
+
+  /// Check that we report target quantities that are out of range
+  /// instead of liquidating the position.
+  #[test]
+  fn rebalance_overflow() {
+    let mut prices = HashMap::new();
+    let _ = prices.insert("MSFT".to_string(), Num::new(1, 1_000_000));
+    let targets = vec![("MSFT", Num::from(1))];
+    let err = rebalance(
+      targets,
+      &[],
+      &Num::from(u64::MAX),
+      &prices,
+      &Thresholds::default(),
+    )
+    .unwrap_err();
+    assert_eq!(err, RebalanceError::Overflow("MSFT".to_string()));
+  }
+}
@@ -0,0 +1,172 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::BTreeMap;
+
+use crate::api::v2::position::Position;
+use crate::api::v2::positions;
+use crate::Client;
+use crate::RequestError;
+
+
+/// A discrepancy between a locally tracked position and the positions
+/// reported by Alpaca.
+///
+/// Quantities are signed, with short positions being represented by
+/// negative values.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Discrepancy {
+  /// A position is tracked locally but not held in the account.
+  Missing {
+    /// The symbol of the position.
+    symbol: String,
+    /// The locally tracked quantity.
+    expected: i64,
+  },
+  /// A position is held in the account but not tracked locally.
+  Extra {
+    /// The symbol of the position.
+    symbol: String,
+    /// The quantity held in the account.
+    actual: i64,
+  },
+  /// The quantity of a position differs between the local state and
+  /// the account.
+  QuantityMismatch {
+    /// The symbol of the position.
+    symbol: String,
+    /// The locally tracked quantity.
+    expected: i64,
+    /// The quantity held in the account.
+    actual: i64,
+  },
+}
+
+impl Discrepancy {
+  /// Retrieve the symbol the discrepancy is about.
+  pub fn symbol(&self) -> &str {
+    match self {
+      Self::Missing { symbol, .. }
+      | Self::Extra { symbol, .. }
+      | Self::QuantityMismatch { symbol, .. } => symbol,
+    }
+  }
+}
+
+
+/// Compare locally tracked positions against the positions held in the
+/// account.
+///
+/// Local positions are provided as pairs of symbol and signed quantity,
+/// with short positions being represented by negative quantities.
+/// Local positions with a quantity of zero are considered flat. The
+/// discrepancies found are reported ordered by symbol.
+pub fn reconcile<I, S>(local: I, remote: &[Position]) -> Vec<Discrepancy>
+where
+  I: IntoIterator<Item = (S, i64)>,
+  S: Into<String>,
+{
+  let mut local = local
+    .into_iter()
+    .map(|(symbol, quantity)| (symbol.into(), quantity))
+    .filter(|(_, quantity)| *quantity != 0)
+    .collect::<BTreeMap<String, i64>>();
+
+  let mut discrepancies = remote
+    .iter()
+    .filter_map(|position| {
+      let actual = position.signed_quantity();
+      match local.remove(&position.symbol) {
+        Some(expected) if expected == actual => None,
+        Some(expected) => Some(Discrepancy::QuantityMismatch {
+          symbol: position.symbol.clone(),
+          expected,
+          actual,
+        }),
+        None => Some(Discrepancy::Extra {
+          symbol: position.symbol.clone(),
+          actual,
+        }),
+      }
+    })
+    .collect::<Vec<_>>();
+
+  discrepancies.extend(
+    local
+      .into_iter()
+      .map(|(symbol, expected)| Discrepancy::Missing { symbol, expected }),
+  );
+  discrepancies.sort_by(|a, b| a.symbol().cmp(b.symbol()));
+  discrepancies
+}
+
+
+/// Retrieve the positions held in the account and compare them against
+/// locally tracked ones.
+///
+/// Please refer to `reconcile` for more details.
+pub async fn reconcile_with<I, S>(
+  client: &Client,
+  local: I,
+) -> Result<Vec<Discrepancy>, RequestError<positions::GetError>>
+where
+  I: IntoIterator<Item = (S, i64)>,
+  S: Into<String>,
+{
+  let remote = client.issue::<positions::Get>(()).await?;
+  Ok(reconcile(local, &remote))
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use num_decimal::Num;
+
+  use crate::api::v2::position;
+  use crate::test_support;
+
+
+  #[test]
+  fn no_discrepancies() {
+    let remote = vec![
+      test_support::position("AAPL", position::Side::Long, 5, Num::from(100)),
+      test_support::position("SPY", position::Side::Short, 3, Num::from(100)),
+    ];
+    let local = vec![("SPY", -3), ("AAPL", 5), ("MSFT", 0)];
+    assert_eq!(reconcile(local, &remote), Vec::new());
+  }
+
+  #[test]
+  fn report_discrepancies() {
+    let remote = vec![
+      test_support::position("AAPL", position::Side::Long, 5, Num::from(100)),
+      test_support::position("SPY", position::Side::Short, 3, Num::from(100)),
+      test_support::position("XLK", position::Side::Long, 1, Num::from(100)),
+    ];
+    let local = vec![("AAPL", 4), ("MSFT", 10), ("SPY", 3)];
+
+    let expected = vec![
+      Discrepancy::QuantityMismatch {
+        symbol: "AAPL".to_string(),
+        expected: 4,
+        actual: 5,
+      },
+      Discrepancy::Missing {
+        symbol: "MSFT".to_string(),
+        expected: 10,
+      },
+      Discrepancy::QuantityMismatch {
+        symbol: "SPY".to_string(),
+        expected: 3,
+        actual: -3,
+      },
+      Discrepancy::Extra {
+        symbol: "XLK".to_string(),
+        actual: 1,
+      },
+    ];
+    assert_eq!(reconcile(local, &remote), expected);
+  }
+}
@@ -0,0 +1,182 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::Duration;
+use std::time::SystemTime;
+
+use futures::stream::unfold;
+use futures::stream::Stream;
+
+use serde_json::Error as JsonError;
+
+use tokio::time::sleep;
+
+use websocket_util::tungstenite::Error as WebSocketError;
+
+use crate::data::v2::stream::Data;
+use crate::data::v2::stream::MessageError;
+use crate::latency::Timestamped;
+
+
+/// The pace at which historical data is replayed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Pace {
+  /// Replay records as fast as they are consumed.
+  Immediate,
+  /// Replay records with the time between them, as recorded, divided
+  /// by the given factor. A factor of one replays data in real time, a
+  /// factor of 60 replays an hour's worth of data in a minute.
+  Compressed(u32),
+}
+
+
+/// Replay historical market data as a stream.
+///
+/// Records are wrapped the same way messages are by the streams
+/// reported by `Client::subscribe`, but errors are never reported.
+/// Records are expected to be ordered by time. The first record is
+/// emitted immediately, while the emission of subsequent ones may be
+/// delayed depending on `pace`. Records without a time are emitted
+/// right away.
+///
+/// Use [`replay_market_data`] for running code consuming the real time
+/// market data stream against recorded data.
+pub fn replay<I, T>(
+  records: I,
+  pace: Pace,
+) -> impl Stream<Item = Result<Result<T, JsonError>, WebSocketError>>
+where
+  I: IntoIterator<Item = T>,
+  T: Timestamped,
+{
+  replay_with(records, pace)
+}
+
+
+/// Replay recorded real time market data as a stream.
+///
+/// The returned stream has the same item type as the one reported by
+/// `Client::subscribe_market_data`, meaning that code consuming live
+/// market data can be run unchanged against recorded data. Please
+/// refer to [`replay`] for details.
+pub fn replay_market_data<I>(
+  records: I,
+  pace: Pace,
+) -> impl Stream<Item = Result<Result<Data, MessageError>, WebSocketError>>
+where
+  I: IntoIterator<Item = Data>,
+{
+  replay_with(records, pace)
+}
+
+
+/// Replay records as a stream, with items wrapped in `Ok`.
+fn replay_with<I, T, E>(
+  records: I,
+  pace: Pace,
+) -> impl Stream<Item = Result<Result<T, E>, WebSocketError>>
+where
+  I: IntoIterator<Item = T>,
+  T: Timestamped,
+{
+  let state = (records.into_iter(), None::<SystemTime>);
+
+  unfold(state, move |(mut records, last)| async move {
+    let record = records.next()?;
+    let time = record.timestamp();
+
+    if let (Pace::Compressed(factor), Some(last), Some(time)) = (pace, last, time) {
+      if let Ok(delta) = time.duration_since(last) {
+        let wait = delta / factor.max(1);
+        if wait > Duration::from_secs(0) {
+          sleep(wait).await;
+        }
+      }
+    }
+    Some((Ok(Ok(record)), (records, time.or(last))))
+  })
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::time::UNIX_EPOCH;
+
+  use futures::StreamExt;
+
+  use num_decimal::Num;
+
+  use crate::data::v2::bars::Bar;
+  use crate::data::v2::stream::SymbolData;
+
+  use tokio::time::pause;
+  use tokio::time::Instant;
+
+  use test_env_log::test;
+
+
+  /// Create a bar starting at the given number of seconds after the
+  /// epoch.
+  fn bar(secs: u64) -> Bar {
+    Bar {
+      time: UNIX_EPOCH + Duration::from_secs(secs),
+      open: Num::from(1),
+      close: Num::from(1),
+      high: Num::from(1),
+      low: Num::from(1),
+      volume: 1,
+      weighted_average: None,
+      trade_count: None,
+    }
+  }
+
+  #[test(tokio::test)]
+  async fn replay_immediately() {
+    let bars = vec![bar(0), bar(60), bar(120)];
+    let replayed = replay(bars.clone(), Pace::Immediate)
+      .map(|result| result.unwrap().unwrap())
+      .collect::<Vec<_>>()
+      .await;
+    assert_eq!(replayed, bars);
+  }
+
+  #[test(tokio::test)]
+  async fn replay_compressed() {
+    pause();
+
+    let start = Instant::now();
+    let bars = vec![bar(0), bar(60), bar(180)];
+    let replayed = replay(bars, Pace::Compressed(60))
+      .collect::<Vec<_>>()
+      .await;
+
+    assert_eq!(replayed.len(), 3);
+    // One second for the first minute and two for the next two, as
+    // measured by the paused clock (which may overshoot slightly).
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_secs(3), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_secs(4), "{:?}", elapsed);
+  }
+  /// Check that recorded real time market data is replayed with the
+  /// item type of the market data stream.
+  #[test(tokio::test)]
+  async fn replay_stream_data() {
+    let data = vec![
+      Data::Bar(SymbolData {
+        symbol: "SPY".to_string(),
+        data: bar(0),
+      }),
+      Data::Bar(SymbolData {
+        symbol: "AAPL".to_string(),
+        data: bar(60),
+      }),
+    ];
+    let replayed = replay_market_data(data, Pace::Immediate)
+      .map(|result| result.unwrap().unwrap().symbol().to_string())
+      .collect::<Vec<_>>()
+      .await;
+    assert_eq!(replayed, vec!["SPY", "AAPL"]);
+  }
+}
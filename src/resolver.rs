@@ -0,0 +1,80 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::io::Result as IoResult;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+use std::vec::IntoIter;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+
+use hyper::client::connect::dns::GaiResolver;
+use hyper::client::connect::dns::Name;
+use hyper::service::Service;
+
+
+/// The type of a user provided function for resolving host names.
+pub(crate) type Resolve =
+  Arc<dyn Fn(&str) -> BoxFuture<'static, IoResult<Vec<SocketAddr>>> + Send + Sync>;
+
+
+/// The means by which host names are resolved when connecting to the
+/// API.
+#[derive(Clone)]
+pub(crate) enum Resolver {
+  /// Host names are resolved using the system's `getaddrinfo`.
+  System(GaiResolver),
+  /// Host names are resolved using a user provided function.
+  Custom(Resolve),
+}
+
+impl Default for Resolver {
+  fn default() -> Self {
+    Self::System(GaiResolver::new())
+  }
+}
+
+impl Debug for Resolver {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    match self {
+      Self::System(resolver) => fmt.debug_tuple("System").field(resolver).finish(),
+      Self::Custom(..) => fmt.debug_tuple("Custom").finish(),
+    }
+  }
+}
+
+impl Service<Name> for Resolver {
+  type Response = IntoIter<SocketAddr>;
+  type Error = std::io::Error;
+  type Future = BoxFuture<'static, IoResult<Self::Response>>;
+
+  fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+    match self {
+      Self::System(resolver) => resolver.poll_ready(cx),
+      Self::Custom(..) => Poll::Ready(Ok(())),
+    }
+  }
+
+  fn call(&mut self, name: Name) -> Self::Future {
+    match self {
+      Self::System(resolver) => {
+        let addrs = resolver.call(name);
+        async move {
+          let addrs = addrs.await?;
+          Ok(addrs.collect::<Vec<_>>().into_iter())
+        }
+        .boxed()
+      },
+      Self::Custom(resolve) => {
+        let addrs = resolve(name.as_str());
+        async move { addrs.await.map(Vec::into_iter) }.boxed()
+      },
+    }
+  }
+}
@@ -0,0 +1,508 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::error::Error as StdError;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use num_decimal::Num;
+
+use tracing::warn;
+
+use crate::api::v2::asset;
+use crate::api::v2::order;
+use crate::api::v2::position::Position;
+use crate::api::v2::positions;
+use crate::Client;
+use crate::RequestError;
+
+
+/// Retrieve the absolute value of a number.
+fn abs(num: &Num) -> Num {
+  if num.is_negative() {
+    -num
+  } else {
+    num.clone()
+  }
+}
+
+
+/// The rules to check order submissions against.
+///
+/// Rules not set are not checked.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Rules {
+  /// The maximum notional value of a single order.
+  pub max_order_notional: Option<Num>,
+  /// The maximum number of shares held in a single symbol, long or
+  /// short, after the order was filled.
+  pub max_position: Option<u64>,
+  /// The maximum gross exposure, i.e., the sum of the absolute market
+  /// values of all positions, after the order was filled.
+  pub max_gross_exposure: Option<Num>,
+  /// Symbols that must not be traded.
+  pub restricted: HashSet<String>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+
+/// A violation of one of the configured rules.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Violation {
+  /// The symbol is restricted from trading.
+  Restricted {
+    /// The symbol of the order.
+    symbol: String,
+  },
+  /// The notional value of the order exceeds the maximum.
+  OrderNotional {
+    /// The notional value of the order.
+    notional: Num,
+    /// The maximum notional value allowed.
+    max: Num,
+  },
+  /// The resulting position would exceed the maximum size.
+  PositionSize {
+    /// The symbol of the order.
+    symbol: String,
+    /// The signed quantity held after the order was filled.
+    quantity: i64,
+    /// The maximum number of shares allowed.
+    max: u64,
+  },
+  /// The quantity of the resulting position is not representable.
+  PositionOverflow {
+    /// The symbol of the order.
+    symbol: String,
+  },
+  /// The resulting gross exposure would exceed the maximum.
+  GrossExposure {
+    /// The gross exposure after the order was filled.
+    exposure: Num,
+    /// The maximum gross exposure allowed.
+    max: Num,
+  },
+  /// The price of the order could not be determined, but it is
+  /// required for checking one of the rules.
+  UnknownPrice {
+    /// The symbol of the order.
+    symbol: String,
+  },
+}
+
+impl Display for Violation {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    match self {
+      Self::Restricted { symbol } => write!(fmt, "symbol {} is restricted", symbol),
+      Self::OrderNotional { notional, max } => write!(
+        fmt,
+        "order notional of {} exceeds maximum of {}",
+        notional, max
+      ),
+      Self::PositionSize {
+        symbol,
+        quantity,
+        max,
+      } => write!(
+        fmt,
+        "position of {} shares in {} exceeds maximum of {}",
+        quantity, symbol, max
+      ),
+      Self::PositionOverflow { symbol } => {
+        write!(fmt, "position in {} would overflow", symbol)
+      },
+      Self::GrossExposure { exposure, max } => write!(
+        fmt,
+        "gross exposure of {} exceeds maximum of {}",
+        exposure, max
+      ),
+      Self::UnknownPrice { symbol } => {
+        write!(fmt, "unable to determine price of order in {}", symbol)
+      },
+    }
+  }
+}
+
+impl StdError for Violation {}
+
+
+/// An error reported when submitting an order through `RiskChecked`.
+#[derive(Debug)]
+pub enum RiskError {
+  /// The order violates one of the configured rules and was not
+  /// submitted.
+  Violation(Violation),
+  /// The positions held could not be retrieved.
+  Positions(RequestError<positions::GetError>),
+  /// The order submission failed.
+  Order(RequestError<order::PostError>),
+}
+
+impl Display for RiskError {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    match self {
+      Self::Violation(err) => write!(fmt, "order rejected locally: {}", err),
+      Self::Positions(err) => write!(fmt, "failed to retrieve positions: {}", err),
+      Self::Order(err) => write!(fmt, "failed to submit order: {}", err),
+    }
+  }
+}
+
+impl StdError for RiskError {
+  fn source(&self) -> Option<&(dyn StdError + 'static)> {
+    match self {
+      Self::Violation(err) => Some(err),
+      Self::Positions(err) => Some(err),
+      Self::Order(err) => Some(err),
+    }
+  }
+}
+
+impl From<Violation> for RiskError {
+  fn from(violation: Violation) -> Self {
+    Self::Violation(violation)
+  }
+}
+
+
+impl Rules {
+  /// Check an order request against the rules, given the positions
+  /// currently held.
+  ///
+  /// `price` is the expected price per share. If it is not provided,
+  /// the limit or stop price of the order is used, or, failing that,
+  /// the current price of an existing position in the symbol.
+  #[allow(clippy::result_large_err)]
+  pub fn check(
+    &self,
+    request: &order::OrderReq,
+    price: Option<&Num>,
+    positions: &[Position],
+  ) -> Result<(), Violation> {
+    // We can only reason about orders identified by symbol. Orders
+    // referencing an asset by ID or with an exchange are matched by
+    // their plain symbol.
+    let symbol = match &request.symbol {
+      asset::Symbol::Sym(symbol)
+      | asset::Symbol::SymExchg(symbol, ..)
+      | asset::Symbol::SymExchgCls(symbol, ..) => symbol.clone(),
+      asset::Symbol::Id(id) => positions
+        .iter()
+        .find(|position| position.asset_id == *id)
+        .map(|position| position.symbol.clone())
+        .unwrap_or_else(|| request.symbol.to_string()),
+    };
+
+    if self.restricted.contains(&symbol) {
+      return Err(Violation::Restricted { symbol })
+    }
+
+    let position = positions.iter().find(|position| position.symbol == symbol);
+    let current = position.map(Position::signed_quantity).unwrap_or(0);
+    let updated = match current.checked_add(request.side.signed(request.quantity)) {
+      Some(updated) => updated,
+      None => return Err(Violation::PositionOverflow { symbol }),
+    };
+
+    if let Some(max) = self.max_position {
+      if updated.unsigned_abs() > max {
+        return Err(Violation::PositionSize {
+          symbol,
+          quantity: updated,
+          max,
+        })
+      }
+    }
+
+    if self.max_order_notional.is_none() && self.max_gross_exposure.is_none() {
+      return Ok(())
+    }
+
+    let price = price
+      .or(request.limit_price.as_ref())
+      .or(request.stop_price.as_ref())
+      .or_else(|| position.map(|position| &position.current_price));
+    let price = match price {
+      Some(price) => price,
+      None => return Err(Violation::UnknownPrice { symbol }),
+    };
+
+    if let Some(max) = &self.max_order_notional {
+      let notional = price * Num::from(request.quantity);
+      if &notional > max {
+        return Err(Violation::OrderNotional {
+          notional,
+          max: max.clone(),
+        })
+      }
+    }
+
+    if let Some(max) = &self.max_gross_exposure {
+      let exposure = positions
+        .iter()
+        .filter(|position| position.symbol != symbol)
+        .fold(Num::from(0), |exposure, position| {
+          exposure + abs(&position.market_value)
+        });
+      let exposure = exposure + price * Num::from(updated.unsigned_abs());
+      if &exposure > max {
+        return Err(Violation::GrossExposure {
+          exposure,
+          max: max.clone(),
+        })
+      }
+    }
+    Ok(())
+  }
+}
+
+
+/// A wrapper around a `Client` checking order submissions against a
+/// set of rules before they are sent.
+///
+/// Orders violating any of the rules are rejected locally, without
+/// being submitted. Checks are based on the positions held at the time
+/// of submission, as retrieved from the /v2/positions endpoint; orders
+/// that are still open are not taken into account.
+///
+/// ```no_run
+/// # use apca::api::v2::order;
+/// # use apca::risk::RiskChecked;
+/// # use apca::risk::Rules;
+/// # use apca::ApiInfo;
+/// # use apca::Client;
+/// # use num_decimal::Num;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let rules = Rules {
+///   max_order_notional: Some(Num::from(10_000)),
+///   ..Default::default()
+/// };
+/// let client = RiskChecked::new(Client::new(ApiInfo::from_env()?), rules);
+/// let request = order::OrderReqInit::default().init("AAPL", order::Side::Buy, 1);
+/// let order = client.submit(request, Some(Num::from(125))).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RiskChecked<C> {
+  /// The wrapped client.
+  client: C,
+  /// The rules to check orders against.
+  rules: Rules,
+}
+
+impl<C> RiskChecked<C>
+where
+  C: Borrow<Client>,
+{
+  /// Create a new `RiskChecked` object wrapping the given client.
+  pub fn new(client: C, rules: Rules) -> Self {
+    Self { client, rules }
+  }
+
+  /// Retrieve the wrapped client.
+  pub fn client(&self) -> &Client {
+    self.client.borrow()
+  }
+
+  /// Retrieve the rules orders are checked against.
+  pub fn rules(&self) -> &Rules {
+    &self.rules
+  }
+
+  /// Check an order against the rules and submit it if no rule is
+  /// violated.
+  ///
+  /// Please refer to `Rules::check` for how the price of the order is
+  /// determined.
+  pub async fn submit(
+    &self,
+    request: order::OrderReq,
+    price: Option<Num>,
+  ) -> Result<order::Order, RiskError> {
+    let positions = self
+      .client()
+      .issue::<positions::Get>(())
+      .await
+      .map_err(RiskError::Positions)?;
+
+    if let Err(violation) = self.rules.check(&request, price.as_ref(), &positions) {
+      warn!(violation = display(&violation), "rejecting order");
+      return Err(RiskError::Violation(violation))
+    }
+
+    self
+      .client()
+      .issue::<order::Post>(request)
+      .await
+      .map_err(RiskError::Order)
+  }
+
+  /// Unwrap the `RiskChecked` object, returning the wrapped client.
+  pub fn into_inner(self) -> C {
+    self.client
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use test_env_log::test;
+
+  use crate::api::v2::position;
+  use crate::test_support;
+
+
+  /// Create a request for a market order.
+  fn market_order(symbol: &str, side: order::Side, quantity: u64) -> order::OrderReq {
+    order::OrderReqInit::default().init(symbol, side, quantity)
+  }
+
+  /// Check that orders for restricted symbols are rejected.
+  #[test]
+  fn reject_restricted_symbol() {
+    let rules = Rules {
+      restricted: vec!["GME".to_string()].into_iter().collect(),
+      ..Default::default()
+    };
+    let request = market_order("GME", order::Side::Buy, 1);
+    let err = rules.check(&request, None, &[]).unwrap_err();
+    assert_eq!(
+      err,
+      Violation::Restricted {
+        symbol: "GME".to_string()
+      }
+    );
+    assert!(rules
+      .check(&market_order("AAPL", order::Side::Buy, 1), None, &[])
+      .is_ok());
+  }
+
+  /// Check that orders exceeding the maximum notional are rejected.
+  #[test]
+  fn check_order_notional() {
+    let rules = Rules {
+      max_order_notional: Some(Num::from(1000)),
+      ..Default::default()
+    };
+    let positions = vec![test_support::position(
+      "AAPL",
+      position::Side::Long,
+      5,
+      Num::from(120),
+    )];
+
+    // The price is taken from the existing position.
+    let request = market_order("AAPL", order::Side::Buy, 9);
+    let err = rules.check(&request, None, &positions).unwrap_err();
+    assert_eq!(
+      err,
+      Violation::OrderNotional {
+        notional: Num::from(1080),
+        max: Num::from(1000),
+      }
+    );
+    assert!(rules
+      .check(&request, Some(&Num::from(100)), &positions)
+      .is_ok());
+
+    let request = market_order("MSFT", order::Side::Buy, 1);
+    let err = rules.check(&request, None, &positions).unwrap_err();
+    assert_eq!(
+      err,
+      Violation::UnknownPrice {
+        symbol: "MSFT".to_string()
+      }
+    );
+  }
+
+  /// Check that orders exceeding the maximum position are rejected.
+  #[test]
+  fn check_position_size() {
+    let rules = Rules {
+      max_position: Some(10),
+      ..Default::default()
+    };
+    let positions = vec![test_support::position(
+      "SPY",
+      position::Side::Short,
+      6,
+      Num::from(400),
+    )];
+
+    let request = market_order("SPY", order::Side::Sell, 5);
+    let err = rules.check(&request, None, &positions).unwrap_err();
+    assert_eq!(
+      err,
+      Violation::PositionSize {
+        symbol: "SPY".to_string(),
+        quantity: -11,
+        max: 10,
+      }
+    );
+
+    let request = market_order("SPY", order::Side::Buy, 16);
+    assert!(rules.check(&request, None, &positions).is_ok());
+  }
+
+  /// Check that orders overflowing a position's quantity are rejected.
+  #[test]
+  fn check_position_overflow() {
+    let rules = Rules::default();
+    let positions = vec![test_support::position(
+      "SPY",
+      position::Side::Long,
+      i64::MAX as u64,
+      Num::from(400),
+    )];
+
+    let request = market_order("SPY", order::Side::Buy, 1);
+    let err = rules.check(&request, None, &positions).unwrap_err();
+    assert_eq!(
+      err,
+      Violation::PositionOverflow {
+        symbol: "SPY".to_string()
+      }
+    );
+
+    let request = market_order("SPY", order::Side::Sell, 1);
+    assert!(rules.check(&request, None, &positions).is_ok());
+  }
+
+  /// Check that orders exceeding the maximum exposure are rejected.
+  #[test]
+  fn check_gross_exposure() {
+    let rules = Rules {
+      max_gross_exposure: Some(Num::from(4000)),
+      ..Default::default()
+    };
+    let positions = vec![
+      test_support::position("AAPL", position::Side::Long, 10, Num::from(120)),
+      test_support::position("SPY", position::Side::Short, 5, Num::from(400)),
+    ];
+
+    // Covering a short position reduces the exposure.
+    let request = market_order("SPY", order::Side::Buy, 2);
+    assert!(rules.check(&request, None, &positions).is_ok());
+
+    let request = market_order("MSFT", order::Side::Buy, 4);
+    let err = rules
+      .check(&request, Some(&Num::from(250)), &positions)
+      .unwrap_err();
+    assert_eq!(
+      err,
+      Violation::GrossExposure {
+        exposure: Num::from(4200),
+        max: Num::from(4000),
+      }
+    );
+  }
+}
@@ -0,0 +1,512 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Submission of an order on a schedule of trading days, e.g., for
+//! dollar cost averaging.
+//!
+//! A `Scheduler` submits its order at market open, or at a fixed
+//! offset after it, on each trading session selected by a predicate.
+//! Every submission carries a client order ID derived from the date of
+//! the session and is recorded in a `Journal`, so that restarting the
+//! scheduler never results in a session's order being submitted twice.
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use apca::api::v2::order;
+//! # use apca::schedule::Config;
+//! # use apca::schedule::FileJournal;
+//! # use apca::schedule::Scheduler;
+//! # use apca::ApiInfo;
+//! # use apca::Client;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = Client::new(ApiInfo::from_env()?);
+//! let request = order::OrderReqInit::default().init("SPY", order::Side::Buy, 1);
+//! let config = Config {
+//!   offset: Duration::from_secs(15 * 60),
+//!   id_prefix: "dca-spy".to_string(),
+//!   ..Default::default()
+//! };
+//! // Buy one share of SPY fifteen minutes after the open of each
+//! // trading session.
+//! let scheduler = Scheduler::new(request, |_| true, FileJournal::new("dca-spy.log"), config);
+//! let _ = scheduler.run(&client).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::error::Error as StdError;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::fs::read_to_string;
+use std::fs::OpenOptions;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use tokio::time::sleep;
+
+use tracing::debug;
+use tracing::info;
+
+use crate::api::v2::calendar;
+use crate::api::v2::order;
+use crate::sessions::Session;
+use crate::sessions::TradingCalendar;
+use crate::util::system_time_to_date_string;
+use crate::Client;
+use crate::RequestError;
+
+
+/// The amount of time for which to cache the market calendar.
+const CALENDAR_TTL: Duration = Duration::from_secs(6 * 3600);
+/// The time span to look ahead when searching for the next session on
+/// which to submit the order.
+const HORIZON: Duration = Duration::from_secs(366 * 86400);
+/// The maximum length of a client order ID prefix, leaving room for
+/// the separator and the session's date within the maximum client
+/// order ID length of 48 characters.
+const MAX_ID_PREFIX_LEN: usize = 48 - "-YYYY-MM-DD".len();
+
+
+/// A trait for persisting the trading sessions for which the order of
+/// a `Scheduler` has been submitted.
+pub trait Journal {
+  /// Check whether the order for the session on the given date has
+  /// been submitted already.
+  fn contains(&self, date: SystemTime) -> Result<bool, IoError>;
+
+  /// Record that the order for the session on the given date has been
+  /// submitted.
+  fn record(&self, date: SystemTime) -> Result<(), IoError>;
+}
+
+
+/// A `Journal` keeping track of submissions in memory only.
+///
+/// Note that with this journal, protection against duplicate
+/// submissions after a restart relies solely on the client order IDs
+/// of previously submitted orders.
+#[derive(Debug, Default)]
+pub struct MemoryJournal {
+  /// The dates of the sessions for which the order was submitted.
+  dates: Mutex<HashSet<SystemTime>>,
+}
+
+impl Journal for MemoryJournal {
+  fn contains(&self, date: SystemTime) -> Result<bool, IoError> {
+    Ok(self.dates.lock().unwrap().contains(&date))
+  }
+
+  fn record(&self, date: SystemTime) -> Result<(), IoError> {
+    let _ = self.dates.lock().unwrap().insert(date);
+    Ok(())
+  }
+}
+
+
+/// A `Journal` keeping track of submissions in a file, containing one
+/// date (YYYY-MM-DD) per line.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FileJournal {
+  /// The path to the file.
+  path: PathBuf,
+}
+
+impl FileJournal {
+  /// Create a new `FileJournal` using the file at the given path, which
+  /// is created as necessary.
+  pub fn new<P>(path: P) -> Self
+  where
+    P: Into<PathBuf>,
+  {
+    Self { path: path.into() }
+  }
+}
+
+impl Journal for FileJournal {
+  fn contains(&self, date: SystemTime) -> Result<bool, IoError> {
+    let content = match read_to_string(&self.path) {
+      Ok(content) => content,
+      Err(err) if err.kind() == ErrorKind::NotFound => return Ok(false),
+      Err(err) => return Err(err),
+    };
+
+    let date = system_time_to_date_string(&date);
+    Ok(content.lines().any(|line| line.trim() == date))
+  }
+
+  fn record(&self, date: SystemTime) -> Result<(), IoError> {
+    let mut file = OpenOptions::new()
+      .create(true)
+      .append(true)
+      .open(&self.path)?;
+    let () = writeln!(file, "{}", system_time_to_date_string(&date))?;
+    file.sync_all()
+  }
+}
+
+
+/// An error reported by a `Scheduler`.
+#[derive(Debug)]
+pub enum ScheduleError {
+  /// The market calendar could not be retrieved.
+  Calendar(RequestError<calendar::GetError>),
+  /// The check for a previously submitted order failed.
+  Lookup(RequestError<order::GetByClientIdError>),
+  /// The order submission failed.
+  Order(RequestError<order::PostError>),
+  /// The journal could not be accessed.
+  Journal(IoError),
+  /// No upcoming trading session was selected.
+  NoSession,
+}
+
+impl Display for ScheduleError {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    match self {
+      Self::Calendar(err) => write!(fmt, "failed to retrieve market calendar: {}", err),
+      Self::Lookup(err) => write!(fmt, "failed to look up previously submitted order: {}", err),
+      Self::Order(err) => write!(fmt, "failed to submit order: {}", err),
+      Self::Journal(err) => write!(fmt, "failed to access journal: {}", err),
+      Self::NoSession => fmt.write_str("no upcoming trading session was selected"),
+    }
+  }
+}
+
+impl StdError for ScheduleError {
+  fn source(&self) -> Option<&(dyn StdError + 'static)> {
+    match self {
+      Self::Calendar(err) => Some(err),
+      Self::Lookup(err) => Some(err),
+      Self::Order(err) => Some(err),
+      Self::Journal(err) => Some(err),
+      Self::NoSession => None,
+    }
+  }
+}
+
+
+/// The configuration of a `Scheduler`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Config {
+  /// The amount of time after market open at which to submit the
+  /// order.
+  ///
+  /// Sessions closing before that time are skipped.
+  pub offset: Duration,
+  /// The prefix of the client order IDs of submitted orders, which are
+  /// of the form `<prefix>-<YYYY-MM-DD>`.
+  ///
+  /// The prefix should be unique to the schedule and must not exceed
+  /// 37 characters, given the maximum client order ID length of 48.
+  /// `Scheduler::new` panics otherwise.
+  pub id_prefix: String,
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      offset: Duration::from_secs(0),
+      id_prefix: "schedule".to_string(),
+      _non_exhaustive: (),
+    }
+  }
+}
+
+
+/// A scheduler submitting an order on selected trading sessions.
+pub struct Scheduler<J> {
+  /// The order to submit.
+  request: order::OrderReq,
+  /// The predicate selecting the sessions on which to submit the
+  /// order.
+  days: Box<dyn Fn(&Session) -> bool + Send + Sync>,
+  /// The journal of sessions for which the order was submitted.
+  journal: J,
+  /// The scheduler's configuration.
+  config: Config,
+  /// The calendar providing trading sessions.
+  calendar: TradingCalendar,
+}
+
+impl<J> Scheduler<J>
+where
+  J: Journal,
+{
+  /// Create a new `Scheduler` submitting the given order on all
+  /// trading sessions for which `days` returns `true`.
+  ///
+  /// Any client order ID set on `request` is replaced with one derived
+  /// from the session, as per `Config::id_prefix`.
+  ///
+  /// # Panics
+  /// The method panics if `Config::id_prefix` exceeds 37 characters.
+  pub fn new<F>(request: order::OrderReq, days: F, journal: J, config: Config) -> Self
+  where
+    F: Fn(&Session) -> bool + Send + Sync + 'static,
+  {
+    assert!(
+      config.id_prefix.chars().count() <= MAX_ID_PREFIX_LEN,
+      "client order ID prefix '{}' exceeds maximum length of {} characters",
+      config.id_prefix,
+      MAX_ID_PREFIX_LEN,
+    );
+
+    Self {
+      request,
+      days: Box::new(days),
+      journal,
+      config,
+      calendar: TradingCalendar::new(CALENDAR_TTL),
+    }
+  }
+
+  /// Retrieve the client order ID of the order for the given session.
+  pub fn client_order_id(&self, session: &Session) -> String {
+    format!(
+      "{}-{}",
+      self.config.id_prefix,
+      system_time_to_date_string(&session.date)
+    )
+  }
+
+  /// Retrieve the time at which to submit the order for the given
+  /// session, if the session is selected and the order has not been
+  /// submitted for it yet.
+  fn submission_time(&self, session: &Session) -> Result<Option<SystemTime>, ScheduleError> {
+    let time = session.open + self.config.offset;
+    if time >= session.close || !(self.days)(session) {
+      return Ok(None)
+    }
+
+    let submitted = self
+      .journal
+      .contains(session.date)
+      .map_err(ScheduleError::Journal)?;
+    if submitted {
+      Ok(None)
+    } else {
+      Ok(Some(time))
+    }
+  }
+
+  /// Determine the next session on which to submit the order, along
+  /// with the time of submission, which may be in the past if the
+  /// session is in progress already.
+  ///
+  /// Sessions up to a year ahead of `time` are considered.
+  pub async fn next(
+    &self,
+    client: &Client,
+    time: SystemTime,
+  ) -> Result<(Session, SystemTime), ScheduleError> {
+    // Sessions are looked up by dates in UTC, but session times are in
+    // US Eastern time. Start a day early so as to not miss a session
+    // that is dated a day earlier in the latter.
+    let start = time - Duration::from_secs(86400);
+    let sessions = self
+      .calendar
+      .sessions(client, start, time + HORIZON)
+      .await
+      .map_err(ScheduleError::Calendar)?;
+
+    for session in sessions.iter().filter(|session| session.close > time) {
+      if let Some(submission) = self.submission_time(session)? {
+        return Ok((*session, submission))
+      }
+    }
+    Err(ScheduleError::NoSession)
+  }
+
+  /// Submit the order for the given session, unless it was submitted
+  /// already.
+  ///
+  /// An order is considered submitted if the session is recorded in
+  /// the journal or if an order with the session's client order ID
+  /// exists. `None` is returned in that case.
+  pub async fn submit(
+    &self,
+    client: &Client,
+    session: &Session,
+  ) -> Result<Option<order::Order>, ScheduleError> {
+    if self
+      .journal
+      .contains(session.date)
+      .map_err(ScheduleError::Journal)?
+    {
+      return Ok(None)
+    }
+
+    let client_order_id = self.client_order_id(session);
+    match client
+      .issue::<order::GetByClientId>(client_order_id.clone())
+      .await
+    {
+      Ok(_) => {
+        debug!(
+          client_order_id = display(&client_order_id),
+          "order submitted previously"
+        );
+        let () = self
+          .journal
+          .record(session.date)
+          .map_err(ScheduleError::Journal)?;
+        return Ok(None)
+      },
+      Err(RequestError::Endpoint(order::GetByClientIdError::NotFound(..))) => (),
+      Err(err) => return Err(ScheduleError::Lookup(err)),
+    }
+
+    let mut request = self.request.clone();
+    request.client_order_id = Some(client_order_id);
+
+    let order = client
+      .issue::<order::Post>(request)
+      .await
+      .map_err(ScheduleError::Order)?;
+    info!(
+      id = display(&order.id),
+      client_order_id = display(&order.client_order_id),
+      "submitted scheduled order"
+    );
+    let () = self
+      .journal
+      .record(session.date)
+      .map_err(ScheduleError::Journal)?;
+    Ok(Some(order))
+  }
+
+  /// Submit the order on all selected sessions, waiting for the
+  /// respective submission time in between.
+  ///
+  /// This method only returns on error.
+  pub async fn run(&self, client: &Client) -> Result<Infallible, ScheduleError> {
+    loop {
+      let (session, time) = self.next(client, SystemTime::now()).await?;
+      if let Ok(wait) = time.duration_since(SystemTime::now()) {
+        debug!(wait = debug(&wait), "waiting for scheduled submission");
+        sleep(wait).await
+      }
+      let _ = self.submit(client, &session).await?;
+    }
+  }
+}
+
+impl<J> Debug for Scheduler<J>
+where
+  J: Debug,
+{
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    fmt
+      .debug_struct("Scheduler")
+      .field("request", &self.request)
+      .field("journal", &self.journal)
+      .field("config", &self.config)
+      .finish()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::env::temp_dir;
+  use std::fs::remove_file;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+  use time_util::parse_system_time_from_str;
+
+  use uuid::Uuid;
+
+  use crate::api::v2::calendar::OpenClose;
+
+
+  /// Parse a single trading session from market calendar JSON.
+  fn session(json: &str) -> Session {
+    Session::from(from_json::<OpenClose>(json).unwrap())
+  }
+
+  /// Check that a `FileJournal` persists the dates recorded in it.
+  #[test]
+  fn record_in_file_journal() {
+    let path = temp_dir().join(Uuid::new_v4().to_string());
+    let date1 = parse_system_time_from_str("2021-03-01T00:00:00Z").unwrap();
+    let date2 = parse_system_time_from_str("2021-03-02T00:00:00Z").unwrap();
+
+    let journal = FileJournal::new(&path);
+    assert!(!journal.contains(date1).unwrap());
+
+    let () = journal.record(date1).unwrap();
+    assert!(journal.contains(date1).unwrap());
+    assert!(!journal.contains(date2).unwrap());
+
+    // A new journal for the same file sees previous records.
+    let journal = FileJournal::new(&path);
+    let () = journal.record(date2).unwrap();
+    assert!(journal.contains(date1).unwrap());
+    assert!(journal.contains(date2).unwrap());
+
+    remove_file(path).unwrap();
+  }
+
+  /// Check that we determine submission times and client order IDs
+  /// correctly.
+  #[test]
+  fn determine_submission() {
+    let regular = session(r#"{"date": "2020-11-25", "open": "09:30", "close": "16:00"}"#);
+    let early = session(r#"{"date": "2020-11-27", "open": "09:30", "close": "13:00"}"#);
+
+    let request = order::OrderReqInit::default().init("SPY", order::Side::Buy, 1);
+    let config = Config {
+      offset: Duration::from_secs(4 * 3600),
+      id_prefix: "dca-spy".to_string(),
+      ..Default::default()
+    };
+    let scheduler = Scheduler::new(
+      request,
+      |session| !session.early_close,
+      MemoryJournal::default(),
+      config,
+    );
+
+    assert_eq!(scheduler.client_order_id(&regular), "dca-spy-2020-11-25");
+    assert_eq!(
+      scheduler.submission_time(&regular).unwrap(),
+      Some(parse_system_time_from_str("2020-11-25T18:30:00Z").unwrap())
+    );
+    // Early closes are not selected.
+    assert_eq!(scheduler.submission_time(&early).unwrap(), None);
+
+    // Sessions recorded in the journal are skipped.
+    let () = scheduler.journal.record(regular.date).unwrap();
+    assert_eq!(scheduler.submission_time(&regular).unwrap(), None);
+  }
+
+  /// Check that creating a `Scheduler` with an overly long client order
+  /// ID prefix panics.
+  #[test]
+  #[should_panic(expected = "exceeds maximum length of 37 characters")]
+  fn reject_long_id_prefix() {
+    let request = order::OrderReqInit::default().init("SPY", order::Side::Buy, 1);
+    let config = Config {
+      id_prefix: "x".repeat(38),
+      ..Default::default()
+    };
+    let _ = Scheduler::new(request, |_| true, MemoryJournal::default(), config);
+  }
+}
@@ -0,0 +1,234 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use crate::api::v2::calendar;
+use crate::api::v2::calendar::CalendarReq;
+use crate::api::v2::calendar::OpenClose;
+use crate::cache::Cache;
+use crate::util::us_eastern_offset;
+use crate::Client;
+use crate::RequestError;
+
+
+/// The time the market regularly closes at, as the duration since
+/// midnight (US Eastern time).
+const REGULAR_CLOSE: Duration = Duration::from_secs(16 * 3600);
+/// The time span to look ahead when searching for the next trading
+/// session. No market closure in recent history lasted anywhere near
+/// as long.
+const LOOKAHEAD: Duration = Duration::from_secs(14 * 86400);
+
+
+/// Convert a date along with a time of day in US Eastern time into a
+/// `SystemTime`.
+fn eastern_time(date: SystemTime, time_of_day: Duration) -> SystemTime {
+  // It is fine to unwrap here because no time relevant to us can ever
+  // be before `UNIX_EPOCH`.
+  let local = (date + time_of_day).duration_since(UNIX_EPOCH).unwrap();
+  let secs = local.as_secs() as i64 - us_eastern_offset(local.as_secs());
+  UNIX_EPOCH + Duration::from_secs(secs as u64)
+}
+
+
+/// Truncate a `SystemTime` to the start of its day (in UTC).
+fn start_of_day(time: SystemTime) -> SystemTime {
+  // It is fine to unwrap here because no time relevant to us can ever
+  // be before `UNIX_EPOCH`.
+  let secs = time.duration_since(UNIX_EPOCH).unwrap().as_secs();
+  UNIX_EPOCH + Duration::from_secs(secs - secs % 86400)
+}
+
+
+/// A single trading session, i.e., the regular trading hours of a
+/// trading day.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Session {
+  /// The trading day, at midnight UTC.
+  pub date: SystemTime,
+  /// The time the market opens at.
+  pub open: SystemTime,
+  /// The time the market closes at.
+  pub close: SystemTime,
+  /// Whether the market closes before its regular closing time on
+  /// this day, e.g., on the day after Thanksgiving.
+  pub early_close: bool,
+}
+
+impl Session {
+  /// Retrieve the duration of the session.
+  pub fn duration(&self) -> Duration {
+    self
+      .close
+      .duration_since(self.open)
+      .unwrap_or_else(|_| Duration::from_secs(0))
+  }
+
+  /// Check whether the market is open at the given time during this
+  /// session.
+  pub fn contains(&self, time: SystemTime) -> bool {
+    (self.open..self.close).contains(&time)
+  }
+}
+
+impl From<OpenClose> for Session {
+  fn from(open_close: OpenClose) -> Self {
+    Self {
+      date: open_close.date,
+      open: eastern_time(open_close.date, open_close.open),
+      close: eastern_time(open_close.date, open_close.close),
+      early_close: open_close.close < REGULAR_CLOSE,
+    }
+  }
+}
+
+
+/// Find the first of the given sessions, which have to be in ascending
+/// order, that has not closed yet at the given time.
+fn next_session<I>(sessions: I, time: SystemTime) -> Option<Session>
+where
+  I: IntoIterator<Item = Session>,
+{
+  sessions.into_iter().find(|session| session.close > time)
+}
+
+
+/// A provider of trading sessions, caching the market calendar.
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use std::time::SystemTime;
+/// # use apca::sessions::TradingCalendar;
+/// # use apca::ApiInfo;
+/// # use apca::Client;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let start = SystemTime::now();
+/// # let end = start + Duration::from_secs(86400 * 30);
+/// let client = Client::new(ApiInfo::from_env()?);
+/// let calendar = TradingCalendar::new(Duration::from_secs(86400));
+/// for session in calendar.sessions(&client, start, end).await? {
+///   println!("{:?} - {:?}", session.open, session.close);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TradingCalendar {
+  /// The cache of market calendar responses.
+  cache: Cache<calendar::Get>,
+}
+
+impl TradingCalendar {
+  /// Create a new `TradingCalendar` caching the market calendar for
+  /// the given amount of time.
+  pub fn new(ttl: Duration) -> Self {
+    Self {
+      cache: Cache::new(ttl),
+    }
+  }
+
+  /// Retrieve the trading sessions on the days between `start` and
+  /// `end` (both inclusive, as dates in UTC), oldest first.
+  pub async fn sessions(
+    &self,
+    client: &Client,
+    start: SystemTime,
+    end: SystemTime,
+  ) -> Result<Vec<Session>, RequestError<calendar::GetError>> {
+    // Only the dates matter to the request, so we truncate times for
+    // requests on the same days to hit the same cache entry.
+    let request = CalendarReq {
+      start: start_of_day(start),
+      end: start_of_day(end),
+    };
+    let calendar = self.cache.issue(client, request).await?;
+    Ok(calendar.into_iter().map(Session::from).collect())
+  }
+
+  /// Retrieve the trading session that is in progress at the given
+  /// time or, if the market is closed, the next one to start.
+  pub async fn next_session(
+    &self,
+    client: &Client,
+    time: SystemTime,
+  ) -> Result<Option<Session>, RequestError<calendar::GetError>> {
+    // Sessions are looked up by dates in UTC, but session times are
+    // in US Eastern time. Start a day early so as to not miss a
+    // session that is dated a day earlier in the latter.
+    let start = time - Duration::from_secs(86400);
+    let sessions = self.sessions(client, start, time + LOOKAHEAD).await?;
+    Ok(next_session(sessions, time))
+  }
+
+  /// Invalidate all cached market calendar responses.
+  pub fn clear(&self) {
+    self.cache.clear()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+  use time_util::parse_system_time_from_str;
+
+
+  /// Parse a market calendar as reported by the /v2/calendar endpoint.
+  fn calendar() -> Vec<Session> {
+    let response = r#"[
+  {"date": "2020-11-25", "open": "09:30", "close": "16:00"},
+  {"date": "2020-11-27", "open": "09:30", "close": "13:00"},
+  {"date": "2021-07-06", "open": "09:30", "close": "16:00"}
+]"#;
+    from_json::<Vec<OpenClose>>(response)
+      .unwrap()
+      .into_iter()
+      .map(Session::from)
+      .collect()
+  }
+
+  /// Check that we correctly convert market calendar entries into
+  /// sessions.
+  #[test]
+  fn convert_sessions() {
+    let time = |time| parse_system_time_from_str(time).unwrap();
+    let sessions = calendar();
+
+    assert_eq!(sessions[0].open, time("2020-11-25T14:30:00Z"));
+    assert_eq!(sessions[0].close, time("2020-11-25T21:00:00Z"));
+    assert!(!sessions[0].early_close);
+    assert_eq!(sessions[0].duration(), Duration::from_secs(23400));
+
+    assert_eq!(sessions[1].close, time("2020-11-27T18:00:00Z"));
+    assert!(sessions[1].early_close);
+
+    // Daylight saving time is in effect in July.
+    assert_eq!(sessions[2].open, time("2021-07-06T13:30:00Z"));
+    assert!(sessions[2].contains(time("2021-07-06T19:59:59Z")));
+    assert!(!sessions[2].contains(time("2021-07-06T20:00:00Z")));
+  }
+
+  /// Check that we find the correct next session for a given time.
+  #[test]
+  fn find_next_session() {
+    let time = |time| parse_system_time_from_str(time).unwrap();
+    let sessions = calendar();
+
+    let next = next_session(sessions.clone(), time("2020-11-25T15:00:00Z")).unwrap();
+    assert_eq!(next, sessions[0]);
+
+    let next = next_session(sessions.clone(), time("2020-11-26T12:00:00Z")).unwrap();
+    assert_eq!(next, sessions[1]);
+
+    assert_eq!(next_session(sessions, time("2021-07-07T00:00:00Z")), None);
+  }
+}
@@ -0,0 +1,205 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::error::Error as StdError;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use futures::future::try_join;
+use futures::TryFutureExt as _;
+
+use tracing::warn;
+
+use crate::api::v2::account;
+use crate::api::v2::account::Account;
+use crate::api::v2::asset;
+use crate::api::v2::asset::Asset;
+use crate::api::v2::order;
+use crate::Client;
+use crate::RequestError;
+
+
+/// A reason for an asset not being available for short selling.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Rejection {
+  /// Shorting is disabled for the account.
+  ShortingDisabled,
+  /// The asset is not shortable.
+  NotShortable {
+    /// The symbol of the asset.
+    symbol: String,
+  },
+  /// The asset is not easy-to-borrow.
+  NotEasyToBorrow {
+    /// The symbol of the asset.
+    symbol: String,
+  },
+}
+
+impl Display for Rejection {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    match self {
+      Self::ShortingDisabled => fmt.write_str("shorting is disabled for the account"),
+      Self::NotShortable { symbol } => write!(fmt, "{} is not shortable", symbol),
+      Self::NotEasyToBorrow { symbol } => write!(fmt, "{} is not easy-to-borrow", symbol),
+    }
+  }
+}
+
+impl StdError for Rejection {}
+
+
+/// An error reported when checking whether an asset can be sold short
+/// or when submitting a short sell order.
+#[derive(Debug)]
+pub enum ShortError {
+  /// The asset can not be sold short and no order was submitted.
+  Rejected(Rejection),
+  /// The account could not be retrieved.
+  Account(RequestError<account::GetError>),
+  /// The asset could not be retrieved.
+  Asset(RequestError<asset::GetError>),
+  /// The order submission failed.
+  Order(RequestError<order::PostError>),
+}
+
+impl Display for ShortError {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    match self {
+      Self::Rejected(err) => write!(fmt, "order rejected locally: {}", err),
+      Self::Account(err) => write!(fmt, "failed to retrieve account: {}", err),
+      Self::Asset(err) => write!(fmt, "failed to retrieve asset: {}", err),
+      Self::Order(err) => write!(fmt, "failed to submit order: {}", err),
+    }
+  }
+}
+
+impl StdError for ShortError {
+  fn source(&self) -> Option<&(dyn StdError + 'static)> {
+    match self {
+      Self::Rejected(err) => Some(err),
+      Self::Account(err) => Some(err),
+      Self::Asset(err) => Some(err),
+      Self::Order(err) => Some(err),
+    }
+  }
+}
+
+impl From<Rejection> for ShortError {
+  fn from(rejection: Rejection) -> Self {
+    Self::Rejected(rejection)
+  }
+}
+
+
+/// Check whether the given asset can be sold short from the given
+/// account.
+///
+/// Alpaca only permits short selling of assets that are shortable as
+/// well as easy-to-borrow, and only for accounts with shorting
+/// enabled.
+pub fn check(account: &Account, asset: &Asset) -> Result<(), Rejection> {
+  if !account.shorting_enabled {
+    return Err(Rejection::ShortingDisabled)
+  }
+  if !asset.shortable {
+    return Err(Rejection::NotShortable {
+      symbol: asset.symbol.clone(),
+    })
+  }
+  if !asset.easy_to_borrow {
+    return Err(Rejection::NotEasyToBorrow {
+      symbol: asset.symbol.clone(),
+    })
+  }
+  Ok(())
+}
+
+
+impl Client {
+  /// Check whether the asset with the given symbol can currently be
+  /// sold short.
+  ///
+  /// The account and the asset are retrieved concurrently. On success,
+  /// the asset is returned.
+  pub async fn check_shortable(&self, symbol: asset::Symbol) -> Result<Asset, ShortError> {
+    let (account, asset) = try_join(
+      self.issue::<account::Get>(()).map_err(ShortError::Account),
+      self.issue::<asset::Get>(symbol).map_err(ShortError::Asset),
+    )
+    .await?;
+
+    let () = check(&account, &asset)?;
+    Ok(asset)
+  }
+
+  /// Submit a short sell order, after checking that the asset it
+  /// refers to can be sold short.
+  ///
+  /// The order is rejected locally, without being submitted, if the
+  /// asset is not shortable or not easy-to-borrow, or if the account
+  /// does not have shorting enabled. Note that the check is performed
+  /// for any order, so it should only be used for sell orders that
+  /// are intended to open or increase a short position.
+  pub async fn submit_short(&self, request: order::OrderReq) -> Result<order::Order, ShortError> {
+    if let Err(err) = self.check_shortable(request.symbol.clone()).await {
+      if let ShortError::Rejected(rejection) = &err {
+        warn!(rejection = display(rejection), "rejecting short sell order");
+      }
+      return Err(err)
+    }
+
+    self
+      .issue::<order::Post>(request)
+      .await
+      .map_err(ShortError::Order)
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use num_decimal::Num;
+
+  use test_env_log::test;
+
+  use crate::test_support;
+
+
+  /// Create an asset with the given borrow properties.
+  fn asset(shortable: bool, easy_to_borrow: bool) -> Asset {
+    let mut asset = test_support::asset("AAPL");
+    asset.shortable = shortable;
+    asset.easy_to_borrow = easy_to_borrow;
+    asset
+  }
+
+  /// Check that we correctly determine whether an asset can be sold
+  /// short.
+  #[test]
+  fn check_shortability() {
+    let mut account = test_support::account(Num::from(10_000));
+    assert_eq!(check(&account, &asset(true, true)), Ok(()));
+    assert_eq!(
+      check(&account, &asset(false, false)),
+      Err(Rejection::NotShortable {
+        symbol: "AAPL".to_string()
+      })
+    );
+    assert_eq!(
+      check(&account, &asset(true, false)),
+      Err(Rejection::NotEasyToBorrow {
+        symbol: "AAPL".to_string()
+      })
+    );
+
+    account.shorting_enabled = false;
+    assert_eq!(
+      check(&account, &asset(true, true)),
+      Err(Rejection::ShortingDisabled)
+    );
+  }
+}
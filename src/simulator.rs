@@ -0,0 +1,803 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use http::Method;
+use http::StatusCode;
+
+use num_decimal::Num;
+
+use serde::Deserialize;
+use serde_json::json;
+use serde_json::to_vec as to_json;
+use serde_json::Value;
+
+use uuid::Uuid;
+
+use crate::api::v2::order;
+use crate::util::system_time_to_rfc3339_string;
+
+
+/// A source of prices driving the fills of simulated orders.
+pub trait PriceSource: Send + Sync {
+  /// Retrieve the current price of the asset with the given symbol, if
+  /// known.
+  fn price(&self, symbol: &str) -> Option<Num>;
+}
+
+impl<F> PriceSource for F
+where
+  F: Fn(&str) -> Option<Num> + Send + Sync,
+{
+  fn price(&self, symbol: &str) -> Option<Num> {
+    self(symbol)
+  }
+}
+
+
+/// The subset of an order submission that we evaluate.
+#[derive(Debug, Deserialize)]
+struct Submission {
+  #[serde(rename = "symbol")]
+  symbol: String,
+  #[serde(rename = "qty")]
+  quantity: String,
+  #[serde(rename = "side")]
+  side: order::Side,
+  #[serde(rename = "order_class")]
+  class: order::Class,
+  #[serde(rename = "type")]
+  type_: order::Type,
+  #[serde(rename = "time_in_force")]
+  time_in_force: order::TimeInForce,
+  #[serde(rename = "limit_price")]
+  limit_price: Option<Num>,
+  #[serde(rename = "stop_price")]
+  stop_price: Option<Num>,
+  #[serde(rename = "extended_hours")]
+  extended_hours: bool,
+  #[serde(rename = "client_order_id")]
+  client_order_id: Option<String>,
+}
+
+
+/// The status of a simulated order.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Status {
+  New,
+  PendingCancel,
+  Filled,
+  Canceled,
+}
+
+impl Status {
+  fn as_str(self) -> &'static str {
+    match self {
+      Self::New => "new",
+      Self::PendingCancel => "pending_cancel",
+      Self::Filled => "filled",
+      Self::Canceled => "canceled",
+    }
+  }
+}
+
+
+/// A simulated order.
+#[derive(Clone, Debug)]
+struct SimOrder {
+  id: Uuid,
+  client_order_id: String,
+  symbol: String,
+  quantity: u64,
+  side: order::Side,
+  type_: order::Type,
+  time_in_force: order::TimeInForce,
+  limit_price: Option<Num>,
+  stop_price: Option<Num>,
+  extended_hours: bool,
+  /// Whether the stop price, if any, has been reached.
+  triggered: bool,
+  status: Status,
+  created_at: SystemTime,
+  updated_at: SystemTime,
+  filled_at: Option<SystemTime>,
+  canceled_at: Option<SystemTime>,
+  average_fill_price: Option<Num>,
+}
+
+impl SimOrder {
+  /// Check whether the order would be filled at the given price.
+  fn fills_at(&mut self, price: &Num) -> bool {
+    let buy = self.side == order::Side::Buy;
+    if !self.triggered {
+      match &self.stop_price {
+        Some(stop) if (buy && price >= stop) || (!buy && price <= stop) => self.triggered = true,
+        _ => return false,
+      }
+    }
+
+    match &self.limit_price {
+      Some(limit) => (buy && price <= limit) || (!buy && price >= limit),
+      None => true,
+    }
+  }
+
+  /// Convert the order into its JSON representation, as reported by
+  /// the API.
+  fn to_json(&self) -> Value {
+    let num = |num: &Option<Num>| num.as_ref().map(ToString::to_string);
+    let time = |time: &Option<SystemTime>| time.as_ref().map(system_time_to_rfc3339_string);
+    let filled = if self.status == Status::Filled {
+      self.quantity
+    } else {
+      0
+    };
+
+    json!({
+      "id": self.id.to_hyphenated().to_string(),
+      "client_order_id": self.client_order_id,
+      "status": self.status.as_str(),
+      "created_at": system_time_to_rfc3339_string(&self.created_at),
+      "updated_at": system_time_to_rfc3339_string(&self.updated_at),
+      "submitted_at": system_time_to_rfc3339_string(&self.created_at),
+      "filled_at": time(&self.filled_at),
+      "expired_at": null,
+      "canceled_at": time(&self.canceled_at),
+      "asset_class": "us_equity",
+      "asset_id": asset_id(&self.symbol).to_hyphenated().to_string(),
+      "symbol": self.symbol,
+      "qty": self.quantity.to_string(),
+      "filled_qty": filled.to_string(),
+      "type": self.type_,
+      "side": self.side,
+      "time_in_force": self.time_in_force,
+      "limit_price": num(&self.limit_price),
+      "stop_price": num(&self.stop_price),
+      "filled_avg_price": num(&self.average_fill_price),
+      "extended_hours": self.extended_hours,
+      "legs": null,
+    })
+  }
+}
+
+
+/// A simulated position.
+#[derive(Clone, Debug, Default)]
+struct Holding {
+  /// The signed quantity held, negative for short positions.
+  quantity: i64,
+  /// The average price paid per share.
+  average_entry_price: Num,
+}
+
+
+/// The mutable state of the simulated account.
+#[derive(Debug)]
+struct State {
+  cash: Num,
+  next_id: u128,
+  orders: Vec<SimOrder>,
+  holdings: BTreeMap<String, Holding>,
+  /// Whether cancellations take effect only once `process` is invoked.
+  hold_cancellations: bool,
+}
+
+
+/// Derive a stable asset ID from a symbol.
+fn asset_id(symbol: &str) -> Uuid {
+  let id = symbol.bytes().fold(0u128, |id, byte| {
+    id.wrapping_mul(257).wrapping_add(u128::from(byte))
+  });
+  Uuid::from_u128(id)
+}
+
+
+/// Create an error response in the format used by Alpaca.
+fn error(status: StatusCode, message: &str) -> (StatusCode, Vec<u8>) {
+  let code = u64::from(status.as_u16()) * 100_000;
+  let body = json!({"code": code, "message": message});
+  // Serialization of a `Value` cannot fail.
+  (status, to_json(&body).unwrap())
+}
+
+
+/// Create the JSON representation of the clock of the simulated
+/// market, which is always open.
+fn clock_json() -> Value {
+  let now = SystemTime::now();
+  let next = system_time_to_rfc3339_string(&(now + Duration::from_secs(24 * 60 * 60)));
+  json!({
+    "is_open": true,
+    "timestamp": system_time_to_rfc3339_string(&now),
+    "next_open": next,
+    "next_close": next,
+  })
+}
+
+
+/// Create a successful response with the given JSON body.
+fn ok(body: &Value) -> (StatusCode, Vec<u8>) {
+  // Serialization of a `Value` cannot fail.
+  (StatusCode::OK, to_json(body).unwrap())
+}
+
+
+/// An in-process simulation of the trading related parts of the Alpaca
+/// API.
+///
+/// The simulator implements the account, clock, order, and position
+/// endpoints of the API on top of a simulated account with a cash
+/// balance. The simulated market is always open. Orders
+/// are filled in full based on the prices reported by a `PriceSource`:
+/// market orders fill at the current price, limit orders once the price
+/// reaches the limit, and stop orders once the stop price is reached.
+/// Fills happen on submission, if possible, and whenever `process` is
+/// invoked.
+///
+/// A `Client` using a simulator is created via `Client::simulated`.
+/// Requests to any other endpoint are answered with a 404 error.
+/// Streaming events is not supported.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use apca::api::v2::order;
+/// # use apca::api::v2::positions;
+/// # use apca::simulator::Simulator;
+/// # use apca::Client;
+/// # use num_decimal::Num;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let prices = |_symbol: &str| Some(Num::from(100));
+/// let simulator = Arc::new(Simulator::new(Num::from(100_000), prices));
+/// let client = Client::simulated(simulator);
+///
+/// let request = order::OrderReqInit::default().init("AAPL", order::Side::Buy, 10);
+/// let order = client.issue::<order::Post>(request).await?;
+/// let positions = client.issue::<positions::Get>(()).await?;
+/// assert_eq!(positions[0].quantity, 10);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Simulator {
+  /// The source of prices.
+  prices: Box<dyn PriceSource>,
+  /// The time the simulated account was created at.
+  created_at: SystemTime,
+  /// The state of the simulated account.
+  state: Mutex<State>,
+}
+
+impl Simulator {
+  /// Create a new `Simulator` for an account with the given cash
+  /// balance, using the provided source of prices.
+  pub fn new<P>(cash: Num, prices: P) -> Self
+  where
+    P: PriceSource + 'static,
+  {
+    Self {
+      prices: Box::new(prices),
+      created_at: SystemTime::now(),
+      state: Mutex::new(State {
+        cash,
+        next_id: 1,
+        orders: Vec::new(),
+        holdings: BTreeMap::new(),
+        hold_cancellations: false,
+      }),
+    }
+  }
+
+  /// Set whether cancellations take effect immediately, which is the
+  /// default, or only once `process` is invoked.
+  ///
+  /// While cancellations are held, canceled orders remain in the
+  /// `pending_cancel` state, just like orders the cancellation of which
+  /// has not yet been confirmed by the execution venue.
+  pub fn hold_cancellations(&self, hold: bool) {
+    self.state.lock().unwrap().hold_cancellations = hold;
+  }
+
+  /// Evaluate all open orders against the current prices, filling
+  /// those that can be filled, and complete pending cancellations.
+  pub fn process(&self) {
+    let mut state = self.state.lock().unwrap();
+    let state = &mut *state;
+    for order in &mut state.orders {
+      match order.status {
+        Status::New => self.try_fill(order, &mut state.cash, &mut state.holdings),
+        Status::PendingCancel => {
+          let now = SystemTime::now();
+          order.status = Status::Canceled;
+          order.canceled_at = Some(now);
+          order.updated_at = now;
+        },
+        Status::Filled | Status::Canceled => (),
+      }
+    }
+  }
+
+  /// Try filling an order at the current price.
+  fn try_fill(
+    &self,
+    order: &mut SimOrder,
+    cash: &mut Num,
+    holdings: &mut BTreeMap<String, Holding>,
+  ) {
+    let price = match self.prices.price(&order.symbol) {
+      Some(price) => price,
+      None => return,
+    };
+    if !order.fills_at(&price) {
+      return
+    }
+
+    let quantity = order.side.signed(order.quantity);
+    *cash -= &price * Num::from(quantity);
+
+    let holding = holdings.entry(order.symbol.clone()).or_default();
+    let updated = holding.quantity + quantity;
+    if holding.quantity == 0 || (holding.quantity < 0) != (updated < 0) {
+      // A new position is opened or the position flips sides.
+      holding.average_entry_price = price.clone();
+    } else if holding.quantity.abs() < updated.abs() {
+      // The position is increased.
+      let cost =
+        &holding.average_entry_price * Num::from(holding.quantity) + &price * Num::from(quantity);
+      holding.average_entry_price = cost / Num::from(updated);
+    }
+    holding.quantity = updated;
+    if updated == 0 {
+      let _ = holdings.remove(&order.symbol);
+    }
+
+    let now = SystemTime::now();
+    order.status = Status::Filled;
+    order.average_fill_price = Some(price);
+    order.filled_at = Some(now);
+    order.updated_at = now;
+  }
+
+  /// Convert a position into its JSON representation, as reported by
+  /// the API.
+  fn position_json(&self, symbol: &str, holding: &Holding) -> Value {
+    let price = self
+      .prices
+      .price(symbol)
+      .unwrap_or_else(|| holding.average_entry_price.clone());
+    let quantity = Num::from(holding.quantity);
+    let market_value = &price * &quantity;
+    let cost_basis = &holding.average_entry_price * &quantity;
+    let gain = &market_value - &cost_basis;
+    let gain_percent = if cost_basis.is_zero() {
+      Num::from(0)
+    } else if cost_basis.is_negative() {
+      &gain / -&cost_basis
+    } else {
+      &gain / &cost_basis
+    };
+    let side = if holding.quantity < 0 {
+      "short"
+    } else {
+      "long"
+    };
+
+    json!({
+      "asset_id": asset_id(symbol).to_hyphenated().to_string(),
+      "symbol": symbol,
+      "exchange": "UNKNOWN",
+      "asset_class": "us_equity",
+      "avg_entry_price": holding.average_entry_price.to_string(),
+      "qty": holding.quantity.abs().to_string(),
+      "side": side,
+      "market_value": market_value.to_string(),
+      "cost_basis": cost_basis.to_string(),
+      "unrealized_pl": gain.to_string(),
+      "unrealized_plpc": gain_percent.to_string(),
+      "unrealized_intraday_pl": gain.to_string(),
+      "unrealized_intraday_plpc": gain_percent.to_string(),
+      "current_price": price.to_string(),
+      "lastday_price": price.to_string(),
+      "change_today": "0",
+    })
+  }
+
+  /// Convert the account into its JSON representation, as reported by
+  /// the API.
+  fn account_json(&self, state: &State) -> Value {
+    let zero = Num::from(0);
+    let (long, short) = state.holdings.iter().fold(
+      (zero.clone(), zero.clone()),
+      |(long, short), (symbol, holding)| {
+        let price = self
+          .prices
+          .price(symbol)
+          .unwrap_or_else(|| holding.average_entry_price.clone());
+        let value = price * Num::from(holding.quantity);
+        if value.is_negative() {
+          (long, short + value)
+        } else {
+          (long + value, short)
+        }
+      },
+    );
+    let equity = &state.cash + &long + &short;
+    let buying_power = if state.cash.is_negative() {
+      zero
+    } else {
+      state.cash.clone()
+    };
+
+    json!({
+      "id": Uuid::nil().to_hyphenated().to_string(),
+      "status": "ACTIVE",
+      "currency": "USD",
+      "cash": state.cash.to_string(),
+      "pattern_day_trader": false,
+      "trade_suspended_by_user": false,
+      "trading_blocked": false,
+      "transfers_blocked": false,
+      "account_blocked": false,
+      "created_at": system_time_to_rfc3339_string(&self.created_at),
+      "shorting_enabled": true,
+      "long_market_value": long.to_string(),
+      "short_market_value": short.to_string(),
+      "equity": equity.to_string(),
+      "last_equity": equity.to_string(),
+      "multiplier": "1",
+      "buying_power": buying_power.to_string(),
+      "initial_margin": "0",
+      "maintenance_margin": "0",
+      "daytrade_count": 0,
+    })
+  }
+
+  /// Submit an order.
+  fn submit(&self, body: &[u8]) -> (StatusCode, Vec<u8>) {
+    let submission = match serde_json::from_slice::<Submission>(body) {
+      Ok(submission) => submission,
+      Err(err) => return error(StatusCode::UNPROCESSABLE_ENTITY, &err.to_string()),
+    };
+    if submission.class != order::Class::Simple {
+      return error(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "only simple orders are supported by the simulator",
+      )
+    }
+    let quantity = match submission.quantity.parse::<u64>() {
+      Ok(quantity) if quantity > 0 => quantity,
+      _ => return error(StatusCode::UNPROCESSABLE_ENTITY, "invalid qty"),
+    };
+
+    let mut state = self.state.lock().unwrap();
+    let state = &mut *state;
+
+    if submission.side == order::Side::Buy {
+      let price = submission
+        .limit_price
+        .clone()
+        .or_else(|| self.prices.price(&submission.symbol));
+      if let Some(price) = price {
+        if price * Num::from(quantity) > state.cash {
+          return error(StatusCode::FORBIDDEN, "insufficient buying power")
+        }
+      }
+    }
+
+    let id = Uuid::from_u128(state.next_id);
+    state.next_id += 1;
+
+    let now = SystemTime::now();
+    let mut order = SimOrder {
+      id,
+      client_order_id: submission
+        .client_order_id
+        .unwrap_or_else(|| id.to_hyphenated().to_string()),
+      symbol: submission.symbol,
+      quantity,
+      side: submission.side,
+      type_: submission.type_,
+      time_in_force: submission.time_in_force,
+      triggered: submission.stop_price.is_none(),
+      limit_price: submission.limit_price,
+      stop_price: submission.stop_price,
+      extended_hours: submission.extended_hours,
+      status: Status::New,
+      created_at: now,
+      updated_at: now,
+      filled_at: None,
+      canceled_at: None,
+      average_fill_price: None,
+    };
+    self.try_fill(&mut order, &mut state.cash, &mut state.holdings);
+
+    let json = order.to_json();
+    state.orders.push(order);
+    ok(&json)
+  }
+
+  /// Cancel the order with the given ID.
+  fn cancel(&self, id: &str) -> (StatusCode, Vec<u8>) {
+    let mut state = self.state.lock().unwrap();
+    let hold = state.hold_cancellations;
+    let order = Uuid::parse_str(id)
+      .ok()
+      .and_then(|id| state.orders.iter_mut().find(|order| order.id == id));
+
+    match order {
+      Some(order) if order.status == Status::New => {
+        let now = SystemTime::now();
+        if hold {
+          order.status = Status::PendingCancel;
+        } else {
+          order.status = Status::Canceled;
+          order.canceled_at = Some(now);
+        }
+        order.updated_at = now;
+        (StatusCode::NO_CONTENT, Vec::new())
+      },
+      Some(_) => error(StatusCode::UNPROCESSABLE_ENTITY, "order is not cancelable"),
+      None => error(StatusCode::NOT_FOUND, "order not found"),
+    }
+  }
+
+  /// List orders.
+  fn list_orders(&self, query: Option<&str>) -> (StatusCode, Vec<u8>) {
+    let mut status = "open";
+    let mut limit = 50;
+    for (key, value) in query.unwrap_or("").split('&').filter_map(|pair| {
+      let mut parts = pair.splitn(2, '=');
+      Some((parts.next()?, parts.next()?))
+    }) {
+      match key {
+        "status" => status = value,
+        "limit" => limit = value.parse().unwrap_or(limit),
+        _ => (),
+      }
+    }
+
+    let state = self.state.lock().unwrap();
+    let orders = state
+      .orders
+      .iter()
+      .rev()
+      .filter(|order| match status {
+        "open" => matches!(order.status, Status::New | Status::PendingCancel),
+        "closed" => matches!(order.status, Status::Filled | Status::Canceled),
+        _ => true,
+      })
+      .take(limit)
+      .map(SimOrder::to_json)
+      .collect::<Vec<_>>();
+    ok(&Value::Array(orders))
+  }
+
+  /// Close the position in the given symbol.
+  fn close(&self, symbol: &str) -> (StatusCode, Vec<u8>) {
+    let mut state = self.state.lock().unwrap();
+    let state = &mut *state;
+    let quantity = match state.holdings.get(symbol) {
+      Some(holding) => holding.quantity,
+      None => return error(StatusCode::NOT_FOUND, "position not found"),
+    };
+
+    let id = Uuid::from_u128(state.next_id);
+    state.next_id += 1;
+
+    let now = SystemTime::now();
+    let mut order = SimOrder {
+      id,
+      client_order_id: id.to_hyphenated().to_string(),
+      symbol: symbol.to_string(),
+      quantity: quantity.unsigned_abs(),
+      side: !order::Side::from_signed(quantity),
+      type_: order::Type::Market,
+      time_in_force: order::TimeInForce::Day,
+      limit_price: None,
+      stop_price: None,
+      extended_hours: false,
+      triggered: true,
+      status: Status::New,
+      created_at: now,
+      updated_at: now,
+      filled_at: None,
+      canceled_at: None,
+      average_fill_price: None,
+    };
+    self.try_fill(&mut order, &mut state.cash, &mut state.holdings);
+
+    let json = order.to_json();
+    state.orders.push(order);
+    ok(&json)
+  }
+
+  /// Handle a request for the given method and path.
+  pub(crate) fn handle(
+    &self,
+    method: &Method,
+    path: &str,
+    query: Option<&str>,
+    body: &[u8],
+  ) -> (StatusCode, Vec<u8>) {
+    let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+
+    match (method, segments.as_slice()) {
+      (&Method::GET, ["v2", "account"]) => {
+        let state = self.state.lock().unwrap();
+        ok(&self.account_json(&state))
+      },
+      (&Method::GET, ["v2", "clock"]) => ok(&clock_json()),
+      (&Method::GET, ["v2", "orders"]) => self.list_orders(query),
+      (&Method::POST, ["v2", "orders"]) => self.submit(body),
+      (&Method::GET, ["v2", "orders", id]) => {
+        let state = self.state.lock().unwrap();
+        let order = Uuid::parse_str(id)
+          .ok()
+          .and_then(|id| state.orders.iter().find(|order| order.id == id));
+        match order {
+          Some(order) => ok(&order.to_json()),
+          None => error(StatusCode::NOT_FOUND, "order not found"),
+        }
+      },
+      (&Method::DELETE, ["v2", "orders", id]) => self.cancel(id),
+      (&Method::GET, ["v2", "positions"]) => {
+        let state = self.state.lock().unwrap();
+        let positions = state
+          .holdings
+          .iter()
+          .map(|(symbol, holding)| self.position_json(symbol, holding))
+          .collect::<Vec<_>>();
+        ok(&Value::Array(positions))
+      },
+      (&Method::GET, ["v2", "positions", symbol]) => {
+        let state = self.state.lock().unwrap();
+        match state.holdings.get(*symbol) {
+          Some(holding) => ok(&self.position_json(symbol, holding)),
+          None => error(StatusCode::NOT_FOUND, "position not found"),
+        }
+      },
+      (&Method::DELETE, ["v2", "positions", symbol]) => self.close(symbol),
+      _ => error(StatusCode::NOT_FOUND, "endpoint not supported by simulator"),
+    }
+  }
+}
+
+impl Debug for Simulator {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    fmt
+      .debug_struct("Simulator")
+      .field("created_at", &self.created_at)
+      .field("state", &self.state)
+      .finish()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::collections::HashMap;
+  use std::sync::Arc;
+  use std::sync::RwLock;
+
+  use crate::api::v2::account;
+  use crate::api::v2::orders;
+  use crate::api::v2::position;
+  use crate::api::v2::positions;
+  use crate::Client;
+  use crate::RequestError;
+
+  use test_env_log::test;
+
+
+  /// Create a simulator along with a handle to the prices it uses.
+  fn simulator(cash: i64) -> (Arc<Simulator>, Arc<RwLock<HashMap<String, Num>>>) {
+    let prices = Arc::new(RwLock::new(HashMap::new()));
+    let source = prices.clone();
+    let source = move |symbol: &str| source.read().unwrap().get(symbol).cloned();
+    (Arc::new(Simulator::new(Num::from(cash), source)), prices)
+  }
+
+  #[test(tokio::test)]
+  async fn fill_market_order() {
+    let (simulator, prices) = simulator(10_000);
+    let _ = prices
+      .write()
+      .unwrap()
+      .insert("AAPL".to_string(), Num::from(100));
+    let client = Client::simulated(simulator);
+
+    let request = order::OrderReqInit::default().init("AAPL", order::Side::Buy, 10);
+    let order = client.issue::<order::Post>(request).await.unwrap();
+    assert_eq!(order.status, order::Status::Filled);
+    assert_eq!(order.average_fill_price, Some(Num::from(100)));
+
+    let _ = prices
+      .write()
+      .unwrap()
+      .insert("AAPL".to_string(), Num::from(110));
+    let position = client.issue::<position::Get>("AAPL".into()).await.unwrap();
+    assert_eq!(position.quantity, 10);
+    assert_eq!(position.side, position::Side::Long);
+    assert_eq!(position.unrealized_gain_total, Num::from(100));
+
+    let account = client.issue::<account::Get>(()).await.unwrap();
+    assert_eq!(account.cash, Num::from(9000));
+    assert_eq!(account.equity, Num::from(10100));
+
+    let order = client
+      .issue::<position::Delete>("AAPL".into())
+      .await
+      .unwrap();
+    assert_eq!(order.side, order::Side::Sell);
+    let positions = client.issue::<positions::Get>(()).await.unwrap();
+    assert!(positions.is_empty());
+    let account = client.issue::<account::Get>(()).await.unwrap();
+    assert_eq!(account.cash, Num::from(10100));
+  }
+
+  #[test(tokio::test)]
+  async fn fill_limit_order() {
+    let (simulator, prices) = simulator(10_000);
+    let _ = prices
+      .write()
+      .unwrap()
+      .insert("SPY".to_string(), Num::from(400));
+    let client = Client::simulated(simulator.clone());
+
+    let request = order::OrderReqInit {
+      type_: order::Type::Limit,
+      limit_price: Some(Num::from(390)),
+      ..Default::default()
+    }
+    .init("SPY", order::Side::Buy, 5);
+    let order = client.issue::<order::Post>(request).await.unwrap();
+    assert_eq!(order.status, order::Status::New);
+
+    let open = client
+      .issue::<orders::Get>(orders::OrdersReq::default())
+      .await
+      .unwrap();
+    assert_eq!(open.len(), 1);
+
+    let _ = prices
+      .write()
+      .unwrap()
+      .insert("SPY".to_string(), Num::from(389));
+    simulator.process();
+
+    let order = client.issue::<order::Get>(order.id).await.unwrap();
+    assert_eq!(order.status, order::Status::Filled);
+    assert_eq!(order.average_fill_price, Some(Num::from(389)));
+
+    let err = client.issue::<order::Delete>(order.id).await.unwrap_err();
+    match err {
+      RequestError::Endpoint(order::DeleteError::NotCancelable(_)) => (),
+      _ => panic!("Received unexpected error: {:?}", err),
+    }
+  }
+
+  #[test(tokio::test)]
+  async fn reject_insufficient_funds() {
+    let (simulator, prices) = simulator(1_000);
+    let _ = prices
+      .write()
+      .unwrap()
+      .insert("AAPL".to_string(), Num::from(100));
+    let client = Client::simulated(simulator);
+
+    let request = order::OrderReqInit::default().init("AAPL", order::Side::Buy, 11);
+    let err = client.issue::<order::Post>(request).await.unwrap_err();
+    match err {
+      RequestError::Endpoint(order::PostError::InsufficientFunds(_)) => (),
+      _ => panic!("Received unexpected error: {:?}", err),
+    }
+  }
+}
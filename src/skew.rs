@@ -0,0 +1,181 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use crate::api::v2::clock;
+use crate::Client;
+use crate::RequestError;
+
+
+/// An estimate of the offset between the local clock and the one of
+/// the server.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Skew {
+  /// The round trip time of the request the estimate is based on.
+  round_trip: Duration,
+  /// Whether the server clock is ahead of the local one.
+  ahead: bool,
+  /// The absolute offset between the two clocks.
+  offset: Duration,
+}
+
+impl Skew {
+  /// Estimate the skew based on a request sent at local time `sent`,
+  /// taking `round_trip` to complete, and the time the server reported
+  /// in its response.
+  ///
+  /// The server time is assumed to have been captured halfway through
+  /// the round trip.
+  pub fn new(sent: SystemTime, round_trip: Duration, server: SystemTime) -> Self {
+    let local = sent + round_trip / 2;
+    let (ahead, offset) = match server.duration_since(local) {
+      Ok(offset) => (true, offset),
+      Err(err) => (false, err.duration()),
+    };
+
+    Self {
+      round_trip,
+      ahead,
+      offset,
+    }
+  }
+
+  /// Retrieve the round trip time of the request the estimate is based
+  /// on.
+  ///
+  /// Half of it is an upper bound for the error of the estimate.
+  pub fn round_trip(&self) -> Duration {
+    self.round_trip
+  }
+
+  /// Retrieve the estimated offset in seconds, positive if the server
+  /// clock is ahead of the local one and negative if it is behind.
+  pub fn offset_secs(&self) -> f64 {
+    let secs = self.offset.as_secs_f64();
+    if self.ahead {
+      secs
+    } else {
+      -secs
+    }
+  }
+
+  /// Convert a local time into the corresponding server time.
+  pub fn to_server_time(&self, local: SystemTime) -> SystemTime {
+    if self.ahead {
+      local + self.offset
+    } else {
+      local - self.offset
+    }
+  }
+
+  /// Convert a server time into the corresponding local time.
+  pub fn to_local_time(&self, server: SystemTime) -> SystemTime {
+    if self.ahead {
+      server - self.offset
+    } else {
+      server + self.offset
+    }
+  }
+
+  /// Retrieve the estimated current server time.
+  pub fn server_now(&self) -> SystemTime {
+    self.to_server_time(SystemTime::now())
+  }
+}
+
+
+impl Client {
+  /// Estimate the skew between the local clock and the one of the
+  /// server by querying the /v2/clock endpoint.
+  ///
+  /// `samples` requests are issued one after the other and the
+  /// estimate based on the one with the shortest round trip time is
+  /// reported, as it bears the least uncertainty. At least one request
+  /// is issued.
+  ///
+  /// Note that the time reported by the server is only a snapshot and
+  /// its precision is limited by the network latency, so the estimate
+  /// should be used for coarse adjustments only, e.g., to avoid
+  /// submitting an order too close to an auction cut-off.
+  pub async fn measure_skew(&self, samples: usize) -> Result<Skew, RequestError<clock::GetError>> {
+    let mut best = None::<Skew>;
+
+    for _ in 0..samples.max(1) {
+      let sent = SystemTime::now();
+      let start = Instant::now();
+      let clock = self.issue::<clock::Get>(()).await?;
+      let skew = Skew::new(sent, start.elapsed(), clock.current);
+
+      match best {
+        Some(best) if best.round_trip <= skew.round_trip => (),
+        _ => best = Some(skew),
+      }
+    }
+
+    // We issued at least one request and bailed out on error, so there
+    // has to be an estimate.
+    Ok(best.unwrap())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::time::UNIX_EPOCH;
+
+  use test_env_log::test;
+
+  use crate::api_info::ApiInfo;
+
+
+  #[test]
+  fn server_ahead() {
+    let sent = UNIX_EPOCH + Duration::from_secs(1000);
+    let server = UNIX_EPOCH + Duration::from_millis(1_002_100);
+    let skew = Skew::new(sent, Duration::from_millis(200), server);
+
+    assert_eq!(skew.round_trip(), Duration::from_millis(200));
+    assert!((skew.offset_secs() - 2.0).abs() < 1e-9);
+    assert_eq!(
+      skew.to_server_time(UNIX_EPOCH + Duration::from_secs(10)),
+      UNIX_EPOCH + Duration::from_secs(12)
+    );
+    assert_eq!(
+      skew.to_local_time(UNIX_EPOCH + Duration::from_secs(12)),
+      UNIX_EPOCH + Duration::from_secs(10)
+    );
+  }
+
+  #[test]
+  fn server_behind() {
+    let sent = UNIX_EPOCH + Duration::from_secs(1000);
+    let server = UNIX_EPOCH + Duration::from_millis(999_550);
+    let skew = Skew::new(sent, Duration::from_millis(100), server);
+
+    assert!((skew.offset_secs() + 0.5).abs() < 1e-9);
+    assert_eq!(
+      skew.to_server_time(UNIX_EPOCH + Duration::from_secs(10)),
+      UNIX_EPOCH + Duration::from_millis(9_500)
+    );
+    assert_eq!(
+      skew.to_local_time(UNIX_EPOCH + Duration::from_millis(9_500)),
+      UNIX_EPOCH + Duration::from_secs(10)
+    );
+  }
+
+  #[test(tokio::test)]
+  async fn measure_clock_skew() {
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+    let skew = client.measure_skew(3).await.unwrap();
+
+    // We assume that both the server clock and ours are synchronized
+    // reasonably well.
+    assert!(skew.offset_secs().abs() < 60.0, "{:?}", skew);
+  }
+}
@@ -0,0 +1,137 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use num_decimal::Num;
+
+use crate::api::v2::order;
+use crate::data::v2::quotes::Quote;
+
+
+/// An estimate of how a market order would be filled.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Estimate {
+  /// The mark price, i.e., the midpoint between bid and ask.
+  pub mark: Num,
+  /// The expected average fill price per share.
+  pub price: Num,
+  /// The expected notional value of the order, i.e., `price` times the
+  /// quantity.
+  pub notional: Num,
+}
+
+impl Estimate {
+  /// Calculate the expected slippage per share relative to the mark
+  /// price.
+  ///
+  /// The value is positive if the order is expected to fill at a worse
+  /// price than the mark.
+  pub fn slippage(&self, side: order::Side) -> Num {
+    match side {
+      order::Side::Buy => &self.price - &self.mark,
+      order::Side::Sell => &self.mark - &self.price,
+    }
+  }
+}
+
+
+/// Estimate the fill of a market order based on a quote.
+///
+/// Shares up to the size quoted on the opposite side of the book are
+/// assumed to fill at the quoted price. Because the depth beyond that
+/// is unknown, any excess is assumed to fill at a price worse by
+/// `excess_slippage`, expressed as a fraction of the quoted price
+/// (e.g., `Num::new(1, 1000)` for ten basis points).
+///
+/// `None` is returned if the quote does not contain a price for the
+/// side of the book the order would execute against.
+pub fn estimate(
+  quote: &Quote,
+  side: order::Side,
+  quantity: u64,
+  excess_slippage: &Num,
+) -> Option<Estimate> {
+  let (price, size) = match side {
+    order::Side::Buy => (&quote.ask_price, quote.ask_size),
+    order::Side::Sell => (&quote.bid_price, quote.bid_size),
+  };
+
+  if !price.is_positive() {
+    return None
+  }
+
+  let filled = quantity.min(size);
+  let excess = quantity - filled;
+  let excess_price = match side {
+    order::Side::Buy => price * (Num::from(1) + excess_slippage),
+    order::Side::Sell => price * (Num::from(1) - excess_slippage),
+  };
+  let notional = price * Num::from(filled) + excess_price * Num::from(excess);
+  let price = if quantity == 0 {
+    price.clone()
+  } else {
+    &notional / Num::from(quantity)
+  };
+  let mark = quote.midpoint().unwrap_or_else(|| price.clone());
+
+  Some(Estimate {
+    mark,
+    price,
+    notional,
+  })
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde_json::from_str as from_json;
+
+  use test_env_log::test;
+
+
+  /// Create a quote with the given bid and ask.
+  fn quote(bid: &str, bid_size: u64, ask: &str, ask_size: u64) -> Quote {
+    let json = format!(
+      r#"{{
+  "t": "2021-02-06T13:35:08.946977536Z",
+  "ax": "C",
+  "ap": {},
+  "as": {},
+  "bx": "C",
+  "bp": {},
+  "bs": {},
+  "c": ["R"],
+  "z": "C"
+}}"#,
+      ask, ask_size, bid, bid_size
+    );
+    from_json::<Quote>(&json).unwrap()
+  }
+
+  #[test]
+  fn estimate_within_quoted_size() {
+    let quote = quote("99.9", 300, "100.1", 200);
+    let estimate = estimate(&quote, order::Side::Buy, 150, &Num::new(1, 100)).unwrap();
+    assert_eq!(estimate.mark, Num::from(100));
+    assert_eq!(estimate.price, Num::new(1001, 10));
+    assert_eq!(estimate.notional, Num::from(15015));
+    assert_eq!(estimate.slippage(order::Side::Buy), Num::new(1, 10));
+  }
+
+  #[test]
+  fn estimate_beyond_quoted_size() {
+    let quote = quote("100", 100, "101", 100);
+    let estimate = estimate(&quote, order::Side::Sell, 200, &Num::new(1, 100)).unwrap();
+    // 100 shares at 100 and another 100 at 99.
+    assert_eq!(estimate.notional, Num::from(19900));
+    assert_eq!(estimate.price, Num::new(995, 10));
+    assert_eq!(estimate.slippage(order::Side::Sell), Num::from(1));
+  }
+
+  #[test]
+  fn estimate_without_price() {
+    let quote = quote("100", 100, "0", 0);
+    assert_eq!(estimate(&quote, order::Side::Buy, 1, &Num::from(0)), None);
+  }
+}
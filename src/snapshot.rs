@@ -0,0 +1,175 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::error::Error as StdError;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use futures::future::try_join4;
+use futures::TryFutureExt as _;
+
+use crate::api::v2::account;
+use crate::api::v2::clock;
+use crate::api::v2::order;
+use crate::api::v2::orders;
+use crate::api::v2::position;
+use crate::api::v2::positions;
+use crate::Client;
+use crate::RequestError;
+
+
+/// An error preventing the retrieval of an account snapshot.
+#[derive(Debug)]
+pub enum SnapshotError {
+  /// The account could not be retrieved.
+  Account(RequestError<account::GetError>),
+  /// The open positions could not be listed.
+  Positions(RequestError<positions::GetError>),
+  /// The open orders could not be listed.
+  Orders(RequestError<orders::GetError>),
+  /// The market clock could not be retrieved.
+  Clock(RequestError<clock::GetError>),
+}
+
+impl Display for SnapshotError {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    match self {
+      Self::Account(err) => write!(fmt, "failed to retrieve account: {}", err),
+      Self::Positions(err) => write!(fmt, "failed to list open positions: {}", err),
+      Self::Orders(err) => write!(fmt, "failed to list open orders: {}", err),
+      Self::Clock(err) => write!(fmt, "failed to retrieve market clock: {}", err),
+    }
+  }
+}
+
+impl StdError for SnapshotError {
+  fn source(&self) -> Option<&(dyn StdError + 'static)> {
+    match self {
+      Self::Account(err) => Some(err),
+      Self::Positions(err) => Some(err),
+      Self::Orders(err) => Some(err),
+      Self::Clock(err) => Some(err),
+    }
+  }
+}
+
+
+/// A snapshot of the current state of the account.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountSnapshot {
+  /// The account.
+  pub account: account::Account,
+  /// The open positions.
+  pub positions: Vec<position::Position>,
+  /// The open orders.
+  pub open_orders: Vec<order::Order>,
+  /// The market clock.
+  pub clock: clock::Clock,
+}
+
+impl AccountSnapshot {
+  /// Look up the open position in the asset with the given symbol.
+  pub fn position(&self, symbol: &str) -> Option<&position::Position> {
+    self
+      .positions
+      .iter()
+      .find(|position| position.symbol == symbol)
+  }
+
+  /// Retrieve an iterator over the open orders for the asset with the
+  /// given symbol.
+  pub fn open_orders_for<'s>(
+    &'s self,
+    symbol: &'s str,
+  ) -> impl Iterator<Item = &'s order::Order> + 's {
+    self
+      .open_orders
+      .iter()
+      .filter(move |order| order.symbol == symbol)
+  }
+}
+
+
+impl Client {
+  /// Retrieve the account, the open positions and orders, and the
+  /// market clock, in one go.
+  ///
+  /// All requests are issued concurrently. Note that the parts of the
+  /// snapshot are not guaranteed to be consistent with each other, as
+  /// the account may change between the individual requests being
+  /// served.
+  pub async fn snapshot(&self) -> Result<AccountSnapshot, SnapshotError> {
+    let (account, positions, open_orders, clock) = try_join4(
+      self
+        .issue::<account::Get>(())
+        .map_err(SnapshotError::Account),
+      self
+        .issue::<positions::Get>(())
+        .map_err(SnapshotError::Positions),
+      self
+        .all_orders(orders::Status::Open, false)
+        .map_err(SnapshotError::Orders),
+      self.issue::<clock::Get>(()).map_err(SnapshotError::Clock),
+    )
+    .await?;
+
+    let snapshot = AccountSnapshot {
+      account,
+      positions,
+      open_orders,
+      clock,
+    };
+    Ok(snapshot)
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::sync::Arc;
+
+  use num_decimal::Num;
+
+  use test_env_log::test;
+
+  use crate::simulator::Simulator;
+
+
+  /// Check that we can retrieve a snapshot of a simulated account.
+  #[test(tokio::test)]
+  async fn retrieve_snapshot() {
+    let prices = |symbol: &str| match symbol {
+      "AAPL" | "MSFT" => Some(Num::from(100)),
+      _ => None,
+    };
+    let simulator = Arc::new(Simulator::new(Num::from(10_000), prices));
+    let client = Client::simulated(simulator);
+
+    let request = order::OrderReqInit::default().init("AAPL", order::Side::Buy, 10);
+    let _ = client.issue::<order::Post>(request).await.unwrap();
+
+    let request = order::OrderReqInit {
+      type_: order::Type::Limit,
+      limit_price: Some(Num::from(90)),
+      ..Default::default()
+    }
+    .init("MSFT", order::Side::Buy, 5);
+    let limit = client.issue::<order::Post>(request).await.unwrap();
+
+    let snapshot = client.snapshot().await.unwrap();
+    assert_eq!(snapshot.account.cash, Num::from(9000));
+    assert_eq!(snapshot.positions.len(), 1);
+    assert_eq!(snapshot.position("AAPL").unwrap().quantity, 10);
+    assert_eq!(snapshot.position("MSFT"), None);
+    assert_eq!(snapshot.open_orders.len(), 1);
+    assert_eq!(
+      snapshot.open_orders_for("MSFT").next().unwrap().id,
+      limit.id
+    );
+    assert_eq!(snapshot.open_orders_for("AAPL").next(), None);
+    assert!(snapshot.clock.open);
+  }
+}
@@ -0,0 +1,128 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::Duration;
+
+#[cfg(feature = "streaming")]
+use std::io::Error as IoError;
+#[cfg(feature = "streaming")]
+use std::io::ErrorKind;
+
+#[cfg(feature = "streaming")]
+use socket2::SockRef;
+#[cfg(feature = "streaming")]
+use socket2::TcpKeepalive;
+
+#[cfg(feature = "streaming")]
+use tokio::net::TcpStream;
+#[cfg(feature = "streaming")]
+use tokio::time::timeout;
+
+#[cfg(feature = "streaming")]
+use tungstenite::client_async_tls;
+#[cfg(feature = "streaming")]
+use tungstenite::MaybeTlsStream;
+#[cfg(feature = "streaming")]
+use tungstenite::WebSocketStream;
+
+#[cfg(feature = "streaming")]
+use url::Url;
+
+#[cfg(feature = "streaming")]
+use websocket_util::tungstenite::handshake::client::Response;
+#[cfg(feature = "streaming")]
+use websocket_util::tungstenite::Error as WebSocketError;
+
+
+/// Options for the TCP connections made to the API, both for issuing
+/// requests and for streaming.
+///
+/// By default, no keepalive probes are sent, Nagle's algorithm is
+/// used, and connection attempts are only bound by the operating
+/// system's timeout.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SocketOptions {
+  /// The time a connection has to be idle before TCP keepalive probes
+  /// are sent, if any.
+  ///
+  /// Long-lived streams that are idle for most of the time, e.g., trade
+  /// updates streams, may otherwise get dropped silently by NAT devices
+  /// along the way.
+  pub keepalive: Option<Duration>,
+  /// Whether to disable Nagle's algorithm, sending data as soon as
+  /// possible.
+  pub nodelay: bool,
+  /// The maximum time to wait for a connection to be established.
+  pub connect_timeout: Option<Duration>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+
+/// Connect to the WebSocket server at the given URL, applying the
+/// provided socket options.
+#[cfg(feature = "streaming")]
+pub(crate) async fn connect(
+  url: Url,
+  options: &SocketOptions,
+) -> Result<(WebSocketStream<MaybeTlsStream<TcpStream>>, Response), WebSocketError> {
+  let host = url
+    .host_str()
+    .ok_or_else(|| WebSocketError::Url("no host name in the url".into()))?;
+  let port = url
+    .port_or_known_default()
+    .ok_or_else(|| WebSocketError::Url("Url scheme not supported".into()))?;
+
+  let connect = TcpStream::connect((host, port));
+  let stream = match options.connect_timeout {
+    Some(duration) => timeout(duration, connect)
+      .await
+      .map_err(|_| IoError::new(ErrorKind::TimedOut, "timed out connecting to server"))??,
+    None => connect.await?,
+  };
+
+  stream.set_nodelay(options.nodelay)?;
+  if let Some(keepalive) = options.keepalive {
+    let keepalive = TcpKeepalive::new().with_time(keepalive);
+    SockRef::from(&stream).set_tcp_keepalive(&keepalive)?;
+  }
+
+  client_async_tls(url, stream).await
+}
+
+
+#[cfg(all(test, feature = "streaming"))]
+mod tests {
+  use super::*;
+
+  use std::net::TcpListener;
+
+  use test_env_log::test;
+
+
+  /// Check that socket options are applied to WebSocket connections.
+  #[test(tokio::test)]
+  async fn connect_with_timeout() {
+    // A listener that accepts connections but never completes the
+    // WebSocket handshake.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = Url::parse(&format!("ws://{}", listener.local_addr().unwrap())).unwrap();
+    let options = SocketOptions {
+      keepalive: Some(Duration::from_secs(30)),
+      nodelay: true,
+      connect_timeout: Some(Duration::from_secs(5)),
+      ..Default::default()
+    };
+
+    let result = timeout(Duration::from_millis(500), connect(url, &options)).await;
+    // Establishing the TCP connection succeeded, only the WebSocket
+    // handshake is pending.
+    assert!(result.is_err());
+
+    drop(listener);
+    let url = Url::parse("ws://127.0.0.1:1").unwrap();
+    let result = connect(url, &options).await;
+    assert!(matches!(result, Err(WebSocketError::Io(..))));
+  }
+}
@@ -0,0 +1,256 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use futures::stream::Stream;
+use futures::StreamExt;
+
+use serde_json::Error as JsonError;
+
+use websocket_util::tungstenite::Error as WebSocketError;
+
+use crate::api::v2::events::TradeUpdate;
+use crate::api::v2::order;
+use crate::api::v2::orders;
+use crate::Client;
+use crate::RequestError;
+
+
+/// A cache of the status of all open orders.
+///
+/// A `StatusCache` is seeded with a snapshot of the open orders, as
+/// reported by the `/v2/orders` endpoint, and then kept up-to-date by
+/// the events reported through the trade updates stream. In contrast
+/// to [`OrderTracker`](crate::order_tracker::OrderTracker), it covers
+/// all orders of the account, regardless of how they were submitted,
+/// and forgets about orders once they are closed. Queries are answered
+/// from memory without contacting the API.
+///
+/// To not miss any updates, the trade updates stream should be
+/// subscribed to before the cache is loaded.
+///
+/// ```no_run
+/// # use futures::StreamExt;
+/// # use apca::api::v2::events;
+/// # use apca::status_cache::StatusCache;
+/// # use apca::ApiInfo;
+/// # use apca::Client;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = Client::new(ApiInfo::from_env()?);
+/// let cache = StatusCache::new();
+/// let updates = client.subscribe::<events::TradeUpdates>().await?;
+/// cache.load(&client).await?;
+///
+/// let mut updates = Box::pin(cache.maintain(updates));
+/// while let Some(_) = updates.next().await {
+///   println!("{} open AAPL orders", cache.open_orders_for("AAPL").len());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct StatusCache {
+  /// The open orders, by ID.
+  orders: Mutex<HashMap<order::Id, order::Order>>,
+}
+
+impl StatusCache {
+  /// Create a new, empty `StatusCache`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Load the currently open orders from the API, replacing all
+  /// previously cached ones.
+  pub async fn load(&self, client: &Client) -> Result<(), RequestError<orders::GetError>> {
+    let orders = client.all_orders(orders::Status::Open, true).await?;
+    self.reset(orders);
+    Ok(())
+  }
+
+  /// Replace all cached orders with the given ones.
+  ///
+  /// Orders that are no longer open are ignored.
+  pub fn reset<I>(&self, orders: I)
+  where
+    I: IntoIterator<Item = order::Order>,
+  {
+    let mut cached = self.orders.lock().unwrap();
+    cached.clear();
+    cached.extend(
+      orders
+        .into_iter()
+        .filter(|order| !order.status.is_terminal())
+        .map(|order| (order.id, order)),
+    );
+  }
+
+  /// Update the cache based on a trade update.
+  ///
+  /// Orders not known so far are added and orders reported as closed
+  /// are removed.
+  pub fn update(&self, update: &TradeUpdate) {
+    let order = &update.order;
+    let mut orders = self.orders.lock().unwrap();
+    if !order.status.is_terminal() {
+      let _ = orders.insert(order.id, order.clone());
+    } else {
+      let _ = orders.remove(&order.id);
+    }
+  }
+
+  /// Keep the cache up-to-date with a stream of trade updates, as
+  /// retrieved via `Client::subscribe`.
+  ///
+  /// The items of the stream are passed through unchanged.
+  pub fn maintain<'c, S>(
+    &'c self,
+    updates: S,
+  ) -> impl Stream<Item = Result<Result<TradeUpdate, JsonError>, WebSocketError>> + 'c
+  where
+    S: Stream<Item = Result<Result<TradeUpdate, JsonError>, WebSocketError>> + 'c,
+  {
+    updates.inspect(move |result| {
+      if let Ok(Ok(update)) = result {
+        self.update(update)
+      }
+    })
+  }
+
+  /// Retrieve the cached state of the open order with the given ID.
+  pub fn get(&self, id: order::Id) -> Option<order::Order> {
+    self.orders.lock().unwrap().get(&id).cloned()
+  }
+
+  /// Retrieve the status of the order with the given ID, if it is still
+  /// open.
+  pub fn status(&self, id: order::Id) -> Option<order::Status> {
+    self
+      .orders
+      .lock()
+      .unwrap()
+      .get(&id)
+      .map(|order| order.status)
+  }
+
+  /// Retrieve all open orders.
+  pub fn open_orders(&self) -> Vec<order::Order> {
+    self.orders.lock().unwrap().values().cloned().collect()
+  }
+
+  /// Retrieve all open orders for the given symbol.
+  pub fn open_orders_for(&self, symbol: &str) -> Vec<order::Order> {
+    self
+      .orders
+      .lock()
+      .unwrap()
+      .values()
+      .filter(|order| order.symbol == symbol)
+      .cloned()
+      .collect()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::sync::Arc;
+
+  use futures::stream::iter;
+
+  use num_decimal::Num;
+
+  use test_env_log::test;
+
+  use crate::api::v2::events::TradeStatus;
+  use crate::simulator::Simulator;
+  use crate::test_support::filled_order;
+  use crate::test_support::order;
+  use crate::test_support::trade_update;
+
+
+  #[test]
+  fn update_cache() {
+    let cache = StatusCache::new();
+    let aapl = order("AAPL", order::Side::Buy, 10);
+    let spy = order("SPY", order::Side::Sell, 5);
+    cache.reset(vec![aapl.clone(), spy.clone()]);
+
+    assert_eq!(cache.open_orders().len(), 2);
+    assert_eq!(cache.open_orders_for("AAPL"), vec![aapl.clone()]);
+    assert_eq!(cache.status(spy.id), Some(order::Status::New));
+
+    let partial = order::Order {
+      status: order::Status::PartiallyFilled,
+      filled_quantity: 4,
+      ..aapl.clone()
+    };
+    cache.update(&trade_update(TradeStatus::PartialFill, partial));
+    assert_eq!(cache.get(aapl.id).unwrap().filled_quantity, 4);
+
+    let filled = order::Order {
+      status: order::Status::Filled,
+      ..aapl.clone()
+    };
+    cache.update(&trade_update(TradeStatus::Filled, filled));
+    assert_eq!(cache.get(aapl.id), None);
+    assert!(cache.open_orders_for("AAPL").is_empty());
+
+    // Orders submitted elsewhere get picked up.
+    let msft = order("MSFT", order::Side::Buy, 1);
+    cache.update(&trade_update(TradeStatus::New, msft.clone()));
+    assert_eq!(cache.open_orders_for("MSFT"), vec![msft]);
+  }
+
+  #[test]
+  fn ignore_closed_orders_on_reset() {
+    let cache = StatusCache::new();
+    let filled = filled_order("AAPL", order::Side::Buy, 1, Num::from(100));
+    let open = order("AAPL", order::Side::Buy, 1);
+    cache.reset(vec![filled, open.clone()]);
+    assert_eq!(cache.open_orders(), vec![open]);
+  }
+
+  #[test(tokio::test)]
+  async fn maintain_from_stream() {
+    let cache = StatusCache::new();
+    let order = order("AAPL", order::Side::Buy, 10);
+    let canceled = order::Order {
+      status: order::Status::Canceled,
+      ..order.clone()
+    };
+    let updates = vec![
+      Ok(Ok(trade_update(TradeStatus::New, order.clone()))),
+      Ok(Ok(trade_update(TradeStatus::Canceled, canceled))),
+    ];
+
+    let mut stream = Box::pin(cache.maintain(iter(updates)));
+    let _ = stream.next().await.unwrap();
+    assert_eq!(cache.status(order.id), Some(order::Status::New));
+    let _ = stream.next().await.unwrap();
+    assert_eq!(cache.status(order.id), None);
+  }
+
+  #[test(tokio::test)]
+  async fn load_open_orders() {
+    let simulator = Simulator::new(Num::from(10_000), |_: &str| None);
+    let client = Client::simulated(Arc::new(simulator));
+    let request = order::OrderReqInit {
+      type_: order::Type::Limit,
+      limit_price: Some(Num::from(100)),
+      ..Default::default()
+    }
+    .init("AAPL", order::Side::Buy, 1);
+    let order = client.issue::<order::Post>(request).await.unwrap();
+
+    let cache = StatusCache::new();
+    cache.load(&client).await.unwrap();
+    assert_eq!(cache.open_orders_for("AAPL").len(), 1);
+    assert_eq!(cache.get(order.id).unwrap().id, order.id);
+  }
+}
@@ -0,0 +1,276 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::cell::RefCell;
+
+use serde::de::value::BorrowedStrDeserializer;
+use serde::de::DeserializeOwned;
+use serde::de::DeserializeSeed;
+use serde::de::Deserializer;
+use serde::de::MapAccess;
+use serde::de::SeqAccess;
+use serde::de::Visitor;
+use serde::forward_to_deserialize_any;
+
+use serde_json::from_slice;
+use serde_json::map::Iter as MapIter;
+use serde_json::Error as JsonError;
+use serde_json::Value;
+
+
+/// Join a path and a key into the path of a nested value.
+fn join(path: &str, key: &str) -> String {
+  if path.is_empty() {
+    key.to_string()
+  } else {
+    format!("{}.{}", path, key)
+  }
+}
+
+
+/// A deserializer wrapping a JSON value that records the paths of all
+/// values that got ignored while deserializing.
+///
+/// Derived `Deserialize` implementations ignore fields they do not
+/// know about by means of `deserialize_ignored_any`, which is what we
+/// hook into.
+struct Checked<'a, 'de> {
+  value: &'de Value,
+  path: String,
+  ignored: &'a RefCell<Vec<String>>,
+}
+
+impl<'a, 'de> Checked<'a, 'de> {
+  fn nested(&self, value: &'de Value, path: String) -> Self {
+    Self {
+      value,
+      path,
+      ignored: self.ignored,
+    }
+  }
+}
+
+impl<'a, 'de> Deserializer<'de> for Checked<'a, 'de> {
+  type Error = JsonError;
+
+  fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+  where
+    V: Visitor<'de>,
+  {
+    match self.value {
+      Value::Object(map) => visitor.visit_map(CheckedMap {
+        iter: map.iter(),
+        next: None,
+        parent: self,
+      }),
+      Value::Array(array) => visitor.visit_seq(CheckedSeq {
+        array,
+        index: 0,
+        parent: self,
+      }),
+      value => value.deserialize_any(visitor),
+    }
+  }
+
+  fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+  where
+    V: Visitor<'de>,
+  {
+    match self.value {
+      Value::Null => visitor.visit_none(),
+      _ => visitor.visit_some(self),
+    }
+  }
+
+  fn deserialize_newtype_struct<V>(
+    self,
+    _name: &'static str,
+    visitor: V,
+  ) -> Result<V::Value, Self::Error>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_newtype_struct(self)
+  }
+
+  fn deserialize_enum<V>(
+    self,
+    name: &'static str,
+    variants: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value, Self::Error>
+  where
+    V: Visitor<'de>,
+  {
+    // Enums are mostly represented as plain strings. We do not descend
+    // into the few carrying data.
+    self.value.deserialize_enum(name, variants, visitor)
+  }
+
+  fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+  where
+    V: Visitor<'de>,
+  {
+    self.ignored.borrow_mut().push(self.path);
+    visitor.visit_unit()
+  }
+
+  forward_to_deserialize_any! {
+    bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+    bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+    identifier
+  }
+}
+
+
+/// The `MapAccess` used for JSON objects.
+struct CheckedMap<'a, 'de> {
+  iter: MapIter<'de>,
+  next: Option<(&'de String, &'de Value)>,
+  parent: Checked<'a, 'de>,
+}
+
+impl<'a, 'de> MapAccess<'de> for CheckedMap<'a, 'de> {
+  type Error = JsonError;
+
+  fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+  where
+    K: DeserializeSeed<'de>,
+  {
+    match self.iter.next() {
+      Some((key, value)) => {
+        self.next = Some((key, value));
+        seed
+          .deserialize(BorrowedStrDeserializer::new(key))
+          .map(Some)
+      },
+      None => Ok(None),
+    }
+  }
+
+  fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+  where
+    V: DeserializeSeed<'de>,
+  {
+    // `next_value_seed` is only ever invoked after `next_key_seed`
+    // reported a key.
+    let (key, value) = self.next.take().unwrap();
+    let path = join(&self.parent.path, key);
+    seed.deserialize(self.parent.nested(value, path))
+  }
+}
+
+
+/// The `SeqAccess` used for JSON arrays.
+struct CheckedSeq<'a, 'de> {
+  array: &'de [Value],
+  index: usize,
+  parent: Checked<'a, 'de>,
+}
+
+impl<'a, 'de> SeqAccess<'de> for CheckedSeq<'a, 'de> {
+  type Error = JsonError;
+
+  fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+  where
+    T: DeserializeSeed<'de>,
+  {
+    match self.array.get(self.index) {
+      Some(value) => {
+        let path = format!("{}[{}]", self.parent.path, self.index);
+        self.index += 1;
+        seed.deserialize(self.parent.nested(value, path)).map(Some)
+      },
+      None => Ok(None),
+    }
+  }
+}
+
+
+/// Find the paths of all fields in the given JSON body that are
+/// ignored when deserializing it into `T`.
+///
+/// Bodies that are not valid JSON or can not be deserialized into `T`
+/// directly (e.g., because the endpoint in question parses responses
+/// in a custom manner) are not checked and yield no paths. The check is
+/// best effort only: fields of data carrying enums and of types using
+/// `serde`'s internally tagged or untagged representations are not
+/// covered.
+pub(crate) fn unknown_fields<T>(body: &[u8]) -> Vec<String>
+where
+  T: DeserializeOwned,
+{
+  let value = match from_slice::<Value>(body) {
+    Ok(value) => value,
+    Err(..) => return Vec::new(),
+  };
+
+  let ignored = RefCell::new(Vec::new());
+  let checked = Checked {
+    value: &value,
+    path: String::new(),
+    ignored: &ignored,
+  };
+
+  match T::deserialize(checked) {
+    Ok(..) => ignored.into_inner(),
+    Err(..) => Vec::new(),
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde::Deserialize;
+
+  use test_env_log::test;
+
+
+  #[derive(Deserialize)]
+  struct Inner {
+    #[allow(unused)]
+    value: u64,
+  }
+
+  #[derive(Deserialize)]
+  struct Outer {
+    #[allow(unused)]
+    name: String,
+    #[allow(unused)]
+    inner: Option<Inner>,
+    #[allow(unused)]
+    list: Vec<Inner>,
+  }
+
+  #[test]
+  fn no_unknown_fields() {
+    let body = br#"{"name":"foo","inner":null,"list":[{"value":1}]}"#;
+    assert_eq!(unknown_fields::<Outer>(body), Vec::<String>::new());
+  }
+
+  #[test]
+  fn report_unknown_fields() {
+    let body = br#"{
+  "name": "foo",
+  "extra": true,
+  "inner": {"value": 1, "more": "x"},
+  "list": [{"value": 1}, {"value": 2, "new": [1, 2]}]
+}"#;
+    let expected = vec![
+      "extra".to_string(),
+      "inner.more".to_string(),
+      "list[1].new".to_string(),
+    ];
+    let mut unknown = unknown_fields::<Outer>(body);
+    unknown.sort();
+    assert_eq!(unknown, expected);
+  }
+
+  #[test]
+  fn ignore_non_matching_body() {
+    assert_eq!(unknown_fields::<Outer>(b""), Vec::<String>::new());
+    assert_eq!(unknown_fields::<Outer>(b"[1]"), Vec::<String>::new());
+  }
+}
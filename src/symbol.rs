@@ -0,0 +1,299 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::convert::TryFrom;
+use std::error::Error as StdError;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::ops::Deref;
+use std::str::FromStr;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::instrument::AssetClass;
+
+
+/// The maximum length of the root symbol of an equity.
+const MAX_ROOT_LEN: usize = 6;
+/// The maximum length of a class or other suffix of an equity symbol.
+const MAX_SUFFIX_LEN: usize = 4;
+/// The maximum length of either currency of a crypto currency pair.
+const MAX_CURRENCY_LEN: usize = 10;
+
+
+/// An error indicating that a string is not a valid symbol.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InvalidSymbol(pub String);
+
+impl Display for InvalidSymbol {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    write!(fmt, "invalid symbol: '{}'", self.0)
+  }
+}
+
+impl StdError for InvalidSymbol {}
+
+
+/// The kind of instrument a symbol refers to.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Kind {
+  /// An equity ticker, e.g., `AAPL` or `BRK.B`.
+  Equity,
+  /// A crypto currency pair, e.g., `BTC/USD`.
+  Crypto,
+  /// An option contract in OCC format, e.g., `AAPL240119C00150000`.
+  Option,
+}
+
+impl Kind {
+  /// Retrieve the asset class of instruments of this kind.
+  pub fn asset_class(self) -> AssetClass {
+    match self {
+      Self::Equity => AssetClass::UsEquity,
+      Self::Crypto => AssetClass::Crypto,
+      Self::Option => AssetClass::UsOption,
+    }
+  }
+}
+
+
+/// Check whether all characters of a string are upper case ASCII
+/// letters or digits.
+fn is_upper_alphanumeric(s: &str) -> bool {
+  s.bytes()
+    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+/// Check whether a string is a valid equity ticker.
+fn is_equity(s: &str) -> bool {
+  let mut parts = s.split('.');
+  // `split` always yields at least one element.
+  let root = parts.next().unwrap();
+  let valid_root = !root.is_empty()
+    && root.len() <= MAX_ROOT_LEN
+    && root.as_bytes()[0].is_ascii_uppercase()
+    && is_upper_alphanumeric(root);
+
+  valid_root
+    && parts.all(|suffix| {
+      !suffix.is_empty()
+        && suffix.len() <= MAX_SUFFIX_LEN
+        && suffix.bytes().all(|c| c.is_ascii_uppercase())
+    })
+}
+
+/// Check whether a string is a valid crypto currency pair.
+fn is_crypto(s: &str) -> bool {
+  match s.split('/').collect::<Vec<_>>().as_slice() {
+    [base, quote] => [base, quote].iter().all(|currency| {
+      (2..=MAX_CURRENCY_LEN).contains(&currency.len()) && is_upper_alphanumeric(currency)
+    }),
+    _ => false,
+  }
+}
+
+/// Check whether a string is a valid option symbol in OCC format,
+/// without padding of the root symbol.
+fn is_option(s: &str) -> bool {
+  // The root is followed by the six digit expiration date, the
+  // contract type, and an eight digit strike price.
+  const TAIL_LEN: usize = 6 + 1 + 8;
+
+  if s.len() <= TAIL_LEN || !s.is_ascii() {
+    return false
+  }
+
+  let (root, tail) = s.split_at(s.len() - TAIL_LEN);
+  let (date, tail) = tail.split_at(6);
+  let (type_, strike) = tail.split_at(1);
+
+  let valid_date = date.bytes().all(|c| c.is_ascii_digit()) && {
+    let month = &date[2..4];
+    let day = &date[4..6];
+    ("01"..="12").contains(&month) && ("01"..="31").contains(&day)
+  };
+
+  root.len() <= MAX_ROOT_LEN
+    && root.bytes().all(|c| c.is_ascii_uppercase())
+    && valid_date
+    && (type_ == "C" || type_ == "P")
+    && strike.bytes().all(|c| c.is_ascii_digit())
+}
+
+
+/// A validated symbol of an equity, a crypto currency pair, or an
+/// option contract.
+///
+/// Symbols are checked for being well formed when they are created,
+/// catching malformed ones before they are sent to the API. Whether
+/// an instrument with the given symbol actually exists is not
+/// checked. A `Symbol` converts into a `String` and so can be used
+/// with all requests accepting a symbol.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Symbol {
+  /// The symbol itself.
+  symbol: String,
+  /// The kind of instrument the symbol refers to.
+  kind: Kind,
+}
+
+impl Symbol {
+  /// Create a new `Symbol`, validating its format.
+  pub fn new<S>(symbol: S) -> Result<Self, InvalidSymbol>
+  where
+    S: Into<String>,
+  {
+    let symbol = symbol.into();
+    let kind = if is_equity(&symbol) {
+      Kind::Equity
+    } else if is_crypto(&symbol) {
+      Kind::Crypto
+    } else if is_option(&symbol) {
+      Kind::Option
+    } else {
+      return Err(InvalidSymbol(symbol))
+    };
+
+    Ok(Self { symbol, kind })
+  }
+
+  /// Retrieve the kind of instrument the symbol refers to.
+  pub fn kind(&self) -> Kind {
+    self.kind
+  }
+
+  /// Retrieve the symbol as a string slice.
+  pub fn as_str(&self) -> &str {
+    &self.symbol
+  }
+}
+
+impl Deref for Symbol {
+  type Target = str;
+
+  fn deref(&self) -> &Self::Target {
+    &self.symbol
+  }
+}
+
+impl AsRef<str> for Symbol {
+  fn as_ref(&self) -> &str {
+    &self.symbol
+  }
+}
+
+impl Display for Symbol {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    fmt.write_str(&self.symbol)
+  }
+}
+
+impl FromStr for Symbol {
+  type Err = InvalidSymbol;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Self::new(s)
+  }
+}
+
+impl TryFrom<String> for Symbol {
+  type Error = InvalidSymbol;
+
+  fn try_from(symbol: String) -> Result<Self, Self::Error> {
+    Self::new(symbol)
+  }
+}
+
+impl From<Symbol> for String {
+  fn from(symbol: Symbol) -> Self {
+    symbol.symbol
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde_json::from_str as from_json;
+  use serde_json::to_string as to_json;
+
+  use test_env_log::test;
+
+
+  #[test]
+  fn parse_equity_symbols() {
+    for symbol in &["A", "AAPL", "GOOGL", "BRK.B", "BF.B", "SPY"] {
+      let symbol = Symbol::new(*symbol).unwrap();
+      assert_eq!(symbol.kind(), Kind::Equity, "{}", symbol);
+    }
+  }
+
+  #[test]
+  fn parse_crypto_symbols() {
+    for symbol in &["BTC/USD", "ETH/BTC", "SHIB/USDT"] {
+      let symbol = Symbol::new(*symbol).unwrap();
+      assert_eq!(symbol.kind(), Kind::Crypto, "{}", symbol);
+      assert_eq!(symbol.kind().asset_class(), AssetClass::Crypto);
+    }
+  }
+
+  #[test]
+  fn parse_option_symbols() {
+    for symbol in &[
+      "AAPL240119C00150000",
+      "SPY211217P00450000",
+      "F230120C00012500",
+    ] {
+      let symbol = Symbol::new(*symbol).unwrap();
+      assert_eq!(symbol.kind(), Kind::Option, "{}", symbol);
+    }
+  }
+
+  #[test]
+  fn reject_invalid_symbols() {
+    let invalid = [
+      "",
+      "aapl",
+      "AAPL ",
+      "TOOLONGX",
+      "BRK.",
+      ".B",
+      "BTC/",
+      "BTC/USD/EUR",
+      "AAPL241319C00150000",
+      "AAPL240119X00150000",
+      "AAPL240119C0015000",
+    ];
+
+    for symbol in invalid.iter() {
+      let err = Symbol::new(*symbol).unwrap_err();
+      assert_eq!(err, InvalidSymbol(symbol.to_string()));
+    }
+  }
+
+  #[test]
+  fn serialize_deserialize_symbol() {
+    let symbol = "BRK.B".parse::<Symbol>().unwrap();
+    let json = to_json(&symbol).unwrap();
+    assert_eq!(json, r#""BRK.B""#);
+    assert_eq!(from_json::<Symbol>(&json).unwrap(), symbol);
+    assert!(from_json::<Symbol>(r#""brk.b""#).is_err());
+  }
+
+  #[test]
+  fn convert_into_string() {
+    fn take<S>(symbol: S) -> String
+    where
+      S: Into<String>,
+    {
+      symbol.into()
+    }
+
+    let symbol = Symbol::new("AAPL").unwrap();
+    assert_eq!(take(symbol), "AAPL");
+  }
+}
@@ -0,0 +1,296 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashMap;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use crate::api::v2::asset::Status;
+use crate::api::v2::assets;
+use crate::data::v2::bars::BarsReq;
+use crate::data::v2::quotes::QuotesReq;
+use crate::data::v2::trades::TradesReq;
+use crate::instrument::AssetClass;
+use crate::Client;
+use crate::RequestError;
+
+
+/// A change to a symbol, i.e., a rename or a delisting.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Change {
+  /// The symbol that got changed.
+  pub symbol: String,
+  /// The time from which on the symbol is no longer valid.
+  pub date: SystemTime,
+  /// The symbol the asset trades under afterwards, if any.
+  ///
+  /// `None` indicates that the asset got delisted.
+  pub successor: Option<String>,
+}
+
+
+/// The result of resolving a symbol for a historical data request.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Resolved {
+  /// The symbol to request data for.
+  pub symbol: String,
+  /// The date as of which the symbol is to be mapped by the server.
+  pub asof: SystemTime,
+}
+
+
+/// A helper for resolving historical tickers, so that historical data
+/// requests spanning renames or ending after a delisting still cover
+/// the entire history of the underlying asset.
+///
+/// The market data API maps symbols as of a given date (the `asof`
+/// parameter), which defaults to the current day. A request for a
+/// ticker that got renamed since, e.g., `FB` (now `META`), hence only
+/// yields data for whatever asset currently trades under it, if any.
+/// Given the changes to symbols, a `SymbolMap` picks the symbol and
+/// `asof` date under which the asset's entire history is available.
+#[derive(Clone, Debug, Default)]
+pub struct SymbolMap {
+  /// Changes to symbols, indexed by the symbol that got changed.
+  changes: HashMap<String, Vec<Change>>,
+  /// The status of known assets, indexed by symbol.
+  assets: HashMap<String, Status>,
+}
+
+impl SymbolMap {
+  /// Create a `SymbolMap` from the given changes to symbols.
+  pub fn new<I>(changes: I) -> Self
+  where
+    I: IntoIterator<Item = Change>,
+  {
+    let mut map = Self::default();
+    changes.into_iter().for_each(|change| map.insert(change));
+    map
+  }
+
+  /// Create a `SymbolMap` knowing about all active and inactive US
+  /// equities, as reported by the /v2/assets endpoint.
+  pub async fn load<I>(client: &Client, changes: I) -> Result<Self, RequestError<assets::GetError>>
+  where
+    I: IntoIterator<Item = Change>,
+  {
+    let mut map = Self::new(changes);
+    for status in [Status::Active, Status::Inactive] {
+      let request = assets::AssetsReqInit {
+        status,
+        class: AssetClass::UsEquity,
+        ..Default::default()
+      }
+      .init();
+
+      let assets = client.issue::<assets::Get>(request).await?;
+      map
+        .assets
+        .extend(assets.into_iter().map(|asset| (asset.symbol, asset.status)));
+    }
+    Ok(map)
+  }
+
+  /// Record a change to a symbol.
+  pub fn insert(&mut self, change: Change) {
+    let changes = self.changes.entry(change.symbol.clone()).or_default();
+    changes.push(change);
+    changes.sort_by_key(|change| change.date);
+  }
+
+  /// Retrieve the status of the asset currently trading under the given
+  /// symbol, if known.
+  ///
+  /// Statuses are only available for `SymbolMap` objects created via
+  /// `SymbolMap::load`.
+  pub fn status(&self, symbol: &str) -> Option<Status> {
+    self.assets.get(symbol).copied()
+  }
+
+  /// Find the first change to `symbol` taking effect after `since` and
+  /// no later than `until`.
+  fn next_change(&self, symbol: &str, since: SystemTime, until: SystemTime) -> Option<&Change> {
+    self
+      .changes
+      .get(symbol)?
+      .iter()
+      .find(|change| change.date > since && change.date <= until)
+  }
+
+  /// Resolve the symbol to use for retrieving data of the asset
+  /// trading under `symbol` at time `start`, for a period ending at
+  /// `end`.
+  ///
+  /// Renames are followed until `end` and the returned `asof` date is
+  /// the last day on which the returned symbol is valid within the
+  /// period.
+  pub fn resolve(&self, symbol: &str, start: SystemTime, end: SystemTime) -> Resolved {
+    let mut symbol = symbol.to_string();
+    let mut since = start;
+    let mut asof = end;
+    // Bound the number of steps to guard against cyclic changes.
+    let total = self.changes.values().map(Vec::len).sum::<usize>();
+
+    for _ in 0..total {
+      let change = match self.next_change(&symbol, since, end) {
+        Some(change) => change,
+        None => break,
+      };
+
+      match &change.successor {
+        Some(successor) => {
+          symbol = successor.clone();
+          since = change.date;
+        },
+        None => {
+          // The symbol is no longer valid starting on the day of the
+          // delisting, so map it as of the day before.
+          asof = change
+            .date
+            .checked_sub(Duration::from_secs(24 * 60 * 60))
+            .unwrap_or(change.date)
+            .max(start);
+          break
+        },
+      }
+    }
+
+    Resolved { symbol, asof }
+  }
+
+  /// Adjust a bars request to cover the entire history of the asset
+  /// trading under the requested symbol at the request's start time.
+  pub fn map_bars(&self, request: &mut BarsReq) {
+    let resolved = self.resolve(&request.symbol, request.start, request.end);
+    request.symbol = resolved.symbol;
+    request.asof = Some(resolved.asof);
+  }
+
+  /// Adjust a quotes request to cover the entire history of the asset
+  /// trading under the requested symbol at the request's start time.
+  pub fn map_quotes(&self, request: &mut QuotesReq) {
+    let resolved = self.resolve(&request.symbol, request.start, request.end);
+    request.symbol = resolved.symbol;
+    request.asof = Some(resolved.asof);
+  }
+
+  /// Adjust a trades request to cover the entire history of the asset
+  /// trading under the requested symbol at the request's start time.
+  pub fn map_trades(&self, request: &mut TradesReq) {
+    let resolved = self.resolve(&request.symbol, request.start, request.end);
+    request.symbol = resolved.symbol;
+    request.asof = Some(resolved.asof);
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use time_util::parse_system_time_from_str;
+
+  use test_env_log::test;
+
+  use crate::data::v2::bars::BarsReqInit;
+  use crate::data::v2::bars::TimeFrame;
+
+
+  /// Parse a time string.
+  fn time(time: &str) -> SystemTime {
+    parse_system_time_from_str(time).unwrap()
+  }
+
+  /// Create a `SymbolMap` with a rename and a delisting.
+  fn map() -> SymbolMap {
+    SymbolMap::new(vec![
+      Change {
+        symbol: "FB".to_string(),
+        date: time("2022-06-09T00:00:00Z"),
+        successor: Some("META".to_string()),
+      },
+      Change {
+        symbol: "TWTR".to_string(),
+        date: time("2022-11-08T00:00:00Z"),
+        successor: None,
+      },
+    ])
+  }
+
+
+  /// Check that we follow renames taking place within the requested
+  /// period.
+  #[test]
+  fn resolve_rename() {
+    let map = map();
+    let start = time("2020-01-01T00:00:00Z");
+    let end = time("2023-01-01T00:00:00Z");
+
+    let resolved = map.resolve("FB", start, end);
+    assert_eq!(resolved.symbol, "META");
+    assert_eq!(resolved.asof, end);
+
+    // A period ending before the rename needs no mapping.
+    let end = time("2021-01-01T00:00:00Z");
+    let resolved = map.resolve("FB", start, end);
+    assert_eq!(resolved.symbol, "FB");
+    assert_eq!(resolved.asof, end);
+
+    // Changes that happened before the start are not applied.
+    let start = time("2023-01-01T00:00:00Z");
+    let end = time("2023-06-01T00:00:00Z");
+    let resolved = map.resolve("FB", start, end);
+    assert_eq!(resolved.symbol, "FB");
+
+    let resolved = map.resolve("AAPL", start, end);
+    assert_eq!(resolved.symbol, "AAPL");
+    assert_eq!(resolved.asof, end);
+  }
+
+  /// Check that we map delisted symbols as of their last trading day.
+  #[test]
+  fn resolve_delisting() {
+    let map = map();
+    let start = time("2020-01-01T00:00:00Z");
+    let end = time("2023-01-01T00:00:00Z");
+
+    let resolved = map.resolve("TWTR", start, end);
+    assert_eq!(resolved.symbol, "TWTR");
+    assert_eq!(resolved.asof, time("2022-11-07T00:00:00Z"));
+  }
+
+  /// Check that cyclic changes do not cause us to loop forever.
+  #[test]
+  fn resolve_cycle() {
+    let map = SymbolMap::new(vec![
+      Change {
+        symbol: "A".to_string(),
+        date: time("2021-01-01T00:00:00Z"),
+        successor: Some("B".to_string()),
+      },
+      Change {
+        symbol: "B".to_string(),
+        date: time("2021-01-01T00:00:00Z"),
+        successor: Some("A".to_string()),
+      },
+    ]);
+
+    let start = time("2020-01-01T00:00:00Z");
+    let end = time("2022-01-01T00:00:00Z");
+    let resolved = map.resolve("A", start, end);
+    assert_eq!(resolved.symbol, "B");
+  }
+
+  /// Check that we can adjust a bars request.
+  #[test]
+  fn map_bars_request() {
+    let start = time("2020-01-01T00:00:00Z");
+    let end = time("2023-01-01T00:00:00Z");
+    let mut request = BarsReqInit::default().init("FB", start, end, TimeFrame::OneDay);
+    map().map_bars(&mut request);
+
+    assert_eq!(request.symbol, "META");
+    assert_eq!(request.asof, Some(end));
+    assert_eq!(map().status("META"), None);
+  }
+}
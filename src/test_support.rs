@@ -0,0 +1,298 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Functions creating valid API objects for use in tests.
+//!
+//! All objects are populated with plausible defaults and the arguments
+//! provided. Because all members of the created objects are public,
+//! they can be adjusted further as necessary, e.g., by using struct
+//! update syntax.
+
+#[cfg(feature = "trading")]
+use std::sync::atomic::AtomicU64;
+#[cfg(feature = "trading")]
+use std::sync::atomic::Ordering;
+use std::time::SystemTime;
+#[cfg(feature = "trading")]
+use std::time::UNIX_EPOCH;
+
+use num_decimal::Num;
+
+#[cfg(feature = "trading")]
+use uuid::Uuid;
+
+#[cfg(feature = "trading")]
+use crate::api::v2::account;
+#[cfg(feature = "trading")]
+use crate::api::v2::asset;
+#[cfg(all(feature = "streaming", feature = "trading"))]
+use crate::api::v2::events;
+#[cfg(feature = "trading")]
+use crate::api::v2::order;
+#[cfg(feature = "trading")]
+use crate::api::v2::position;
+#[cfg(feature = "data")]
+use crate::data::v2::bars::Bar;
+#[cfg(feature = "data")]
+use crate::data::v2::conditions::Tape;
+#[cfg(feature = "data")]
+use crate::data::v2::exchanges::Exchange;
+#[cfg(feature = "data")]
+use crate::data::v2::quotes::Quote;
+#[cfg(feature = "data")]
+use crate::data::v2::trades::Trade;
+#[cfg(feature = "trading")]
+use crate::instrument;
+
+
+/// Create a new ID, unique for the lifetime of the program.
+#[cfg(feature = "trading")]
+fn unique_id() -> Uuid {
+  static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+  Uuid::from_u128(u128::from(NEXT_ID.fetch_add(1, Ordering::Relaxed)))
+}
+
+
+/// Create an active `Account` holding the given amount of cash and no
+/// positions.
+#[cfg(feature = "trading")]
+pub fn account(cash: Num) -> account::Account {
+  account::Account {
+    id: account::Id(unique_id()),
+    status: account::Status::Active,
+    currency: account::Currency::Usd,
+    cash: cash.clone(),
+    day_trader: false,
+    trading_suspended: false,
+    trading_blocked: false,
+    transfers_blocked: false,
+    account_blocked: false,
+    created_at: UNIX_EPOCH,
+    shorting_enabled: true,
+    market_value_long: Num::from(0),
+    market_value_short: Num::from(0),
+    equity: cash.clone(),
+    last_equity: cash.clone(),
+    multiplier: Num::from(1),
+    buying_power: cash,
+    initial_margin: Num::from(0),
+    maintenance_margin: Num::from(0),
+    daytrade_count: 0,
+    swap_rate: None,
+    swap_fee_bps: None,
+  }
+}
+
+
+/// Create an active, tradable `Asset` for the given symbol that is
+/// marginable, shortable, and easy to borrow.
+#[cfg(feature = "trading")]
+pub fn asset(symbol: &str) -> asset::Asset {
+  asset::Asset {
+    id: asset::Id(unique_id()),
+    class: asset::Class::UsEquity,
+    exchange: asset::Exchange::Nasdaq,
+    symbol: symbol.to_string(),
+    status: asset::Status::Active,
+    tradable: true,
+    marginable: true,
+    shortable: true,
+    easy_to_borrow: true,
+    maintenance_margin_requirement: None,
+    attributes: Vec::new(),
+  }
+}
+
+
+/// Create a new unfilled market `Order` for the given symbol with a
+/// unique ID.
+#[cfg(feature = "trading")]
+pub fn order(symbol: &str, side: order::Side, quantity: u64) -> order::Order {
+  let id = unique_id();
+  order::Order {
+    id: order::Id(id),
+    client_order_id: id.to_string(),
+    status: order::Status::New,
+    created_at: SystemTime::now(),
+    updated_at: None,
+    submitted_at: None,
+    filled_at: None,
+    expired_at: None,
+    canceled_at: None,
+    asset_class: instrument::AssetClass::UsEquity,
+    asset_id: asset::Id(unique_id()),
+    symbol: symbol.to_string(),
+    quantity,
+    filled_quantity: 0,
+    type_: order::Type::Market,
+    side,
+    time_in_force: order::TimeInForce::Day,
+    limit_price: None,
+    stop_price: None,
+    average_fill_price: None,
+    extended_hours: false,
+    swap_rate: None,
+    swap_fee_bps: None,
+    local_notional: None,
+    local_limit_price: None,
+    local_stop_price: None,
+    local_average_fill_price: None,
+    position_intent: None,
+    legs: Vec::new(),
+  }
+}
+
+
+/// Create a market `Order` for the given symbol that got filled in its
+/// entirety at the given price.
+#[cfg(feature = "trading")]
+pub fn filled_order(symbol: &str, side: order::Side, quantity: u64, price: Num) -> order::Order {
+  let now = SystemTime::now();
+  order::Order {
+    status: order::Status::Filled,
+    updated_at: Some(now),
+    submitted_at: Some(now),
+    filled_at: Some(now),
+    filled_quantity: quantity,
+    average_fill_price: Some(price),
+    ..order(symbol, side, quantity)
+  }
+}
+
+
+/// Create a `Position` in the given symbol, entered and currently
+/// valued at `price`.
+#[cfg(feature = "trading")]
+pub fn position(
+  symbol: &str,
+  side: position::Side,
+  quantity: u64,
+  price: Num,
+) -> position::Position {
+  let value = &price * Num::from(quantity);
+  let market_value = match side {
+    position::Side::Long => value.clone(),
+    position::Side::Short => -value.clone(),
+  };
+
+  position::Position {
+    asset_id: asset::Id(unique_id()),
+    symbol: symbol.to_string(),
+    exchange: instrument::Exchange::Nasdaq,
+    asset_class: instrument::AssetClass::UsEquity,
+    average_entry_price: price.clone(),
+    quantity,
+    side,
+    market_value,
+    cost_basis: value,
+    unrealized_gain_total: Num::from(0),
+    unrealized_gain_total_percent: Num::from(0),
+    unrealized_gain_today: Num::from(0),
+    unrealized_gain_today_percent: Num::from(0),
+    current_price: price.clone(),
+    last_day_price: price,
+    change_today: Num::from(0),
+  }
+}
+
+
+/// Create a `TradeUpdate` reporting the given event for an order,
+/// without any execution details.
+#[cfg(all(feature = "streaming", feature = "trading"))]
+pub fn trade_update(event: events::TradeStatus, order: order::Order) -> events::TradeUpdate {
+  events::TradeUpdate {
+    event,
+    order,
+    execution_id: None,
+    price: None,
+    quantity: None,
+    position_quantity: None,
+    timestamp: None,
+  }
+}
+
+
+/// Create a `Bar` starting at the given time.
+#[cfg(feature = "data")]
+pub fn bar(time: SystemTime, open: Num, high: Num, low: Num, close: Num, volume: u64) -> Bar {
+  Bar {
+    time,
+    open,
+    close,
+    high,
+    low,
+    volume,
+    weighted_average: None,
+    trade_count: None,
+  }
+}
+
+
+/// Create a `Quote` with the given bid and ask price, each for 100
+/// shares.
+#[cfg(feature = "data")]
+pub fn quote(time: SystemTime, bid_price: Num, ask_price: Num) -> Quote {
+  Quote {
+    time,
+    ask_exchange: Exchange::NasdaqOmx,
+    ask_price,
+    ask_size: 100,
+    bid_exchange: Exchange::NasdaqOmx,
+    bid_price,
+    bid_size: 100,
+    conditions: vec!["R".to_string()],
+    tape: Tape::C,
+  }
+}
+
+
+/// Create a `Trade` of the given size at the given price.
+#[cfg(feature = "data")]
+pub fn trade(time: SystemTime, price: Num, size: u64) -> Trade {
+  Trade {
+    time,
+    exchange: Exchange::NasdaqOmx,
+    price,
+    size,
+    conditions: vec!["@".to_string()],
+    id: 1,
+    tape: Tape::C,
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use test_env_log::test;
+
+
+  #[cfg(feature = "trading")]
+  #[test]
+  fn create_orders() {
+    let first = order("AAPL", order::Side::Buy, 10);
+    let second = filled_order("AAPL", order::Side::Sell, 5, Num::from(100));
+
+    assert_ne!(first.id, second.id);
+    assert_eq!(first.status, order::Status::New);
+    assert_eq!(second.status, order::Status::Filled);
+    assert_eq!(second.filled_quantity, 5);
+    assert_eq!(second.average_fill_price, Some(Num::from(100)));
+  }
+
+  #[cfg(feature = "trading")]
+  #[test]
+  fn create_position() {
+    let position = position("SPY", position::Side::Short, 3, Num::from(400));
+    assert_eq!(position.market_value, Num::from(-1200));
+    assert_eq!(position.cost_basis, Num::from(1200));
+  }
+
+  #[cfg(feature = "data")]
+  #[test]
+  fn create_quote() {
+    let quote = quote(SystemTime::now(), Num::from(99), Num::from(101));
+    assert!(quote.bid_price < quote.ask_price);
+  }
+}
@@ -0,0 +1,142 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use num_decimal::Num;
+
+use crate::api::v2::order;
+
+
+/// The direction in which to round a price.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Rounding {
+  /// Round towards the next lower valid price.
+  Down,
+  /// Round towards the next higher valid price.
+  Up,
+  /// Round to the nearest valid price.
+  Nearest,
+}
+
+
+/// Retrieve the minimum price increment valid for the given price.
+///
+/// Prices of at least one dollar are accepted in increments of one
+/// cent, while sub-dollar prices may use up to four decimal places.
+pub fn tick_size(price: &Num) -> Num {
+  if price >= &Num::from(1) {
+    Num::new(1, 100)
+  } else {
+    Num::new(1, 10000)
+  }
+}
+
+
+/// Round a price to a valid increment, in the given direction.
+///
+/// The API rejects orders whose prices are not multiples of the tick
+/// size with a rather unspecific error, so prices should be rounded
+/// before submission.
+pub fn round_price(price: &Num, rounding: Rounding) -> Num {
+  let tick = tick_size(price);
+  let ticks = price / &tick;
+  let ticks = match rounding {
+    Rounding::Down => ticks.trunc(),
+    Rounding::Up if ticks.fract().is_zero() => ticks,
+    Rounding::Up => ticks.trunc() + 1,
+    Rounding::Nearest => ticks.round(),
+  };
+  ticks * tick
+}
+
+
+/// Round all prices of an order request to valid increments.
+///
+/// Limit prices are rounded in the direction that is less favorable
+/// for the party submitting the order, i.e., down for buy orders and up
+/// for sell orders, so that rounding never makes the order more
+/// aggressive than intended. The same applies to the limit prices of
+/// the take profit and stop loss legs of bracket orders, which are on
+/// the opposite side of the order. Stop prices are rounded to the
+/// nearest valid price.
+pub fn round_order(request: &mut order::OrderReq) {
+  let (limit, exit_limit) = match request.side {
+    order::Side::Buy => (Rounding::Down, Rounding::Up),
+    order::Side::Sell => (Rounding::Up, Rounding::Down),
+  };
+
+  if let Some(price) = &mut request.limit_price {
+    *price = round_price(price, limit);
+  }
+  if let Some(price) = &mut request.stop_price {
+    *price = round_price(price, Rounding::Nearest);
+  }
+  if let Some(order::TakeProfit::Limit(price)) = &mut request.take_profit {
+    *price = round_price(price, exit_limit);
+  }
+  match &mut request.stop_loss {
+    Some(order::StopLoss::Stop(stop)) => {
+      *stop = round_price(stop, Rounding::Nearest);
+    },
+    Some(order::StopLoss::StopLimit(stop, price)) => {
+      *stop = round_price(stop, Rounding::Nearest);
+      *price = round_price(price, exit_limit);
+    },
+    None => (),
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use test_env_log::test;
+
+
+  #[test]
+  fn round_prices() {
+    let price = Num::new(123456, 1000);
+    assert_eq!(round_price(&price, Rounding::Down), Num::new(12345, 100));
+    assert_eq!(round_price(&price, Rounding::Up), Num::new(12346, 100));
+    assert_eq!(round_price(&price, Rounding::Nearest), Num::new(12346, 100));
+
+    let price = Num::new(123455, 1_000_000);
+    assert_eq!(round_price(&price, Rounding::Down), Num::new(1234, 10000));
+    assert_eq!(round_price(&price, Rounding::Up), Num::new(1235, 10000));
+
+    // Prices that are valid already are left untouched.
+    let price = Num::new(5, 100);
+    assert_eq!(round_price(&price, Rounding::Up), price);
+    assert_eq!(round_price(&price, Rounding::Down), price);
+  }
+
+  #[test]
+  fn round_order_prices() {
+    let mut request = order::OrderReqInit {
+      class: order::Class::Bracket,
+      type_: order::Type::Limit,
+      limit_price: Some(Num::new(100_009, 1000)),
+      take_profit: Some(order::TakeProfit::Limit(Num::new(110_001, 1000))),
+      stop_loss: Some(order::StopLoss::StopLimit(
+        Num::new(90_004, 1000),
+        Num::new(89_999, 1000),
+      )),
+      ..Default::default()
+    }
+    .init("AAPL", order::Side::Buy, 1);
+
+    round_order(&mut request);
+    assert_eq!(request.limit_price, Some(Num::new(10000, 100)));
+    assert_eq!(
+      request.take_profit,
+      Some(order::TakeProfit::Limit(Num::new(11001, 100)))
+    );
+    assert_eq!(
+      request.stop_loss,
+      Some(order::StopLoss::StopLimit(
+        Num::new(9000, 100),
+        Num::new(9000, 100)
+      ))
+    );
+  }
+}
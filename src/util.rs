@@ -0,0 +1,350 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Serialization and time related helpers shared among the various
+//! API families.
+
+#[cfg(feature = "data")]
+use std::borrow::Cow;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use serde::de::Error as SerdeError;
+use serde::de::Unexpected;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+use serde_variant::to_variant_name;
+
+use time_util::parse_system_time_from_date_str;
+
+
+/// Define a newtype ID wrapping a `Uuid`.
+///
+/// Besides the type itself, the macro provides `Deref`, `Display`,
+/// `FromStr`, and `From<Uuid>` implementations for it. The ID is
+/// formatted as a hyphenated UUID and can be parsed back from such a
+/// string.
+#[cfg(any(feature = "broker", feature = "trading"))]
+macro_rules! Id {
+  ( $(#[$docs:meta])* $pub:vis struct $name:ident(pub Uuid); ) => {
+    $(#[$docs])*
+    $pub struct $name(pub ::uuid::Uuid);
+
+    impl ::std::ops::Deref for $name {
+      type Target = ::uuid::Uuid;
+
+      fn deref(&self) -> &Self::Target {
+        &self.0
+      }
+    }
+
+    impl ::std::fmt::Display for $name {
+      fn fmt(&self, fmt: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        write!(fmt, "{}", self.0.to_hyphenated_ref())
+      }
+    }
+
+    impl ::std::str::FromStr for $name {
+      type Err = ::uuid::Error;
+
+      fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ::uuid::Uuid::parse_str(s).map(Self)
+      }
+    }
+
+    impl From<::uuid::Uuid> for $name {
+      fn from(uuid: ::uuid::Uuid) -> Self {
+        Self(uuid)
+      }
+    }
+  };
+}
+
+
+/// Deserialize an optional date string (e.g., "2021-01-05") as a
+/// `SystemTime`.
+pub fn optional_system_time_from_date_str<'de, D>(
+  deserializer: D,
+) -> Result<Option<SystemTime>, D::Error>
+where
+  D: Deserializer<'de>,
+{
+  match Option::<String>::deserialize(deserializer)? {
+    Some(date) => parse_system_time_from_date_str(&date)
+      .ok_or_else(|| SerdeError::invalid_value(Unexpected::Str(&date), &"a date string"))
+      .map(Some),
+    None => Ok(None),
+  }
+}
+
+/// Deserialize a `Vec` that may be represented as `null` when empty.
+#[cfg(any(feature = "data", feature = "trading"))]
+pub fn vec_from_optional<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+  D: Deserializer<'de>,
+  T: Deserialize<'de>,
+{
+  Option::<Vec<T>>::deserialize(deserializer).map(Option::unwrap_or_default)
+}
+
+/// Deserialize an optional string, borrowing it from the input where
+/// possible.
+///
+/// Contrary to `Option<&str>`, strings that contain escape sequences
+/// are supported as well, in which case an owned copy is created.
+#[cfg(feature = "data")]
+pub fn optional_cow_str<'de, 'a, D>(deserializer: D) -> Result<Option<Cow<'a, str>>, D::Error>
+where
+  D: Deserializer<'de>,
+  'de: 'a,
+{
+  // A `Cow` only gets borrowed from the input when marked as such
+  // explicitly, which is not possible when wrapped in an `Option`. So
+  // go through a newtype that does the marking for us.
+  #[derive(Deserialize)]
+  struct Borrowed<'a>(#[serde(borrow)] Cow<'a, str>);
+
+  Option::<Borrowed<'a>>::deserialize(deserializer).map(|s| s.map(|Borrowed(s)| s))
+}
+
+/// Convert a number of days since 1970-01-01 into a (year, month, day)
+/// triple.
+// The algorithm is the one described by Howard Hinnant in his
+// "chrono-Compatible Low-Level Date Algorithms" paper.
+pub fn civil_from_days(days: u64) -> (u64, u64, u64) {
+  let days = days + 719468;
+  let era = days / 146097;
+  let doe = days - era * 146097;
+  let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+  let mp = (5 * doy + 2) / 153;
+  let day = doy - (153 * mp + 2) / 5 + 1;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 };
+  let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+  (year, month, day)
+}
+
+/// Convert a (year, month, day) triple into the number of days since
+/// 1970-01-01.
+// This is the inverse of `civil_from_days`.
+#[cfg(any(feature = "data", feature = "trading"))]
+pub fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+  let year = if month <= 2 { year - 1 } else { year };
+  let era = year / 400;
+  let yoe = year - era * 400;
+  let mp = if month > 2 { month - 3 } else { month + 9 };
+  let doy = (153 * mp + 2) / 5 + day - 1;
+  let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+  era * 146097 + doe - 719468
+}
+
+/// Calculate the offset of US Eastern time (the time zone of the US
+/// stock exchanges) to UTC, in seconds, at the given number of seconds
+/// since the Unix epoch.
+///
+/// Daylight saving time is accounted for according to the rules in
+/// effect since 2007, i.e., it starts on the second Sunday of March and
+/// ends on the first Sunday of November, both at 02:00 local time.
+#[cfg(any(feature = "data", feature = "trading"))]
+pub fn us_eastern_offset(secs: u64) -> i64 {
+  const EST: i64 = -5 * 3600;
+  const EDT: i64 = -4 * 3600;
+
+  // Find the first Sunday on or after the given day. 1970-01-01 was a
+  // Thursday.
+  let sunday_on_or_after = |days: u64| days + (7 - (days + 4) % 7) % 7;
+
+  let (year, _, _) = civil_from_days(secs / 86400);
+  let dst_start = sunday_on_or_after(days_from_civil(year, 3, 1)) + 7;
+  let dst_end = sunday_on_or_after(days_from_civil(year, 11, 1));
+  // 02:00 EST and 02:00 EDT, respectively, in UTC.
+  let dst_start = dst_start * 86400 + 7 * 3600;
+  let dst_end = dst_end * 86400 + 6 * 3600;
+
+  if (dst_start..dst_end).contains(&secs) {
+    EDT
+  } else {
+    EST
+  }
+}
+
+/// Split a `SystemTime` into the number of whole seconds since the
+/// Unix epoch, rounded towards negative infinity, and the remaining
+/// nanoseconds.
+///
+/// Times before `UNIX_EPOCH` (e.g., dates of birth) are supported and
+/// result in a negative number of seconds.
+fn unix_time(time: &SystemTime) -> (i64, u32) {
+  match time.duration_since(UNIX_EPOCH) {
+    Ok(duration) => (duration.as_secs() as i64, duration.subsec_nanos()),
+    Err(err) => {
+      let duration = err.duration();
+      let secs = -(duration.as_secs() as i64);
+      match duration.subsec_nanos() {
+        0 => (secs, 0),
+        nanos => (secs - 1, 1_000_000_000 - nanos),
+      }
+    },
+  }
+}
+
+/// Format a `SystemTime` as a date string of the form YYYY-MM-DD (in
+/// UTC).
+pub fn system_time_to_date_string(time: &SystemTime) -> String {
+  let (secs, _) = unix_time(time);
+  let days = secs.div_euclid(86400);
+  // The Gregorian calendar repeats every 400 years (146097 days). Shift
+  // dates before the epoch by as many of these cycles as necessary to
+  // work with a non-negative number of days.
+  let cycles = if days < 0 { (146096 - days) / 146097 } else { 0 };
+  let (year, month, day) = civil_from_days((days + cycles * 146097) as u64);
+  let year = year as i64 - cycles * 400;
+  format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Format a `SystemTime` as an RFC 3339 string (in UTC).
+pub fn system_time_to_rfc3339_string(time: &SystemTime) -> String {
+  let (secs, nanos) = unix_time(time);
+  let secs = secs.rem_euclid(86400);
+  let date = system_time_to_date_string(time);
+  let (hour, minute, second) = (secs / 3600, secs / 60 % 60, secs % 60);
+
+  if nanos == 0 {
+    format!("{}T{:02}:{:02}:{:02}Z", date, hour, minute, second)
+  } else {
+    let fraction = format!("{:09}", nanos);
+    let fraction = fraction.trim_end_matches('0');
+    format!("{}T{:02}:{:02}:{:02}.{}Z", date, hour, minute, second, fraction)
+  }
+}
+
+/// Serialize a `SystemTime` as a date string of the form YYYY-MM-DD.
+pub fn system_time_to_date_str<S>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error>
+where
+  S: Serializer,
+{
+  serializer.serialize_str(&system_time_to_date_string(time))
+}
+
+/// Serialize an optional `SystemTime` as a date string of the form
+/// YYYY-MM-DD.
+#[cfg(any(feature = "broker", feature = "data"))]
+pub fn optional_system_time_to_date_str<S>(
+  time: &Option<SystemTime>,
+  serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+  S: Serializer,
+{
+  match time {
+    Some(time) => system_time_to_date_str(time, serializer),
+    None => serializer.serialize_none(),
+  }
+}
+
+/// Serialize a slice of enum variants into a string of their textual
+/// representations, separated by comma.
+pub fn slice_to_str<S, T>(slice: &[T], serializer: S) -> Result<S::Ok, S::Error>
+where
+  S: Serializer,
+  T: Serialize,
+{
+  // `serde_urlencoded` seemingly does not know how to handle a `Vec`.
+  // So what we do is we convert each and every element to a string and
+  // then concatenate them, separating each by comma.
+  let s = slice
+    .iter()
+    // We know that we are dealing with an enum variant and the
+    // function will never return an error for those, so it's fine to
+    // unwrap.
+    .map(|variant| to_variant_name(variant).unwrap())
+    .collect::<Vec<_>>()
+    .join(",");
+  serializer.serialize_str(&s)
+}
+
+/// Serialize a slice of strings into a single string with the
+/// individual elements separated by comma.
+#[cfg(any(feature = "broker", feature = "data"))]
+pub fn strings_to_str<S, T>(slice: &[T], serializer: S) -> Result<S::Ok, S::Error>
+where
+  S: Serializer,
+  T: AsRef<str>,
+{
+  let s = slice
+    .iter()
+    .map(AsRef::as_ref)
+    .collect::<Vec<_>>()
+    .join(",");
+  serializer.serialize_str(&s)
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use time_util::parse_system_time_from_str;
+
+
+  #[test]
+  fn format_date() {
+    let time = parse_system_time_from_str("2021-01-05T13:35:25Z").unwrap();
+    assert_eq!(system_time_to_date_string(&time), "2021-01-05");
+
+    let time = parse_system_time_from_str("2020-02-29T00:00:00Z").unwrap();
+    assert_eq!(system_time_to_date_string(&time), "2020-02-29");
+
+    let time = parse_system_time_from_str("1999-12-31T23:59:59Z").unwrap();
+    assert_eq!(system_time_to_date_string(&time), "1999-12-31");
+
+    let time = parse_system_time_from_str("1969-12-31T23:59:59.5Z").unwrap();
+    assert_eq!(system_time_to_date_string(&time), "1969-12-31");
+
+    let time = parse_system_time_from_str("1964-02-29T12:00:00Z").unwrap();
+    assert_eq!(system_time_to_date_string(&time), "1964-02-29");
+
+    let time = parse_system_time_from_str("1600-03-01T00:00:00Z").unwrap();
+    assert_eq!(system_time_to_date_string(&time), "1600-03-01");
+  }
+
+  #[cfg(any(feature = "data", feature = "trading"))]
+  #[test]
+  fn convert_civil_dates() {
+    for days in &[0, 59, 365, 11016, 18628, 19000] {
+      let (year, month, day) = civil_from_days(*days);
+      assert_eq!(days_from_civil(year, month, day), *days);
+    }
+    assert_eq!(days_from_civil(2021, 1, 5), 18632);
+  }
+
+  #[cfg(any(feature = "data", feature = "trading"))]
+  #[test]
+  fn calculate_us_eastern_offset() {
+    let offset = |time| {
+      let time = parse_system_time_from_str(time).unwrap();
+      us_eastern_offset(time.duration_since(UNIX_EPOCH).unwrap().as_secs()) / 3600
+    };
+
+    assert_eq!(offset("2021-01-05T14:30:00Z"), -5);
+    assert_eq!(offset("2021-07-05T13:30:00Z"), -4);
+    assert_eq!(offset("2021-03-14T06:59:59Z"), -5);
+    assert_eq!(offset("2021-03-14T07:00:00Z"), -4);
+    assert_eq!(offset("2021-11-07T05:59:59Z"), -4);
+    assert_eq!(offset("2021-11-07T06:00:00Z"), -5);
+  }
+
+  #[test]
+  fn format_rfc3339() {
+    let time = parse_system_time_from_str("2021-02-01T16:01:00Z").unwrap();
+    assert_eq!(system_time_to_rfc3339_string(&time), "2021-02-01T16:01:00Z");
+
+    let time = parse_system_time_from_str("2021-02-01T16:01:00.0123Z").unwrap();
+    assert_eq!(system_time_to_rfc3339_string(&time), "2021-02-01T16:01:00.0123Z");
+
+    let time = parse_system_time_from_str("1969-12-31T23:59:58.25Z").unwrap();
+    assert_eq!(system_time_to_rfc3339_string(&time), "1969-12-31T23:59:58.25Z");
+  }
+}
@@ -0,0 +1,292 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::borrow::Borrow;
+use std::error::Error as StdError;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::time::Duration;
+
+use tracing::info;
+use tracing::warn;
+
+use crate::api::v2::asset;
+use crate::api::v2::order;
+use crate::api::v2::orders;
+use crate::cancel::CancelError;
+use crate::Client;
+use crate::RequestError;
+
+
+/// What to do about open orders on the opposite side.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Policy {
+  /// Do not submit the order and report the conflicting orders
+  /// instead.
+  #[default]
+  Reject,
+  /// Cancel the conflicting orders, wait for the cancellations to
+  /// take effect, and submit the order afterwards.
+  Cancel,
+}
+
+
+/// The configuration of a `WashGuarded` client.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Config {
+  /// What to do about open orders on the opposite side.
+  pub policy: Policy,
+  /// The maximum time to wait for the cancellation of each conflicting
+  /// order to take effect, when canceling them.
+  pub cancel_timeout: Duration,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      policy: Policy::default(),
+      cancel_timeout: Duration::from_secs(10),
+      _non_exhaustive: (),
+    }
+  }
+}
+
+
+/// An error reported when submitting an order through `WashGuarded`.
+#[derive(Debug)]
+pub enum WashError {
+  /// Open orders on the opposite side exist for the symbol and the
+  /// order was not submitted.
+  Conflict(Vec<order::Order>),
+  /// The open orders could not be retrieved.
+  Orders(RequestError<orders::GetError>),
+  /// A conflicting order could not be canceled.
+  Cancel(CancelError),
+  /// The order submission failed.
+  Order(RequestError<order::PostError>),
+}
+
+impl Display for WashError {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    match self {
+      Self::Conflict(orders) => write!(
+        fmt,
+        "order rejected locally: {} open order(s) on the opposite side",
+        orders.len()
+      ),
+      Self::Orders(err) => write!(fmt, "failed to retrieve open orders: {}", err),
+      Self::Cancel(err) => write!(fmt, "failed to cancel conflicting order: {}", err),
+      Self::Order(err) => write!(fmt, "failed to submit order: {}", err),
+    }
+  }
+}
+
+impl StdError for WashError {
+  fn source(&self) -> Option<&(dyn StdError + 'static)> {
+    match self {
+      Self::Conflict(..) => None,
+      Self::Orders(err) => Some(err),
+      Self::Cancel(err) => Some(err),
+      Self::Order(err) => Some(err),
+    }
+  }
+}
+
+
+/// Find the orders among `open` that are on the opposite side of the
+/// given order request for the same symbol.
+///
+/// Orders are matched by asset ID if the request references the asset
+/// by ID, and by plain symbol otherwise.
+pub fn conflicts<'o>(request: &order::OrderReq, open: &'o [order::Order]) -> Vec<&'o order::Order> {
+  let same_asset = |order: &order::Order| match &request.symbol {
+    asset::Symbol::Sym(symbol)
+    | asset::Symbol::SymExchg(symbol, ..)
+    | asset::Symbol::SymExchgCls(symbol, ..) => order.symbol == *symbol,
+    asset::Symbol::Id(id) => order.asset_id == *id,
+  };
+
+  open
+    .iter()
+    .filter(|order| order.side != request.side && same_asset(order))
+    .collect()
+}
+
+
+/// A wrapper around a `Client` checking order submissions for open
+/// orders on the opposite side in the same symbol, which Alpaca rejects
+/// as potential wash trades.
+///
+/// Depending on the configured `Policy`, such orders are either
+/// reported as an error or canceled before the order is submitted.
+///
+/// ```no_run
+/// # use apca::api::v2::order;
+/// # use apca::wash_trade::Config;
+/// # use apca::wash_trade::Policy;
+/// # use apca::wash_trade::WashGuarded;
+/// # use apca::ApiInfo;
+/// # use apca::Client;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let config = Config {
+///   policy: Policy::Cancel,
+///   ..Default::default()
+/// };
+/// let client = WashGuarded::new(Client::new(ApiInfo::from_env()?), config);
+/// let request = order::OrderReqInit::default().init("AAPL", order::Side::Buy, 1);
+/// let order = client.submit(request).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct WashGuarded<C> {
+  /// The wrapped client.
+  client: C,
+  /// The configuration in use.
+  config: Config,
+}
+
+impl<C> WashGuarded<C>
+where
+  C: Borrow<Client>,
+{
+  /// Create a new `WashGuarded` object wrapping the given client.
+  pub fn new(client: C, config: Config) -> Self {
+    Self { client, config }
+  }
+
+  /// Retrieve the wrapped client.
+  pub fn client(&self) -> &Client {
+    self.client.borrow()
+  }
+
+  /// Retrieve the configuration in use.
+  pub fn config(&self) -> &Config {
+    &self.config
+  }
+
+  /// Check for open orders on the opposite side and submit the order
+  /// if there are none or once they got canceled.
+  ///
+  /// Note that conflicting orders may fill while being canceled. Such
+  /// an order no longer conflicts and the submission goes ahead.
+  pub async fn submit(&self, request: order::OrderReq) -> Result<order::Order, WashError> {
+    let open = self
+      .client()
+      .all_orders(orders::Status::Open, false)
+      .await
+      .map_err(WashError::Orders)?;
+
+    let conflicts = conflicts(&request, &open);
+    if !conflicts.is_empty() {
+      match self.config.policy {
+        Policy::Reject => {
+          warn!(
+            symbol = display(&request.symbol),
+            count = conflicts.len(),
+            "rejecting potential wash trade"
+          );
+          let conflicts = conflicts.into_iter().cloned().collect();
+          return Err(WashError::Conflict(conflicts))
+        },
+        Policy::Cancel => {
+          for order in conflicts {
+            info!(id = display(&order.id), "canceling conflicting order");
+            let _ = self
+              .client()
+              .cancel_and_confirm(order.id, self.config.cancel_timeout)
+              .await
+              .map_err(WashError::Cancel)?;
+          }
+        },
+      }
+    }
+
+    self
+      .client()
+      .issue::<order::Post>(request)
+      .await
+      .map_err(WashError::Order)
+  }
+
+  /// Unwrap the `WashGuarded` object, returning the wrapped client.
+  pub fn into_inner(self) -> C {
+    self.client
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::sync::Arc;
+
+  use num_decimal::Num;
+
+  use test_env_log::test;
+
+  use crate::simulator::Simulator;
+  use crate::test_support;
+
+
+  /// Check that we find open orders on the opposite side in the same
+  /// symbol.
+  #[test]
+  fn find_conflicts() {
+    let open = vec![
+      test_support::order("SPY", order::Side::Sell, 1),
+      test_support::order("SPY", order::Side::Buy, 1),
+      test_support::order("AAPL", order::Side::Sell, 1),
+    ];
+
+    let request = order::OrderReqInit::default().init("SPY", order::Side::Buy, 1);
+    let found = conflicts(&request, &open);
+    assert_eq!(found, vec![&open[0]]);
+
+    let request = order::OrderReqInit::default().init(open[2].asset_id, order::Side::Buy, 1);
+    let found = conflicts(&request, &open);
+    assert_eq!(found, vec![&open[2]]);
+
+    let request = order::OrderReqInit::default().init("MSFT", order::Side::Sell, 1);
+    assert!(conflicts(&request, &open).is_empty());
+  }
+
+  /// Check that we reject or cancel conflicting orders, as configured.
+  #[test(tokio::test)]
+  async fn guard_submission() {
+    let simulator = Simulator::new(Num::from(100_000), |_: &str| Some(Num::from(100)));
+    let client = Client::simulated(Arc::new(simulator));
+
+    let sell = order::OrderReqInit {
+      type_: order::Type::Limit,
+      limit_price: Some(Num::from(200)),
+      ..Default::default()
+    }
+    .init("SPY", order::Side::Sell, 1);
+    let sell = client.issue::<order::Post>(sell).await.unwrap();
+    let buy = order::OrderReqInit::default().init("SPY", order::Side::Buy, 1);
+
+    let guarded = WashGuarded::new(&client, Config::default());
+    match guarded.submit(buy.clone()).await.unwrap_err() {
+      WashError::Conflict(orders) => assert_eq!(orders, vec![sell.clone()]),
+      err => panic!("Received unexpected error: {:?}", err),
+    }
+
+    let config = Config {
+      policy: Policy::Cancel,
+      ..Default::default()
+    };
+    let guarded = WashGuarded::new(&client, config);
+    let order = guarded.submit(buy).await.unwrap();
+    assert_eq!(order.status, order::Status::Filled);
+
+    let sell = client.issue::<order::Get>(sell.id).await.unwrap();
+    assert_eq!(sell.status, order::Status::Canceled);
+  }
+}
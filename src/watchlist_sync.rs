@@ -0,0 +1,248 @@
+// Copyright (C) 2021 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::BTreeSet;
+use std::error::Error as StdError;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use tracing::debug;
+
+use crate::api::v2::watchlist;
+use crate::data::v2::stream::MarketData;
+use crate::data::v2::stream::Subscriber;
+use crate::Client;
+use crate::Error;
+use crate::RequestError;
+
+
+/// An error preventing the synchronization of market data
+/// subscriptions with a watchlist.
+#[derive(Debug)]
+pub enum SyncError {
+  /// The watchlist could not be retrieved.
+  Watchlist(RequestError<watchlist::GetByNameError>),
+  /// The subscriptions could not be changed.
+  Subscribe(Error),
+}
+
+impl Display for SyncError {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    match self {
+      Self::Watchlist(err) => write!(fmt, "failed to retrieve watchlist: {}", err),
+      Self::Subscribe(err) => write!(fmt, "failed to change subscriptions: {}", err),
+    }
+  }
+}
+
+impl StdError for SyncError {
+  fn source(&self) -> Option<&(dyn StdError + 'static)> {
+    match self {
+      Self::Watchlist(err) => Some(err),
+      Self::Subscribe(err) => Some(err),
+    }
+  }
+}
+
+
+/// The kinds of market data to subscribe to for each symbol on a
+/// watchlist.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Channels {
+  /// Whether to subscribe to minute bars.
+  pub bars: bool,
+  /// Whether to subscribe to quotes.
+  pub quotes: bool,
+  /// Whether to subscribe to trades.
+  pub trades: bool,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl Channels {
+  /// Create a `MarketData` object covering the given symbols on the
+  /// enabled channels.
+  fn market_data<'s, I>(&self, symbols: I) -> MarketData
+  where
+    I: IntoIterator<Item = &'s String>,
+  {
+    let symbols = symbols.into_iter().cloned().collect::<Vec<_>>();
+    let select = |enabled| {
+      if enabled {
+        symbols.clone()
+      } else {
+        Vec::new()
+      }
+    };
+
+    MarketData {
+      bars: select(self.bars),
+      quotes: select(self.quotes),
+      trades: select(self.trades),
+    }
+  }
+}
+
+
+/// The changes made to the subscriptions as part of a synchronization.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Changes {
+  /// The symbols that were added to the watchlist and subscribed to.
+  pub added: Vec<String>,
+  /// The symbols that were removed from the watchlist and
+  /// unsubscribed from.
+  pub removed: Vec<String>,
+}
+
+impl Changes {
+  /// Check whether no changes were made.
+  pub fn is_empty(&self) -> bool {
+    self.added.is_empty() && self.removed.is_empty()
+  }
+}
+
+
+/// Determine the changes required to go from the `current` set of
+/// symbols to the `target` one.
+fn changes(current: &BTreeSet<String>, target: &BTreeSet<String>) -> Changes {
+  Changes {
+    added: target.difference(current).cloned().collect(),
+    removed: current.difference(target).cloned().collect(),
+  }
+}
+
+
+/// A helper for keeping the market data subscriptions of a stream in
+/// sync with the symbols on a named watchlist.
+///
+/// Watchlist changes are not pushed by the API. `WatchlistSync::sync`
+/// hence has to be invoked periodically, e.g., once a minute, for
+/// changes to be picked up. Only the difference to the previously
+/// synchronized state is subscribed or unsubscribed.
+#[derive(Debug)]
+pub struct WatchlistSync {
+  /// The name of the watchlist.
+  name: String,
+  /// The kinds of market data to subscribe to.
+  channels: Channels,
+  /// The handle for changing the stream's subscriptions.
+  subscriber: Subscriber,
+  /// The symbols currently subscribed to.
+  symbols: BTreeSet<String>,
+}
+
+impl WatchlistSync {
+  /// Create a `WatchlistSync` object for the watchlist with the given
+  /// name, changing the subscriptions of the stream `subscriber`
+  /// belongs to.
+  ///
+  /// No subscriptions are changed until `sync` is invoked.
+  pub fn new<S>(name: S, channels: Channels, subscriber: Subscriber) -> Self
+  where
+    S: Into<String>,
+  {
+    Self {
+      name: name.into(),
+      channels,
+      subscriber,
+      symbols: BTreeSet::new(),
+    }
+  }
+
+  /// Retrieve the symbols currently subscribed to.
+  pub fn symbols(&self) -> impl Iterator<Item = &str> {
+    self.symbols.iter().map(String::as_str)
+  }
+
+  /// Apply the given changes to the subscriptions.
+  #[allow(clippy::result_large_err)]
+  fn apply(&mut self, changes: &Changes) -> Result<(), Error> {
+    if !changes.removed.is_empty() {
+      let data = self.channels.market_data(&changes.removed);
+      let () = self.subscriber.unsubscribe(&data)?;
+      changes.removed.iter().for_each(|symbol| {
+        let _ = self.symbols.remove(symbol);
+      });
+    }
+
+    if !changes.added.is_empty() {
+      let data = self.channels.market_data(&changes.added);
+      let () = self.subscriber.subscribe(&data)?;
+      self.symbols.extend(changes.added.iter().cloned());
+    }
+    Ok(())
+  }
+
+  /// Retrieve the watchlist and adjust the subscriptions to match the
+  /// symbols on it.
+  pub async fn sync(&mut self, client: &Client) -> Result<Changes, SyncError> {
+    let watchlist = client
+      .issue::<watchlist::GetByName>(self.name.clone())
+      .await
+      .map_err(SyncError::Watchlist)?;
+
+    let symbols = watchlist.symbols().map(str::to_string).collect();
+    let changes = changes(&self.symbols, &symbols);
+    if !changes.is_empty() {
+      debug!(
+        watchlist = display(&self.name),
+        added = debug(&changes.added),
+        removed = debug(&changes.removed),
+        "synchronizing market data subscriptions"
+      );
+      let () = self.apply(&changes).map_err(SyncError::Subscribe)?;
+    }
+    Ok(changes)
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use test_env_log::test;
+
+
+  /// Check that we create `MarketData` objects only for the enabled
+  /// channels.
+  #[test]
+  fn channel_market_data() {
+    let channels = Channels {
+      quotes: true,
+      trades: true,
+      ..Default::default()
+    };
+    let symbols = vec!["AAPL".to_string(), "SPY".to_string()];
+    let data = channels.market_data(&symbols);
+
+    assert!(data.bars.is_empty());
+    assert_eq!(data.quotes, symbols);
+    assert_eq!(data.trades, symbols);
+  }
+
+  /// Check that we determine the changes between two sets of symbols
+  /// correctly.
+  #[test]
+  fn symbol_changes() {
+    let set = |symbols: &[&str]| {
+      symbols
+        .iter()
+        .map(|symbol| symbol.to_string())
+        .collect::<BTreeSet<_>>()
+    };
+
+    let diff = changes(&set(&[]), &set(&["SPY", "AAPL"]));
+    assert_eq!(diff.added, vec!["AAPL", "SPY"]);
+    assert!(diff.removed.is_empty());
+
+    let diff = changes(&set(&["SPY", "AAPL"]), &set(&["SPY", "MSFT"]));
+    assert_eq!(diff.added, vec!["MSFT"]);
+    assert_eq!(diff.removed, vec!["AAPL"]);
+
+    let diff = changes(&set(&["SPY"]), &set(&["SPY"]));
+    assert!(diff.is_empty());
+  }
+}